@@ -0,0 +1,262 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Measures how many events per second a [`Client`] receives during a flood
+//! of motion events, for a range of [read-aheads].
+//!
+//! A fake X server on a TCP port from 6090 to 6099 accepts the connection and
+//! sends the events as fast as it can.
+//!
+//! ```sh
+//! cargo bench --bench event_flood
+//! ```
+//!
+//! [read-aheads]: Client::set_read_ahead
+
+use bytes::{BufMut, BytesMut};
+use std::{
+	io::{self, Read, Write},
+	net::TcpListener,
+	thread,
+	time::Instant,
+};
+use tokio::runtime;
+use xrs::{AuthInfo, Client, Display, DisplayName};
+
+/// The number of motion events sent in each run.
+const EVENTS: usize = 2_000_000;
+/// The number of events written by the fake X server at once.
+const EVENTS_PER_WRITE: usize = 512;
+
+/// The read-aheads compared, in bytes.
+const READ_AHEADS: [usize; 5] = [32, 4 * 1024, 16 * 1024, 64 * 1024, 256 * 1024];
+
+/// The event code of `MotionNotify` events.
+const MOTION_NOTIFY: u8 = 6;
+
+/// The displays the fake X server tries to listen as.
+const DISPLAYS: std::ops::RangeInclusive<i16> = 90..=99;
+
+fn main() -> io::Result<()> {
+	let runtime = runtime::Builder::new_current_thread().enable_io().build()?;
+
+	println!("{:>12} {:>14} {:>10}", "read-ahead", "events/s", "ms");
+
+	for read_ahead in READ_AHEADS {
+		let (listener, display) = listen()?;
+		let server = thread::spawn(move || serve(listener));
+
+		let elapsed = runtime.block_on(async {
+			let mut client = connect(display).await?;
+			client.set_read_ahead(read_ahead);
+
+			let start = Instant::now();
+			let mut received = 0;
+
+			while client.next_event().await?.is_some() {
+				received += 1;
+			}
+
+			assert_eq!(received, EVENTS, "not every event was received");
+
+			io::Result::Ok(start.elapsed())
+		})?;
+
+		server.join().expect("the fake X server panicked")?;
+
+		println!(
+			"{read_ahead:>12} {:>14.0} {:>10.1}",
+			EVENTS as f64 / elapsed.as_secs_f64(),
+			elapsed.as_secs_f64() * 1000.0,
+		);
+	}
+
+	Ok(())
+}
+
+/// Listens for a connection on the TCP port of the first free display in
+/// [`DISPLAYS`].
+fn listen() -> io::Result<(TcpListener, i16)> {
+	let mut last_error = None;
+
+	for display in DISPLAYS {
+		match TcpListener::bind(("127.0.0.1", 6000 + display as u16)) {
+			Ok(listener) => return Ok((listener, display)),
+			Err(error) => last_error = Some(error),
+		}
+	}
+
+	Err(last_error.expect("there is at least one display to try"))
+}
+
+/// Connects a [`Client`] to the fake X server listening as `display`.
+async fn connect(display: i16) -> io::Result<Client> {
+	let name = DisplayName::parse(&format!("127.0.0.1:{display}"))
+		.map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+	let auth = AuthInfo {
+		protocol_name: String::new(),
+		protocol_data: Vec::new(),
+	};
+
+	Client::connect(Display::Specific(name), Some(auth))
+		.await
+		.map_err(|_| io::Error::other("failed to connect to the fake X server"))
+}
+
+/// Accepts one connection, sends it [`EVENTS`] motion events, and closes it.
+fn serve(listener: TcpListener) -> io::Result<()> {
+	let (mut stream, _) = listener.accept()?;
+	stream.set_nodelay(true)?;
+
+	// The connection setup request: its header, then the authorization
+	// protocol name and data.
+	let mut header = [0; 12];
+	stream.read_exact(&mut header)?;
+
+	let name_length = u16::from_be_bytes([header[6], header[7]]) as usize;
+	let data_length = u16::from_be_bytes([header[8], header[9]]) as usize;
+	let mut auth = vec![0; name_length.next_multiple_of(4) + data_length.next_multiple_of(4)];
+	stream.read_exact(&mut auth)?;
+
+	stream.write_all(&setup())?;
+
+	// Answer the `QueryExtension` request for BIG-REQUESTS: it isn't
+	// supported.
+	let mut request = [0; 4];
+	stream.read_exact(&mut request)?;
+
+	let mut body = vec![0; usize::from(u16::from_be_bytes([request[2], request[3]])) * 4 - 4];
+	stream.read_exact(&mut body)?;
+
+	let mut reply = BytesMut::with_capacity(32);
+	reply.put_u8(1);
+	reply.put_u8(0);
+	reply.put_u16(1);
+	reply.put_bytes(0, 28);
+	stream.write_all(&reply)?;
+
+	let mut events = BytesMut::with_capacity(32 * EVENTS_PER_WRITE);
+
+	for event in 0..EVENTS {
+		let position = (event % 1000) as i16;
+
+		events.put_u8(MOTION_NOTIFY);
+		// Normal motion.
+		events.put_u8(0);
+		events.put_u16(1);
+		// time, root, event and child.
+		events.put_u32(event as u32);
+		events.put_u32(0x3b2);
+		events.put_u32(0x3b2);
+		events.put_u32(0);
+		// root-x, root-y, event-x and event-y.
+		events.put_i16(position);
+		events.put_i16(position);
+		events.put_i16(position);
+		events.put_i16(position);
+		// state, same-screen and 1 unused byte.
+		events.put_u16(0);
+		events.put_u8(1);
+		events.put_u8(0);
+
+		if events.len() == events.capacity() {
+			stream.write_all(&events)?;
+			events.clear();
+		}
+	}
+
+	stream.write_all(&events)
+}
+
+/// Returns a successful connection setup reply for a single 1920x1080
+/// TrueColor screen.
+fn setup() -> BytesMut {
+	/// The vendor string, which is padded to 4 bytes.
+	const VENDOR: &[u8] = b"Fake";
+
+	let mut body = BytesMut::new();
+
+	// release-number, resource-id-base, resource-id-mask and
+	// motion-buffer-size.
+	body.put_u32(1);
+	body.put_u32(0x0040_0000);
+	body.put_u32(0x001f_ffff);
+	body.put_u32(256);
+	body.put_u16(VENDOR.len() as u16);
+	// maximum-request-length.
+	body.put_u16(u16::MAX);
+	// 1 screen, 1 pixmap format.
+	body.put_u8(1);
+	body.put_u8(1);
+	// image-byte-order, bitmap-format-bit-order, bitmap-format-scanline-unit
+	// and bitmap-format-scanline-pad.
+	body.put_u8(0);
+	body.put_u8(0);
+	body.put_u8(32);
+	body.put_u8(32);
+	// min-keycode and max-keycode, then 4 unused bytes.
+	body.put_u8(8);
+	body.put_u8(255);
+	body.put_bytes(0, 4);
+	body.put_slice(VENDOR);
+
+	// The pixmap format: depth, bits-per-pixel and scanline-pad, then 5 unused
+	// bytes.
+	body.put_u8(24);
+	body.put_u8(32);
+	body.put_u8(32);
+	body.put_bytes(0, 5);
+
+	// The screen: root, default-colormap, white-pixel, black-pixel and
+	// current-input-masks.
+	body.put_u32(0x3b2);
+	body.put_u32(0x20);
+	body.put_u32(0x00ff_ffff);
+	body.put_u32(0);
+	body.put_u32(0);
+	// Its size in pixels and millimetres.
+	body.put_u16(1920);
+	body.put_u16(1080);
+	body.put_u16(508);
+	body.put_u16(285);
+	// min-installed-maps, max-installed-maps, root-visual, backing-stores,
+	// save-unders, root-depth and 1 allowed depth.
+	body.put_u16(1);
+	body.put_u16(1);
+	body.put_u32(0x21);
+	body.put_u8(0);
+	body.put_u8(0);
+	body.put_u8(24);
+	body.put_u8(1);
+
+	// The allowed depth: depth, 1 unused byte, 1 visual and 4 unused bytes.
+	body.put_u8(24);
+	body.put_u8(0);
+	body.put_u16(1);
+	body.put_bytes(0, 4);
+
+	// The visual: visual-id, TrueColor, bits-per-rgb-value, colormap-entries,
+	// the red, green and blue masks, and 4 unused bytes.
+	body.put_u32(0x21);
+	body.put_u8(4);
+	body.put_u8(8);
+	body.put_u16(256);
+	body.put_u32(0x00ff_0000);
+	body.put_u32(0x0000_ff00);
+	body.put_u32(0x0000_00ff);
+	body.put_bytes(0, 4);
+
+	let mut setup = BytesMut::with_capacity(8 + body.len());
+
+	// Success, then 1 unused byte, the protocol version and the length of
+	// the body in 4-byte units.
+	setup.put_u8(1);
+	setup.put_u8(0);
+	setup.put_u16(11);
+	setup.put_u16(0);
+	setup.put_u16((body.len() / 4) as u16);
+	setup.put_slice(&body);
+
+	setup
+}
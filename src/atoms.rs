@@ -0,0 +1,608 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Interning atoms: the IDs the X server uses in place of strings such as
+//! property names and types.
+//!
+//! Atoms don't change for the lifetime of the X server, so the answers to
+//! [`Client::intern_atom`] and [`Client::get_atom_name`] are cached in both
+//! directions for the rest of the connection. [`Client::intern_atoms`] sends
+//! all of its requests before waiting for any of the replies, so interning
+//! many atoms costs a single round trip.
+//!
+//! Atoms are shown with their names in [`Debug`] and [`Display`] output when
+//! they are predefined. Other atoms' names are only known once they have been
+//! interned or named, so a snapshot of the cache is taken with
+//! [`Client::atom_names`] to [resolve] the atoms in a value:
+//!
+//! ```no_run
+//! # use xrs::{event::Event, Client};
+//! # async fn example(client: &mut Client) -> std::io::Result<()> {
+//! while let Some(frame) = client.next_event().await? {
+//!     if let Some(event) = Event::from_frame(&frame) {
+//!         // Prints `Atom(301, "_NET_WM_NAME")` rather than `Atom(301)`.
+//!         dbg!(client.atom_names().resolve(&event));
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Debug`]: fmt::Debug
+//! [`Display`]: fmt::Display
+//! [resolve]: AtomNames::resolve
+
+use crate::{
+	client::RoundTrip,
+	raw::{assert_wire_size, WireSize, X11Frame},
+	request::{self, Reply, Request},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{
+	cell::RefCell,
+	collections::HashMap,
+	fmt,
+	fmt::{Debug, Display, Formatter},
+	sync::Arc,
+};
+use tokio::io;
+
+/// The major opcode of the `InternAtom` request.
+const INTERN_ATOM: u8 = 16;
+/// The major opcode of the `GetAtomName` request.
+const GET_ATOM_NAME: u8 = 17;
+
+/// The names of the predefined atoms, in order starting from `1`.
+const PREDEFINED: [&str; 68] = [
+	"PRIMARY",
+	"SECONDARY",
+	"ARC",
+	"ATOM",
+	"BITMAP",
+	"CARDINAL",
+	"COLORMAP",
+	"CURSOR",
+	"CUT_BUFFER0",
+	"CUT_BUFFER1",
+	"CUT_BUFFER2",
+	"CUT_BUFFER3",
+	"CUT_BUFFER4",
+	"CUT_BUFFER5",
+	"CUT_BUFFER6",
+	"CUT_BUFFER7",
+	"DRAWABLE",
+	"FONT",
+	"INTEGER",
+	"PIXMAP",
+	"POINT",
+	"RECTANGLE",
+	"RESOURCE_MANAGER",
+	"RGB_COLOR_MAP",
+	"RGB_BEST_MAP",
+	"RGB_BLUE_MAP",
+	"RGB_DEFAULT_MAP",
+	"RGB_GRAY_MAP",
+	"RGB_GREEN_MAP",
+	"RGB_RED_MAP",
+	"STRING",
+	"VISUALID",
+	"WINDOW",
+	"WM_COMMAND",
+	"WM_HINTS",
+	"WM_CLIENT_MACHINE",
+	"WM_ICON_NAME",
+	"WM_ICON_SIZE",
+	"WM_NAME",
+	"WM_NORMAL_HINTS",
+	"WM_SIZE_HINTS",
+	"WM_ZOOM_HINTS",
+	"MIN_SPACE",
+	"NORM_SPACE",
+	"MAX_SPACE",
+	"END_SPACE",
+	"SUPERSCRIPT_X",
+	"SUPERSCRIPT_Y",
+	"SUBSCRIPT_X",
+	"SUBSCRIPT_Y",
+	"UNDERLINE_POSITION",
+	"UNDERLINE_THICKNESS",
+	"STRIKEOUT_ASCENT",
+	"STRIKEOUT_DESCENT",
+	"ITALIC_ANGLE",
+	"X_HEIGHT",
+	"QUAD_WIDTH",
+	"WEIGHT",
+	"POINT_SIZE",
+	"RESOLUTION",
+	"COPYRIGHT",
+	"NOTICE",
+	"FONT_NAME",
+	"FAMILY_NAME",
+	"FULL_NAME",
+	"CAP_HEIGHT",
+	"WM_CLASS",
+	"WM_TRANSIENT_FOR",
+];
+
+/// An atom: a unique ID for a string, such as a property name or type.
+///
+/// Atoms are formatted with their names if they are predefined or are being
+/// formatted by [`AtomNames::resolve`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Atom(pub u32);
+
+/// The predefined atoms, which exist without having to be interned.
+impl Atom {
+	/// No atom (`None`).
+	pub const NONE: Self = Self(0);
+
+	pub const PRIMARY: Self = Self(1);
+	pub const SECONDARY: Self = Self(2);
+	pub const ARC: Self = Self(3);
+	pub const ATOM: Self = Self(4);
+	pub const BITMAP: Self = Self(5);
+	pub const CARDINAL: Self = Self(6);
+	pub const COLORMAP: Self = Self(7);
+	pub const CURSOR: Self = Self(8);
+	pub const CUT_BUFFER0: Self = Self(9);
+	pub const CUT_BUFFER1: Self = Self(10);
+	pub const CUT_BUFFER2: Self = Self(11);
+	pub const CUT_BUFFER3: Self = Self(12);
+	pub const CUT_BUFFER4: Self = Self(13);
+	pub const CUT_BUFFER5: Self = Self(14);
+	pub const CUT_BUFFER6: Self = Self(15);
+	pub const CUT_BUFFER7: Self = Self(16);
+	pub const DRAWABLE: Self = Self(17);
+	pub const FONT: Self = Self(18);
+	pub const INTEGER: Self = Self(19);
+	pub const PIXMAP: Self = Self(20);
+	pub const POINT: Self = Self(21);
+	pub const RECTANGLE: Self = Self(22);
+	pub const RESOURCE_MANAGER: Self = Self(23);
+	pub const RGB_COLOR_MAP: Self = Self(24);
+	pub const RGB_BEST_MAP: Self = Self(25);
+	pub const RGB_BLUE_MAP: Self = Self(26);
+	pub const RGB_DEFAULT_MAP: Self = Self(27);
+	pub const RGB_GRAY_MAP: Self = Self(28);
+	pub const RGB_GREEN_MAP: Self = Self(29);
+	pub const RGB_RED_MAP: Self = Self(30);
+	pub const STRING: Self = Self(31);
+	pub const VISUALID: Self = Self(32);
+	pub const WINDOW: Self = Self(33);
+	pub const WM_COMMAND: Self = Self(34);
+	pub const WM_HINTS: Self = Self(35);
+	pub const WM_CLIENT_MACHINE: Self = Self(36);
+	pub const WM_ICON_NAME: Self = Self(37);
+	pub const WM_ICON_SIZE: Self = Self(38);
+	pub const WM_NAME: Self = Self(39);
+	pub const WM_NORMAL_HINTS: Self = Self(40);
+	pub const WM_SIZE_HINTS: Self = Self(41);
+	pub const WM_ZOOM_HINTS: Self = Self(42);
+	pub const MIN_SPACE: Self = Self(43);
+	pub const NORM_SPACE: Self = Self(44);
+	pub const MAX_SPACE: Self = Self(45);
+	pub const END_SPACE: Self = Self(46);
+	pub const SUPERSCRIPT_X: Self = Self(47);
+	pub const SUPERSCRIPT_Y: Self = Self(48);
+	pub const SUBSCRIPT_X: Self = Self(49);
+	pub const SUBSCRIPT_Y: Self = Self(50);
+	pub const UNDERLINE_POSITION: Self = Self(51);
+	pub const UNDERLINE_THICKNESS: Self = Self(52);
+	pub const STRIKEOUT_ASCENT: Self = Self(53);
+	pub const STRIKEOUT_DESCENT: Self = Self(54);
+	pub const ITALIC_ANGLE: Self = Self(55);
+	pub const X_HEIGHT: Self = Self(56);
+	pub const QUAD_WIDTH: Self = Self(57);
+	pub const WEIGHT: Self = Self(58);
+	pub const POINT_SIZE: Self = Self(59);
+	pub const RESOLUTION: Self = Self(60);
+	pub const COPYRIGHT: Self = Self(61);
+	pub const NOTICE: Self = Self(62);
+	pub const FONT_NAME: Self = Self(63);
+	pub const FAMILY_NAME: Self = Self(64);
+	pub const FULL_NAME: Self = Self(65);
+	pub const CAP_HEIGHT: Self = Self(66);
+	pub const WM_CLASS: Self = Self(67);
+	pub const WM_TRANSIENT_FOR: Self = Self(68);
+}
+
+impl Atom {
+	/// Returns the name of this atom if it is predefined.
+	pub fn predefined_name(self) -> Option<&'static str> {
+		let index = (self.0 as usize).checked_sub(1)?;
+
+		PREDEFINED.get(index).copied()
+	}
+
+	/// Returns the predefined atom called `name`, if there is one.
+	pub fn predefined(name: &str) -> Option<Self> {
+		PREDEFINED
+			.iter()
+			.position(|predefined| *predefined == name)
+			.map(|index| Self(index as u32 + 1))
+	}
+}
+
+impl WireSize for Atom {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+impl Debug for Atom {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		with_name(*self, |name| {
+			let mut tuple = f.debug_tuple("Atom");
+			tuple.field(&self.0);
+
+			if let Some(name) = name {
+				tuple.field(&name);
+			}
+
+			tuple.finish()
+		})
+	}
+}
+
+impl Display for Atom {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		with_name(*self, |name| match name {
+			Some(name) => write!(f, "{name}"),
+			None => write!(f, "{}", self.0),
+		})
+	}
+}
+
+thread_local! {
+	/// The names of the atoms being formatted by [`AtomNames::resolve`] on
+	/// this thread, if any are.
+	static RESOLVING: RefCell<Option<Arc<HashMap<Atom, String>>>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the name of `atom`, if it is predefined or is being
+/// formatted by [`AtomNames::resolve`].
+fn with_name<R>(atom: Atom, f: impl FnOnce(Option<&str>) -> R) -> R {
+	if let Some(name) = atom.predefined_name() {
+		return f(Some(name));
+	}
+
+	RESOLVING.with(|names| {
+		let names = names.borrow();
+
+		f(names
+			.as_ref()
+			.and_then(|names| names.get(&atom))
+			.map(String::as_str))
+	})
+}
+
+/// The names of atoms which had been interned or named on a connection when
+/// they were taken.
+///
+/// See [`Client::atom_names`].
+#[derive(Debug, Clone, Default)]
+pub struct AtomNames {
+	names: Arc<HashMap<Atom, String>>,
+}
+
+impl AtomNames {
+	/// Returns the name of `atom`, if it is predefined or is known.
+	pub fn name(&self, atom: Atom) -> Option<&str> {
+		atom.predefined_name()
+			.or_else(|| self.names.get(&atom).map(String::as_str))
+	}
+
+	/// Wraps `value` so that the atoms in its [`Debug`] and [`Display`]
+	/// output are shown with their names.
+	///
+	/// Any value holding [`Atom`]s can be resolved, as the names are looked
+	/// up as each atom is formatted.
+	pub fn resolve<'value, T: ?Sized>(&'value self, value: &'value T) -> Resolved<'value, T> {
+		Resolved { names: self, value }
+	}
+
+	/// Formats with `f` while the atoms formatted on this thread are named
+	/// from these names.
+	fn scope(&self, f: impl FnOnce() -> fmt::Result) -> fmt::Result {
+		let previous = RESOLVING.with(|names| names.replace(Some(Arc::clone(&self.names))));
+		let result = f();
+		RESOLVING.with(|names| names.replace(previous));
+
+		result
+	}
+}
+
+/// A value whose atoms are formatted with their names.
+///
+/// See [`AtomNames::resolve`].
+pub struct Resolved<'value, T: ?Sized> {
+	names: &'value AtomNames,
+	value: &'value T,
+}
+
+impl<T: Debug + ?Sized> Debug for Resolved<'_, T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.names.scope(|| self.value.fmt(f))
+	}
+}
+
+impl<T: Display + ?Sized> Display for Resolved<'_, T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.names.scope(|| self.value.fmt(f))
+	}
+}
+
+/// The atoms which have been interned or named, cached in both directions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AtomCache {
+	atoms: HashMap<String, Atom>,
+	names: HashMap<Atom, String>,
+}
+
+impl AtomCache {
+	/// Returns the cached atom called `name`.
+	fn atom(&self, name: &str) -> Option<Atom> {
+		Atom::predefined(name).or_else(|| self.atoms.get(name).copied())
+	}
+
+	/// Returns the cached name of `atom`.
+	fn name(&self, atom: Atom) -> Option<&str> {
+		atom.predefined_name()
+			.or_else(|| self.names.get(&atom).map(String::as_str))
+	}
+
+	/// Caches `atom` as being called `name`.
+	fn insert(&mut self, name: &str, atom: Atom) {
+		self.atoms.insert(name.to_owned(), atom);
+		self.names.insert(atom, name.to_owned());
+	}
+}
+
+/// The `InternAtom` request, whose reply is the atom called `name`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InternAtom<'a> {
+	pub name: &'a str,
+	/// Whether to return [`Atom::NONE`] if the atom doesn't exist, rather
+	/// than creating it.
+	pub only_if_exists: bool,
+}
+
+impl<'a> InternAtom<'a> {
+	/// Returns an `InternAtom` request for the atom called `name`, or an
+	/// error if `name` is too long to be sent.
+	fn new(name: &'a str, only_if_exists: bool) -> Result<Self, ReplyError> {
+		if u16::try_from(name.len()).is_err() {
+			return Err(ReplyError::Io(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"atom name is too long",
+			)));
+		}
+
+		Ok(Self {
+			name,
+			only_if_exists,
+		})
+	}
+}
+
+impl Request for InternAtom<'_> {
+	type Reply = Atom;
+
+	const NAME: &'static str = "InternAtom";
+	const OPCODE: u8 = INTERN_ATOM;
+
+	fn metabyte(&self) -> u8 {
+		self.only_if_exists.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		// Names which are too long are rejected by `InternAtom::new`; a longer
+		// name given directly is truncated.
+		body.put_u16(self.name.len() as u16);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_slice(self.name.as_bytes());
+	}
+}
+
+assert_wire_size!("InternAtom reply": [u8, u8, u16, u32, Atom, [u8; 20]] == 32);
+
+impl Reply for Atom {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[a0, a1, a2, a3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self(u32::from_be_bytes([a0, a1, a2, a3])))
+	}
+}
+
+/// The `GetAtomName` request, whose reply is the [`AtomName`] of an atom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetAtomName(pub Atom);
+
+impl Request for GetAtomName {
+	type Reply = AtomName;
+
+	const NAME: &'static str = "GetAtomName";
+	const OPCODE: u8 = GET_ATOM_NAME;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0 .0);
+	}
+}
+
+assert_wire_size!(GetAtomName: [u8, [u8; 1], u16, Atom]);
+
+/// The name of an atom.
+///
+/// This is the reply to a `GetAtomName` request. Names which aren't valid
+/// UTF-8 are converted lossily.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AtomName(pub String);
+
+impl Reply for AtomName {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[l0, l1] = chunk.get(..2)? else {
+			return None;
+		};
+		let length = usize::from(u16::from_be_bytes([l0, l1]));
+		let name = chunk.get(24..24 + length)?;
+
+		Some(Self(String::from_utf8_lossy(name).into_owned()))
+	}
+}
+
+/// Returns the atom called `name`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::intern_atom`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn intern_atom(
+	connection: &mut impl RoundTrip, name: &str, only_if_exists: bool,
+) -> Result<Option<Atom>, ReplyError> {
+	if let Some(atom) = connection.writer().atoms.atom(name) {
+		return Ok(Some(atom));
+	}
+
+	match request::reply_to(connection, &InternAtom::new(name, only_if_exists)?).await? {
+		// Only returned if `only_if_exists` is set. This isn't cached, as
+		// another client may create the atom later.
+		Atom::NONE => Ok(None),
+
+		atom => {
+			connection.writer().atoms.insert(name, atom);
+
+			Ok(Some(atom))
+		},
+	}
+}
+
+/// Returns the atom called `name`, creating it if it doesn't exist.
+#[cfg_attr(not(any(feature = "ewmh", feature = "selection")), allow(dead_code))]
+pub(crate) async fn atom(connection: &mut impl RoundTrip, name: &str) -> Result<Atom, ReplyError> {
+	intern_atom(connection, name, false)
+		.await?
+		.ok_or_else(|| ReplyError::malformed("InternAtom"))
+}
+
+/// Returns the atoms called `names`, in the same order, creating any which
+/// don't exist.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::intern_atoms`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn intern_atoms(
+	connection: &mut impl RoundTrip, names: &[&str],
+) -> Result<Vec<Atom>, ReplyError> {
+	let mut atoms = Vec::with_capacity(names.len());
+	let mut cookies = Vec::new();
+
+	for (index, name) in names.iter().enumerate() {
+		match connection.writer().atoms.atom(name) {
+			Some(atom) => atoms.push(atom),
+
+			None => {
+				atoms.push(Atom::NONE);
+
+				let cookie =
+					request::send_request(connection, &InternAtom::new(name, false)?).await?;
+				cookies.push((index, cookie));
+			},
+		}
+	}
+
+	for (index, cookie) in cookies {
+		let atom = request::wait_for(connection, cookie).await?;
+
+		connection.writer().atoms.insert(names[index], atom);
+		atoms[index] = atom;
+	}
+
+	Ok(atoms)
+}
+
+/// Returns the name of `atom`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_atom_name`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_atom_name(
+	connection: &mut impl RoundTrip, atom: Atom,
+) -> Result<String, ReplyError> {
+	if let Some(name) = connection.writer().atoms.name(atom) {
+		return Ok(name.to_owned());
+	}
+
+	let AtomName(name) = request::reply_to(connection, &GetAtomName(atom)).await?;
+	connection.writer().atoms.insert(&name, atom);
+
+	Ok(name)
+}
+
+impl Client {
+	/// Returns the names of the atoms which have been interned or named so
+	/// far.
+	///
+	/// See [`AtomNames`].
+	pub fn atom_names(&self) -> AtomNames {
+		self.writer.atom_names()
+	}
+
+	/// Returns the atom called `name`.
+	///
+	/// If `only_if_exists` is set, `None` is returned if the atom doesn't
+	/// exist; otherwise, it is created.
+	///
+	/// Atoms are cached, so only the first lookup of each name makes a round
+	/// trip to the X server.
+	pub async fn intern_atom(
+		&mut self, name: &str, only_if_exists: bool,
+	) -> Result<Option<Atom>, ReplyError> {
+		intern_atom(self, name, only_if_exists).await
+	}
+
+	/// Returns the atoms called `names`, in the same order, creating any which
+	/// don't exist.
+	///
+	/// The requests for atoms which aren't cached are all sent before waiting
+	/// for any of the replies.
+	pub async fn intern_atoms(&mut self, names: &[&str]) -> Result<Vec<Atom>, ReplyError> {
+		intern_atoms(self, names).await
+	}
+
+	/// Returns the name of `atom`.
+	///
+	/// Names are cached along with interned atoms, so this only makes a round
+	/// trip to the X server for atoms which haven't been seen before.
+	pub async fn get_atom_name(&mut self, atom: Atom) -> Result<String, ReplyError> {
+		get_atom_name(self, atom).await
+	}
+}
+
+impl RequestWriter {
+	/// Returns the names of the atoms which have been interned or named so
+	/// far.
+	///
+	/// See [`Client::atom_names`].
+	pub fn atom_names(&self) -> AtomNames {
+		AtomNames {
+			names: Arc::new(self.atoms.names.clone()),
+		}
+	}
+}
@@ -0,0 +1,506 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Counting double and triple clicks.
+//!
+//! The X server reports each button press separately. A [`ClickCounter`]
+//! counts consecutive presses of the same button which are close enough
+//! together, in both time and distance, to be one multi-click:
+//!
+//! ```no_run
+//! # use xrs::{click::ClickCounter, event::Event, Client};
+//! # async fn example(client: &mut Client) -> std::io::Result<()> {
+//! let mut clicks = ClickCounter::new();
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     let Some(event) = Event::from_frame(&frame) else {
+//!         continue;
+//!     };
+//!
+//!     if let Some(click) = clicks.observe(&event) {
+//!         if click.pressed && click.count == 2 {
+//!             println!("double-clicked button {}", click.event.detail);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The scroll wheel's buttons, 4 to 7, are never counted: each of their
+//! presses is a separate scroll step rather than a click.
+//!
+//! Times are measured with the events' server [`Timestamp`]s, so they are
+//! unaffected by how long events take to be delivered and read.
+//!
+//! A press which the pointer moves too far from before the button is released
+//! is a drag rather than a click: [`ClickCounter::is_drag`] says whether a
+//! motion event has moved far enough, [`Click::dragged`] says whether a
+//! release ends a drag, and the next press starts a new click.
+//!
+//! When events are read from a spawned [`EventReader`], its [`EventStream`]
+//! can count the clicks itself with [`EventStream::counting_clicks`]:
+//!
+//! ```no_run
+//! # use xrs::{click::ClickCounter, Client, ReplyError};
+//! # async fn example(client: Client) -> Result<(), ReplyError> {
+//! let (reader, _writer) = client.split();
+//! let mut events = reader.spawn().counting_clicks(ClickCounter::new());
+//!
+//! while let Some(result) = events.recv().await {
+//!     let (event, click) = result?;
+//!
+//!     match click {
+//!         Some(click) if click.pressed && click.count == 3 => println!("triple-clicked"),
+//!         _ => println!("{event}"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`EventReader`]: crate::EventReader
+
+use crate::{
+	event::{Event, KeyButtonEvent},
+	geometry::Point,
+	raw::X11Frame,
+	time::Timestamp,
+	x11,
+	EventStream,
+	ReplyError,
+};
+use std::{
+	ops::RangeInclusive,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+/// The default longest time between presses of a multi-click: 400ms.
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(400);
+/// The default furthest distance the pointer can move between presses of a
+/// multi-click: 5 pixels.
+const DEFAULT_DISTANCE: u16 = 5;
+/// The default furthest distance the pointer can move while a button is held
+/// before it is a drag: 8 pixels.
+const DEFAULT_DRAG_DISTANCE: u16 = 8;
+
+/// The buttons which the scroll wheel presses: up, down, left and right.
+const SCROLL_BUTTONS: RangeInclusive<u8> = 4..=7;
+
+/// How close together presses must be to count as one multi-click.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClickThresholds {
+	/// The longest time between one press and the next.
+	pub interval: Duration,
+	/// The furthest the pointer can move, horizontally or vertically, between
+	/// one press and the next.
+	pub distance: u16,
+	/// The furthest the pointer can move, horizontally or vertically, while
+	/// a button is held before it is dragging rather than clicking.
+	pub drag_distance: u16,
+}
+
+impl Default for ClickThresholds {
+	fn default() -> Self {
+		Self {
+			interval: DEFAULT_INTERVAL,
+			distance: DEFAULT_DISTANCE,
+			drag_distance: DEFAULT_DRAG_DISTANCE,
+		}
+	}
+}
+
+/// A button event with the number of the click it is part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Click {
+	/// The `ButtonPress` or `ButtonRelease` event.
+	pub event: KeyButtonEvent<u8>,
+	/// Whether the button was pressed, rather than released.
+	pub pressed: bool,
+	/// `1` for a single click, `2` for a double click, `3` for a triple
+	/// click, and so on.
+	///
+	/// Releases have the count of the press before them.
+	pub count: u32,
+	/// Whether the pointer moved further than the drag distance between the
+	/// press and this release.
+	///
+	/// This is always `false` for presses.
+	pub dragged: bool,
+}
+
+/// The last press counted by a [`ClickCounter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct LastPress {
+	button: u8,
+	time: Timestamp,
+	position: Point,
+	count: u32,
+
+	/// Whether the button is still held.
+	held: bool,
+	/// Whether the button was released after a drag.
+	dragged: bool,
+}
+
+/// Counts consecutive clicks of the same button.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct ClickCounter {
+	thresholds: ClickThresholds,
+	last: Option<LastPress>,
+}
+
+impl ClickCounter {
+	/// Creates a `ClickCounter` with the default thresholds: 400ms and 5
+	/// pixels between presses, and 8 pixels to start a drag.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a `ClickCounter` with the given `thresholds`.
+	pub fn with_thresholds(thresholds: ClickThresholds) -> Self {
+		Self {
+			thresholds,
+			last: None,
+		}
+	}
+
+	/// Returns the thresholds within which presses are counted as one
+	/// multi-click.
+	pub const fn thresholds(&self) -> ClickThresholds {
+		self.thresholds
+	}
+
+	/// Sets the thresholds within which presses are counted as one
+	/// multi-click.
+	pub fn set_thresholds(&mut self, thresholds: ClickThresholds) {
+		self.thresholds = thresholds;
+	}
+
+	/// Counts `event` if it is a `ButtonPress` or `ButtonRelease` event.
+	///
+	/// Returns `None` for other events, and for presses and releases of the
+	/// scroll wheel's buttons, which don't affect the count.
+	pub fn observe(&mut self, event: &Event) -> Option<Click> {
+		match event {
+			Event::ButtonPress(button) | Event::ButtonRelease(button)
+				if SCROLL_BUTTONS.contains(&button.detail) =>
+			{
+				None
+			},
+
+			Event::ButtonPress(press) => Some(Click {
+				event: *press,
+				pressed: true,
+				count: self.press(press),
+				dragged: false,
+			}),
+
+			Event::ButtonRelease(release) => Some(self.release(release)),
+
+			_ => None,
+		}
+	}
+
+	/// Returns whether `event` is a `MotionNotify` event which has moved the
+	/// pointer further than the drag distance from where the last press's
+	/// button, which is still held, was pressed.
+	pub fn is_drag(&self, event: &Event) -> bool {
+		match (event, self.last) {
+			(Event::MotionNotify(motion), Some(last)) if last.held => {
+				self.is_dragged(&last, motion.root_position)
+			},
+
+			_ => false,
+		}
+	}
+
+	/// Forgets the last press, so that the next press starts a new click.
+	pub fn reset(&mut self) {
+		self.last = None;
+	}
+
+	/// Records `press`, returning its click count.
+	fn press(&mut self, press: &KeyButtonEvent<u8>) -> u32 {
+		let count = match self.last {
+			Some(last)
+				if last.button == press.detail && !last.dragged && self.is_within(&last, press) =>
+			{
+				last.count.saturating_add(1)
+			},
+
+			_ => 1,
+		};
+
+		self.last = Some(LastPress {
+			button: press.detail,
+			time: press.time,
+			position: press.root_position,
+			count,
+
+			held: true,
+			dragged: false,
+		});
+
+		count
+	}
+
+	/// Records `release`, returning it with the count of the press before it.
+	fn release(&mut self, release: &KeyButtonEvent<u8>) -> Click {
+		let (count, dragged) = match self.last {
+			Some(last) if last.button == release.detail => {
+				let dragged = last.held && self.is_dragged(&last, release.root_position);
+
+				self.last = Some(LastPress {
+					held: false,
+					dragged,
+					..last
+				});
+
+				(last.count, dragged)
+			},
+
+			_ => (1, false),
+		};
+
+		Click {
+			event: *release,
+			pressed: false,
+			count,
+			dragged,
+		}
+	}
+
+	/// Returns whether `position` is further than the drag distance from
+	/// `last`.
+	fn is_dragged(&self, last: &LastPress, position: Point) -> bool {
+		let distance = self.thresholds.drag_distance;
+
+		position.x.abs_diff(last.position.x) > distance
+			|| position.y.abs_diff(last.position.y) > distance
+	}
+
+	/// Returns whether `press` is close enough to `last` to continue its
+	/// multi-click.
+	fn is_within(&self, last: &LastPress, press: &KeyButtonEvent<u8>) -> bool {
+		let elapsed = press.time.wrapping_since(last.time);
+		// A press stamped earlier than the last press starts a new click.
+		let interval = u128::try_from(elapsed)
+			.is_ok_and(|elapsed| elapsed <= self.thresholds.interval.as_millis());
+
+		let distance = self.thresholds.distance;
+
+		interval
+			&& press.root_position.x.abs_diff(last.position.x) <= distance
+			&& press.root_position.y.abs_diff(last.position.y) <= distance
+	}
+}
+
+impl EventStream {
+	/// Decodes the stream's events, counting clicks with `counter`.
+	///
+	/// Each event is returned with its [`Click`] if it is a counted
+	/// `ButtonPress` or `ButtonRelease` event. Errors not claimed by a
+	/// [`ReplyCookie`] are returned as [`ReplyError::X11`], unless they are
+	/// sent elsewhere by [`EventReader::spawn_with_errors`].
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	/// [`EventReader::spawn_with_errors`]: crate::EventReader::spawn_with_errors
+	pub fn counting_clicks(self, counter: ClickCounter) -> ClickEvents {
+		ClickEvents {
+			stream: self,
+			counter,
+		}
+	}
+}
+
+/// The events from an [`EventStream`], with their click counts.
+///
+/// See [`EventStream::counting_clicks`].
+#[derive(Debug)]
+pub struct ClickEvents {
+	stream: EventStream,
+	counter: ClickCounter,
+}
+
+impl ClickEvents {
+	/// Returns the [`ClickCounter`] counting the clicks, such as to change
+	/// its thresholds or check whether a motion event [is a drag].
+	///
+	/// [is a drag]: ClickCounter::is_drag
+	pub fn counter(&mut self) -> &mut ClickCounter {
+		&mut self.counter
+	}
+
+	/// Receives the next event, with its click count if it is a button
+	/// event, or the next unclaimed error.
+	///
+	/// Returns `None` once the connection has been closed and every received
+	/// event has been returned.
+	pub async fn recv(&mut self) -> Option<Result<(Event, Option<Click>), ReplyError>> {
+		loop {
+			match self.stream.recv().await? {
+				Ok(frame) => {
+					if let Some(counted) = self.count(&frame) {
+						return Some(counted);
+					}
+				},
+
+				Err(error) => return Some(Err(ReplyError::Io(error))),
+			}
+		}
+	}
+
+	/// Decodes `frame`, if it is an event, and counts it, or decodes it as an
+	/// error.
+	///
+	/// Returns `None` for other frames, which are skipped.
+	fn count(&mut self, frame: &X11Frame) -> Option<Result<(Event, Option<Click>), ReplyError>> {
+		if let Some(error) = x11::Error::from_frame(frame) {
+			return Some(Err(ReplyError::X11(error)));
+		}
+
+		let event = Event::from_frame(frame)?;
+		let click = self.counter.observe(&event);
+
+		Some(Ok((event, click)))
+	}
+}
+
+impl futures_core::Stream for ClickEvents {
+	type Item = Result<(Event, Option<Click>), ReplyError>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		loop {
+			match Pin::new(&mut self.stream).poll_next(cx) {
+				Poll::Ready(Some(Ok(frame))) => {
+					if let Some(counted) = self.count(&frame) {
+						return Poll::Ready(Some(counted));
+					}
+				},
+
+				Poll::Ready(Some(Err(error))) => {
+					return Poll::Ready(Some(Err(ReplyError::Io(error))))
+				},
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::window::Window;
+
+	/// Returns an event for `button` at `time`, with the pointer at `x`, `y`.
+	fn button(button: u8, time: u32, x: i16, y: i16) -> KeyButtonEvent<u8> {
+		KeyButtonEvent {
+			detail: button,
+			time: Timestamp(time),
+
+			root: Window(1),
+			event: Window(1),
+			child: None,
+
+			root_position: Point::new(x, y),
+			event_position: Point::new(x, y),
+
+			state: 0,
+			same_screen: true,
+		}
+	}
+
+	/// Presses and releases `button` at `time`, returning the press's count.
+	fn click(counter: &mut ClickCounter, button_number: u8, time: u32) -> Option<u32> {
+		let count = counter
+			.observe(&Event::ButtonPress(button(button_number, time, 0, 0)))
+			.map(|click| click.count);
+		counter.observe(&Event::ButtonRelease(button(
+			button_number,
+			time + 10,
+			0,
+			0,
+		)));
+
+		count
+	}
+
+	#[test]
+	fn presses_within_the_interval_are_counted() {
+		let mut counter = ClickCounter::new();
+
+		assert_eq!(click(&mut counter, 1, 1000), Some(1));
+		assert_eq!(click(&mut counter, 1, 1300), Some(2));
+		assert_eq!(click(&mut counter, 1, 1700), Some(3));
+	}
+
+	#[test]
+	fn presses_after_the_interval_start_a_new_click() {
+		let mut counter = ClickCounter::new();
+
+		assert_eq!(click(&mut counter, 1, 1000), Some(1));
+		assert_eq!(click(&mut counter, 1, 1401), Some(1));
+		// A press stamped before the last one isn't within the interval
+		// either.
+		assert_eq!(click(&mut counter, 1, 1300), Some(1));
+	}
+
+	#[test]
+	fn a_different_button_resets_the_count() {
+		let mut counter = ClickCounter::new();
+
+		assert_eq!(click(&mut counter, 1, 1000), Some(1));
+		assert_eq!(click(&mut counter, 1, 1100), Some(2));
+		assert_eq!(click(&mut counter, 3, 1200), Some(1));
+		assert_eq!(click(&mut counter, 1, 1300), Some(1));
+	}
+
+	#[test]
+	fn scroll_buttons_are_not_counted() {
+		let mut counter = ClickCounter::new();
+
+		assert_eq!(click(&mut counter, 1, 1000), Some(1));
+
+		for scroll in SCROLL_BUTTONS {
+			assert_eq!(click(&mut counter, scroll, 1100), None);
+		}
+
+		// Scrolling doesn't interrupt a multi-click.
+		assert_eq!(click(&mut counter, 1, 1200), Some(2));
+	}
+
+	#[test]
+	fn moving_past_the_drag_distance_is_a_drag() {
+		let mut counter = ClickCounter::new();
+
+		counter.observe(&Event::ButtonPress(button(1, 1000, 0, 0)));
+
+		assert!(!counter.is_drag(&Event::MotionNotify(button(0, 1010, 8, 8))));
+		assert!(counter.is_drag(&Event::MotionNotify(button(0, 1020, 9, 0))));
+
+		let release = counter.observe(&Event::ButtonRelease(button(1, 1030, 0, 9)));
+		assert!(release.is_some_and(|release| release.dragged));
+
+		// The next press starts a new click after a drag.
+		assert_eq!(click(&mut counter, 1, 1100), Some(1));
+	}
+
+	#[test]
+	fn releasing_within_the_drag_distance_is_a_click() {
+		let mut counter = ClickCounter::new();
+
+		counter.observe(&Event::ButtonPress(button(1, 1000, 0, 0)));
+
+		let release = counter.observe(&Event::ButtonRelease(button(1, 1010, -8, 8)));
+		assert!(release.is_some_and(|release| !release.dragged));
+
+		assert_eq!(click(&mut counter, 1, 1100), Some(2));
+	}
+}
@@ -2,24 +2,61 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-mod rw;
+#[cfg(feature = "batch")]
+mod batch;
+mod big_requests;
+mod events;
+#[cfg(any(feature = "metrics", feature = "profiler"))]
+pub(crate) mod in_flight;
+#[cfg(feature = "metrics")]
+mod instrument;
+mod queue;
+mod reply;
+pub(crate) mod rw;
+mod setup;
+
+#[cfg(feature = "batch")]
+pub use batch::*;
+pub use events::*;
+pub use queue::*;
+pub use reply::*;
+pub use setup::{
+	BackingStores,
+	Depth,
+	ImageOrder,
+	PixmapFormat,
+	Screen,
+	Setup,
+	Visual,
+	VisualClass,
+	VisualRequirements,
+};
 
-use crate::stream::Stream;
-use async_std::io;
+use crate::{
+	atoms::AtomCache,
+	extension::{Degradation, ExtensionInfo},
+	keyboard::ModifierMasks,
+	raw::RawFrameHook,
+	server::ServerInfo,
+	stream::{ReadStream, Stream, WriteStream},
+	window::Window,
+	xauth,
+	xid::XidAllocator,
+};
 use bytes::BytesMut;
 use std::{
+	collections::{HashMap, VecDeque},
 	env,
 	fmt,
 	fmt::Formatter,
 	net::{IpAddr, Ipv4Addr, Ipv6Addr},
+	num::ParseIntError,
+	sync::{atomic::AtomicBool, Arc},
+	time::Instant,
 };
 #[cfg(unix)]
 use tokio::net::UnixStream;
-use tokio::{
-	io,
-	io::{AsyncWriteExt, BufWriter},
-	net::TcpStream,
-};
+use tokio::{io, io::BufWriter, net::TcpStream, sync::Mutex};
 // use xrb::{
 // 	connection::{
 // 		ConnectionAuthenticationError,
@@ -32,17 +69,201 @@ use tokio::{
 // };
 // use xrbk::{Readable, Writable};
 
+/// The capacity of the buffer requests are written to before being sent.
+const WRITE_BUFFER_CAPACITY: usize = 64 * 1024;
+/// The default number of buffered bytes at which queued requests are flushed.
+const DEFAULT_FLUSH_THRESHOLD: usize = 8 * 1024;
+/// The default number of bytes read ahead from the X server in each read.
+const DEFAULT_READ_AHEAD: usize = 64 * 1024;
+/// The fewest bytes read ahead from the X server in each read: the length of
+/// an event.
+const MIN_READ_AHEAD: usize = 32;
+
+#[allow(dead_code)]
 enum BitmapFormat {
 	U8,
 	U16,
 	U32,
 }
 
+/// A connection to an X server.
+///
+/// `Client` is [`Send`] and [`Sync`], so it can be moved to and shared
+/// between tasks on multi-threaded runtimes. It can also be [split] into an
+/// [`EventReader`] and a [`RequestWriter`] which can be used from separate
+/// tasks at the same time.
+///
+/// [split]: Client::split
 pub struct Client {
-	stream: BufWriter<Stream>,
+	pub(crate) reader: EventReader,
+	pub(crate) writer: RequestWriter,
+}
+
+/// The half of a [`Client`] which receives frames from the X server.
+///
+/// See [`Client::split`].
+pub struct EventReader {
+	stream: ReadStream,
 	/// A buffer to read bytes into.
+	///
+	/// Frames are parsed from the front of the buffer as bytes are read into
+	/// the back. Once every complete frame has been parsed, reserving space
+	/// for the next read moves the remaining bytes of any partial frame to
+	/// the front, so the same allocation is reused like a ring.
 	buffer: BytesMut,
-	// TODO: store info provided by the X server
+	/// The number of bytes reserved in `buffer` before each read.
+	///
+	/// See [`EventReader::set_read_ahead`].
+	read_ahead: usize,
+	/// When the bytes in `buffer` were received.
+	///
+	/// Each entry is the end of a range of bytes in `buffer` and the time at
+	/// which those bytes were read from the stream. Entries are removed once
+	/// the bytes they cover are parsed.
+	received: VecDeque<(usize, Instant)>,
+	/// Frames which were received while waiting for a reply, to be delivered
+	/// before any others, and when they were received.
+	deferred: VecDeque<(rw::X11Frame, Instant)>,
+
+	/// The requests awaiting replies.
+	replies: reply::Replies,
+
+	/// File descriptors received from the X server which haven't been
+	/// claimed by a reply yet.
+	///
+	/// See [`Client::take_fds`].
+	#[cfg(all(unix, feature = "fds"))]
+	pub(crate) fds: VecDeque<std::os::fd::OwnedFd>,
+
+	/// A hook called with every frame received from the X server.
+	///
+	/// See [`Client::set_raw_frame_hook`].
+	pub(crate) frame_hook: Option<RawFrameHook>,
+
+	/// Set when a `MappingNotify` event for the modifier or keyboard mapping
+	/// is received, so that the [`RequestWriter`]'s cached modifier masks are
+	/// resolved again.
+	pub(crate) modifiers_changed: Arc<AtomicBool>,
+
+	/// Records request latencies while it is running.
+	#[cfg(feature = "profiler")]
+	pub(crate) profiler: crate::profiler::Profiler,
+
+	#[cfg(feature = "metrics")]
+	in_flight: instrument::SharedInFlight,
+}
+
+/// The half of a [`Client`] which sends requests to the X server.
+///
+/// See [`Client::split`].
+///
+/// # Awaiting replies
+///
+/// A `RequestWriter` doesn't read frames from the X server itself. Requests
+/// which need a reply are sent with the helper functions which take a
+/// [`RoundTrip`], such as [`property::get_property`], rather than with
+/// methods. Given a `RequestWriter`, those functions await replies directly,
+/// including those which are only needed the first time an extension is used
+/// or when a new range of resource IDs has to be requested: the reply is only
+/// received once the [`EventReader`] reads it. The `EventReader` must be
+/// reading frames elsewhere, such as after being [spawned], or those
+/// functions never return.
+///
+/// [`property::get_property`]: crate::property::get_property
+/// [spawned]: EventReader::spawn
+pub struct RequestWriter {
+	/// The stream requests are written to.
+	///
+	/// It is shared with the timer which writes held requests once they are
+	/// due; see [`RequestWriter::set_batching`].
+	stream: Arc<Mutex<BufWriter<WriteStream>>>,
+	/// Whether file descriptors can be passed over `stream`.
+	#[cfg(all(unix, feature = "fds"))]
+	can_pass_fds: bool,
+
+	/// The number of buffered bytes at which queued requests are flushed.
+	///
+	/// See [`RequestWriter::set_flush_threshold`].
+	flush_threshold: usize,
+	/// How requests are batched, if they are.
+	///
+	/// See [`RequestWriter::set_batching`].
+	#[cfg(feature = "batch")]
+	batching: Option<Batching>,
+	/// When the oldest request which hasn't been flushed yet was written, if
+	/// there is one.
+	///
+	/// This is only changed while `stream` is locked, so that it agrees with
+	/// what is in the stream's buffer.
+	#[cfg(feature = "batch")]
+	held_since: Arc<std::sync::Mutex<Option<Instant>>>,
+
+	/// The maximum length of a request, in 4-byte blocks.
+	maximum_request_length: u32,
+	/// Whether the BIG-REQUESTS extension has been enabled.
+	big_requests: bool,
+
+	/// The sequence number of the last request sent.
+	///
+	/// Sequence numbers are assigned to requests in the order they are sent,
+	/// starting from `1`, and wrap around after `65535`.
+	sequence: u16,
+
+	/// The requests awaiting replies.
+	replies: reply::Replies,
+
+	/// Set when a [`ServerGrabGuard`] is dropped without being released, so
+	/// that an `UngrabServer` request is sent before the next request.
+	///
+	/// [`ServerGrabGuard`]: crate::grab::ServerGrabGuard
+	pub(crate) ungrab_server: Arc<AtomicBool>,
+
+	/// The information provided by the X server when connecting.
+	setup: Setup,
+	/// The index of the screen in `setup` chosen when connecting.
+	default_screen: usize,
+
+	/// Hands out resource IDs.
+	pub(crate) xids: XidAllocator,
+	/// The extensions which have been queried, by name.
+	pub(crate) extensions: HashMap<String, Option<ExtensionInfo>>,
+	/// The versions of extensions negotiated with the X server, by name.
+	#[cfg_attr(
+		not(any(
+			feature = "composite",
+			feature = "damage",
+			feature = "dri3",
+			feature = "pointer",
+			feature = "randr",
+			feature = "region",
+			feature = "render",
+			feature = "screensaver",
+			feature = "sync",
+			feature = "xkb"
+		)),
+		allow(dead_code)
+	)]
+	pub(crate) versions: HashMap<&'static str, (u32, u32)>,
+	/// The atoms which have been interned or named.
+	pub(crate) atoms: AtomCache,
+	/// The X server, once it has been identified.
+	pub(crate) server: Option<ServerInfo>,
+	/// The modifier masks, once they have been resolved.
+	///
+	/// See [`Client::modifier_masks`].
+	pub(crate) modifier_masks: Option<ModifierMasks>,
+	/// Shared with the [`EventReader`], which sets it when the modifier
+	/// masks need to be resolved again.
+	pub(crate) modifiers_changed: Arc<AtomicBool>,
+	/// The features which have fallen back from an unsupported extension.
+	pub(crate) degradations: Vec<Degradation>,
+
+	/// Records request latencies while it is running.
+	#[cfg(feature = "profiler")]
+	pub(crate) profiler: crate::profiler::Profiler,
+
+	#[cfg(feature = "metrics")]
+	in_flight: instrument::SharedInFlight,
 }
 
 pub enum ConnectError {
@@ -51,21 +272,132 @@ pub enum ConnectError {
 	Parse(DisplayNameParseError),
 	Io(io::Error),
 
-	Failed(ConnectionFailure),
-	Auth(ConnectionAuthenticationError),
+	/// The X server refused the connection, giving the contained reason.
+	Failed(String),
+	/// The X server requires further authentication, giving the contained
+	/// reason.
+	///
+	/// This is usually because no authorization was given or found, or the
+	/// authorization was rejected.
+	Auth(String),
+}
+
+impl From<io::Error> for ConnectError {
+	fn from(error: io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+impl Drop for EventReader {
+	fn drop(&mut self) {
+		// Nothing can receive the replies still being waited for.
+		self.replies.close();
+	}
 }
 
 impl Client {
-	pub async fn send<Req: Request>(&mut self, request: Req) -> Result<(), io::Error> {
-		if let Err(error) = request.write_to(&mut self.stream) {
-			return Err(io::Error::new(io::ErrorKind::Other, error));
-		}
+	/// Returns the sequence number of the last request sent.
+	///
+	/// If no requests have been sent yet, this is `0`.
+	pub const fn sequence(&self) -> u16 {
+		self.writer.sequence()
+	}
 
-		self.stream.flush().await?;
+	/// Returns the number of buffered bytes at which requests queued with
+	/// [`send_queued`] are flushed.
+	///
+	/// [`send_queued`]: Client::send_queued
+	pub const fn flush_threshold(&self) -> usize {
+		self.writer.flush_threshold()
+	}
 
-		// TODO: replies
+	/// Sets the number of buffered bytes at which requests queued with
+	/// [`send_queued`] are flushed.
+	///
+	/// The buffer holds at most 64 KiB, so it is flushed when it is full
+	/// whatever the threshold.
+	///
+	/// [`send_queued`]: Client::send_queued
+	pub fn set_flush_threshold(&mut self, threshold: usize) {
+		self.writer.set_flush_threshold(threshold);
+	}
 
-		Ok(())
+	/// Returns the number of bytes read ahead from the X server in each
+	/// read.
+	pub const fn read_ahead(&self) -> usize {
+		self.reader.read_ahead()
+	}
+
+	/// Sets the number of bytes read ahead from the X server in each read.
+	///
+	/// Each read takes as many bytes as the X server has sent, up to the
+	/// read-ahead, and every complete frame among them is delivered before
+	/// reading again. A large read-ahead keeps up with floods of events, such
+	/// as motion events, with fewer reads. It is 64 KiB by default, and at
+	/// least 32 bytes.
+	pub fn set_read_ahead(&mut self, read_ahead: usize) {
+		self.reader.set_read_ahead(read_ahead);
+	}
+
+	/// Returns the information provided by the X server when connecting.
+	pub fn setup(&self) -> &Setup {
+		self.writer.setup()
+	}
+
+	/// Returns the X server's screens.
+	pub fn screens(&self) -> &[Screen] {
+		self.writer.screens()
+	}
+
+	/// Returns the screen chosen by the display name when connecting, or the
+	/// first screen if none was chosen.
+	pub fn default_screen(&self) -> &Screen {
+		self.writer.default_screen()
+	}
+
+	/// Returns the root window of the [default screen].
+	///
+	/// [default screen]: Client::default_screen
+	pub fn root_window(&self) -> Window {
+		self.writer.root_window()
+	}
+
+	/// Returns the visual with the given ID, if any screen supports it.
+	pub fn visual(&self, id: u32) -> Option<&Visual> {
+		self.writer.visual(id)
+	}
+
+	/// Returns the best visual of the [default screen] which meets
+	/// `requirements`, if any do.
+	///
+	/// See [`Screen::best_visual_for`].
+	///
+	/// [default screen]: Client::default_screen
+	pub fn best_visual_for(&self, requirements: VisualRequirements) -> Option<&Visual> {
+		self.writer.best_visual_for(requirements)
+	}
+
+	/// Splits the `Client` into an [`EventReader`], which receives frames from
+	/// the X server, and a [`RequestWriter`], which sends requests to it.
+	///
+	/// The two halves can be moved to separate tasks, for example to keep
+	/// input handling separate from rendering.
+	pub fn split(self) -> (EventReader, RequestWriter) {
+		(self.reader, self.writer)
+	}
+
+	/// Borrows the [`EventReader`] and [`RequestWriter`] halves of the
+	/// `Client` without splitting it.
+	pub fn split_mut(&mut self) -> (&mut EventReader, &mut RequestWriter) {
+		(&mut self.reader, &mut self.writer)
+	}
+
+	/// Recombines an [`EventReader`] and a [`RequestWriter`] which were
+	/// previously [split] from a `Client`.
+	///
+	/// [split]: Client::split
+	pub fn unsplit(reader: EventReader, writer: RequestWriter) -> Self {
+		Self { reader, writer }
 	}
 
 	pub async fn connect(display: Display, auth: Option<AuthInfo>) -> Result<Self, ConnectError> {
@@ -74,7 +406,7 @@ impl Client {
 			protocol,
 			hostname,
 			display,
-			screen: _,
+			screen,
 		} = match display {
 			Display::Default => {
 				let display_env = &env::var("DISPLAY")
@@ -90,52 +422,191 @@ impl Client {
 		};
 
 		// Open the appropriate data stream.
-		let mut stream = BufWriter::new(Stream::open(&protocol, &hostname, display).await?);
-		let mut buffer = BytesMut::with_capacity(4096);
+		let mut stream = Stream::open(&protocol, &hostname, display).await?;
 
-		let (auth_name, auth_data) = match auth {
-			Some(AuthInfo {
-				protocol_name,
-				protocol_data,
-			}) => (&*protocol_name, &*protocol_data),
+		// If no authorization was given, look for it in the user's
+		// Xauthority file.
+		let auth = match auth {
+			Some(auth) => Some(auth),
+			None => xauth::find(stream.peer_ip()?, display).await,
+		};
+
+		let setup = Setup::parse(&setup::handshake(&mut stream, auth.as_ref()).await?)?;
+
+		let default_screen = screen.unwrap_or(0) as usize;
+		if default_screen >= setup.screens.len() {
+			return Err(ConnectError::Io(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("the X server has no screen {default_screen}"),
+			)));
+		}
+
+		let (read_stream, write_stream) = stream.into_split();
+
+		let replies = reply::Replies::default();
+		let modifiers_changed = Arc::<AtomicBool>::default();
+
+		#[cfg(feature = "profiler")]
+		let profiler = crate::profiler::Profiler::default();
+		#[cfg(feature = "metrics")]
+		let in_flight = instrument::SharedInFlight::default();
 
-			None => ("", ""),
+		let mut client = Self {
+			reader: EventReader {
+				stream: read_stream,
+				buffer: BytesMut::with_capacity(DEFAULT_READ_AHEAD),
+				read_ahead: DEFAULT_READ_AHEAD,
+				received: VecDeque::new(),
+				deferred: VecDeque::new(),
+
+				replies: replies.clone(),
+
+				#[cfg(all(unix, feature = "fds"))]
+				fds: VecDeque::new(),
+
+				frame_hook: None,
+				modifiers_changed: modifiers_changed.clone(),
+
+				#[cfg(feature = "profiler")]
+				profiler: profiler.clone(),
+
+				#[cfg(feature = "metrics")]
+				in_flight: in_flight.clone(),
+			},
+
+			writer: RequestWriter {
+				#[cfg(all(unix, feature = "fds"))]
+				can_pass_fds: write_stream.can_pass_fds(),
+				stream: Arc::new(Mutex::new(BufWriter::with_capacity(
+					WRITE_BUFFER_CAPACITY,
+					write_stream,
+				))),
+				flush_threshold: DEFAULT_FLUSH_THRESHOLD,
+				#[cfg(feature = "batch")]
+				batching: None,
+				#[cfg(feature = "batch")]
+				held_since: Arc::default(),
+				maximum_request_length: u32::from(setup.maximum_request_length),
+				big_requests: false,
+				sequence: 0,
+
+				replies,
+
+				ungrab_server: Arc::default(),
+
+				xids: XidAllocator::new(setup.resource_id_base, setup.resource_id_mask),
+
+				setup,
+				default_screen,
+
+				extensions: HashMap::new(),
+				versions: HashMap::new(),
+				atoms: AtomCache::default(),
+				server: None,
+				modifier_masks: None,
+				modifiers_changed,
+				degradations: Vec::new(),
+
+				#[cfg(feature = "profiler")]
+				profiler,
+
+				#[cfg(feature = "metrics")]
+				in_flight,
+			},
 		};
-		// let message = InitConnection {
-		// 	auth_protocol_name: auth_name.into(),
-		// 	auth_protocol_data: auth_data.into(),
-		// };
-		//
-		// // Serialize the `message`.
-		// message.write_to(&mut stream).unwrap();
-		//
-		// // Send the `InitConnection` message.
-		// if let Err(error) = stream.flush().await {
-		// 	return Err(ConnectError::Io(error));
-		// }
-		//
-		// // Receive the connection response.
-		// let response = match ConnectionResponse::read_from(&mut stream) {
-		// 	Ok(response) => response,
-		//
-		// 	Err(error) => {
-		// 		return Err(ConnectError::Io(io::Error::new(
-		// 			io::ErrorKind::Other,
-		// 			error,
-		// 		)))
-		// 	},
-		// };
-		//
-		// match response {
-		// 	ConnectionResponse::Success(ConnectionSuccess { .. }) => Ok(Self {
-		// 		stream: BufWriter::new(stream),
-		// 		buffer: BytesMut::with_capacity(4096),
-		// 	}),
-		//
-		// 	ConnectionResponse::Failed(failure) => Err(ConnectError::Failed(failure)),
-		// 	ConnectionResponse::Authenticate(auth_error) =>
-		// Err(ConnectError::Auth(auth_error)), }
-		Ok(Self { stream, buffer })
+
+		client.enable_big_requests().await?;
+
+		Ok(client)
+	}
+}
+
+impl AsMut<RequestWriter> for Client {
+	fn as_mut(&mut self) -> &mut RequestWriter {
+		&mut self.writer
+	}
+}
+
+impl EventReader {
+	/// Returns the number of bytes read ahead from the X server in each
+	/// read.
+	///
+	/// See [`Client::read_ahead`].
+	pub const fn read_ahead(&self) -> usize {
+		self.read_ahead
+	}
+
+	/// Sets the number of bytes read ahead from the X server in each read.
+	///
+	/// See [`Client::set_read_ahead`].
+	pub fn set_read_ahead(&mut self, read_ahead: usize) {
+		self.read_ahead = read_ahead.max(MIN_READ_AHEAD);
+	}
+}
+
+impl RequestWriter {
+	/// Returns the sequence number of the last request sent.
+	///
+	/// If no requests have been sent yet, this is `0`.
+	pub const fn sequence(&self) -> u16 {
+		self.sequence
+	}
+
+	/// Returns the number of buffered bytes at which queued requests are
+	/// flushed.
+	///
+	/// See [`Client::flush_threshold`].
+	pub const fn flush_threshold(&self) -> usize {
+		self.flush_threshold
+	}
+
+	/// Sets the number of buffered bytes at which queued requests are
+	/// flushed.
+	///
+	/// See [`Client::set_flush_threshold`].
+	pub fn set_flush_threshold(&mut self, threshold: usize) {
+		self.flush_threshold = threshold;
+	}
+
+	/// Returns the information provided by the X server when connecting.
+	///
+	/// See [`Client::setup`].
+	pub fn setup(&self) -> &Setup {
+		&self.setup
+	}
+
+	/// Returns the X server's screens.
+	pub fn screens(&self) -> &[Screen] {
+		&self.setup.screens
+	}
+
+	/// Returns the screen chosen by the display name when connecting.
+	///
+	/// See [`Client::default_screen`].
+	pub fn default_screen(&self) -> &Screen {
+		&self.setup.screens[self.default_screen]
+	}
+
+	/// Returns the root window of the [default screen].
+	///
+	/// [default screen]: RequestWriter::default_screen
+	pub fn root_window(&self) -> Window {
+		Window(self.default_screen().root)
+	}
+
+	/// Returns the visual with the given ID, if any screen supports it.
+	pub fn visual(&self, id: u32) -> Option<&Visual> {
+		self.setup.visual(id)
+	}
+
+	/// Returns the best visual of the [default screen] which meets
+	/// `requirements`, if any do.
+	///
+	/// See [`Client::best_visual_for`].
+	///
+	/// [default screen]: RequestWriter::default_screen
+	pub fn best_visual_for(&self, requirements: VisualRequirements) -> Option<&Visual> {
+		self.default_screen().best_visual_for(requirements)
 	}
 }
 
@@ -146,7 +617,7 @@ impl Stream {
 		Ok(match (protocol, hostname) {
 			// IPv4 with address
 			(Some(Protocol::Inet), Some(Hostname::Other(hostname))) => Self::TcpStream(
-				match Self::open_tcp_stream(Some(IpType::V4), Some(&*hostname), display).await {
+				match Self::open_tcp_stream(Some(IpType::V4), Some(hostname), display).await {
 					Ok(stream) => stream,
 					Err(error) => return Err(ConnectError::Io(error)),
 				},
@@ -157,7 +628,7 @@ impl Stream {
 			| (Some(Protocol::Tcp), Some(Hostname::Inet6(hostname)))
 			| (Some(Protocol::Inet6), Some(Hostname::Inet6(hostname)))
 			| (Some(Protocol::Inet6), Some(Hostname::Other(hostname))) => Self::TcpStream(
-				match Self::open_tcp_stream(Some(IpType::V6), Some(&*hostname), display).await {
+				match Self::open_tcp_stream(Some(IpType::V6), Some(hostname), display).await {
 					Ok(stream) => stream,
 					Err(error) => return Err(ConnectError::Io(error)),
 				},
@@ -166,7 +637,7 @@ impl Stream {
 			// TCP with address but unspecified IP version
 			(None, Some(Hostname::Other(hostname)))
 			| (Some(Protocol::Tcp), Some(Hostname::Other(hostname))) => Self::TcpStream(
-				match Self::open_tcp_stream(None, Some(&*hostname), display).await {
+				match Self::open_tcp_stream(None, Some(hostname), display).await {
 					Ok(stream) => stream,
 					Err(error) => return Err(ConnectError::Io(error)),
 				},
@@ -238,22 +709,24 @@ impl Stream {
 
 		match (ip_type, hostname) {
 			// IP version interpreted
-			(None, Some(address)) => TcpStream::connect((address.parse::<IpAddr>()?, port)),
+			(None, Some(address)) => {
+				TcpStream::connect((parse_address::<IpAddr>(address)?, port)).await
+			},
 
 			// IPv6 with address
 			(Some(IpType::V6), Some(address)) => {
-				TcpStream::connect((address.parse::<Ipv6Addr>()?, port))
+				TcpStream::connect((parse_address::<Ipv6Addr>(address)?, port)).await
 			},
 			// IPv6 localhost
-			(Some(IpType::V6), None) => TcpStream::connect((Ipv6Addr::LOCALHOST, port)),
+			(Some(IpType::V6), None) => TcpStream::connect((Ipv6Addr::LOCALHOST, port)).await,
 
 			// IPv4 with address
 			(Some(IpType::V4), Some(address)) => {
-				TcpStream::connect((address.parse::<Ipv4Addr>()?, port))
+				TcpStream::connect((parse_address::<Ipv4Addr>(address)?, port)).await
 			},
 			// IPv4 localhost (also the fallback)
 			(Some(IpType::V4), None) | (None, None) => {
-				TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+				TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await
 			},
 		}
 	}
@@ -263,10 +736,19 @@ impl Stream {
 		// FIXME: see if we need to check /var/tsol/doors/.X11-unix/X on Solaris
 		let socket = format!("/tmp/.X11-unix/X{}", display);
 
-		UnixStream::connect(socket)
+		UnixStream::connect(socket).await
 	}
 }
 
+/// Parses an IP address, reporting a failure as an [`io::Error`].
+fn parse_address<Addr: std::str::FromStr<Err = std::net::AddrParseError>>(
+	address: &str,
+) -> Result<Addr, io::Error> {
+	address
+		.parse::<Addr>()
+		.map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+}
+
 pub enum Display {
 	Default,
 	Specific(DisplayName),
@@ -289,6 +771,18 @@ pub enum Hostname {
 	Other(String),
 }
 
+impl fmt::Display for Hostname {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::DecNet(hostname) | Self::Other(hostname) => write!(f, "{}", hostname),
+			Self::Inet6(hostname) => write!(f, "[{}]", hostname),
+
+			#[cfg(unix)]
+			Self::Unix => write!(f, "unix"),
+		}
+	}
+}
+
 impl DisplayName {
 	pub const fn new(display: i16) -> Self {
 		Self {
@@ -359,8 +853,14 @@ pub enum DisplayNameParseError {
 	UnrecognizedProtocol,
 }
 
+impl From<ParseIntError> for DisplayNameParseError {
+	fn from(_: ParseIntError) -> Self {
+		Self::IllFormatted
+	}
+}
+
 impl DisplayName {
-	pub fn parse(mut name: &str) -> Result<Self, IllFormatted> {
+	pub fn parse(mut name: &str) -> Result<Self, DisplayNameParseError> {
 		let protocol = if let Some((protocol, _name)) = name.split_once('/') {
 			name = _name;
 
@@ -372,21 +872,23 @@ impl DisplayName {
 		let hostname = if let Some((hostname, _name)) = name.rsplit_once(':') {
 			name = _name;
 
-			let first = |name| name.get(0);
-			let last = |name| name.get(name.len() - 1);
-
-			Some(if let Some(':') = last(hostname) {
-				Hostname::DecNet(hostname[..hostname.len() - 1].to_owned())
-			} else if let Some('[') = first(hostname) && let Some(']') = last(hostname) {
-				Hostname::Inet6(hostname[1..hostname.len() - 1].to_owned())
-			} else {
-				match hostname {
-					#[cfg(unix)]
-					"unix" => Hostname::Unix,
-
-					other => Hostname::Other(other.to_owned()),
-				}
-			})
+			Some(
+				if let Some(hostname) = hostname.strip_suffix(':') {
+					Hostname::DecNet(hostname.to_owned())
+				} else if let Some(hostname) = hostname
+					.strip_prefix('[')
+					.and_then(|hostname| hostname.strip_suffix(']'))
+				{
+					Hostname::Inet6(hostname.to_owned())
+				} else {
+					match hostname {
+						#[cfg(unix)]
+						"unix" => Hostname::Unix,
+
+						other => Hostname::Other(other.to_owned()),
+					}
+				},
+			)
 		} else {
 			None
 		};
@@ -462,7 +964,13 @@ enum IpType {
 	V6,
 }
 
+/// Authorization to connect to an X server.
+///
+/// If no `AuthInfo` is given to [`Client::connect`], a matching
+/// `MIT-MAGIC-COOKIE-1` entry is looked up in the user's Xauthority file.
 pub struct AuthInfo {
+	/// The name of the authorization protocol, such as `MIT-MAGIC-COOKIE-1`.
 	pub protocol_name: String,
-	pub protocol_data: String,
+	/// The authorization data, such as a cookie.
+	pub protocol_data: Vec<u8>,
 }
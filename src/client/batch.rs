@@ -0,0 +1,184 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Micro-batching of requests, holding them briefly so that bursts of
+//! requests are written to the connection together.
+//!
+//! Normally, [`send_raw`] writes each request to the connection as soon as it
+//! is sent. Clients which send many small requests, particularly over TCP,
+//! spend much of their time on those writes. With [batching] enabled, sent
+//! requests are held until either [`max_bytes`] of them are waiting or the
+//! oldest has been held for [`max_delay`], then written together.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use xrs::{Batching, Client};
+//! # fn example(client: &mut Client) {
+//! client.set_batching(Some(Batching {
+//!     max_delay: Duration::from_micros(500),
+//!     max_bytes: 16 * 1024,
+//! }));
+//! # }
+//! ```
+//!
+//! A timer is started when the first request of a batch is held, so held
+//! requests are written once [`max_delay`] has passed even if the client
+//! sends nothing else. The timer runs on the tokio runtime the request was
+//! sent from, which must have its time driver enabled.
+//!
+//! Requests are always sent before waiting for a reply, and [`Client`]
+//! sends them before reading frames, as the frames may be responses to
+//! them.
+//!
+//! [`send_raw`]: Client::send_raw
+//! [batching]: Client::set_batching
+//! [`max_bytes`]: Batching::max_bytes
+//! [`max_delay`]: Batching::max_delay
+
+use super::DEFAULT_FLUSH_THRESHOLD;
+use crate::{Client, RequestWriter};
+use std::{
+	sync::{Arc, Mutex, MutexGuard},
+	time::{Duration, Instant},
+};
+use tokio::{io::AsyncWriteExt, time};
+
+/// How requests are batched before being written to the connection.
+///
+/// See [`Client::set_batching`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Batching {
+	/// The longest a request is held before being written.
+	pub max_delay: Duration,
+	/// The number of bytes of held requests at which they are written.
+	///
+	/// This replaces the [flush threshold].
+	///
+	/// [flush threshold]: Client::set_flush_threshold
+	pub max_bytes: usize,
+}
+
+impl Default for Batching {
+	fn default() -> Self {
+		Self {
+			max_delay: Duration::from_micros(500),
+			max_bytes: DEFAULT_FLUSH_THRESHOLD,
+		}
+	}
+}
+
+impl Client {
+	/// Returns how requests are batched, or `None` if each request is written
+	/// as soon as it is sent.
+	pub const fn batching(&self) -> Option<Batching> {
+		self.writer.batching()
+	}
+
+	/// Sets how requests are batched, or stops batching them if `batching`
+	/// is `None`.
+	///
+	/// While batching, requests sent with [`send_raw`] are held like those
+	/// queued with [`send_queued`], but for no longer than
+	/// [`Batching::max_delay`]. The [flush threshold] is set to
+	/// [`Batching::max_bytes`], and is reset to its default when batching is
+	/// stopped.
+	///
+	/// [`send_raw`]: Client::send_raw
+	/// [`send_queued`]: Client::send_queued
+	/// [flush threshold]: Client::set_flush_threshold
+	pub fn set_batching(&mut self, batching: Option<Batching>) {
+		self.writer.set_batching(batching);
+	}
+}
+
+impl RequestWriter {
+	/// Returns how requests are batched.
+	///
+	/// See [`Client::batching`].
+	pub const fn batching(&self) -> Option<Batching> {
+		self.batching
+	}
+
+	/// Sets how requests are batched, or stops batching them if `batching`
+	/// is `None`.
+	///
+	/// See [`Client::set_batching`].
+	pub fn set_batching(&mut self, batching: Option<Batching>) {
+		self.flush_threshold =
+			batching.map_or(DEFAULT_FLUSH_THRESHOLD, |batching| batching.max_bytes);
+		self.batching = batching;
+	}
+
+	/// Returns whether held requests are due to be written, which they
+	/// always are unless batching is enabled.
+	pub(crate) fn batch_due(&self) -> bool {
+		let Some(batching) = self.batching else {
+			return true;
+		};
+
+		lock(&self.held_since).is_none_or(|since| Instant::now() >= since + batching.max_delay)
+	}
+
+	/// Records that a request has been written to the stream's buffer.
+	///
+	/// If it is the first request of a batch, a timer is started to write the
+	/// batch once it is due. This must be called with the stream locked.
+	pub(crate) fn hold(&self) {
+		let mut held_since = lock(&self.held_since);
+
+		if held_since.is_some() {
+			return;
+		}
+
+		let since = Instant::now();
+		*held_since = Some(since);
+
+		if let Some(batching) = self.batching {
+			self.start_timer(since, since + batching.max_delay);
+		}
+	}
+
+	/// Records that the requests in the stream's buffer have been written.
+	///
+	/// This must be called with the stream locked.
+	pub(crate) fn release(&self) {
+		*lock(&self.held_since) = None;
+	}
+
+	/// Spawns a task which writes the batch held `since` at `deadline`, if it
+	/// hasn't been written by then.
+	fn start_timer(&self, since: Instant, deadline: Instant) {
+		// The task doesn't keep the stream open once the writer is dropped.
+		let stream = Arc::downgrade(&self.stream);
+		let held_since = Arc::clone(&self.held_since);
+
+		tokio::spawn(async move {
+			time::sleep_until(deadline.into()).await;
+
+			let Some(stream) = stream.upgrade() else {
+				return;
+			};
+			let mut stream = stream.lock().await;
+
+			// The batch may already have been written, and another one
+			// started.
+			if *lock(&held_since) != Some(since) {
+				return;
+			}
+
+			// If writing fails, the requests are kept in the buffer, and the
+			// error is returned when the writer next writes them.
+			if stream.flush().await.is_ok() {
+				*lock(&held_since) = None;
+			}
+		});
+	}
+}
+
+/// Locks `mutex`, even if another thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+}
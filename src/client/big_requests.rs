@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The BIG-REQUESTS extension, which raises the maximum request length.
+//!
+//! The length in a request's header is a `u16` number of 4-byte blocks, so
+//! requests are limited to 256 KiB. Once BIG-REQUESTS is enabled, longer
+//! requests give a length of `0` in their header followed by a `u32` length,
+//! up to the larger maximum given by the X server. It is enabled when
+//! connecting if the X server supports it.
+
+use super::rw::X11Frame;
+use crate::{
+	extension,
+	raw::{assert_wire_size, RawRequest},
+	request::{self, Reply, Request},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::BytesMut;
+use tokio::io;
+
+/// The name of the BIG-REQUESTS extension.
+const BIG_REQUESTS: &str = "BIG-REQUESTS";
+
+/// The minor opcode of the `BigReqEnable` request.
+const ENABLE: u8 = 0;
+
+/// The BIG-REQUESTS extension's `BigReqEnable` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BigReqEnable;
+
+impl Request for BigReqEnable {
+	type Reply = MaximumRequestLength;
+
+	const NAME: &'static str = "BigReqEnable";
+	const EXTENSION: Option<&'static str> = Some(BIG_REQUESTS);
+	const OPCODE: u8 = ENABLE;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(BigReqEnable: [u8, u8, u16]);
+
+/// The maximum request length, in 4-byte blocks, once BIG-REQUESTS is
+/// enabled.
+///
+/// This is the reply to a `BigReqEnable` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct MaximumRequestLength(u32);
+
+assert_wire_size!("BigReqEnable reply": [u8, u8, u16, u32, u32, [u8; 20]] == 32);
+
+impl Reply for MaximumRequestLength {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[l0, l1, l2, l3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self(u32::from_be_bytes([l0, l1, l2, l3])))
+	}
+}
+
+impl Client {
+	/// Enables BIG-REQUESTS, if the X server supports it, and remembers the
+	/// maximum request length it gives.
+	pub(super) async fn enable_big_requests(&mut self) -> io::Result<()> {
+		let result = async {
+			if extension::query_extension(self, BIG_REQUESTS)
+				.await?
+				.is_none()
+			{
+				return Ok(None);
+			}
+
+			let MaximumRequestLength(length) = request::reply_to(self, &BigReqEnable).await?;
+
+			Ok(Some(length))
+		}
+		.await;
+
+		match result {
+			Ok(Some(maximum_request_length)) => {
+				self.writer.big_requests = true;
+				self.writer.maximum_request_length = maximum_request_length;
+
+				Ok(())
+			},
+			Ok(None) => Ok(()),
+
+			Err(ReplyError::Io(error)) => Err(error),
+			Err(ReplyError::X11(error)) => Err(io::Error::other(error)),
+			Err(ReplyError::UnsupportedVersion(error)) => Err(io::Error::other(error)),
+			Err(ReplyError::Disconnected) => Err(io::ErrorKind::ConnectionReset.into()),
+		}
+	}
+
+	/// Returns the maximum length of a request, in 4-byte blocks.
+	///
+	/// If BIG-REQUESTS is enabled, this is the maximum it gives, and requests
+	/// longer than 65535 blocks are sent with its extended length encoding.
+	/// Otherwise, this is the [maximum from the setup].
+	///
+	/// [maximum from the setup]: super::Setup::maximum_request_length
+	pub const fn maximum_request_length(&self) -> u32 {
+		self.writer.maximum_request_length()
+	}
+
+	/// Returns whether BIG-REQUESTS was enabled when connecting.
+	pub const fn big_requests_enabled(&self) -> bool {
+		self.writer.big_requests_enabled()
+	}
+}
+
+impl RequestWriter {
+	/// Returns the maximum length of a request, in 4-byte blocks.
+	///
+	/// See [`Client::maximum_request_length`].
+	pub const fn maximum_request_length(&self) -> u32 {
+		self.maximum_request_length
+	}
+
+	/// Returns whether BIG-REQUESTS was enabled when connecting.
+	pub const fn big_requests_enabled(&self) -> bool {
+		self.big_requests
+	}
+
+	/// Converts `request` into a frame, using the extended length encoding if
+	/// BIG-REQUESTS is enabled and the request needs it.
+	///
+	/// Returns an [`InvalidInput`] error if the request is longer than the
+	/// maximum request length.
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	pub(crate) fn request_frame(&self, request: RawRequest) -> io::Result<X11Frame> {
+		let mut blocks = 1 + request.body.len().div_ceil(4);
+
+		// The extended length encoding takes an extra block.
+		if blocks > usize::from(u16::MAX) {
+			blocks += 1;
+		}
+
+		if blocks > self.maximum_request_length as usize {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!(
+					"request of {blocks} blocks is longer than the maximum request length of {} \
+					 blocks",
+					self.maximum_request_length,
+				),
+			));
+		}
+
+		if self.big_requests {
+			request.into_big_frame()
+		} else {
+			request.into_frame()
+		}
+		.map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+	}
+}
@@ -0,0 +1,252 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Receiving events, either by reading them directly or from a background
+//! task.
+//!
+//! Reading frames is what delivers replies to [`ReplyCookie`]s, so something
+//! must keep reading for cookies to resolve. Either call [`next_event`] in a
+//! loop, or [spawn] a task which reads frames in the background and sends the
+//! events to an [`EventStream`], while requests are sent from other tasks
+//! with the [`RequestWriter`].
+//!
+//! [`ReplyCookie`]: crate::ReplyCookie
+//! [`RequestWriter`]: crate::RequestWriter
+//! [`next_event`]: EventReader::next_event
+//! [spawn]: EventReader::spawn
+
+use super::rw::{Error, X11Frame};
+use crate::{x11, Client, EventReader};
+use std::{
+	pin::Pin,
+	task::{Context, Poll},
+	time::Instant,
+};
+use tokio::{io, sync::mpsc, task::JoinHandle};
+
+impl Client {
+	/// Receives the next event, or error not claimed by a [`ReplyCookie`],
+	/// from the X server.
+	///
+	/// Requests held by batching are sent first, as the events may be
+	/// responses to them. See [`EventReader::next_event`].
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub async fn next_event(&mut self) -> io::Result<Option<X11Frame>> {
+		self.flush_batch().await?;
+
+		self.reader.next_event().await
+	}
+
+	/// Sends any held requests, then receives the next frame for which
+	/// `matches` returns true.
+	///
+	/// Other frames are kept to be returned by [`next_event`] later. Returns
+	/// `None` if the connection was closed by the X server.
+	///
+	/// [`next_event`]: Client::next_event
+	#[cfg_attr(not(any(feature = "filter", feature = "selection")), allow(dead_code))]
+	pub(crate) async fn wait_for_frame(
+		&mut self, matches: impl FnMut(&X11Frame) -> bool,
+	) -> io::Result<Option<X11Frame>> {
+		self.writer.flush().await?;
+
+		self.reader.wait_for_frame(matches).await
+	}
+}
+
+impl EventReader {
+	/// Receives the next event, or error not claimed by a [`ReplyCookie`],
+	/// from the X server.
+	///
+	/// Errors are returned alongside events because requests which do not
+	/// generate replies have no cookie to report them to. Replies to requests
+	/// sent with [`send_raw`], which are not tracked, are discarded; use
+	/// [`send_raw_with_reply`] for requests whose replies are wanted.
+	///
+	/// Returns `None` if the connection was closed by the X server.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	/// [`send_raw`]: crate::RequestWriter::send_raw
+	/// [`send_raw_with_reply`]: crate::RequestWriter::send_raw_with_reply
+	pub async fn next_event(&mut self) -> io::Result<Option<X11Frame>> {
+		loop {
+			match self.read_frame().await {
+				Ok(Some(X11Frame::Reply { .. })) => continue,
+				Ok(frame) => return Ok(frame),
+
+				Err(Error::Io(error)) => return Err(error),
+				Err(Error::Incomplete) => return Err(io::ErrorKind::UnexpectedEof.into()),
+			}
+		}
+	}
+
+	/// Receives the next frame for which `matches` returns true, starting
+	/// with those which have already been received.
+	///
+	/// See [`Client::wait_for_frame`].
+	#[cfg_attr(not(any(feature = "filter", feature = "selection")), allow(dead_code))]
+	pub(crate) async fn wait_for_frame(
+		&mut self, mut matches: impl FnMut(&X11Frame) -> bool,
+	) -> io::Result<Option<X11Frame>> {
+		if let Some(index) = self.deferred.iter().position(|(frame, _)| matches(frame)) {
+			return Ok(self.deferred.remove(index).map(|(frame, _)| frame));
+		}
+
+		loop {
+			match self.receive_frame().await {
+				Ok(Some(frame)) if matches(&frame) => return Ok(Some(frame)),
+				Ok(Some(frame)) => self.deferred.push_back((frame, Instant::now())),
+				Ok(None) => return Ok(None),
+
+				Err(Error::Io(error)) => return Err(error),
+				Err(Error::Incomplete) => return Err(io::ErrorKind::UnexpectedEof.into()),
+			}
+		}
+	}
+
+	/// Spawns a task which reads frames in the background, returning an
+	/// [`EventStream`] of the events and unclaimed errors it receives.
+	///
+	/// While the task is running, [`ReplyCookie`]s can be awaited directly
+	/// from any task.
+	///
+	/// Events are buffered without limit until they are received from the
+	/// `EventStream`, so that the task never stops reading replies while
+	/// waiting for events to be consumed. Buffered events are not included in
+	/// [`queue_snapshot`]s.
+	///
+	/// This must be called from within a tokio runtime.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	/// [`queue_snapshot`]: EventReader::queue_snapshot
+	pub fn spawn(self) -> EventStream {
+		self.spawn_inner(None)
+	}
+
+	/// Spawns a task which reads frames in the background, like [`spawn`],
+	/// but sends errors not claimed by a [`ReplyCookie`] to a separate
+	/// [`ErrorStream`] instead of the [`EventStream`].
+	///
+	/// Errors are decoded into [`x11::Error`]s. If the `ErrorStream` is
+	/// dropped, further errors are discarded.
+	///
+	/// This must be called from within a tokio runtime.
+	///
+	/// [`spawn`]: EventReader::spawn
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub fn spawn_with_errors(self) -> (EventStream, ErrorStream) {
+		let (sender, receiver) = mpsc::unbounded_channel();
+
+		(self.spawn_inner(Some(sender)), ErrorStream { receiver })
+	}
+
+	/// Spawns the background task, sending errors to `errors` if it is given.
+	fn spawn_inner(mut self, errors: Option<mpsc::UnboundedSender<x11::Error>>) -> EventStream {
+		let (sender, receiver) = mpsc::unbounded_channel();
+
+		let task = tokio::spawn(async move {
+			loop {
+				match self.next_event().await {
+					Ok(Some(frame)) => {
+						if let Some(errors) = &errors {
+							if let Some(error) = x11::Error::from_frame(&frame) {
+								let _ = errors.send(error);
+								continue;
+							}
+						}
+
+						// The receiver is only dropped along with the
+						// `EventStream`, which aborts this task.
+						let _ = sender.send(Ok(frame));
+					},
+					Ok(None) => break,
+
+					Err(error) => {
+						let _ = sender.send(Err(error));
+						break;
+					},
+				}
+			}
+		});
+
+		EventStream { receiver, task }
+	}
+}
+
+/// A stream of the events and unclaimed errors received by an
+/// [`EventReader`] in a background task.
+///
+/// The stream ends when the connection is closed; an I/O error is the last
+/// item if it was not closed cleanly.
+///
+/// Dropping the `EventStream` stops the background task. Any
+/// [`ReplyCookie`]s still awaiting replies then resolve to
+/// [`ReplyError::Disconnected`].
+///
+/// See [`EventReader::spawn`].
+///
+/// [`ReplyCookie`]: crate::ReplyCookie
+/// [`ReplyError::Disconnected`]: crate::ReplyError::Disconnected
+#[derive(Debug)]
+pub struct EventStream {
+	receiver: mpsc::UnboundedReceiver<io::Result<X11Frame>>,
+	task: JoinHandle<()>,
+}
+
+impl EventStream {
+	/// Receives the next event or unclaimed error.
+	///
+	/// Returns `None` once the connection has been closed and every received
+	/// event has been returned.
+	pub async fn recv(&mut self) -> Option<io::Result<X11Frame>> {
+		self.receiver.recv().await
+	}
+}
+
+impl futures_core::Stream for EventStream {
+	type Item = io::Result<X11Frame>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
+
+impl Drop for EventStream {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+/// A stream of the errors not claimed by a [`ReplyCookie`], received by an
+/// [`EventReader`] in a background task.
+///
+/// The stream ends when the background task stops, either because the
+/// connection was closed or because its [`EventStream`] was dropped.
+///
+/// See [`EventReader::spawn_with_errors`].
+///
+/// [`ReplyCookie`]: crate::ReplyCookie
+#[derive(Debug)]
+pub struct ErrorStream {
+	receiver: mpsc::UnboundedReceiver<x11::Error>,
+}
+
+impl ErrorStream {
+	/// Receives the next error.
+	///
+	/// Returns `None` once the background task has stopped and every received
+	/// error has been returned.
+	pub async fn recv(&mut self) -> Option<x11::Error> {
+		self.receiver.recv().await
+	}
+}
+
+impl futures_core::Stream for ErrorStream {
+	type Item = x11::Error;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
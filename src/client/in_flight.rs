@@ -0,0 +1,92 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tracking which requests the X server has yet to process, for measuring
+//! their latency.
+//!
+//! Both the profiler and the `metrics` instrumentation record requests as
+//! they are written and complete them as responses are read.
+
+use crate::raw::X11Frame;
+use std::{collections::VecDeque, time::Instant};
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The event code of `KeymapNotify` events, which have no sequence number.
+const KEYMAP_NOTIFY: u8 = 11;
+
+/// Returns the sequence number of the last request the X server processed
+/// before sending `frame`, and whether `frame` is a response to it.
+///
+/// Returns `None` for frames without a sequence number.
+pub(crate) fn sequence_of(frame: &X11Frame) -> Option<(u16, bool)> {
+	match frame {
+		X11Frame::Reply { sequence, .. } | X11Frame::Error { sequence, .. } => {
+			Some((*sequence, true))
+		},
+
+		// An event's sequence number tells us that every request up to that
+		// point has been processed, but it isn't a response to any of them.
+		X11Frame::Event { code, chunk } if code & !SEND_EVENT_MASK != KEYMAP_NOTIFY => {
+			Some((u16::from_be_bytes([chunk[1], chunk[2]]), false))
+		},
+		X11Frame::GenericEvent { sequence, .. } => Some((*sequence, false)),
+
+		_ => None,
+	}
+}
+
+/// The sequence number, `T` (such as what kind of request it is), and time
+/// sent of requests which the X server has not yet been seen to process,
+/// oldest first.
+#[derive(Debug, Clone)]
+pub(crate) struct InFlight<T>(VecDeque<(u16, T, Instant)>);
+
+impl<T> Default for InFlight<T> {
+	fn default() -> Self {
+		Self(VecDeque::new())
+	}
+}
+
+impl<T: Copy> InFlight<T> {
+	/// Records that a request with the given `sequence` number was just sent.
+	pub(crate) fn push(&mut self, sequence: u16, request: T) {
+		self.0.push_back((sequence, request, Instant::now()));
+	}
+
+	/// Forgets every in-flight request.
+	#[cfg_attr(not(feature = "profiler"), allow(dead_code))]
+	pub(crate) fn clear(&mut self) {
+		self.0.clear();
+	}
+
+	/// Removes requests up to `sequence` from the in-flight requests, since
+	/// the X server must have processed them.
+	///
+	/// If `responded` is true and the request with the sequence number
+	/// `sequence` is in flight, it is returned along with when it was sent.
+	pub(crate) fn complete(&mut self, sequence: u16, responded: bool) -> Option<(T, Instant)> {
+		/// Sequence numbers wrap around, so any sequence number less than half
+		/// the range behind `sequence` is considered to be before it.
+		const HALF: u16 = 0x8000;
+
+		let mut completed = None;
+
+		while let Some(&(in_flight_sequence, request, sent)) = self.0.front() {
+			if sequence.wrapping_sub(in_flight_sequence) >= HALF {
+				break;
+			}
+
+			self.0.pop_front();
+
+			if responded && in_flight_sequence == sequence {
+				completed = Some((request, sent));
+			}
+		}
+
+		completed
+	}
+}
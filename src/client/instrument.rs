@@ -0,0 +1,114 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Metrics recorded through the [`metrics`] facade when the `metrics` feature
+//! is enabled.
+//!
+//! The following metrics are recorded, and are sent to whichever recorder is
+//! installed by the application (e.g. a Prometheus exporter):
+//!
+//! | Name                          | Type      | Labels   |
+//! |-------------------------------|-----------|----------|
+//! | `xrs_requests_sent_total`     | counter   | `opcode` |
+//! | `xrs_request_latency_seconds` | histogram | `opcode` |
+//! | `xrs_replies_received_total`  | counter   |          |
+//! | `xrs_errors_received_total`   | counter   | `code`   |
+//! | `xrs_events_received_total`   | counter   | `code`   |
+//! | `xrs_read_buffer_bytes`       | gauge     |          |
+//!
+//! Request latency is the time from a request being written to its reply or
+//! error being received, so it is only recorded for requests which generate
+//! one.
+
+use super::in_flight::{self, InFlight};
+use crate::{raw::X11Frame, EventReader, RequestWriter};
+use std::sync::{Arc, Mutex};
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The event code of generic events.
+const GENERIC_EVENT: u8 = 35;
+
+/// The major opcode of each request which the X server has not yet been seen
+/// to process.
+///
+/// This is shared between the [`EventReader`] and [`RequestWriter`] of a
+/// client, since requests are recorded when they are written and completed
+/// when a response is read.
+#[derive(Clone, Default)]
+pub(crate) struct SharedInFlight(Arc<Mutex<InFlight<u8>>>);
+
+impl SharedInFlight {
+	/// Records that a request with the given `sequence` number and
+	/// `major_opcode` was just sent.
+	fn push(&self, sequence: u16, major_opcode: u8) {
+		let mut in_flight = self.0.lock().unwrap_or_else(|error| error.into_inner());
+
+		in_flight.push(sequence, major_opcode);
+	}
+
+	/// Removes requests up to `sequence` from the in-flight requests, and
+	/// records the latency of the request with the sequence number `sequence`
+	/// if `responded` is true.
+	fn complete(&self, sequence: u16, responded: bool) {
+		let mut in_flight = self.0.lock().unwrap_or_else(|error| error.into_inner());
+
+		if let Some((opcode, sent)) = in_flight.complete(sequence, responded) {
+			metrics::histogram!("xrs_request_latency_seconds", "opcode" => opcode.to_string())
+				.record(sent.elapsed().as_secs_f64());
+		}
+	}
+}
+
+impl RequestWriter {
+	/// Records that `frame` was just sent.
+	pub(super) fn record_frame_sent(&mut self, frame: &X11Frame) {
+		let X11Frame::Request { major_opcode, .. } = frame else {
+			return;
+		};
+
+		metrics::counter!("xrs_requests_sent_total", "opcode" => major_opcode.to_string())
+			.increment(1);
+
+		self.in_flight.push(self.sequence, *major_opcode);
+	}
+}
+
+impl EventReader {
+	/// Records that `frame` was just received.
+	pub(super) fn record_frame_received(&mut self, frame: &X11Frame) {
+		match frame {
+			X11Frame::Reply { .. } => {
+				metrics::counter!("xrs_replies_received_total").increment(1);
+			},
+
+			X11Frame::Error { code, .. } => {
+				metrics::counter!("xrs_errors_received_total", "code" => code.to_string())
+					.increment(1);
+			},
+
+			X11Frame::Event { code, .. } => {
+				let code = code & !SEND_EVENT_MASK;
+
+				metrics::counter!("xrs_events_received_total", "code" => code.to_string())
+					.increment(1);
+			},
+
+			X11Frame::GenericEvent { .. } => {
+				metrics::counter!("xrs_events_received_total", "code" => GENERIC_EVENT.to_string())
+					.increment(1);
+			},
+
+			X11Frame::Request { .. } => {},
+		}
+
+		if let Some((sequence, responded)) = in_flight::sequence_of(frame) {
+			self.in_flight.complete(sequence, responded);
+		}
+
+		metrics::gauge!("xrs_read_buffer_bytes").set(self.buffer.len() as f64);
+	}
+}
@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Inspection of frames which have been received from the X server but not
+//! yet delivered.
+//!
+//! This is a debugging aid: if a UI appears stuck, a [`QueueSnapshot`] shows
+//! whether events are arriving from the X server but are not being consumed.
+
+use super::rw::X11Frame;
+use crate::{Client, EventReader};
+use bytes::BytesMut;
+use std::{
+	fmt,
+	fmt::Formatter,
+	time::{Duration, Instant},
+};
+
+/// The event code of `KeymapNotify` events, which do not contain a sequence
+/// number.
+const KEYMAP_NOTIFY: u8 = 11;
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// A snapshot of the frames which have been received by a [`Client`] but not
+/// yet delivered.
+///
+/// See [`Client::queue_snapshot`].
+#[derive(Debug, Clone)]
+pub struct QueueSnapshot {
+	/// The complete frames waiting to be delivered, in the order they were
+	/// received.
+	pub frames: Vec<QueuedFrame>,
+	/// The number of bytes received after the last complete frame, belonging
+	/// to a frame which has only been partially received.
+	pub incomplete_bytes: usize,
+}
+
+/// A frame which has been received from the X server but not yet delivered.
+#[derive(Debug, Clone)]
+pub struct QueuedFrame {
+	/// What type of frame this is.
+	pub kind: QueuedFrameKind,
+	/// The sequence number of the frame, if it has one.
+	///
+	/// `KeymapNotify` events are the only frames without a sequence number.
+	pub sequence: Option<u16>,
+	/// The length of the frame in bytes.
+	pub length: usize,
+	/// How long it has been since the frame was fully received.
+	pub age: Duration,
+}
+
+/// The type of a [`QueuedFrame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueuedFrameKind {
+	/// A reply to a request.
+	Reply,
+	/// An error generated by a request.
+	Error {
+		/// The code identifying the type of error.
+		code: u8,
+	},
+	/// An event.
+	Event {
+		/// The code identifying the type of event.
+		code: u8,
+		/// Whether the event was sent by another client with a `SendEvent`
+		/// request.
+		send_event: bool,
+	},
+}
+
+impl Client {
+	/// Takes a snapshot of the frames which have been received from the X
+	/// server but have not yet been delivered.
+	///
+	/// This does not consume any frames; it is intended for debugging.
+	pub fn queue_snapshot(&self) -> QueueSnapshot {
+		self.reader.queue_snapshot()
+	}
+}
+
+impl EventReader {
+	/// Takes a snapshot of the frames which have been received from the X
+	/// server but have not yet been delivered.
+	///
+	/// This does not consume any frames; it is intended for debugging.
+	pub fn queue_snapshot(&self) -> QueueSnapshot {
+		let now = Instant::now();
+
+		// Frames which were received while waiting for a reply are delivered
+		// before those still in the buffer.
+		let mut frames: Vec<_> = self
+			.deferred
+			.iter()
+			.map(|(frame, received)| {
+				let mut bytes = BytesMut::with_capacity(frame.size());
+				frame.write_to(&mut bytes);
+
+				queued_frame(&bytes, now.saturating_duration_since(*received))
+			})
+			.collect();
+
+		let mut buf = &self.buffer[..];
+
+		loop {
+			let start = self.buffer.len() - buf.len();
+
+			if X11Frame::check(&mut buf).is_err() {
+				break;
+			}
+
+			let end = self.buffer.len() - buf.len();
+
+			// The frame was fully received when its last byte was received.
+			let received = self
+				.received
+				.iter()
+				.find(|(received_end, _)| *received_end >= end)
+				.map_or(now, |(_, time)| *time);
+
+			frames.push(queued_frame(
+				&self.buffer[start..end],
+				now.saturating_duration_since(received),
+			));
+		}
+
+		QueueSnapshot {
+			incomplete_bytes: buf.len(),
+			frames,
+		}
+	}
+}
+
+/// Describes the received `frame`, which was fully received `age` ago.
+fn queued_frame(frame: &[u8], age: Duration) -> QueuedFrame {
+	let sequence = Some(u16::from_be_bytes([frame[2], frame[3]]));
+
+	let (kind, sequence) = match frame[0] {
+		0 => (QueuedFrameKind::Error { code: frame[1] }, sequence),
+		1 => (QueuedFrameKind::Reply, sequence),
+
+		code => {
+			let send_event = code & SEND_EVENT_MASK != 0;
+			let code = code & !SEND_EVENT_MASK;
+
+			let sequence = if code == KEYMAP_NOTIFY {
+				None
+			} else {
+				sequence
+			};
+
+			(QueuedFrameKind::Event { code, send_event }, sequence)
+		},
+	};
+
+	QueuedFrame {
+		kind,
+		sequence,
+		length: frame.len(),
+		age,
+	}
+}
+
+impl fmt::Display for QueueSnapshot {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"{} queued frame(s), {} incomplete byte(s)",
+			self.frames.len(),
+			self.incomplete_bytes,
+		)?;
+
+		for frame in &self.frames {
+			writeln!(f, "  {}", frame)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl fmt::Display for QueuedFrame {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.kind {
+			QueuedFrameKind::Reply => write!(f, "reply")?,
+			QueuedFrameKind::Error { code } => write!(f, "error (code {})", code)?,
+
+			QueuedFrameKind::Event { code, send_event } => {
+				write!(f, "event (code {})", code)?;
+
+				if send_event {
+					write!(f, " [sent]")?;
+				}
+			},
+		}
+
+		if let Some(sequence) = self.sequence {
+			write!(f, ", sequence {}", sequence)?;
+		}
+
+		write!(f, ", {} bytes, age {:?}", self.length, self.age)
+	}
+}
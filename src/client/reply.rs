@@ -0,0 +1,594 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Correlation of replies and errors with the requests which generated them.
+//!
+//! Requests which generate a reply are tracked by their sequence number. When
+//! the [`EventReader`] receives a reply or error with that sequence number,
+//! it is delivered to the request's [`ReplyCookie`] instead of being returned
+//! by [`read_raw_frame`].
+//!
+//! [`read_raw_frame`]: EventReader::read_raw_frame
+
+use super::rw::{Error, X11Frame};
+use crate::{
+	extension::UnsupportedVersion,
+	raw::RawRequest,
+	x11,
+	Client,
+	EventReader,
+	RequestWriter,
+};
+#[cfg(all(unix, feature = "fds"))]
+use std::os::fd::OwnedFd;
+use std::{
+	collections::HashMap,
+	fmt,
+	fmt::Formatter,
+	future::Future,
+	pin::Pin,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+		Mutex,
+		MutexGuard,
+	},
+	task::{Context, Poll, Waker},
+	time::Instant,
+};
+use tokio::io;
+
+/// The state of a request which generates a reply.
+enum Slot {
+	/// The reply has not been received yet.
+	Waiting(Option<Waker>),
+	/// The reply or error has been received.
+	Received(Result<X11Frame, x11::Error>),
+
+	/// The connection was closed before the reply was received.
+	Closed,
+	/// The request's [`ReplyCookie`] was dropped, so its reply is to be
+	/// discarded when it is received.
+	Abandoned,
+}
+
+/// The requests awaiting replies, shared between an [`EventReader`] and a
+/// [`RequestWriter`].
+#[derive(Clone, Default)]
+pub(crate) struct Replies {
+	slots: Arc<Mutex<HashMap<u16, Slot>>>,
+	/// Whether the connection has been closed, so that requests registered
+	/// afterwards are never going to receive a reply.
+	///
+	/// This is only read or written while `slots` is locked.
+	closed: Arc<AtomicBool>,
+
+	/// The file descriptors sent with the replies of requests which expect
+	/// them, by sequence number.
+	#[cfg(all(unix, feature = "fds"))]
+	fds: Arc<Mutex<HashMap<u16, Vec<OwnedFd>>>>,
+}
+
+/// Locks `mutex`, even if another thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	// The maps are never left in an inconsistent state, so it is fine to keep
+	// using them if another thread panicked while holding the lock.
+	mutex
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Replies {
+	fn lock(&self) -> MutexGuard<'_, HashMap<u16, Slot>> {
+		lock(&self.slots)
+	}
+
+	/// Expects the reply to the request with the given `sequence` number to
+	/// be sent with file descriptors.
+	#[cfg(all(unix, feature = "fds"))]
+	fn expect_fds(&self, sequence: u16) {
+		lock(&self.fds).insert(sequence, Vec::new());
+	}
+
+	/// Whether the reply to the request with the given `sequence` number is
+	/// expected to be sent with file descriptors.
+	#[cfg(all(unix, feature = "fds"))]
+	pub(crate) fn expects_fds(&self, sequence: u16) -> bool {
+		lock(&self.fds).contains_key(&sequence)
+	}
+
+	/// Stores the file descriptors sent with the reply to the request with
+	/// the given `sequence` number.
+	#[cfg(all(unix, feature = "fds"))]
+	pub(crate) fn store_fds(&self, sequence: u16, fds: Vec<OwnedFd>) {
+		lock(&self.fds).insert(sequence, fds);
+	}
+
+	/// Takes the file descriptors sent with the reply to the request with the
+	/// given `sequence` number.
+	#[cfg(all(unix, feature = "fds"))]
+	fn take_fds(&self, sequence: u16) -> Vec<OwnedFd> {
+		lock(&self.fds).remove(&sequence).unwrap_or_default()
+	}
+
+	/// Starts waiting for the reply to the request with the given
+	/// `sequence` number.
+	///
+	/// If the connection has already been closed, the request is marked as
+	/// never going to receive a reply.
+	fn register(&self, sequence: u16) {
+		let mut slots = self.lock();
+
+		let slot = match self.closed.load(Ordering::Relaxed) {
+			false => Slot::Waiting(None),
+			true => Slot::Closed,
+		};
+		slots.insert(sequence, slot);
+	}
+
+	/// Delivers `frame` to the [`ReplyCookie`] waiting for it.
+	///
+	/// Returns `frame` if it is not a reply or error to a tracked request.
+	pub(crate) fn deliver(&self, frame: X11Frame) -> Option<X11Frame> {
+		let sequence = match &frame {
+			X11Frame::Reply { sequence, .. } | X11Frame::Error { sequence, .. } => *sequence,
+
+			_ => return Some(frame),
+		};
+
+		let mut slots = self.lock();
+
+		match slots.remove(&sequence) {
+			Some(Slot::Waiting(waker)) => {
+				let received = match x11::Error::from_frame(&frame) {
+					Some(error) => Err(error),
+					None => Ok(frame),
+				};
+				slots.insert(sequence, Slot::Received(received));
+
+				if let Some(waker) = waker {
+					waker.wake();
+				}
+
+				None
+			},
+
+			Some(Slot::Abandoned) => {
+				// Any file descriptors sent with the reply are closed.
+				#[cfg(all(unix, feature = "fds"))]
+				self.take_fds(sequence);
+
+				None
+			},
+
+			Some(slot) => {
+				slots.insert(sequence, slot);
+
+				Some(frame)
+			},
+			None => Some(frame),
+		}
+	}
+
+	/// Marks every request still waiting for a reply as never going to
+	/// receive one, because the connection was closed.
+	pub(crate) fn close(&self) {
+		#[cfg(all(unix, feature = "fds"))]
+		lock(&self.fds).clear();
+
+		let mut slots = self.lock();
+		self.closed.store(true, Ordering::Relaxed);
+
+		slots.retain(|_, slot| !matches!(slot, Slot::Abandoned));
+
+		for slot in slots.values_mut() {
+			if let Slot::Waiting(waker) = slot {
+				if let Some(waker) = waker.take() {
+					waker.wake();
+				}
+
+				*slot = Slot::Closed;
+			}
+		}
+	}
+
+	/// Takes the reply to the request with the given `sequence` number if it
+	/// has been received, otherwise storing `waker` to be woken when it is.
+	fn poll(&self, sequence: u16, waker: Option<&Waker>) -> Poll<Result<X11Frame, ReplyError>> {
+		let mut slots = self.lock();
+
+		match slots.remove(&sequence) {
+			Some(Slot::Received(Ok(reply))) => Poll::Ready(Ok(reply)),
+			Some(Slot::Received(Err(error))) => Poll::Ready(Err(ReplyError::X11(error))),
+
+			Some(Slot::Waiting(previous)) => {
+				let waker = waker.cloned().or(previous);
+				slots.insert(sequence, Slot::Waiting(waker));
+
+				Poll::Pending
+			},
+
+			Some(Slot::Closed | Slot::Abandoned) | None => {
+				Poll::Ready(Err(ReplyError::Disconnected))
+			},
+		}
+	}
+
+	/// Stops waiting for the reply to the request with the given `sequence`
+	/// number, discarding it if it has already been received.
+	fn abandon(&self, sequence: u16) {
+		let mut slots = self.lock();
+
+		match slots.remove(&sequence) {
+			Some(Slot::Waiting(_)) => {
+				slots.insert(sequence, Slot::Abandoned);
+			},
+
+			// Any file descriptors sent with the reply are closed.
+			#[cfg(all(unix, feature = "fds"))]
+			Some(Slot::Received(_)) => {
+				self.take_fds(sequence);
+			},
+
+			_ => {},
+		}
+	}
+}
+
+/// A request's pending reply.
+///
+/// A `ReplyCookie` is a [`Future`] which resolves once the reply or error is
+/// received. Replies are only received while frames are being read, so it can
+/// only be awaited directly if the [`EventReader`] is being read from
+/// elsewhere, such as another task. Otherwise, use [`Client::wait_for_reply`],
+/// which reads frames until the reply arrives.
+///
+/// If a `ReplyCookie` is dropped, its reply is discarded when it is received.
+#[must_use = "the reply is discarded if the cookie is dropped"]
+pub struct ReplyCookie {
+	sequence: u16,
+	replies: Replies,
+
+	/// Whether the reply has been taken, so there is nothing to abandon.
+	done: bool,
+}
+
+impl ReplyCookie {
+	/// The sequence number of the request.
+	pub const fn sequence(&self) -> u16 {
+		self.sequence
+	}
+
+	/// Takes the reply if it has been received.
+	fn try_take(&mut self) -> Option<Result<X11Frame, ReplyError>> {
+		match self.replies.poll(self.sequence, None) {
+			Poll::Ready(result) => {
+				self.done = true;
+
+				Some(result)
+			},
+
+			Poll::Pending => None,
+		}
+	}
+}
+
+impl Future for ReplyCookie {
+	type Output = Result<X11Frame, ReplyError>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let poll = self.replies.poll(self.sequence, Some(cx.waker()));
+
+		if poll.is_ready() {
+			self.done = true;
+		}
+
+		poll
+	}
+}
+
+impl Drop for ReplyCookie {
+	fn drop(&mut self) {
+		if !self.done {
+			self.replies.abandon(self.sequence);
+		}
+	}
+}
+
+impl fmt::Debug for ReplyCookie {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ReplyCookie")
+			.field("sequence", &self.sequence)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The reason a [`ReplyCookie`] did not resolve to a reply.
+#[derive(Debug)]
+pub enum ReplyError {
+	/// The X server responded to the request with an error.
+	X11(x11::Error),
+	/// An I/O error occurred while reading frames.
+	Io(io::Error),
+	/// The connection was closed before the reply was received.
+	Disconnected,
+	/// The X server's version of an extension is older than that needed for
+	/// the request, which wasn't sent.
+	UnsupportedVersion(UnsupportedVersion),
+}
+
+impl ReplyError {
+	/// Returns an error for a reply to the named request which could not be
+	/// read.
+	pub(crate) fn malformed(request: &str) -> Self {
+		Self::Io(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("malformed {request} reply"),
+		))
+	}
+}
+
+impl fmt::Display for ReplyError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::X11(error) => write!(f, "the X server responded with {error}"),
+
+			Self::Io(error) => write!(f, "{error}"),
+			Self::Disconnected => write!(f, "the connection was closed before the reply arrived"),
+			Self::UnsupportedVersion(error) => write!(f, "{error}"),
+		}
+	}
+}
+
+impl std::error::Error for ReplyError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::X11(error) => Some(error),
+			Self::Io(error) => Some(error),
+			Self::UnsupportedVersion(error) => Some(error),
+
+			Self::Disconnected => None,
+		}
+	}
+}
+
+/// A connection which can send requests and wait for their replies: either a
+/// [`Client`] or a [`RequestWriter`].
+///
+/// The helper functions which need a reply, such as
+/// [`property::get_property`], take any `RoundTrip`, so they can be used
+/// with either. A `Client` reads frames until the reply arrives, while a
+/// `RequestWriter` [awaits it directly].
+///
+/// This trait is sealed: it can't be implemented outside of X.RS.
+///
+/// [`property::get_property`]: crate::property::get_property
+/// [awaits it directly]: RequestWriter#awaiting-replies
+// Only `Client` and `RequestWriter` implement it, and their futures are
+// `Send`, so the lint's concern about unnameable `Send` bounds doesn't apply.
+#[allow(async_fn_in_trait)]
+pub trait RoundTrip: sealed::Sealed {
+	/// The [`RequestWriter`] requests are sent with.
+	fn writer(&mut self) -> &mut RequestWriter;
+
+	/// Sends a [`RawRequest`] which generates a reply, returning a
+	/// [`ReplyCookie`] for that reply.
+	async fn send_with_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie>;
+	/// Waits for the reply for `cookie`, and returns it.
+	async fn reply(&mut self, cookie: ReplyCookie) -> Result<X11Frame, ReplyError>;
+}
+
+mod sealed {
+	/// Prevents [`RoundTrip`] being implemented outside of X.RS.
+	///
+	/// [`RoundTrip`]: super::RoundTrip
+	pub trait Sealed {}
+
+	impl Sealed for crate::Client {}
+	impl Sealed for crate::RequestWriter {}
+}
+
+impl RoundTrip for Client {
+	fn writer(&mut self) -> &mut RequestWriter {
+		&mut self.writer
+	}
+
+	async fn send_with_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		self.send_raw_with_reply(request).await
+	}
+
+	async fn reply(&mut self, cookie: ReplyCookie) -> Result<X11Frame, ReplyError> {
+		self.wait_for_reply(cookie).await
+	}
+}
+
+impl RoundTrip for RequestWriter {
+	fn writer(&mut self) -> &mut RequestWriter {
+		self
+	}
+
+	async fn send_with_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		self.send_raw_with_reply(request).await
+	}
+
+	async fn reply(&mut self, cookie: ReplyCookie) -> Result<X11Frame, ReplyError> {
+		self.flush().await.map_err(ReplyError::Io)?;
+
+		cookie.await
+	}
+}
+
+/// Sends `request`, whose reply is sent with file descriptors, and waits for
+/// the reply and its file descriptors.
+#[cfg(all(unix, feature = "fds"))]
+#[cfg_attr(not(feature = "dri3"), allow(dead_code))]
+pub(crate) async fn reply_with_fds(
+	connection: &mut impl RoundTrip, request: RawRequest,
+) -> Result<(X11Frame, Vec<OwnedFd>), ReplyError> {
+	let replies = connection.writer().replies.clone();
+
+	let cookie = connection
+		.writer()
+		.send_raw_with_fd_reply(request)
+		.await
+		.map_err(ReplyError::Io)?;
+	let sequence = cookie.sequence();
+
+	let reply = connection.reply(cookie).await;
+	let fds = replies.take_fds(sequence);
+
+	Ok((reply?, fds))
+}
+
+impl Client {
+	/// Sends a [`RawRequest`] which generates a reply, returning a
+	/// [`ReplyCookie`] for that reply.
+	///
+	/// See [`RequestWriter::send_raw_with_reply`].
+	pub async fn send_raw_with_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		self.writer.send_raw_with_reply(request).await
+	}
+
+	/// Reads frames until the reply for `cookie` is received, and returns it.
+	///
+	/// Any queued requests are sent first, in case the reply depends on
+	/// them. Other frames read in the meantime are kept and returned by
+	/// [`read_raw_frame`] as normal.
+	///
+	/// [`read_raw_frame`]: Client::read_raw_frame
+	pub async fn wait_for_reply(&mut self, cookie: ReplyCookie) -> Result<X11Frame, ReplyError> {
+		self.writer.flush().await.map_err(ReplyError::Io)?;
+
+		self.reader.wait_for_reply(cookie).await
+	}
+
+	/// Sends a [`RawRequest`] whose reply is sent with file descriptors,
+	/// returning a [`ReplyCookie`] for that reply.
+	///
+	/// The file descriptors are kept for the reply when they are received,
+	/// rather than being queued for [`take_fds`]. Wait for the reply with
+	/// [`wait_for_reply_with_fds`] to receive them; they are closed if it is
+	/// waited for with [`wait_for_reply`] instead.
+	///
+	/// [`take_fds`]: Client::take_fds
+	/// [`wait_for_reply_with_fds`]: Client::wait_for_reply_with_fds
+	/// [`wait_for_reply`]: Client::wait_for_reply
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn send_raw_with_fd_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		self.writer.send_raw_with_fd_reply(request).await
+	}
+
+	/// Reads frames until the reply for `cookie` is received, and returns it
+	/// along with the file descriptors sent with it.
+	///
+	/// Only replies to requests sent with [`send_raw_with_fd_reply`] are
+	/// returned with their file descriptors; no file descriptors are returned
+	/// with other replies.
+	///
+	/// [`send_raw_with_fd_reply`]: Client::send_raw_with_fd_reply
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn wait_for_reply_with_fds(
+		&mut self, cookie: ReplyCookie,
+	) -> Result<(X11Frame, Vec<OwnedFd>), ReplyError> {
+		let sequence = cookie.sequence();
+		let reply = self.wait_for_reply(cookie).await;
+
+		Ok((reply?, self.writer.replies.take_fds(sequence)))
+	}
+}
+
+impl RequestWriter {
+	/// Sends a [`RawRequest`] which generates a reply, returning a
+	/// [`ReplyCookie`] for that reply.
+	///
+	/// Only use this for requests which generate a reply; the cookie for a
+	/// request without one never resolves, unless the request generates an
+	/// error.
+	pub async fn send_raw_with_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		// A pending `UngrabServer` takes the next sequence number when it is
+		// written, so it is written first.
+		self.buffer_ungrab_server().await?;
+
+		// The request is registered before it is sent so that its reply
+		// cannot be received before it is being waited for.
+		let sequence = self.sequence().wrapping_add(1);
+		self.replies.register(sequence);
+
+		let cookie = ReplyCookie {
+			sequence,
+			replies: self.replies.clone(),
+
+			done: false,
+		};
+
+		self.send_raw(request).await?;
+
+		Ok(cookie)
+	}
+
+	/// Sends a [`RawRequest`] whose reply is sent with file descriptors,
+	/// returning a [`ReplyCookie`] for that reply.
+	///
+	/// See [`Client::send_raw_with_fd_reply`].
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn send_raw_with_fd_reply(&mut self, request: RawRequest) -> io::Result<ReplyCookie> {
+		self.buffer_ungrab_server().await?;
+
+		// The file descriptors are expected before the request is sent, so
+		// that they can't be received before they are being waited for.
+		let sequence = self.sequence().wrapping_add(1);
+		self.replies.expect_fds(sequence);
+
+		self.send_raw_with_reply(request)
+			.await
+			.inspect_err(|_| drop(self.replies.take_fds(sequence)))
+	}
+
+	/// Waits for the reply for `cookie`, and returns it along with the file
+	/// descriptors sent with it.
+	///
+	/// Replies are [awaited directly]. See [`Client::wait_for_reply_with_fds`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn wait_for_reply_with_fds(
+		&mut self, cookie: ReplyCookie,
+	) -> Result<(X11Frame, Vec<OwnedFd>), ReplyError> {
+		self.flush().await.map_err(ReplyError::Io)?;
+
+		let sequence = cookie.sequence();
+		let reply = cookie.await;
+
+		Ok((reply?, self.replies.take_fds(sequence)))
+	}
+}
+
+impl EventReader {
+	/// Reads frames until the reply for `cookie` is received, and returns it.
+	///
+	/// See [`Client::wait_for_reply`].
+	pub async fn wait_for_reply(
+		&mut self, mut cookie: ReplyCookie,
+	) -> Result<X11Frame, ReplyError> {
+		loop {
+			if let Some(result) = cookie.try_take() {
+				return result;
+			}
+
+			match self.next_frame().await {
+				Ok(Some(frame)) => {
+					if let Some(frame) = self.replies.deliver(frame) {
+						self.deferred.push_back((frame, Instant::now()));
+					}
+				},
+				Ok(None) => return cookie.try_take().unwrap_or(Err(ReplyError::Disconnected)),
+
+				Err(Error::Io(error)) => return Err(ReplyError::Io(error)),
+				Err(Error::Incomplete) => {
+					return Err(ReplyError::Io(io::ErrorKind::UnexpectedEof.into()));
+				},
+			}
+		}
+	}
+}
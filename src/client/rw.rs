@@ -3,335 +3,88 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use self::Error::Incomplete;
-use crate::Client;
-use bytes::{Buf, Bytes};
-use std::io::Cursor;
-use tokio::{
-	io,
-	io::{AsyncReadExt, AsyncWriteExt},
-};
-
-pub(crate) enum X11Frame {
-	/// <table>
-	///     <tbody>
-	///         <tr>
-	///             <td><b>Byte</b></td>
-	///             <td><b>Meaning</b></td>
-	///         </tr>
-	///         <tr>
-	///             <td>0</td>
-	///             <td><code>major_opcode</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>1</td>
-	///             <td><code>metabyte</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>2</td>
-	///             <td rowspan="2"><code>length</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>3</td>
-	///         </tr>
-	///         <tr>
-	///             <td>4..(4 + (length * 4))</td>
-	///             <td><code>chunk</code></td>
-	///         </tr>
-	///     </tbody>
-	/// </table>
-	///
-	/// Note: requests are never read by X.RS; requests are received by X
-	/// servers, while X.RS is a client library. The only way to tell apart
-	/// requests vs. replies & events is whether they are received by an X
-	/// client or whether they are received by the X server.
-	Request {
-		/// The major opcode identifying the type of request (for core requests)
-		/// or the extension that defines the request (for extension requests).
-		major_opcode: u8,
-		/// A single byte in the header which may be used for additional data.
-		metabyte: u8,
-		/// The length of the request in blocks (units of 4 bytes).
-		///
-		/// If the big-requests extension is enabled and this is `0`, then the
-		/// next block is the length of the request instead, but as a `u32`
-		/// value.
-		length: u16,
-
-		/// Additional data contained in the request, if any.
-		chunk: Bytes,
-	},
-
-	/// <table>
-	///     <tbody>
-	///         <tr>
-	///             <td><b>Byte</b></td>
-	///             <td><b>Meaning</b></td>
-	///         </tr>
-	///         <tr>
-	///             <td>0</td>
-	///             <td><code>1</code> - reply indicator</td>
-	///         </tr>
-	///         <tr>
-	///             <td>1</td>
-	///             <td><code>metabyte</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>2</td>
-	///             <td rowspan="2"><code>sequence</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>3</td>
-	///         </tr>
-	///         <tr>
-	///             <td>4</td>
-	///             <td rowspan="4"><code>length</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>5</td>
-	///         </tr>
-	///         <tr>
-	///             <td>6</td>
-	///         </tr>
-	///         <tr>
-	///             <td>7</td>
-	///         </tr>
-	///         <tr>
-	///             <td>8..(32 + (length * 4))</td>
-	///             <td><code>chunk</code></td>
-	///         </tr>
-	///     </tbody>
-	/// </table>
-	Reply {
-		/// A single byte in the header which may be used for additional data.
-		metabyte: u8,
-		/// The sequence number which identifies when the request that generated
-		/// the reply was sent.
-		sequence: u16,
-		/// The length of any additional data after the first 8 blocks (32
-		/// bytes) in the reply in blocks (units of 4 bytes).
-		length: u32,
-
-		/// Data contained in the reply.
-		chunk: Bytes,
-	},
-
-	/// <table>
-	///     <tbody>
-	///         <tr>
-	///             <td><b>Byte</b></td>
-	///             <td><b>Meaning</b></td>
-	///         </tr>
-	///         <tr>
-	///             <td>0</td>
-	///             <td><code>code</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>1..32</td>
-	///             <td><code>chunk</code></td>
-	///         </tr>
-	///     </tbody>
-	/// </table>
-	Event {
-		/// The code uniquely identifying the type of event.
-		code: u8,
-		/// Data contained in the event.
-		chunk: [u8; 31],
-	},
-
-	/// <table>
-	///     <tbody>
-	///         <tr>
-	///             <td><b>Byte</b></td>
-	///             <td><b>Meaning</b></td>
-	///         </tr>
-	///         <tr>
-	///             <td>0</td>
-	///             <td><code>0</code> - error indicator</td>
-	///         </tr>
-	///         <tr>
-	///             <td>1</td>
-	///             <td><code>code</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>2</td>
-	///             <td rowspan="2"><code>sequence</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>3</td>
-	///         </tr>
-	///         <tr>
-	///             <td>4</td>
-	///             <td rowspan="4"><code>metablock</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>5</td>
-	///         </tr>
-	///         <tr>
-	///             <td>6</td>
-	///         </tr>
-	///         <tr>
-	///             <td>7</td>
-	///         </tr>
-	///         <tr>
-	///             <td>8</td>
-	///             <td rowspan="2"><code>minor_opcode</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>9</td>
-	///         </tr>
-	///         <tr>
-	///             <td>10</td>
-	///             <td><code>major_opcode</code></td>
-	///         </tr>
-	///         <tr>
-	///             <td>11..32</td>
-	///             <td><code>chunk</code></td>
-	///         </tr>
-	///     </tbody>
-	/// </table>
-	Error {
-		/// The code uniquely identifying the type of error.
-		code: u8,
-		/// The sequence number which identifies when the last request sent that
-		/// relates to the generation of this error was sent.
-		sequence: u16,
-		/// A single block (4 bytes) in the header which may be used for
-		/// additional data.
-		metablock: [u8; 4],
-
-		/// The minor opcode identifying the type of the last request sent that
-		/// relates to the generation of this error within its extension (`0`
-		/// for core requests).
-		minor_opcode: u16,
-		/// The major opcode identifying the type of (for core requests) or the
-		/// extension which defines (for extension requests) the last request
-		/// sent that relates to the generation of this error.
-		major_opcode: u8,
-
-		/// Additional data contained in the error.
-		chunk: [u8; 21],
-	},
-}
-
-impl X11Frame {
-	// https://tokio.rs/tokio/tutorial/framing
-	pub(crate) fn check(buf: &mut Cursor<&[u8]>) -> Result<(), Error> {
-		const BLOCK: usize = 4;
-		const REPLY_BODY: usize = 32 - (2 * BLOCK); // 24
-
-		match get_u8(buf)? {
-			// Reply
-			1 => {
-				// Skip the rest of the first block; it can't be invalid at this
-				// level of abstraction.
-				skip(buf, BLOCK - 1)?;
-				let len = get_u32(buf)? as usize;
-
-				// Skip the reply's data, verifying that enough bytes are
-				// present in the process.
-				skip(buf, REPLY_BODY + (len * BLOCK))
-			},
-
-			// Error or event
-			// Errors and events are always 32 bytes; none can be invalid at
-			// this level of abstraction, so we skip all of them, verifying that
-			// there are enough in the process.
-			0 | _ => skip(buf, 32 - 1),
-		}
-	}
-
+use crate::{grab::UngrabServer, keyboard, request::Request, Client, EventReader, RequestWriter};
+#[cfg(not(all(unix, feature = "fds")))]
+use bytes::BufMut;
+use bytes::{Buf, BytesMut};
+use std::{sync::atomic::Ordering, time::Instant};
+#[cfg(not(all(unix, feature = "fds")))]
+use tokio::io::AsyncReadExt;
+use tokio::{io, io::AsyncWriteExt};
+pub use xrs_proto::X11Frame;
+
+impl EventReader {
 	// https://tokio.rs/tokio/tutorial/framing
-	pub(crate) fn parse(buf: &mut Cursor<&[u8]>) -> Result<Self, Error> {
-		const BLOCK: usize = 4;
-
-		const MESSAGE_BASE: usize = 32;
-		const ERROR_BODY: usize = MESSAGE_BASE - 7;
-		const REPLY_BODY: usize = MESSAGE_BASE - (2 * BLOCK);
-
-		match get_u8(buf)? {
-			// Error
-			0 => {
-				let error_code = get_u8(buf)?;
-				let sequence = get_u16(buf)?;
-				let metablock = [get_u8(buf)?, get_u8(buf)?, get_u8(buf)?, get_u8(buf)?];
-
-				let minor_opcode = get_u16(buf)?;
-				let major_opcode = get_u8(buf)?;
+	fn parse_frame(&mut self) -> Result<Option<X11Frame>, Error> {
+		let mut buf = &self.buffer[..];
 
-				let chunk = buf.chunk()[..ERROR_BODY].try_into().unwrap();
-				skip(buf, ERROR_BODY)?;
+		match X11Frame::check(&mut buf) {
+			Ok(_) => {
+				let length = self.buffer.len() - buf.len();
 
-				Ok(Self::Error {
-					code: error_code,
-					sequence,
-					metablock,
+				let frame = X11Frame::parse(&mut &self.buffer[..length])?;
 
-					minor_opcode,
-					major_opcode,
+				self.buffer.advance(length);
+				self.consume_received(length);
 
-					chunk,
-				})
-			},
+				#[cfg(feature = "metrics")]
+				self.record_frame_received(&frame);
+				#[cfg(feature = "profiler")]
+				self.profiler.frame_received(&frame);
 
-			// Reply
-			1 => {
-				let metabyte = get_u8(buf)?;
-				let sequence = get_u16(buf)?;
-				let length = get_u32(buf)?;
+				#[cfg(all(unix, feature = "fds"))]
+				self.claim_fds(&frame);
 
-				let bytes = REPLY_BODY + ((length as usize) * BLOCK);
-				let chunk = Bytes::copy_from_slice(&buf.chunk()[..bytes]);
-				skip(buf, bytes)?;
+				if keyboard::changes_modifier_masks(&frame) {
+					self.modifiers_changed.store(true, Ordering::Release);
+				}
 
-				Ok(Self::Reply {
-					metabyte,
-					sequence,
-					length,
+				if let Some(hook) = &mut self.frame_hook {
+					hook(&frame);
+				}
 
-					chunk,
-				})
+				Ok(Some(frame))
 			},
 
-			// Event
-			event_code => {
-				let bytes = MESSAGE_BASE - 1;
-				let chunk = buf.chunk()[..bytes].try_into().unwrap();
-				skip(buf, bytes)?;
-
-				Ok(Self::Event {
-					code: event_code,
-					chunk,
-				})
-			},
+			Err(xrs_proto::Incomplete) => Ok(None),
 		}
 	}
-}
-
-impl Client {
-	// https://tokio.rs/tokio/tutorial/framing
-	fn parse_frame(&mut self) -> Result<Option<X11Frame>, Error> {
-		let mut buf = Cursor::new(&self.buffer[..]);
-
-		match X11Frame::check(&mut buf) {
-			Ok(_) => {
-				let length = buf.position() as usize;
 
-				buf.set_position(0);
-
-				let frame = X11Frame::parse(&mut buf)?;
-
-				self.buffer.advance(length);
+	/// Receives the next frame, starting with those which were read while
+	/// waiting for a reply.
+	pub(crate) async fn read_frame(&mut self) -> Result<Option<X11Frame>, Error> {
+		if let Some((frame, _)) = self.deferred.pop_front() {
+			return Ok(Some(frame));
+		}
 
-				Ok(Some(frame))
-			},
+		self.receive_frame().await
+	}
 
-			Err(Incomplete) => Ok(None),
-			Err(other) => Err(other),
+	/// Receives the next frame from the stream which is not a reply or error
+	/// being waited for by a [`ReplyCookie`].
+	///
+	/// [`ReplyCookie`]: super::ReplyCookie
+	pub(crate) async fn receive_frame(&mut self) -> Result<Option<X11Frame>, Error> {
+		while let Some(frame) = self.next_frame().await? {
+			if let Some(frame) = self.replies.deliver(frame) {
+				return Ok(Some(frame));
+			}
 		}
+
+		Ok(None)
 	}
 
+	/// Receives the next frame from the stream.
+	///
+	/// Each read takes up to the [read-ahead] from the stream, and the stream
+	/// is only read again once every complete frame read has been parsed.
+	///
+	/// If the connection is closed, requests still awaiting replies are told
+	/// that they will not receive them.
+	///
+	/// [read-ahead]: EventReader::set_read_ahead
 	// https://tokio.rs/tokio/tutorial/framing
-	pub(crate) async fn read_frame(&mut self) -> Result<Option<X11Frame>, Error> {
+	pub(crate) async fn next_frame(&mut self) -> Result<Option<X11Frame>, Error> {
 		/// The end of the stream is reached when there are 0 bytes remaining.
 		const END_OF_STREAM: usize = 0;
 
@@ -340,133 +93,248 @@ impl Client {
 				return Ok(Some(frame));
 			}
 
-			if self.stream.read_buf(&mut self.buffer).await? == END_OF_STREAM {
+			self.buffer.reserve(self.read_ahead);
+
+			#[cfg(all(unix, feature = "fds"))]
+			let read = self
+				.stream
+				.read_with_fds(&mut self.buffer, self.read_ahead, &mut self.fds)
+				.await;
+			#[cfg(not(all(unix, feature = "fds")))]
+			let read = self
+				.stream
+				.read_buf(&mut (&mut self.buffer).limit(self.read_ahead))
+				.await;
+
+			let read = match read {
+				Ok(read) => read,
+
+				Err(error) => {
+					self.replies.close();
+					return Err(error.into());
+				},
+			};
+
+			if read == END_OF_STREAM {
+				self.replies.close();
+
 				return if self.buffer.is_empty() {
 					Ok(None)
 				} else {
-					Err("connection reset by peer".into())
+					Err(Error::Io(io::ErrorKind::ConnectionReset.into()))
 				};
 			}
+
+			self.received.push_back((self.buffer.len(), Instant::now()));
 		}
 	}
 
-	// https://tokio.rs/tokio/tutorial/framing
-	pub(crate) async fn write_frame(&mut self, frame: &X11Frame) -> io::Result<()> {
-		match frame {
-			X11Frame::Request {
-				major_opcode,
-				metabyte,
-				length,
-				chunk,
-			} => {
-				self.stream.write_u8(*major_opcode).await?;
-				self.stream.write_u8(*metabyte).await?;
-				self.stream.write_u16(*length).await?;
-				self.stream.write(&*chunk).await?;
-			},
-
-			X11Frame::Reply {
-				metabyte,
-				sequence,
-				length,
-				chunk,
-			} => {
-				self.stream.write_u8(1).await?;
-				self.stream.write_u8(*metabyte).await?;
-				self.stream.write_u16(*sequence).await?;
-				self.stream.write_u32(*length).await?;
-				self.stream.write(&*chunk).await?;
-			},
+	/// Hands the file descriptors sent with `frame` to the request waiting
+	/// for it, if it is a reply to a request which expects them.
+	///
+	/// The number of file descriptors sent with such a reply is given by its
+	/// metabyte. They are received with the reply's bytes, so they are at the
+	/// front of the received file descriptors by the time it is parsed.
+	#[cfg(all(unix, feature = "fds"))]
+	fn claim_fds(&mut self, frame: &X11Frame) {
+		let X11Frame::Reply {
+			sequence, metabyte, ..
+		} = frame
+		else {
+			return;
+		};
+
+		if self.replies.expects_fds(*sequence) {
+			let count = usize::from(*metabyte).min(self.fds.len());
+
+			self.replies
+				.store_fds(*sequence, self.fds.drain(..count).collect());
+		}
+	}
 
-			X11Frame::Event { code, chunk } => {
-				self.stream.write_u8(*code).await?;
-				self.stream.write(&*chunk).await?;
-			},
+	/// Forgets when the first `length` bytes of the buffer were received, once
+	/// they have been parsed.
+	fn consume_received(&mut self, length: usize) {
+		while self.received.front().is_some_and(|(end, _)| *end <= length) {
+			self.received.pop_front();
+		}
 
-			X11Frame::Error {
-				code,
-				sequence,
-				metablock,
-				minor_opcode,
-				major_opcode,
-				chunk,
-			} => {
-				self.stream.write_u8(0).await?;
-				self.stream.write_u8(*code).await?;
-				self.stream.write_u16(*sequence).await?;
-				self.stream.write(&*metablock).await?;
-				self.stream.write_u16(*minor_opcode).await?;
-				self.stream.write_u8(*major_opcode).await?;
-				self.stream.write(&*chunk).await?;
-			},
+		for (end, _) in &mut self.received {
+			*end -= length;
 		}
+	}
+}
 
-		self.stream.flush().await?;
+impl Client {
+	/// Writes held requests before reading frames if requests are being
+	/// batched, as the frames may be responses to them.
+	///
+	/// An `UngrabServer` request left by a dropped [`ServerGrabGuard`] is
+	/// also sent, so that a client which only reads events doesn't keep the
+	/// server grabbed.
+	///
+	/// [`ServerGrabGuard`]: crate::grab::ServerGrabGuard
+	pub(crate) async fn flush_batch(&mut self) -> io::Result<()> {
+		#[cfg(feature = "batch")]
+		let batching = self.writer.batching.is_some();
+		#[cfg(not(feature = "batch"))]
+		let batching = false;
+
+		if batching || self.writer.ungrab_server_pending() {
+			self.writer.flush().await?;
+		}
 
 		Ok(())
 	}
 }
 
-enum Error {
-	Incomplete,
-	Other,
-}
+impl RequestWriter {
+	/// Writes `frame` to the stream and flushes it, along with any queued
+	/// frames.
+	pub(crate) async fn write_frame(&mut self, frame: &X11Frame) -> io::Result<()> {
+		self.queue_frame(frame).await?;
+
+		// While batching, requests are held until the batch is due instead.
+		#[cfg(feature = "batch")]
+		if !self.batch_due() {
+			return Ok(());
+		}
 
-fn peek_u8(buf: &mut Cursor<&[u8]>) -> Result<u8, Error> {
-	if !buf.has_remaining() {
-		return Err(Incomplete);
+		self.flush_frames().await
 	}
 
-	Ok(buf.chunk()[0])
-}
+	/// Writes `frame` to the stream's buffer, flushing it only if the buffer
+	/// has reached the flush threshold.
+	pub(crate) async fn queue_frame(&mut self, frame: &X11Frame) -> io::Result<()> {
+		self.buffer_ungrab_server().await?;
+		self.buffer_frame(frame).await?;
 
-fn get_u8(buf: &mut Cursor<&[u8]>) -> Result<u8, Error> {
-	if !buf.has_remaining() {
-		return Err(Incomplete);
+		if self.stream.lock().await.buffer().len() >= self.flush_threshold {
+			self.flush_frames().await?;
+		}
+
+		Ok(())
 	}
 
-	Ok(buf.get_u8())
-}
+	/// Writes `frame` to the stream's buffer.
+	// https://tokio.rs/tokio/tutorial/framing
+	async fn buffer_frame(&mut self, frame: &X11Frame) -> io::Result<()> {
+		let mut buf = BytesMut::new();
+		frame.write_to(&mut buf);
+
+		{
+			let mut stream = self.stream.lock().await;
+
+			stream.write_all(&buf).await?;
+			#[cfg(feature = "batch")]
+			self.hold();
+		}
+
+		if let X11Frame::Request { .. } = frame {
+			self.sequence = self.sequence.wrapping_add(1);
+		}
+
+		#[cfg(feature = "metrics")]
+		self.record_frame_sent(frame);
+		#[cfg(feature = "profiler")]
+		self.profiler.request_sent(self.sequence, frame);
 
-fn peek_u16(buf: &mut Cursor<&[u8]>) -> Result<u16, Error> {
-	if buf.remaining() < 2 {
-		return Err(Incomplete);
+		Ok(())
 	}
 
-	let chunk = buf.chunk();
-	Ok(u16::from_be_bytes([chunk[0], chunk[1]]))
-}
+	/// Writes an `UngrabServer` request to the stream's buffer if a
+	/// [`ServerGrabGuard`] was dropped without being released.
+	///
+	/// [`ServerGrabGuard`]: crate::grab::ServerGrabGuard
+	pub(crate) async fn buffer_ungrab_server(&mut self) -> io::Result<()> {
+		if self.ungrab_server.swap(false, Ordering::AcqRel) {
+			let frame = self.request_frame(UngrabServer.to_raw(UngrabServer::OPCODE))?;
+
+			self.buffer_frame(&frame).await?;
+		}
 
-fn get_u16(buf: &mut Cursor<&[u8]>) -> Result<u16, Error> {
-	if buf.remaining() < 2 {
-		return Err(Incomplete);
+		Ok(())
 	}
 
-	Ok(buf.get_u16())
-}
+	/// Returns whether an `UngrabServer` request is waiting to be sent
+	/// because a [`ServerGrabGuard`] was dropped without being released.
+	///
+	/// [`ServerGrabGuard`]: crate::grab::ServerGrabGuard
+	pub(crate) fn ungrab_server_pending(&self) -> bool {
+		self.ungrab_server.load(Ordering::Acquire)
+	}
+
+	/// Writes `frame` to the stream with `fds` attached, after any queued
+	/// frames.
+	#[cfg(all(unix, feature = "fds"))]
+	pub(crate) async fn write_frame_with_fds(
+		&mut self, frame: &X11Frame, fds: &[std::os::fd::BorrowedFd<'_>],
+	) -> io::Result<()> {
+		// Queued frames must reach the X server first, as they were sent
+		// first.
+		self.flush_frames().await?;
+
+		let mut buf = BytesMut::new();
+		frame.write_to(&mut buf);
+
+		self.stream
+			.lock()
+			.await
+			.get_mut()
+			.write_with_fds(&buf, fds)
+			.await?;
+
+		if let X11Frame::Request { .. } = frame {
+			self.sequence = self.sequence.wrapping_add(1);
+		}
+
+		#[cfg(feature = "metrics")]
+		self.record_frame_sent(frame);
+		#[cfg(feature = "profiler")]
+		self.profiler.request_sent(self.sequence, frame);
 
-fn peek_u32(buf: &mut Cursor<&[u8]>) -> Result<u32, Error> {
-	if buf.remaining() < 4 {
-		return Err(Incomplete);
+		Ok(())
 	}
 
-	let chunk = buf.chunk();
-	Ok(u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-}
+	/// Returns whether file descriptors can be passed to the X server.
+	///
+	/// See [`Client::can_pass_fds`].
+	///
+	/// [`Client::can_pass_fds`]: crate::Client::can_pass_fds
+	#[cfg(all(unix, feature = "fds"))]
+	pub fn can_pass_fds(&self) -> bool {
+		self.can_pass_fds
+	}
+
+	/// Sends any queued frames to the X server.
+	pub(crate) async fn flush_frames(&mut self) -> io::Result<()> {
+		self.buffer_ungrab_server().await?;
+
+		let mut stream = self.stream.lock().await;
 
-fn get_u32(buf: &mut Cursor<&[u8]>) -> Result<u32, Error> {
-	if buf.remaining() < 4 {
-		return Err(Incomplete);
+		stream.flush().await?;
+		#[cfg(feature = "batch")]
+		self.release();
+
+		Ok(())
 	}
+}
 
-	Ok(buf.get_u32())
+pub(crate) enum Error {
+	/// Not enough bytes have been received to parse a whole frame yet.
+	Incomplete,
+	/// An I/O error occurred while reading from the stream.
+	Io(io::Error),
 }
 
-fn skip(buf: &mut Cursor<&[u8]>, count: usize) -> Result<(), Error> {
-	if buf.remaining() < count {
-		return Err(Incomplete);
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Self::Io(error)
 	}
+}
 
-	buf.advance(count);
-	Ok(())
+impl From<xrs_proto::Incomplete> for Error {
+	fn from(_: xrs_proto::Incomplete) -> Self {
+		Incomplete
+	}
 }
@@ -0,0 +1,612 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The connection setup, and the information about the X server it provides.
+
+use crate::{event_mask::EventMask, stream::Stream, AuthInfo, ConnectError};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::{
+	io,
+	io::{AsyncReadExt, AsyncWriteExt},
+};
+
+/// The byte order byte indicating that the client sends its values in
+/// big-endian byte order.
+const BIG_ENDIAN: u8 = b'B';
+
+/// The major version of the X11 protocol.
+const PROTOCOL_MAJOR_VERSION: u16 = 11;
+/// The minor version of the X11 protocol.
+const PROTOCOL_MINOR_VERSION: u16 = 0;
+
+/// The status of a connection setup response which refused the connection.
+const FAILED: u8 = 0;
+/// The status of a connection setup response which accepted the connection.
+const SUCCESS: u8 = 1;
+/// The status of a connection setup response which requires further
+/// authentication.
+const AUTHENTICATE: u8 = 2;
+
+/// Returns the number of bytes needed to pad `length` to a multiple of 4.
+const fn pad(length: usize) -> usize {
+	(4 - (length % 4)) % 4
+}
+
+/// Sends the connection setup request, authorized with `auth`, and reads the
+/// server's response.
+///
+/// Returns the data of a successful response, following its 8-byte header.
+pub(crate) async fn handshake(
+	stream: &mut Stream, auth: Option<&AuthInfo>,
+) -> Result<Bytes, ConnectError> {
+	let (name, data) = match auth {
+		Some(AuthInfo {
+			protocol_name,
+			protocol_data,
+		}) => (protocol_name.as_bytes(), &protocol_data[..]),
+
+		None => (&[][..], &[][..]),
+	};
+
+	let too_long = |_| io::Error::new(io::ErrorKind::InvalidInput, "authorization is too long");
+
+	let name_length = u16::try_from(name.len()).map_err(too_long)?;
+	let data_length = u16::try_from(data.len()).map_err(too_long)?;
+
+	let mut request =
+		BytesMut::with_capacity(12 + name.len() + pad(name.len()) + data.len() + pad(data.len()));
+
+	request.put_u8(BIG_ENDIAN);
+	request.put_u8(0);
+	request.put_u16(PROTOCOL_MAJOR_VERSION);
+	request.put_u16(PROTOCOL_MINOR_VERSION);
+	request.put_u16(name_length);
+	request.put_u16(data_length);
+	request.put_u16(0);
+
+	request.put_slice(name);
+	request.put_bytes(0, pad(name.len()));
+	request.put_slice(data);
+	request.put_bytes(0, pad(data.len()));
+
+	stream.write_all(&request).await?;
+	stream.flush().await?;
+
+	let mut header = [0; 8];
+	stream.read_exact(&mut header).await?;
+
+	// The length of the rest of the response, in 4-byte units.
+	let length = u16::from_be_bytes([header[6], header[7]]);
+
+	let mut body = vec![0; 4 * (length as usize)];
+	stream.read_exact(&mut body).await?;
+
+	match header[0] {
+		SUCCESS => Ok(body.into()),
+
+		FAILED => {
+			let reason_length = header[1] as usize;
+			let reason = body.get(..reason_length).unwrap_or(&body);
+
+			Err(ConnectError::Failed(
+				String::from_utf8_lossy(reason).into_owned(),
+			))
+		},
+
+		AUTHENTICATE => {
+			// The reason is padded to a multiple of 4 bytes, but its length is
+			// not given.
+			let reason = String::from_utf8_lossy(&body);
+
+			Err(ConnectError::Auth(reason.trim_end_matches('\0').to_owned()))
+		},
+
+		status => Err(ConnectError::Io(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("unrecognized connection setup status: {status}"),
+		))),
+	}
+}
+
+/// The information the X server provides about itself when a connection is
+/// set up.
+///
+/// See [`Client::setup`].
+///
+/// [`Client::setup`]: crate::Client::setup
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Setup {
+	/// The vendor's release number of the X server.
+	pub release_number: u32,
+
+	/// The bits set in every resource ID assigned to the client.
+	pub resource_id_base: u32,
+	/// The bits which may vary between the resource IDs assigned to the
+	/// client.
+	pub resource_id_mask: u32,
+
+	/// The approximate number of pointer motion events kept in the X server's
+	/// motion history buffer.
+	pub motion_buffer_size: u32,
+	/// The maximum length of a request, in 4-byte units.
+	pub maximum_request_length: u16,
+
+	/// The byte order of image data.
+	pub image_byte_order: ImageOrder,
+	/// The bit order of each scanline unit in bitmaps.
+	pub bitmap_bit_order: ImageOrder,
+	/// The size of each unit of a bitmap's scanlines, in bits.
+	pub bitmap_scanline_unit: u8,
+	/// The multiple of bits to which each scanline of a bitmap is padded.
+	pub bitmap_scanline_pad: u8,
+
+	/// The smallest keycode the X server sends.
+	pub min_keycode: u8,
+	/// The largest keycode the X server sends.
+	pub max_keycode: u8,
+
+	/// The name of the X server's vendor.
+	pub vendor: String,
+
+	/// The formats of images in `ZPixmap` format, one for each depth the X
+	/// server supports.
+	pub pixmap_formats: Vec<PixmapFormat>,
+	/// The X server's screens.
+	pub screens: Vec<Screen>,
+}
+
+/// The order of the bytes or bits in image data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageOrder {
+	LeastSignificantFirst,
+	MostSignificantFirst,
+}
+
+/// The format of images of a particular depth in `ZPixmap` format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixmapFormat {
+	pub depth: u8,
+	/// The number of bits each pixel takes up.
+	pub bits_per_pixel: u8,
+	/// The multiple of bits to which each scanline is padded.
+	pub scanline_pad: u8,
+}
+
+/// A screen of the X server, with its own root window.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Screen {
+	/// The screen's root window.
+	pub root: u32,
+	/// The colormap initially associated with the root window.
+	pub default_colormap: u32,
+
+	/// The pixel value for white in the default colormap.
+	pub white_pixel: u32,
+	/// The pixel value for black in the default colormap.
+	pub black_pixel: u32,
+
+	/// The events selected on the root window by every client when the
+	/// connection was set up.
+	pub current_input_masks: EventMask,
+
+	/// The width of the screen in pixels.
+	pub width: u16,
+	/// The height of the screen in pixels.
+	pub height: u16,
+	/// The width of the screen in millimeters.
+	pub width_mm: u16,
+	/// The height of the screen in millimeters.
+	pub height_mm: u16,
+
+	/// The minimum number of colormaps which can be installed at once.
+	pub min_installed_maps: u16,
+	/// The maximum number of colormaps which can be installed at once.
+	pub max_installed_maps: u16,
+
+	/// The visual of the root window.
+	pub root_visual: u32,
+	/// When the screen maintains the contents of windows which are obscured.
+	pub backing_stores: BackingStores,
+	/// Whether the screen supports saving the contents obscured by windows
+	/// with `save_under` set.
+	pub save_unders: bool,
+	/// The depth of the root window.
+	pub root_depth: u8,
+
+	/// The depths for which windows and pixmaps can be created on this
+	/// screen, and the visuals supported at each.
+	pub allowed_depths: Vec<Depth>,
+}
+
+impl Screen {
+	/// Returns the visual with the given ID, if this screen supports it.
+	pub fn visual(&self, id: u32) -> Option<&Visual> {
+		self.visuals().find(|visual| visual.id == id)
+	}
+
+	/// Returns an iterator over every visual supported by this screen.
+	pub fn visuals(&self) -> impl DoubleEndedIterator<Item = &Visual> {
+		self.allowed_depths
+			.iter()
+			.flat_map(|depth| depth.visuals.iter())
+	}
+
+	/// Returns the visual of the root window.
+	pub fn root_visual(&self) -> Option<&Visual> {
+		self.visual(self.root_visual)
+	}
+
+	/// Returns the given depth, if windows and pixmaps of that depth can be
+	/// created on this screen.
+	pub fn depth(&self, depth: u8) -> Option<&Depth> {
+		self.allowed_depths
+			.iter()
+			.find(|allowed| allowed.depth == depth)
+	}
+
+	/// Returns an iterator over the visuals of this screen which meet
+	/// `requirements`.
+	pub fn visuals_matching(
+		&self, requirements: VisualRequirements,
+	) -> impl DoubleEndedIterator<Item = &Visual> {
+		self.visuals()
+			.filter(move |visual| requirements.matches(visual))
+	}
+
+	/// Returns the best visual of this screen which meets `requirements`, if
+	/// any do.
+	///
+	/// The root visual is preferred, as windows using it can share the
+	/// default colormap, followed by the visual with the most bits per color
+	/// value, then the deepest, then the one with the most colormap entries.
+	/// Visuals which are otherwise equal are chosen in the order the X server
+	/// listed them.
+	///
+	/// ```no_run
+	/// # use xrs::{Client, VisualClass, VisualRequirements};
+	/// # fn example(client: &Client) {
+	/// let argb = client.default_screen().best_visual_for(VisualRequirements {
+	///     depth: Some(32),
+	///     class: Some(VisualClass::TrueColor),
+	///     ..VisualRequirements::default()
+	/// });
+	/// # }
+	/// ```
+	pub fn best_visual_for(&self, requirements: VisualRequirements) -> Option<&Visual> {
+		// `max_by_key` returns the last of equal visuals, so they are searched
+		// in reverse to return the first.
+		self.visuals_matching(requirements)
+			.rev()
+			.max_by_key(|visual| {
+				(
+					visual.id == self.root_visual,
+					visual.bits_per_rgb_value,
+					visual.depth,
+					visual.colormap_entries,
+				)
+			})
+	}
+
+	/// Returns a 32-bit `TrueColor` visual, whose spare 8 bits are used as an
+	/// alpha channel by compositing managers, if this screen supports one.
+	pub fn argb_visual(&self) -> Option<&Visual> {
+		self.best_visual_for(VisualRequirements::ARGB)
+	}
+}
+
+/// When a screen maintains the contents of windows which are obscured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BackingStores {
+	Never,
+	WhenMapped,
+	Always,
+}
+
+/// A depth supported by a screen, and the visuals supported at that depth.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Depth {
+	pub depth: u8,
+	pub visuals: Vec<Visual>,
+}
+
+/// A way of mapping pixel values to colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Visual {
+	/// The ID of the visual.
+	pub id: u32,
+	/// The depth at which the visual is supported.
+	pub depth: u8,
+	pub class: VisualClass,
+
+	/// The number of bits of each of the red, green and blue values.
+	pub bits_per_rgb_value: u8,
+	/// The number of entries in colormaps of this visual.
+	pub colormap_entries: u16,
+
+	/// The bits of a pixel value holding its red value, for `TrueColor` and
+	/// `DirectColor` visuals.
+	pub red_mask: u32,
+	/// The bits of a pixel value holding its green value, for `TrueColor`
+	/// and `DirectColor` visuals.
+	pub green_mask: u32,
+	/// The bits of a pixel value holding its blue value, for `TrueColor` and
+	/// `DirectColor` visuals.
+	pub blue_mask: u32,
+}
+
+/// The class of a [`Visual`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VisualClass {
+	StaticGray,
+	GrayScale,
+	StaticColor,
+	PseudoColor,
+	TrueColor,
+	DirectColor,
+}
+
+/// What a [`Visual`] must support to be chosen by [`Screen::best_visual_for`].
+///
+/// Requirements which are `None` accept any visual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VisualRequirements {
+	/// The depth of the visual.
+	pub depth: Option<u8>,
+	/// The class of the visual.
+	pub class: Option<VisualClass>,
+
+	/// The fewest bits each of the red, green and blue values may have.
+	pub min_bits_per_rgb_value: Option<u8>,
+	/// The fewest entries colormaps of the visual may have.
+	pub min_colormap_entries: Option<u16>,
+}
+
+impl VisualRequirements {
+	/// A 32-bit `TrueColor` visual, whose spare 8 bits are used as an alpha
+	/// channel by compositing managers.
+	pub const ARGB: Self = Self {
+		depth: Some(32),
+		class: Some(VisualClass::TrueColor),
+
+		min_bits_per_rgb_value: None,
+		min_colormap_entries: None,
+	};
+
+	/// Whether `visual` meets these requirements.
+	pub fn matches(&self, visual: &Visual) -> bool {
+		self.depth.is_none_or(|depth| visual.depth == depth)
+			&& self.class.is_none_or(|class| visual.class == class)
+			&& self
+				.min_bits_per_rgb_value
+				.is_none_or(|bits| visual.bits_per_rgb_value >= bits)
+			&& self
+				.min_colormap_entries
+				.is_none_or(|entries| visual.colormap_entries >= entries)
+	}
+}
+
+impl Setup {
+	/// Returns the visual with the given ID, if any screen supports it.
+	pub fn visual(&self, id: u32) -> Option<&Visual> {
+		self.screens.iter().find_map(|screen| screen.visual(id))
+	}
+
+	/// Returns the best visual of any screen which meets `requirements`, and
+	/// the screen it is on.
+	///
+	/// Screens are searched in order, so a visual is returned from the first
+	/// screen with any which meet the requirements. See
+	/// [`Screen::best_visual_for`].
+	pub fn best_visual_for(&self, requirements: VisualRequirements) -> Option<(&Screen, &Visual)> {
+		self.screens.iter().find_map(|screen| {
+			screen
+				.best_visual_for(requirements)
+				.map(|visual| (screen, visual))
+		})
+	}
+
+	/// Returns the format of images in `ZPixmap` format of the given depth,
+	/// if the X server supports it.
+	pub fn pixmap_format(&self, depth: u8) -> Option<&PixmapFormat> {
+		self.pixmap_formats
+			.iter()
+			.find(|format| format.depth == depth)
+	}
+
+	/// Parses the data of a successful connection setup response.
+	pub(crate) fn parse(mut data: &[u8]) -> io::Result<Self> {
+		ensure(data, 32)?;
+
+		let release_number = data.get_u32();
+		let resource_id_base = data.get_u32();
+		let resource_id_mask = data.get_u32();
+		let motion_buffer_size = data.get_u32();
+		let vendor_length = data.get_u16() as usize;
+		let maximum_request_length = data.get_u16();
+		let screen_count = data.get_u8();
+		let format_count = data.get_u8();
+		let image_byte_order = image_order(data.get_u8())?;
+		let bitmap_bit_order = image_order(data.get_u8())?;
+		let bitmap_scanline_unit = data.get_u8();
+		let bitmap_scanline_pad = data.get_u8();
+		let min_keycode = data.get_u8();
+		let max_keycode = data.get_u8();
+		data.advance(4);
+
+		ensure(data, vendor_length + pad(vendor_length))?;
+		let vendor = String::from_utf8_lossy(&data[..vendor_length]).into_owned();
+		data.advance(vendor_length + pad(vendor_length));
+
+		ensure(data, 8 * (format_count as usize))?;
+		let pixmap_formats = (0..format_count)
+			.map(|_| {
+				let format = PixmapFormat {
+					depth: data.get_u8(),
+					bits_per_pixel: data.get_u8(),
+					scanline_pad: data.get_u8(),
+				};
+				data.advance(5);
+
+				format
+			})
+			.collect();
+
+		let screens = (0..screen_count)
+			.map(|_| Screen::parse(&mut data))
+			.collect::<io::Result<_>>()?;
+
+		Ok(Self {
+			release_number,
+
+			resource_id_base,
+			resource_id_mask,
+
+			motion_buffer_size,
+			maximum_request_length,
+
+			image_byte_order,
+			bitmap_bit_order,
+			bitmap_scanline_unit,
+			bitmap_scanline_pad,
+
+			min_keycode,
+			max_keycode,
+
+			vendor,
+
+			pixmap_formats,
+			screens,
+		})
+	}
+}
+
+impl Screen {
+	/// Parses a screen from the start of `data`, advancing past it.
+	fn parse(data: &mut &[u8]) -> io::Result<Self> {
+		ensure(data, 40)?;
+
+		let root = data.get_u32();
+		let default_colormap = data.get_u32();
+		let white_pixel = data.get_u32();
+		let black_pixel = data.get_u32();
+		let current_input_masks = EventMask(data.get_u32());
+		let width = data.get_u16();
+		let height = data.get_u16();
+		let width_mm = data.get_u16();
+		let height_mm = data.get_u16();
+		let min_installed_maps = data.get_u16();
+		let max_installed_maps = data.get_u16();
+		let root_visual = data.get_u32();
+		let backing_stores = match data.get_u8() {
+			0 => BackingStores::Never,
+			1 => BackingStores::WhenMapped,
+			2 => BackingStores::Always,
+
+			other => return Err(invalid(format!("unrecognized backing stores: {other}"))),
+		};
+		let save_unders = data.get_u8() != 0;
+		let root_depth = data.get_u8();
+		let depth_count = data.get_u8();
+
+		let allowed_depths = (0..depth_count)
+			.map(|_| Depth::parse(data))
+			.collect::<io::Result<_>>()?;
+
+		Ok(Self {
+			root,
+			default_colormap,
+
+			white_pixel,
+			black_pixel,
+
+			current_input_masks,
+
+			width,
+			height,
+			width_mm,
+			height_mm,
+
+			min_installed_maps,
+			max_installed_maps,
+
+			root_visual,
+			backing_stores,
+			save_unders,
+			root_depth,
+
+			allowed_depths,
+		})
+	}
+}
+
+impl Depth {
+	/// Parses a depth from the start of `data`, advancing past it.
+	fn parse(data: &mut &[u8]) -> io::Result<Self> {
+		ensure(data, 8)?;
+
+		let depth = data.get_u8();
+		data.advance(1);
+		let visual_count = data.get_u16() as usize;
+		data.advance(4);
+
+		ensure(data, 24 * visual_count)?;
+		let visuals = (0..visual_count)
+			.map(|_| {
+				let id = data.get_u32();
+				let class = match data.get_u8() {
+					0 => VisualClass::StaticGray,
+					1 => VisualClass::GrayScale,
+					2 => VisualClass::StaticColor,
+					3 => VisualClass::PseudoColor,
+					4 => VisualClass::TrueColor,
+					5 => VisualClass::DirectColor,
+
+					other => return Err(invalid(format!("unrecognized visual class: {other}"))),
+				};
+
+				let visual = Visual {
+					id,
+					depth,
+					class,
+
+					bits_per_rgb_value: data.get_u8(),
+					colormap_entries: data.get_u16(),
+
+					red_mask: data.get_u32(),
+					green_mask: data.get_u32(),
+					blue_mask: data.get_u32(),
+				};
+				data.advance(4);
+
+				Ok(visual)
+			})
+			.collect::<io::Result<_>>()?;
+
+		Ok(Self { depth, visuals })
+	}
+}
+
+/// Reads an [`ImageOrder`] from the setup response.
+fn image_order(order: u8) -> io::Result<ImageOrder> {
+	match order {
+		0 => Ok(ImageOrder::LeastSignificantFirst),
+		1 => Ok(ImageOrder::MostSignificantFirst),
+
+		other => Err(invalid(format!("unrecognized image order: {other}"))),
+	}
+}
+
+/// Returns an error if `data` is shorter than `length` bytes.
+fn ensure(data: &[u8], length: usize) -> io::Result<()> {
+	match data.len() >= length {
+		true => Ok(()),
+		false => Err(invalid("connection setup response is too short".to_owned())),
+	}
+}
+
+/// Returns an [`InvalidData`](io::ErrorKind::InvalidData) error for a
+/// malformed setup response.
+fn invalid(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,472 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The Composite extension, which redirects windows into offscreen pixmaps.
+//!
+//! A compositing manager redirects every child of the root window with
+//! [`Client::redirect_subwindows`], so that they are drawn into offscreen
+//! pixmaps instead of onto the screen. It then names each window's pixmap
+//! with [`Client::name_window_pixmap`] and draws them itself, usually onto
+//! the [composite overlay window] with the RENDER extension, watching for
+//! changes with the Damage extension:
+//!
+//! ```no_run
+//! # use xrs::{composite::RedirectMode, Client};
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let root = client.root_window();
+//!
+//! client.composite_version().await?;
+//! client.redirect_subwindows(root, RedirectMode::Manual).await?;
+//!
+//! let overlay = client.get_overlay_window(root).await?;
+//! // Draw each window's pixmap onto `overlay`...
+//! # let _ = overlay;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [composite overlay window]: Client::get_overlay_window
+
+use crate::{
+	client::RoundTrip,
+	extension,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	window::Window,
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the Composite extension.
+const COMPOSITE: &str = "Composite";
+
+/// The minor opcode of the `CompositeQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `CompositeRedirectWindow` request.
+const REDIRECT_WINDOW: u8 = 1;
+/// The minor opcode of the `CompositeRedirectSubwindows` request.
+const REDIRECT_SUBWINDOWS: u8 = 2;
+/// The minor opcode of the `CompositeUnredirectWindow` request.
+const UNREDIRECT_WINDOW: u8 = 3;
+/// The minor opcode of the `CompositeUnredirectSubwindows` request.
+const UNREDIRECT_SUBWINDOWS: u8 = 4;
+/// The minor opcode of the `CompositeNameWindowPixmap` request.
+const NAME_WINDOW_PIXMAP: u8 = 6;
+/// The minor opcode of the `CompositeGetOverlayWindow` request.
+const GET_OVERLAY_WINDOW: u8 = 7;
+/// The minor opcode of the `CompositeReleaseOverlayWindow` request.
+const RELEASE_OVERLAY_WINDOW: u8 = 8;
+
+/// The version of Composite requested by this client.
+const VERSION: (u32, u32) = (0, 4);
+
+/// Who draws the contents of redirected windows onto the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RedirectMode {
+	/// The X server draws redirected windows onto their parents as usual, so
+	/// that only their contents are kept offscreen.
+	Automatic,
+	/// The client which redirected the windows draws them itself, as a
+	/// compositing manager does.
+	///
+	/// Only one client can redirect a window manually at a time.
+	Manual,
+}
+
+/// Defines Composite requests which (un)redirect a window or its children.
+macro_rules! redirect_requests {
+	($($(#[$attr:meta])* $name:ident = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name {
+				pub window: Window,
+				pub mode: RedirectMode,
+			}
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = concat!("Composite", stringify!($name));
+				const EXTENSION: Option<&'static str> = Some(COMPOSITE);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(3);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.window.id());
+					body.put_u8(self.mode as u8);
+					// 3 unused bytes.
+					body.put_bytes(0, 3);
+				}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16, Window, u8, [u8; 3]]);
+		)*
+	};
+}
+
+/// The Composite extension's `CompositeQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "CompositeQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(COMPOSITE);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of Composite supported by both the X server and the client.
+///
+/// This is the reply to a `CompositeQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("CompositeQueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[ma0, ma1, ma2, ma3, mi0, mi1, mi2, mi3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([ma0, ma1, ma2, ma3]),
+			minor_version: u32::from_be_bytes([mi0, mi1, mi2, mi3]),
+		})
+	}
+}
+
+redirect_requests! {
+	/// The Composite extension's `CompositeRedirectWindow` request.
+	///
+	/// See [`Client::redirect_window`].
+	RedirectWindow = REDIRECT_WINDOW;
+	/// The Composite extension's `CompositeRedirectSubwindows` request.
+	///
+	/// See [`Client::redirect_subwindows`].
+	RedirectSubwindows = REDIRECT_SUBWINDOWS;
+	/// The Composite extension's `CompositeUnredirectWindow` request.
+	///
+	/// See [`Client::unredirect_window`].
+	UnredirectWindow = UNREDIRECT_WINDOW;
+	/// The Composite extension's `CompositeUnredirectSubwindows` request.
+	///
+	/// See [`Client::unredirect_subwindows`].
+	UnredirectSubwindows = UNREDIRECT_SUBWINDOWS;
+}
+
+/// The Composite extension's `CompositeNameWindowPixmap` request.
+///
+/// See [`Client::name_window_pixmap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NameWindowPixmap {
+	pub window: Window,
+	/// The ID to name the pixmap with.
+	pub pixmap: u32,
+}
+
+impl Request for NameWindowPixmap {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CompositeNameWindowPixmap";
+	const EXTENSION: Option<&'static str> = Some(COMPOSITE);
+	const OPCODE: u8 = NAME_WINDOW_PIXMAP;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u32(self.pixmap);
+	}
+}
+
+assert_wire_size!(NameWindowPixmap: [u8, u8, u16, Window, u32]);
+
+/// The Composite extension's `CompositeGetOverlayWindow` request.
+///
+/// See [`Client::get_overlay_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetOverlayWindow(pub Window);
+
+impl Request for GetOverlayWindow {
+	type Reply = OverlayWindow;
+
+	const NAME: &'static str = "CompositeGetOverlayWindow";
+	const EXTENSION: Option<&'static str> = Some(COMPOSITE);
+	const OPCODE: u8 = GET_OVERLAY_WINDOW;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(GetOverlayWindow: [u8, u8, u16, Window]);
+
+/// The composite overlay window of a screen.
+///
+/// This is the reply to a `CompositeGetOverlayWindow` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OverlayWindow(pub Window);
+
+assert_wire_size!("CompositeGetOverlayWindow reply": [u8, u8, u16, u32, Window, [u8; 20]] == 32);
+
+impl Reply for OverlayWindow {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[w0, w1, w2, w3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self(Window(u32::from_be_bytes([w0, w1, w2, w3]))))
+	}
+}
+
+/// The Composite extension's `CompositeReleaseOverlayWindow` request.
+///
+/// See [`Client::release_overlay_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReleaseOverlayWindow(pub Window);
+
+impl Request for ReleaseOverlayWindow {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CompositeReleaseOverlayWindow";
+	const EXTENSION: Option<&'static str> = Some(COMPOSITE);
+	const OPCODE: u8 = RELEASE_OVERLAY_WINDOW;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(ReleaseOverlayWindow: [u8, u8, u16, Window]);
+
+/// Queries the version of Composite supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::composite_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn composite_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	let request = QueryVersion {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+
+	extension::negotiate_version(connection, COMPOSITE, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await
+}
+
+/// Names the offscreen pixmap holding the contents of the redirected
+/// `window`, returning the pixmap's ID.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, or a new range of resource IDs has to be requested, its reply is
+/// [awaited directly].
+///
+/// See [`Client::name_window_pixmap`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn name_window_pixmap(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<u32, XidError> {
+	let pixmap = xid::generate_id(connection).await?;
+
+	request::send(connection, &NameWindowPixmap { window, pixmap }).await?;
+
+	Ok(pixmap)
+}
+
+/// Gets the composite overlay window of `window`'s screen.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_overlay_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_overlay_window(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Window, ReplyError> {
+	let OverlayWindow(overlay) = request::reply_to(connection, &GetOverlayWindow(window)).await?;
+
+	Ok(overlay)
+}
+
+/// Redirects `window` and its descendants into an offscreen pixmap.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, its reply is [awaited directly].
+///
+/// See [`Client::redirect_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn redirect_window(
+	connection: &mut impl RoundTrip, window: Window, mode: RedirectMode,
+) -> Result<(), ReplyError> {
+	request::send(connection, &RedirectWindow { window, mode }).await
+}
+
+/// Redirects each of `window`'s children into their own offscreen
+/// pixmaps.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, its reply is [awaited directly].
+///
+/// See [`Client::redirect_subwindows`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn redirect_subwindows(
+	connection: &mut impl RoundTrip, window: Window, mode: RedirectMode,
+) -> Result<(), ReplyError> {
+	request::send(connection, &RedirectSubwindows { window, mode }).await
+}
+
+/// Stops redirecting `window`.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, its reply is [awaited directly].
+///
+/// See [`Client::unredirect_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn unredirect_window(
+	connection: &mut impl RoundTrip, window: Window, mode: RedirectMode,
+) -> Result<(), ReplyError> {
+	request::send(connection, &UnredirectWindow { window, mode }).await
+}
+
+/// Stops redirecting `window`'s children.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, its reply is [awaited directly].
+///
+/// See [`Client::unredirect_subwindows`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn unredirect_subwindows(
+	connection: &mut impl RoundTrip, window: Window, mode: RedirectMode,
+) -> Result<(), ReplyError> {
+	request::send(connection, &UnredirectSubwindows { window, mode }).await
+}
+
+/// Releases the composite overlay window of `window`'s screen.
+///
+/// With a [`RequestWriter`], if the Composite extension hasn't been queried
+/// yet, its reply is [awaited directly].
+///
+/// See [`Client::release_overlay_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn release_overlay_window(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<(), ReplyError> {
+	request::send(connection, &ReleaseOverlayWindow(window)).await
+}
+
+impl Client {
+	/// Queries the version of Composite supported by the X server, as its
+	/// major and minor version.
+	///
+	/// The version is negotiated once, so only the first query makes a round
+	/// trip to the X server.
+	pub async fn composite_version(&mut self) -> Result<(u32, u32), ReplyError> {
+		composite_version(self).await
+	}
+
+	/// Redirects `window` and its descendants into an offscreen pixmap.
+	pub async fn redirect_window(
+		&mut self, window: Window, mode: RedirectMode,
+	) -> Result<(), ReplyError> {
+		redirect_window(self, window, mode).await
+	}
+
+	/// Redirects each of `window`'s children, current and future, into their
+	/// own offscreen pixmaps.
+	///
+	/// Compositing managers redirect the root window's children with
+	/// [`RedirectMode::Manual`].
+	pub async fn redirect_subwindows(
+		&mut self, window: Window, mode: RedirectMode,
+	) -> Result<(), ReplyError> {
+		redirect_subwindows(self, window, mode).await
+	}
+
+	/// Stops redirecting `window`, which must have been redirected by this
+	/// client with the same `mode`.
+	pub async fn unredirect_window(
+		&mut self, window: Window, mode: RedirectMode,
+	) -> Result<(), ReplyError> {
+		unredirect_window(self, window, mode).await
+	}
+
+	/// Stops redirecting `window`'s children, which must have been redirected
+	/// by this client with the same `mode`.
+	pub async fn unredirect_subwindows(
+		&mut self, window: Window, mode: RedirectMode,
+	) -> Result<(), ReplyError> {
+		unredirect_subwindows(self, window, mode).await
+	}
+
+	/// Names the offscreen pixmap holding the contents of the redirected
+	/// `window`, returning the pixmap's ID.
+	///
+	/// A window gets a new pixmap whenever it is mapped or resized, so it must
+	/// be named again then. The pixmap stays valid until it is freed, even
+	/// once the window has a new one.
+	pub async fn name_window_pixmap(&mut self, window: Window) -> Result<u32, XidError> {
+		name_window_pixmap(self, window).await
+	}
+
+	/// Gets the composite overlay window of `window`'s screen, which is
+	/// above every other window and below the screen saver, for compositing
+	/// managers to draw onto.
+	///
+	/// The overlay window is mapped until every client which got it has
+	/// [released] it.
+	///
+	/// [released]: Client::release_overlay_window
+	pub async fn get_overlay_window(&mut self, window: Window) -> Result<Window, ReplyError> {
+		get_overlay_window(self, window).await
+	}
+
+	/// Releases the composite overlay window of `window`'s screen.
+	pub async fn release_overlay_window(&mut self, window: Window) -> Result<(), ReplyError> {
+		release_overlay_window(self, window).await
+	}
+}
@@ -0,0 +1,331 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Translating coordinates between windows.
+//!
+//! [`Client::translate_coordinates`] asks the X server to translate a point
+//! from one window's coordinate space to another's, which costs a round trip.
+//! A [`CoordinateCache`] remembers windows' parents, positions and border
+//! widths so that points can be translated locally instead, which matters in
+//! tight loops such as pointer tracking.
+//!
+//! The cache is kept up to date with the `ConfigureNotify`, `ReparentNotify`
+//! and `DestroyNotify` events it is given, so `StructureNotify` or
+//! `SubstructureNotify` must be selected on the cached windows or their
+//! parents.
+
+use crate::{
+	client::RoundTrip,
+	geometry::Point,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, Reply, Request},
+	window::{GetGeometry, QueryTree, Window},
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+use std::collections::HashMap;
+
+/// The major opcode of the `TranslateCoordinates` request.
+const TRANSLATE_COORDINATES: u8 = 40;
+
+/// The event code of `DestroyNotify` events.
+const DESTROY_NOTIFY: u8 = 17;
+/// The event code of `ReparentNotify` events.
+const REPARENT_NOTIFY: u8 = 21;
+/// The event code of `ConfigureNotify` events.
+const CONFIGURE_NOTIFY: u8 = 22;
+
+/// A window's place in the window tree, as remembered by a
+/// [`CoordinateCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowPlacement {
+	/// The window's parent, or `None` for root windows.
+	pub parent: Option<Window>,
+	/// The position of the outside corner of the window's border, relative
+	/// to its parent's origin.
+	pub position: Point,
+	/// The width of the window's border.
+	pub border_width: u16,
+}
+
+impl WindowPlacement {
+	/// The offset of the window's origin, inside its border, from its
+	/// parent's origin.
+	fn offset(&self) -> (i32, i32) {
+		let border_width = i32::from(self.border_width);
+
+		(
+			i32::from(self.position.x) + border_width,
+			i32::from(self.position.y) + border_width,
+		)
+	}
+}
+
+/// Remembers where windows are within their ancestors, to translate
+/// coordinates between them without asking the X server.
+#[derive(Debug, Clone, Default)]
+pub struct CoordinateCache {
+	windows: HashMap<Window, WindowPlacement>,
+}
+
+impl CoordinateCache {
+	/// Creates a new, empty `CoordinateCache`.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Remembers `window`'s placement.
+	pub fn insert(&mut self, window: Window, placement: WindowPlacement) {
+		self.windows.insert(window, placement);
+	}
+
+	/// Returns `window`'s placement, if it is known.
+	pub fn get(&self, window: Window) -> Option<&WindowPlacement> {
+		self.windows.get(&window)
+	}
+
+	/// Forgets `window`'s placement.
+	pub fn forget(&mut self, window: Window) {
+		self.windows.remove(&window);
+	}
+
+	/// Forgets every window's placement.
+	pub fn clear(&mut self) {
+		self.windows.clear();
+	}
+
+	/// Updates the cache from `frame` if it is a `ConfigureNotify`,
+	/// `ReparentNotify` or `DestroyNotify` event for a known window.
+	///
+	/// Returns whether the cache was changed.
+	pub fn observe(&mut self, frame: &X11Frame) -> bool {
+		let X11Frame::Event { code, chunk } = frame else {
+			return false;
+		};
+
+		let u32_at =
+			|i: usize| u32::from_be_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+		let i16_at = |i: usize| i16::from_be_bytes([chunk[i], chunk[i + 1]]);
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let window = Window(u32_at(7));
+
+		match *code {
+			DESTROY_NOTIFY => self.windows.remove(&window).is_some(),
+
+			REPARENT_NOTIFY => match self.windows.get_mut(&window) {
+				Some(placement) => {
+					placement.parent = Some(Window(u32_at(11)));
+					placement.position = Point::new(i16_at(15), i16_at(17));
+
+					true
+				},
+
+				None => false,
+			},
+
+			CONFIGURE_NOTIFY => match self.windows.get_mut(&window) {
+				Some(placement) => {
+					placement.position = Point::new(i16_at(15), i16_at(17));
+					placement.border_width = u16::from_be_bytes([chunk[23], chunk[24]]);
+
+					true
+				},
+
+				None => false,
+			},
+
+			// Synthetic events, which have the `SendEvent` bit set in their
+			// code, are ignored: window managers send `ConfigureNotify` events
+			// with positions relative to the root window rather than the
+			// parent.
+			_ => false,
+		}
+	}
+
+	/// Returns the position of `window`'s origin relative to its root
+	/// window's origin, along with the root window.
+	///
+	/// Returns `None` if the placement of `window` or any of its ancestors is
+	/// not known.
+	fn origin(&self, mut window: Window) -> Option<(Window, (i32, i32))> {
+		let (mut x, mut y) = (0, 0);
+
+		// Guards against cycles, which can appear if reparenting events are
+		// missed.
+		for _ in 0..=self.windows.len() {
+			let placement = self.windows.get(&window)?;
+
+			match placement.parent {
+				Some(parent) => {
+					let (offset_x, offset_y) = placement.offset();
+					x += offset_x;
+					y += offset_y;
+
+					window = parent;
+				},
+
+				None => return Some((window, (x, y))),
+			}
+		}
+
+		None
+	}
+
+	/// Translates `point` from `source`'s coordinate space to
+	/// `destination`'s.
+	///
+	/// Returns `None` if the placement of either window or any of their
+	/// ancestors is not known, if they are on different screens, or if the
+	/// translated point does not fit in 16 bits.
+	pub fn translate(&self, source: Window, destination: Window, point: Point) -> Option<Point> {
+		let (source_root, (source_x, source_y)) = self.origin(source)?;
+		let (destination_root, (destination_x, destination_y)) = self.origin(destination)?;
+
+		if source_root != destination_root {
+			return None;
+		}
+
+		Point::checked(
+			i32::from(point.x) + source_x - destination_x,
+			i32::from(point.y) + source_y - destination_y,
+		)
+	}
+
+	/// Asks the X server for the placement of `window` and each of its
+	/// ancestors whose placement isn't already known.
+	pub async fn learn(&mut self, client: &mut Client, window: Window) -> Result<(), ReplyError> {
+		let mut window = window;
+
+		while !self.windows.contains_key(&window) {
+			let placement = query_placement(client, window).await?;
+			self.windows.insert(window, placement);
+
+			match placement.parent {
+				Some(parent) => window = parent,
+				None => break,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The `TranslateCoordinates` request.
+///
+/// See [`Client::translate_coordinates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TranslateCoordinates {
+	pub source: Window,
+	pub destination: Window,
+	/// The point in `source`'s coordinate space.
+	pub point: Point,
+}
+
+impl Request for TranslateCoordinates {
+	type Reply = TranslatedCoordinates;
+
+	const NAME: &'static str = "TranslateCoordinates";
+	const OPCODE: u8 = TRANSLATE_COORDINATES;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.source.0);
+		body.put_u32(self.destination.0);
+		self.point.write_to(body);
+	}
+}
+
+assert_wire_size!(TranslateCoordinates: [u8, [u8; 1], u16, Window, Window, Point]);
+
+/// A point translated to another window's coordinate space.
+///
+/// This is the reply to a `TranslateCoordinates` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TranslatedCoordinates {
+	/// The translated point, or `None` if the windows are on different
+	/// screens.
+	pub point: Option<Point>,
+	/// The child of the destination window which contains the point, if any.
+	pub child: Option<Window>,
+}
+
+assert_wire_size!("TranslateCoordinates reply": [u8, bool, u16, u32, Window, Point, [u8; 16]] == 32);
+
+impl Reply for TranslatedCoordinates {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte: same_screen,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+		let &[c0, c1, c2, c3, x0, x1, y0, y1] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			point: (*same_screen != 0)
+				.then(|| Point::new(i16::from_be_bytes([x0, x1]), i16::from_be_bytes([y0, y1]))),
+			child: match u32::from_be_bytes([c0, c1, c2, c3]) {
+				0 => None,
+				child => Some(Window(child)),
+			},
+		})
+	}
+}
+
+/// Queries `window`'s parent, position and border width.
+async fn query_placement(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<WindowPlacement, ReplyError> {
+	let tree = request::reply_to(connection, &QueryTree(window)).await?;
+	let geometry = request::reply_to(connection, &GetGeometry { drawable: window.0 }).await?;
+
+	Ok(WindowPlacement {
+		parent: tree.parent,
+		position: geometry.area.position(),
+		border_width: geometry.border_width,
+	})
+}
+
+/// Asks the X server to translate `point` from `source`'s coordinate
+/// space to `destination`'s.
+///
+/// With a [`RequestWriter`], the reply is [awaited directly].
+///
+/// See [`Client::translate_coordinates`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn translate_coordinates(
+	connection: &mut impl RoundTrip, source: Window, destination: Window, point: Point,
+) -> Result<Option<Point>, ReplyError> {
+	let request = TranslateCoordinates {
+		source,
+		destination,
+		point,
+	};
+
+	Ok(request::reply_to(connection, &request).await?.point)
+}
+
+impl Client {
+	/// Asks the X server to translate `point` from `source`'s coordinate
+	/// space to `destination`'s.
+	///
+	/// Returns `None` if the windows are on different screens.
+	///
+	/// This costs a round trip; see [`CoordinateCache`] for translating
+	/// coordinates locally.
+	pub async fn translate_coordinates(
+		&mut self, source: Window, destination: Window, point: Point,
+	) -> Result<Option<Point>, ReplyError> {
+		translate_coordinates(self, source, destination, point).await
+	}
+}
@@ -0,0 +1,924 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Creating cursors from the cursor font, Xcursor themes and ARGB images.
+//!
+//! Every X server has the cursor font, whose glyphs are two-color cursors
+//! named like `left_ptr` or `xterm`. Desktops instead use Xcursor themes:
+//! directories of files holding full-color, possibly animated cursors at
+//! several sizes, which are uploaded with RENDER.
+//!
+//! [`Client::load_cursor`] loads a cursor from the user's theme, as chosen by
+//! the `XCURSOR_THEME` and `XCURSOR_SIZE` environment variables, falling back
+//! to the cursor font if the theme doesn't have it or the X server can't show
+//! it. [`Window::set_cursor`] then shows it over a window:
+//!
+//! ```no_run
+//! # use xrs::{cursor::CursorTheme, window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), xrs::cursor::CursorError> {
+//! let theme = CursorTheme::from_env(client.setup());
+//! let cursor = client.load_cursor(&theme, "pointer").await?;
+//!
+//! window.set_cursor(client.as_mut(), Some(cursor)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Cursors can also be created from a cursor font glyph directly, with
+//! [`Client::create_font_cursor`], or from [`CursorImage`]s, with
+//! [`Client::create_argb_cursor`].
+
+use crate::{
+	client::RoundTrip,
+	draw::Drawable,
+	extension,
+	gc::{self, GcValues},
+	geometry::{Point, Size},
+	image::{Image, ImageFormat},
+	raw::assert_wire_size,
+	render::{self, CreateAnimCursor, CreateCursor, PictureValues, StandardFormat},
+	request::{self, NoReply, Request},
+	window::{Window, WindowAttributes},
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{
+	collections::HashSet,
+	env,
+	error::Error,
+	fmt::{self, Formatter},
+	fs,
+	path::PathBuf,
+};
+use tokio::io;
+
+/// The major opcode of the `OpenFont` request.
+const OPEN_FONT: u8 = 45;
+/// The major opcode of the `CloseFont` request.
+const CLOSE_FONT: u8 = 46;
+/// The major opcode of the `CreatePixmap` request.
+const CREATE_PIXMAP: u8 = 53;
+/// The major opcode of the `FreePixmap` request.
+const FREE_PIXMAP: u8 = 54;
+/// The major opcode of the `CreateGlyphCursor` request.
+const CREATE_GLYPH_CURSOR: u8 = 94;
+/// The major opcode of the `FreeCursor` request.
+const FREE_CURSOR: u8 = 95;
+
+/// The version of RENDER which added cursors made from pictures.
+const CURSOR_VERSION: (u32, u32) = (0, 5);
+/// The version of RENDER which added animated cursors.
+const ANIM_CURSOR_VERSION: (u32, u32) = (0, 8);
+
+/// The name of the font holding the standard cursors.
+const CURSOR_FONT: &str = "cursor";
+
+/// The names of the cursor font's cursors.
+///
+/// Each cursor's glyph is at twice its index, and its mask is the glyph
+/// after it.
+const FONT_CURSORS: [&str; 77] = [
+	"X_cursor",
+	"arrow",
+	"based_arrow_down",
+	"based_arrow_up",
+	"boat",
+	"bogosity",
+	"bottom_left_corner",
+	"bottom_right_corner",
+	"bottom_side",
+	"bottom_tee",
+	"box_spiral",
+	"center_ptr",
+	"circle",
+	"clock",
+	"coffee_mug",
+	"cross",
+	"cross_reverse",
+	"crosshair",
+	"diamond_cross",
+	"dot",
+	"dotbox",
+	"double_arrow",
+	"draft_large",
+	"draft_small",
+	"draped_box",
+	"exchange",
+	"fleur",
+	"gobbler",
+	"gumby",
+	"hand1",
+	"hand2",
+	"heart",
+	"icon",
+	"iron_cross",
+	"left_ptr",
+	"left_side",
+	"left_tee",
+	"leftbutton",
+	"ll_angle",
+	"lr_angle",
+	"man",
+	"middlebutton",
+	"mouse",
+	"pencil",
+	"pirate",
+	"plus",
+	"question_arrow",
+	"right_ptr",
+	"right_side",
+	"right_tee",
+	"rightbutton",
+	"rtl_logo",
+	"sailboat",
+	"sb_down_arrow",
+	"sb_h_double_arrow",
+	"sb_left_arrow",
+	"sb_right_arrow",
+	"sb_up_arrow",
+	"sb_v_double_arrow",
+	"shuttle",
+	"sizing",
+	"spider",
+	"spraycan",
+	"star",
+	"target",
+	"tcross",
+	"top_left_arrow",
+	"top_left_corner",
+	"top_right_corner",
+	"top_side",
+	"top_tee",
+	"trek",
+	"ul_angle",
+	"umbrella",
+	"ur_angle",
+	"watch",
+	"xterm",
+];
+
+/// The cursor font's cursors for the CSS cursor names used by themes.
+const FONT_ALIASES: [(&str, &str); 23] = [
+	("default", "left_ptr"),
+	("pointer", "hand2"),
+	("text", "xterm"),
+	("wait", "watch"),
+	("progress", "watch"),
+	("help", "question_arrow"),
+	("move", "fleur"),
+	("all-scroll", "fleur"),
+	("grabbing", "fleur"),
+	("not-allowed", "X_cursor"),
+	("cell", "plus"),
+	("n-resize", "top_side"),
+	("s-resize", "bottom_side"),
+	("e-resize", "right_side"),
+	("w-resize", "left_side"),
+	("ne-resize", "top_right_corner"),
+	("nw-resize", "top_left_corner"),
+	("se-resize", "bottom_right_corner"),
+	("sw-resize", "bottom_left_corner"),
+	("ew-resize", "sb_h_double_arrow"),
+	("col-resize", "sb_h_double_arrow"),
+	("ns-resize", "sb_v_double_arrow"),
+	("row-resize", "sb_v_double_arrow"),
+];
+
+/// The directories searched for cursor themes if `XCURSOR_PATH` isn't set.
+/// A leading `~` is the home directory.
+const DEFAULT_PATH: [&str; 4] = [
+	"~/.local/share/icons",
+	"~/.icons",
+	"/usr/share/icons",
+	"/usr/share/pixmaps",
+];
+
+/// The theme used if `XCURSOR_THEME` isn't set, and searched after any
+/// other theme.
+const DEFAULT_THEME: &str = "default";
+
+/// How deeply inherited themes are followed.
+const MAX_INHERITANCE: usize = 8;
+
+/// The magic bytes at the start of an Xcursor file.
+const XCURSOR_MAGIC: &[u8; 4] = b"Xcur";
+/// The type of Xcursor chunks holding images.
+const XCURSOR_IMAGE: u32 = 0xfffd_0002;
+/// The size of an Xcursor image chunk's header.
+const XCURSOR_IMAGE_HEADER: usize = 36;
+/// The largest width or height of an Xcursor image.
+const XCURSOR_MAX_DIMENSION: u32 = 0x7fff;
+
+/// A handle to a cursor.
+///
+/// Like [`Window`], a `Cursor` is only an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cursor(pub u32);
+
+impl Cursor {
+	/// The cursor's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+
+	/// Frees the cursor. Windows showing it keep doing so until their cursor
+	/// is changed.
+	pub async fn free(self, writer: &mut RequestWriter) -> io::Result<()> {
+		request::send_core(writer, &FreeCursor(self)).await
+	}
+}
+
+/// The `OpenFont` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpenFont<'a> {
+	/// The ID of the new font.
+	pub font: u32,
+	pub name: &'a str,
+}
+
+impl Request for OpenFont<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "OpenFont";
+	const OPCODE: u8 = OPEN_FONT;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.font);
+		body.put_u16(self.name.len() as u16);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_slice(self.name.as_bytes());
+	}
+}
+
+/// The `CloseFont` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CloseFont(pub u32);
+
+impl Request for CloseFont {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CloseFont";
+	const OPCODE: u8 = CLOSE_FONT;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(CloseFont: [u8, [u8; 1], u16, u32]);
+
+/// The `CreatePixmap` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreatePixmap {
+	pub depth: u8,
+	/// The ID of the new pixmap.
+	pub pixmap: u32,
+	/// A drawable on the screen the pixmap is for.
+	pub drawable: Drawable,
+	pub size: Size,
+}
+
+impl Request for CreatePixmap {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CreatePixmap";
+	const OPCODE: u8 = CREATE_PIXMAP;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn metabyte(&self) -> u8 {
+		self.depth
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.pixmap);
+		body.put_u32(self.drawable.id());
+		body.put_u16(self.size.width);
+		body.put_u16(self.size.height);
+	}
+}
+
+assert_wire_size!(CreatePixmap: [u8, [u8; 1], u16, u32, u32, Size]);
+
+/// The `FreePixmap` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreePixmap(pub u32);
+
+impl Request for FreePixmap {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "FreePixmap";
+	const OPCODE: u8 = FREE_PIXMAP;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(FreePixmap: [u8, [u8; 1], u16, u32]);
+
+/// The `CreateGlyphCursor` request, which creates a two-color cursor from
+/// font glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateGlyphCursor {
+	/// The ID of the new cursor.
+	pub cursor: u32,
+	/// The font and glyph of the cursor's image.
+	pub source: (u32, u16),
+	/// The font and glyph of the cursor's mask.
+	pub mask: (u32, u16),
+	/// The foreground red, green and blue.
+	pub foreground: [u16; 3],
+	/// The background red, green and blue.
+	pub background: [u16; 3],
+}
+
+impl Request for CreateGlyphCursor {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CreateGlyphCursor";
+	const OPCODE: u8 = CREATE_GLYPH_CURSOR;
+	const LENGTH: Option<u16> = Some(8);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.cursor);
+		body.put_u32(self.source.0);
+		body.put_u32(self.mask.0);
+		body.put_u16(self.source.1);
+		body.put_u16(self.mask.1);
+
+		for channel in self.foreground.into_iter().chain(self.background) {
+			body.put_u16(channel);
+		}
+	}
+}
+
+assert_wire_size!(
+	CreateGlyphCursor: [u8, [u8; 1], u16, u32, u32, u32, u16, u16, [u16; 3], [u16; 3]]
+);
+
+/// The `FreeCursor` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeCursor(pub Cursor);
+
+impl Request for FreeCursor {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "FreeCursor";
+	const OPCODE: u8 = FREE_CURSOR;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(FreeCursor: [u8, [u8; 1], u16, u32]);
+
+impl Window {
+	/// Shows `cursor` while the pointer is over the window, or its parent's
+	/// cursor if it is `None`.
+	pub async fn set_cursor(
+		self, writer: &mut RequestWriter, cursor: Option<Cursor>,
+	) -> io::Result<()> {
+		let attributes = WindowAttributes {
+			cursor: Some(cursor.map_or(0, Cursor::id)),
+			..WindowAttributes::default()
+		};
+
+		self.set_attributes(writer, &attributes).await
+	}
+}
+
+/// A frame of a full-color cursor.
+///
+/// Its [`Debug`] output only shows the number of pixels.
+///
+/// [`Debug`]: fmt::Debug
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CursorImage {
+	pub width: u16,
+	pub height: u16,
+	/// The point of the image which is at the pointer's position.
+	pub hotspot: Point,
+	/// How long the frame is shown for in an animated cursor, in
+	/// milliseconds.
+	pub delay: u32,
+
+	/// The image's ARGB pixels, with each row after the last.
+	///
+	/// Colors are premultiplied by their alpha.
+	pub pixels: Vec<u32>,
+}
+
+impl fmt::Debug for CursorImage {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CursorImage")
+			.field("width", &self.width)
+			.field("height", &self.height)
+			.field("hotspot", &self.hotspot)
+			.field("delay", &self.delay)
+			.field("pixels", &format_args!("[…; {} pixels]", self.pixels.len()))
+			.finish()
+	}
+}
+
+impl CursorImage {
+	/// Reads the frames of the cursor in an Xcursor file whose nominal size is
+	/// closest to `size`.
+	///
+	/// Returns `None` if `data` isn't an Xcursor file or has no images.
+	pub fn from_xcursor(data: &[u8], size: u32) -> Option<Vec<Self>> {
+		let u32_at = |offset: usize| -> Option<u32> {
+			let bytes = data.get(offset..offset.checked_add(4)?)?;
+
+			Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+		};
+
+		if data.get(..4)? != XCURSOR_MAGIC {
+			return None;
+		}
+
+		let header_size = u32_at(4)? as usize;
+		let toc_length = u32_at(12)? as usize;
+
+		// Each table of contents entry is its chunk's type, subtype and
+		// position.
+		let images: Vec<(u32, usize)> = (0..toc_length)
+			.map(|index| header_size.checked_add(index.checked_mul(12)?))
+			.map(|entry| {
+				let entry = entry?;
+
+				Some((
+					u32_at(entry)?,
+					u32_at(entry + 4)?,
+					u32_at(entry + 8)? as usize,
+				))
+			})
+			.collect::<Option<Vec<_>>>()?
+			.into_iter()
+			.filter(|&(r#type, ..)| r#type == XCURSOR_IMAGE)
+			.map(|(_, nominal_size, position)| (nominal_size, position))
+			.collect();
+
+		let nominal_size = images
+			.iter()
+			.map(|&(nominal_size, _)| nominal_size)
+			.min_by_key(|nominal_size| nominal_size.abs_diff(size))?;
+
+		images
+			.iter()
+			.filter(|&&(image_size, _)| image_size == nominal_size)
+			.map(|&(_, position)| {
+				let field = |index: usize| u32_at(position + 4 * index);
+
+				let (width, height) = (field(4)?, field(5)?);
+				let (x, y) = (field(6)?, field(7)?);
+
+				if width > XCURSOR_MAX_DIMENSION
+					|| height > XCURSOR_MAX_DIMENSION
+					|| x > width || y > height
+				{
+					return None;
+				}
+
+				let start = position + field(0)?.max(XCURSOR_IMAGE_HEADER as u32) as usize;
+				let length = width as usize * height as usize * 4;
+
+				let pixels = data
+					.get(start..start.checked_add(length)?)?
+					.chunks_exact(4)
+					.map(|pixel| u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+					.collect();
+
+				Some(Self {
+					width: width as u16,
+					height: height as u16,
+					hotspot: Point::new(x as i16, y as i16),
+					delay: field(8)?,
+
+					pixels,
+				})
+			})
+			.collect()
+	}
+}
+
+/// An Xcursor theme, and the size of cursor to load from it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CursorTheme {
+	/// The name of the theme, such as `Adwaita`.
+	pub name: String,
+	/// The nominal size of cursor to load, in pixels.
+	pub size: u32,
+
+	/// The directories searched for themes, in order.
+	pub path: Vec<PathBuf>,
+}
+
+impl CursorTheme {
+	/// Returns the theme `name` at `size`, searched for in the directories in
+	/// `XCURSOR_PATH`, or the usual icon directories if it isn't set.
+	pub fn new(name: impl Into<String>, size: u32) -> Self {
+		let path = match env::var("XCURSOR_PATH") {
+			Ok(path) => path.split(':').map(expand_home).collect(),
+			Err(_) => DEFAULT_PATH.into_iter().map(expand_home).collect(),
+		};
+
+		Self {
+			name: name.into(),
+			size,
+
+			path,
+		}
+	}
+
+	/// Returns the user's theme, as chosen by the `XCURSOR_THEME` and
+	/// `XCURSOR_SIZE` environment variables.
+	///
+	/// Without `XCURSOR_SIZE`, the size is a 48th of the smaller dimension
+	/// of the default screen.
+	pub fn from_env(setup: &crate::Setup) -> Self {
+		let name = env::var("XCURSOR_THEME").unwrap_or_else(|_| DEFAULT_THEME.to_owned());
+
+		let size = env::var("XCURSOR_SIZE")
+			.ok()
+			.and_then(|size| size.parse().ok())
+			.filter(|&size| size > 0)
+			.unwrap_or_else(|| {
+				let screen = setup.screens.first();
+				let dimension = screen.map_or(0, |screen| screen.width.min(screen.height));
+
+				u32::from(dimension / 48).max(1)
+			});
+
+		Self::new(name, size)
+	}
+
+	/// Finds the Xcursor file for the cursor called `name`, searching the
+	/// theme, then the themes it inherits from, then the default theme.
+	pub fn find(&self, name: &str) -> Option<PathBuf> {
+		let mut searched = HashSet::new();
+
+		self.find_in(&self.name, name, &mut searched, 0)
+			.or_else(|| self.find_in(DEFAULT_THEME, name, &mut searched, 0))
+	}
+
+	/// Finds the Xcursor file for `name` in `theme` or the themes it inherits
+	/// from, skipping themes which have already been `searched`.
+	fn find_in(
+		&self, theme: &str, name: &str, searched: &mut HashSet<String>, depth: usize,
+	) -> Option<PathBuf> {
+		if depth > MAX_INHERITANCE || !searched.insert(theme.to_owned()) {
+			return None;
+		}
+
+		let found = self
+			.path
+			.iter()
+			.map(|directory| directory.join(theme).join("cursors").join(name))
+			.find(|file| file.is_file());
+
+		if found.is_some() {
+			return found;
+		}
+
+		self.path
+			.iter()
+			.filter_map(|directory| {
+				fs::read_to_string(directory.join(theme).join("index.theme")).ok()
+			})
+			.flat_map(|index| inherited_themes(&index))
+			.find_map(|parent| self.find_in(&parent, name, searched, depth + 1))
+	}
+
+	/// Loads the frames of the cursor called `name` at the theme's size.
+	///
+	/// Returns `None` if the theme doesn't have the cursor, or its file can't
+	/// be read.
+	pub fn load_images(&self, name: &str) -> Option<Vec<CursorImage>> {
+		let data = fs::read(self.find(name)?).ok()?;
+
+		CursorImage::from_xcursor(&data, self.size)
+	}
+}
+
+/// Replaces a leading `~` in `directory` with the home directory.
+fn expand_home(directory: &str) -> PathBuf {
+	match (directory.strip_prefix('~'), env::var_os("HOME")) {
+		(Some(rest), Some(home)) => {
+			let mut path = PathBuf::from(home);
+			path.push(rest.trim_start_matches('/'));
+
+			path
+		},
+
+		_ => PathBuf::from(directory),
+	}
+}
+
+/// Returns the themes listed by the `Inherits` key of an `index.theme` file.
+fn inherited_themes(index: &str) -> Vec<String> {
+	index
+		.lines()
+		.filter_map(|line| line.trim().strip_prefix("Inherits"))
+		.filter_map(|rest| rest.trim_start().strip_prefix('='))
+		.flat_map(|themes| themes.split([',', ';']))
+		.map(|theme| theme.trim().to_owned())
+		.filter(|theme| !theme.is_empty())
+		.collect()
+}
+
+/// An error creating a cursor.
+#[derive(Debug)]
+pub enum CursorError {
+	/// No cursor with the name was found.
+	NotFound,
+	/// The X server can't show full-color cursors, as it doesn't support
+	/// version 0.5 of RENDER or 32-bit pixmaps.
+	ArgbUnsupported,
+	/// An ID for the cursor or a resource used to make it couldn't be
+	/// generated.
+	Id(XidError),
+	/// An error occurred communicating with the X server.
+	Reply(ReplyError),
+}
+
+impl fmt::Display for CursorError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NotFound => write!(f, "no cursor with that name was found"),
+			Self::ArgbUnsupported => write!(f, "the X server does not support ARGB cursors"),
+			Self::Id(error) => write!(f, "{error}"),
+			Self::Reply(error) => write!(f, "{error}"),
+		}
+	}
+}
+
+impl Error for CursorError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::NotFound | Self::ArgbUnsupported => None,
+			Self::Id(error) => Some(error),
+			Self::Reply(error) => Some(error),
+		}
+	}
+}
+
+impl From<XidError> for CursorError {
+	fn from(error: XidError) -> Self {
+		Self::Id(error)
+	}
+}
+
+impl From<ReplyError> for CursorError {
+	fn from(error: ReplyError) -> Self {
+		Self::Reply(error)
+	}
+}
+
+impl From<io::Error> for CursorError {
+	fn from(error: io::Error) -> Self {
+		Self::Reply(ReplyError::Io(error))
+	}
+}
+
+/// Returns the index of the cursor font's cursor for `name`, which is either
+/// one of its own names or a CSS cursor name.
+fn font_cursor_index(name: &str) -> Option<u16> {
+	let name = FONT_ALIASES
+		.iter()
+		.find(|&&(alias, _)| alias == name)
+		.map_or(name, |&(_, font_name)| font_name);
+
+	FONT_CURSORS
+		.iter()
+		.position(|&font_name| font_name == name)
+		.map(|index| index as u16)
+}
+
+/// Creates a cursor from the cursor font's cursor called `name`.
+///
+/// With a [`RequestWriter`], if a new range of resource IDs has to be
+/// requested, its reply is [awaited directly].
+///
+/// See [`Client::create_font_cursor`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_font_cursor(
+	connection: &mut impl RoundTrip, name: &str,
+) -> Result<Cursor, CursorError> {
+	let index = font_cursor_index(name).ok_or(CursorError::NotFound)?;
+
+	let font = xid::generate_id(connection).await?;
+	let cursor = xid::generate_id(connection).await?;
+
+	let writer = connection.writer();
+	let open_font = OpenFont {
+		font,
+		name: CURSOR_FONT,
+	};
+	request::send_core(writer, &open_font).await?;
+
+	// Each cursor's mask is the glyph after it.
+	let request = CreateGlyphCursor {
+		cursor,
+		source: (font, index * 2),
+		mask: (font, index * 2 + 1),
+		// A black foreground and white background.
+		foreground: [0; 3],
+		background: [0xffff; 3],
+	};
+	request::send_core(writer, &request).await?;
+
+	// The cursor keeps its glyphs, so the font is no longer needed.
+	request::send_core(writer, &CloseFont(font)).await?;
+
+	Ok(Cursor(cursor))
+}
+
+/// Creates a cursor showing `image`, using the ARGB32 picture format
+/// `format`.
+async fn create_image_cursor(
+	connection: &mut impl RoundTrip, image: &CursorImage, format: u32,
+) -> Result<Cursor, CursorError> {
+	let root = connection.writer().root_window();
+
+	let mut pixmap_image = Image::new(
+		connection.writer().setup(),
+		ImageFormat::ZPixmap,
+		32,
+		image.width,
+		image.height,
+	)
+	.ok_or(CursorError::ArgbUnsupported)?;
+
+	for (index, &pixel) in image.pixels.iter().enumerate() {
+		let x = (index % usize::from(image.width.max(1))) as u16;
+		let y = (index / usize::from(image.width.max(1))) as u16;
+
+		pixmap_image.set_pixel(x, y, pixel);
+	}
+
+	let pixmap = xid::generate_id(connection).await?;
+
+	let request = CreatePixmap {
+		depth: 32,
+		pixmap,
+		drawable: Drawable(root.0),
+		size: Size::new(image.width, image.height),
+	};
+	request::send_core(connection.writer(), &request).await?;
+
+	let gc = gc::create_gc(connection, pixmap, &GcValues::default()).await?;
+	Drawable(pixmap)
+		.put_image(connection.writer(), &gc, &pixmap_image, Point::new(0, 0))
+		.await?;
+	gc.free(connection.writer()).await?;
+
+	let picture =
+		render::create_picture(connection, Drawable(pixmap), format, &PictureValues::new()).await?;
+
+	let cursor = xid::generate_id(connection).await?;
+
+	let request = CreateCursor {
+		cursor,
+		source: picture,
+		hotspot: image.hotspot,
+	};
+	request::send(connection, &request).await?;
+
+	// The cursor keeps a copy of the image.
+	render::free_picture(connection, picture).await?;
+	request::send_core(connection.writer(), &FreePixmap(pixmap)).await?;
+
+	Ok(Cursor(cursor))
+}
+
+/// Creates a full-color cursor from `images` with RENDER.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_argb_cursor`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_argb_cursor(
+	connection: &mut impl RoundTrip, images: &[CursorImage],
+) -> Result<Cursor, CursorError> {
+	let Some(first) = images.first() else {
+		return Err(CursorError::NotFound);
+	};
+
+	if extension::query_extension(connection, render::RENDER)
+		.await?
+		.is_none()
+	{
+		return Err(CursorError::ArgbUnsupported);
+	}
+
+	let version = render::render_version(connection).await?;
+	if version < CURSOR_VERSION {
+		return Err(CursorError::ArgbUnsupported);
+	}
+
+	let formats = render::query_pict_formats(connection).await?;
+	let format = formats
+		.standard(StandardFormat::Argb32)
+		.ok_or(CursorError::ArgbUnsupported)?
+		.id;
+
+	// Without animated cursors, only the first frame is shown.
+	if images.len() == 1 || version < ANIM_CURSOR_VERSION {
+		return create_image_cursor(connection, first, format).await;
+	}
+
+	let mut frames = Vec::with_capacity(images.len());
+
+	for image in images {
+		frames.push((
+			create_image_cursor(connection, image, format).await?,
+			image.delay,
+		));
+	}
+
+	let cursor = xid::generate_id(connection).await?;
+
+	let ids: Vec<(u32, u32)> = frames
+		.iter()
+		.map(|&(frame, delay)| (frame.id(), delay))
+		.collect();
+	let request = CreateAnimCursor {
+		cursor,
+		frames: &ids,
+	};
+	request::send(connection, &request).await?;
+
+	// The animated cursor keeps its frames.
+	for (frame, _) in frames {
+		frame.free(connection.writer()).await?;
+	}
+
+	Ok(Cursor(cursor))
+}
+
+/// Loads the cursor called `name` from `theme`, falling back to the
+/// cursor font.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::load_cursor`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn load_cursor(
+	connection: &mut impl RoundTrip, theme: &CursorTheme, name: &str,
+) -> Result<Cursor, CursorError> {
+	if let Some(images) = theme.load_images(name) {
+		match create_argb_cursor(connection, &images).await {
+			Err(CursorError::ArgbUnsupported) => {},
+			result => return result,
+		}
+	}
+
+	create_font_cursor(connection, name).await
+}
+
+impl Client {
+	/// Creates a cursor from the cursor font's cursor called `name`, such as
+	/// `left_ptr` or `xterm`.
+	///
+	/// Common CSS cursor names, such as `pointer` or `text`, are also
+	/// accepted. Returns [`CursorError::NotFound`] if the cursor font has no
+	/// such cursor.
+	pub async fn create_font_cursor(&mut self, name: &str) -> Result<Cursor, CursorError> {
+		create_font_cursor(self, name).await
+	}
+
+	/// Creates a full-color cursor from `images` with RENDER, animated
+	/// through them if there is more than one.
+	///
+	/// Returns [`CursorError::ArgbUnsupported`] if the X server can't show
+	/// full-color cursors. If it can't animate them, only the first image is
+	/// shown.
+	pub async fn create_argb_cursor(
+		&mut self, images: &[CursorImage],
+	) -> Result<Cursor, CursorError> {
+		create_argb_cursor(self, images).await
+	}
+
+	/// Loads the cursor called `name` from `theme`, falling back to the
+	/// [cursor font] if the theme doesn't have it or the X server can't show
+	/// full-color cursors.
+	///
+	/// [cursor font]: Client::create_font_cursor
+	pub async fn load_cursor(
+		&mut self, theme: &CursorTheme, name: &str,
+	) -> Result<Cursor, CursorError> {
+		load_cursor(self, theme, name).await
+	}
+}
@@ -0,0 +1,552 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The Damage extension, which reports the areas of drawables drawn on.
+//!
+//! A compositing manager creates a [`Damage`] object for each redirected
+//! window, and is sent a [`DamageNotifyEvent`] when the window's contents
+//! change. It [subtracts] the damage before redrawing the area which was
+//! damaged, so that it is told of the next change:
+//!
+//! ```no_run
+//! # use xrs::{damage::{DamageNotifyEvent, ReportLevel}, Client};
+//! # async fn example(client: &mut Client, window: xrs::window::Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let damage = client.create_damage(window.into(), ReportLevel::NonEmpty).await?;
+//! let first_event = client
+//!     .query_extension("DAMAGE")
+//!     .await?
+//!     .ok_or("no Damage extension")?
+//!     .first_event;
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     if let Some(event) = DamageNotifyEvent::from_frame(first_event, &frame) {
+//!         let damaged = client.subtract_damage(event.damage, None).await?;
+//!         // Redraw `damaged`...
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Areas of damage are given and returned as [`Region`]s, which are stored on
+//! the X server as XFIXES regions while they are used. A compositing manager
+//! which redraws the whole window anyway can [clear] the damage instead,
+//! which doesn't need XFIXES.
+//!
+//! [subtracts]: Client::subtract_damage
+//! [clear]: Client::clear_damage
+
+use crate::{
+	client::RoundTrip,
+	draw::Drawable,
+	extension,
+	geometry::Rectangle,
+	raw::{assert_wire_size, X11Frame},
+	region::{self, Region},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the Damage extension.
+const DAMAGE: &str = "DAMAGE";
+
+/// The minor opcode of the `DamageQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `DamageCreate` request.
+const CREATE: u8 = 1;
+/// The minor opcode of the `DamageDestroy` request.
+const DESTROY: u8 = 2;
+/// The minor opcode of the `DamageSubtract` request.
+const SUBTRACT: u8 = 3;
+/// The minor opcode of the `DamageAdd` request.
+const ADD: u8 = 4;
+
+/// The version of Damage requested by this client.
+///
+/// The X server rejects every other Damage request until the client has
+/// sent its version.
+const VERSION: (u32, u32) = (1, 1);
+
+/// The offset of a `DamageNotify` event's code from the extension's first
+/// event code.
+const NOTIFY: u8 = 0;
+
+/// The bit set in an event's code if it was sent with `SendEvent`.
+const SEND_EVENT_MASK: u8 = 0x80;
+/// The bit set in a `DamageNotify` event's level if more events follow it
+/// for the same damage.
+const MORE_MASK: u8 = 0x80;
+
+/// A handle to a damage object, which accumulates the areas of a drawable
+/// which are drawn on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Damage(pub u32);
+
+impl Damage {
+	/// The damage object's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+}
+
+/// When [`DamageNotifyEvent`]s are generated for a [`Damage`] object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportLevel {
+	/// An event for every rectangle drawn.
+	RawRectangles,
+	/// An event for each rectangle drawn which isn't already damaged.
+	DeltaRectangles,
+	/// An event whenever the bounding box of the damage grows.
+	BoundingBox,
+	/// An event when the damage stops being empty.
+	///
+	/// This is the least chatty level: compositing managers use it and
+	/// [subtract] the damage once they have redrawn.
+	///
+	/// [subtract]: Client::subtract_damage
+	NonEmpty,
+}
+
+impl ReportLevel {
+	pub(crate) const fn from_value(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(Self::RawRectangles),
+			1 => Some(Self::DeltaRectangles),
+			2 => Some(Self::BoundingBox),
+			3 => Some(Self::NonEmpty),
+
+			_ => None,
+		}
+	}
+}
+
+/// A `DamageNotify` event, generated when a drawable with a [`Damage`] object
+/// is drawn on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DamageNotifyEvent {
+	pub level: ReportLevel,
+	/// Whether more events for the same damage immediately follow this one.
+	pub more: bool,
+
+	pub drawable: Drawable,
+	pub damage: Damage,
+	pub timestamp: Timestamp,
+
+	/// The area which was damaged, relative to the drawable's origin; for
+	/// [`ReportLevel::BoundingBox`] and [`ReportLevel::NonEmpty`], the
+	/// bounding box of all of the damage.
+	pub area: Rectangle,
+	/// The drawable's geometry.
+	pub geometry: Rectangle,
+}
+
+impl DamageNotifyEvent {
+	/// Decodes a `DamageNotifyEvent` from an event frame, given the Damage
+	/// extension's [first event code].
+	///
+	/// Returns `None` if `frame` is not a `DamageNotify` event.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	pub fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		if (code & !SEND_EVENT_MASK).wrapping_sub(first_event) != NOTIFY {
+			return None;
+		}
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let u16_at = |offset: usize| u16::from_be_bytes([chunk[offset - 1], chunk[offset]]);
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				chunk[offset - 1],
+				chunk[offset],
+				chunk[offset + 1],
+				chunk[offset + 2],
+			])
+		};
+		let rectangle_at = |offset: usize| Rectangle {
+			x: u16_at(offset) as i16,
+			y: u16_at(offset + 2) as i16,
+			width: u16_at(offset + 4),
+			height: u16_at(offset + 6),
+		};
+
+		Some(Self {
+			level: ReportLevel::from_value(chunk[0] & !MORE_MASK)?,
+			more: chunk[0] & MORE_MASK != 0,
+
+			drawable: Drawable(u32_at(4)),
+			damage: Damage(u32_at(8)),
+			timestamp: Timestamp(u32_at(12)),
+
+			area: rectangle_at(16),
+			geometry: rectangle_at(24),
+		})
+	}
+}
+
+/// The Damage extension's `DamageQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "DamageQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(DAMAGE);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of Damage supported by both the X server and the client.
+///
+/// This is the reply to a `DamageQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("DamageQueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[ma0, ma1, ma2, ma3, mi0, mi1, mi2, mi3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([ma0, ma1, ma2, ma3]),
+			minor_version: u32::from_be_bytes([mi0, mi1, mi2, mi3]),
+		})
+	}
+}
+
+/// The Damage extension's `DamageCreate` request.
+///
+/// See [`Client::create_damage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Create {
+	/// The ID of the new damage object.
+	pub damage: Damage,
+	pub drawable: Drawable,
+	pub level: ReportLevel,
+}
+
+impl Request for Create {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DamageCreate";
+	const EXTENSION: Option<&'static str> = Some(DAMAGE);
+	const OPCODE: u8 = CREATE;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.damage.id());
+		body.put_u32(self.drawable.id());
+		body.put_u8(self.level as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(Create: [u8, u8, u16, u32, u32, u8, [u8; 3]]);
+
+/// The Damage extension's `DamageDestroy` request.
+///
+/// See [`Client::destroy_damage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Destroy(pub Damage);
+
+impl Request for Destroy {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DamageDestroy";
+	const EXTENSION: Option<&'static str> = Some(DAMAGE);
+	const OPCODE: u8 = DESTROY;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(Destroy: [u8, u8, u16, u32]);
+
+/// The Damage extension's `DamageSubtract` request.
+///
+/// See [`Client::subtract_damage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subtract {
+	pub damage: Damage,
+	/// The XFIXES region to remove, or `None` to remove all of the damage.
+	pub repair: Option<u32>,
+	/// The XFIXES region set to the damage which was removed.
+	pub parts: Option<u32>,
+}
+
+impl Request for Subtract {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DamageSubtract";
+	const EXTENSION: Option<&'static str> = Some(DAMAGE);
+	const OPCODE: u8 = SUBTRACT;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.damage.id());
+		body.put_u32(self.repair.unwrap_or(0));
+		body.put_u32(self.parts.unwrap_or(0));
+	}
+}
+
+assert_wire_size!(Subtract: [u8, u8, u16, u32, u32, u32]);
+
+/// The Damage extension's `DamageAdd` request.
+///
+/// See [`Client::add_damage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Add {
+	pub drawable: Drawable,
+	/// The XFIXES region reported as damaged.
+	pub region: u32,
+}
+
+impl Request for Add {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DamageAdd";
+	const EXTENSION: Option<&'static str> = Some(DAMAGE);
+	const OPCODE: u8 = ADD;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.region);
+	}
+}
+
+assert_wire_size!(Add: [u8, u8, u16, u32, u32]);
+
+/// Negotiates the version of Damage supported by the X server.
+async fn query_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	let request = QueryVersion {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+
+	extension::negotiate_version(connection, DAMAGE, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await
+}
+
+/// Creates a damage object which accumulates the areas of `drawable`
+/// which are drawn on.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_damage`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_damage(
+	connection: &mut impl RoundTrip, drawable: Drawable, level: ReportLevel,
+) -> Result<Damage, XidError> {
+	// The X server rejects Damage requests from clients which haven't
+	// negotiated the version.
+	query_version(connection).await?;
+	let damage = Damage(xid::generate_id(connection).await?);
+
+	let request = Create {
+		damage,
+		drawable,
+		level,
+	};
+	request::send(connection, &request).await?;
+
+	Ok(damage)
+}
+
+/// Removes all of `damage`'s damage.
+///
+/// With a [`RequestWriter`], if the Damage extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::clear_damage`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn clear_damage(
+	connection: &mut impl RoundTrip, damage: Damage,
+) -> Result<(), ReplyError> {
+	let request = Subtract {
+		damage,
+		repair: None,
+		parts: None,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Removes `repair` from `damage`, or all of its damage if `repair` is
+/// `None`, returning the damage which was removed.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::subtract_damage`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn subtract_damage(
+	connection: &mut impl RoundTrip, damage: Damage, repair: Option<&Region>,
+) -> Result<Region, XidError> {
+	let repair = match repair {
+		Some(repair) => Some(region::create_xfixes_region(connection, repair).await?),
+		None => None,
+	};
+	let parts = region::create_xfixes_region(connection, &Region::new()).await?;
+
+	let request = Subtract {
+		damage,
+		repair,
+		parts: Some(parts),
+	};
+	request::send(connection, &request).await?;
+
+	let removed = region::fetch_xfixes_region(connection, parts).await?;
+
+	for id in repair.into_iter().chain([parts]) {
+		region::destroy_xfixes_region(connection, id).await?;
+	}
+
+	Ok(removed)
+}
+
+/// Reports `region` of `drawable` as damaged.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::add_damage`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn add_damage(
+	connection: &mut impl RoundTrip, drawable: Drawable, region: &Region,
+) -> Result<(), XidError> {
+	let id = region::create_xfixes_region(connection, region).await?;
+
+	request::send(
+		connection,
+		&Add {
+			drawable,
+			region: id,
+		},
+	)
+	.await?;
+	region::destroy_xfixes_region(connection, id).await?;
+
+	Ok(())
+}
+
+/// Queries the version of Damage supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::damage_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn damage_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	query_version(connection).await
+}
+
+/// Destroys `damage`.
+///
+/// With a [`RequestWriter`], if the Damage extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::destroy_damage`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn destroy_damage(
+	connection: &mut impl RoundTrip, damage: Damage,
+) -> Result<(), ReplyError> {
+	request::send(connection, &Destroy(damage)).await
+}
+
+impl Client {
+	/// Queries the version of Damage supported by the X server, as its major
+	/// and minor version.
+	///
+	/// The version is negotiated once, so only the first query makes a round
+	/// trip to the X server.
+	pub async fn damage_version(&mut self) -> Result<(u32, u32), ReplyError> {
+		damage_version(self).await
+	}
+
+	/// Creates a damage object which accumulates the areas of `drawable`
+	/// which are drawn on, generating [`DamageNotifyEvent`]s as given by
+	/// `level`.
+	///
+	/// The client's Damage version is sent first, as the X server requires.
+	pub async fn create_damage(
+		&mut self, drawable: Drawable, level: ReportLevel,
+	) -> Result<Damage, XidError> {
+		create_damage(self, drawable, level).await
+	}
+
+	/// Destroys `damage`.
+	pub async fn destroy_damage(&mut self, damage: Damage) -> Result<(), ReplyError> {
+		destroy_damage(self, damage).await
+	}
+
+	/// Removes all of `damage`'s damage, without finding out what it was.
+	pub async fn clear_damage(&mut self, damage: Damage) -> Result<(), ReplyError> {
+		clear_damage(self, damage).await
+	}
+
+	/// Removes `repair` from `damage`, or all of its damage if `repair` is
+	/// `None`, returning the damage which was removed.
+	///
+	/// This needs version 2 of the XFIXES extension, which stores the regions
+	/// on the X server.
+	pub async fn subtract_damage(
+		&mut self, damage: Damage, repair: Option<&Region>,
+	) -> Result<Region, XidError> {
+		subtract_damage(self, damage, repair).await
+	}
+
+	/// Reports `region` of `drawable` as damaged, for drawing which the X
+	/// server can't see, such as direct rendering.
+	///
+	/// This needs version 2 of the XFIXES extension, which stores the region
+	/// on the X server.
+	pub async fn add_damage(
+		&mut self, drawable: Drawable, region: &Region,
+	) -> Result<(), XidError> {
+		add_damage(self, drawable, region).await
+	}
+}
@@ -0,0 +1,964 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Drag and drop with the XDND protocol.
+//!
+//! A drag is offered by a [`DragSource`], which owns the `XdndSelection`
+//! selection and converts it to the types of data being dragged. As the
+//! pointer moves, the source tells the window under it that supports XDND,
+//! a [`DropTarget`], where the drag is with `ClientMessage`s; the target
+//! answers whether it would accept a drop there. Once dropped, the target
+//! gets the data by converting the selection, then tells the source that it
+//! has finished.
+//!
+//! A window accepts drops once [`Client::accept_drops`] has marked it as
+//! aware of XDND. Its [`DropTarget`] decodes the messages it is sent into
+//! [`DropEvent`]s, which it must answer:
+//!
+//! ```no_run
+//! # use xrs::{atoms::Atom, dnd::{DndAction, DropEvent}, event::Event, window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window, uri_list: Atom) -> Result<(), xrs::selection::SelectionError> {
+//! let mut target = client.accept_drops(window).await?;
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     let Some(event) = Event::from_frame(&frame) else {
+//!         continue;
+//!     };
+//!
+//!     match target.handle(&event) {
+//!         Some(DropEvent::Position { .. }) => {
+//!             if target.types(client).await?.contains(&uri_list) {
+//!                 target.accept(client.as_mut(), DndAction::Copy).await?;
+//!             } else {
+//!                 target.reject(client.as_mut()).await?;
+//!             }
+//!         },
+//!
+//!         Some(DropEvent::Drop { .. }) => {
+//!             let uris = target.receive(client, uri_list).await?;
+//!             println!("dropped {uris:?}");
+//!
+//!             let action = uris.is_some().then_some(DndAction::Copy);
+//!             target.finish(client.as_mut(), action).await?;
+//!         },
+//!
+//!         _ => {},
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Client::start_drag`] starts a drag, usually when the pointer moves far
+//! enough with a button held. The [`DragSource`] is told where the pointer
+//! moves, and given events to answer the target's messages and requests for
+//! the data:
+//!
+//! ```no_run
+//! # use xrs::{atoms::Atom, dnd::DndAction, event::Event, property::Property, time::Timestamp, Client};
+//! # async fn example(client: &mut Client, time: Timestamp) -> Result<(), xrs::selection::SelectionError> {
+//! let text = Property::text(Atom::STRING, "Hello, world!");
+//!
+//! let Some(mut source) = client.start_drag(vec![(Atom::STRING, text)], time).await? else {
+//!     return Ok(());
+//! };
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     match Event::from_frame(&frame) {
+//!         Some(Event::MotionNotify(motion)) => {
+//!             source
+//!                 .motion(client, motion.root_position, motion.time, DndAction::Copy)
+//!                 .await?;
+//!         },
+//!         Some(Event::ButtonRelease(release)) => {
+//!             if !source.end(client.as_mut(), release.time).await? {
+//!                 break;
+//!             }
+//!         },
+//!
+//!         Some(event) => {
+//!             source.handle(client, &event).await?;
+//!         },
+//!         None => {},
+//!     }
+//!
+//!     if source.result().is_some() {
+//!         break;
+//!     }
+//! }
+//!
+//! source.release(client.as_mut()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Targets which only support versions of XDND before 3 are ignored, and
+//! `XdndProxy` windows are not followed.
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	coordinates::TranslateCoordinates,
+	event::{ClientMessageData, ClientMessageEvent, Event, SendEvent},
+	event_mask::EventMask,
+	geometry::Point,
+	property::{self, Property, PropertyMode},
+	request,
+	selection::{self, SelectionError, SelectionOwner, TransferOptions},
+	time::Timestamp,
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use tokio::io;
+
+/// The version of XDND implemented.
+const XDND_VERSION: u8 = 5;
+/// The oldest version of XDND that targets are sent drags with.
+const MIN_XDND_VERSION: u8 = 3;
+
+/// The number of types which fit in an `XdndEnter` message; drags of more
+/// types list them in the source's `XdndTypeList` property.
+const ENTER_TYPES: usize = 3;
+
+/// The names of the atoms used by XDND, in the order of [`Atoms`]' fields.
+const ATOM_NAMES: [&str; 14] = [
+	"XdndAware",
+	"XdndSelection",
+	"XdndEnter",
+	"XdndPosition",
+	"XdndStatus",
+	"XdndLeave",
+	"XdndDrop",
+	"XdndFinished",
+	"XdndTypeList",
+	"XdndActionCopy",
+	"XdndActionMove",
+	"XdndActionLink",
+	"XdndActionAsk",
+	"XdndActionPrivate",
+];
+
+/// The atoms used by XDND.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Atoms {
+	aware: Atom,
+	selection: Atom,
+
+	enter: Atom,
+	position: Atom,
+	status: Atom,
+	leave: Atom,
+	drop: Atom,
+	finished: Atom,
+
+	type_list: Atom,
+
+	copy: Atom,
+	r#move: Atom,
+	link: Atom,
+	ask: Atom,
+	private: Atom,
+}
+
+impl Atoms {
+	/// Interns the atoms used by XDND.
+	async fn intern(connection: &mut impl RoundTrip) -> Result<Self, ReplyError> {
+		let atoms = atoms::intern_atoms(connection, &ATOM_NAMES).await?;
+
+		let [aware, selection, enter, position, status, leave, drop, finished, type_list, copy, r#move, link, ask, private] =
+			atoms[..]
+		else {
+			return Err(ReplyError::malformed("InternAtom"));
+		};
+
+		Ok(Self {
+			aware,
+			selection,
+
+			enter,
+			position,
+			status,
+			leave,
+			drop,
+			finished,
+
+			type_list,
+
+			copy,
+			r#move,
+			link,
+			ask,
+			private,
+		})
+	}
+
+	/// Returns the action named by `atom`, or `None` for [`Atom::NONE`].
+	fn action(&self, atom: Atom) -> Option<DndAction> {
+		Some(match atom {
+			Atom::NONE => return None,
+
+			atom if atom == self.copy => DndAction::Copy,
+			atom if atom == self.r#move => DndAction::Move,
+			atom if atom == self.link => DndAction::Link,
+			atom if atom == self.ask => DndAction::Ask,
+			atom if atom == self.private => DndAction::Private,
+
+			atom => DndAction::Other(atom),
+		})
+	}
+
+	/// Returns the atom naming `action`.
+	const fn action_atom(&self, action: DndAction) -> Atom {
+		match action {
+			DndAction::Copy => self.copy,
+			DndAction::Move => self.r#move,
+			DndAction::Link => self.link,
+			DndAction::Ask => self.ask,
+			DndAction::Private => self.private,
+
+			DndAction::Other(atom) => atom,
+		}
+	}
+}
+
+/// What is done with dropped data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DndAction {
+	/// The data is copied to the target.
+	Copy,
+	/// The data is moved to the target, and deleted by the source.
+	Move,
+	/// The target links to the data.
+	Link,
+	/// The target asks the user what to do.
+	Ask,
+	/// The target does something only it understands.
+	Private,
+
+	/// Another action.
+	Other(Atom),
+}
+
+/// Packs `point` into a `u32`, with `x` in its high 16 bits.
+const fn pack_point(point: Point) -> u32 {
+	((point.x as u16 as u32) << 16) | point.y as u16 as u32
+}
+
+/// Unpacks a point packed by [`pack_point`].
+const fn unpack_point(value: u32) -> Point {
+	Point::new((value >> 16) as u16 as i16, value as u16 as i16)
+}
+
+/// Sends an XDND message of `message_type` to `window`.
+async fn send_message(
+	writer: &mut RequestWriter, window: Window, message_type: Atom, data: [u32; 5],
+) -> io::Result<()> {
+	let event = ClientMessageEvent {
+		window,
+		message_type,
+		data: ClientMessageData::U32(data),
+	};
+
+	request::send_core(
+		writer,
+		&SendEvent {
+			propagate: false,
+			destination: window,
+			// No event mask: the event is sent to the window's owner.
+			event_mask: EventMask::NONE,
+			event: event.to_bytes(),
+		},
+	)
+	.await
+}
+
+/// Returns the data of `event` if it is an XDND message sent to `window`.
+fn message(event: &Event, window: Window) -> Option<(Atom, [u32; 5])> {
+	let Event::ClientMessage(ClientMessageEvent {
+		window: destination,
+		message_type,
+		data: ClientMessageData::U32(data),
+	}) = *event
+	else {
+		return None;
+	};
+
+	(destination == window).then_some((message_type, data))
+}
+
+/// The drag a [`DropTarget`] is taking part in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Drag {
+	source: Window,
+	/// The version of XDND used.
+	version: u8,
+
+	/// The first types of the data being dragged.
+	types: Vec<Atom>,
+	/// Whether there are more types, listed in the source's `XdndTypeList`.
+	more_types: bool,
+
+	/// The time of the last position or drop.
+	time: Timestamp,
+}
+
+/// An XDND message received by a [`DropTarget`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DropEvent {
+	/// A drag has entered the window.
+	Enter {
+		source: Window,
+		/// The first three types of the data being dragged.
+		///
+		/// If `more_types` is true, all of them are listed by
+		/// [`DropTarget::types`].
+		types: Vec<Atom>,
+		more_types: bool,
+	},
+	/// The drag moved within the window.
+	///
+	/// This must be answered with [`DropTarget::accept`] or
+	/// [`DropTarget::reject`] before the source sends the next position.
+	Position {
+		source: Window,
+		/// The position of the pointer, relative to the root window.
+		position: Point,
+		time: Timestamp,
+		/// The action the source asks for, if any.
+		action: Option<DndAction>,
+	},
+	/// The drag left the window, or was cancelled.
+	Leave { source: Window },
+	/// The data was dropped on the window.
+	///
+	/// It can be received with [`DropTarget::receive`], and the drop must be
+	/// answered with [`DropTarget::finish`].
+	Drop { source: Window, time: Timestamp },
+}
+
+/// A window which accepts drops, decoding the XDND messages it is sent.
+///
+/// See [`Client::accept_drops`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DropTarget {
+	window: Window,
+	atoms: Atoms,
+
+	drag: Option<Drag>,
+}
+
+impl DropTarget {
+	/// Returns the window which accepts drops.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// Returns the source of the drag currently over the window, if any.
+	pub fn source(&self) -> Option<Window> {
+		self.drag.as_ref().map(|drag| drag.source)
+	}
+
+	/// Decodes `event` if it is an XDND message sent to the window.
+	///
+	/// Messages from sources other than that of the current drag are
+	/// ignored.
+	pub fn handle(&mut self, event: &Event) -> Option<DropEvent> {
+		let (message_type, data) = message(event, self.window)?;
+		let (source, flags) = (Window(data[0]), data[1]);
+
+		if message_type == self.atoms.enter {
+			let types: Vec<Atom> = data[2..2 + ENTER_TYPES]
+				.iter()
+				.map(|&atom| Atom(atom))
+				.filter(|&atom| atom != Atom::NONE)
+				.collect();
+			let more_types = flags & 1 != 0;
+
+			self.drag = Some(Drag {
+				source,
+				version: ((flags >> 24) as u8).min(XDND_VERSION),
+
+				types: types.clone(),
+				more_types,
+
+				time: Timestamp::CURRENT_TIME,
+			});
+
+			return Some(DropEvent::Enter {
+				source,
+				types,
+				more_types,
+			});
+		}
+
+		let drag = self.drag.as_mut().filter(|drag| drag.source == source)?;
+
+		match message_type {
+			message_type if message_type == self.atoms.position => {
+				drag.time = Timestamp(data[3]);
+
+				Some(DropEvent::Position {
+					source,
+					position: unpack_point(data[2]),
+					time: drag.time,
+					action: self.atoms.action(Atom(data[4])),
+				})
+			},
+
+			message_type if message_type == self.atoms.leave => {
+				self.drag = None;
+
+				Some(DropEvent::Leave { source })
+			},
+
+			message_type if message_type == self.atoms.drop => {
+				drag.time = Timestamp(data[2]);
+
+				Some(DropEvent::Drop {
+					source,
+					time: drag.time,
+				})
+			},
+
+			_ => None,
+		}
+	}
+
+	/// Returns every type of the data being dragged, reading them from the
+	/// source's `XdndTypeList` property if there are more than fit in its
+	/// `XdndEnter` message.
+	pub async fn types(&self, client: &mut Client) -> Result<Vec<Atom>, ReplyError> {
+		let Some(drag) = &self.drag else {
+			return Ok(Vec::new());
+		};
+
+		if !drag.more_types {
+			return Ok(drag.types.clone());
+		}
+
+		let types = property::get_property(client, drag.source, self.atoms.type_list, Atom::ATOM)
+			.await?
+			.and_then(|value| value.to_atoms());
+
+		Ok(types.unwrap_or_else(|| drag.types.clone()))
+	}
+
+	/// Tells the source that the data would be accepted, with `action`, if it
+	/// were dropped at its last position.
+	pub async fn accept(&self, writer: &mut RequestWriter, action: DndAction) -> io::Result<()> {
+		self.send_status(writer, Some(action)).await
+	}
+
+	/// Tells the source that the data wouldn't be accepted if it were dropped
+	/// at its last position.
+	pub async fn reject(&self, writer: &mut RequestWriter) -> io::Result<()> {
+		self.send_status(writer, None).await
+	}
+
+	/// Sends an `XdndStatus` message to the source, accepting the drag with
+	/// `action` if there is one.
+	async fn send_status(
+		&self, writer: &mut RequestWriter, action: Option<DndAction>,
+	) -> io::Result<()> {
+		let Some(drag) = &self.drag else {
+			return Ok(());
+		};
+
+		// Bit 1 asks for positions to be sent even within an empty rectangle,
+		// so that every position is sent.
+		let flags = 0b10 | u32::from(action.is_some());
+		let action = action.map_or(Atom::NONE, |action| self.atoms.action_atom(action));
+
+		let data = [self.window.0, flags, 0, 0, action.0];
+
+		send_message(writer, drag.source, self.atoms.status, data).await
+	}
+
+	/// Receives the dropped data as `type`, or `None` if the source couldn't
+	/// convert it to `type` or there is no drop.
+	///
+	/// See [`Client::get_selection`].
+	pub async fn receive(
+		&self, client: &mut Client, r#type: Atom,
+	) -> Result<Option<Property>, SelectionError> {
+		let Some(drag) = &self.drag else {
+			return Ok(None);
+		};
+
+		selection::get_selection(
+			client,
+			self.atoms.selection,
+			r#type,
+			drag.time,
+			TransferOptions::default(),
+		)
+		.await
+	}
+
+	/// Tells the source that the drop has been dealt with, having performed
+	/// `action`, or that it failed if `action` is `None`.
+	pub async fn finish(
+		&mut self, writer: &mut RequestWriter, action: Option<DndAction>,
+	) -> io::Result<()> {
+		let Some(drag) = self.drag.take() else {
+			return Ok(());
+		};
+
+		// Whether it succeeded and the action performed were added in
+		// version 5.
+		let data = if drag.version >= 5 {
+			let action = action.map_or(Atom::NONE, |action| self.atoms.action_atom(action));
+
+			[
+				self.window.0,
+				u32::from(action != Atom::NONE),
+				action.0,
+				0,
+				0,
+			]
+		} else {
+			[self.window.0, 0, 0, 0, 0]
+		};
+
+		send_message(writer, drag.source, self.atoms.finished, data).await
+	}
+}
+
+/// Marks `window` as accepting drops.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::accept_drops`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn accept_drops(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<DropTarget, ReplyError> {
+	let atoms = Atoms::intern(connection).await?;
+
+	window
+		.set_property(
+			connection.writer(),
+			atoms.aware,
+			&Property::u32s(Atom::ATOM, &[u32::from(XDND_VERSION)]),
+			PropertyMode::Replace,
+		)
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(DropTarget {
+		window,
+		atoms,
+
+		drag: None,
+	})
+}
+
+/// The outcome of a drag, once the target has finished with the drop or the
+/// drag has ended without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DropResult {
+	/// Whether the data was dropped and accepted.
+	pub accepted: bool,
+	/// The action the target performed, if it said.
+	pub action: Option<DndAction>,
+}
+
+/// The target a [`DragSource`] is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Target {
+	window: Window,
+	/// The version of XDND used.
+	version: u8,
+
+	/// Whether the target would accept a drop at the last position.
+	accepted: bool,
+	/// The action the target would perform.
+	action: Option<DndAction>,
+
+	/// Whether an `XdndStatus` is awaited for the last position sent.
+	awaiting_status: bool,
+	/// The latest position, sent once the awaited `XdndStatus` is received.
+	pending: Option<(Point, Timestamp, DndAction)>,
+}
+
+/// A drag being offered by this client.
+///
+/// The source owns the `XdndSelection` selection, converting it to the
+/// dragged data. See [`Client::start_drag`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the drag isn't sent to any target until the source is told where the pointer is"]
+pub struct DragSource {
+	owner: SelectionOwner,
+	atoms: Atoms,
+	/// The types of the dragged data.
+	types: Vec<Atom>,
+
+	target: Option<Target>,
+	/// The target the data was dropped on, which hasn't finished with it.
+	dropped: Option<Target>,
+	result: Option<DropResult>,
+}
+
+impl DragSource {
+	/// Returns the window which owns the drag's selection.
+	pub const fn window(&self) -> Window {
+		self.owner.window()
+	}
+
+	/// Returns the target the drag is over, if any.
+	pub fn target(&self) -> Option<Window> {
+		self.target.map(|target| target.window)
+	}
+
+	/// Returns the action the target would perform if the data were dropped
+	/// now, or `None` if it wouldn't accept it.
+	pub fn accepted_action(&self) -> Option<DndAction> {
+		self.target
+			.filter(|target| target.accepted)
+			.and_then(|target| target.action)
+	}
+
+	/// Returns the outcome of the drag, once it has ended.
+	pub const fn result(&self) -> Option<DropResult> {
+		self.result
+	}
+
+	/// Tells the drag that the pointer moved to `position`, relative to the
+	/// root window, asking for `action`.
+	///
+	/// The drag leaves the last target and enters the window under the
+	/// pointer which accepts drops, if they differ, then tells it the
+	/// position.
+	pub async fn motion(
+		&mut self, client: &mut Client, position: Point, time: Timestamp, action: DndAction,
+	) -> Result<(), ReplyError> {
+		let found = find_target(client, position, self.atoms.aware).await?;
+
+		if found.map(|(window, _)| window) != self.target() {
+			self.leave(client.as_mut()).await.map_err(ReplyError::Io)?;
+
+			if let Some((window, version)) = found {
+				self.enter(client.as_mut(), window, version)
+					.await
+					.map_err(ReplyError::Io)?;
+			}
+		}
+
+		let Some(target) = &mut self.target else {
+			return Ok(());
+		};
+
+		// Only one position is sent at a time, so that a slow target isn't
+		// flooded with them.
+		if target.awaiting_status {
+			target.pending = Some((position, time, action));
+
+			return Ok(());
+		}
+
+		self.send_position(client.as_mut(), position, time, action)
+			.await
+			.map_err(ReplyError::Io)
+	}
+
+	/// Sends an `XdndEnter` message to `window`, making it the target.
+	async fn enter(
+		&mut self, writer: &mut RequestWriter, window: Window, version: u8,
+	) -> io::Result<()> {
+		let mut data = [
+			self.window().0,
+			(u32::from(version) << 24) | u32::from(self.types.len() > ENTER_TYPES),
+			0,
+			0,
+			0,
+		];
+
+		for (slot, r#type) in data[2..].iter_mut().zip(&self.types) {
+			*slot = r#type.0;
+		}
+
+		send_message(writer, window, self.atoms.enter, data).await?;
+
+		self.target = Some(Target {
+			window,
+			version,
+
+			accepted: false,
+			action: None,
+
+			awaiting_status: false,
+			pending: None,
+		});
+
+		Ok(())
+	}
+
+	/// Sends an `XdndLeave` message to the target, if there is one.
+	async fn leave(&mut self, writer: &mut RequestWriter) -> io::Result<()> {
+		let Some(target) = self.target.take() else {
+			return Ok(());
+		};
+
+		let data = [self.window().0, 0, 0, 0, 0];
+
+		send_message(writer, target.window, self.atoms.leave, data).await
+	}
+
+	/// Sends an `XdndPosition` message to the target.
+	async fn send_position(
+		&mut self, writer: &mut RequestWriter, position: Point, time: Timestamp, action: DndAction,
+	) -> io::Result<()> {
+		let Some(target) = &mut self.target else {
+			return Ok(());
+		};
+
+		target.awaiting_status = true;
+		let window = target.window;
+
+		let data = [
+			self.owner.window().0,
+			0,
+			pack_point(position),
+			time.0,
+			self.atoms.action_atom(action).0,
+		];
+
+		send_message(writer, window, self.atoms.position, data).await
+	}
+
+	/// Answers requests for the dragged data, and the target's `XdndStatus`
+	/// and `XdndFinished` messages.
+	///
+	/// Returns whether the event was for this drag.
+	pub async fn handle(&mut self, client: &mut Client, event: &Event) -> Result<bool, ReplyError> {
+		if self.owner.handle(client, event).await? {
+			return Ok(true);
+		}
+
+		let Some((message_type, data)) = message(event, self.window()) else {
+			return Ok(false);
+		};
+		let window = Window(data[0]);
+
+		match message_type {
+			message_type if message_type == self.atoms.status => {
+				let Some(target) = self
+					.target
+					.as_mut()
+					.filter(|target| target.window == window)
+				else {
+					return Ok(false);
+				};
+
+				target.accepted = data[1] & 1 != 0;
+				target.action = self.atoms.action(Atom(data[4]));
+				target.awaiting_status = false;
+
+				if let Some((position, time, action)) = target.pending.take() {
+					self.send_position(client.as_mut(), position, time, action)
+						.await
+						.map_err(ReplyError::Io)?;
+				}
+
+				Ok(true)
+			},
+
+			message_type if message_type == self.atoms.finished => {
+				let Some(target) = self.dropped.filter(|target| target.window == window) else {
+					return Ok(false);
+				};
+
+				self.dropped = None;
+				self.result = Some(
+					if target.version >= 5 {
+						DropResult {
+							accepted: data[1] & 1 != 0,
+							action: self.atoms.action(Atom(data[2])),
+						}
+					} else {
+						DropResult {
+							accepted: true,
+							action: target.action,
+						}
+					},
+				);
+
+				Ok(true)
+			},
+
+			_ => Ok(false),
+		}
+	}
+
+	/// Ends the drag where the pointer was last moved, usually when the
+	/// button is released.
+	///
+	/// If the target would accept the data, it is dropped, and the drag
+	/// keeps handling requests for the data until the target has finished.
+	/// Otherwise, the drag leaves the target and ends.
+	///
+	/// Returns whether the data was dropped.
+	pub async fn end(&mut self, writer: &mut RequestWriter, time: Timestamp) -> io::Result<bool> {
+		match self.target.filter(|target| target.accepted) {
+			Some(target) => {
+				self.target = None;
+				self.dropped = Some(target);
+
+				let data = [self.window().0, 0, time.0, 0, 0];
+				send_message(writer, target.window, self.atoms.drop, data).await?;
+
+				Ok(true)
+			},
+
+			None => {
+				self.cancel(writer).await?;
+
+				Ok(false)
+			},
+		}
+	}
+
+	/// Cancels the drag, leaving the target if there is one.
+	pub async fn cancel(&mut self, writer: &mut RequestWriter) -> io::Result<()> {
+		self.leave(writer).await?;
+
+		self.result = Some(DropResult {
+			accepted: false,
+			action: None,
+		});
+
+		Ok(())
+	}
+
+	/// Gives up ownership of the drag's selection and destroys its window.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		self.owner.release(writer).await
+	}
+}
+
+/// Returns the child of `window` containing `position`, relative to `root`,
+/// if any.
+async fn child_at(
+	connection: &mut impl RoundTrip, root: Window, window: Window, position: Point,
+) -> Result<Option<Window>, ReplyError> {
+	let request = TranslateCoordinates {
+		source: root,
+		destination: window,
+		point: position,
+	};
+
+	Ok(request::reply_to(connection, &request).await?.child)
+}
+
+/// Returns the version of XDND `window` supports, if it accepts drops with
+/// a version drags are sent with.
+async fn xdnd_version(
+	connection: &mut impl RoundTrip, window: Window, aware: Atom,
+) -> Result<Option<u8>, ReplyError> {
+	let version = property::get_property(connection, window, aware, Atom::ATOM)
+		.await?
+		.and_then(|value| value.to_u32s())
+		.and_then(|values| values.first().copied());
+
+	Ok(version
+		.filter(|&version| version >= u32::from(MIN_XDND_VERSION))
+		.map(|version| version.min(u32::from(XDND_VERSION)) as u8))
+}
+
+/// Finds the window containing `position` which accepts drops, and the
+/// version of XDND it supports.
+async fn find_target(
+	connection: &mut impl RoundTrip, position: Point, aware: Atom,
+) -> Result<Option<(Window, u8)>, ReplyError> {
+	let root = connection.writer().root_window();
+	let mut window = root;
+
+	// Descend through the windows containing `position` until one accepts
+	// drops: window managers put top-level windows inside frames.
+	while let Some(child) = child_at(connection, root, window, position).await? {
+		if let Some(version) = xdnd_version(connection, child, aware).await? {
+			return Ok(Some((child, version)));
+		}
+
+		window = child;
+	}
+
+	Ok(None)
+}
+
+/// Starts a drag of `values`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::start_drag`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn start_drag(
+	connection: &mut impl RoundTrip, values: Vec<(Atom, Property)>, time: Timestamp,
+) -> Result<Option<DragSource>, SelectionError> {
+	let atoms = Atoms::intern(connection).await?;
+
+	let Some(mut owner) =
+		selection::take_selection(connection, atoms.selection, None, time).await?
+	else {
+		return Ok(None);
+	};
+
+	let types: Vec<Atom> = values.iter().map(|(r#type, _)| *r#type).collect();
+
+	if types.len() > ENTER_TYPES {
+		owner
+			.window()
+			.set_property(
+				connection.writer(),
+				atoms.type_list,
+				&Property::atoms(&types),
+				PropertyMode::Replace,
+			)
+			.await?;
+	}
+
+	for (r#type, value) in values {
+		owner.set_value(r#type, value);
+	}
+
+	Ok(Some(DragSource {
+		owner,
+		atoms,
+		types,
+
+		target: None,
+		dropped: None,
+		result: None,
+	}))
+}
+
+impl Client {
+	/// Marks `window` as accepting drops, returning a [`DropTarget`] which
+	/// decodes the XDND messages it is sent.
+	///
+	/// Only top-level windows are sent drags.
+	pub async fn accept_drops(&mut self, window: Window) -> Result<DropTarget, ReplyError> {
+		accept_drops(self, window).await
+	}
+
+	/// Starts a drag of `values`, each the dragged data converted to its
+	/// type, such as `text/uri-list`.
+	///
+	/// `time` should be the timestamp of the event which started the drag.
+	/// Returns `None` if the drag's selection couldn't be taken because
+	/// another drag started after `time`.
+	///
+	/// See the [module docs] for how to drive the returned [`DragSource`].
+	///
+	/// [module docs]: crate::dnd
+	pub async fn start_drag(
+		&mut self, values: Vec<(Atom, Property)>, time: Timestamp,
+	) -> Result<Option<DragSource>, SelectionError> {
+		start_drag(self, values, time).await
+	}
+}
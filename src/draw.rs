@@ -0,0 +1,435 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Drawing lines, rectangles, polygons and arcs on windows and pixmaps.
+//!
+//! Drawing is done on a [`Drawable`] with a [graphics context], which gives
+//! the color, line width and other settings to draw with:
+//!
+//! ```no_run
+//! # use xrs::{draw::Drawable, gc::GcValues, geometry::{Point, Rectangle, Size}, Client};
+//! # async fn example(client: &mut Client, window: xrs::window::Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let gc = client.create_gc(window.id(), &GcValues::new().foreground(0x0000ff)).await?;
+//! let drawable = Drawable::from(window);
+//!
+//! let (_, writer) = client.split_mut();
+//! drawable
+//!     .draw_rectangles(writer, &gc, &[Rectangle::new(Point::new(10, 10), Size::new(80, 40))])
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! None of these requests have replies; errors are reported as events.
+//!
+//! [graphics context]: crate::gc
+
+use crate::{
+	gc::Gc,
+	geometry::{Point, Rectangle, Size},
+	raw::assert_wire_size,
+	request::{self, NoReply, Request},
+	window::Window,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::io;
+
+/// The major opcode of the `ClearArea` request.
+const CLEAR_AREA: u8 = 61;
+/// The major opcode of the `CopyArea` request.
+const COPY_AREA: u8 = 62;
+/// The major opcode of the `PolyLine` request.
+const POLY_LINE: u8 = 65;
+/// The major opcode of the `PolyRectangle` request.
+const POLY_RECTANGLE: u8 = 67;
+/// The major opcode of the `PolyArc` request.
+const POLY_ARC: u8 = 68;
+/// The major opcode of the `FillPoly` request.
+const FILL_POLY: u8 = 69;
+
+/// A handle to something which can be drawn on: a window or a pixmap.
+///
+/// Like [`Window`], a `Drawable` is only an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Drawable(pub u32);
+
+impl From<Window> for Drawable {
+	fn from(window: Window) -> Self {
+		Self(window.id())
+	}
+}
+
+/// How the points of a line or polygon are given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CoordinateMode {
+	/// Every point is relative to the drawable's origin.
+	#[default]
+	Origin,
+	/// Every point after the first is relative to the point before it.
+	Previous,
+}
+
+/// What the X server may assume about a polygon's shape when filling it.
+///
+/// Assuming a simpler shape can make filling faster, but a polygon which
+/// isn't that shape is filled incorrectly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PolygonShape {
+	/// The polygon's edges may intersect.
+	#[default]
+	Complex,
+	/// The polygon's edges don't intersect, but it may be concave.
+	Nonconvex,
+	/// The polygon is convex.
+	Convex,
+}
+
+/// An elliptical arc, given by the rectangle bounding its ellipse and the
+/// angles it spans.
+///
+/// Angles are in 64ths of a degree, counterclockwise from three o'clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Arc {
+	/// The rectangle bounding the arc's ellipse.
+	pub bounds: Rectangle,
+
+	/// The angle at which the arc starts.
+	pub start: i16,
+	/// The angle the arc spans from `start`; negative angles go clockwise.
+	pub extent: i16,
+}
+
+impl Arc {
+	/// A whole ellipse's worth of 64ths of a degree.
+	pub const FULL_CIRCLE: i16 = 360 * 64;
+
+	/// Creates an arc of the ellipse bounded by `bounds`.
+	pub const fn new(bounds: Rectangle, start: i16, extent: i16) -> Self {
+		Self {
+			bounds,
+			start,
+			extent,
+		}
+	}
+
+	/// Creates an arc spanning the whole ellipse bounded by `bounds`.
+	pub const fn ellipse(bounds: Rectangle) -> Self {
+		Self::new(bounds, 0, Self::FULL_CIRCLE)
+	}
+
+	/// Writes the arc as it is encoded in requests.
+	pub fn write_to(&self, buf: &mut impl BufMut) {
+		self.bounds.write_to(buf);
+		buf.put_i16(self.start);
+		buf.put_i16(self.extent);
+	}
+}
+
+/// The `PolyLine` request.
+///
+/// See [`Drawable::draw_lines`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolyLine<'a> {
+	pub mode: CoordinateMode,
+	pub drawable: Drawable,
+	pub gc: u32,
+	pub points: &'a [Point],
+}
+
+impl Request for PolyLine<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "PolyLine";
+	const OPCODE: u8 = POLY_LINE;
+
+	fn metabyte(&self) -> u8 {
+		self.mode as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+
+		for point in self.points {
+			point.write_to(body);
+		}
+	}
+}
+
+// Followed by the points.
+assert_wire_size!("PolyLine request": [u8, u8, u16, u32, u32] == 12);
+
+/// The `PolyRectangle` request.
+///
+/// See [`Drawable::draw_rectangles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolyRectangle<'a> {
+	pub drawable: Drawable,
+	pub gc: u32,
+	pub rectangles: &'a [Rectangle],
+}
+
+impl Request for PolyRectangle<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "PolyRectangle";
+	const OPCODE: u8 = POLY_RECTANGLE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+
+		for rectangle in self.rectangles {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+// Followed by the rectangles.
+assert_wire_size!("PolyRectangle request": [u8, [u8; 1], u16, u32, u32] == 12);
+
+/// The `PolyArc` request.
+///
+/// See [`Drawable::draw_arcs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolyArc<'a> {
+	pub drawable: Drawable,
+	pub gc: u32,
+	pub arcs: &'a [Arc],
+}
+
+impl Request for PolyArc<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "PolyArc";
+	const OPCODE: u8 = POLY_ARC;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+
+		for arc in self.arcs {
+			arc.write_to(body);
+		}
+	}
+}
+
+// Followed by the arcs.
+assert_wire_size!("PolyArc request": [u8, [u8; 1], u16, u32, u32] == 12);
+
+/// The `FillPoly` request.
+///
+/// See [`Drawable::fill_polygon`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FillPoly<'a> {
+	pub drawable: Drawable,
+	pub gc: u32,
+	pub shape: PolygonShape,
+	pub mode: CoordinateMode,
+	pub points: &'a [Point],
+}
+
+impl Request for FillPoly<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "FillPoly";
+	const OPCODE: u8 = FILL_POLY;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+		body.put_u8(self.shape as u8);
+		body.put_u8(self.mode as u8);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+
+		for point in self.points {
+			point.write_to(body);
+		}
+	}
+}
+
+// Followed by the points.
+assert_wire_size!("FillPoly request": [u8, [u8; 1], u16, u32, u32, u8, u8, [u8; 2]] == 16);
+
+/// The `CopyArea` request.
+///
+/// See [`Drawable::copy_area`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CopyArea {
+	pub source: Drawable,
+	pub destination: Drawable,
+	pub gc: u32,
+	/// The area of `source` to copy.
+	pub area: Rectangle,
+	/// Where on `destination` to copy it to.
+	pub position: Point,
+}
+
+impl Request for CopyArea {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CopyArea";
+	const OPCODE: u8 = COPY_AREA;
+	const LENGTH: Option<u16> = Some(7);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.source.id());
+		body.put_u32(self.destination.id());
+		body.put_u32(self.gc);
+		self.area.position().write_to(body);
+		self.position.write_to(body);
+		body.put_u16(self.area.width);
+		body.put_u16(self.area.height);
+	}
+}
+
+assert_wire_size!(CopyArea: [u8, [u8; 1], u16, u32, u32, u32, Point, Point, Size]);
+
+/// The `ClearArea` request.
+///
+/// See [`Window::clear_area`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClearArea {
+	/// Whether `Expose` events are generated for the cleared area.
+	pub exposures: bool,
+	pub window: Window,
+	pub area: Rectangle,
+}
+
+impl Request for ClearArea {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ClearArea";
+	const OPCODE: u8 = CLEAR_AREA;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn metabyte(&self) -> u8 {
+		self.exposures.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		self.area.write_to(body);
+	}
+}
+
+assert_wire_size!(ClearArea: [u8, [u8; 1], u16, Window, Rectangle]);
+
+impl Drawable {
+	/// The drawable's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+
+	/// Draws lines joining each of `points` to the next.
+	///
+	/// Where the lines meet is drawn with the [join style], and if the last
+	/// point is the first, the ends meet with the join style too.
+	///
+	/// [join style]: crate::gc::GcValues::join_style
+	pub async fn draw_lines(
+		self, writer: &mut RequestWriter, gc: &Gc, mode: CoordinateMode, points: &[Point],
+	) -> io::Result<()> {
+		let request = PolyLine {
+			mode,
+			drawable: self,
+			gc: gc.id(),
+			points,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Draws the outlines of `rectangles`.
+	///
+	/// Each outline covers the rectangle's right and bottom edges, so it is
+	/// one pixel wider and taller than the rectangle.
+	pub async fn draw_rectangles(
+		self, writer: &mut RequestWriter, gc: &Gc, rectangles: &[Rectangle],
+	) -> io::Result<()> {
+		let request = PolyRectangle {
+			drawable: self,
+			gc: gc.id(),
+			rectangles,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Fills the polygon with the corners `points`.
+	///
+	/// The polygon is closed automatically if the last point isn't the
+	/// first.
+	pub async fn fill_polygon(
+		self, writer: &mut RequestWriter, gc: &Gc, shape: PolygonShape, mode: CoordinateMode,
+		points: &[Point],
+	) -> io::Result<()> {
+		let request = FillPoly {
+			drawable: self,
+			gc: gc.id(),
+			shape,
+			mode,
+			points,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Draws `arcs`.
+	pub async fn draw_arcs(
+		self, writer: &mut RequestWriter, gc: &Gc, arcs: &[Arc],
+	) -> io::Result<()> {
+		let request = PolyArc {
+			drawable: self,
+			gc: gc.id(),
+			arcs,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Copies the `area` of this drawable to `destination` at `position`.
+	///
+	/// Both drawables must have the same root window and depth. Parts of
+	/// `area` which are obscured or outside of this drawable are exposed on
+	/// `destination` if `gc` has [graphics exposures] enabled, rather than
+	/// being copied.
+	///
+	/// [graphics exposures]: crate::gc::GcValues::graphics_exposures
+	pub async fn copy_area(
+		self, writer: &mut RequestWriter, gc: &Gc, area: Rectangle, destination: Drawable,
+		position: Point,
+	) -> io::Result<()> {
+		let request = CopyArea {
+			source: self,
+			destination,
+			gc: gc.id(),
+			area,
+			position,
+		};
+
+		request::send_core(writer, &request).await
+	}
+}
+
+impl Window {
+	/// Clears `area` of the window to its background.
+	///
+	/// A `width` or `height` of `0` extends the area to the window's right or
+	/// bottom edge. If `exposures` is `true`, `Expose` events are generated
+	/// for the cleared area.
+	pub async fn clear_area(
+		self, writer: &mut RequestWriter, area: Rectangle, exposures: bool,
+	) -> io::Result<()> {
+		let request = ClearArea {
+			exposures,
+			window: self,
+			area,
+		};
+
+		request::send_core(writer, &request).await
+	}
+}
@@ -0,0 +1,576 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The DRI3 extension, which shares GPU buffers with the X server.
+//!
+//! DRI3 passes file descriptors over the connection instead of copying data
+//! through it: [`Client::dri3_open`] returns a file descriptor for the GPU
+//! device the X server renders with, buffers allocated on that device are
+//! turned into pixmaps with [`Client::pixmap_from_buffer`], and pixmaps are
+//! turned back into buffers with [`Client::buffer_from_pixmap`]:
+//!
+//! ```no_run
+//! # use xrs::{draw::Drawable, Client};
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let root = Drawable::from(client.root_window());
+//!
+//! let device = client.dri3_open(root, 0).await?;
+//! // Allocate a buffer on `device` with a graphics API and render to it...
+//! # let buffer: xrs::dri3::Dri3Buffer = todo!();
+//!
+//! let pixmap = client.pixmap_from_buffer(root, buffer).await?;
+//! # let _ = (device, pixmap);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! File descriptors can only be passed over Unix domain sockets, so DRI3
+//! requests return an [`Unsupported`] error over TCP.
+//!
+//! [`Unsupported`]: io::ErrorKind::Unsupported
+
+use crate::{
+	client::{reply_with_fds, RoundTrip},
+	draw::Drawable,
+	extension,
+	raw::{assert_wire_size, X11Frame},
+	request::{NoReply, Reply, Request},
+	xid,
+	xid::XidError,
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+use std::{
+	io,
+	os::fd::{AsFd, OwnedFd},
+};
+
+/// The name of the DRI3 extension.
+const DRI3: &str = "DRI3";
+
+/// The version of DRI3 which X.RS implements.
+const VERSION: (u32, u32) = (1, 0);
+
+/// The minor opcode of the `DRI3QueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `DRI3Open` request.
+const OPEN: u8 = 1;
+/// The minor opcode of the `DRI3PixmapFromBuffer` request.
+const PIXMAP_FROM_BUFFER: u8 = 2;
+/// The minor opcode of the `DRI3BufferFromPixmap` request.
+const BUFFER_FROM_PIXMAP: u8 = 3;
+/// The minor opcode of the `DRI3FenceFromFD` request.
+const FENCE_FROM_FD: u8 = 4;
+/// The minor opcode of the `DRI3FDFromFence` request.
+const FD_FROM_FENCE: u8 = 5;
+
+/// A GPU buffer shared with the X server as a file descriptor, such as a
+/// DMA-BUF.
+#[derive(Debug)]
+pub struct Dri3Buffer {
+	/// The file descriptor of the buffer.
+	pub fd: OwnedFd,
+	/// The size of the buffer in bytes.
+	pub size: u32,
+
+	/// The width of the image in the buffer, in pixels.
+	pub width: u16,
+	/// The height of the image in the buffer, in pixels.
+	pub height: u16,
+	/// The number of bytes between the start of each row of the image.
+	pub stride: u16,
+
+	/// The depth of the image.
+	pub depth: u8,
+	/// The number of bits used for each pixel of the image.
+	pub bits_per_pixel: u8,
+}
+
+/// Returns the DRI3 extension's major opcode, or an [`Unsupported`] error if
+/// the X server doesn't support DRI3 or file descriptors can't be passed to
+/// it.
+///
+/// [`Unsupported`]: io::ErrorKind::Unsupported
+async fn dri3_opcode(connection: &mut impl RoundTrip) -> Result<u8, ReplyError> {
+	if !connection.writer().can_pass_fds() {
+		return Err(ReplyError::Io(io::Error::new(
+			io::ErrorKind::Unsupported,
+			"DRI3 requires file descriptors to be passed over a Unix domain socket",
+		)));
+	}
+
+	Ok(extension::require_extension(connection, DRI3)
+		.await?
+		.major_opcode)
+}
+
+/// The DRI3 extension's `DRI3QueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "DRI3QueryVersion";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of DRI3 supported by both the X server and the client.
+///
+/// This is the reply to a `DRI3QueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("DRI3QueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[ma0, ma1, ma2, ma3, mi0, mi1, mi2, mi3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([ma0, ma1, ma2, ma3]),
+			minor_version: u32::from_be_bytes([mi0, mi1, mi2, mi3]),
+		})
+	}
+}
+
+/// The reply to a DRI3 request whose only result is file descriptors, which
+/// are received alongside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FdReply {
+	/// The number of file descriptors sent with the reply.
+	pub nfd: u8,
+}
+
+assert_wire_size!("DRI3Open reply": [u8, u8, u16, u32, [u8; 24]] == 32);
+assert_wire_size!("DRI3FDFromFence reply": [u8, u8, u16, u32, [u8; 24]] == 32);
+
+impl Reply for FdReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { metabyte, .. } = frame else {
+			return None;
+		};
+
+		Some(Self { nfd: *metabyte })
+	}
+}
+
+/// The DRI3 extension's `DRI3Open` request.
+///
+/// See [`Client::dri3_open`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Open {
+	pub drawable: Drawable,
+	/// The RandR provider to open the device of, or `0` for the one which
+	/// renders `drawable`.
+	pub provider: u32,
+}
+
+impl Request for Open {
+	type Reply = FdReply;
+
+	const NAME: &'static str = "DRI3Open";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = OPEN;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.provider);
+	}
+}
+
+assert_wire_size!(Open: [u8, u8, u16, u32, u32]);
+
+/// The DRI3 extension's `DRI3PixmapFromBuffer` request.
+///
+/// The buffer's file descriptor is sent alongside the request.
+///
+/// See [`Client::pixmap_from_buffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixmapFromBuffer {
+	/// The ID of the new pixmap.
+	pub pixmap: u32,
+	pub drawable: Drawable,
+	pub size: u32,
+	pub width: u16,
+	pub height: u16,
+	pub stride: u16,
+	pub depth: u8,
+	pub bits_per_pixel: u8,
+}
+
+impl Request for PixmapFromBuffer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DRI3PixmapFromBuffer";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = PIXMAP_FROM_BUFFER;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.pixmap);
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.size);
+		body.put_u16(self.width);
+		body.put_u16(self.height);
+		body.put_u16(self.stride);
+		body.put_u8(self.depth);
+		body.put_u8(self.bits_per_pixel);
+	}
+}
+
+assert_wire_size!(PixmapFromBuffer: [u8, u8, u16, u32, u32, u32, u16, u16, u16, u8, u8]);
+
+/// The DRI3 extension's `DRI3BufferFromPixmap` request.
+///
+/// See [`Client::buffer_from_pixmap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferFromPixmap(pub u32);
+
+impl Request for BufferFromPixmap {
+	type Reply = BufferFromPixmapReply;
+
+	const NAME: &'static str = "DRI3BufferFromPixmap";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = BUFFER_FROM_PIXMAP;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(BufferFromPixmap: [u8, u8, u16, u32]);
+
+/// The layout of the buffer backing a pixmap, whose file descriptor is
+/// received alongside it.
+///
+/// This is the reply to a `DRI3BufferFromPixmap` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferFromPixmapReply {
+	pub size: u32,
+	pub width: u16,
+	pub height: u16,
+	pub stride: u16,
+	pub depth: u8,
+	pub bits_per_pixel: u8,
+}
+
+assert_wire_size!(
+	"DRI3BufferFromPixmap reply": [u8, u8, u16, u32, u32, u16, u16, u16, u8, u8, [u8; 12]] == 32
+);
+
+impl Reply for BufferFromPixmapReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[s0, s1, s2, s3, w0, w1, h0, h1, st0, st1, depth, bits_per_pixel] = chunk.get(..12)?
+		else {
+			return None;
+		};
+
+		Some(Self {
+			size: u32::from_be_bytes([s0, s1, s2, s3]),
+			width: u16::from_be_bytes([w0, w1]),
+			height: u16::from_be_bytes([h0, h1]),
+			stride: u16::from_be_bytes([st0, st1]),
+			depth,
+			bits_per_pixel,
+		})
+	}
+}
+
+/// The DRI3 extension's `DRI3FenceFromFD` request.
+///
+/// The fence's file descriptor is sent alongside the request.
+///
+/// See [`Client::fence_from_fd`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FenceFromFd {
+	pub drawable: Drawable,
+	/// The ID of the new SYNC fence.
+	pub fence: u32,
+	pub initially_triggered: bool,
+}
+
+impl Request for FenceFromFd {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DRI3FenceFromFD";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = FENCE_FROM_FD;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.fence);
+		body.put_u8(self.initially_triggered.into());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(FenceFromFd: [u8, u8, u16, u32, u32, u8, [u8; 3]]);
+
+/// The DRI3 extension's `DRI3FDFromFence` request.
+///
+/// See [`Client::fd_from_fence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FdFromFence {
+	pub drawable: Drawable,
+	pub fence: u32,
+}
+
+impl Request for FdFromFence {
+	type Reply = FdReply;
+
+	const NAME: &'static str = "DRI3FDFromFence";
+	const EXTENSION: Option<&'static str> = Some(DRI3);
+	const OPCODE: u8 = FD_FROM_FENCE;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.fence);
+	}
+}
+
+assert_wire_size!(FdFromFence: [u8, u8, u16, u32, u32]);
+
+/// Sends the DRI3 `request`, whose reply is sent with one file descriptor,
+/// returning the reply and that file descriptor.
+async fn dri3_reply_with_fd<R: Request>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<(R::Reply, OwnedFd), ReplyError>
+where
+	R::Reply: Reply,
+{
+	let dri3 = dri3_opcode(connection).await?;
+
+	let (frame, fds) = reply_with_fds(connection, request.to_raw(dri3)).await?;
+
+	let reply = R::Reply::from_reply(&frame).ok_or_else(|| ReplyError::malformed(R::NAME))?;
+	let Some(fd) = fds.into_iter().next() else {
+		return Err(ReplyError::malformed(R::NAME));
+	};
+
+	Ok((reply, fd))
+}
+
+/// Queries the version of DRI3 supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::dri3_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn dri3_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	dri3_opcode(connection).await?;
+
+	let request = QueryVersion {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+
+	extension::negotiate_version(connection, DRI3, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await
+}
+
+/// Opens the GPU device the X server renders `drawable` with.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::dri3_open`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn dri3_open(
+	connection: &mut impl RoundTrip, drawable: Drawable, provider: u32,
+) -> Result<OwnedFd, ReplyError> {
+	let (_, fd) = dri3_reply_with_fd(connection, &Open { drawable, provider }).await?;
+
+	Ok(fd)
+}
+
+/// Creates a pixmap which is backed by `buffer`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::pixmap_from_buffer`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn pixmap_from_buffer(
+	connection: &mut impl RoundTrip, drawable: Drawable, buffer: Dri3Buffer,
+) -> Result<u32, XidError> {
+	let dri3 = dri3_opcode(connection).await?;
+	let pixmap = xid::generate_id(connection).await?;
+
+	let request = PixmapFromBuffer {
+		pixmap,
+		drawable,
+		size: buffer.size,
+		width: buffer.width,
+		height: buffer.height,
+		stride: buffer.stride,
+		depth: buffer.depth,
+		bits_per_pixel: buffer.bits_per_pixel,
+	};
+
+	// The X server keeps its own copy of the file descriptor, so ours is
+	// closed once it has been sent.
+	connection
+		.writer()
+		.send_raw_with_fds(request.to_raw(dri3), &[buffer.fd.as_fd()])
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(pixmap)
+}
+
+/// Exports the GPU buffer backing `pixmap`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::buffer_from_pixmap`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn buffer_from_pixmap(
+	connection: &mut impl RoundTrip, pixmap: u32,
+) -> Result<Dri3Buffer, ReplyError> {
+	let (reply, fd) = dri3_reply_with_fd(connection, &BufferFromPixmap(pixmap)).await?;
+
+	Ok(Dri3Buffer {
+		fd,
+		size: reply.size,
+
+		width: reply.width,
+		height: reply.height,
+		stride: reply.stride,
+
+		depth: reply.depth,
+		bits_per_pixel: reply.bits_per_pixel,
+	})
+}
+
+/// Creates a SYNC fence from the file descriptor of a shared-memory fence.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::fence_from_fd`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn fence_from_fd(
+	connection: &mut impl RoundTrip, drawable: Drawable, fd: OwnedFd, initially_triggered: bool,
+) -> Result<u32, XidError> {
+	let dri3 = dri3_opcode(connection).await?;
+	let fence = xid::generate_id(connection).await?;
+
+	let request = FenceFromFd {
+		drawable,
+		fence,
+		initially_triggered,
+	};
+
+	connection
+		.writer()
+		.send_raw_with_fds(request.to_raw(dri3), &[fd.as_fd()])
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(fence)
+}
+
+/// Exports the shared-memory fence backing the SYNC fence `fence`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::fd_from_fence`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn fd_from_fence(
+	connection: &mut impl RoundTrip, drawable: Drawable, fence: u32,
+) -> Result<OwnedFd, ReplyError> {
+	let (_, fd) = dri3_reply_with_fd(connection, &FdFromFence { drawable, fence }).await?;
+
+	Ok(fd)
+}
+
+impl Client {
+	/// Queries the version of DRI3 supported by the X server, as its major
+	/// and minor version.
+	///
+	/// The version is negotiated once, so only the first query makes a round
+	/// trip to the X server.
+	///
+	/// Returns an [`Unsupported`] error if the X server doesn't support DRI3
+	/// or the connection isn't over a Unix domain socket.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn dri3_version(&mut self) -> Result<(u32, u32), ReplyError> {
+		dri3_version(self).await
+	}
+
+	/// Opens the GPU device the X server renders `drawable` with, returning
+	/// its file descriptor.
+	///
+	/// `provider` is a RandR provider, or 0 for the X server's choice.
+	pub async fn dri3_open(
+		&mut self, drawable: Drawable, provider: u32,
+	) -> Result<OwnedFd, ReplyError> {
+		dri3_open(self, drawable, provider).await
+	}
+
+	/// Creates a pixmap on the same screen as `drawable` which is backed by
+	/// `buffer`, returning the pixmap's ID.
+	pub async fn pixmap_from_buffer(
+		&mut self, drawable: Drawable, buffer: Dri3Buffer,
+	) -> Result<u32, XidError> {
+		pixmap_from_buffer(self, drawable, buffer).await
+	}
+
+	/// Exports the GPU buffer backing `pixmap`.
+	pub async fn buffer_from_pixmap(&mut self, pixmap: u32) -> Result<Dri3Buffer, ReplyError> {
+		buffer_from_pixmap(self, pixmap).await
+	}
+
+	/// Creates a SYNC fence on the same screen as `drawable` from `fd`, the
+	/// file descriptor of a shared-memory fence, returning the fence's ID.
+	pub async fn fence_from_fd(
+		&mut self, drawable: Drawable, fd: OwnedFd, initially_triggered: bool,
+	) -> Result<u32, XidError> {
+		fence_from_fd(self, drawable, fd, initially_triggered).await
+	}
+
+	/// Exports the shared-memory fence backing the SYNC fence `fence`,
+	/// returning its file descriptor.
+	pub async fn fd_from_fence(
+		&mut self, drawable: Drawable, fence: u32,
+	) -> Result<OwnedFd, ReplyError> {
+		fd_from_fence(self, drawable, fence).await
+	}
+}
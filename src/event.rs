@@ -0,0 +1,1216 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Decoding core protocol events.
+//!
+//! [`X11Frame::Event`]s only hold an event code and 31 bytes of data.
+//! [`Event::from_frame`] decodes them into typed events, so that their
+//! fields can be read without knowing where they are in the data:
+//!
+//! ```no_run
+//! # use xrs::{event::Event, Client};
+//! # async fn example(client: &mut Client) -> std::io::Result<()> {
+//! while let Some(frame) = client.read_raw_frame().await? {
+//!     match Event::from_frame(&frame) {
+//!         Some(Event::KeyPress(key)) => println!("key {} pressed", key.detail),
+//!         Some(Event::ConfigureNotify(configure)) => println!("{:?}", configure.area),
+//!
+//!         _ => {},
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Events defined by extensions are decoded as [`Event::Extension`], except
+//! for [`X11Frame::GenericEvent`]s, which can be longer than 32 bytes. They
+//! are routed to decoders registered for their extension with
+//! [`GenericEventDecoders`].
+//!
+//! The event [`codes`], and the values of fields which are decoded the same
+//! way without X.RS, such as [`FocusDetail`], are defined in the no_std
+//! `xrs-proto` crate along with the reader for events' fields.
+
+use crate::{
+	atoms::Atom,
+	event_mask::EventMask,
+	geometry::{Point, Rectangle},
+	keyboard::Keycode,
+	raw::{assert_wire_size, HexBytes, X11Frame},
+	request::{NoReply, Request},
+	time::Timestamp,
+	window::{StackMode, Window},
+};
+use bytes::{BufMut, BytesMut};
+use std::{collections::HashMap, fmt, fmt::Formatter, ops::Deref};
+use xrs_proto::{EventFields, SEND_EVENT_MASK};
+
+pub use xrs_proto::{
+	event_codes as codes,
+	ClientMessageData,
+	FocusDetail,
+	FocusMode,
+	MappingRequest,
+	Place,
+	PropertyState,
+	Visibility,
+};
+
+/// The major opcode of the `SendEvent` request.
+const SEND_EVENT: u8 = 25;
+
+/// The fields shared by key, button and motion events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyButtonEvent<Detail> {
+	/// The keycode of key events, the button of button events, or whether
+	/// motion events are hints (`1`) or not (`0`).
+	pub detail: Detail,
+	pub time: Timestamp,
+
+	/// The root window of the screen the event happened on.
+	pub root: Window,
+	/// The window the event is reported relative to.
+	pub event: Window,
+	/// The child of `event` which contains the pointer, if any.
+	pub child: Option<Window>,
+
+	/// The pointer's position relative to `root`.
+	pub root_position: Point,
+	/// The pointer's position relative to `event`, if `same_screen` is
+	/// `true`.
+	pub event_position: Point,
+
+	/// The state of the modifiers and pointer buttons before the event.
+	pub state: u16,
+	/// Whether `event` is on the same screen as `root`.
+	pub same_screen: bool,
+}
+
+/// An `EnterNotify` or `LeaveNotify` event: the pointer crossed into or out
+/// of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CrossingEvent {
+	/// The window's relationship to the crossing.
+	///
+	/// Only the [`Ancestor`], [`Virtual`], [`Inferior`], [`Nonlinear`] and
+	/// [`NonlinearVirtual`] details apply to crossing events.
+	///
+	/// [`Ancestor`]: FocusDetail::Ancestor
+	/// [`Virtual`]: FocusDetail::Virtual
+	/// [`Inferior`]: FocusDetail::Inferior
+	/// [`Nonlinear`]: FocusDetail::Nonlinear
+	/// [`NonlinearVirtual`]: FocusDetail::NonlinearVirtual
+	pub detail: FocusDetail,
+	pub time: Timestamp,
+
+	pub root: Window,
+	pub event: Window,
+	pub child: Option<Window>,
+
+	pub root_position: Point,
+	pub event_position: Point,
+
+	pub state: u16,
+	/// Whether the crossing happened normally or because of a grab.
+	///
+	/// Crossing events are never [`WhileGrabbed`].
+	///
+	/// [`WhileGrabbed`]: FocusMode::WhileGrabbed
+	pub mode: FocusMode,
+
+	pub same_screen: bool,
+	/// Whether `event` is or contains the focus window.
+	pub focus: bool,
+}
+
+/// A `FocusIn` or `FocusOut` event.
+///
+/// Focus changes can be tracked with a `FocusTracker`, if the `focus`
+/// feature is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusEvent {
+	pub detail: FocusDetail,
+	/// The window which gained or lost the focus.
+	pub event: Window,
+	pub mode: FocusMode,
+}
+
+/// An `Expose` event: an area of a window needs to be redrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExposeEvent {
+	pub window: Window,
+	pub area: Rectangle,
+	/// The number of `Expose` events for the window which follow this one.
+	pub count: u16,
+}
+
+/// A `GraphicsExposure` event: an area of a `CopyArea` or `CopyPlane`
+/// request's destination couldn't be copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphicsExposureEvent {
+	pub drawable: u32,
+	pub area: Rectangle,
+	/// The minor opcode of the request, for extension requests.
+	pub minor_opcode: u16,
+	/// The number of `GraphicsExposure` events which follow this one.
+	pub count: u16,
+	pub major_opcode: u8,
+}
+
+/// A `NoExposure` event: all of a `CopyArea` or `CopyPlane` request's
+/// destination was copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoExposureEvent {
+	pub drawable: u32,
+	pub minor_opcode: u16,
+	pub major_opcode: u8,
+}
+
+/// A `VisibilityNotify` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VisibilityEvent {
+	pub window: Window,
+	pub state: Visibility,
+}
+
+/// A `CreateNotify` event: a window was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateEvent {
+	pub parent: Window,
+	pub window: Window,
+	pub area: Rectangle,
+	pub border_width: u16,
+	pub override_redirect: bool,
+}
+
+/// A `DestroyNotify` event: a window was destroyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DestroyEvent {
+	/// The window the event was selected on: `window` or its parent.
+	pub event: Window,
+	pub window: Window,
+}
+
+/// An `UnmapNotify` event: a window was unmapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnmapEvent {
+	pub event: Window,
+	pub window: Window,
+	/// Whether the window was unmapped because its parent was resized and
+	/// its gravity is `Unmap`.
+	pub from_configure: bool,
+}
+
+/// A `MapNotify` event: a window was mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapEvent {
+	pub event: Window,
+	pub window: Window,
+	pub override_redirect: bool,
+}
+
+/// A `MapRequest` event: a client asked for a window to be mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapRequestEvent {
+	pub parent: Window,
+	pub window: Window,
+}
+
+/// A `ReparentNotify` event: a window was moved to a new parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReparentEvent {
+	pub event: Window,
+	pub window: Window,
+	pub parent: Window,
+	/// The window's position relative to its new parent.
+	pub position: Point,
+	pub override_redirect: bool,
+}
+
+/// A `ConfigureNotify` event: a window's position, size, border width or
+/// stacking order changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigureEvent {
+	pub event: Window,
+	pub window: Window,
+	/// The sibling the window is stacked directly above, if any.
+	pub above_sibling: Option<Window>,
+	/// The window's area, relative to its parent.
+	pub area: Rectangle,
+	pub border_width: u16,
+	pub override_redirect: bool,
+}
+
+/// A `ConfigureRequest` event: a client asked for a window to be configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigureRequestEvent {
+	pub stack_mode: StackMode,
+	pub parent: Window,
+	pub window: Window,
+	pub sibling: Option<Window>,
+	pub area: Rectangle,
+	pub border_width: u16,
+	/// Which of the fields were given in the request, as in a
+	/// `ConfigureWindow` request's value mask.
+	pub value_mask: u16,
+}
+
+/// A `GravityNotify` event: a window was moved because its parent was
+/// resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GravityEvent {
+	pub event: Window,
+	pub window: Window,
+	pub position: Point,
+}
+
+/// A `ResizeRequest` event: a client asked for a window to be resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResizeRequestEvent {
+	pub window: Window,
+	pub width: u16,
+	pub height: u16,
+}
+
+/// A `CirculateNotify` or `CirculateRequest` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CirculateEvent {
+	/// The window the event was selected on, or the parent for requests.
+	pub event: Window,
+	pub window: Window,
+	pub place: Place,
+}
+
+/// A `PropertyNotify` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PropertyEvent {
+	pub window: Window,
+	pub atom: Atom,
+	pub time: Timestamp,
+	pub state: PropertyState,
+}
+
+/// A `SelectionClear` event: the client lost ownership of a selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectionClearEvent {
+	pub time: Timestamp,
+	pub owner: Window,
+	pub selection: Atom,
+}
+
+/// A `SelectionRequest` event: a client asked the selection's owner to
+/// convert it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectionRequestEvent {
+	pub time: Timestamp,
+	pub owner: Window,
+	pub requestor: Window,
+	pub selection: Atom,
+	pub target: Atom,
+	/// The property to store the conversion in, or [`Atom::NONE`] from
+	/// obsolete clients.
+	pub property: Atom,
+}
+
+/// A `SelectionNotify` event: a selection was converted, or couldn't be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectionNotifyEvent {
+	pub time: Timestamp,
+	pub requestor: Window,
+	pub selection: Atom,
+	pub target: Atom,
+	/// The property the conversion was stored in, or [`Atom::NONE`] if it
+	/// failed.
+	pub property: Atom,
+}
+
+/// A `ColormapNotify` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColormapEvent {
+	pub window: Window,
+	/// The window's colormap, or `0` (`None`).
+	pub colormap: u32,
+	/// Whether the window's colormap was changed, rather than installed or
+	/// uninstalled.
+	pub new: bool,
+	/// Whether the colormap is installed.
+	pub installed: bool,
+}
+
+/// A `ClientMessage` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientMessageEvent {
+	pub window: Window,
+	/// What the message means to the receiving client.
+	pub message_type: Atom,
+	pub data: ClientMessageData,
+}
+
+impl SelectionNotifyEvent {
+	/// Returns the event as it is sent in a [`SendEvent`] request.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		let mut event = [0; 32];
+		let mut buf = &mut event[..];
+
+		buf.put_u8(codes::SELECTION_NOTIFY);
+		// 1 unused byte, and the sequence number, which is filled in by the
+		// X server.
+		buf.put_bytes(0, 3);
+		buf.put_u32(self.time.0);
+		buf.put_u32(self.requestor.0);
+		buf.put_u32(self.selection.0);
+		buf.put_u32(self.target.0);
+		buf.put_u32(self.property.0);
+
+		event
+	}
+}
+
+impl ClientMessageEvent {
+	/// Returns the event as it is sent in a [`SendEvent`] request.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		let mut event = [0; 32];
+		let mut buf = &mut event[..];
+
+		buf.put_u8(codes::CLIENT_MESSAGE);
+		buf.put_u8(self.data.format());
+		// The sequence number, which is filled in by the X server.
+		buf.put_u16(0);
+		buf.put_u32(self.window.0);
+		buf.put_u32(self.message_type.0);
+
+		match self.data {
+			ClientMessageData::U8(data) => buf.put_slice(&data),
+			ClientMessageData::U16(data) => data.iter().for_each(|&value| buf.put_u16(value)),
+			ClientMessageData::U32(data) => data.iter().for_each(|&value| buf.put_u32(value)),
+		}
+
+		event
+	}
+}
+
+/// The `SendEvent` request, which sends an event to a window's clients.
+///
+/// The event's code has its most significant bit set by the X server, so
+/// that receivers can tell it was sent with `SendEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SendEvent {
+	/// Whether the event propagates to the window's ancestors if no client
+	/// has selected `event_mask` on it.
+	pub propagate: bool,
+	pub destination: Window,
+	/// The events the event is sent as, or an empty mask to send it to the
+	/// client which created `destination`.
+	pub event_mask: EventMask,
+	/// The event, as given by methods such as [`ClientMessageEvent::to_bytes`].
+	pub event: [u8; 32],
+}
+
+impl Request for SendEvent {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SendEvent";
+	const OPCODE: u8 = SEND_EVENT;
+	const LENGTH: Option<u16> = Some(11);
+
+	fn metabyte(&self) -> u8 {
+		self.propagate.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.destination.0);
+		body.put_u32(self.event_mask.0);
+		body.put_slice(&self.event);
+	}
+}
+
+assert_wire_size!(SendEvent: [u8, [u8; 1], u16, Window, u32, [u8; 32]]);
+
+/// A `MappingNotify` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MappingEvent {
+	pub request: MappingRequest,
+	pub first_keycode: Keycode,
+	pub count: u8,
+}
+
+/// A decoded event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+	KeyPress(KeyButtonEvent<Keycode>),
+	KeyRelease(KeyButtonEvent<Keycode>),
+	ButtonPress(KeyButtonEvent<u8>),
+	ButtonRelease(KeyButtonEvent<u8>),
+	MotionNotify(KeyButtonEvent<u8>),
+
+	EnterNotify(CrossingEvent),
+	LeaveNotify(CrossingEvent),
+	FocusIn(FocusEvent),
+	FocusOut(FocusEvent),
+	/// The state of every key, as a bit vector of keycodes 8 to 255, sent
+	/// after `EnterNotify` and `FocusIn` events if selected.
+	KeymapNotify([u8; 31]),
+
+	Expose(ExposeEvent),
+	GraphicsExposure(GraphicsExposureEvent),
+	NoExposure(NoExposureEvent),
+	VisibilityNotify(VisibilityEvent),
+
+	CreateNotify(CreateEvent),
+	DestroyNotify(DestroyEvent),
+	UnmapNotify(UnmapEvent),
+	MapNotify(MapEvent),
+	MapRequest(MapRequestEvent),
+	ReparentNotify(ReparentEvent),
+	ConfigureNotify(ConfigureEvent),
+	ConfigureRequest(ConfigureRequestEvent),
+	GravityNotify(GravityEvent),
+	ResizeRequest(ResizeRequestEvent),
+	CirculateNotify(CirculateEvent),
+	CirculateRequest(CirculateEvent),
+
+	PropertyNotify(PropertyEvent),
+	SelectionClear(SelectionClearEvent),
+	SelectionRequest(SelectionRequestEvent),
+	SelectionNotify(SelectionNotifyEvent),
+	ColormapNotify(ColormapEvent),
+	ClientMessage(ClientMessageEvent),
+	MappingNotify(MappingEvent),
+
+	/// An event defined by an extension, identified by its `code`.
+	///
+	/// The extension's [first event code] is needed to tell which of its
+	/// events this is.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	Extension {
+		code: u8,
+		data: [u8; 31],
+	},
+}
+
+/// Reads the fields of core events which have X.RS types, such as windows,
+/// as well as those read by [`EventFields`].
+struct Fields<'data>(EventFields<'data>);
+
+impl<'data> Deref for Fields<'data> {
+	type Target = EventFields<'data>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl Fields<'_> {
+	fn window(&self, offset: usize) -> Window {
+		Window(self.u32(offset))
+	}
+
+	/// Reads a window which may be `None`.
+	fn optional_window(&self, offset: usize) -> Option<Window> {
+		match self.u32(offset) {
+			0 => None,
+			window => Some(Window(window)),
+		}
+	}
+
+	fn atom(&self, offset: usize) -> Atom {
+		Atom(self.u32(offset))
+	}
+
+	fn time(&self, offset: usize) -> Timestamp {
+		Timestamp(self.u32(offset))
+	}
+
+	fn point(&self, offset: usize) -> Point {
+		Point::new(self.i16(offset), self.i16(offset + 2))
+	}
+
+	fn rectangle(&self, offset: usize) -> Rectangle {
+		Rectangle {
+			x: self.i16(offset),
+			y: self.i16(offset + 2),
+			width: self.u16(offset + 4),
+			height: self.u16(offset + 6),
+		}
+	}
+
+	fn input<Detail>(&self, detail: Detail) -> KeyButtonEvent<Detail> {
+		KeyButtonEvent {
+			detail,
+			time: self.time(4),
+
+			root: self.window(8),
+			event: self.window(12),
+			child: self.optional_window(16),
+
+			root_position: self.point(20),
+			event_position: self.point(24),
+
+			state: self.u16(28),
+			same_screen: self.bool(30),
+		}
+	}
+
+	fn crossing(&self) -> Option<CrossingEvent> {
+		Some(CrossingEvent {
+			detail: FocusDetail::from_value(self.u8(1))?,
+			time: self.time(4),
+
+			root: self.window(8),
+			event: self.window(12),
+			child: self.optional_window(16),
+
+			root_position: self.point(20),
+			event_position: self.point(24),
+
+			state: self.u16(28),
+			mode: FocusMode::from_value(self.u8(30))?,
+
+			same_screen: self.u8(31) & 0b10 != 0,
+			focus: self.u8(31) & 0b01 != 0,
+		})
+	}
+
+	fn focus(&self) -> Option<FocusEvent> {
+		Some(FocusEvent {
+			detail: FocusDetail::from_value(self.u8(1))?,
+			event: self.window(4),
+			mode: FocusMode::from_value(self.u8(8))?,
+		})
+	}
+
+	fn circulate(&self) -> Option<CirculateEvent> {
+		Some(CirculateEvent {
+			event: self.window(4),
+			window: self.window(8),
+			place: Place::from_value(self.u8(16))?,
+		})
+	}
+}
+
+// The layouts of the core events, as given by the protocol: each is 32
+// bytes, starting with its code.
+assert_wire_size!("KeyPress": [
+	u8, Keycode, u16, Timestamp, Window, Window, Window, Point, Point, u16, bool, [u8; 1],
+] == 32);
+assert_wire_size!("ButtonPress": [
+	u8, u8, u16, Timestamp, Window, Window, Window, Point, Point, u16, bool, [u8; 1],
+] == 32);
+assert_wire_size!("EnterNotify": [
+	u8, u8, u16, Timestamp, Window, Window, Window, Point, Point, u16, u8, u8,
+] == 32);
+assert_wire_size!("FocusIn": [u8, u8, u16, Window, u8, [u8; 23]] == 32);
+assert_wire_size!("KeymapNotify": [u8, [u8; 31]] == 32);
+assert_wire_size!("Expose": [u8, [u8; 1], u16, Window, Rectangle, u16, [u8; 14]] == 32);
+assert_wire_size!("GraphicsExposure": [
+	u8, [u8; 1], u16, u32, Rectangle, u16, u16, u8, [u8; 11],
+] == 32);
+assert_wire_size!("NoExposure": [u8, [u8; 1], u16, u32, u16, u8, [u8; 21]] == 32);
+assert_wire_size!("VisibilityNotify": [u8, [u8; 1], u16, Window, u8, [u8; 23]] == 32);
+assert_wire_size!("CreateNotify": [
+	u8, [u8; 1], u16, Window, Window, Rectangle, u16, bool, [u8; 9],
+] == 32);
+assert_wire_size!("DestroyNotify": [u8, [u8; 1], u16, Window, Window, [u8; 20]] == 32);
+assert_wire_size!("UnmapNotify": [u8, [u8; 1], u16, Window, Window, bool, [u8; 19]] == 32);
+assert_wire_size!("MapNotify": [u8, [u8; 1], u16, Window, Window, bool, [u8; 19]] == 32);
+assert_wire_size!("MapRequest": [u8, [u8; 1], u16, Window, Window, [u8; 20]] == 32);
+assert_wire_size!("ReparentNotify": [
+	u8, [u8; 1], u16, Window, Window, Window, Point, bool, [u8; 11],
+] == 32);
+assert_wire_size!("ConfigureNotify": [
+	u8, [u8; 1], u16, Window, Window, Window, Rectangle, u16, bool, [u8; 5],
+] == 32);
+assert_wire_size!("ConfigureRequest": [
+	u8, u8, u16, Window, Window, Window, Rectangle, u16, u16, [u8; 4],
+] == 32);
+assert_wire_size!("GravityNotify": [u8, [u8; 1], u16, Window, Window, Point, [u8; 16]] == 32);
+assert_wire_size!("ResizeRequest": [u8, [u8; 1], u16, Window, u16, u16, [u8; 20]] == 32);
+assert_wire_size!("CirculateNotify": [
+	u8, [u8; 1], u16, Window, Window, [u8; 4], u8, [u8; 15],
+] == 32);
+assert_wire_size!("PropertyNotify": [
+	u8, [u8; 1], u16, Window, Atom, Timestamp, u8, [u8; 15],
+] == 32);
+assert_wire_size!("SelectionClear": [u8, [u8; 1], u16, Timestamp, Window, Atom, [u8; 16]] == 32);
+assert_wire_size!("SelectionRequest": [
+	u8, [u8; 1], u16, Timestamp, Window, Window, Atom, Atom, Atom, [u8; 4],
+] == 32);
+assert_wire_size!("SelectionNotify": [
+	u8, [u8; 1], u16, Timestamp, Window, Atom, Atom, Atom, [u8; 8],
+] == 32);
+assert_wire_size!("ColormapNotify": [u8, [u8; 1], u16, Window, u32, bool, u8, [u8; 18]] == 32);
+assert_wire_size!("ClientMessage": [u8, u8, u16, Window, Atom, [u8; 20]] == 32);
+assert_wire_size!("MappingNotify": [u8, [u8; 1], u16, u8, Keycode, u8, [u8; 25]] == 32);
+
+impl Event {
+	/// Decodes an `Event` from an event frame.
+	///
+	/// Returns `None` if `frame` is not an event, or if it is a core event
+	/// with an invalid value.
+	pub fn from_frame(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+		let fields = Fields(EventFields(chunk));
+
+		Some(match code & !SEND_EVENT_MASK {
+			codes::KEY_PRESS => Self::KeyPress(fields.input(fields.u8(1))),
+			codes::KEY_RELEASE => Self::KeyRelease(fields.input(fields.u8(1))),
+			codes::BUTTON_PRESS => Self::ButtonPress(fields.input(fields.u8(1))),
+			codes::BUTTON_RELEASE => Self::ButtonRelease(fields.input(fields.u8(1))),
+			codes::MOTION_NOTIFY => Self::MotionNotify(fields.input(fields.u8(1))),
+
+			codes::ENTER_NOTIFY => Self::EnterNotify(fields.crossing()?),
+			codes::LEAVE_NOTIFY => Self::LeaveNotify(fields.crossing()?),
+			codes::FOCUS_IN => Self::FocusIn(fields.focus()?),
+			codes::FOCUS_OUT => Self::FocusOut(fields.focus()?),
+			codes::KEYMAP_NOTIFY => Self::KeymapNotify(*chunk),
+
+			codes::EXPOSE => Self::Expose(ExposeEvent {
+				window: fields.window(4),
+				area: fields.rectangle(8),
+				count: fields.u16(16),
+			}),
+			codes::GRAPHICS_EXPOSURE => Self::GraphicsExposure(GraphicsExposureEvent {
+				drawable: fields.u32(4),
+				area: fields.rectangle(8),
+				minor_opcode: fields.u16(16),
+				count: fields.u16(18),
+				major_opcode: fields.u8(20),
+			}),
+			codes::NO_EXPOSURE => Self::NoExposure(NoExposureEvent {
+				drawable: fields.u32(4),
+				minor_opcode: fields.u16(8),
+				major_opcode: fields.u8(10),
+			}),
+			codes::VISIBILITY_NOTIFY => Self::VisibilityNotify(VisibilityEvent {
+				window: fields.window(4),
+				state: Visibility::from_value(fields.u8(8))?,
+			}),
+
+			codes::CREATE_NOTIFY => Self::CreateNotify(CreateEvent {
+				parent: fields.window(4),
+				window: fields.window(8),
+				area: fields.rectangle(12),
+				border_width: fields.u16(20),
+				override_redirect: fields.bool(22),
+			}),
+			codes::DESTROY_NOTIFY => Self::DestroyNotify(DestroyEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+			}),
+			codes::UNMAP_NOTIFY => Self::UnmapNotify(UnmapEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+				from_configure: fields.bool(12),
+			}),
+			codes::MAP_NOTIFY => Self::MapNotify(MapEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+				override_redirect: fields.bool(12),
+			}),
+			codes::MAP_REQUEST => Self::MapRequest(MapRequestEvent {
+				parent: fields.window(4),
+				window: fields.window(8),
+			}),
+			codes::REPARENT_NOTIFY => Self::ReparentNotify(ReparentEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+				parent: fields.window(12),
+				position: fields.point(16),
+				override_redirect: fields.bool(20),
+			}),
+			codes::CONFIGURE_NOTIFY => Self::ConfigureNotify(ConfigureEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+				above_sibling: fields.optional_window(12),
+				area: fields.rectangle(16),
+				border_width: fields.u16(24),
+				override_redirect: fields.bool(26),
+			}),
+			codes::CONFIGURE_REQUEST => Self::ConfigureRequest(ConfigureRequestEvent {
+				stack_mode: StackMode::from_value(fields.u8(1))?,
+				parent: fields.window(4),
+				window: fields.window(8),
+				sibling: fields.optional_window(12),
+				area: fields.rectangle(16),
+				border_width: fields.u16(24),
+				value_mask: fields.u16(26),
+			}),
+			codes::GRAVITY_NOTIFY => Self::GravityNotify(GravityEvent {
+				event: fields.window(4),
+				window: fields.window(8),
+				position: fields.point(12),
+			}),
+			codes::RESIZE_REQUEST => Self::ResizeRequest(ResizeRequestEvent {
+				window: fields.window(4),
+				width: fields.u16(8),
+				height: fields.u16(10),
+			}),
+			codes::CIRCULATE_NOTIFY => Self::CirculateNotify(fields.circulate()?),
+			codes::CIRCULATE_REQUEST => Self::CirculateRequest(fields.circulate()?),
+
+			codes::PROPERTY_NOTIFY => Self::PropertyNotify(PropertyEvent {
+				window: fields.window(4),
+				atom: fields.atom(8),
+				time: fields.time(12),
+				state: PropertyState::from_value(fields.u8(16))?,
+			}),
+			codes::SELECTION_CLEAR => Self::SelectionClear(SelectionClearEvent {
+				time: fields.time(4),
+				owner: fields.window(8),
+				selection: fields.atom(12),
+			}),
+			codes::SELECTION_REQUEST => Self::SelectionRequest(SelectionRequestEvent {
+				time: fields.time(4),
+				owner: fields.window(8),
+				requestor: fields.window(12),
+				selection: fields.atom(16),
+				target: fields.atom(20),
+				property: fields.atom(24),
+			}),
+			codes::SELECTION_NOTIFY => Self::SelectionNotify(SelectionNotifyEvent {
+				time: fields.time(4),
+				requestor: fields.window(8),
+				selection: fields.atom(12),
+				target: fields.atom(16),
+				property: fields.atom(20),
+			}),
+			codes::COLORMAP_NOTIFY => Self::ColormapNotify(ColormapEvent {
+				window: fields.window(4),
+				colormap: fields.u32(8),
+				new: fields.bool(12),
+				installed: fields.bool(13),
+			}),
+			codes::CLIENT_MESSAGE => Self::ClientMessage(ClientMessageEvent {
+				window: fields.window(4),
+				message_type: fields.atom(8),
+				data: fields.client_message_data()?,
+			}),
+			codes::MAPPING_NOTIFY => Self::MappingNotify(MappingEvent {
+				request: MappingRequest::from_value(fields.u8(4))?,
+				first_keycode: fields.u8(5),
+				count: fields.u8(6),
+			}),
+
+			code => Self::Extension { code, data: *chunk },
+		})
+	}
+
+	/// Returns whether `frame` is an event which was sent with a `SendEvent`
+	/// request, rather than generated by the X server.
+	pub const fn is_sent(frame: &X11Frame) -> bool {
+		matches!(frame, X11Frame::Event { code, .. } if *code & SEND_EVENT_MASK != 0)
+	}
+
+	/// The event's code, without the bit set for events sent with a
+	/// `SendEvent` request.
+	pub const fn code(&self) -> u8 {
+		match self {
+			Self::KeyPress(_) => codes::KEY_PRESS,
+			Self::KeyRelease(_) => codes::KEY_RELEASE,
+			Self::ButtonPress(_) => codes::BUTTON_PRESS,
+			Self::ButtonRelease(_) => codes::BUTTON_RELEASE,
+			Self::MotionNotify(_) => codes::MOTION_NOTIFY,
+
+			Self::EnterNotify(_) => codes::ENTER_NOTIFY,
+			Self::LeaveNotify(_) => codes::LEAVE_NOTIFY,
+			Self::FocusIn(_) => codes::FOCUS_IN,
+			Self::FocusOut(_) => codes::FOCUS_OUT,
+			Self::KeymapNotify(_) => codes::KEYMAP_NOTIFY,
+
+			Self::Expose(_) => codes::EXPOSE,
+			Self::GraphicsExposure(_) => codes::GRAPHICS_EXPOSURE,
+			Self::NoExposure(_) => codes::NO_EXPOSURE,
+			Self::VisibilityNotify(_) => codes::VISIBILITY_NOTIFY,
+
+			Self::CreateNotify(_) => codes::CREATE_NOTIFY,
+			Self::DestroyNotify(_) => codes::DESTROY_NOTIFY,
+			Self::UnmapNotify(_) => codes::UNMAP_NOTIFY,
+			Self::MapNotify(_) => codes::MAP_NOTIFY,
+			Self::MapRequest(_) => codes::MAP_REQUEST,
+			Self::ReparentNotify(_) => codes::REPARENT_NOTIFY,
+			Self::ConfigureNotify(_) => codes::CONFIGURE_NOTIFY,
+			Self::ConfigureRequest(_) => codes::CONFIGURE_REQUEST,
+			Self::GravityNotify(_) => codes::GRAVITY_NOTIFY,
+			Self::ResizeRequest(_) => codes::RESIZE_REQUEST,
+			Self::CirculateNotify(_) => codes::CIRCULATE_NOTIFY,
+			Self::CirculateRequest(_) => codes::CIRCULATE_REQUEST,
+
+			Self::PropertyNotify(_) => codes::PROPERTY_NOTIFY,
+			Self::SelectionClear(_) => codes::SELECTION_CLEAR,
+			Self::SelectionRequest(_) => codes::SELECTION_REQUEST,
+			Self::SelectionNotify(_) => codes::SELECTION_NOTIFY,
+			Self::ColormapNotify(_) => codes::COLORMAP_NOTIFY,
+			Self::ClientMessage(_) => codes::CLIENT_MESSAGE,
+			Self::MappingNotify(_) => codes::MAPPING_NOTIFY,
+
+			Self::Extension { code, .. } => *code,
+		}
+	}
+
+	/// The event's name in the protocol, such as `KeyPress`, or `Extension`
+	/// for events defined by extensions.
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::KeyPress(_) => "KeyPress",
+			Self::KeyRelease(_) => "KeyRelease",
+			Self::ButtonPress(_) => "ButtonPress",
+			Self::ButtonRelease(_) => "ButtonRelease",
+			Self::MotionNotify(_) => "MotionNotify",
+
+			Self::EnterNotify(_) => "EnterNotify",
+			Self::LeaveNotify(_) => "LeaveNotify",
+			Self::FocusIn(_) => "FocusIn",
+			Self::FocusOut(_) => "FocusOut",
+			Self::KeymapNotify(_) => "KeymapNotify",
+
+			Self::Expose(_) => "Expose",
+			Self::GraphicsExposure(_) => "GraphicsExposure",
+			Self::NoExposure(_) => "NoExposure",
+			Self::VisibilityNotify(_) => "VisibilityNotify",
+
+			Self::CreateNotify(_) => "CreateNotify",
+			Self::DestroyNotify(_) => "DestroyNotify",
+			Self::UnmapNotify(_) => "UnmapNotify",
+			Self::MapNotify(_) => "MapNotify",
+			Self::MapRequest(_) => "MapRequest",
+			Self::ReparentNotify(_) => "ReparentNotify",
+			Self::ConfigureNotify(_) => "ConfigureNotify",
+			Self::ConfigureRequest(_) => "ConfigureRequest",
+			Self::GravityNotify(_) => "GravityNotify",
+			Self::ResizeRequest(_) => "ResizeRequest",
+			Self::CirculateNotify(_) => "CirculateNotify",
+			Self::CirculateRequest(_) => "CirculateRequest",
+
+			Self::PropertyNotify(_) => "PropertyNotify",
+			Self::SelectionClear(_) => "SelectionClear",
+			Self::SelectionRequest(_) => "SelectionRequest",
+			Self::SelectionNotify(_) => "SelectionNotify",
+			Self::ColormapNotify(_) => "ColormapNotify",
+			Self::ClientMessage(_) => "ClientMessage",
+			Self::MappingNotify(_) => "MappingNotify",
+
+			Self::Extension { .. } => "Extension",
+		}
+	}
+
+	/// The window the event is about, such as the window which was mapped for
+	/// a `MapNotify` event, or the window the pointer was in for a
+	/// `ButtonPress` event.
+	///
+	/// Returns `None` for events which aren't about a window: `KeymapNotify`,
+	/// `MappingNotify`, extension events, and `GraphicsExposure` and
+	/// `NoExposure` events, whose drawable may be a pixmap.
+	pub const fn window(&self) -> Option<Window> {
+		Some(match self {
+			Self::KeyPress(event)
+			| Self::KeyRelease(event)
+			| Self::ButtonPress(event)
+			| Self::ButtonRelease(event)
+			| Self::MotionNotify(event) => event.event,
+
+			Self::EnterNotify(event) | Self::LeaveNotify(event) => event.event,
+			Self::FocusIn(event) | Self::FocusOut(event) => event.event,
+
+			Self::Expose(event) => event.window,
+			Self::VisibilityNotify(event) => event.window,
+
+			Self::CreateNotify(event) => event.window,
+			Self::DestroyNotify(event) => event.window,
+			Self::UnmapNotify(event) => event.window,
+			Self::MapNotify(event) => event.window,
+			Self::MapRequest(event) => event.window,
+			Self::ReparentNotify(event) => event.window,
+			Self::ConfigureNotify(event) => event.window,
+			Self::ConfigureRequest(event) => event.window,
+			Self::GravityNotify(event) => event.window,
+			Self::ResizeRequest(event) => event.window,
+			Self::CirculateNotify(event) | Self::CirculateRequest(event) => event.window,
+
+			Self::PropertyNotify(event) => event.window,
+			Self::SelectionClear(event) => event.owner,
+			Self::SelectionRequest(event) => event.owner,
+			Self::SelectionNotify(event) => event.requestor,
+			Self::ColormapNotify(event) => event.window,
+			Self::ClientMessage(event) => event.window,
+
+			Self::KeymapNotify(_)
+			| Self::GraphicsExposure(_)
+			| Self::NoExposure(_)
+			| Self::MappingNotify(_)
+			| Self::Extension { .. } => return None,
+		})
+	}
+
+	/// The window the event was reported to.
+	///
+	/// This is the [`window`] the event is about, except for events reported
+	/// to another window: the parent of the window for `CreateNotify`,
+	/// `MapRequest` and `ConfigureRequest` events, and the window
+	/// `StructureNotify` or `SubstructureNotify` was selected on for other
+	/// notifications about a window's structure. A window manager's events
+	/// for the clients it manages are all reported to the root window.
+	///
+	/// [`window`]: Event::window
+	pub const fn event_window(&self) -> Option<Window> {
+		match self {
+			Self::CreateNotify(event) => Some(event.parent),
+			Self::MapRequest(event) => Some(event.parent),
+			Self::ConfigureRequest(event) => Some(event.parent),
+
+			Self::DestroyNotify(event) => Some(event.event),
+			Self::UnmapNotify(event) => Some(event.event),
+			Self::MapNotify(event) => Some(event.event),
+			Self::ReparentNotify(event) => Some(event.event),
+			Self::ConfigureNotify(event) => Some(event.event),
+			Self::GravityNotify(event) => Some(event.event),
+			Self::CirculateNotify(event) | Self::CirculateRequest(event) => Some(event.event),
+
+			event => event.window(),
+		}
+	}
+}
+
+/// Summarizes the event on one line, with its name and most useful fields,
+/// such as `PropertyNotify on 0x2a00003: WM_NAME new value at 1234ms`.
+///
+/// Atoms are shown with their names where known; see
+/// [`AtomNames::resolve`].
+///
+/// [`AtomNames::resolve`]: crate::atoms::AtomNames::resolve
+impl fmt::Display for Event {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())?;
+
+		match self {
+			Self::KeyPress(event) | Self::KeyRelease(event) => write!(
+				f,
+				" of keycode {} on {} at {} (state {:#x})",
+				event.detail, event.event, event.event_position, event.state
+			),
+			Self::ButtonPress(event) | Self::ButtonRelease(event) => write!(
+				f,
+				" of button {} on {} at {} (state {:#x})",
+				event.detail, event.event, event.event_position, event.state
+			),
+			Self::MotionNotify(event) => write!(
+				f,
+				" on {} at {} (state {:#x})",
+				event.event, event.event_position, event.state
+			),
+
+			Self::EnterNotify(event) | Self::LeaveNotify(event) => write!(
+				f,
+				" on {} at {} ({:?}, {:?})",
+				event.event, event.event_position, event.detail, event.mode
+			),
+			Self::FocusIn(event) | Self::FocusOut(event) => {
+				write!(
+					f,
+					" on {} ({:?}, {:?})",
+					event.event, event.detail, event.mode
+				)
+			},
+			Self::KeymapNotify(_) => Ok(()),
+
+			Self::Expose(event) => write!(
+				f,
+				" of {} on {} ({} more)",
+				event.area, event.window, event.count
+			),
+			Self::GraphicsExposure(event) => write!(
+				f,
+				" of {} on {:#x} ({} more)",
+				event.area, event.drawable, event.count
+			),
+			Self::NoExposure(event) => write!(f, " on {:#x}", event.drawable),
+			Self::VisibilityNotify(event) => write!(f, " on {}: {:?}", event.window, event.state),
+
+			Self::CreateNotify(event) => write!(
+				f,
+				" of {} in {} at {}",
+				event.window, event.parent, event.area
+			),
+			Self::DestroyNotify(DestroyEvent { window, .. })
+			| Self::UnmapNotify(UnmapEvent { window, .. })
+			| Self::MapNotify(MapEvent { window, .. })
+			| Self::MapRequest(MapRequestEvent { window, .. }) => write!(f, " of {window}"),
+			Self::ReparentNotify(event) => write!(
+				f,
+				" of {} to {} at {}",
+				event.window, event.parent, event.position
+			),
+			Self::ConfigureNotify(event) => {
+				write!(f, " of {} to {}", event.window, event.area)
+			},
+			Self::ConfigureRequest(event) => write!(
+				f,
+				" of {} to {} (mask {:#x})",
+				event.window, event.area, event.value_mask
+			),
+			Self::GravityNotify(event) => {
+				write!(f, " of {} to {}", event.window, event.position)
+			},
+			Self::ResizeRequest(event) => write!(
+				f,
+				" of {} to {}x{}",
+				event.window, event.width, event.height
+			),
+			Self::CirculateNotify(event) | Self::CirculateRequest(event) => {
+				write!(f, " of {} to {:?}", event.window, event.place)
+			},
+
+			Self::PropertyNotify(event) => {
+				let state = match event.state {
+					PropertyState::NewValue => "new value",
+					PropertyState::Deleted => "deleted",
+				};
+
+				write!(
+					f,
+					" on {}: {} {state} at {}",
+					event.window, event.atom, event.time
+				)
+			},
+			Self::SelectionClear(event) => write!(
+				f,
+				" of {} from {} at {}",
+				event.selection, event.owner, event.time
+			),
+			Self::SelectionRequest(event) => write!(
+				f,
+				" of {} as {} into {} on {} at {}",
+				event.selection, event.target, event.property, event.requestor, event.time
+			),
+			Self::SelectionNotify(event) => write!(
+				f,
+				" of {} as {} into {} on {} at {}",
+				event.selection, event.target, event.property, event.requestor, event.time
+			),
+			Self::ColormapNotify(event) => write!(
+				f,
+				" on {}: {:#x}{}",
+				event.window,
+				event.colormap,
+				if event.installed { " installed" } else { "" }
+			),
+			Self::ClientMessage(event) => {
+				write!(f, " {} to {}: ", event.message_type, event.window)?;
+
+				match event.data {
+					ClientMessageData::U8(data) => write!(f, "{:?}", HexBytes(&data)),
+					ClientMessageData::U16(data) => write!(f, "{data:?}"),
+					ClientMessageData::U32(data) => write!(f, "{data:?}"),
+				}
+			},
+			Self::MappingNotify(event) => write!(
+				f,
+				" of {:?} for keycodes {}..{}",
+				event.request,
+				event.first_keycode,
+				u16::from(event.first_keycode) + u16::from(event.count)
+			),
+
+			Self::Extension { code, .. } => write!(f, " {code}"),
+		}
+	}
+}
+
+/// A decoder for the generic events of an extension.
+type GenericEventDecoder<T> = Box<dyn Fn(u16, &[u8]) -> Option<T> + Send + Sync>;
+
+/// Decoders for [`X11Frame::GenericEvent`]s, registered by the major opcode of
+/// the extension which defines them.
+///
+/// Each decoder is given the event's type within its extension and its data,
+/// starting at byte 10 of the event, and returns `None` if it can't decode
+/// the event:
+///
+/// ```no_run
+/// # use xrs::{event::GenericEventDecoders, Client};
+/// #[derive(Debug)]
+/// enum InputEvent {
+///     Motion { device: u16 },
+/// }
+///
+/// # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+/// let Some(xinput) = client.query_extension("XInputExtension").await? else {
+///     return Ok(());
+/// };
+///
+/// let mut decoders = GenericEventDecoders::new();
+/// decoders.register(xinput.major_opcode, |event_type, data| match event_type {
+///     6 => Some(InputEvent::Motion {
+///         device: u16::from_be_bytes([data[0], data[1]]),
+///     }),
+///
+///     _ => None,
+/// });
+///
+/// while let Some(frame) = client.next_event().await? {
+///     if let Some(event) = decoders.decode(&frame) {
+///         println!("{event:?}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct GenericEventDecoders<T> {
+	decoders: HashMap<u8, GenericEventDecoder<T>>,
+}
+
+impl<T> Default for GenericEventDecoders<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> GenericEventDecoders<T> {
+	/// Creates an empty set of decoders.
+	pub fn new() -> Self {
+		Self {
+			decoders: HashMap::new(),
+		}
+	}
+
+	/// Registers `decoder` for the generic events of the extension with the
+	/// given major opcode, replacing any decoder already registered for it.
+	pub fn register(
+		&mut self, extension: u8, decoder: impl Fn(u16, &[u8]) -> Option<T> + Send + Sync + 'static,
+	) {
+		self.decoders.insert(extension, Box::new(decoder));
+	}
+
+	/// Removes the decoder registered for the extension with the given major
+	/// opcode, returning whether there was one.
+	pub fn unregister(&mut self, extension: u8) -> bool {
+		self.decoders.remove(&extension).is_some()
+	}
+
+	/// Returns whether a decoder is registered for the extension with the
+	/// given major opcode.
+	pub fn is_registered(&self, extension: u8) -> bool {
+		self.decoders.contains_key(&extension)
+	}
+
+	/// Decodes `frame` with the decoder registered for its extension.
+	///
+	/// Returns `None` if `frame` is not a generic event, if no decoder is
+	/// registered for its extension, or if the decoder returns `None`.
+	pub fn decode(&self, frame: &X11Frame) -> Option<T> {
+		let X11Frame::GenericEvent {
+			extension,
+			event_type,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+
+		(self.decoders.get(extension)?)(*event_type, chunk)
+	}
+}
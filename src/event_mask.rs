@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Event masks, which select the events a client receives for a window.
+//!
+//! Working out which masks produce which events is a common source of
+//! trial and error, so [`presets`] has curated masks for common kinds of
+//! clients.
+
+use crate::{
+	raw::RawRequest,
+	request::Request,
+	window::{ChangeWindowAttributes, Window, WindowAttributes},
+};
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+/// A set of events selected on a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EventMask(pub u32);
+
+impl EventMask {
+	/// No events.
+	pub const NONE: Self = Self(0);
+
+	pub const KEY_PRESS: Self = Self(1 << 0);
+	pub const KEY_RELEASE: Self = Self(1 << 1);
+	pub const BUTTON_PRESS: Self = Self(1 << 2);
+	pub const BUTTON_RELEASE: Self = Self(1 << 3);
+	pub const ENTER_WINDOW: Self = Self(1 << 4);
+	pub const LEAVE_WINDOW: Self = Self(1 << 5);
+	pub const POINTER_MOTION: Self = Self(1 << 6);
+	pub const POINTER_MOTION_HINT: Self = Self(1 << 7);
+	pub const BUTTON_1_MOTION: Self = Self(1 << 8);
+	pub const BUTTON_2_MOTION: Self = Self(1 << 9);
+	pub const BUTTON_3_MOTION: Self = Self(1 << 10);
+	pub const BUTTON_4_MOTION: Self = Self(1 << 11);
+	pub const BUTTON_5_MOTION: Self = Self(1 << 12);
+	pub const BUTTON_MOTION: Self = Self(1 << 13);
+	pub const KEYMAP_STATE: Self = Self(1 << 14);
+	pub const EXPOSURE: Self = Self(1 << 15);
+	pub const VISIBILITY_CHANGE: Self = Self(1 << 16);
+	pub const STRUCTURE_NOTIFY: Self = Self(1 << 17);
+	pub const RESIZE_REDIRECT: Self = Self(1 << 18);
+	pub const SUBSTRUCTURE_NOTIFY: Self = Self(1 << 19);
+	pub const SUBSTRUCTURE_REDIRECT: Self = Self(1 << 20);
+	pub const FOCUS_CHANGE: Self = Self(1 << 21);
+	pub const PROPERTY_CHANGE: Self = Self(1 << 22);
+	pub const COLORMAP_CHANGE: Self = Self(1 << 23);
+	pub const OWNER_GRAB_BUTTON: Self = Self(1 << 24);
+
+	/// The events which only one client at a time may select on a window.
+	///
+	/// Selecting any of these on a window for which another client has
+	/// already selected them generates an `Access` error.
+	pub const EXCLUSIVE: Self =
+		Self(Self::BUTTON_PRESS.0 | Self::RESIZE_REDIRECT.0 | Self::SUBSTRUCTURE_REDIRECT.0);
+
+	/// Returns the union of this mask and `other`.
+	pub const fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+
+	/// Whether every event in `other` is also in this mask.
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// Whether this mask selects no events.
+	pub const fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Returns a `ChangeWindowAttributes` request which selects the events in
+	/// this mask on `window`, replacing any events this client previously
+	/// selected on it.
+	pub fn select_input_request(self, window: u32) -> RawRequest {
+		let request = ChangeWindowAttributes {
+			window: Window(window),
+			attributes: WindowAttributes {
+				event_mask: Some(self),
+				..WindowAttributes::default()
+			},
+		};
+
+		request.to_raw(ChangeWindowAttributes::OPCODE)
+	}
+}
+
+impl BitOr for EventMask {
+	type Output = Self;
+
+	fn bitor(self, other: Self) -> Self {
+		self.union(other)
+	}
+}
+
+impl BitOrAssign for EventMask {
+	fn bitor_assign(&mut self, other: Self) {
+		*self = self.union(other);
+	}
+}
+
+impl BitAnd for EventMask {
+	type Output = Self;
+
+	fn bitand(self, other: Self) -> Self {
+		Self(self.0 & other.0)
+	}
+}
+
+impl Not for EventMask {
+	type Output = Self;
+
+	fn not(self) -> Self {
+		Self(!self.0)
+	}
+}
+
+/// Curated event masks for common kinds of clients.
+pub mod presets {
+	use super::EventMask;
+
+	/// The events an ordinary application window needs: keyboard and pointer
+	/// input, exposure, focus, and changes to its own size, position and
+	/// properties.
+	pub const INTERACTIVE_APP: EventMask = EventMask(
+		EventMask::KEY_PRESS.0
+			| EventMask::KEY_RELEASE.0
+			| EventMask::BUTTON_PRESS.0
+			| EventMask::BUTTON_RELEASE.0
+			| EventMask::POINTER_MOTION.0
+			| EventMask::ENTER_WINDOW.0
+			| EventMask::LEAVE_WINDOW.0
+			| EventMask::EXPOSURE.0
+			| EventMask::STRUCTURE_NOTIFY.0
+			| EventMask::FOCUS_CHANGE.0
+			| EventMask::PROPERTY_CHANGE.0,
+	);
+
+	/// The events a window manager selects on the root window: redirection
+	/// of top-level windows being mapped, configured and circulated,
+	/// notification of their changes, root window properties, and clicks and
+	/// crossings on the root window.
+	///
+	/// This includes [`SUBSTRUCTURE_REDIRECT`], which only one client can
+	/// select on the root window: selecting it fails with an `Access` error
+	/// if another window manager is running.
+	///
+	/// [`SUBSTRUCTURE_REDIRECT`]: EventMask::SUBSTRUCTURE_REDIRECT
+	pub const WM_ROOT: EventMask = EventMask(
+		EventMask::SUBSTRUCTURE_REDIRECT.0
+			| EventMask::SUBSTRUCTURE_NOTIFY.0
+			| EventMask::STRUCTURE_NOTIFY.0
+			| EventMask::PROPERTY_CHANGE.0
+			| EventMask::BUTTON_PRESS.0
+			| EventMask::ENTER_WINDOW.0
+			| EventMask::LEAVE_WINDOW.0,
+	);
+
+	/// The events a client observing other clients' windows needs, such as a
+	/// taskbar or a pager: changes to the window and its children, its
+	/// properties, and its focus.
+	///
+	/// This contains none of the [`EXCLUSIVE`] events, so it never interferes
+	/// with the windows' own clients or the window manager.
+	///
+	/// [`EXCLUSIVE`]: EventMask::EXCLUSIVE
+	pub const PASSIVE_MONITOR: EventMask = EventMask(
+		EventMask::STRUCTURE_NOTIFY.0
+			| EventMask::SUBSTRUCTURE_NOTIFY.0
+			| EventMask::PROPERTY_CHANGE.0
+			| EventMask::FOCUS_CHANGE.0,
+	);
+}
+
+// The passive preset must be safe to select on any window.
+const _: () = assert!(presets::PASSIVE_MONITOR.0 & EventMask::EXCLUSIVE.0 == 0);
@@ -0,0 +1,1012 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Helpers for the Extended Window Manager Hints (EWMH).
+//!
+//! Window managers with focus stealing prevention ignore requests to activate
+//! windows which do not say where they came from, or whose timestamp is older
+//! than the user's last interaction with another window. A [`UserTime`]
+//! remembers the timestamp of the last user input event, which
+//! [`Client::activate_window`] sends along with its source.
+//!
+//! [`Client::frame_extents`] reads the size of the decorations the window
+//! manager adds around a window, and [`FrameExtents`] converts between the
+//! areas covered by the window and by its frame.
+//!
+//! [`Client::window_icons`] reads a window's `_NET_WM_ICON` as RGBA images,
+//! from which [`best_icon`] picks the one to draw at a given size.
+//!
+//! [`Client::window_title`] reads a window's title from `_NET_WM_NAME`, or
+//! `WM_NAME` for clients which don't set it, and a [`TitleStream`] reads it
+//! again whenever either changes. [`Client::client_list`] reads the windows
+//! managed by the window manager, and a [`ClientListStream`] reports which
+//! were added and removed whenever that list changes.
+//!
+//! Window managers differ in how they implement parts of the EWMH.
+//! [`Client::detect_wm`] identifies the running window manager, and a
+//! [`QuirkRegistry`] gives the [`Quirks`] known for it.
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	event::{ClientMessageData, ClientMessageEvent, Event, SendEvent},
+	event_mask::EventMask,
+	geometry::Rectangle,
+	property::{self, Property, PropertyMode},
+	raw::X11Frame,
+	request,
+	time::Timestamp,
+	window::{GetWindowAttributes, Window, WindowAttributes},
+	Client,
+	EventStream,
+	ReplyError,
+	RequestWriter,
+};
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+	io,
+};
+
+/// The event code of `KeyPress` events.
+const KEY_PRESS: u8 = 2;
+/// The event code of `ButtonRelease` events.
+const BUTTON_RELEASE: u8 = 5;
+
+/// Where a request to activate a window comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActivationSource {
+	/// A client following an older version of the EWMH, which did not
+	/// indicate sources.
+	Legacy,
+	/// An application activating one of its own windows.
+	Application,
+	/// A pager or taskbar acting on the user's behalf.
+	Pager,
+}
+
+/// Remembers the timestamp of the last user input event.
+///
+/// Window managers compare activation timestamps with the user's last
+/// interaction, so the timestamp sent with an activation request should be
+/// that of the input event which caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct UserTime {
+	last: Option<Timestamp>,
+}
+
+impl UserTime {
+	/// Creates a new `UserTime` which has not seen any user input.
+	pub const fn new() -> Self {
+		Self { last: None }
+	}
+
+	/// Records the timestamp of `frame` if it is a key or button event.
+	///
+	/// Synthetic events sent by other clients are ignored, as they do not
+	/// represent the user's input. Returns the recorded timestamp.
+	pub fn observe(&mut self, frame: &X11Frame) -> Option<Timestamp> {
+		let X11Frame::Event { code, .. } = frame else {
+			return None;
+		};
+
+		if !(KEY_PRESS..=BUTTON_RELEASE).contains(code) {
+			return None;
+		}
+
+		let timestamp = Timestamp::from_event(frame)?;
+		self.record(timestamp);
+
+		Some(timestamp)
+	}
+
+	/// Records a user input timestamp, unless it is earlier than the last
+	/// one recorded.
+	pub fn record(&mut self, timestamp: Timestamp) {
+		match self.last {
+			Some(last) if !timestamp.is_after(last) => {},
+			_ => self.last = Some(timestamp),
+		}
+	}
+
+	/// The timestamp of the last user input event, if any have been seen.
+	pub const fn last(&self) -> Option<Timestamp> {
+		self.last
+	}
+}
+
+/// One of a window's icons, as an RGBA image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Icon {
+	pub width: u32,
+	pub height: u32,
+
+	/// The icon's pixels, row by row, as non-premultiplied red, green, blue
+	/// and alpha bytes.
+	pub rgba: Vec<u8>,
+}
+
+impl Icon {
+	/// Reads the icons in a `_NET_WM_ICON` value: for each icon, its width
+	/// and height followed by its pixels in ARGB.
+	///
+	/// Reading stops at the first icon whose pixels are missing.
+	fn parse_all(mut values: &[u32]) -> Vec<Self> {
+		let mut icons = Vec::new();
+
+		while let [width, height, rest @ ..] = values {
+			let Some(pixel_count) = (*width as usize).checked_mul(*height as usize) else {
+				break;
+			};
+			let Some(pixels) = rest.get(..pixel_count) else {
+				break;
+			};
+
+			icons.push(Self {
+				width: *width,
+				height: *height,
+
+				rgba: pixels
+					.iter()
+					.flat_map(|argb| {
+						let [a, r, g, b] = argb.to_be_bytes();
+						[r, g, b, a]
+					})
+					.collect(),
+			});
+
+			values = &rest[pixel_count..];
+		}
+
+		icons
+	}
+}
+
+/// Picks the icon to draw in a `size` by `size` square: the smallest icon at
+/// least that big, so that it only has to be scaled down, or the largest icon
+/// if none are.
+pub fn best_icon(icons: &[Icon], size: u32) -> Option<&Icon> {
+	let fits = |icon: &&Icon| icon.width >= size && icon.height >= size;
+	let area = |icon: &&Icon| u64::from(icon.width) * u64::from(icon.height);
+
+	icons
+		.iter()
+		.filter(fits)
+		.min_by_key(area)
+		.or_else(|| icons.iter().max_by_key(area))
+}
+
+/// Reads `window`'s icons from its `_NET_WM_ICON` property.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::window_icons`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn window_icons(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Vec<Icon>, ReplyError> {
+	let net_wm_icon = atoms::atom(connection, "_NET_WM_ICON").await?;
+
+	let values = property::get_property(connection, window, net_wm_icon, Atom::CARDINAL)
+		.await?
+		.and_then(|value| value.to_u32s())
+		.unwrap_or_default();
+
+	Ok(Icon::parse_all(&values))
+}
+
+/// The running window manager, as identified by [`Client::detect_wm`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowManager {
+	/// The child window the window manager created to show it is running.
+	pub check_window: Window,
+	/// The window manager's name, from the `_NET_WM_NAME` of its
+	/// `check_window`.
+	///
+	/// This is empty if the window manager doesn't name itself.
+	pub name: String,
+}
+
+impl WindowManager {
+	/// Returns the quirks known for this window manager by the built-in
+	/// [`QuirkRegistry`].
+	pub fn quirks(&self) -> Quirks {
+		QuirkRegistry::new().get(&self.name)
+	}
+}
+
+/// Parts of the EWMH which window managers implement differently.
+///
+/// The [`Default`] assumes as little as possible about the window manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Quirks {
+	/// Whether the window manager answers `_NET_REQUEST_FRAME_EXTENTS` by
+	/// setting `_NET_FRAME_EXTENTS` before the window is mapped.
+	///
+	/// Otherwise, `_NET_FRAME_EXTENTS` may only be set once the window has
+	/// been mapped and reparented, so clients should wait for it to change.
+	pub frame_extents_on_request: bool,
+	/// Whether the window manager supports the extended form of
+	/// `_NET_WM_SYNC_REQUEST`, with two sync counters and
+	/// `_NET_WM_FRAME_DRAWN` messages.
+	///
+	/// Otherwise, only the basic form with a single counter should be used.
+	pub extended_sync_counter: bool,
+}
+
+/// The [`Quirks`] of window managers, by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuirkRegistry {
+	quirks: HashMap<String, Quirks>,
+}
+
+impl Default for QuirkRegistry {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl QuirkRegistry {
+	/// Creates a `QuirkRegistry` with the quirks of known window managers.
+	pub fn new() -> Self {
+		let mut registry = Self::empty();
+
+		let mutter = Quirks {
+			frame_extents_on_request: true,
+			extended_sync_counter: true,
+		};
+
+		registry.insert("GNOME Shell", mutter);
+		registry.insert("Mutter", mutter);
+		registry.insert(
+			"KWin",
+			Quirks {
+				frame_extents_on_request: true,
+				extended_sync_counter: false,
+			},
+		);
+
+		registry
+	}
+
+	/// Creates a `QuirkRegistry` with no known window managers.
+	pub fn empty() -> Self {
+		Self {
+			quirks: HashMap::new(),
+		}
+	}
+
+	/// Sets the quirks of the window manager called `name`.
+	pub fn insert(&mut self, name: &str, quirks: Quirks) {
+		self.quirks.insert(name.to_owned(), quirks);
+	}
+
+	/// Returns the quirks of the window manager called `name`, or the
+	/// [default](Quirks::default) quirks if it isn't known.
+	pub fn get(&self, name: &str) -> Quirks {
+		self.quirks.get(name).copied().unwrap_or_default()
+	}
+}
+
+/// Reads a single window from `property` on `window`.
+async fn window_property(
+	connection: &mut impl RoundTrip, window: Window, property: Atom,
+) -> Result<Option<Window>, ReplyError> {
+	let value = property::get_property(connection, window, property, Atom::WINDOW)
+		.await?
+		.and_then(|value| value.to_u32s());
+
+	Ok(match value.as_deref() {
+		Some(&[window, ..]) => Some(Window(window)),
+		_ => None,
+	})
+}
+
+/// Reads which window the window manager considers active.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::active_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn active_window(connection: &mut impl RoundTrip) -> Result<Option<Window>, ReplyError> {
+	let net_active_window = atoms::atom(connection, "_NET_ACTIVE_WINDOW").await?;
+	let root = connection.writer().root_window();
+
+	// The property is `None` while no window is active.
+	Ok(window_property(connection, root, net_active_window)
+		.await?
+		.filter(|window| window.0 != 0))
+}
+
+/// Identifies the running window manager.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::detect_wm`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn detect_wm(
+	connection: &mut impl RoundTrip,
+) -> Result<Option<WindowManager>, ReplyError> {
+	let check = atoms::atom(connection, "_NET_SUPPORTING_WM_CHECK").await?;
+	let root = connection.writer().root_window();
+
+	let Some(check_window) = window_property(connection, root, check).await? else {
+		return Ok(None);
+	};
+
+	// A window manager which has exited may leave the property on the root
+	// window, so the child window must exist and point to itself.
+	match window_property(connection, check_window, check).await {
+		Ok(Some(window)) if window == check_window => {},
+
+		Ok(_) | Err(ReplyError::X11(_)) => return Ok(None),
+		Err(error) => return Err(error),
+	}
+
+	let net_wm_name = atoms::atom(connection, "_NET_WM_NAME").await?;
+	let name = property::get_property(connection, check_window, net_wm_name, Atom::NONE)
+		.await?
+		.map(|value| String::from_utf8_lossy(&value.data).into_owned())
+		.unwrap_or_default();
+
+	Ok(Some(WindowManager { check_window, name }))
+}
+
+/// Sends a 32-bit format `ClientMessage` about `window` to the root window,
+/// where the window manager receives it.
+pub(crate) async fn send_root_message(
+	connection: &mut impl RoundTrip, window: Window, message_type: Atom, data: [u32; 5],
+) -> Result<(), ReplyError> {
+	let root = connection.writer().root_window();
+
+	let event = ClientMessageEvent {
+		window,
+		message_type,
+		data: ClientMessageData::U32(data),
+	};
+
+	request::send_core(
+		connection.writer(),
+		&SendEvent {
+			propagate: false,
+			destination: root,
+			event_mask: EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+			event: event.to_bytes(),
+		},
+	)
+	.await
+	.map_err(ReplyError::Io)?;
+
+	Ok(())
+}
+
+/// Asks the window manager to activate `window`.
+///
+/// With a [`RequestWriter`], the reply to `InternAtom` is [awaited directly].
+///
+/// See [`Client::activate_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn activate_window(
+	connection: &mut impl RoundTrip, window: Window, source: ActivationSource,
+	timestamp: Timestamp, active: Option<Window>,
+) -> Result<(), ReplyError> {
+	let net_active_window = atoms::atom(connection, "_NET_ACTIVE_WINDOW").await?;
+
+	let data = [
+		source as u32,
+		timestamp.0,
+		active.map_or(0, |active| active.0),
+		0,
+		0,
+	];
+
+	send_root_message(connection, window, net_active_window, data).await
+}
+
+/// The widths of the decorations the window manager adds around a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FrameExtents {
+	/// The width of the decorations left of the window.
+	pub left: u32,
+	/// The width of the decorations right of the window.
+	pub right: u32,
+	/// The height of the decorations above the window.
+	pub top: u32,
+	/// The height of the decorations below the window.
+	pub bottom: u32,
+}
+
+impl FrameExtents {
+	/// Returns the area covered by a window's frame, given the area covered
+	/// by the window itself.
+	///
+	/// Returns `None` if the frame's area can't be represented by a
+	/// [`Rectangle`].
+	pub fn frame_rectangle(&self, client: Rectangle) -> Option<Rectangle> {
+		let [left, right, top, bottom] = self.edges()?;
+
+		Rectangle::from_edges(
+			client.left() - left,
+			client.top() - top,
+			client.right() + right,
+			client.bottom() + bottom,
+		)
+	}
+
+	/// Returns the area covered by a window itself, given the area covered by
+	/// its frame.
+	///
+	/// Returns `None` if the frame is too small to contain its decorations.
+	pub fn client_rectangle(&self, frame: Rectangle) -> Option<Rectangle> {
+		let [left, right, top, bottom] = self.edges()?;
+
+		Rectangle::from_edges(
+			frame.left() + left,
+			frame.top() + top,
+			frame.right() - right,
+			frame.bottom() - bottom,
+		)
+	}
+
+	/// The extents as `i32`s, in the order they appear in the property.
+	///
+	/// Returns `None` if any is wider than a [`Rectangle`] can be.
+	fn edges(&self) -> Option<[i32; 4]> {
+		Some([
+			i32::from(u16::try_from(self.left).ok()?),
+			i32::from(u16::try_from(self.right).ok()?),
+			i32::from(u16::try_from(self.top).ok()?),
+			i32::from(u16::try_from(self.bottom).ok()?),
+		])
+	}
+}
+
+/// Reads the widths of the decorations the window manager has added
+/// around `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::frame_extents`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn frame_extents(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Option<FrameExtents>, ReplyError> {
+	let net_frame_extents = atoms::atom(connection, "_NET_FRAME_EXTENTS").await?;
+
+	let values = property::get_property(connection, window, net_frame_extents, Atom::CARDINAL)
+		.await?
+		.and_then(|value| value.to_u32s());
+
+	Ok(match values.as_deref() {
+		Some(&[left, right, top, bottom, ..]) => Some(FrameExtents {
+			left,
+			right,
+			top,
+			bottom,
+		}),
+
+		_ => None,
+	})
+}
+
+/// Asks the window manager to set the `_NET_FRAME_EXTENTS` of `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::request_frame_extents`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn request_frame_extents(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<(), ReplyError> {
+	let net_request_frame_extents = atoms::atom(connection, "_NET_REQUEST_FRAME_EXTENTS").await?;
+
+	send_root_message(connection, window, net_request_frame_extents, [0; 5]).await
+}
+
+/// Sets the `_NET_WM_USER_TIME` property of `window`.
+///
+/// With a [`RequestWriter`], the reply to `InternAtom` is [awaited directly].
+///
+/// See [`Client::set_user_time`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_user_time(
+	connection: &mut impl RoundTrip, window: Window, timestamp: Timestamp,
+) -> Result<(), ReplyError> {
+	let net_wm_user_time = atoms::atom(connection, "_NET_WM_USER_TIME").await?;
+
+	window
+		.set_property(
+			connection.writer(),
+			net_wm_user_time,
+			&Property::u32s(Atom::CARDINAL, &[timestamp.0]),
+			PropertyMode::Replace,
+		)
+		.await
+		.map_err(ReplyError::Io)
+}
+
+/// Reads `window`'s title from its `_NET_WM_NAME`, or its `WM_NAME` if that
+/// isn't set.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::window_title`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn window_title(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Option<String>, ReplyError> {
+	let net_wm_name = atoms::atom(connection, "_NET_WM_NAME").await?;
+
+	read_title(connection, window, net_wm_name).await
+}
+
+/// Reads `window`'s title, preferring `_NET_WM_NAME`, which is always UTF-8,
+/// to `WM_NAME`.
+async fn read_title(
+	connection: &mut impl RoundTrip, window: Window, net_wm_name: Atom,
+) -> Result<Option<String>, ReplyError> {
+	if let Some(title) = property::get_property(connection, window, net_wm_name, Atom::NONE)
+		.await?
+		.and_then(|value| value.to_text())
+	{
+		return Ok(Some(title));
+	}
+
+	Ok(
+		property::get_property(connection, window, Atom::WM_NAME, Atom::NONE)
+			.await?
+			.and_then(|value| value.to_text()),
+	)
+}
+
+/// Adds `PropertyChange` to the events this client has selected on `window`,
+/// keeping those it had already selected.
+async fn select_property_changes(
+	writer: &mut RequestWriter, window: Window,
+) -> Result<(), ReplyError> {
+	let state = request::reply_to(writer, &GetWindowAttributes(window)).await?;
+
+	let attributes = WindowAttributes {
+		event_mask: Some(state.your_event_mask | EventMask::PROPERTY_CHANGE),
+		..WindowAttributes::default()
+	};
+
+	window
+		.set_attributes(writer, &attributes)
+		.await
+		.map_err(ReplyError::Io)
+}
+
+/// Shortens `title` to at most `max_chars` characters, replacing the end
+/// with `…` if it is longer.
+///
+/// ```
+/// # use xrs::ewmh::ellipsize;
+/// assert_eq!(ellipsize("Terminal", 16), "Terminal");
+/// assert_eq!(ellipsize("~/src/xrs — Terminal", 8), "~/src/x…");
+/// ```
+pub fn ellipsize(title: &str, max_chars: usize) -> Cow<'_, str> {
+	if title.chars().nth(max_chars).is_none() {
+		return Cow::Borrowed(title);
+	}
+	let Some(kept) = max_chars.checked_sub(1) else {
+		return Cow::Borrowed("");
+	};
+
+	Cow::Owned(title.chars().take(kept).chain(['…']).collect())
+}
+
+impl Window {
+	/// Watches the window's title, returning a [`TitleStream`] which reads it
+	/// again whenever its `_NET_WM_NAME` or `WM_NAME` changes.
+	///
+	/// `PropertyChange` is added to the events this client has selected on
+	/// the window, and the window's current title is read. Replies are
+	/// [awaited directly], so the `PropertyNotify` events are received from
+	/// `events`, and its [`EventReader`] must have been [spawned].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	/// [`EventReader`]: crate::EventReader
+	/// [spawned]: crate::EventReader::spawn
+	pub async fn title_stream(
+		self, events: EventStream, mut writer: RequestWriter,
+	) -> Result<TitleStream, ReplyError> {
+		let net_wm_name = atoms::atom(&mut writer, "_NET_WM_NAME").await?;
+
+		// The events are selected first, so that no change after the title is
+		// read is missed.
+		select_property_changes(&mut writer, self).await?;
+		let title = read_title(&mut writer, self, net_wm_name).await?;
+
+		Ok(TitleStream {
+			events,
+			writer,
+			window: self,
+			net_wm_name,
+			title: title.unwrap_or_default(),
+			max_chars: None,
+		})
+	}
+}
+
+/// A window's titles, read again whenever its `_NET_WM_NAME` or `WM_NAME`
+/// changes.
+///
+/// The title is read from `_NET_WM_NAME` if it is set, and from `WM_NAME`
+/// otherwise, so taskbars don't have to watch both. A window with neither
+/// has an empty title.
+///
+/// See [`Window::title_stream`].
+pub struct TitleStream {
+	events: EventStream,
+	writer: RequestWriter,
+	window: Window,
+	net_wm_name: Atom,
+
+	/// The current title, before it is ellipsized.
+	title: String,
+	/// The number of characters titles are ellipsized to, if any.
+	max_chars: Option<usize>,
+}
+
+impl TitleStream {
+	/// Returns the window's current title.
+	pub fn title(&self) -> Cow<'_, str> {
+		match self.max_chars {
+			Some(max_chars) => ellipsize(&self.title, max_chars),
+			None => Cow::Borrowed(&self.title),
+		}
+	}
+
+	/// Sets the number of characters titles are [ellipsized] to, or `None`
+	/// to return them in full.
+	///
+	/// [ellipsized]: ellipsize
+	pub fn set_max_chars(&mut self, max_chars: Option<usize>) {
+		self.max_chars = max_chars;
+	}
+
+	/// Returns the [`RequestWriter`] the titles are read with, to send other
+	/// requests.
+	pub fn writer(&mut self) -> &mut RequestWriter {
+		&mut self.writer
+	}
+
+	/// Stops watching the title, returning the [`EventStream`] and
+	/// [`RequestWriter`].
+	///
+	/// `PropertyChange` remains selected on the window.
+	pub fn into_parts(self) -> (EventStream, RequestWriter) {
+		(self.events, self.writer)
+	}
+
+	/// Receives the window's title once it has changed.
+	///
+	/// Other events are discarded, as are errors not claimed by a
+	/// [`ReplyCookie`].
+	///
+	/// Returns `None` once the connection has been closed.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub async fn recv(&mut self) -> Option<Result<Cow<'_, str>, ReplyError>> {
+		loop {
+			let atoms = [self.net_wm_name, Atom::WM_NAME];
+			if let Err(error) = property_changed(&mut self.events, self.window, &atoms).await? {
+				return Some(Err(ReplyError::Io(error)));
+			}
+
+			let title = match read_title(&mut self.writer, self.window, self.net_wm_name).await {
+				Ok(title) => title.unwrap_or_default(),
+				Err(error) => return Some(Err(error)),
+			};
+
+			// Setting `WM_NAME` doesn't change the title while `_NET_WM_NAME`
+			// is set.
+			if title != self.title {
+				self.title = title;
+
+				return Some(Ok(self.title()));
+			}
+		}
+	}
+}
+
+/// Waits for a `PropertyNotify` event for one of `atoms` on `window`.
+///
+/// Other events are discarded, as are errors not claimed by a
+/// [`ReplyCookie`]. Returns `None` once the connection has been closed.
+///
+/// [`ReplyCookie`]: crate::ReplyCookie
+async fn property_changed(
+	events: &mut EventStream, window: Window, atoms: &[Atom],
+) -> Option<io::Result<()>> {
+	loop {
+		let frame = match events.recv().await? {
+			Ok(frame) => frame,
+			Err(error) => return Some(Err(error)),
+		};
+
+		match Event::from_frame(&frame) {
+			Some(Event::PropertyNotify(event))
+				if event.window == window && atoms.contains(&event.atom) =>
+			{
+				return Some(Ok(()));
+			},
+
+			_ => {},
+		}
+	}
+}
+
+/// Reads the windows managed by the window manager from the root window's
+/// `_NET_CLIENT_LIST`, in the order they were mapped.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::client_list`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn client_list(connection: &mut impl RoundTrip) -> Result<Vec<Window>, ReplyError> {
+	let net_client_list = atoms::atom(connection, "_NET_CLIENT_LIST").await?;
+	let root = connection.writer().root_window();
+
+	read_client_list(connection, root, net_client_list).await
+}
+
+/// Reads the `_NET_CLIENT_LIST` of `root`, or no windows if it isn't set.
+async fn read_client_list(
+	connection: &mut impl RoundTrip, root: Window, net_client_list: Atom,
+) -> Result<Vec<Window>, ReplyError> {
+	Ok(
+		property::get_property(connection, root, net_client_list, Atom::WINDOW)
+			.await?
+			.and_then(|value| value.to_windows())
+			.unwrap_or_default(),
+	)
+}
+
+/// Watches the windows managed by the window manager, returning a
+/// [`ClientListStream`] which reports the windows added to and removed from
+/// the root window's `_NET_CLIENT_LIST`.
+///
+/// `PropertyChange` is added to the events this client has selected on the
+/// root window, and the current list is read. Replies are [awaited
+/// directly], so the `PropertyNotify` events are received from `events`,
+/// and its [`EventReader`] must have been [spawned].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+/// [`EventReader`]: crate::EventReader
+/// [spawned]: crate::EventReader::spawn
+pub async fn client_list_stream(
+	events: EventStream, mut writer: RequestWriter,
+) -> Result<ClientListStream, ReplyError> {
+	let net_client_list = atoms::atom(&mut writer, "_NET_CLIENT_LIST").await?;
+	let root = writer.root_window();
+
+	select_property_changes(&mut writer, root).await?;
+	let clients = read_client_list(&mut writer, root, net_client_list).await?;
+
+	Ok(ClientListStream {
+		events,
+		writer,
+		root,
+		net_client_list,
+		clients,
+	})
+}
+
+/// The windows added to and removed from `_NET_CLIENT_LIST` by one change.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ClientListChange {
+	/// The windows newly managed by the window manager, in the order they
+	/// appear in the list.
+	pub added: Vec<Window>,
+	/// The windows no longer managed by the window manager, in the order
+	/// they appeared in the list.
+	pub removed: Vec<Window>,
+}
+
+impl ClientListChange {
+	/// Returns the windows added to and removed from `old` to give `new`.
+	pub fn between(old: &[Window], new: &[Window]) -> Self {
+		let old_windows: HashSet<_> = old.iter().collect();
+		let new_windows: HashSet<_> = new.iter().collect();
+
+		Self {
+			added: new
+				.iter()
+				.filter(|window| !old_windows.contains(window))
+				.copied()
+				.collect(),
+			removed: old
+				.iter()
+				.filter(|window| !new_windows.contains(window))
+				.copied()
+				.collect(),
+		}
+	}
+
+	/// Whether no windows were added or removed.
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty()
+	}
+}
+
+/// The changes to the windows managed by the window manager, as listed in
+/// the root window's `_NET_CLIENT_LIST`.
+///
+/// Each change is reported as the windows added and removed, rather than
+/// the whole list, so that taskbars only update the buttons which changed.
+/// Changes which only restack windows are not reported.
+///
+/// See [`client_list_stream`].
+pub struct ClientListStream {
+	events: EventStream,
+	writer: RequestWriter,
+	root: Window,
+	net_client_list: Atom,
+
+	/// The current list.
+	clients: Vec<Window>,
+}
+
+impl ClientListStream {
+	/// Returns the windows currently managed by the window manager.
+	pub fn clients(&self) -> &[Window] {
+		&self.clients
+	}
+
+	/// Returns the [`RequestWriter`] the list is read with, to send other
+	/// requests.
+	pub fn writer(&mut self) -> &mut RequestWriter {
+		&mut self.writer
+	}
+
+	/// Stops watching the list, returning the [`EventStream`] and
+	/// [`RequestWriter`].
+	///
+	/// `PropertyChange` remains selected on the root window.
+	pub fn into_parts(self) -> (EventStream, RequestWriter) {
+		(self.events, self.writer)
+	}
+
+	/// Receives the next change to the list.
+	///
+	/// Other events are discarded, as are errors not claimed by a
+	/// [`ReplyCookie`].
+	///
+	/// Returns `None` once the connection has been closed.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub async fn recv(&mut self) -> Option<Result<ClientListChange, ReplyError>> {
+		loop {
+			let atoms = [self.net_client_list];
+			if let Err(error) = property_changed(&mut self.events, self.root, &atoms).await? {
+				return Some(Err(ReplyError::Io(error)));
+			}
+
+			let clients =
+				match read_client_list(&mut self.writer, self.root, self.net_client_list).await {
+					Ok(clients) => clients,
+					Err(error) => return Some(Err(error)),
+				};
+
+			let change = ClientListChange::between(&self.clients, &clients);
+			self.clients = clients;
+
+			if !change.is_empty() {
+				return Some(Ok(change));
+			}
+		}
+	}
+}
+
+impl Client {
+	/// Asks the window manager to activate `window`, by sending a
+	/// `_NET_ACTIVE_WINDOW` client message to the root window.
+	///
+	/// `timestamp` should be that of the user input event which caused the
+	/// activation, such as from a [`UserTime`]; window managers may ignore
+	/// activations with [`Timestamp::CURRENT_TIME`]. `active` is the client's
+	/// currently active window, if it has one.
+	pub async fn activate_window(
+		&mut self, window: Window, source: ActivationSource, timestamp: Timestamp,
+		active: Option<Window>,
+	) -> Result<(), ReplyError> {
+		activate_window(self, window, source, timestamp, active).await
+	}
+
+	/// Reads which window the window manager considers active, from the
+	/// `_NET_ACTIVE_WINDOW` property of the root window.
+	///
+	/// Returns `None` if no window is active.
+	pub async fn active_window(&mut self) -> Result<Option<Window>, ReplyError> {
+		active_window(self).await
+	}
+
+	/// Sets the `_NET_WM_USER_TIME` property of `window` to the timestamp of
+	/// the last user input event it received.
+	///
+	/// Window managers use this to decide whether a newly mapped window
+	/// should be given the focus.
+	pub async fn set_user_time(
+		&mut self, window: Window, timestamp: Timestamp,
+	) -> Result<(), ReplyError> {
+		set_user_time(self, window, timestamp).await
+	}
+
+	/// Identifies the running window manager, from the
+	/// `_NET_SUPPORTING_WM_CHECK` window it sets on the root window.
+	///
+	/// Returns `None` if no EWMH-compliant window manager is running.
+	pub async fn detect_wm(&mut self) -> Result<Option<WindowManager>, ReplyError> {
+		detect_wm(self).await
+	}
+
+	/// Reads the widths of the decorations the window manager has added
+	/// around `window`, from its `_NET_FRAME_EXTENTS` property.
+	///
+	/// Returns `None` if the property isn't set: the window may not have been
+	/// mapped yet, in which case see [`request_frame_extents`].
+	///
+	/// [`request_frame_extents`]: Client::request_frame_extents
+	pub async fn frame_extents(
+		&mut self, window: Window,
+	) -> Result<Option<FrameExtents>, ReplyError> {
+		frame_extents(self, window).await
+	}
+
+	/// Asks the window manager to set the `_NET_FRAME_EXTENTS` of `window`,
+	/// which hasn't been mapped yet, to the decorations it would add.
+	///
+	/// Not every window manager answers this before the window is mapped;
+	/// see [`Quirks::frame_extents_on_request`]. Wait for a `PropertyNotify`
+	/// event for `_NET_FRAME_EXTENTS` before reading them.
+	pub async fn request_frame_extents(&mut self, window: Window) -> Result<(), ReplyError> {
+		request_frame_extents(self, window).await
+	}
+
+	/// Reads `window`'s icons from its `_NET_WM_ICON` property.
+	///
+	/// Returns no icons if the property isn't set. See [`best_icon`] for
+	/// choosing which to draw.
+	pub async fn window_icons(&mut self, window: Window) -> Result<Vec<Icon>, ReplyError> {
+		window_icons(self, window).await
+	}
+
+	/// Reads `window`'s title from its `_NET_WM_NAME`, or its `WM_NAME` if
+	/// that isn't set.
+	///
+	/// Returns `None` if neither is set. See [`Window::title_stream`] for
+	/// watching the title for changes.
+	pub async fn window_title(&mut self, window: Window) -> Result<Option<String>, ReplyError> {
+		window_title(self, window).await
+	}
+
+	/// Reads the windows managed by the window manager from the root
+	/// window's `_NET_CLIENT_LIST`, in the order they were mapped.
+	///
+	/// Returns no windows if the property isn't set. See
+	/// [`client_list_stream`] for watching the list for changes.
+	pub async fn client_list(&mut self) -> Result<Vec<Window>, ReplyError> {
+		client_list(self).await
+	}
+}
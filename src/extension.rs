@@ -0,0 +1,313 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Querying which extensions the X server supports.
+//!
+//! Extension requests are sent with the extension's major opcode, and its
+//! events and errors are numbered from its first event and error codes, all
+//! of which are assigned by the X server. [`Client::query_extension`] looks
+//! them up, remembering the answer for the rest of the connection.
+//!
+//! Most extensions also ask the client which version of them it supports
+//! before accepting its requests. This crate tells each extension the
+//! highest version of it that it uses, once per connection, as X servers
+//! such as Xorg replace a client's version each time it is given. Requests
+//! needing a newer version than the X server supports fail with
+//! [`ReplyError::UnsupportedVersion`] without being sent.
+//!
+//! Some features prefer an extension but can work without it, such as
+//! listing monitors, which falls back from RandR to Xinerama. Rather than
+//! failing, they use the fallback and record a [`Degradation`], which
+//! [`Client::degradations`] reports.
+
+use crate::{
+	client::RoundTrip,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, Reply, Request},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{error::Error, fmt};
+use tokio::io;
+
+/// The major opcode of the `QueryExtension` request.
+const QUERY_EXTENSION: u8 = 98;
+
+/// The opcodes and codes assigned to an extension by the X server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtensionInfo {
+	/// The major opcode of the extension's requests.
+	pub major_opcode: u8,
+	/// The code of the extension's first event, if it has any.
+	pub first_event: u8,
+	/// The code of the extension's first error, if it has any.
+	pub first_error: u8,
+}
+
+/// An extension's version, supported by the X server, is older than that
+/// needed for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnsupportedVersion {
+	/// The name of the extension.
+	pub extension: &'static str,
+	/// The version needed, as its major and minor version.
+	pub required: (u32, u32),
+	/// The version negotiated with the X server.
+	pub supported: (u32, u32),
+}
+
+impl fmt::Display for UnsupportedVersion {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"the request needs {} {}.{}, but the X server supports {}.{}",
+			self.extension, self.required.0, self.required.1, self.supported.0, self.supported.1,
+		)
+	}
+}
+
+impl Error for UnsupportedVersion {}
+
+/// A feature which fell back to a less capable way of working, as the X
+/// server doesn't support the extension it prefers.
+///
+/// See [`Client::degradations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Degradation {
+	/// What fell back, such as `"monitors"`.
+	pub feature: &'static str,
+	/// The extension, and version if it matters, which was preferred.
+	pub preferred: &'static str,
+	/// What was used instead.
+	pub fallback: &'static str,
+}
+
+impl fmt::Display for Degradation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} used {} in place of {}",
+			self.feature, self.fallback, self.preferred,
+		)
+	}
+}
+
+/// The `QueryExtension` request, whose reply is whether the extension called
+/// `name` is present, and if so, its [`ExtensionInfo`].
+///
+/// See [`Client::query_extension`], which caches the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryExtension<'a> {
+	pub name: &'a str,
+}
+
+impl<'a> QueryExtension<'a> {
+	/// Returns a `QueryExtension` request for the extension called `name`, or
+	/// an error if `name` is too long to be sent.
+	fn new(name: &'a str) -> io::Result<Self> {
+		if u16::try_from(name.len()).is_err() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"extension name is too long",
+			));
+		}
+
+		Ok(Self { name })
+	}
+}
+
+impl Request for QueryExtension<'_> {
+	type Reply = ExtensionPresence;
+
+	const NAME: &'static str = "QueryExtension";
+	const OPCODE: u8 = QUERY_EXTENSION;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		// Names which are too long are rejected by `QueryExtension::new`; a
+		// longer name given directly is truncated.
+		body.put_u16(self.name.len() as u16);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_slice(self.name.as_bytes());
+	}
+}
+
+/// The [`ExtensionInfo`] of an extension, or `None` if it is not present.
+///
+/// This is the reply to a `QueryExtension` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtensionPresence(pub Option<ExtensionInfo>);
+
+assert_wire_size!("QueryExtension reply": [u8, u8, u16, u32, u8, u8, u8, u8, [u8; 20]] == 32);
+
+impl Reply for ExtensionPresence {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[present, major_opcode, first_event, first_error] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self((present != 0).then_some(ExtensionInfo {
+			major_opcode,
+			first_event,
+			first_error,
+		})))
+	}
+}
+
+/// Queries the extension called `name`, using the cached answer if it has
+/// been queried before.
+pub(crate) async fn query_extension(
+	connection: &mut impl RoundTrip, name: &str,
+) -> Result<Option<ExtensionInfo>, ReplyError> {
+	if let Some(info) = connection.writer().extensions.get(name) {
+		return Ok(*info);
+	}
+
+	// This isn't sent with `request::reply_to`, which looks up extensions
+	// with this function.
+	let request = QueryExtension::new(name).map_err(ReplyError::Io)?;
+	let cookie = connection
+		.send_with_reply(request.to_raw(QUERY_EXTENSION))
+		.await
+		.map_err(ReplyError::Io)?;
+
+	let ExtensionPresence(info) = ExtensionPresence::from_reply(&connection.reply(cookie).await?)
+		.ok_or_else(|| ReplyError::malformed("QueryExtension"))?;
+
+	connection.writer().extensions.insert(name.to_owned(), info);
+
+	Ok(info)
+}
+
+/// Queries the extension called `name` like [`query_extension`], returning an
+/// [`Unsupported`] error if it is not present.
+///
+/// [`Unsupported`]: io::ErrorKind::Unsupported
+pub(crate) async fn require_extension(
+	connection: &mut impl RoundTrip, name: &str,
+) -> Result<ExtensionInfo, ReplyError> {
+	query_extension(connection, name).await?.ok_or_else(|| {
+		ReplyError::Io(io::Error::new(
+			io::ErrorKind::Unsupported,
+			format!("the X server does not support the {name} extension"),
+		))
+	})
+}
+
+/// Returns the version of the extension called `name` negotiated with the X
+/// server, sending `request` to negotiate it if it hasn't been yet.
+///
+/// `request` must ask for the highest version of the extension used by this
+/// crate, as X servers such as Xorg replace the version a client supports
+/// each time it is sent. `version` reads the version from its reply.
+#[cfg_attr(
+	not(any(
+		feature = "composite",
+		feature = "damage",
+		feature = "dri3",
+		feature = "pointer",
+		feature = "randr",
+		feature = "region",
+		feature = "render",
+		feature = "screensaver",
+		feature = "sync"
+	)),
+	allow(dead_code)
+)]
+pub(crate) async fn negotiate_version<R: Request>(
+	connection: &mut impl RoundTrip, name: &'static str, request: &R,
+	version: impl FnOnce(R::Reply) -> (u32, u32),
+) -> Result<(u32, u32), ReplyError>
+where
+	R::Reply: Reply,
+{
+	if let Some(&version) = connection.writer().versions.get(name) {
+		return Ok(version);
+	}
+
+	let version = version(request::reply_to(connection, request).await?);
+	connection.writer().versions.insert(name, version);
+
+	Ok(version)
+}
+
+/// Returns an [`UnsupportedVersion`] error if `supported`, the version of the
+/// extension called `name` negotiated with the X server, is older than
+/// `required`.
+#[cfg_attr(not(any(feature = "pointer", feature = "region")), allow(dead_code))]
+pub(crate) fn require_version(
+	name: &'static str, supported: (u32, u32), required: (u32, u32),
+) -> Result<(), ReplyError> {
+	match supported >= required {
+		true => Ok(()),
+
+		false => Err(ReplyError::UnsupportedVersion(UnsupportedVersion {
+			extension: name,
+			required,
+			supported,
+		})),
+	}
+}
+
+impl Client {
+	/// Queries whether the X server supports the extension called `name`,
+	/// and if so, which opcodes and codes it has been assigned.
+	///
+	/// The answer is cached, so only the first query for each extension makes
+	/// a round trip to the X server.
+	///
+	/// Returns `None` if the extension is not supported.
+	pub async fn query_extension(
+		&mut self, name: &str,
+	) -> Result<Option<ExtensionInfo>, ReplyError> {
+		query_extension(self, name).await
+	}
+
+	/// Returns the features which have fallen back to a less capable way of
+	/// working, as the X server doesn't support the extension they prefer,
+	/// in the order they first did so.
+	///
+	/// Each is only reported once, however many times it falls back.
+	pub fn degradations(&self) -> &[Degradation] {
+		self.writer.degradations()
+	}
+}
+
+impl RequestWriter {
+	/// Queries whether the X server supports the extension called `name`,
+	/// and if so, which opcodes and codes it has been assigned.
+	///
+	/// The reply is [awaited directly].
+	///
+	/// See [`Client::query_extension`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	pub async fn query_extension(
+		&mut self, name: &str,
+	) -> Result<Option<ExtensionInfo>, ReplyError> {
+		query_extension(self, name).await
+	}
+
+	/// Returns the features which have fallen back to a less capable way of
+	/// working, as the X server doesn't support the extension they prefer.
+	///
+	/// See [`Client::degradations`].
+	pub fn degradations(&self) -> &[Degradation] {
+		&self.degradations
+	}
+
+	/// Records that a feature has fallen back to a less capable way of
+	/// working, unless it has already been recorded.
+	#[cfg_attr(not(any(feature = "input", feature = "monitor")), allow(dead_code))]
+	pub(crate) fn degrade(&mut self, degradation: Degradation) {
+		if !self.degradations.contains(&degradation) {
+			self.degradations.push(degradation);
+		}
+	}
+}
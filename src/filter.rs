@@ -0,0 +1,435 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Filters which pick out events by their window, type and fields.
+//!
+//! Small filters are combined with [`and`], [`or`] and [`not`] into one
+//! [`Filter`], instead of nesting `match`es, and used to wait for a matching
+//! event with [`Client::wait_for_event`], or to receive only matching events
+//! from an [`EventStream`]:
+//!
+//! ```no_run
+//! # use xrs::{filter::{self, ButtonPress, Filter}, Client};
+//! # async fn example(client: &mut Client, window: xrs::window::Window) -> std::io::Result<()> {
+//! let right_click = filter::window(window)
+//!     .and(filter::event::<ButtonPress>())
+//!     .and(filter::button(3));
+//!
+//! if let Some(event) = client.wait_for_event(&right_click).await? {
+//!     println!("right-clicked: {event}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each filter and combinator is its own type, so a combined filter compiles
+//! down to the same comparisons as a hand-written `match`, without any
+//! allocation or dynamic dispatch.
+//!
+//! Closures taking an [`Event`] are filters too, to match on any other
+//! fields:
+//!
+//! ```
+//! # use xrs::{event::Event, filter::{self, Filter}};
+//! # fn example(root: xrs::window::Window) {
+//! let wide_windows = filter::reported_to(root).and(|event: &Event| {
+//!     matches!(event, Event::ConfigureNotify(configure) if configure.area.width > 800)
+//! });
+//! # }
+//! ```
+//!
+//! [`and`]: Filter::and
+//! [`or`]: Filter::or
+//! [`not`]: Filter::not
+
+use crate::{
+	atoms::Atom,
+	event::{codes, Event},
+	keyboard::Keycode,
+	raw::X11Frame,
+	window::Window,
+	Client,
+	EventStream,
+};
+use std::{
+	marker::PhantomData,
+	pin::Pin,
+	task::{Context, Poll},
+};
+use tokio::io;
+
+/// A predicate on events.
+///
+/// See the [module documentation](self).
+pub trait Filter {
+	/// Returns whether `event` matches the filter.
+	fn matches(&self, event: &Event) -> bool;
+
+	/// Returns whether `frame` is an event which matches the filter.
+	///
+	/// Replies, errors and events which can't be decoded never match.
+	fn matches_frame(&self, frame: &X11Frame) -> bool {
+		Event::from_frame(frame).is_some_and(|event| self.matches(&event))
+	}
+
+	/// Matches events which match both this filter and `other`.
+	///
+	/// `other` is only checked if this filter matches.
+	fn and<F: Filter>(self, other: F) -> And<Self, F>
+	where
+		Self: Sized,
+	{
+		And(self, other)
+	}
+
+	/// Matches events which match either this filter or `other`.
+	///
+	/// `other` is only checked if this filter doesn't match.
+	fn or<F: Filter>(self, other: F) -> Or<Self, F>
+	where
+		Self: Sized,
+	{
+		Or(self, other)
+	}
+
+	/// Matches events which don't match this filter.
+	fn not(self) -> Not<Self>
+	where
+		Self: Sized,
+	{
+		Not(self)
+	}
+}
+
+impl<F: Fn(&Event) -> bool> Filter for F {
+	fn matches(&self, event: &Event) -> bool {
+		self(event)
+	}
+}
+
+/// Matches events which match both filters.
+///
+/// See [`Filter::and`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for And<A, B> {
+	fn matches(&self, event: &Event) -> bool {
+		self.0.matches(event) && self.1.matches(event)
+	}
+}
+
+/// Matches events which match either filter.
+///
+/// See [`Filter::or`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for Or<A, B> {
+	fn matches(&self, event: &Event) -> bool {
+		self.0.matches(event) || self.1.matches(event)
+	}
+}
+
+/// Matches events which don't match a filter.
+///
+/// See [`Filter::not`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Not<F>(pub F);
+
+impl<F: Filter> Filter for Not<F> {
+	fn matches(&self, event: &Event) -> bool {
+		!self.0.matches(event)
+	}
+}
+
+/// Matches events about a window.
+///
+/// See [`window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OnWindow(pub Window);
+
+/// Matches events about `window`, as given by [`Event::window`].
+pub const fn window(window: Window) -> OnWindow {
+	OnWindow(window)
+}
+
+impl Filter for OnWindow {
+	fn matches(&self, event: &Event) -> bool {
+		event.window() == Some(self.0)
+	}
+}
+
+/// Matches events reported to a window.
+///
+/// See [`reported_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportedTo(pub Window);
+
+/// Matches events reported to `window`, as given by [`Event::event_window`].
+///
+/// Unlike [`window`], this matches the notifications about every child of
+/// `window` when `SubstructureNotify` or `SubstructureRedirect` is selected
+/// on it.
+pub const fn reported_to(window: Window) -> ReportedTo {
+	ReportedTo(window)
+}
+
+impl Filter for ReportedTo {
+	fn matches(&self, event: &Event) -> bool {
+		event.event_window() == Some(self.0)
+	}
+}
+
+/// A type of core event, such as [`ButtonPress`], for [`event`].
+pub trait EventKind {
+	/// The event's code, as returned by [`Event::code`].
+	const CODE: u8;
+}
+
+/// Matches one type of event.
+///
+/// See [`event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OfKind<K>(PhantomData<K>);
+
+/// Matches events of the type `K`, such as [`ButtonPress`].
+pub const fn event<K: EventKind>() -> OfKind<K> {
+	OfKind(PhantomData)
+}
+
+impl<K: EventKind> Filter for OfKind<K> {
+	fn matches(&self, event: &Event) -> bool {
+		event.code() == K::CODE
+	}
+}
+
+macro_rules! event_kinds {
+	($($kind:ident = $code:ident),* $(,)?) => {
+		$(
+			#[doc = concat!("The `", stringify!($kind), "` event, for [`event`].")]
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $kind;
+
+			impl EventKind for $kind {
+				const CODE: u8 = codes::$code;
+			}
+		)*
+	};
+}
+
+event_kinds! {
+	KeyPress = KEY_PRESS,
+	KeyRelease = KEY_RELEASE,
+	ButtonPress = BUTTON_PRESS,
+	ButtonRelease = BUTTON_RELEASE,
+	MotionNotify = MOTION_NOTIFY,
+
+	EnterNotify = ENTER_NOTIFY,
+	LeaveNotify = LEAVE_NOTIFY,
+	FocusIn = FOCUS_IN,
+	FocusOut = FOCUS_OUT,
+	KeymapNotify = KEYMAP_NOTIFY,
+
+	Expose = EXPOSE,
+	GraphicsExposure = GRAPHICS_EXPOSURE,
+	NoExposure = NO_EXPOSURE,
+	VisibilityNotify = VISIBILITY_NOTIFY,
+
+	CreateNotify = CREATE_NOTIFY,
+	DestroyNotify = DESTROY_NOTIFY,
+	UnmapNotify = UNMAP_NOTIFY,
+	MapNotify = MAP_NOTIFY,
+	MapRequest = MAP_REQUEST,
+	ReparentNotify = REPARENT_NOTIFY,
+	ConfigureNotify = CONFIGURE_NOTIFY,
+	ConfigureRequest = CONFIGURE_REQUEST,
+	GravityNotify = GRAVITY_NOTIFY,
+	ResizeRequest = RESIZE_REQUEST,
+	CirculateNotify = CIRCULATE_NOTIFY,
+	CirculateRequest = CIRCULATE_REQUEST,
+
+	PropertyNotify = PROPERTY_NOTIFY,
+	SelectionClear = SELECTION_CLEAR,
+	SelectionRequest = SELECTION_REQUEST,
+	SelectionNotify = SELECTION_NOTIFY,
+	ColormapNotify = COLORMAP_NOTIFY,
+	ClientMessage = CLIENT_MESSAGE,
+	MappingNotify = MAPPING_NOTIFY,
+}
+
+/// Matches presses and releases of a pointer button.
+///
+/// See [`button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WithButton(pub u8);
+
+/// Matches `ButtonPress` and `ButtonRelease` events for `button`, such as
+/// `3` for the right button.
+///
+/// Combine it with [`event`] to match only presses or releases.
+pub const fn button(button: u8) -> WithButton {
+	WithButton(button)
+}
+
+impl Filter for WithButton {
+	fn matches(&self, event: &Event) -> bool {
+		matches!(
+			event,
+			Event::ButtonPress(button) | Event::ButtonRelease(button) if button.detail == self.0
+		)
+	}
+}
+
+/// Matches presses and releases of a key.
+///
+/// See [`key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WithKey(pub Keycode);
+
+/// Matches `KeyPress` and `KeyRelease` events for `keycode`.
+///
+/// Combine it with [`event`] to match only presses or releases.
+pub const fn key(keycode: Keycode) -> WithKey {
+	WithKey(keycode)
+}
+
+impl Filter for WithKey {
+	fn matches(&self, event: &Event) -> bool {
+		matches!(
+			event,
+			Event::KeyPress(key) | Event::KeyRelease(key) if key.detail == self.0
+		)
+	}
+}
+
+/// Matches changes to a property.
+///
+/// See [`property`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OnProperty(pub Atom);
+
+/// Matches `PropertyNotify` events for the property called `atom`.
+pub const fn property(atom: Atom) -> OnProperty {
+	OnProperty(atom)
+}
+
+impl Filter for OnProperty {
+	fn matches(&self, event: &Event) -> bool {
+		matches!(event, Event::PropertyNotify(property) if property.atom == self.0)
+	}
+}
+
+/// Matches client messages of one type.
+///
+/// See [`message`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WithMessageType(pub Atom);
+
+/// Matches `ClientMessage` events whose message type is `message_type`, such
+/// as `WM_PROTOCOLS`.
+pub const fn message(message_type: Atom) -> WithMessageType {
+	WithMessageType(message_type)
+}
+
+impl Filter for WithMessageType {
+	fn matches(&self, event: &Event) -> bool {
+		matches!(event, Event::ClientMessage(message) if message.message_type == self.0)
+	}
+}
+
+impl Client {
+	/// Sends any held requests, then receives the next event which matches
+	/// `filter`.
+	///
+	/// Other events and errors are kept to be returned by [`next_event`]
+	/// later, so none are lost while waiting. Returns `None` if the
+	/// connection was closed by the X server.
+	///
+	/// [`next_event`]: Client::next_event
+	pub async fn wait_for_event(&mut self, filter: &impl Filter) -> io::Result<Option<Event>> {
+		let mut matched = None;
+
+		let frame = self
+			.wait_for_frame(|frame| {
+				matched = Event::from_frame(frame).filter(|event| filter.matches(event));
+
+				matched.is_some()
+			})
+			.await?;
+
+		Ok(frame.and(matched))
+	}
+}
+
+impl EventStream {
+	/// Decodes the stream's events, only keeping those which match `filter`.
+	///
+	/// Events which don't match are discarded, as are errors not claimed by a
+	/// [`ReplyCookie`]; use [`spawn_with_errors`] to receive those
+	/// separately.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	/// [`spawn_with_errors`]: crate::EventReader::spawn_with_errors
+	pub fn matching<F: Filter>(self, filter: F) -> MatchingEvents<F> {
+		MatchingEvents {
+			stream: self,
+			filter,
+		}
+	}
+}
+
+/// The events from an [`EventStream`] which match a [`Filter`].
+///
+/// See [`EventStream::matching`].
+#[derive(Debug)]
+pub struct MatchingEvents<F> {
+	stream: EventStream,
+	filter: F,
+}
+
+impl<F: Filter> MatchingEvents<F> {
+	/// Receives the next matching event.
+	///
+	/// Returns `None` once the connection has been closed and every received
+	/// event has been returned.
+	pub async fn recv(&mut self) -> Option<io::Result<Event>> {
+		loop {
+			match self.stream.recv().await? {
+				Ok(frame) => {
+					if let Some(event) = self.matching(&frame) {
+						return Some(Ok(event));
+					}
+				},
+
+				Err(error) => return Some(Err(error)),
+			}
+		}
+	}
+
+	/// Decodes `frame`, if it is an event which matches the filter.
+	fn matching(&self, frame: &X11Frame) -> Option<Event> {
+		Event::from_frame(frame).filter(|event| self.filter.matches(event))
+	}
+}
+
+impl<F: Filter + Unpin> futures_core::Stream for MatchingEvents<F> {
+	type Item = io::Result<Event>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		loop {
+			match Pin::new(&mut self.stream).poll_next(cx) {
+				Poll::Ready(Some(Ok(frame))) => {
+					if let Some(event) = self.matching(&frame) {
+						return Poll::Ready(Some(Ok(event)));
+					}
+				},
+
+				Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+}
@@ -0,0 +1,475 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Setting the input focus, and tracking its changes.
+//!
+//! [`Client::set_input_focus`] gives a window the input focus, and
+//! [`Client::get_input_focus`] queries which window has it.
+//! [`Client::set_input_focus_checked`] does both, to tell whether the focus
+//! change took effect: window managers may refuse or redirect focus changes,
+//! and the X server ignores them if their timestamp is out of date.
+//!
+//! A [`FocusTracker`] records the `FocusIn` and `FocusOut` events it is given
+//! and keeps a most-recently-focused list of windows, which is what alt-tab
+//! style window switchers need.
+//!
+//! The tracker only sees the events it is given: it can be fed from
+//! [`Client::set_raw_frame_hook`] or wherever frames are received.
+//!
+//! [`Client::set_raw_frame_hook`]: crate::Client::set_raw_frame_hook
+
+use crate::{
+	client::RoundTrip,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{collections::VecDeque, io, time::Instant};
+
+pub use crate::event::{FocusDetail, FocusMode};
+
+/// The major opcode of the `SetInputFocus` request.
+const SET_INPUT_FOCUS: u8 = 42;
+/// The major opcode of the `GetInputFocus` request.
+const GET_INPUT_FOCUS: u8 = 43;
+
+/// The event code of `FocusIn` events.
+const FOCUS_IN: u8 = 9;
+/// The event code of `FocusOut` events.
+const FOCUS_OUT: u8 = 10;
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The default maximum number of [`FocusChange`]s kept by a [`FocusTracker`].
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Whether a window gained or lost the input focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusDirection {
+	/// The window gained the input focus (a `FocusIn` event).
+	In,
+	/// The window lost the input focus (a `FocusOut` event).
+	Out,
+}
+
+/// A single recorded focus transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusChange {
+	/// The window which gained or lost the focus.
+	pub window: u32,
+	/// Whether the window gained or lost the focus.
+	pub direction: FocusDirection,
+
+	pub detail: FocusDetail,
+	pub mode: FocusMode,
+
+	/// When the focus event was recorded.
+	///
+	/// Focus events do not carry a server timestamp, so this is the time at
+	/// which the event was given to the [`FocusTracker`].
+	pub time: Instant,
+}
+
+impl FocusChange {
+	/// Reads a `FocusChange` from a `FocusIn` or `FocusOut` event frame.
+	///
+	/// Returns `None` if `frame` is not a focus event.
+	pub fn from_event(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		let direction = match code & !SEND_EVENT_MASK {
+			FOCUS_IN => FocusDirection::In,
+			FOCUS_OUT => FocusDirection::Out,
+
+			_ => return None,
+		};
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let detail = FocusDetail::from_value(chunk[0])?;
+		let window = u32::from_be_bytes([chunk[3], chunk[4], chunk[5], chunk[6]]);
+		let mode = FocusMode::from_value(chunk[7])?;
+
+		Some(Self {
+			window,
+			direction,
+
+			detail,
+			mode,
+
+			time: Instant::now(),
+		})
+	}
+
+	/// Whether this change means that `window` itself became the focused
+	/// window, as opposed to the focus moving around within it or a grab
+	/// temporarily taking it.
+	pub const fn is_window_focused(&self) -> bool {
+		matches!(self.direction, FocusDirection::In)
+			&& matches!(self.mode, FocusMode::Normal | FocusMode::WhileGrabbed)
+			&& matches!(
+				self.detail,
+				FocusDetail::Ancestor
+					| FocusDetail::Virtual
+					| FocusDetail::Nonlinear
+					| FocusDetail::NonlinearVirtual
+			)
+	}
+}
+
+/// Records focus changes and keeps track of which windows were focused most
+/// recently.
+#[derive(Debug, Clone)]
+pub struct FocusTracker {
+	/// The most recent focus changes, oldest first.
+	history: VecDeque<FocusChange>,
+	/// The maximum length of `history`.
+	capacity: usize,
+
+	/// Windows in the order they were most recently focused, most recent
+	/// first.
+	recent: Vec<u32>,
+}
+
+impl Default for FocusTracker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FocusTracker {
+	/// Creates a new `FocusTracker` which keeps the last 256 focus changes.
+	pub fn new() -> Self {
+		Self::with_capacity(DEFAULT_CAPACITY)
+	}
+
+	/// Creates a new `FocusTracker` which keeps the last `capacity` focus
+	/// changes.
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			history: VecDeque::with_capacity(capacity),
+			capacity,
+
+			recent: Vec::new(),
+		}
+	}
+
+	/// Records `frame` if it is a `FocusIn` or `FocusOut` event.
+	///
+	/// Returns the recorded change, or `None` if `frame` was not a focus
+	/// event.
+	pub fn observe(&mut self, frame: &X11Frame) -> Option<FocusChange> {
+		let change = FocusChange::from_event(frame)?;
+		self.record(change);
+
+		Some(change)
+	}
+
+	/// Records a focus change.
+	pub fn record(&mut self, change: FocusChange) {
+		if self.capacity > 0 {
+			if self.history.len() == self.capacity {
+				self.history.pop_front();
+			}
+
+			self.history.push_back(change);
+		}
+
+		if change.is_window_focused() {
+			self.recent.retain(|window| *window != change.window);
+			self.recent.insert(0, change.window);
+		}
+	}
+
+	/// Stops tracking `window`, for example once it has been destroyed.
+	pub fn forget(&mut self, window: u32) {
+		self.recent.retain(|recent| *recent != window);
+	}
+
+	/// The recorded focus changes, oldest first.
+	pub fn history(&self) -> impl Iterator<Item = &FocusChange> {
+		self.history.iter()
+	}
+
+	/// Windows in the order they were most recently focused, most recent
+	/// first.
+	pub fn recent(&self) -> &[u32] {
+		&self.recent
+	}
+
+	/// The window which is currently focused, as far as this tracker knows.
+	pub fn focused(&self) -> Option<u32> {
+		self.recent.first().copied()
+	}
+
+	/// The window which was focused before the currently focused window.
+	pub fn last_focused(&self) -> Option<u32> {
+		self.recent.get(1).copied()
+	}
+}
+
+/// What the input focus reverts to if the focused window becomes unviewable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RevertTo {
+	/// No window has the focus, so keyboard events are discarded.
+	None,
+	/// The focus follows the pointer's root window.
+	PointerRoot,
+	/// The focus moves to the closest viewable ancestor of the window.
+	///
+	/// This is what the ICCCM recommends for clients.
+	#[default]
+	Parent,
+}
+
+impl RevertTo {
+	/// Returns the `RevertTo` encoded as `value`.
+	const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::None,
+			1 => Self::PointerRoot,
+			2 => Self::Parent,
+
+			_ => return None,
+		})
+	}
+}
+
+/// What has, or is to be given, the input focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusTarget {
+	/// No window, so keyboard events are discarded.
+	None,
+	/// The root window of whichever screen the pointer is on.
+	PointerRoot,
+	/// A window, which must be viewable.
+	Window(Window),
+}
+
+impl From<Window> for FocusTarget {
+	fn from(window: Window) -> Self {
+		Self::Window(window)
+	}
+}
+
+impl FocusTarget {
+	/// Returns the `FocusTarget` encoded as `value`.
+	const fn from_value(value: u32) -> Self {
+		match value {
+			0 => Self::None,
+			1 => Self::PointerRoot,
+
+			window => Self::Window(Window(window)),
+		}
+	}
+
+	/// Returns the value this `FocusTarget` is encoded as.
+	const fn to_value(self) -> u32 {
+		match self {
+			Self::None => 0,
+			Self::PointerRoot => 1,
+
+			Self::Window(window) => window.0,
+		}
+	}
+}
+
+/// The current input focus, as reported by `GetInputFocus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputFocus {
+	pub focus: FocusTarget,
+	pub revert_to: RevertTo,
+}
+
+assert_wire_size!("GetInputFocus reply": [u8, u8, u16, u32, Window, [u8; 20]] == 32);
+
+impl Reply for InputFocus {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte: revert_to,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+		let &[f0, f1, f2, f3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			focus: FocusTarget::from_value(u32::from_be_bytes([f0, f1, f2, f3])),
+			revert_to: RevertTo::from_value(*revert_to)?,
+		})
+	}
+}
+
+/// The `GetInputFocus` request, whose reply is an [`InputFocus`].
+///
+/// See [`Client::get_input_focus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetInputFocus;
+
+impl Request for GetInputFocus {
+	type Reply = InputFocus;
+
+	const NAME: &'static str = "GetInputFocus";
+	const OPCODE: u8 = GET_INPUT_FOCUS;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(GetInputFocus: [u8, [u8; 1], u16]);
+
+/// The `SetInputFocus` request.
+///
+/// See [`Client::set_input_focus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetInputFocus {
+	pub focus: FocusTarget,
+	pub revert_to: RevertTo,
+	pub time: Timestamp,
+}
+
+impl Request for SetInputFocus {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SetInputFocus";
+	const OPCODE: u8 = SET_INPUT_FOCUS;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn metabyte(&self) -> u8 {
+		self.revert_to as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.focus.to_value());
+		body.put_u32(self.time.0);
+	}
+}
+
+assert_wire_size!(SetInputFocus: [u8, [u8; 1], u16, Window, Timestamp]);
+
+/// Whether a focus change made with [`Client::set_input_focus_checked`] took
+/// effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusOutcome {
+	/// The target has the focus.
+	Focused,
+	/// Something else has the focus: the window manager redirected it, or the
+	/// X server ignored the request because its timestamp was out of date.
+	Overridden(InputFocus),
+}
+
+impl FocusOutcome {
+	/// Returns whether the target has the focus.
+	pub const fn is_focused(&self) -> bool {
+		matches!(self, Self::Focused)
+	}
+}
+
+/// Gives `focus` the input focus.
+pub(crate) async fn set_input_focus(
+	writer: &mut RequestWriter, focus: FocusTarget, revert_to: RevertTo, time: Timestamp,
+) -> io::Result<()> {
+	request::send_core(
+		writer,
+		&SetInputFocus {
+			focus,
+			revert_to,
+			time,
+		},
+	)
+	.await
+}
+
+/// Queries which window has the input focus, and what it reverts to.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::get_input_focus`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_input_focus(connection: &mut impl RoundTrip) -> Result<InputFocus, ReplyError> {
+	request::reply_to(connection, &GetInputFocus).await
+}
+
+/// Gives `focus` the input focus, then queries the input focus to check
+/// that the change took effect.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::set_input_focus_checked`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_input_focus_checked(
+	connection: &mut impl RoundTrip, focus: impl Into<FocusTarget>, revert_to: RevertTo,
+	time: Timestamp,
+) -> Result<FocusOutcome, ReplyError> {
+	let focus = focus.into();
+
+	set_input_focus(connection.writer(), focus, revert_to, time)
+		.await
+		.map_err(ReplyError::Io)?;
+
+	// Requests are processed in order, so the reply reflects the focus change
+	// if it took effect.
+	let current = get_input_focus(connection).await?;
+
+	Ok(match current.focus == focus {
+		true => FocusOutcome::Focused,
+		false => FocusOutcome::Overridden(current),
+	})
+}
+
+impl Client {
+	/// Queries which window has the input focus, and what it reverts to.
+	pub async fn get_input_focus(&mut self) -> Result<InputFocus, ReplyError> {
+		get_input_focus(self).await
+	}
+
+	/// Gives `focus` the input focus.
+	///
+	/// `time` should be the timestamp of the event which caused the focus
+	/// change, such as a button press. The X server ignores the request if
+	/// `time` is earlier than the last focus change or later than its current
+	/// time. [`Timestamp::CURRENT_TIME`] always takes effect, but the ICCCM
+	/// asks clients not to use it, since it can steal the focus back from a
+	/// more recent change.
+	pub async fn set_input_focus(
+		&mut self, focus: impl Into<FocusTarget>, revert_to: RevertTo, time: Timestamp,
+	) -> io::Result<()> {
+		set_input_focus(self.split_mut().1, focus.into(), revert_to, time).await
+	}
+
+	/// Gives `focus` the input focus, then queries the input focus to check
+	/// that the change took effect.
+	///
+	/// See [`Client::set_input_focus`] for `time`. A window manager may still
+	/// move the focus afterwards, in response to the `FocusIn` event.
+	pub async fn set_input_focus_checked(
+		&mut self, focus: impl Into<FocusTarget>, revert_to: RevertTo, time: Timestamp,
+	) -> Result<FocusOutcome, ReplyError> {
+		set_input_focus_checked(self, focus, revert_to, time).await
+	}
+}
+
+impl RequestWriter {
+	/// Gives `focus` the input focus.
+	///
+	/// See [`Client::set_input_focus`].
+	pub async fn set_input_focus(
+		&mut self, focus: impl Into<FocusTarget>, revert_to: RevertTo, time: Timestamp,
+	) -> io::Result<()> {
+		set_input_focus(self, focus.into(), revert_to, time).await
+	}
+}
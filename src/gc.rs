@@ -0,0 +1,697 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Graphics contexts, which hold the settings used by drawing requests.
+//!
+//! A [`Gc`] is created with [`Client::create_gc`] from [`GcValues`], which
+//! are built up by chaining setters:
+//!
+//! ```no_run
+//! # use xrs::{gc::{GcValues, LineStyle}, Client};
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let root = client.root_window();
+//! let values = GcValues::new()
+//!     .foreground(0xff0000)
+//!     .line_width(2)
+//!     .line_style(LineStyle::OnOffDash);
+//!
+//! let gc = client.create_gc(root.id(), &values).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A `Gc` remembers the values it was given, so [`Gc::change`] only sends
+//! the values which have changed, and sends nothing if none have.
+
+use crate::{
+	client::RoundTrip,
+	geometry::{Point, Rectangle},
+	raw::assert_wire_size,
+	request::{self, NoReply, Request},
+	window,
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::io;
+
+/// The major opcode of the `CreateGC` request.
+const CREATE_GC: u8 = 55;
+/// The major opcode of the `ChangeGC` request.
+const CHANGE_GC: u8 = 56;
+/// The major opcode of the `CopyGC` request.
+const COPY_GC: u8 = 57;
+/// The major opcode of the `SetClipRectangles` request.
+const SET_CLIP_RECTANGLES: u8 = 59;
+/// The major opcode of the `FreeGC` request.
+const FREE_GC: u8 = 60;
+
+/// The number of values in a graphics context.
+const VALUE_COUNT: usize = 23;
+
+/// How the bits of a source pixel are combined with the bits of the
+/// destination pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Function {
+	/// `0`.
+	Clear,
+	/// `source & destination`.
+	And,
+	/// `source & !destination`.
+	AndReverse,
+	/// `source`.
+	#[default]
+	Copy,
+	/// `!source & destination`.
+	AndInverted,
+	/// `destination`.
+	NoOp,
+	/// `source ^ destination`.
+	Xor,
+	/// `source | destination`.
+	Or,
+	/// `!source & !destination`.
+	Nor,
+	/// `!source ^ destination`.
+	Equivalent,
+	/// `!destination`.
+	Invert,
+	/// `source | !destination`.
+	OrReverse,
+	/// `!source`.
+	CopyInverted,
+	/// `!source | destination`.
+	OrInverted,
+	/// `!source | !destination`.
+	Nand,
+	/// `1`.
+	Set,
+}
+
+/// Which parts of a line are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineStyle {
+	/// The whole line is drawn.
+	#[default]
+	Solid,
+	/// The even dashes are drawn.
+	OnOffDash,
+	/// The even dashes are drawn, and the odd dashes are drawn in the
+	/// background.
+	DoubleDash,
+}
+
+/// How the ends of lines are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CapStyle {
+	/// Like `Butt`, but the end point of zero-width lines is not drawn.
+	NotLast,
+	/// Lines end square at their end points.
+	#[default]
+	Butt,
+	/// Lines end in a semicircle around their end points.
+	Round,
+	/// Lines end square, extended past their end points by half the line
+	/// width.
+	Projecting,
+}
+
+/// How the corners where lines meet are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum JoinStyle {
+	/// The outer edges of the lines are extended to meet at a point.
+	#[default]
+	Miter,
+	/// The corner is rounded.
+	Round,
+	/// The corner is cut off.
+	Bevel,
+}
+
+/// How areas are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillStyle {
+	/// With the foreground.
+	#[default]
+	Solid,
+	/// With the tile.
+	Tiled,
+	/// With the foreground, where the stipple has bits set.
+	Stippled,
+	/// With the foreground where the stipple has bits set, and the
+	/// background elsewhere.
+	OpaqueStippled,
+}
+
+/// Which points are inside a self-intersecting polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillRule {
+	/// Points which a ray crosses the polygon's edges an odd number of times
+	/// to reach.
+	#[default]
+	EvenOdd,
+	/// Points which the polygon's edges wind around a non-zero number of
+	/// times.
+	Winding,
+}
+
+/// Whether drawing on a window is clipped by its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SubwindowMode {
+	/// Children obscure what is drawn.
+	#[default]
+	ClipByChildren,
+	/// What is drawn is drawn over children too.
+	IncludeInferiors,
+}
+
+/// How arcs are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ArcMode {
+	/// As the area between the arc and the line joining its ends.
+	Chord,
+	/// As the area between the arc and the lines from its ends to its center.
+	#[default]
+	PieSlice,
+}
+
+/// Values of a graphics context, for [`Client::create_gc`] and
+/// [`Gc::change`].
+///
+/// Values which are `None` are left unchanged, or given their default values
+/// when creating a graphics context. Each value has a setter of the same
+/// name, so that they can be chained:
+///
+/// ```
+/// # use xrs::gc::{Function, GcValues};
+/// let values = GcValues::new().function(Function::Xor).foreground(0xffffff);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GcValues {
+	/// How source pixels are combined with destination pixels.
+	pub function: Option<Function>,
+	/// The planes which are drawn to.
+	pub plane_mask: Option<u32>,
+	/// The pixel value drawn with.
+	pub foreground: Option<u32>,
+	/// The pixel value drawn behind stipples and double dashes.
+	pub background: Option<u32>,
+	/// The width of lines in pixels, or `0` for thin lines drawn with a fast
+	/// algorithm.
+	pub line_width: Option<u16>,
+	/// Which parts of lines are drawn.
+	pub line_style: Option<LineStyle>,
+	/// How the ends of lines are drawn.
+	pub cap_style: Option<CapStyle>,
+	/// How the corners where lines meet are drawn.
+	pub join_style: Option<JoinStyle>,
+	/// How areas are filled.
+	pub fill_style: Option<FillStyle>,
+	/// Which points are inside self-intersecting polygons.
+	pub fill_rule: Option<FillRule>,
+	/// The pixmap areas are filled with when the fill style is `Tiled`.
+	pub tile: Option<u32>,
+	/// The bitmap used when the fill style is `Stippled` or
+	/// `OpaqueStippled`.
+	pub stipple: Option<u32>,
+	/// The x coordinate of the origin of the tile or stipple.
+	pub tile_stipple_x_origin: Option<i16>,
+	/// The y coordinate of the origin of the tile or stipple.
+	pub tile_stipple_y_origin: Option<i16>,
+	/// The font text is drawn with.
+	pub font: Option<u32>,
+	/// Whether drawing on windows is clipped by their children.
+	pub subwindow_mode: Option<SubwindowMode>,
+	/// Whether `GraphicsExposure` events are generated when copying areas
+	/// which are obscured.
+	pub graphics_exposures: Option<bool>,
+	/// The x coordinate of the origin of the clip mask.
+	pub clip_x_origin: Option<i16>,
+	/// The y coordinate of the origin of the clip mask.
+	pub clip_y_origin: Option<i16>,
+	/// The bitmap outside of whose set bits nothing is drawn, or `0` (`None`)
+	/// for no clipping.
+	pub clip_mask: Option<u32>,
+	/// How far into the dash pattern lines start.
+	pub dash_offset: Option<u16>,
+	/// The length of every dash, for a pattern of equal dashes.
+	pub dashes: Option<u8>,
+	/// How arcs are filled.
+	pub arc_mode: Option<ArcMode>,
+}
+
+/// Defines a chainable setter for each of the [`GcValues`].
+macro_rules! setters {
+	($($name:ident: $type:ty),* $(,)?) => {
+		impl GcValues {
+			$(
+				#[doc = concat!("Sets [`", stringify!($name), "`](GcValues::", stringify!($name), ").")]
+				pub const fn $name(mut self, $name: $type) -> Self {
+					self.$name = Some($name);
+					self
+				}
+			)*
+		}
+	};
+}
+
+setters! {
+	function: Function,
+	plane_mask: u32,
+	foreground: u32,
+	background: u32,
+	line_width: u16,
+	line_style: LineStyle,
+	cap_style: CapStyle,
+	join_style: JoinStyle,
+	fill_style: FillStyle,
+	fill_rule: FillRule,
+	tile: u32,
+	stipple: u32,
+	tile_stipple_x_origin: i16,
+	tile_stipple_y_origin: i16,
+	font: u32,
+	subwindow_mode: SubwindowMode,
+	graphics_exposures: bool,
+	clip_x_origin: i16,
+	clip_y_origin: i16,
+	clip_mask: u32,
+	dash_offset: u16,
+	dashes: u8,
+	arc_mode: ArcMode,
+}
+
+impl GcValues {
+	/// Creates `GcValues` with every value unset.
+	pub const fn new() -> Self {
+		Self {
+			function: None,
+			plane_mask: None,
+			foreground: None,
+			background: None,
+			line_width: None,
+			line_style: None,
+			cap_style: None,
+			join_style: None,
+			fill_style: None,
+			fill_rule: None,
+			tile: None,
+			stipple: None,
+			tile_stipple_x_origin: None,
+			tile_stipple_y_origin: None,
+			font: None,
+			subwindow_mode: None,
+			graphics_exposures: None,
+			clip_x_origin: None,
+			clip_y_origin: None,
+			clip_mask: None,
+			dash_offset: None,
+			dashes: None,
+			arc_mode: None,
+		}
+	}
+
+	/// Returns the values as they are encoded in requests, in the order of
+	/// their bits in the value mask.
+	fn to_values(self) -> [Option<u32>; VALUE_COUNT] {
+		[
+			self.function.map(|function| function as u32),
+			self.plane_mask,
+			self.foreground,
+			self.background,
+			self.line_width.map(u32::from),
+			self.line_style.map(|style| style as u32),
+			self.cap_style.map(|style| style as u32),
+			self.join_style.map(|style| style as u32),
+			self.fill_style.map(|style| style as u32),
+			self.fill_rule.map(|rule| rule as u32),
+			self.tile,
+			self.stipple,
+			self.tile_stipple_x_origin.map(|x| x as u32),
+			self.tile_stipple_y_origin.map(|y| y as u32),
+			self.font,
+			self.subwindow_mode.map(|mode| mode as u32),
+			self.graphics_exposures.map(u32::from),
+			self.clip_x_origin.map(|x| x as u32),
+			self.clip_y_origin.map(|y| y as u32),
+			self.clip_mask,
+			self.dash_offset.map(u32::from),
+			self.dashes.map(u32::from),
+			self.arc_mode.map(|mode| mode as u32),
+		]
+	}
+
+	/// Returns the values which are set in these values and differ from those
+	/// in `current`.
+	fn changes_from(&self, current: &Self) -> Self {
+		let mut changes = *self;
+
+		macro_rules! unchanged {
+			($($name:ident),*) => {
+				$(
+					if changes.$name == current.$name {
+						changes.$name = None;
+					}
+				)*
+			};
+		}
+
+		unchanged!(
+			function,
+			plane_mask,
+			foreground,
+			background,
+			line_width,
+			line_style,
+			cap_style,
+			join_style,
+			fill_style,
+			fill_rule,
+			tile,
+			stipple,
+			tile_stipple_x_origin,
+			tile_stipple_y_origin,
+			font,
+			subwindow_mode,
+			graphics_exposures,
+			clip_x_origin,
+			clip_y_origin,
+			clip_mask,
+			dash_offset,
+			dashes,
+			arc_mode
+		);
+
+		changes
+	}
+
+	/// Replaces each of these values with the corresponding value in `other`,
+	/// if it is set.
+	fn update(&mut self, other: &Self) {
+		macro_rules! update {
+			($($name:ident),*) => {
+				$(
+					if other.$name.is_some() {
+						self.$name = other.$name;
+					}
+				)*
+			};
+		}
+
+		update!(
+			function,
+			plane_mask,
+			foreground,
+			background,
+			line_width,
+			line_style,
+			cap_style,
+			join_style,
+			fill_style,
+			fill_rule,
+			tile,
+			stipple,
+			tile_stipple_x_origin,
+			tile_stipple_y_origin,
+			font,
+			subwindow_mode,
+			graphics_exposures,
+			clip_x_origin,
+			clip_y_origin,
+			clip_mask,
+			dash_offset,
+			dashes,
+			arc_mode
+		);
+	}
+}
+
+/// A graphics context.
+///
+/// A `Gc` remembers the values it has been given, so that [`Gc::change`] can
+/// skip values which have not changed. Values changed by other means, such
+/// as by another client, are not noticed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Gc {
+	id: u32,
+
+	/// The values known to be set on the graphics context.
+	///
+	/// Values which are `None` have not been set, so have their defaults.
+	values: GcValues,
+}
+
+/// The `CreateGC` request.
+///
+/// See [`Client::create_gc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateGC {
+	/// The ID of the new graphics context.
+	pub gc: u32,
+	/// A window or pixmap with the root window and depth of the drawables
+	/// the graphics context is for.
+	pub drawable: u32,
+	pub values: GcValues,
+}
+
+impl Request for CreateGC {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CreateGC";
+	const OPCODE: u8 = CREATE_GC;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		let values = self.values.to_values();
+
+		body.put_u32(self.gc);
+		body.put_u32(self.drawable);
+		body.put_u32(window::value_mask(&values));
+		window::write_values(body, &values);
+	}
+}
+
+/// The `ChangeGC` request.
+///
+/// See [`Gc::change`], which only sends the values which have changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeGC {
+	pub gc: u32,
+	pub values: GcValues,
+}
+
+impl Request for ChangeGC {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ChangeGC";
+	const OPCODE: u8 = CHANGE_GC;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		let values = self.values.to_values();
+
+		body.put_u32(self.gc);
+		body.put_u32(window::value_mask(&values));
+		window::write_values(body, &values);
+	}
+}
+
+/// The `CopyGC` request.
+///
+/// See [`Gc::copy_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CopyGC {
+	pub source: u32,
+	pub destination: u32,
+	/// The values to copy, with bit `n` set to copy the `n`th value in the
+	/// order of [`GcValues`]' fields.
+	pub value_mask: u32,
+}
+
+impl Request for CopyGC {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CopyGC";
+	const OPCODE: u8 = COPY_GC;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.source);
+		body.put_u32(self.destination);
+		body.put_u32(self.value_mask);
+	}
+}
+
+assert_wire_size!(CopyGC: [u8, [u8; 1], u16, u32, u32, u32]);
+
+/// How the rectangles given to a [`SetClipRectangles`] request are sorted.
+///
+/// The X server may generate an error or clip incorrectly if they are not
+/// sorted as claimed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ClipOrdering {
+	#[default]
+	Unsorted,
+	/// Sorted by their top edges.
+	YSorted,
+	/// Sorted by their top edges, then their left edges.
+	YXSorted,
+	/// Sorted like `YXSorted`, with every rectangle in a horizontal band
+	/// having the same top and bottom edges, as the rectangles of a `Region`
+	/// are.
+	YXBanded,
+}
+
+/// The `SetClipRectangles` request, which sets a graphics context's clip mask
+/// to a list of rectangles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetClipRectangles<'a> {
+	pub ordering: ClipOrdering,
+	pub gc: u32,
+	/// The origin the rectangles are relative to.
+	pub origin: Point,
+	pub rectangles: &'a [Rectangle],
+}
+
+impl Request for SetClipRectangles<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SetClipRectangles";
+	const OPCODE: u8 = SET_CLIP_RECTANGLES;
+
+	fn metabyte(&self) -> u8 {
+		self.ordering as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.gc);
+		self.origin.write_to(body);
+
+		for rectangle in self.rectangles {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+/// The `FreeGC` request.
+///
+/// See [`Gc::free`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeGC(pub u32);
+
+impl Request for FreeGC {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "FreeGC";
+	const OPCODE: u8 = FREE_GC;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(FreeGC: [u8, [u8; 1], u16, u32]);
+
+/// Creates a graphics context for drawing on drawables like `drawable`.
+///
+/// With a [`RequestWriter`], if a new range of resource IDs has to be
+/// requested, its reply is [awaited directly].
+///
+/// See [`Client::create_gc`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_gc(
+	connection: &mut impl RoundTrip, drawable: u32, values: &GcValues,
+) -> Result<Gc, XidError> {
+	let id = xid::generate_id(connection).await?;
+
+	let request = CreateGC {
+		gc: id,
+		drawable,
+		values: *values,
+	};
+	request::send_core(connection.writer(), &request)
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(Gc {
+		id,
+		values: *values,
+	})
+}
+
+impl Client {
+	/// Creates a graphics context for drawing on drawables with the same
+	/// root window and depth as `drawable`, which is a window or pixmap.
+	///
+	/// Values which aren't given have their defaults.
+	pub async fn create_gc(&mut self, drawable: u32, values: &GcValues) -> Result<Gc, XidError> {
+		create_gc(self, drawable, values).await
+	}
+}
+
+impl Gc {
+	/// The graphics context's ID.
+	pub const fn id(&self) -> u32 {
+		self.id
+	}
+
+	/// The values known to be set on the graphics context.
+	///
+	/// Values which are `None` have not been set since it was created, so
+	/// have their defaults.
+	pub const fn values(&self) -> &GcValues {
+		&self.values
+	}
+
+	/// Changes the given values of the graphics context.
+	///
+	/// Only values which differ from those already known to be set are sent,
+	/// and no request is sent if none do.
+	pub async fn change(
+		&mut self, writer: &mut RequestWriter, values: &GcValues,
+	) -> io::Result<()> {
+		let changes = values.changes_from(&self.values);
+
+		if changes == GcValues::new() {
+			return Ok(());
+		}
+
+		let request = ChangeGC {
+			gc: self.id,
+			values: changes,
+		};
+		request::send_core(writer, &request).await?;
+
+		self.values.update(values);
+
+		Ok(())
+	}
+
+	/// Copies every value of this graphics context to `destination`, which
+	/// must have the same root window and depth.
+	pub async fn copy_to(
+		&self, writer: &mut RequestWriter, destination: &mut Gc,
+	) -> io::Result<()> {
+		/// The value mask with every value's bit set.
+		const ALL_VALUES: u32 = (1 << VALUE_COUNT) - 1;
+
+		let request = CopyGC {
+			source: self.id,
+			destination: destination.id,
+			value_mask: ALL_VALUES,
+		};
+		request::send_core(writer, &request).await?;
+
+		destination.values = self.values;
+
+		Ok(())
+	}
+
+	/// Frees the graphics context.
+	pub async fn free(self, writer: &mut RequestWriter) -> io::Result<()> {
+		request::send_core(writer, &FreeGC(self.id)).await
+	}
+}
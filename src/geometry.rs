@@ -0,0 +1,312 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Points, sizes and rectangles in the ranges used by the protocol.
+//!
+//! Coordinates in the protocol are `i16`s and lengths are `u16`s, so a
+//! rectangle's far edges can lie beyond the range of a coordinate. Edges are
+//! therefore worked with as `i32`s, and converted back either with checked
+//! conversions, which fail if the result is out of range, or with clamping
+//! conversions, which saturate to the nearest representable value.
+
+use crate::raw::WireSize;
+use bytes::BufMut;
+use std::{fmt, fmt::Formatter};
+
+/// Converts `coordinate` to an `i16`, saturating if it is out of range.
+fn clamp_coordinate(coordinate: i32) -> i16 {
+	coordinate.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+/// Converts `length` to a `u16`, saturating if it is out of range.
+fn clamp_length(length: i32) -> u16 {
+	length.clamp(0, u16::MAX.into()) as u16
+}
+
+/// A position, such as that of a window or of the pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point {
+	pub x: i16,
+	pub y: i16,
+}
+
+impl Point {
+	/// The point at `0`, `0`.
+	pub const ORIGIN: Self = Self::new(0, 0);
+
+	/// Creates a new `Point` at `x`, `y`.
+	pub const fn new(x: i16, y: i16) -> Self {
+		Self { x, y }
+	}
+
+	/// Creates a new `Point` at `x`, `y`, or returns `None` if either
+	/// coordinate is out of range.
+	pub fn checked(x: i32, y: i32) -> Option<Self> {
+		Some(Self::new(x.try_into().ok()?, y.try_into().ok()?))
+	}
+
+	/// Creates a new `Point` at `x`, `y`, clamping each coordinate to the
+	/// range of an `i16`.
+	pub fn clamped(x: i32, y: i32) -> Self {
+		Self::new(clamp_coordinate(x), clamp_coordinate(y))
+	}
+
+	/// Returns this point moved by `dx`, `dy`, or `None` if the result is out
+	/// of range.
+	///
+	/// ```
+	/// # use xrs::geometry::Point;
+	/// assert_eq!(Point::new(1, 2).checked_offset(3, -4), Some(Point::new(4, -2)));
+	/// assert_eq!(Point::new(1, 0).checked_offset(i32::MAX, 0), None);
+	/// ```
+	pub fn checked_offset(self, dx: i32, dy: i32) -> Option<Self> {
+		Self::checked(
+			i32::from(self.x).checked_add(dx)?,
+			i32::from(self.y).checked_add(dy)?,
+		)
+	}
+
+	/// Returns this point moved by `dx`, `dy`, clamping the result to the range
+	/// of an `i16`.
+	pub fn saturating_offset(self, dx: i32, dy: i32) -> Self {
+		Self::clamped(
+			i32::from(self.x).saturating_add(dx),
+			i32::from(self.y).saturating_add(dy),
+		)
+	}
+
+	/// Writes this point as a protocol `POINT`.
+	pub fn write_to(&self, buf: &mut impl BufMut) {
+		buf.put_i16(self.x);
+		buf.put_i16(self.y);
+	}
+}
+
+impl WireSize for Point {
+	const WIRE_SIZE: usize = 2 * i16::WIRE_SIZE;
+}
+
+impl WireSize for Size {
+	const WIRE_SIZE: usize = 2 * u16::WIRE_SIZE;
+}
+
+impl WireSize for Rectangle {
+	const WIRE_SIZE: usize = Point::WIRE_SIZE + Size::WIRE_SIZE;
+}
+
+/// Formats the point as `(x, y)`.
+impl fmt::Display for Point {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "({}, {})", self.x, self.y)
+	}
+}
+
+/// The dimensions of something, such as a window or an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Size {
+	pub width: u16,
+	pub height: u16,
+}
+
+impl Size {
+	/// Creates a new `Size` of `width` by `height`.
+	pub const fn new(width: u16, height: u16) -> Self {
+		Self { width, height }
+	}
+
+	/// Creates a new `Size` of `width` by `height`, or returns `None` if
+	/// either length is out of range.
+	pub fn checked(width: i32, height: i32) -> Option<Self> {
+		Some(Self::new(width.try_into().ok()?, height.try_into().ok()?))
+	}
+
+	/// Creates a new `Size` of `width` by `height`, clamping each length to
+	/// the range of a `u16`.
+	pub fn clamped(width: i32, height: i32) -> Self {
+		Self::new(clamp_length(width), clamp_length(height))
+	}
+
+	/// Whether this size covers no area.
+	pub const fn is_empty(&self) -> bool {
+		self.width == 0 || self.height == 0
+	}
+
+	/// The number of pixels covered by this size.
+	pub const fn area(&self) -> u32 {
+		(self.width as u32) * (self.height as u32)
+	}
+}
+
+/// Formats the size as `{width}x{height}`.
+impl fmt::Display for Size {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}x{}", self.width, self.height)
+	}
+}
+
+/// A rectangular area.
+///
+/// This is the protocol's `RECTANGLE`: its position is that of its top-left
+/// corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rectangle {
+	pub x: i16,
+	pub y: i16,
+
+	pub width: u16,
+	pub height: u16,
+}
+
+impl Rectangle {
+	/// Creates a new `Rectangle` with its top-left corner at `position`.
+	pub const fn new(position: Point, size: Size) -> Self {
+		Self {
+			x: position.x,
+			y: position.y,
+
+			width: size.width,
+			height: size.height,
+		}
+	}
+
+	/// Returns the rectangle between the given edges, or `None` if it cannot
+	/// be represented.
+	///
+	/// `right` and `bottom` are one past the last column and row covered by
+	/// the rectangle.
+	///
+	/// ```
+	/// # use xrs::geometry::Rectangle;
+	/// let rectangle = Rectangle::from_edges(-1, 0, 9, 5).unwrap();
+	/// assert_eq!((rectangle.x, rectangle.width), (-1, 10));
+	///
+	/// assert_eq!(Rectangle::from_edges(-1, 0, i32::MAX, 1), None);
+	/// ```
+	pub fn from_edges(left: i32, top: i32, right: i32, bottom: i32) -> Option<Self> {
+		Some(Self::new(
+			Point::checked(left, top)?,
+			Size::checked(right.checked_sub(left)?, bottom.checked_sub(top)?)?,
+		))
+	}
+
+	/// Returns the rectangle between the given edges, clamped to the area
+	/// that a `Rectangle` can represent.
+	///
+	/// `right` and `bottom` are one past the last column and row covered by
+	/// the rectangle.
+	///
+	/// ```
+	/// # use xrs::geometry::Rectangle;
+	/// let rectangle = Rectangle::from_edges_clamped(-1, i32::MIN, i32::MAX, 1);
+	/// assert_eq!((rectangle.x, rectangle.width), (-1, u16::MAX));
+	/// assert_eq!((rectangle.y, rectangle.height), (i16::MIN, 32769));
+	/// ```
+	pub fn from_edges_clamped(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+		let position = Point::clamped(left, top);
+
+		Self::new(
+			position,
+			Size::clamped(
+				right.saturating_sub(i32::from(position.x)),
+				bottom.saturating_sub(i32::from(position.y)),
+			),
+		)
+	}
+
+	/// The position of this rectangle's top-left corner.
+	pub const fn position(&self) -> Point {
+		Point::new(self.x, self.y)
+	}
+
+	/// The size of this rectangle.
+	pub const fn size(&self) -> Size {
+		Size::new(self.width, self.height)
+	}
+
+	/// Whether this rectangle covers no area.
+	pub const fn is_empty(&self) -> bool {
+		self.size().is_empty()
+	}
+
+	/// The coordinate of the leftmost column covered by this rectangle.
+	pub fn left(&self) -> i32 {
+		i32::from(self.x)
+	}
+
+	/// The coordinate of the topmost row covered by this rectangle.
+	pub fn top(&self) -> i32 {
+		i32::from(self.y)
+	}
+
+	/// The coordinate one past the rightmost column covered by this
+	/// rectangle.
+	pub fn right(&self) -> i32 {
+		self.left() + i32::from(self.width)
+	}
+
+	/// The coordinate one past the bottommost row covered by this rectangle.
+	pub fn bottom(&self) -> i32 {
+		self.top() + i32::from(self.height)
+	}
+
+	/// Whether `point` lies within this rectangle.
+	pub fn contains(&self, point: Point) -> bool {
+		let (x, y) = (i32::from(point.x), i32::from(point.y));
+
+		self.left() <= x && x < self.right() && self.top() <= y && y < self.bottom()
+	}
+
+	/// Returns the area covered by both this rectangle and `other`, or `None`
+	/// if they do not overlap.
+	pub fn intersection(&self, other: &Self) -> Option<Self> {
+		let (left, top) = (self.left().max(other.left()), self.top().max(other.top()));
+		let (right, bottom) = (
+			self.right().min(other.right()),
+			self.bottom().min(other.bottom()),
+		);
+
+		if left >= right || top >= bottom {
+			return None;
+		}
+
+		Self::from_edges(left, top, right, bottom)
+	}
+
+	/// Returns the smallest rectangle containing both this rectangle and
+	/// `other`, clamped to the area that a `Rectangle` can represent.
+	pub fn bounding(&self, other: &Self) -> Self {
+		Self::from_edges_clamped(
+			self.left().min(other.left()),
+			self.top().min(other.top()),
+			self.right().max(other.right()),
+			self.bottom().max(other.bottom()),
+		)
+	}
+
+	/// Returns this rectangle moved by `dx`, `dy`, or `None` if its position
+	/// would be out of range.
+	pub fn checked_offset(&self, dx: i32, dy: i32) -> Option<Self> {
+		Some(Self::new(
+			self.position().checked_offset(dx, dy)?,
+			self.size(),
+		))
+	}
+
+	/// Writes this rectangle as a protocol `RECTANGLE`.
+	pub fn write_to(&self, buf: &mut impl BufMut) {
+		self.position().write_to(buf);
+
+		buf.put_u16(self.width);
+		buf.put_u16(self.height);
+	}
+}
+
+/// Formats the rectangle as X geometry strings are written:
+/// `{width}x{height}+{x}+{y}`, with negative coordinates written as `-`
+/// instead of `+`.
+impl fmt::Display for Rectangle {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}x{}{:+}{:+}", self.width, self.height, self.x, self.y)
+	}
+}
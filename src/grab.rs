@@ -0,0 +1,796 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Grabbing the pointer, the keyboard, buttons, keys and the server.
+//!
+//! Active grabs, made with [`Client::grab_pointer`] and
+//! [`Client::grab_keyboard`], take control of a device immediately, such as
+//! while dragging something. Passive grabs, made with [`Client::grab_button`]
+//! and [`Client::grab_key`], only become active grabs when the button or key
+//! is pressed, which is how window managers bind shortcuts.
+//!
+//! Grabbing the server with [`Client::grab_server`] stops the X server from
+//! processing requests from other clients until it is ungrabbed, so that a
+//! window manager can inspect and change windows without them changing in
+//! between. Forgetting to ungrab the server freezes every other client, so
+//! the grab is held by a [`ServerGrabGuard`] which ungrabs the server when
+//! it is dropped, such as when an error is returned early with `?`:
+//!
+//! ```no_run
+//! # use xrs::{geometry::Point, window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window, frame: Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let grab = client.grab_server().await?;
+//!
+//! // If either of these fail, dropping `grab` still ungrabs the server.
+//! window
+//!     .reparent(client.split_mut().1, frame, Point { x: 0, y: 0 })
+//!     .await?;
+//! frame.map(client.split_mut().1).await?;
+//!
+//! grab.release(client.split_mut().1).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+	client::RoundTrip,
+	event_mask::EventMask,
+	keyboard::Keycode,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+/// The major opcode of the `GrabPointer` request.
+const GRAB_POINTER: u8 = 26;
+/// The major opcode of the `UngrabPointer` request.
+const UNGRAB_POINTER: u8 = 27;
+/// The major opcode of the `GrabButton` request.
+const GRAB_BUTTON: u8 = 28;
+/// The major opcode of the `UngrabButton` request.
+const UNGRAB_BUTTON: u8 = 29;
+/// The major opcode of the `GrabKeyboard` request.
+const GRAB_KEYBOARD: u8 = 31;
+/// The major opcode of the `UngrabKeyboard` request.
+const UNGRAB_KEYBOARD: u8 = 32;
+/// The major opcode of the `GrabKey` request.
+const GRAB_KEY: u8 = 33;
+/// The major opcode of the `UngrabKey` request.
+const UNGRAB_KEY: u8 = 34;
+/// The major opcode of the `GrabServer` request.
+const GRAB_SERVER: u8 = 36;
+/// The major opcode of the `UngrabServer` request.
+const UNGRAB_SERVER: u8 = 37;
+
+/// The `AnyButton` and `AnyKey` wildcards.
+const ANY: u8 = 0;
+
+/// The modifier mask matching any combination of modifiers, including none,
+/// for [`Client::grab_button`] and [`Client::grab_key`].
+pub const ANY_MODIFIER: u16 = 0x8000;
+
+/// The result of a `GrabPointer` or `GrabKeyboard` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GrabStatus {
+	Success,
+	/// Another client has grabbed the device.
+	///
+	/// Under Xwayland, this is also the result of grabbing the keyboard if
+	/// none of the client's windows are focused.
+	AlreadyGrabbed,
+	/// The grab's time is earlier than the last grab or later than the X
+	/// server's current time.
+	InvalidTime,
+	/// The grab window or `confine_to` window isn't viewable.
+	NotViewable,
+	/// Another client has frozen the device with a synchronous grab.
+	Frozen,
+}
+
+/// How a device's events are processed while it is grabbed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GrabMode {
+	/// Events are frozen after the grab activates until the grabbing client
+	/// sends an `AllowEvents` request.
+	Synchronous,
+	/// Events are processed as usual.
+	#[default]
+	Asynchronous,
+}
+
+/// Options for grabbing the pointer with [`Client::grab_pointer`] or a
+/// button with [`Client::grab_button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PointerGrab {
+	/// Whether pointer events are reported to the client's windows as usual,
+	/// instead of only to the grab window.
+	pub owner_events: bool,
+	/// The pointer events reported to the grab window.
+	pub event_mask: EventMask,
+
+	pub pointer_mode: GrabMode,
+	pub keyboard_mode: GrabMode,
+
+	/// The window to confine the pointer to while it is grabbed, if any.
+	pub confine_to: Option<Window>,
+	/// The cursor shown while the pointer is grabbed, or `None` to keep the
+	/// cursor of the window the pointer is in.
+	pub cursor: Option<u32>,
+
+	/// When the grab was made, such as the time of the event which caused
+	/// it.
+	///
+	/// Passive grabs activate at the time of the button press instead, so
+	/// this is ignored by [`Client::grab_button`].
+	pub time: Timestamp,
+}
+
+impl Default for PointerGrab {
+	fn default() -> Self {
+		Self {
+			owner_events: false,
+			event_mask: EventMask::NONE,
+
+			pointer_mode: GrabMode::Asynchronous,
+			keyboard_mode: GrabMode::Asynchronous,
+
+			confine_to: None,
+			cursor: None,
+
+			time: Timestamp::CURRENT_TIME,
+		}
+	}
+}
+
+/// Options for grabbing the keyboard with [`Client::grab_keyboard`] or a key
+/// with [`Client::grab_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyboardGrab {
+	/// Whether key events are reported to the client's windows as usual,
+	/// instead of only to the grab window.
+	pub owner_events: bool,
+
+	pub pointer_mode: GrabMode,
+	pub keyboard_mode: GrabMode,
+
+	/// When the grab was made, such as the time of the event which caused
+	/// it.
+	///
+	/// Passive grabs activate at the time of the key press instead, so this
+	/// is ignored by [`Client::grab_key`].
+	pub time: Timestamp,
+}
+
+impl Default for KeyboardGrab {
+	fn default() -> Self {
+		Self {
+			owner_events: false,
+
+			pointer_mode: GrabMode::Asynchronous,
+			keyboard_mode: GrabMode::Asynchronous,
+
+			time: Timestamp::CURRENT_TIME,
+		}
+	}
+}
+
+/// Keeps the server grabbed until it is [released] or dropped.
+///
+/// Dropping the guard can't send the `UngrabServer` request itself, so it is
+/// sent before the next request from the same connection, or the next time
+/// the [`Client`] reads a frame or requests are flushed. Releasing the guard
+/// sends it immediately.
+///
+/// Server grabs don't nest: releasing any guard ungrabs the server.
+///
+/// See [`Client::grab_server`].
+///
+/// [released]: ServerGrabGuard::release
+#[derive(Debug)]
+#[must_use = "the server is ungrabbed as soon as the guard is dropped"]
+pub struct ServerGrabGuard {
+	/// The connection's flag which asks for `UngrabServer` to be sent.
+	ungrab: Arc<AtomicBool>,
+	/// Whether `UngrabServer` has already been sent.
+	released: bool,
+}
+
+impl ServerGrabGuard {
+	/// Ungrabs the server.
+	pub async fn release(mut self, writer: &mut RequestWriter) -> io::Result<()> {
+		self.released = true;
+
+		request::send_core(writer, &UngrabServer).await
+	}
+}
+
+impl Drop for ServerGrabGuard {
+	fn drop(&mut self) {
+		if !self.released {
+			self.ungrab.store(true, Ordering::Release);
+		}
+	}
+}
+
+impl GrabMode {
+	/// Returns the mode's value in the protocol.
+	pub(crate) const fn value(self) -> u8 {
+		match self {
+			Self::Synchronous => 0,
+			Self::Asynchronous => 1,
+		}
+	}
+}
+
+assert_wire_size!("GrabPointer reply": [u8, u8, u16, u32, [u8; 24]] == 32);
+
+impl Reply for GrabStatus {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte: status, ..
+		} = frame
+		else {
+			return None;
+		};
+
+		Self::from_value(*status)
+	}
+}
+
+impl GrabStatus {
+	/// Returns the status with the value `status` in the protocol.
+	pub(crate) const fn from_value(status: u8) -> Option<Self> {
+		Some(match status {
+			0 => Self::Success,
+			1 => Self::AlreadyGrabbed,
+			2 => Self::InvalidTime,
+			3 => Self::NotViewable,
+			4 => Self::Frozen,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Puts the event mask, modes, `confine_to` window and cursor of a pointer
+/// grab, which `GrabPointer` and `GrabButton` share.
+fn put_pointer_grab(body: &mut BytesMut, grab: &PointerGrab) {
+	// Pointer events are all in the lower 16 bits of the event mask.
+	body.put_u16(grab.event_mask.0 as u16);
+	body.put_u8(grab.pointer_mode.value());
+	body.put_u8(grab.keyboard_mode.value());
+	body.put_u32(grab.confine_to.map_or(0, Window::id));
+	body.put_u32(grab.cursor.unwrap_or(0));
+}
+
+/// The `GrabPointer` request.
+///
+/// See [`Client::grab_pointer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabPointer {
+	pub window: Window,
+	pub grab: PointerGrab,
+}
+
+impl Request for GrabPointer {
+	type Reply = GrabStatus;
+
+	const NAME: &'static str = "GrabPointer";
+	const OPCODE: u8 = GRAB_POINTER;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn metabyte(&self) -> u8 {
+		self.grab.owner_events.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		put_pointer_grab(body, &self.grab);
+		body.put_u32(self.grab.time.0);
+	}
+}
+
+assert_wire_size!(GrabPointer: [u8, bool, u16, Window, u16, u8, u8, Window, u32, Timestamp]);
+
+/// The `UngrabPointer` request.
+///
+/// See [`Client::ungrab_pointer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UngrabPointer {
+	pub time: Timestamp,
+}
+
+impl Request for UngrabPointer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "UngrabPointer";
+	const OPCODE: u8 = UNGRAB_POINTER;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.time.0);
+	}
+}
+
+assert_wire_size!(UngrabPointer: [u8, [u8; 1], u16, Timestamp]);
+
+/// The `GrabButton` request.
+///
+/// See [`Client::grab_button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabButton {
+	pub window: Window,
+	/// The button to grab, or `None` for every button.
+	pub button: Option<u8>,
+	pub modifiers: u16,
+	pub grab: PointerGrab,
+}
+
+impl Request for GrabButton {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "GrabButton";
+	const OPCODE: u8 = GRAB_BUTTON;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn metabyte(&self) -> u8 {
+		self.grab.owner_events.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		put_pointer_grab(body, &self.grab);
+		body.put_u8(self.button.unwrap_or(ANY));
+		// 1 unused byte.
+		body.put_u8(0);
+		body.put_u16(self.modifiers);
+	}
+}
+
+assert_wire_size!(GrabButton: [u8, bool, u16, Window, u16, u8, u8, Window, u32, u8, [u8; 1], u16]);
+
+/// The `UngrabButton` request.
+///
+/// See [`Client::ungrab_button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UngrabButton {
+	pub window: Window,
+	/// The button to ungrab, or `None` for every button.
+	pub button: Option<u8>,
+	pub modifiers: u16,
+}
+
+impl Request for UngrabButton {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "UngrabButton";
+	const OPCODE: u8 = UNGRAB_BUTTON;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn metabyte(&self) -> u8 {
+		self.button.unwrap_or(ANY)
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u16(self.modifiers);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(UngrabButton: [u8, u8, u16, Window, u16, [u8; 2]]);
+
+/// The `GrabKeyboard` request.
+///
+/// See [`Client::grab_keyboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabKeyboard {
+	pub window: Window,
+	pub grab: KeyboardGrab,
+}
+
+impl Request for GrabKeyboard {
+	type Reply = GrabStatus;
+
+	const NAME: &'static str = "GrabKeyboard";
+	const OPCODE: u8 = GRAB_KEYBOARD;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn metabyte(&self) -> u8 {
+		self.grab.owner_events.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u32(self.grab.time.0);
+		body.put_u8(self.grab.pointer_mode.value());
+		body.put_u8(self.grab.keyboard_mode.value());
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(GrabKeyboard: [u8, bool, u16, Window, Timestamp, u8, u8, [u8; 2]]);
+
+/// The `UngrabKeyboard` request.
+///
+/// See [`Client::ungrab_keyboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UngrabKeyboard {
+	pub time: Timestamp,
+}
+
+impl Request for UngrabKeyboard {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "UngrabKeyboard";
+	const OPCODE: u8 = UNGRAB_KEYBOARD;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.time.0);
+	}
+}
+
+assert_wire_size!(UngrabKeyboard: [u8, [u8; 1], u16, Timestamp]);
+
+/// The `GrabKey` request.
+///
+/// See [`Client::grab_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabKey {
+	pub window: Window,
+	/// The key to grab, or `None` for every key.
+	pub key: Option<Keycode>,
+	pub modifiers: u16,
+	pub grab: KeyboardGrab,
+}
+
+impl Request for GrabKey {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "GrabKey";
+	const OPCODE: u8 = GRAB_KEY;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn metabyte(&self) -> u8 {
+		self.grab.owner_events.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u16(self.modifiers);
+		body.put_u8(self.key.unwrap_or(ANY));
+		body.put_u8(self.grab.pointer_mode.value());
+		body.put_u8(self.grab.keyboard_mode.value());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(GrabKey: [u8, bool, u16, Window, u16, Keycode, u8, u8, [u8; 3]]);
+
+/// The `UngrabKey` request.
+///
+/// See [`Client::ungrab_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UngrabKey {
+	pub window: Window,
+	/// The key to ungrab, or `None` for every key.
+	pub key: Option<Keycode>,
+	pub modifiers: u16,
+}
+
+impl Request for UngrabKey {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "UngrabKey";
+	const OPCODE: u8 = UNGRAB_KEY;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn metabyte(&self) -> u8 {
+		self.key.unwrap_or(ANY)
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u16(self.modifiers);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(UngrabKey: [u8, Keycode, u16, Window, u16, [u8; 2]]);
+
+/// The `GrabServer` request.
+///
+/// Prefer [`Client::grab_server`], whose guard ungrabs the server when it is
+/// dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabServer;
+
+impl Request for GrabServer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "GrabServer";
+	const OPCODE: u8 = GRAB_SERVER;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(GrabServer: [u8, [u8; 1], u16]);
+
+/// The `UngrabServer` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UngrabServer;
+
+impl Request for UngrabServer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "UngrabServer";
+	const OPCODE: u8 = UNGRAB_SERVER;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(UngrabServer: [u8, [u8; 1], u16]);
+
+/// Actively grabs the pointer for `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::grab_pointer`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn grab_pointer(
+	connection: &mut impl RoundTrip, window: Window, grab: &PointerGrab,
+) -> Result<GrabStatus, ReplyError> {
+	request::reply_to(
+		connection,
+		&GrabPointer {
+			window,
+			grab: *grab,
+		},
+	)
+	.await
+}
+
+/// Actively grabs the keyboard for `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::grab_keyboard`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn grab_keyboard(
+	connection: &mut impl RoundTrip, window: Window, grab: &KeyboardGrab,
+) -> Result<GrabStatus, ReplyError> {
+	request::reply_to(
+		connection,
+		&GrabKeyboard {
+			window,
+			grab: *grab,
+		},
+	)
+	.await
+}
+
+impl Client {
+	/// Actively grabs the pointer, so that pointer events are reported to
+	/// `window` until the grab is released with [`ungrab_pointer`].
+	///
+	/// The X server also releases the grab if `window`, or the `confine_to`
+	/// window, becomes unviewable.
+	///
+	/// [`ungrab_pointer`]: Client::ungrab_pointer
+	pub async fn grab_pointer(
+		&mut self, window: Window, grab: &PointerGrab,
+	) -> Result<GrabStatus, ReplyError> {
+		grab_pointer(self, window, grab).await
+	}
+
+	/// Releases the pointer if this client has grabbed it.
+	///
+	/// The grab is only released if `time` isn't earlier than when it was
+	/// made.
+	pub async fn ungrab_pointer(&mut self, time: Timestamp) -> io::Result<()> {
+		self.writer.ungrab_pointer(time).await
+	}
+
+	/// Actively grabs the keyboard, so that key events are reported to
+	/// `window` until the grab is released with [`ungrab_keyboard`].
+	///
+	/// The X server also releases the grab if `window` becomes unviewable.
+	///
+	/// [`ungrab_keyboard`]: Client::ungrab_keyboard
+	pub async fn grab_keyboard(
+		&mut self, window: Window, grab: &KeyboardGrab,
+	) -> Result<GrabStatus, ReplyError> {
+		grab_keyboard(self, window, grab).await
+	}
+
+	/// Releases the keyboard if this client has grabbed it.
+	///
+	/// The grab is only released if `time` isn't earlier than when it was
+	/// made.
+	pub async fn ungrab_keyboard(&mut self, time: Timestamp) -> io::Result<()> {
+		self.writer.ungrab_keyboard(time).await
+	}
+
+	/// Passively grabs `button` on `window`, so that the pointer is grabbed
+	/// whenever `button` is pressed with exactly `modifiers` held while the
+	/// pointer is in `window`.
+	///
+	/// `None` grabs every button, and [`ANY_MODIFIER`] matches any
+	/// combination of modifiers. Lock modifiers such as NumLock count as
+	/// modifiers too, so shortcuts are usually grabbed once for each
+	/// combination of [lock masks].
+	///
+	/// The X server generates an `Access` error if another client has already
+	/// grabbed the same combination on `window`.
+	///
+	/// [lock masks]: crate::keyboard::ModifierMasks::lock_masks
+	pub async fn grab_button(
+		&mut self, window: Window, button: Option<u8>, modifiers: u16, grab: &PointerGrab,
+	) -> io::Result<()> {
+		self.writer
+			.grab_button(window, button, modifiers, grab)
+			.await
+	}
+
+	/// Releases a passive grab of `button` with `modifiers` on `window`.
+	///
+	/// `None` and [`ANY_MODIFIER`] match every button and combination of
+	/// modifiers, like in [`grab_button`].
+	///
+	/// [`grab_button`]: Client::grab_button
+	pub async fn ungrab_button(
+		&mut self, window: Window, button: Option<u8>, modifiers: u16,
+	) -> io::Result<()> {
+		self.writer.ungrab_button(window, button, modifiers).await
+	}
+
+	/// Passively grabs `key` on `window`, so that the keyboard is grabbed
+	/// whenever `key` is pressed with exactly `modifiers` held while `window`
+	/// or one of its descendants has the input focus.
+	///
+	/// `None` grabs every key, and [`ANY_MODIFIER`] matches any combination
+	/// of modifiers. See [`grab_button`] for lock modifiers.
+	///
+	/// The X server generates an `Access` error if another client has already
+	/// grabbed the same combination on `window`.
+	///
+	/// [`grab_button`]: Client::grab_button
+	pub async fn grab_key(
+		&mut self, window: Window, key: Option<Keycode>, modifiers: u16, grab: &KeyboardGrab,
+	) -> io::Result<()> {
+		self.writer.grab_key(window, key, modifiers, grab).await
+	}
+
+	/// Releases a passive grab of `key` with `modifiers` on `window`.
+	///
+	/// `None` and [`ANY_MODIFIER`] match every key and combination of
+	/// modifiers, like in [`grab_key`].
+	///
+	/// [`grab_key`]: Client::grab_key
+	pub async fn ungrab_key(
+		&mut self, window: Window, key: Option<Keycode>, modifiers: u16,
+	) -> io::Result<()> {
+		self.writer.ungrab_key(window, key, modifiers).await
+	}
+
+	/// Grabs the server, so that requests from other clients aren't
+	/// processed until it is ungrabbed.
+	///
+	/// The server stays grabbed until the returned [`ServerGrabGuard`] is
+	/// released or dropped, or this client disconnects. Requests which take
+	/// long, such as waiting for another client, shouldn't be made while the
+	/// server is grabbed, as they will never complete.
+	pub async fn grab_server(&mut self) -> io::Result<ServerGrabGuard> {
+		self.writer.grab_server().await
+	}
+}
+
+impl RequestWriter {
+	/// Releases the pointer if this client has grabbed it.
+	///
+	/// See [`Client::ungrab_pointer`].
+	pub async fn ungrab_pointer(&mut self, time: Timestamp) -> io::Result<()> {
+		request::send_core(self, &UngrabPointer { time }).await
+	}
+
+	/// Releases the keyboard if this client has grabbed it.
+	///
+	/// See [`Client::ungrab_keyboard`].
+	pub async fn ungrab_keyboard(&mut self, time: Timestamp) -> io::Result<()> {
+		request::send_core(self, &UngrabKeyboard { time }).await
+	}
+
+	/// Passively grabs `button` on `window`.
+	///
+	/// See [`Client::grab_button`].
+	pub async fn grab_button(
+		&mut self, window: Window, button: Option<u8>, modifiers: u16, grab: &PointerGrab,
+	) -> io::Result<()> {
+		let request = GrabButton {
+			window,
+			button,
+			modifiers,
+			grab: *grab,
+		};
+
+		request::send_core(self, &request).await
+	}
+
+	/// Releases a passive grab of `button` on `window`.
+	///
+	/// See [`Client::ungrab_button`].
+	pub async fn ungrab_button(
+		&mut self, window: Window, button: Option<u8>, modifiers: u16,
+	) -> io::Result<()> {
+		let request = UngrabButton {
+			window,
+			button,
+			modifiers,
+		};
+
+		request::send_core(self, &request).await
+	}
+
+	/// Passively grabs `key` on `window`.
+	///
+	/// See [`Client::grab_key`].
+	pub async fn grab_key(
+		&mut self, window: Window, key: Option<Keycode>, modifiers: u16, grab: &KeyboardGrab,
+	) -> io::Result<()> {
+		let request = GrabKey {
+			window,
+			key,
+			modifiers,
+			grab: *grab,
+		};
+
+		request::send_core(self, &request).await
+	}
+
+	/// Releases a passive grab of `key` on `window`.
+	///
+	/// See [`Client::ungrab_key`].
+	pub async fn ungrab_key(
+		&mut self, window: Window, key: Option<Keycode>, modifiers: u16,
+	) -> io::Result<()> {
+		let request = UngrabKey {
+			window,
+			key,
+			modifiers,
+		};
+
+		request::send_core(self, &request).await
+	}
+
+	/// Grabs the server.
+	///
+	/// See [`Client::grab_server`].
+	pub async fn grab_server(&mut self) -> io::Result<ServerGrabGuard> {
+		request::send_core(self, &GrabServer).await?;
+
+		Ok(ServerGrabGuard {
+			ungrab: Arc::clone(&self.ungrab_server),
+			released: false,
+		})
+	}
+}
@@ -0,0 +1,602 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Helpers for the Inter-Client Communication Conventions Manual (ICCCM).
+//!
+//! Clients describe their top-level windows to the window manager with
+//! properties: [`WindowHints`] in `WM_HINTS`, [`SizeHints`] in
+//! `WM_NORMAL_HINTS`, a [`ClassHint`] in `WM_CLASS` and the protocols they
+//! take part in in `WM_PROTOCOLS`. The hints are set with [`Window`] methods
+//! and read with [`Client`] methods.
+//!
+//! Window managers ask clients to close a window or take the focus by sending
+//! them `WM_PROTOCOLS` client messages, which [`Client::protocol_message`]
+//! decodes and [`ProtocolMessage::answer`] answers.
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	focus::{self, FocusTarget, RevertTo},
+	geometry::{Point, Size},
+	property::{self, Property, PropertyMode},
+	raw::X11Frame,
+	time::Timestamp,
+	window::{Gravity, Window},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use std::io;
+
+/// The event code of `ClientMessage` events.
+const CLIENT_MESSAGE: u8 = 33;
+/// The bit set in the code of events sent with `SendEvent`.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The number of values in a `WM_HINTS` property.
+const WINDOW_HINTS_LENGTH: usize = 9;
+/// The number of values in a `WM_SIZE_HINTS` property.
+const SIZE_HINTS_LENGTH: usize = 18;
+
+/// The state a window is in, or asks to be mapped in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowState {
+	/// The window is not managed by the window manager.
+	Withdrawn = 0,
+	/// The window is visible.
+	Normal = 1,
+	/// The window is iconified: it is hidden, and represented by an icon.
+	Iconic = 3,
+}
+
+impl WindowState {
+	/// Returns the `WindowState` encoded as `value`.
+	const fn from_value(value: u32) -> Option<Self> {
+		match value {
+			0 => Some(Self::Withdrawn),
+			1 => Some(Self::Normal),
+			3 => Some(Self::Iconic),
+
+			_ => None,
+		}
+	}
+}
+
+/// Hints about a window other than its size and position, stored in its
+/// `WM_HINTS` property.
+///
+/// Hints which are `None` are not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WindowHints {
+	/// Whether the client relies on the window manager to give the window
+	/// the input focus.
+	pub input: Option<bool>,
+	/// The state the window should be in when it is first mapped.
+	pub initial_state: Option<WindowState>,
+	/// The pixmap to use as the window's icon.
+	pub icon_pixmap: Option<u32>,
+	/// The window to use as the window's icon.
+	pub icon_window: Option<Window>,
+	/// Where the window's icon should be placed on the root window.
+	pub icon_position: Option<Point>,
+	/// The bitmap of which pixels of the [`icon_pixmap`] to draw.
+	///
+	/// [`icon_pixmap`]: WindowHints::icon_pixmap
+	pub icon_mask: Option<u32>,
+	/// The leader of the group of windows this window belongs to.
+	pub window_group: Option<Window>,
+
+	/// Whether the window needs the user's attention.
+	pub urgent: bool,
+}
+
+impl WindowHints {
+	const INPUT: u32 = 1 << 0;
+	const STATE: u32 = 1 << 1;
+	const ICON_PIXMAP: u32 = 1 << 2;
+	const ICON_WINDOW: u32 = 1 << 3;
+	const ICON_POSITION: u32 = 1 << 4;
+	const ICON_MASK: u32 = 1 << 5;
+	const WINDOW_GROUP: u32 = 1 << 6;
+	const URGENCY: u32 = 1 << 8;
+
+	/// Decodes `WindowHints` from the values of a `WM_HINTS` property.
+	///
+	/// Missing values, which some older clients omit, are treated as zero.
+	fn from_values(values: &[u32]) -> Option<Self> {
+		let (&flags, _) = values.split_first()?;
+		let value = |i: usize| values.get(i).copied().unwrap_or(0);
+		let set = |flag: u32| flags & flag != 0;
+
+		Some(Self {
+			input: set(Self::INPUT).then(|| value(1) != 0),
+			initial_state: set(Self::STATE)
+				.then(|| WindowState::from_value(value(2)))
+				.flatten(),
+			icon_pixmap: set(Self::ICON_PIXMAP).then(|| value(3)),
+			icon_window: set(Self::ICON_WINDOW).then(|| Window(value(4))),
+			icon_position: set(Self::ICON_POSITION)
+				.then(|| Point::clamped(value(5) as i32, value(6) as i32)),
+			icon_mask: set(Self::ICON_MASK).then(|| value(7)),
+			window_group: set(Self::WINDOW_GROUP).then(|| Window(value(8))),
+
+			urgent: set(Self::URGENCY),
+		})
+	}
+
+	/// Encodes the hints as the values of a `WM_HINTS` property.
+	fn to_values(self) -> [u32; WINDOW_HINTS_LENGTH] {
+		let mut flags = 0;
+		let mut flag = |flag: u32, set: bool| {
+			if set {
+				flags |= flag;
+			}
+		};
+
+		flag(Self::INPUT, self.input.is_some());
+		flag(Self::STATE, self.initial_state.is_some());
+		flag(Self::ICON_PIXMAP, self.icon_pixmap.is_some());
+		flag(Self::ICON_WINDOW, self.icon_window.is_some());
+		flag(Self::ICON_POSITION, self.icon_position.is_some());
+		flag(Self::ICON_MASK, self.icon_mask.is_some());
+		flag(Self::WINDOW_GROUP, self.window_group.is_some());
+		flag(Self::URGENCY, self.urgent);
+
+		let icon_position = self.icon_position.unwrap_or_default();
+
+		[
+			flags,
+			u32::from(self.input.unwrap_or_default()),
+			self.initial_state.map_or(0, |state| state as u32),
+			self.icon_pixmap.unwrap_or_default(),
+			self.icon_window.map_or(0, Window::id),
+			i32::from(icon_position.x) as u32,
+			i32::from(icon_position.y) as u32,
+			self.icon_mask.unwrap_or_default(),
+			self.window_group.map_or(0, Window::id),
+		]
+	}
+}
+
+/// A ratio of width to height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AspectRatio {
+	pub numerator: u32,
+	pub denominator: u32,
+}
+
+/// Hints about a window's size and position, stored in its
+/// `WM_NORMAL_HINTS` property.
+///
+/// Hints which are `None` are not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SizeHints {
+	/// The position the window asks to be placed at.
+	pub position: Option<Point>,
+	/// The size the window asks to be given.
+	pub size: Option<Size>,
+	/// Whether the [`position`] and [`size`] were chosen by the user rather
+	/// than by the program.
+	///
+	/// [`position`]: SizeHints::position
+	/// [`size`]: SizeHints::size
+	pub user_specified: bool,
+
+	/// The smallest size the window can usefully be.
+	pub min_size: Option<Size>,
+	/// The largest size the window can usefully be.
+	pub max_size: Option<Size>,
+	/// The steps in which the window prefers to be resized, from its
+	/// [`base_size`].
+	///
+	/// [`base_size`]: SizeHints::base_size
+	pub resize_increment: Option<Size>,
+	/// The smallest and largest aspect ratios the window can usefully have.
+	pub aspect: Option<(AspectRatio, AspectRatio)>,
+	/// The size the window's [`resize_increment`]s are counted from.
+	///
+	/// [`resize_increment`]: SizeHints::resize_increment
+	pub base_size: Option<Size>,
+	/// Which point of the window's frame its [`position`] refers to.
+	///
+	/// [`position`]: SizeHints::position
+	pub gravity: Option<Gravity>,
+}
+
+impl SizeHints {
+	const USER_POSITION: u32 = 1 << 0;
+	const USER_SIZE: u32 = 1 << 1;
+	const PROGRAM_POSITION: u32 = 1 << 2;
+	const PROGRAM_SIZE: u32 = 1 << 3;
+	const MIN_SIZE: u32 = 1 << 4;
+	const MAX_SIZE: u32 = 1 << 5;
+	const RESIZE_INCREMENT: u32 = 1 << 6;
+	const ASPECT: u32 = 1 << 7;
+	const BASE_SIZE: u32 = 1 << 8;
+	const GRAVITY: u32 = 1 << 9;
+
+	/// Decodes `SizeHints` from the values of a `WM_SIZE_HINTS` property.
+	///
+	/// Missing values, which some older clients omit, are treated as zero.
+	fn from_values(values: &[u32]) -> Option<Self> {
+		let (&flags, _) = values.split_first()?;
+		let value = |i: usize| values.get(i).copied().unwrap_or(0) as i32;
+		let set = |flag: u32| flags & flag != 0;
+		let size = |i: usize| Size::clamped(value(i), value(i + 1));
+		let ratio = |i: usize| AspectRatio {
+			numerator: value(i) as u32,
+			denominator: value(i + 1) as u32,
+		};
+
+		Some(Self {
+			position: set(Self::USER_POSITION | Self::PROGRAM_POSITION)
+				.then(|| Point::clamped(value(1), value(2))),
+			size: set(Self::USER_SIZE | Self::PROGRAM_SIZE).then(|| size(3)),
+			user_specified: set(Self::USER_POSITION | Self::USER_SIZE),
+
+			min_size: set(Self::MIN_SIZE).then(|| size(5)),
+			max_size: set(Self::MAX_SIZE).then(|| size(7)),
+			resize_increment: set(Self::RESIZE_INCREMENT).then(|| size(9)),
+			aspect: set(Self::ASPECT).then(|| (ratio(11), ratio(13))),
+			base_size: set(Self::BASE_SIZE).then(|| size(15)),
+			gravity: set(Self::GRAVITY)
+				.then(|| Gravity::from_value(value(17) as u32))
+				.flatten(),
+		})
+	}
+
+	/// Encodes the hints as the values of a `WM_SIZE_HINTS` property.
+	fn to_values(self) -> [u32; SIZE_HINTS_LENGTH] {
+		let (position, size) = if self.user_specified {
+			(Self::USER_POSITION, Self::USER_SIZE)
+		} else {
+			(Self::PROGRAM_POSITION, Self::PROGRAM_SIZE)
+		};
+
+		let mut flags = 0;
+		let mut flag = |flag: u32, set: bool| {
+			if set {
+				flags |= flag;
+			}
+		};
+
+		flag(position, self.position.is_some());
+		flag(size, self.size.is_some());
+		flag(Self::MIN_SIZE, self.min_size.is_some());
+		flag(Self::MAX_SIZE, self.max_size.is_some());
+		flag(Self::RESIZE_INCREMENT, self.resize_increment.is_some());
+		flag(Self::ASPECT, self.aspect.is_some());
+		flag(Self::BASE_SIZE, self.base_size.is_some());
+		flag(Self::GRAVITY, self.gravity.is_some());
+
+		let point = |point: Option<Point>| {
+			let point = point.unwrap_or_default();
+			[i32::from(point.x) as u32, i32::from(point.y) as u32]
+		};
+		let size = |size: Option<Size>| {
+			let size = size.unwrap_or_default();
+			[u32::from(size.width), u32::from(size.height)]
+		};
+		let (min_aspect, max_aspect) = match self.aspect {
+			Some((min, max)) => (
+				[min.numerator, min.denominator],
+				[max.numerator, max.denominator],
+			),
+			None => ([0; 2], [0; 2]),
+		};
+
+		let mut values = [0; SIZE_HINTS_LENGTH];
+
+		values[0] = flags;
+		values[1..3].copy_from_slice(&point(self.position));
+		values[3..5].copy_from_slice(&size(self.size));
+		values[5..7].copy_from_slice(&size(self.min_size));
+		values[7..9].copy_from_slice(&size(self.max_size));
+		values[9..11].copy_from_slice(&size(self.resize_increment));
+		values[11..13].copy_from_slice(&min_aspect);
+		values[13..15].copy_from_slice(&max_aspect);
+		values[15..17].copy_from_slice(&size(self.base_size));
+		values[17] = self.gravity.map_or(0, |gravity| gravity as u32);
+
+		values
+	}
+}
+
+/// The names of a window's application, stored in its `WM_CLASS` property.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ClassHint {
+	/// The name of this instance of the application, such as from its
+	/// `-name` argument or the `RESOURCE_NAME` environment variable.
+	pub instance: String,
+	/// The name of the application's class, shared by all its instances.
+	pub class: String,
+}
+
+/// A protocol which a window manager asks a client to take part in with a
+/// [`ProtocolMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+	/// `WM_DELETE_WINDOW`: the user asked for the window to be closed.
+	DeleteWindow,
+	/// `WM_TAKE_FOCUS`: the window should take the input focus if it wants
+	/// it.
+	TakeFocus,
+
+	/// Any other protocol.
+	Other(Atom),
+}
+
+/// A `WM_PROTOCOLS` client message sent by the window manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtocolMessage {
+	/// The window the message is about.
+	pub window: Window,
+	/// The protocol the message is for.
+	pub protocol: Protocol,
+	/// The time of the event which caused the message.
+	pub timestamp: Timestamp,
+}
+
+impl ProtocolMessage {
+	/// Answers the message in the usual way.
+	///
+	/// For [`Protocol::DeleteWindow`], the window is destroyed; clients which
+	/// want to confirm with the user first should not answer those messages
+	/// this way. For [`Protocol::TakeFocus`], the window is given the input
+	/// focus. Other protocols are ignored.
+	pub async fn answer(&self, writer: &mut RequestWriter) -> io::Result<()> {
+		match self.protocol {
+			Protocol::DeleteWindow => self.window.destroy(writer).await,
+			Protocol::TakeFocus => {
+				focus::set_input_focus(
+					writer,
+					FocusTarget::Window(self.window),
+					RevertTo::Parent,
+					self.timestamp,
+				)
+				.await
+			},
+
+			Protocol::Other(_) => Ok(()),
+		}
+	}
+}
+
+/// Reads `property` from `window` as 32-bit values of `type`.
+async fn u32s_property(
+	connection: &mut impl RoundTrip, window: Window, property: Atom, r#type: Atom,
+) -> Result<Option<Vec<u32>>, ReplyError> {
+	Ok(property::get_property(connection, window, property, r#type)
+		.await?
+		.and_then(|value| value.to_u32s()))
+}
+
+/// Reads `window`'s `WM_HINTS`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::window_hints`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn window_hints(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Option<WindowHints>, ReplyError> {
+	let values = u32s_property(connection, window, Atom::WM_HINTS, Atom::WM_HINTS).await?;
+
+	Ok(values.as_deref().and_then(WindowHints::from_values))
+}
+
+/// Reads `window`'s `WM_NORMAL_HINTS`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::size_hints`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn size_hints(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Option<SizeHints>, ReplyError> {
+	let values = u32s_property(
+		connection,
+		window,
+		Atom::WM_NORMAL_HINTS,
+		Atom::WM_SIZE_HINTS,
+	)
+	.await?;
+
+	Ok(values.as_deref().and_then(SizeHints::from_values))
+}
+
+/// Reads `window`'s `WM_CLASS`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::class_hint`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn class_hint(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Option<ClassHint>, ReplyError> {
+	let value = property::get_property(connection, window, Atom::WM_CLASS, Atom::STRING).await?;
+
+	Ok(value
+		.and_then(|value| value.to_wm_class())
+		.map(|(instance, class)| ClassHint { instance, class }))
+}
+
+/// Reads the protocols `window` takes part in from its `WM_PROTOCOLS`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::protocols`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn protocols(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Vec<Atom>, ReplyError> {
+	let wm_protocols = atoms::atom(connection, "WM_PROTOCOLS").await?;
+
+	let value = property::get_property(connection, window, wm_protocols, Atom::ATOM).await?;
+
+	Ok(value.and_then(|value| value.to_atoms()).unwrap_or_default())
+}
+
+/// Sets the protocols `window` takes part in in its `WM_PROTOCOLS`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_protocols`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_protocols(
+	connection: &mut impl RoundTrip, window: Window, protocols: &[Atom],
+) -> Result<(), ReplyError> {
+	let wm_protocols = atoms::atom(connection, "WM_PROTOCOLS").await?;
+
+	window
+		.set_property(
+			connection.writer(),
+			wm_protocols,
+			&Property::atoms(protocols),
+			PropertyMode::Replace,
+		)
+		.await
+		.map_err(ReplyError::Io)
+}
+
+/// Decodes `frame` if it is a `WM_PROTOCOLS` client message.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::protocol_message`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn protocol_message(
+	connection: &mut impl RoundTrip, frame: &X11Frame,
+) -> Result<Option<ProtocolMessage>, ReplyError> {
+	let X11Frame::Event { code, chunk } = frame else {
+		return Ok(None);
+	};
+
+	// Window managers send these messages with `SendEvent`, so the bit is
+	// expected to be set. The format, in `chunk[0]`, must be 32.
+	if code & !SEND_EVENT_MASK != CLIENT_MESSAGE || chunk[0] != 32 {
+		return Ok(None);
+	}
+
+	let u32_at =
+		|i: usize| u32::from_be_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+
+	// The event's chunk starts after its code, so offsets are one less than
+	// those in the protocol.
+	let window = Window(u32_at(3));
+	let message_type = Atom(u32_at(7));
+	let protocol = Atom(u32_at(11));
+	let timestamp = Timestamp(u32_at(15));
+
+	if message_type != atoms::atom(connection, "WM_PROTOCOLS").await? {
+		return Ok(None);
+	}
+
+	let protocol = if protocol == atoms::atom(connection, "WM_DELETE_WINDOW").await? {
+		Protocol::DeleteWindow
+	} else if protocol == atoms::atom(connection, "WM_TAKE_FOCUS").await? {
+		Protocol::TakeFocus
+	} else {
+		Protocol::Other(protocol)
+	};
+
+	Ok(Some(ProtocolMessage {
+		window,
+		protocol,
+		timestamp,
+	}))
+}
+
+impl Client {
+	/// Reads `window`'s `WM_HINTS`.
+	///
+	/// Returns `None` if the property isn't set or is malformed.
+	pub async fn window_hints(
+		&mut self, window: Window,
+	) -> Result<Option<WindowHints>, ReplyError> {
+		window_hints(self, window).await
+	}
+
+	/// Reads `window`'s `WM_NORMAL_HINTS`.
+	///
+	/// Returns `None` if the property isn't set or is malformed.
+	pub async fn size_hints(&mut self, window: Window) -> Result<Option<SizeHints>, ReplyError> {
+		size_hints(self, window).await
+	}
+
+	/// Reads `window`'s `WM_CLASS`.
+	///
+	/// Returns `None` if the property isn't set or is malformed.
+	pub async fn class_hint(&mut self, window: Window) -> Result<Option<ClassHint>, ReplyError> {
+		class_hint(self, window).await
+	}
+
+	/// Reads the protocols `window` takes part in from its `WM_PROTOCOLS`.
+	pub async fn protocols(&mut self, window: Window) -> Result<Vec<Atom>, ReplyError> {
+		protocols(self, window).await
+	}
+
+	/// Sets the protocols `window` takes part in, such as `WM_DELETE_WINDOW`
+	/// and `WM_TAKE_FOCUS`, in its `WM_PROTOCOLS`.
+	pub async fn set_protocols(
+		&mut self, window: Window, protocols: &[Atom],
+	) -> Result<(), ReplyError> {
+		set_protocols(self, window, protocols).await
+	}
+
+	/// Decodes `frame` if it is a `WM_PROTOCOLS` client message.
+	///
+	/// The protocol atoms are interned the first time this is called, and
+	/// cached afterwards.
+	pub async fn protocol_message(
+		&mut self, frame: &X11Frame,
+	) -> Result<Option<ProtocolMessage>, ReplyError> {
+		protocol_message(self, frame).await
+	}
+}
+
+impl Window {
+	/// Sets the window's `WM_HINTS`.
+	pub async fn set_hints(
+		self, writer: &mut RequestWriter, hints: &WindowHints,
+	) -> io::Result<()> {
+		let value = Property::u32s(Atom::WM_HINTS, &hints.to_values());
+
+		self.set_property(writer, Atom::WM_HINTS, &value, PropertyMode::Replace)
+			.await
+	}
+
+	/// Sets the window's `WM_NORMAL_HINTS`.
+	pub async fn set_size_hints(
+		self, writer: &mut RequestWriter, hints: &SizeHints,
+	) -> io::Result<()> {
+		let value = Property::u32s(Atom::WM_SIZE_HINTS, &hints.to_values());
+
+		self.set_property(writer, Atom::WM_NORMAL_HINTS, &value, PropertyMode::Replace)
+			.await
+	}
+
+	/// Sets the window's `WM_CLASS`.
+	///
+	/// Both names must be in Latin-1, as the property's type is `STRING`.
+	pub async fn set_class_hint(
+		self, writer: &mut RequestWriter, hint: &ClassHint,
+	) -> io::Result<()> {
+		let Some(value) = Property::latin1(&format!("{}\0{}\0", hint.instance, hint.class)) else {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"WM_CLASS names must be in Latin-1",
+			));
+		};
+
+		self.set_property(writer, Atom::WM_CLASS, &value, PropertyMode::Replace)
+			.await
+	}
+}
@@ -0,0 +1,877 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Reading and writing images on drawables.
+//!
+//! A single `GetImage` reply holds the whole requested area, so capturing a
+//! very large area means one very large reply, which the X server has to
+//! allocate in full. [`Client::get_image_tiled`] instead requests the area as
+//! a series of horizontal strips and stitches them together.
+//!
+//! Requests are limited to the [maximum request length], so
+//! [`Drawable::put_image`] similarly splits images too large for a single
+//! `PutImage` request into strips.
+//!
+//! An [`Image`] knows how its data is laid out: its format, bits per pixel,
+//! scanline padding and byte order. Images made with [`Image::new`] or
+//! [`Image::from_rgba`] are laid out as the X server expects, as advertised
+//! in its [`Setup`], so they can be drawn without any bit-twiddling whatever
+//! the X server's padding or byte order. [`Image::to_rgba`] converts images
+//! read from the X server back into RGBA buffers.
+//!
+//! [maximum request length]: Client::maximum_request_length
+
+use crate::{
+	client::RoundTrip,
+	draw::Drawable,
+	gc::Gc,
+	geometry::{Point, Rectangle, Size},
+	raw::{assert_wire_size, HexBytes, RawRequest, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	Client,
+	ImageOrder,
+	ReplyError,
+	RequestWriter,
+	Setup,
+	Visual,
+	VisualClass,
+};
+use bytes::{BufMut, BytesMut};
+use std::{fmt, fmt::Formatter, io};
+
+/// The major opcode of the `PutImage` request.
+const PUT_IMAGE: u8 = 72;
+/// The major opcode of the `GetImage` request.
+const GET_IMAGE: u8 = 73;
+
+/// The number of unused bytes in a `GetImage` reply between its `visual` and
+/// its image data.
+const REPLY_PADDING: usize = 20;
+
+/// The most bytes a pixel takes up in `ZPixmap` format on any common server,
+/// used to estimate the size of image data before it is received.
+const MAX_BYTES_PER_PIXEL: usize = 4;
+
+/// The default maximum size of each strip's image data: 4 MiB.
+const DEFAULT_MAX_TILE_BYTES: usize = 4 * 1024 * 1024;
+
+/// The number of 4-byte blocks in a `PutImage` request before its image
+/// data.
+const PUT_IMAGE_HEADER_BLOCKS: usize = 6;
+
+/// The format of image data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ImageFormat {
+	/// A single plane, for drawables with a depth of 1.
+	Bitmap,
+	/// One bitmap for each plane, from the most significant plane to the
+	/// least.
+	XyPixmap,
+	/// Scanlines of whole pixels.
+	#[default]
+	ZPixmap,
+}
+
+/// An image, along with the layout of its data.
+///
+/// Its [`Debug`] output only shows the start of its data.
+///
+/// [`Debug`]: fmt::Debug
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+	pub format: ImageFormat,
+	/// The number of bits in each pixel value.
+	pub depth: u8,
+	/// The visual of the window the image was read from or is meant for, or
+	/// `0` (`None`) if there isn't one.
+	pub visual: u32,
+
+	pub width: u16,
+	pub height: u16,
+
+	/// The number of bits each pixel takes up in `ZPixmap` format, or `1` in
+	/// other formats.
+	pub bits_per_pixel: u8,
+	/// The multiple of bits to which each scanline is padded.
+	pub scanline_pad: u8,
+	/// The number of bits in each unit of a bitmap's scanlines, and of the
+	/// scanlines of pixels which take up 1 bit: 8, 16 or 32.
+	///
+	/// Bits are ordered within whole units, which are stored in the byte
+	/// order, so the bytes of each unit are swapped if the byte order isn't
+	/// the bit order.
+	pub scanline_unit: u8,
+	/// The byte order of pixels which take up more than one byte, and of
+	/// pixels which take up 4 bits.
+	pub byte_order: ImageOrder,
+	/// The order of the bits in each byte of a bitmap, and of pixels which
+	/// take up 1 bit.
+	pub bit_order: ImageOrder,
+
+	/// The image data, one padded scanline after another.
+	///
+	/// In `XyPixmap` format, this is every scanline of each plane in turn,
+	/// from the most significant plane to the least.
+	pub data: Vec<u8>,
+}
+
+impl fmt::Debug for Image {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Image")
+			.field("format", &self.format)
+			.field("depth", &self.depth)
+			.field("visual", &self.visual)
+			.field("width", &self.width)
+			.field("height", &self.height)
+			.field("bits_per_pixel", &self.bits_per_pixel)
+			.field("scanline_pad", &self.scanline_pad)
+			.field("scanline_unit", &self.scanline_unit)
+			.field("byte_order", &self.byte_order)
+			.field("bit_order", &self.bit_order)
+			.field("data", &HexBytes(&self.data))
+			.finish()
+	}
+}
+
+impl Image {
+	/// Creates a blank image in `format` laid out as the X server lays out
+	/// images of `depth`.
+	///
+	/// Returns `None` if the X server doesn't support `depth` in `ZPixmap`
+	/// format, or if `format` is `Bitmap` and `depth` isn't 1.
+	pub fn new(
+		setup: &Setup, format: ImageFormat, depth: u8, width: u16, height: u16,
+	) -> Option<Self> {
+		let (bits_per_pixel, scanline_pad) = match format {
+			ImageFormat::ZPixmap => {
+				let pixmap_format = setup
+					.pixmap_formats
+					.iter()
+					.find(|pixmap_format| pixmap_format.depth == depth)?;
+
+				(pixmap_format.bits_per_pixel, pixmap_format.scanline_pad)
+			},
+
+			ImageFormat::Bitmap if depth != 1 => return None,
+			ImageFormat::Bitmap | ImageFormat::XyPixmap => (1, setup.bitmap_scanline_pad),
+		};
+
+		let mut image = Self {
+			format,
+			depth,
+			visual: 0,
+
+			width,
+			height,
+
+			bits_per_pixel,
+			scanline_pad,
+			scanline_unit: setup.bitmap_scanline_unit,
+			byte_order: setup.image_byte_order,
+			bit_order: setup.bitmap_bit_order,
+
+			data: Vec::new(),
+		};
+		image.data = vec![0; image.plane_bytes() * image.planes()];
+
+		Some(image)
+	}
+
+	/// Converts an RGBA buffer, 4 bytes per pixel with each row after the
+	/// last, into a `ZPixmap` image for `visual`, laid out as the X server
+	/// expects.
+	///
+	/// If `visual` has bits which aren't part of its red, green or blue
+	/// masks, such as a 32-bit ARGB visual, the alpha value is written to
+	/// them.
+	///
+	/// Returns `None` if `visual` isn't `TrueColor` or `DirectColor`, if the X
+	/// server doesn't support its depth, or if `rgba` is too short.
+	pub fn from_rgba(
+		setup: &Setup, visual: &Visual, width: u16, height: u16, rgba: &[u8],
+	) -> Option<Self> {
+		let masks = ColorMasks::new(visual)?;
+		let mut image = Self::new(setup, ImageFormat::ZPixmap, visual.depth, width, height)?;
+		image.visual = visual.id;
+
+		let pixels = rgba.get(..usize::from(width) * usize::from(height) * 4)?;
+
+		for (index, color) in pixels.chunks_exact(4).enumerate() {
+			let x = (index % usize::from(width)) as u16;
+			let y = (index / usize::from(width)) as u16;
+
+			image.set_pixel(x, y, masks.encode([color[0], color[1], color[2], color[3]]));
+		}
+
+		Some(image)
+	}
+
+	/// Converts the image into an RGBA buffer, 4 bytes per pixel with each
+	/// row after the last, using `visual`'s color masks.
+	///
+	/// Pixels are fully opaque unless `visual` has bits which aren't part of
+	/// its red, green or blue masks, in which case they hold the alpha value.
+	///
+	/// Returns `None` if `visual` isn't `TrueColor` or `DirectColor`, or if
+	/// the image's bits per pixel aren't supported.
+	pub fn to_rgba(&self, visual: &Visual) -> Option<Vec<u8>> {
+		let masks = ColorMasks::new(visual)?;
+		let mut rgba = Vec::with_capacity(usize::from(self.width) * usize::from(self.height) * 4);
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				rgba.extend_from_slice(&masks.decode(self.pixel(x, y)?));
+			}
+		}
+
+		Some(rgba)
+	}
+
+	/// Converts the image into `format`, laid out as the X server lays out
+	/// images of its depth.
+	///
+	/// This also converts images between different padding or byte orders.
+	/// Returns `None` in the same cases as [`Image::new`], or if the image's
+	/// bits per pixel aren't supported.
+	pub fn convert(&self, setup: &Setup, format: ImageFormat) -> Option<Self> {
+		let mut image = Self::new(setup, format, self.depth, self.width, self.height)?;
+		image.visual = self.visual;
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				image.set_pixel(x, y, self.pixel(x, y)?);
+			}
+		}
+
+		Some(image)
+	}
+
+	/// The number of bytes in each scanline of `data`, including padding.
+	pub fn bytes_per_line(&self) -> usize {
+		let bits = usize::from(self.width) * usize::from(self.bits_per_pixel);
+		let pad = usize::from(self.scanline_pad.max(8));
+
+		bits.div_ceil(pad) * pad / 8
+	}
+
+	/// The number of planes in `data`: the depth in `XyPixmap` format, and `1`
+	/// otherwise.
+	fn planes(&self) -> usize {
+		match self.format {
+			ImageFormat::XyPixmap => usize::from(self.depth),
+			ImageFormat::Bitmap | ImageFormat::ZPixmap => 1,
+		}
+	}
+
+	/// The number of bytes in each plane of `data`.
+	fn plane_bytes(&self) -> usize {
+		self.bytes_per_line() * usize::from(self.height)
+	}
+
+	/// Returns the pixel value at `x`, `y`.
+	///
+	/// Returns `None` if `x`, `y` is outside of the image, if `data` is too
+	/// short, or if the image's bits per pixel aren't 1, 4, 8, 16, 24 or 32.
+	pub fn pixel(&self, x: u16, y: u16) -> Option<u32> {
+		if x >= self.width || y >= self.height {
+			return None;
+		}
+
+		let line = usize::from(y) * self.bytes_per_line();
+
+		match (self.format, self.bits_per_pixel) {
+			(ImageFormat::ZPixmap, bits @ (1 | 4)) => {
+				let (index, shift) = self.sub_byte_position(x, bits);
+				let byte = *self.data.get(line + index)?;
+
+				Some(u32::from(byte >> shift) & ((1 << bits) - 1))
+			},
+
+			(ImageFormat::ZPixmap, bits @ (8 | 16 | 24 | 32)) => {
+				let bytes = usize::from(bits / 8);
+				let start = line + usize::from(x) * bytes;
+				let bytes = self.data.get(start..start + bytes)?;
+
+				Some(match self.byte_order {
+					ImageOrder::MostSignificantFirst => bytes
+						.iter()
+						.fold(0, |pixel, &byte| (pixel << 8) | u32::from(byte)),
+					ImageOrder::LeastSignificantFirst => bytes
+						.iter()
+						.rev()
+						.fold(0, |pixel, &byte| (pixel << 8) | u32::from(byte)),
+				})
+			},
+
+			(ImageFormat::ZPixmap, _) => None,
+
+			(ImageFormat::Bitmap | ImageFormat::XyPixmap, _) => {
+				let (index, shift) = self.sub_byte_position(x, 1);
+				let planes = self.planes();
+
+				(0..planes).try_fold(0, |pixel, plane| {
+					let byte = *self.data.get(plane * self.plane_bytes() + line + index)?;
+
+					Some((pixel << 1) | u32::from((byte >> shift) & 1))
+				})
+			},
+		}
+	}
+
+	/// Sets the pixel value at `x`, `y`, returning whether it was set.
+	///
+	/// Bits of `pixel` beyond the image's depth are ignored. The pixel isn't
+	/// set in the same cases that [`Image::pixel`] returns `None`.
+	pub fn set_pixel(&mut self, x: u16, y: u16, pixel: u32) -> bool {
+		if x >= self.width || y >= self.height {
+			return false;
+		}
+
+		let line = usize::from(y) * self.bytes_per_line();
+
+		match (self.format, self.bits_per_pixel) {
+			(ImageFormat::ZPixmap, bits @ (1 | 4)) => {
+				let (index, shift) = self.sub_byte_position(x, bits);
+				let mask = ((1u8 << bits) - 1) << shift;
+
+				let Some(byte) = self.data.get_mut(line + index) else {
+					return false;
+				};
+				*byte = (*byte & !mask) | (((pixel as u8) << shift) & mask);
+
+				true
+			},
+
+			(ImageFormat::ZPixmap, bits @ (8 | 16 | 24 | 32)) => {
+				let count = usize::from(bits / 8);
+				let start = line + usize::from(x) * count;
+				let byte_order = self.byte_order;
+
+				let Some(bytes) = self.data.get_mut(start..start + count) else {
+					return false;
+				};
+
+				for (offset, byte) in bytes.iter_mut().enumerate() {
+					let significance = match byte_order {
+						ImageOrder::MostSignificantFirst => count - 1 - offset,
+						ImageOrder::LeastSignificantFirst => offset,
+					};
+
+					*byte = (pixel >> (significance * 8)) as u8;
+				}
+
+				true
+			},
+
+			(ImageFormat::ZPixmap, _) => false,
+
+			(ImageFormat::Bitmap | ImageFormat::XyPixmap, _) => {
+				let (index, shift) = self.sub_byte_position(x, 1);
+				let planes = self.planes();
+				let plane_bytes = self.plane_bytes();
+
+				for plane in 0..planes {
+					let bit = (pixel >> (planes - 1 - plane)) as u8 & 1;
+
+					let Some(byte) = self.data.get_mut(plane * plane_bytes + line + index) else {
+						return false;
+					};
+					*byte = (*byte & !(1 << shift)) | (bit << shift);
+				}
+
+				true
+			},
+		}
+	}
+
+	/// Returns the index within its scanline of the byte holding the pixel
+	/// at `x` of `bits` bits, and the shift of the pixel within that byte.
+	///
+	/// The pixels within each byte are ordered by the bit order for 1-bit
+	/// pixels, and by the byte order otherwise. The bytes of each scanline
+	/// unit of 1-bit pixels are swapped if the byte order isn't the bit order.
+	fn sub_byte_position(&self, x: u16, bits: u8) -> (usize, u8) {
+		let offset = usize::from(x) * usize::from(bits);
+		let within = (offset % 8) as u8;
+		let mut index = offset / 8;
+
+		let order = match bits {
+			1 => self.bit_order,
+			_ => self.byte_order,
+		};
+
+		let shift = match order {
+			ImageOrder::LeastSignificantFirst => within,
+			ImageOrder::MostSignificantFirst => 8 - bits - within,
+		};
+
+		if bits == 1 && self.byte_order != self.bit_order {
+			let unit_bytes = usize::from(self.scanline_unit / 8).max(1);
+			let within_unit = index % unit_bytes;
+
+			index = index - within_unit + (unit_bytes - 1 - within_unit);
+		}
+
+		(index, shift)
+	}
+}
+
+/// The color masks of a `TrueColor` or `DirectColor` visual, for converting
+/// pixel values to and from RGBA.
+struct ColorMasks {
+	red: u32,
+	green: u32,
+	blue: u32,
+	/// The bits within the visual's depth which aren't part of the red, green
+	/// or blue masks.
+	alpha: u32,
+}
+
+impl ColorMasks {
+	fn new(visual: &Visual) -> Option<Self> {
+		if !matches!(
+			visual.class,
+			VisualClass::TrueColor | VisualClass::DirectColor
+		) {
+			return None;
+		}
+
+		let depth_mask = u32::MAX
+			.checked_shr(32 - u32::from(visual.depth.min(32)))
+			.unwrap_or(0);
+		let color_mask = visual.red_mask | visual.green_mask | visual.blue_mask;
+
+		Some(Self {
+			red: visual.red_mask,
+			green: visual.green_mask,
+			blue: visual.blue_mask,
+			alpha: depth_mask & !color_mask,
+		})
+	}
+
+	/// Scales an 8-bit channel value into `mask`.
+	fn scale_to(value: u8, mask: u32) -> u32 {
+		if mask == 0 {
+			return 0;
+		}
+
+		let shift = mask.trailing_zeros();
+		let max = u64::from(mask >> shift);
+
+		((u64::from(value) * max / 255) as u32) << shift
+	}
+
+	/// Scales the value in `mask` of `pixel` to an 8-bit channel value.
+	fn scale_from(pixel: u32, mask: u32) -> Option<u8> {
+		if mask == 0 {
+			return None;
+		}
+
+		let shift = mask.trailing_zeros();
+		let max = u64::from(mask >> shift);
+
+		Some((u64::from((pixel & mask) >> shift) * 255 / max) as u8)
+	}
+
+	fn encode(&self, [red, green, blue, alpha]: [u8; 4]) -> u32 {
+		Self::scale_to(red, self.red)
+			| Self::scale_to(green, self.green)
+			| Self::scale_to(blue, self.blue)
+			| Self::scale_to(alpha, self.alpha)
+	}
+
+	fn decode(&self, pixel: u32) -> [u8; 4] {
+		[
+			Self::scale_from(pixel, self.red).unwrap_or(0),
+			Self::scale_from(pixel, self.green).unwrap_or(0),
+			Self::scale_from(pixel, self.blue).unwrap_or(0),
+			Self::scale_from(pixel, self.alpha).unwrap_or(u8::MAX),
+		]
+	}
+}
+
+/// Options for [`Client::get_image_tiled`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileOptions {
+	/// The maximum size of each strip's image data, in bytes.
+	///
+	/// Every strip is at least one row high, whatever this limit.
+	pub max_tile_bytes: usize,
+	/// Whether to send the requests for every strip before waiting for any
+	/// of the replies.
+	///
+	/// This saves a round trip per strip, but means the X server may have
+	/// several strips' replies queued at once.
+	pub pipeline: bool,
+}
+
+impl Default for TileOptions {
+	fn default() -> Self {
+		Self {
+			max_tile_bytes: DEFAULT_MAX_TILE_BYTES,
+			pipeline: false,
+		}
+	}
+}
+
+/// The `GetImage` request, which reads an area of a drawable.
+///
+/// Areas are always read in `ZPixmap` format.
+///
+/// See [`Client::get_image`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetImage {
+	pub drawable: Drawable,
+	pub area: Rectangle,
+	/// The planes to read; `!0` reads all of them.
+	pub plane_mask: u32,
+}
+
+impl Request for GetImage {
+	type Reply = ImageData;
+
+	const NAME: &'static str = "GetImage";
+	const OPCODE: u8 = GET_IMAGE;
+	const LENGTH: Option<u16> = Some(5);
+
+	fn metabyte(&self) -> u8 {
+		ImageFormat::ZPixmap as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		self.area.write_to(body);
+		body.put_u32(self.plane_mask);
+	}
+}
+
+assert_wire_size!(GetImage: [u8, [u8; 1], u16, u32, Rectangle, u32]);
+
+/// The image data read by a `GetImage` request.
+///
+/// It is laid out as the X server lays out images of its `depth`; see
+/// [`Image::new`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageData {
+	pub depth: u8,
+	/// The visual of the window the data was read from, or `0` (`None`) if it
+	/// was read from a pixmap.
+	pub visual: u32,
+	pub data: Vec<u8>,
+}
+
+assert_wire_size!("GetImage reply": [u8, u8, u16, u32, u32, [u8; REPLY_PADDING]] == 32);
+
+impl Reply for ImageData {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+
+		let &[v0, v1, v2, v3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			depth: *metabyte,
+			visual: u32::from_be_bytes([v0, v1, v2, v3]),
+			data: chunk.get(4 + REPLY_PADDING..)?.to_vec(),
+		})
+	}
+}
+
+/// The `PutImage` request, which draws image data on a drawable.
+///
+/// See [`Drawable::put_image`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PutImage<'a> {
+	pub format: ImageFormat,
+	pub drawable: Drawable,
+	pub gc: u32,
+	pub size: Size,
+	/// Where the image's top-left corner is drawn.
+	pub position: Point,
+	pub depth: u8,
+	/// The image data, laid out as [`Image::data`] is.
+	pub data: &'a [u8],
+}
+
+impl Request for PutImage<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "PutImage";
+	const OPCODE: u8 = PUT_IMAGE;
+
+	fn metabyte(&self) -> u8 {
+		self.format as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+		body.put_u16(self.size.width);
+		body.put_u16(self.size.height);
+		self.position.write_to(body);
+		// left-pad
+		body.put_u8(0);
+		body.put_u8(self.depth);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_slice(self.data);
+	}
+}
+
+/// Reads an [`Image`] of the given size in `ZPixmap` format from
+/// `image_data`, laid out as `setup` gives for its depth.
+fn from_reply(
+	image_data: ImageData, width: u16, height: u16, setup: &Setup,
+) -> Result<Image, ReplyError> {
+	let mut image = Image::new(setup, ImageFormat::ZPixmap, image_data.depth, width, height)
+		.ok_or_else(|| ReplyError::malformed("GetImage"))?;
+
+	image.visual = image_data.visual;
+	image.data = image_data.data;
+
+	Ok(image)
+}
+
+/// Splits `area` into full-width strips, each of which is expected to have
+/// at most `max_bytes` of image data.
+fn strips(area: Rectangle, max_bytes: usize) -> Vec<Rectangle> {
+	let row_bytes = (area.width as usize) * MAX_BYTES_PER_PIXEL;
+	let rows = (max_bytes / row_bytes.max(1)).clamp(1, u16::MAX as usize) as u16;
+
+	// An empty area is still requested, so that its depth and visual are
+	// known.
+	if area.height == 0 {
+		return vec![area];
+	}
+
+	(0..area.height)
+		.step_by(rows as usize)
+		.map(|offset| Rectangle {
+			y: area.y.wrapping_add_unsigned(offset),
+			height: rows.min(area.height - offset),
+
+			..area
+		})
+		.collect()
+}
+
+/// Reads `area` of `drawable` in `ZPixmap` format as a series of
+/// horizontal strips, stitching them together.
+///
+/// With a [`RequestWriter`], the replies are [awaited directly].
+///
+/// See [`Client::get_image_tiled`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_image_tiled(
+	connection: &mut impl RoundTrip, drawable: u32, area: Rectangle, plane_mask: u32,
+	options: TileOptions,
+) -> Result<Image, ReplyError> {
+	let drawable = Drawable(drawable);
+	let strips = strips(area, options.max_tile_bytes);
+
+	let mut image: Option<Image> = None;
+
+	let mut append = |strip: Image| match &mut image {
+		Some(image) => image.data.extend_from_slice(&strip.data),
+		None => {
+			image = Some(Image {
+				height: area.height,
+				..strip
+			})
+		},
+	};
+
+	if options.pipeline {
+		let mut cookies = Vec::with_capacity(strips.len());
+
+		for &area in &strips {
+			let request = GetImage {
+				drawable,
+				area,
+				plane_mask,
+			};
+			cookies.push(request::send_request(connection, &request).await?);
+		}
+
+		for (strip, cookie) in strips.iter().zip(cookies) {
+			let image_data = request::wait_for(connection, cookie).await?;
+			append(from_reply(
+				image_data,
+				strip.width,
+				strip.height,
+				connection.writer().setup(),
+			)?);
+		}
+	} else {
+		for strip in &strips {
+			let request = GetImage {
+				drawable,
+				area: *strip,
+				plane_mask,
+			};
+			let image_data = request::reply_to(connection, &request).await?;
+			append(from_reply(
+				image_data,
+				strip.width,
+				strip.height,
+				connection.writer().setup(),
+			)?);
+		}
+	}
+
+	image.ok_or_else(|| ReplyError::malformed("GetImage"))
+}
+
+impl Client {
+	/// Reads `area` of `drawable` in `ZPixmap` format with a single `GetImage`
+	/// request.
+	///
+	/// Only the planes set in `plane_mask` are read; use `!0` for all planes.
+	pub async fn get_image(
+		&mut self, drawable: u32, area: Rectangle, plane_mask: u32,
+	) -> Result<Image, ReplyError> {
+		let request = GetImage {
+			drawable: Drawable(drawable),
+			area,
+			plane_mask,
+		};
+		let image_data = request::reply_to(self, &request).await?;
+
+		from_reply(image_data, area.width, area.height, self.setup())
+	}
+
+	/// Reads `area` of `drawable` in `ZPixmap` format as a series of
+	/// horizontal strips, stitching them together.
+	///
+	/// Strips span the whole width of `area`, so that their image data can be
+	/// joined without knowing the drawable's pixel format. Each strip is
+	/// sized to keep its data under [`TileOptions::max_tile_bytes`],
+	/// assuming at most 4 bytes per pixel.
+	pub async fn get_image_tiled(
+		&mut self, drawable: u32, area: Rectangle, plane_mask: u32, options: TileOptions,
+	) -> Result<Image, ReplyError> {
+		get_image_tiled(self, drawable, area, plane_mask, options).await
+	}
+}
+
+/// Splits `image` into `PutImage` requests for strips of whole rows, each
+/// short enough for a maximum request length of `maximum_length` blocks.
+fn put_image_requests(
+	drawable: Drawable, gc: &Gc, image: &Image, position: Point, maximum_length: usize,
+) -> io::Result<Vec<RawRequest>> {
+	let planes = image.planes();
+	let height = usize::from(image.height);
+
+	if image.width == 0 || height == 0 || planes == 0 {
+		return Ok(Vec::new());
+	}
+
+	if image.data.len() < image.plane_bytes() * planes {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"the image's data is shorter than its size and layout require",
+		));
+	}
+
+	// The bytes in one row of every plane.
+	let line_bytes = image.bytes_per_line();
+	let row_bytes = line_bytes * planes;
+
+	let available_bytes = maximum_length.saturating_sub(PUT_IMAGE_HEADER_BLOCKS) * 4;
+	let rows = match available_bytes / row_bytes.max(1) {
+		0 => {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"a single row of the image is longer than the maximum request length",
+			))
+		},
+		rows => rows.min(height),
+	};
+
+	let mut requests = Vec::with_capacity(height.div_ceil(rows));
+
+	for first_row in (0..height).step_by(rows) {
+		let strip_rows = rows.min(height - first_row);
+		let mut data = Vec::with_capacity(strip_rows * row_bytes);
+
+		// Each plane's rows are contiguous, so a strip of each plane is
+		// copied in turn.
+		for plane in 0..planes {
+			let start = (plane * height + first_row) * line_bytes;
+			data.extend_from_slice(&image.data[start..start + strip_rows * line_bytes]);
+		}
+
+		let y = i16::try_from(first_row)
+			.ok()
+			.and_then(|offset| position.y.checked_add(offset))
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"the image extends past the largest y coordinate",
+				)
+			})?;
+
+		let request = PutImage {
+			format: image.format,
+			drawable,
+			gc: gc.id(),
+			size: Size::new(image.width, strip_rows as u16),
+			position: Point::new(position.x, y),
+			depth: image.depth,
+			data: &data,
+		};
+		requests.push(request.to_raw(PUT_IMAGE));
+	}
+
+	Ok(requests)
+}
+
+impl Drawable {
+	/// Draws `image` on the drawable, with its top-left corner at
+	/// `position`.
+	///
+	/// The image must be laid out as the X server expects, such as one made
+	/// with [`Image::new`] or [`Image::from_rgba`] or converted with
+	/// [`Image::convert`]. Its visual is ignored. Images too long for a single
+	/// `PutImage` request are sent as a series of horizontal strips.
+	///
+	/// Returns an [`InvalidInput`] error if `image.data` is too short, if a
+	/// single row of the image is longer than the maximum request length, or
+	/// if a strip of the image would start past the largest y coordinate.
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	pub async fn put_image(
+		self, writer: &mut RequestWriter, gc: &Gc, image: &Image, position: Point,
+	) -> io::Result<()> {
+		let maximum_length = writer.maximum_request_length() as usize;
+		// Requests sent with the extended length encoding take an extra
+		// block.
+		let maximum_length = maximum_length - usize::from(writer.big_requests_enabled());
+
+		for request in put_image_requests(self, gc, image, position, maximum_length)? {
+			writer.send_queued(request).await?;
+		}
+
+		writer.flush().await
+	}
+
+	/// Reads `area` of the drawable in `ZPixmap` format.
+	///
+	/// Large areas are read as a series of horizontal strips which are
+	/// joined into one image; see [`Client::get_image_tiled`].
+	///
+	/// Only the planes set in `plane_mask` are read; use `!0` for all planes.
+	pub async fn get_image(
+		self, writer: &mut RequestWriter, area: Rectangle, plane_mask: u32,
+	) -> Result<Image, ReplyError> {
+		get_image_tiled(writer, self.id(), area, plane_mask, TileOptions::default()).await
+	}
+}
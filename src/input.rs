@@ -0,0 +1,1473 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Keyboard and pointer input, whether or not the X server supports XInput 2.
+//!
+//! The core protocol's `KeyPress`, `ButtonPress` and `MotionNotify` events
+//! don't say which device they came from, and only have whole-pixel
+//! positions. XInput 2 sends equivalent device events with both, but not
+//! every X server supports it. [`InputEvent`] holds the fields the two have in
+//! common, so that applications can handle input the same way with either.
+//!
+//! [`Client::input_adapter`] selects XInput 2 device events on a window if
+//! they are supported, and the returned [`InputAdapter`] converts both kinds
+//! of event into [`InputEvent`]s. The core events should still be selected on
+//! the window, so that input is received without XInput 2: the X server only
+//! sends the core events when no XInput 2 events are selected for them.
+//!
+//! ```no_run
+//! # use xrs::{input::InputKind, window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let adapter = client.input_adapter(window).await?;
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     if let Some(input) = adapter.decode(&frame) {
+//!         if let InputKind::ButtonPress(button) = input.kind {
+//!             println!("button {button} pressed on device {:?}", input.source);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With XInput 2, each physical device is a slave device attached to a
+//! master pointer or keyboard, which moves a cursor or has a focus of its
+//! own. [`Client::change_hierarchy`] adds and removes master devices, and
+//! moves slave devices between them, for multi-pointer setups. Core requests
+//! which don't say which pointer they are about, such as `QueryPointer`, use
+//! the client's [client pointer].
+//!
+//! XInput 2 grabs, made with [`Client::grab_device`] and
+//! [`Client::passive_grab_device`], grab one master device rather than the
+//! client pointer or its keyboard. Passive grabs of touches let gesture
+//! recognizers see a touch sequence before deciding whether it is theirs:
+//! the grabbing client gets the touch events first, and must either accept
+//! the sequence, ending it for every other client, or reject it, passing it
+//! on to the next, with [`Client::allow_device_events`].
+//!
+//! [client pointer]: Client::set_client_pointer
+
+use crate::{
+	client::RoundTrip,
+	event::{Event, KeyButtonEvent},
+	extension::Degradation,
+	geometry::Point,
+	grab::{GrabMode, GrabStatus},
+	keyboard::Keycode,
+	pointer::{self, XISelectEvents, XINPUT},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	window::Window,
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+use std::io;
+
+/// The name of the XTEST extension.
+pub(crate) const XTEST: &str = "XTEST";
+/// The minor opcode of the `XTestFakeInput` request.
+const XTEST_FAKE_INPUT: u8 = 2;
+
+/// The event code of core `KeyPress` events.
+const KEY_PRESS: u8 = 2;
+
+/// The event type of XInput 2 `KeyPress` events.
+const XI_KEY_PRESS: u16 = 2;
+/// The event type of XInput 2 `KeyRelease` events.
+const XI_KEY_RELEASE: u16 = 3;
+/// The event type of XInput 2 `ButtonPress` events.
+const XI_BUTTON_PRESS: u16 = 4;
+/// The event type of XInput 2 `ButtonRelease` events.
+const XI_BUTTON_RELEASE: u16 = 5;
+/// The event type of XInput 2 `Motion` events.
+const XI_MOTION: u16 = 6;
+
+/// The flag set on XInput 2 key events which were generated by autorepeat.
+const XI_KEY_REPEAT: u32 = 1 << 16;
+
+/// The minor opcode of the `XIChangeHierarchy` request.
+const XI_CHANGE_HIERARCHY: u8 = 43;
+/// The minor opcode of the `XISetClientPointer` request.
+const XI_SET_CLIENT_POINTER: u8 = 44;
+/// The minor opcode of the `XIGetClientPointer` request.
+const XI_GET_CLIENT_POINTER: u8 = 45;
+
+/// The minor opcode of the `XIGrabDevice` request.
+const XI_GRAB_DEVICE: u8 = 51;
+/// The minor opcode of the `XIUngrabDevice` request.
+const XI_UNGRAB_DEVICE: u8 = 52;
+/// The minor opcode of the `XIAllowEvents` request.
+const XI_ALLOW_EVENTS: u8 = 53;
+/// The minor opcode of the `XIPassiveGrabDevice` request.
+const XI_PASSIVE_GRAB_DEVICE: u8 = 54;
+/// The minor opcode of the `XIPassiveUngrabDevice` request.
+const XI_PASSIVE_UNGRAB_DEVICE: u8 = 55;
+
+/// The version of XInput which added master and slave devices.
+const XI_HIERARCHY_VERSION: (u32, u32) = (2, 0);
+/// The version of XInput which added touch events and grabs.
+const XI_TOUCH_VERSION: (u32, u32) = (2, 2);
+
+/// The `XIGrabModeTouch` grab mode, which passive touch grabs must use.
+const XI_GRAB_MODE_TOUCH: u8 = 2;
+/// The `XIAnyButton` and `XIAnyKeycode` wildcards.
+const XI_ANY: u32 = 0;
+/// The `BadAccess` error code, which Xorg gives as the status of modifiers
+/// already grabbed by another client.
+const BAD_ACCESS: u8 = 10;
+
+/// The modifier mask matching any combination of modifiers, including none,
+/// for [`Client::passive_grab_device`].
+pub const XI_ANY_MODIFIER: u32 = 1 << 31;
+
+/// Input being read from core events, which don't say which device they came
+/// from, as XInput 2 isn't supported.
+const CORE_INPUT: Degradation = Degradation {
+	feature: "input",
+	preferred: "XInput 2",
+	fallback: "core events",
+};
+
+/// The ID of an input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceId(pub u16);
+
+impl DeviceId {
+	/// The master pointer which every pointer is attached to by default.
+	///
+	/// Core pointer events are reported as coming from this device.
+	pub const VIRTUAL_CORE_POINTER: Self = Self(2);
+	/// The master keyboard which every keyboard is attached to by default.
+	///
+	/// Core key events are reported as coming from this device.
+	pub const VIRTUAL_CORE_KEYBOARD: Self = Self(3);
+}
+
+/// What happened in an [`InputEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKind {
+	KeyPress(Keycode),
+	KeyRelease(Keycode),
+	ButtonPress(u8),
+	ButtonRelease(u8),
+	Motion,
+}
+
+/// A pointer position, which is fractional if it was reported by XInput 2.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct InputPosition {
+	pub x: f64,
+	pub y: f64,
+}
+
+impl From<Point> for InputPosition {
+	fn from(point: Point) -> Self {
+		Self {
+			x: f64::from(point.x),
+			y: f64::from(point.y),
+		}
+	}
+}
+
+/// A key, button or motion event from either the core protocol or XInput 2.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+	pub kind: InputKind,
+
+	/// The master device the event was sent for.
+	pub device: DeviceId,
+	/// The physical device which generated the event.
+	///
+	/// This is the same as `device` for core events, which don't say which
+	/// physical device generated them.
+	pub source: DeviceId,
+	pub time: Timestamp,
+
+	/// The root window of the screen the event happened on.
+	pub root: Window,
+	/// The window the event is reported relative to.
+	pub event: Window,
+	/// The child of `event` which contains the pointer, if any.
+	pub child: Option<Window>,
+
+	/// The pointer's position relative to `root`.
+	pub root_position: InputPosition,
+	/// The pointer's position relative to `event`.
+	pub event_position: InputPosition,
+
+	/// The state of the modifiers, pointer buttons and keyboard group before
+	/// the event, as in core events.
+	pub state: u16,
+	/// Whether a key event was generated by autorepeat.
+	///
+	/// This is always `false` for core events, which can't tell.
+	pub repeat: bool,
+}
+
+impl InputEvent {
+	/// Converts a core key, button or motion event into an `InputEvent`.
+	///
+	/// Returns `None` for any other event.
+	pub fn from_core(event: &Event) -> Option<Self> {
+		let (kind, fields) = match event {
+			Event::KeyPress(key) => (InputKind::KeyPress(key.detail), core_fields(key)),
+			Event::KeyRelease(key) => (InputKind::KeyRelease(key.detail), core_fields(key)),
+			Event::ButtonPress(button) => {
+				(InputKind::ButtonPress(button.detail), core_fields(button))
+			},
+			Event::ButtonRelease(button) => {
+				(InputKind::ButtonRelease(button.detail), core_fields(button))
+			},
+			Event::MotionNotify(motion) => (InputKind::Motion, core_fields(motion)),
+
+			_ => return None,
+		};
+
+		let device = match kind {
+			InputKind::KeyPress(_) | InputKind::KeyRelease(_) => DeviceId::VIRTUAL_CORE_KEYBOARD,
+			_ => DeviceId::VIRTUAL_CORE_POINTER,
+		};
+
+		Some(Self {
+			kind,
+
+			device,
+			source: device,
+
+			..fields
+		})
+	}
+
+	/// Converts an XInput 2 key, button or motion event into an `InputEvent`,
+	/// given the XInput extension's major opcode.
+	///
+	/// Returns `None` for any other frame.
+	pub fn from_xi2(xinput: u8, frame: &X11Frame) -> Option<Self> {
+		let X11Frame::GenericEvent {
+			extension,
+			event_type,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+
+		if *extension != xinput {
+			return None;
+		}
+
+		// The chunk starts after the event type, at byte 10 of the event.
+		let u16_at = |offset: usize| -> Option<u16> {
+			Some(u16::from_be_bytes(
+				chunk.get(offset..offset + 2)?.try_into().ok()?,
+			))
+		};
+		let u32_at = |offset: usize| -> Option<u32> {
+			Some(u32::from_be_bytes(
+				chunk.get(offset..offset + 4)?.try_into().ok()?,
+			))
+		};
+		// `FP1616` values.
+		let position_at = |offset: usize| -> Option<InputPosition> {
+			Some(InputPosition {
+				x: f64::from(u32_at(offset)? as i32) / 65536.0,
+				y: f64::from(u32_at(offset + 4)? as i32) / 65536.0,
+			})
+		};
+
+		let detail = u32_at(6)?;
+		let flags = u32_at(46)?;
+
+		let kind = match *event_type {
+			XI_KEY_PRESS => InputKind::KeyPress(detail.try_into().ok()?),
+			XI_KEY_RELEASE => InputKind::KeyRelease(detail.try_into().ok()?),
+			XI_BUTTON_PRESS => InputKind::ButtonPress(detail.try_into().ok()?),
+			XI_BUTTON_RELEASE => InputKind::ButtonRelease(detail.try_into().ok()?),
+			XI_MOTION => InputKind::Motion,
+
+			_ => return None,
+		};
+
+		// Core events' state holds the effective modifiers in its low byte,
+		// buttons 1 to 5 in the next 5 bits, and the keyboard group after them.
+		let modifiers = u32_at(62)? & 0xff;
+		let group = u32::from(*chunk.get(69)?) & 0b11;
+		let buttons = if u16_at(38)? > 0 {
+			(u32_at(70)? >> 1) & 0b1_1111
+		} else {
+			0
+		};
+
+		Some(Self {
+			kind,
+
+			device: DeviceId(u16_at(0)?),
+			source: DeviceId(u16_at(42)?),
+			time: Timestamp(u32_at(2)?),
+
+			root: Window(u32_at(10)?),
+			event: Window(u32_at(14)?),
+			child: match u32_at(18)? {
+				0 => None,
+				child => Some(Window(child)),
+			},
+
+			root_position: position_at(22)?,
+			event_position: position_at(30)?,
+
+			state: (modifiers | (buttons << 8) | (group << 13)) as u16,
+			repeat: matches!(kind, InputKind::KeyPress(_)) && flags & XI_KEY_REPEAT != 0,
+		})
+	}
+}
+
+/// Converts the fields shared by core key, button and motion events, with a
+/// placeholder kind and devices.
+fn core_fields<Detail>(event: &KeyButtonEvent<Detail>) -> InputEvent {
+	InputEvent {
+		kind: InputKind::Motion,
+
+		device: DeviceId::VIRTUAL_CORE_POINTER,
+		source: DeviceId::VIRTUAL_CORE_POINTER,
+		time: event.time,
+
+		root: event.root,
+		event: event.event,
+		child: event.child,
+
+		root_position: event.root_position.into(),
+		event_position: event.event_position.into(),
+
+		state: event.state,
+		repeat: false,
+	}
+}
+
+/// Converts core and XInput 2 input events into [`InputEvent`]s.
+///
+/// See [`Client::input_adapter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputAdapter {
+	window: Window,
+	/// The major opcode of XInput, if XInput 2 events were selected.
+	xinput: Option<u8>,
+}
+
+impl InputAdapter {
+	/// Creates an adapter which only converts core events, for windows on
+	/// which XInput 2 events weren't selected.
+	pub const fn core(window: Window) -> Self {
+		Self {
+			window,
+			xinput: None,
+		}
+	}
+
+	/// Returns the window the adapter was created for.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// Returns whether XInput 2 events were selected on the window.
+	pub const fn is_xi2(&self) -> bool {
+		self.xinput.is_some()
+	}
+
+	/// Converts `frame` into an [`InputEvent`], if it is a core or XInput 2
+	/// key, button or motion event.
+	pub fn decode(&self, frame: &X11Frame) -> Option<InputEvent> {
+		match frame {
+			X11Frame::GenericEvent { .. } => InputEvent::from_xi2(self.xinput?, frame),
+
+			_ => InputEvent::from_core(&Event::from_frame(frame)?),
+		}
+	}
+}
+
+/// The XTEST extension's `XTestFakeInput` request, which makes the X server
+/// act as though a key, button or pointer motion event happened.
+///
+/// [`FakeInput::default`] is a `KeyPress` of keycode `0` which happens
+/// immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FakeInput {
+	/// The event code of the event: `KeyPress`, `KeyRelease`, `ButtonPress`,
+	/// `ButtonRelease` or `MotionNotify`.
+	pub event_type: u8,
+	/// The keycode or button, or for `MotionNotify`, `1` if `position` is
+	/// relative to the pointer's current position.
+	pub detail: u8,
+	/// How long after the request is processed the event happens, in
+	/// milliseconds, or `CurrentTime` for immediately.
+	pub time: Timestamp,
+	/// For `MotionNotify`, the root window the pointer is moved to, or
+	/// `None` for the root window it is already on.
+	pub root: Option<Window>,
+	/// For `MotionNotify`, where the pointer is moved to.
+	pub position: Point,
+	/// The XInput device the event comes from, or `0` for the core devices.
+	pub device: u8,
+}
+
+impl Default for FakeInput {
+	fn default() -> Self {
+		Self {
+			event_type: KEY_PRESS,
+			detail: 0,
+			time: Timestamp::CURRENT_TIME,
+			root: None,
+			position: Point::ORIGIN,
+			device: 0,
+		}
+	}
+}
+
+impl Request for FakeInput {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XTestFakeInput";
+	const EXTENSION: Option<&'static str> = Some(XTEST);
+	const OPCODE: u8 = XTEST_FAKE_INPUT;
+	const LENGTH: Option<u16> = Some(9);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.event_type);
+		body.put_u8(self.detail);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_u32(self.time.0);
+		body.put_u32(self.root.map_or(0, Window::id));
+		// 8 unused bytes.
+		body.put_bytes(0, 8);
+		self.position.write_to(body);
+		// 7 unused bytes.
+		body.put_bytes(0, 7);
+		body.put_u8(self.device);
+	}
+}
+
+assert_wire_size!(
+	FakeInput: [u8, u8, u16, u8, u8, [u8; 2], Timestamp, Window, [u8; 8], Point, [u8; 7], u8]
+);
+
+/// Selects XInput 2 key, button and motion events on `window` if the X
+/// server supports XInput 2, returning an [`InputAdapter`].
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::input_adapter`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn input_adapter(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<InputAdapter, ReplyError> {
+	let Some(xinput) = pointer::query_xinput2(connection).await? else {
+		connection.writer().degrade(CORE_INPUT);
+
+		return Ok(InputAdapter::core(window));
+	};
+
+	let request = XISelectEvents {
+		window,
+		device: pointer::XI_ALL_MASTER_DEVICES,
+		mask: (1 << XI_KEY_PRESS)
+			| (1 << XI_KEY_RELEASE)
+			| (1 << XI_BUTTON_PRESS)
+			| (1 << XI_BUTTON_RELEASE)
+			| (1 << XI_MOTION),
+	};
+
+	connection
+		.writer()
+		.send_raw(request.to_raw(xinput))
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(InputAdapter {
+		window,
+		xinput: Some(xinput),
+	})
+}
+
+impl Client {
+	/// Selects XInput 2 key, button and motion events on `window` if the X
+	/// server supports XInput 2, returning an [`InputAdapter`] which converts
+	/// them, or the core events otherwise, into [`InputEvent`]s.
+	///
+	/// The core events should still be selected on `window` for when XInput 2
+	/// isn't supported, which is recorded as a [`Degradation`].
+	///
+	/// [`Degradation`]: crate::extension::Degradation
+	pub async fn input_adapter(&mut self, window: Window) -> Result<InputAdapter, ReplyError> {
+		input_adapter(self, window).await
+	}
+}
+
+/// What happens to the slave devices of a master device when it is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReturnMode {
+	/// The slave pointers and keyboards are attached to these master devices.
+	AttachTo {
+		pointer: DeviceId,
+		keyboard: DeviceId,
+	},
+	/// The slave devices are left floating, attached to no master device.
+	Float,
+}
+
+/// A change made to the device hierarchy by an `XIChangeHierarchy` request.
+///
+/// See [`Client::change_hierarchy`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HierarchyChange {
+	/// Adds a master pointer and keyboard, named `name` followed by
+	/// `" pointer"` and `" keyboard"`.
+	AddMaster {
+		name: String,
+		/// Whether the new devices send core events.
+		send_core: bool,
+		/// Whether the new devices are enabled.
+		enable: bool,
+	},
+	/// Removes a master device, along with the master device paired with
+	/// it.
+	RemoveMaster {
+		/// Either of the master devices to remove.
+		device: DeviceId,
+		return_mode: ReturnMode,
+	},
+	/// Attaches a slave device to a master device, detaching it from its
+	/// current master device first.
+	AttachSlave { device: DeviceId, master: DeviceId },
+	/// Detaches a slave device from its master device, leaving it floating.
+	DetachSlave { device: DeviceId },
+}
+
+impl HierarchyChange {
+	/// Returns the change's type in the protocol.
+	const fn change_type(&self) -> u16 {
+		match self {
+			Self::AddMaster { .. } => 1,
+			Self::RemoveMaster { .. } => 2,
+			Self::AttachSlave { .. } => 3,
+			Self::DetachSlave { .. } => 4,
+		}
+	}
+
+	/// Writes the change as a `HIERARCHYCHANGE`.
+	fn write_to(&self, body: &mut BytesMut) {
+		body.put_u16(self.change_type());
+
+		match self {
+			Self::AddMaster {
+				name,
+				send_core,
+				enable,
+			} => {
+				let padding = (4 - name.len() % 4) % 4;
+
+				// Names which are too long are rejected by
+				// `XIChangeHierarchy::new`; a longer name given directly is
+				// truncated.
+				body.put_u16(((8 + name.len() + padding) / 4) as u16);
+				body.put_u16(name.len() as u16);
+				body.put_u8((*send_core).into());
+				body.put_u8((*enable).into());
+				body.put_slice(name.as_bytes());
+				body.put_bytes(0, padding);
+			},
+
+			Self::RemoveMaster {
+				device,
+				return_mode,
+			} => {
+				body.put_u16(3);
+				body.put_u16(device.0);
+
+				match return_mode {
+					ReturnMode::AttachTo { pointer, keyboard } => {
+						body.put_u8(1);
+						// 1 unused byte.
+						body.put_u8(0);
+						body.put_u16(pointer.0);
+						body.put_u16(keyboard.0);
+					},
+
+					ReturnMode::Float => {
+						body.put_u8(2);
+						// 5 unused bytes.
+						body.put_bytes(0, 5);
+					},
+				}
+			},
+
+			Self::AttachSlave { device, master } => {
+				body.put_u16(2);
+				body.put_u16(device.0);
+				body.put_u16(master.0);
+			},
+
+			Self::DetachSlave { device } => {
+				body.put_u16(2);
+				body.put_u16(device.0);
+				// 2 unused bytes.
+				body.put_bytes(0, 2);
+			},
+		}
+	}
+}
+
+/// The XInput extension's `XIChangeHierarchy` request.
+///
+/// See [`Client::change_hierarchy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIChangeHierarchy<'a> {
+	pub changes: &'a [HierarchyChange],
+}
+
+impl<'a> XIChangeHierarchy<'a> {
+	/// Returns an `XIChangeHierarchy` request making `changes`, or an error if
+	/// there are too many of them, or a name is too long, to be sent.
+	fn new(changes: &'a [HierarchyChange]) -> io::Result<Self> {
+		let name_too_long = |change: &HierarchyChange| match change {
+			HierarchyChange::AddMaster { name, .. } => u16::try_from(name.len()).is_err(),
+			_ => false,
+		};
+
+		if u8::try_from(changes.len()).is_err() || changes.iter().any(name_too_long) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"too many hierarchy changes, or a master device name is too long",
+			));
+		}
+
+		Ok(Self { changes })
+	}
+}
+
+impl Request for XIChangeHierarchy<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XIChangeHierarchy";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_CHANGE_HIERARCHY;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.changes.len() as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+
+		for change in self.changes {
+			change.write_to(body);
+		}
+	}
+}
+
+/// The XInput extension's `XISetClientPointer` request.
+///
+/// See [`Client::set_client_pointer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XISetClientPointer {
+	/// A window whose owner's client pointer is set, or `None` for this
+	/// client's.
+	pub window: Option<Window>,
+	/// The master pointer.
+	pub device: DeviceId,
+}
+
+impl Request for XISetClientPointer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XISetClientPointer";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_SET_CLIENT_POINTER;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.map_or(0, Window::id));
+		body.put_u16(self.device.0);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(XISetClientPointer: [u8, u8, u16, Window, u16, [u8; 2]]);
+
+/// The XInput extension's `XIGetClientPointer` request.
+///
+/// See [`Client::client_pointer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIGetClientPointer {
+	/// A window whose owner's client pointer is returned, or `None` for this
+	/// client's.
+	pub window: Option<Window>,
+}
+
+impl Request for XIGetClientPointer {
+	type Reply = ClientPointer;
+
+	const NAME: &'static str = "XIGetClientPointer";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_GET_CLIENT_POINTER;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.map_or(0, Window::id));
+	}
+}
+
+assert_wire_size!(XIGetClientPointer: [u8, u8, u16, Window]);
+
+/// The master pointer used by a client for core requests and events which
+/// don't say which pointer they are about.
+///
+/// This is the reply to an `XIGetClientPointer` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientPointer {
+	pub device: DeviceId,
+	/// Whether the client pointer was set with `XISetClientPointer`, rather
+	/// than chosen by the X server.
+	pub explicit: bool,
+}
+
+assert_wire_size!("XIGetClientPointer reply": [u8, u8, u16, u32, u8, u8, u16, [u8; 20]] == 32);
+
+impl Reply for ClientPointer {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[set, _, d0, d1] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			device: DeviceId(u16::from_be_bytes([d0, d1])),
+			explicit: set != 0,
+		})
+	}
+}
+
+/// Makes `changes` to the device hierarchy, in order.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::change_hierarchy`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn change_hierarchy(
+	connection: &mut impl RoundTrip, changes: &[HierarchyChange],
+) -> Result<(), ReplyError> {
+	let request = XIChangeHierarchy::new(changes).map_err(ReplyError::Io)?;
+	pointer::require_xinput2(connection, XI_HIERARCHY_VERSION).await?;
+
+	request::send(connection, &request).await
+}
+
+/// Sets the client pointer of `window`'s owner, or of this client if
+/// `window` is `None`, to the master pointer `device`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_client_pointer`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_client_pointer(
+	connection: &mut impl RoundTrip, window: Option<Window>, device: DeviceId,
+) -> Result<(), ReplyError> {
+	pointer::require_xinput2(connection, XI_HIERARCHY_VERSION).await?;
+
+	request::send(connection, &XISetClientPointer { window, device }).await
+}
+
+/// Gets the client pointer of `window`'s owner, or of this client if
+/// `window` is `None`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::client_pointer`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn client_pointer(
+	connection: &mut impl RoundTrip, window: Option<Window>,
+) -> Result<ClientPointer, ReplyError> {
+	pointer::require_xinput2(connection, XI_HIERARCHY_VERSION).await?;
+
+	request::reply_to(connection, &XIGetClientPointer { window }).await
+}
+
+impl Client {
+	/// Makes `changes` to the XInput 2 device hierarchy, in order, such as
+	/// adding a master pointer and attaching a mouse to it.
+	///
+	/// The X server reports the new hierarchy with `XIHierarchyChanged`
+	/// events. It generates an error, and makes none of the changes, if any
+	/// of them is invalid, such as removing a slave device or the virtual
+	/// core devices.
+	///
+	/// Returns an [`Unsupported`] error if the X server doesn't support
+	/// XInput 2.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn change_hierarchy(
+		&mut self, changes: &[HierarchyChange],
+	) -> Result<(), ReplyError> {
+		change_hierarchy(self, changes).await
+	}
+
+	/// Sets the client pointer of `window`'s owner, or of this client if
+	/// `window` is `None`, to the master pointer `device`.
+	///
+	/// The client pointer is the pointer used by core requests and events
+	/// which don't say which pointer they are about, such as `QueryPointer`
+	/// and `GrabPointer`, and the keyboard paired with it is used for core
+	/// keyboard requests.
+	///
+	/// Returns an [`Unsupported`] error if the X server doesn't support
+	/// XInput 2.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn set_client_pointer(
+		&mut self, window: Option<Window>, device: DeviceId,
+	) -> Result<(), ReplyError> {
+		set_client_pointer(self, window, device).await
+	}
+
+	/// Gets the client pointer of `window`'s owner, or of this client if
+	/// `window` is `None`.
+	///
+	/// See [`set_client_pointer`].
+	///
+	/// [`set_client_pointer`]: Client::set_client_pointer
+	pub async fn client_pointer(
+		&mut self, window: Option<Window>,
+	) -> Result<ClientPointer, ReplyError> {
+		client_pointer(self, window).await
+	}
+}
+
+/// Options for grabbing a device with [`Client::grab_device`] or
+/// [`Client::passive_grab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceGrab {
+	/// Whether the device's events are reported to the client's windows as
+	/// usual, instead of only to the grab window.
+	pub owner_events: bool,
+	/// The XInput 2 events reported to the grab window, with bit `n`
+	/// selecting event type `n`.
+	pub mask: u32,
+
+	/// How the grabbed device's events are processed.
+	///
+	/// This is ignored for passive grabs of touches, whose events are
+	/// processed until they are accepted or rejected.
+	pub mode: GrabMode,
+	/// How the events of the master device paired with the grabbed device
+	/// are processed.
+	pub paired_device_mode: GrabMode,
+
+	/// The cursor shown while a pointer is grabbed, or `None` to keep the
+	/// cursor of the window it is in.
+	pub cursor: Option<u32>,
+
+	/// When the grab was made, such as the time of the event which caused
+	/// it.
+	pub time: Timestamp,
+}
+
+impl Default for DeviceGrab {
+	fn default() -> Self {
+		Self {
+			owner_events: false,
+			mask: 0,
+
+			mode: GrabMode::Asynchronous,
+			paired_device_mode: GrabMode::Asynchronous,
+
+			cursor: None,
+
+			time: Timestamp::CURRENT_TIME,
+		}
+	}
+}
+
+/// What activates a passive grab made with [`Client::passive_grab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PassiveGrabKind {
+	/// Pressing the button, or any button if `None`.
+	Button(Option<u8>),
+	/// Pressing the key, or any key if `None`.
+	Keycode(Option<Keycode>),
+	/// The pointer entering the grab window.
+	Enter,
+	/// The grab window gaining the input focus.
+	FocusIn,
+	/// A touch beginning in the grab window.
+	///
+	/// This needs XInput 2.2.
+	TouchBegin,
+}
+
+impl PassiveGrabKind {
+	/// Returns the grab's type and detail in the protocol.
+	fn values(self) -> (u8, u32) {
+		match self {
+			Self::Button(button) => (0, button.map_or(XI_ANY, u32::from)),
+			Self::Keycode(key) => (1, key.map_or(XI_ANY, u32::from)),
+			Self::Enter => (2, 0),
+			Self::FocusIn => (3, 0),
+			Self::TouchBegin => (4, 0),
+		}
+	}
+}
+
+/// How [`Client::allow_device_events`] releases a device's frozen events, or
+/// handles a grabbed touch sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventMode {
+	/// Processes the device's events as usual again.
+	AsyncDevice,
+	/// Processes the device's events until the next one which is reported,
+	/// and then freezes it again.
+	SyncDevice,
+	/// Releases a passive grab which froze the device, processing the event
+	/// which activated it as though it hadn't been grabbed.
+	ReplayDevice,
+	/// Processes the events of the master device paired with the device as
+	/// usual again.
+	AsyncPairedDevice,
+	/// Processes the events of both the device and its paired device as
+	/// usual again.
+	AsyncPair,
+	/// Like [`SyncDevice`] for both the device and its paired device.
+	///
+	/// [`SyncDevice`]: EventMode::SyncDevice
+	SyncPair,
+	/// Accepts the touch sequence `touch`, grabbed passively on
+	/// `grab_window`, ending it for every other client listening to it.
+	///
+	/// This needs XInput 2.2.
+	AcceptTouch { touch: u32, grab_window: Window },
+	/// Rejects the touch sequence `touch`, grabbed passively on
+	/// `grab_window`, so that it is passed on to the next client listening
+	/// to it, which is sent the events already received.
+	///
+	/// This needs XInput 2.2.
+	RejectTouch { touch: u32, grab_window: Window },
+}
+
+impl EventMode {
+	/// Returns the mode's value in the protocol.
+	const fn value(self) -> u8 {
+		match self {
+			Self::AsyncDevice => 0,
+			Self::SyncDevice => 1,
+			Self::ReplayDevice => 2,
+			Self::AsyncPairedDevice => 3,
+			Self::AsyncPair => 4,
+			Self::SyncPair => 5,
+			Self::AcceptTouch { .. } => 6,
+			Self::RejectTouch { .. } => 7,
+		}
+	}
+
+	/// Returns the touch sequence and its grab window, if the mode accepts
+	/// or rejects one.
+	const fn touch(self) -> Option<(u32, Window)> {
+		match self {
+			Self::AcceptTouch { touch, grab_window } | Self::RejectTouch { touch, grab_window } => {
+				Some((touch, grab_window))
+			},
+
+			_ => None,
+		}
+	}
+}
+
+/// A combination of modifiers which couldn't be grabbed by an
+/// `XIPassiveGrabDevice` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailedGrab {
+	pub modifiers: u32,
+	pub status: GrabStatus,
+}
+
+/// The combinations of modifiers which couldn't be grabbed.
+///
+/// This is the reply to an `XIPassiveGrabDevice` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailedGrabs(pub Vec<FailedGrab>);
+
+/// The size of a `GRABMODIFIERINFO` in bytes.
+const GRAB_MODIFIER_INFO: usize = 8;
+
+assert_wire_size!("XIPassiveGrabDevice reply": [u8, u8, u16, u32, u16, [u8; 22]] == 32);
+
+impl Reply for FailedGrabs {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[n0, n1] = chunk.get(..2)? else {
+			return None;
+		};
+		let count = usize::from(u16::from_be_bytes([n0, n1]));
+
+		let failures = chunk.get(24..)?.get(..count * GRAB_MODIFIER_INFO)?;
+
+		failures
+			.chunks_exact(GRAB_MODIFIER_INFO)
+			.map(|failure| {
+				let status = match failure[4] {
+					BAD_ACCESS => GrabStatus::AlreadyGrabbed,
+					status => GrabStatus::from_value(status)?,
+				};
+
+				Some(FailedGrab {
+					modifiers: u32::from_be_bytes(failure[..4].try_into().ok()?),
+					status,
+				})
+			})
+			.collect::<Option<_>>()
+			.map(Self)
+	}
+}
+
+/// The status of an `XIGrabDevice` request.
+///
+/// This is the reply to an `XIGrabDevice` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceGrabStatus(pub GrabStatus);
+
+assert_wire_size!("XIGrabDevice reply": [u8, u8, u16, u32, u8, [u8; 23]] == 32);
+
+impl Reply for DeviceGrabStatus {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		GrabStatus::from_value(*chunk.first()?).map(Self)
+	}
+}
+
+/// The XInput extension's `XIGrabDevice` request.
+///
+/// See [`Client::grab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIGrabDevice {
+	pub window: Window,
+	pub device: DeviceId,
+	pub grab: DeviceGrab,
+}
+
+impl Request for XIGrabDevice {
+	type Reply = DeviceGrabStatus;
+
+	const NAME: &'static str = "XIGrabDevice";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_GRAB_DEVICE;
+	const LENGTH: Option<u16> = Some(7);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u32(self.grab.time.0);
+		body.put_u32(self.grab.cursor.unwrap_or(0));
+		body.put_u16(self.device.0);
+		body.put_u8(self.grab.mode.value());
+		body.put_u8(self.grab.paired_device_mode.value());
+		body.put_u8(self.grab.owner_events.into());
+		// 1 unused byte.
+		body.put_u8(0);
+		// mask-len
+		body.put_u16(1);
+		body.put_u32(self.grab.mask);
+	}
+}
+
+assert_wire_size!(
+	XIGrabDevice: [u8, u8, u16, Window, Timestamp, u32, u16, u8, u8, bool, [u8; 1], u16, u32]
+);
+
+/// The XInput extension's `XIUngrabDevice` request.
+///
+/// See [`Client::ungrab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIUngrabDevice {
+	pub device: DeviceId,
+	pub time: Timestamp,
+}
+
+impl Request for XIUngrabDevice {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XIUngrabDevice";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_UNGRAB_DEVICE;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.time.0);
+		body.put_u16(self.device.0);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(XIUngrabDevice: [u8, u8, u16, Timestamp, u16, [u8; 2]]);
+
+/// The XInput extension's `XIAllowEvents` request.
+///
+/// Touch sequences are only accepted or rejected by the XInput 2.2 form of
+/// the request, which is only sent for them.
+///
+/// See [`Client::allow_device_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIAllowEvents {
+	pub device: DeviceId,
+	pub time: Timestamp,
+	pub mode: EventMode,
+}
+
+impl Request for XIAllowEvents {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XIAllowEvents";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_ALLOW_EVENTS;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.time.0);
+		body.put_u16(self.device.0);
+		body.put_u8(self.mode.value());
+		// 1 unused byte.
+		body.put_u8(0);
+
+		if let Some((touch, grab_window)) = self.mode.touch() {
+			body.put_u32(touch);
+			body.put_u32(grab_window.id());
+		}
+	}
+}
+
+/// The XInput extension's `XIPassiveGrabDevice` request.
+///
+/// See [`Client::passive_grab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIPassiveGrabDevice<'a> {
+	pub window: Window,
+	pub device: DeviceId,
+	pub kind: PassiveGrabKind,
+	/// The combinations of modifiers to grab with, which may include
+	/// [`XI_ANY_MODIFIER`].
+	pub modifiers: &'a [u32],
+	pub grab: DeviceGrab,
+}
+
+impl Request for XIPassiveGrabDevice<'_> {
+	type Reply = FailedGrabs;
+
+	const NAME: &'static str = "XIPassiveGrabDevice";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_PASSIVE_GRAB_DEVICE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		let (grab_type, detail) = self.kind.values();
+		let mode = match self.kind {
+			PassiveGrabKind::TouchBegin => XI_GRAB_MODE_TOUCH,
+			_ => self.grab.mode.value(),
+		};
+		// Longer lists are rejected by `check_modifiers`; a longer list given
+		// directly is truncated.
+		let modifiers = &self.modifiers[..self.modifiers.len().min(u16::MAX.into())];
+
+		body.put_u32(self.window.id());
+		body.put_u32(self.grab.time.0);
+		body.put_u32(self.grab.cursor.unwrap_or(0));
+		body.put_u32(detail);
+		body.put_u16(self.device.0);
+		body.put_u16(modifiers.len() as u16);
+		// mask-len
+		body.put_u16(1);
+		body.put_u8(grab_type);
+		body.put_u8(mode);
+		body.put_u8(self.grab.paired_device_mode.value());
+		body.put_u8(self.grab.owner_events.into());
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+
+		body.put_u32(self.grab.mask);
+		for &modifier in modifiers {
+			body.put_u32(modifier);
+		}
+	}
+}
+
+/// The XInput extension's `XIPassiveUngrabDevice` request.
+///
+/// See [`Client::passive_ungrab_device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIPassiveUngrabDevice<'a> {
+	pub window: Window,
+	pub device: DeviceId,
+	pub kind: PassiveGrabKind,
+	pub modifiers: &'a [u32],
+}
+
+impl Request for XIPassiveUngrabDevice<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XIPassiveUngrabDevice";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_PASSIVE_UNGRAB_DEVICE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		let (grab_type, detail) = self.kind.values();
+		// Longer lists are rejected by `check_modifiers`; a longer list given
+		// directly is truncated.
+		let modifiers = &self.modifiers[..self.modifiers.len().min(u16::MAX.into())];
+
+		body.put_u32(self.window.id());
+		body.put_u32(detail);
+		body.put_u16(self.device.0);
+		body.put_u16(modifiers.len() as u16);
+		body.put_u8(grab_type);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+
+		for &modifier in modifiers {
+			body.put_u32(modifier);
+		}
+	}
+}
+
+/// Returns an error if there are too many combinations of `modifiers` to be
+/// sent in one passive grab request.
+fn check_modifiers(modifiers: &[u32]) -> Result<(), ReplyError> {
+	match u16::try_from(modifiers.len()) {
+		Ok(_) => Ok(()),
+
+		Err(_) => Err(ReplyError::Io(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"too many combinations of modifiers to grab",
+		))),
+	}
+}
+
+/// Tells the X server that this client uses XInput 2, returning an error if
+/// it doesn't support the version needed to grab with `kind`.
+async fn require_grab_version(
+	connection: &mut impl RoundTrip, kind: PassiveGrabKind,
+) -> Result<(), ReplyError> {
+	let version = match kind {
+		PassiveGrabKind::TouchBegin => XI_TOUCH_VERSION,
+		_ => XI_HIERARCHY_VERSION,
+	};
+
+	pointer::require_xinput2(connection, version).await
+}
+
+/// Actively grabs `device` for `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::grab_device`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn grab_device(
+	connection: &mut impl RoundTrip, window: Window, device: DeviceId, grab: &DeviceGrab,
+) -> Result<GrabStatus, ReplyError> {
+	pointer::require_xinput2(connection, XI_HIERARCHY_VERSION).await?;
+
+	let request = XIGrabDevice {
+		window,
+		device,
+		grab: *grab,
+	};
+	let DeviceGrabStatus(status) = request::reply_to(connection, &request).await?;
+
+	Ok(status)
+}
+
+/// Releases `device` if this client has grabbed it.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::ungrab_device`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn ungrab_device(
+	connection: &mut impl RoundTrip, device: DeviceId, time: Timestamp,
+) -> Result<(), ReplyError> {
+	pointer::require_xinput2(connection, XI_HIERARCHY_VERSION).await?;
+
+	request::send(connection, &XIUngrabDevice { device, time }).await
+}
+
+/// Releases `device`'s frozen events, or accepts or rejects a touch
+/// sequence, according to `mode`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::allow_device_events`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn allow_device_events(
+	connection: &mut impl RoundTrip, device: DeviceId, time: Timestamp, mode: EventMode,
+) -> Result<(), ReplyError> {
+	let version = match mode.touch() {
+		Some(_) => XI_TOUCH_VERSION,
+		None => XI_HIERARCHY_VERSION,
+	};
+	pointer::require_xinput2(connection, version).await?;
+
+	request::send(connection, &XIAllowEvents { device, time, mode }).await
+}
+
+/// Passively grabs `device` on `window` whenever `kind` happens with one of
+/// the combinations of `modifiers` held, returning those which couldn't be
+/// grabbed.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::passive_grab_device`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn passive_grab_device(
+	connection: &mut impl RoundTrip, window: Window, device: DeviceId, kind: PassiveGrabKind,
+	modifiers: &[u32], grab: &DeviceGrab,
+) -> Result<Vec<FailedGrab>, ReplyError> {
+	check_modifiers(modifiers)?;
+	require_grab_version(connection, kind).await?;
+
+	let request = XIPassiveGrabDevice {
+		window,
+		device,
+		kind,
+		modifiers,
+		grab: *grab,
+	};
+	let FailedGrabs(failures) = request::reply_to(connection, &request).await?;
+
+	Ok(failures)
+}
+
+/// Releases the passive grabs of `device` on `window` for `kind` with the
+/// combinations of `modifiers`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::passive_ungrab_device`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn passive_ungrab_device(
+	connection: &mut impl RoundTrip, window: Window, device: DeviceId, kind: PassiveGrabKind,
+	modifiers: &[u32],
+) -> Result<(), ReplyError> {
+	check_modifiers(modifiers)?;
+	require_grab_version(connection, kind).await?;
+
+	let request = XIPassiveUngrabDevice {
+		window,
+		device,
+		kind,
+		modifiers,
+	};
+
+	request::send(connection, &request).await
+}
+
+impl Client {
+	/// Actively grabs the master device `device`, so that its events are
+	/// reported to `window` until the grab is released with
+	/// [`ungrab_device`].
+	///
+	/// Returns an [`Unsupported`] error if the X server doesn't support
+	/// XInput 2.
+	///
+	/// [`ungrab_device`]: Client::ungrab_device
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn grab_device(
+		&mut self, window: Window, device: DeviceId, grab: &DeviceGrab,
+	) -> Result<GrabStatus, ReplyError> {
+		grab_device(self, window, device, grab).await
+	}
+
+	/// Releases `device` if this client has grabbed it.
+	///
+	/// The grab is only released if `time` isn't earlier than when it was
+	/// made.
+	pub async fn ungrab_device(
+		&mut self, device: DeviceId, time: Timestamp,
+	) -> Result<(), ReplyError> {
+		ungrab_device(self, device, time).await
+	}
+
+	/// Releases `device`'s events frozen by a synchronous grab, or accepts or
+	/// rejects a touch sequence grabbed with [`PassiveGrabKind::TouchBegin`],
+	/// according to `mode`.
+	///
+	/// Nothing happens if `time` is earlier than when the device was last
+	/// grabbed, or later than the X server's current time.
+	///
+	/// Accepting or rejecting a touch sequence returns an
+	/// [`UnsupportedVersion`] error if the X server doesn't support
+	/// XInput 2.2.
+	///
+	/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+	pub async fn allow_device_events(
+		&mut self, device: DeviceId, time: Timestamp, mode: EventMode,
+	) -> Result<(), ReplyError> {
+		allow_device_events(self, device, time, mode).await
+	}
+
+	/// Passively grabs `device` on `window`, so that it is grabbed whenever
+	/// `kind` happens with exactly one of the combinations of `modifiers`
+	/// held.
+	///
+	/// [`XI_ANY_MODIFIER`] matches any combination of modifiers. Returns the
+	/// combinations which couldn't be grabbed, such as those already grabbed
+	/// by another client; the others are grabbed regardless.
+	///
+	/// Touches are grabbed by the first client to grab them on the outermost
+	/// window, and their events must be accepted or rejected with
+	/// [`allow_device_events`]. Grabbing touches returns an
+	/// [`UnsupportedVersion`] error if the X server doesn't support
+	/// XInput 2.2.
+	///
+	/// [`allow_device_events`]: Client::allow_device_events
+	/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+	pub async fn passive_grab_device(
+		&mut self, window: Window, device: DeviceId, kind: PassiveGrabKind, modifiers: &[u32],
+		grab: &DeviceGrab,
+	) -> Result<Vec<FailedGrab>, ReplyError> {
+		passive_grab_device(self, window, device, kind, modifiers, grab).await
+	}
+
+	/// Releases the passive grabs of `device` on `window` for `kind` with
+	/// the combinations of `modifiers`.
+	///
+	/// `None` buttons and keys, and [`XI_ANY_MODIFIER`], match every button,
+	/// key and combination of modifiers, like in [`passive_grab_device`].
+	///
+	/// [`passive_grab_device`]: Client::passive_grab_device
+	pub async fn passive_ungrab_device(
+		&mut self, window: Window, device: DeviceId, kind: PassiveGrabKind, modifiers: &[u32],
+	) -> Result<(), ReplyError> {
+		passive_ungrab_device(self, window, device, kind, modifiers).await
+	}
+}
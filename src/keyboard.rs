@@ -0,0 +1,397 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Keyboard mappings and modifier resolution.
+//!
+//! The core protocol only has eight modifiers: `Shift`, `Lock`, `Control`,
+//! and `Mod1` through `Mod5`. Which of `Mod1`–`Mod5` correspond to keys such
+//! as NumLock or Super is not fixed; it depends on the keyboard mapping
+//! configured on the X server. [`ModifierMasks::resolve`] works this out by
+//! cross-referencing the modifier mapping with the keysyms of each keycode.
+//!
+//! [`Client::modifier_masks`] does so for the X server's current mappings,
+//! and remembers the result until a `MappingNotify` event reports that
+//! either mapping has changed.
+
+use crate::{
+	client::RoundTrip,
+	event::codes::MAPPING_NOTIFY,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, Reply, Request},
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+use std::sync::atomic::Ordering;
+
+/// Identifies a physical key.
+pub type Keycode = u8;
+/// Identifies a symbol on a key, such as a character or a modifier.
+pub type Keysym = u32;
+
+/// The major opcode of the `GetKeyboardMapping` request.
+const GET_KEYBOARD_MAPPING: u8 = 101;
+/// The major opcode of the `GetModifierMapping` request.
+const GET_MODIFIER_MAPPING: u8 = 119;
+
+/// The number of unused bytes at the start of the replies read in this
+/// module, after the 8-byte reply header.
+const REPLY_PADDING: usize = 24;
+
+/// The `request` of `MappingNotify` events for the modifier mapping.
+const MAPPING_MODIFIER: u8 = 0;
+/// The `request` of `MappingNotify` events for the keyboard mapping.
+const MAPPING_KEYBOARD: u8 = 1;
+
+/// The number of modifiers in the core protocol.
+const MODIFIERS: usize = 8;
+/// The index of `Mod1` in the modifier mapping; `Shift`, `Lock` and `Control`
+/// come before it.
+const FIRST_MOD: usize = 3;
+
+/// Keysyms which are relevant to modifier resolution.
+pub mod keysyms {
+	use super::Keysym;
+
+	pub const SCROLL_LOCK: Keysym = 0xff14;
+	pub const MODE_SWITCH: Keysym = 0xff7e;
+	pub const NUM_LOCK: Keysym = 0xff7f;
+
+	pub const META_L: Keysym = 0xffe7;
+	pub const META_R: Keysym = 0xffe8;
+	pub const ALT_L: Keysym = 0xffe9;
+	pub const ALT_R: Keysym = 0xffea;
+	pub const SUPER_L: Keysym = 0xffeb;
+	pub const SUPER_R: Keysym = 0xffec;
+	pub const HYPER_L: Keysym = 0xffed;
+	pub const HYPER_R: Keysym = 0xffee;
+
+	pub const ISO_LEVEL3_SHIFT: Keysym = 0xfe03;
+}
+
+/// Which keycodes are mapped to each of the eight core modifiers.
+///
+/// This is the reply to a `GetModifierMapping` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModifierMapping {
+	/// The maximum number of keycodes mapped to any one modifier.
+	pub keycodes_per_modifier: u8,
+	/// The keycodes mapped to each modifier, in the order `Shift`, `Lock`,
+	/// `Control`, `Mod1`, ..., `Mod5`.
+	///
+	/// There are `keycodes_per_modifier` keycodes for each modifier. A keycode
+	/// of `0` means the slot is unused.
+	pub keycodes: Vec<Keycode>,
+}
+
+impl ModifierMapping {
+	/// Returns a `GetModifierMapping` request.
+	pub const fn request() -> GetModifierMapping {
+		GetModifierMapping
+	}
+
+	/// Reads a `ModifierMapping` from the reply to a `GetModifierMapping`
+	/// request.
+	///
+	/// Returns `None` if `frame` is not a reply or is too short.
+	pub fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+
+		let count = MODIFIERS * (*metabyte as usize);
+		let keycodes = chunk.get(REPLY_PADDING..REPLY_PADDING + count)?;
+
+		Some(Self {
+			keycodes_per_modifier: *metabyte,
+			keycodes: keycodes.to_vec(),
+		})
+	}
+
+	/// Returns the keycodes mapped to the modifier at the given `index`, where
+	/// `0` is `Shift` and `7` is `Mod5`.
+	pub fn modifier(&self, index: usize) -> &[Keycode] {
+		let width = self.keycodes_per_modifier as usize;
+
+		self.keycodes
+			.get(index * width..(index + 1) * width)
+			.unwrap_or(&[])
+	}
+}
+
+impl Reply for ModifierMapping {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		Self::from_reply(frame)
+	}
+}
+
+/// The `GetModifierMapping` request, whose reply is a [`ModifierMapping`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetModifierMapping;
+
+impl Request for GetModifierMapping {
+	type Reply = ModifierMapping;
+
+	const NAME: &'static str = "GetModifierMapping";
+	const OPCODE: u8 = GET_MODIFIER_MAPPING;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(GetModifierMapping: [u8, [u8; 1], u16]);
+
+/// The keysyms associated with a range of keycodes.
+///
+/// This is the reply to a `GetKeyboardMapping` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyboardMapping {
+	/// The first keycode described by this mapping.
+	pub first_keycode: Keycode,
+	/// The number of keysyms associated with each keycode.
+	pub keysyms_per_keycode: u8,
+	/// The keysyms of each keycode, `keysyms_per_keycode` at a time.
+	///
+	/// A keysym of `0` (`NoSymbol`) means the slot is unused.
+	pub keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+	/// Returns a `GetKeyboardMapping` request for `count` keycodes starting at
+	/// `first_keycode`.
+	pub const fn request(first_keycode: Keycode, count: u8) -> GetKeyboardMapping {
+		GetKeyboardMapping {
+			first_keycode,
+			count,
+		}
+	}
+
+	/// Reads a `KeyboardMapping` from the reply to a `GetKeyboardMapping`
+	/// request which started at `first_keycode`.
+	///
+	/// Returns `None` if `frame` is not a reply.
+	pub fn from_reply(first_keycode: Keycode, frame: &X11Frame) -> Option<Self> {
+		Some(Keysyms::from_reply(frame)?.into_mapping(first_keycode))
+	}
+
+	/// Returns the keysyms associated with the given `keycode`.
+	///
+	/// Returns an empty slice if `keycode` is not described by this mapping.
+	pub fn keysyms(&self, keycode: Keycode) -> &[Keysym] {
+		let Some(offset) = keycode.checked_sub(self.first_keycode) else {
+			return &[];
+		};
+
+		let width = self.keysyms_per_keycode as usize;
+		let start = (offset as usize) * width;
+
+		self.keysyms.get(start..start + width).unwrap_or(&[])
+	}
+}
+
+/// The `GetKeyboardMapping` request, whose reply is the [`Keysyms`] of a
+/// range of keycodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetKeyboardMapping {
+	pub first_keycode: Keycode,
+	pub count: u8,
+}
+
+impl Request for GetKeyboardMapping {
+	type Reply = Keysyms;
+
+	const NAME: &'static str = "GetKeyboardMapping";
+	const OPCODE: u8 = GET_KEYBOARD_MAPPING;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.first_keycode);
+		body.put_u8(self.count);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(GetKeyboardMapping: [u8, [u8; 1], u16, Keycode, u8, [u8; 2]]);
+
+/// The keysyms of a range of keycodes.
+///
+/// This is the reply to a `GetKeyboardMapping` request, which doesn't
+/// repeat the range's first keycode; [`Keysyms::into_mapping`] adds it back.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Keysyms {
+	/// The number of keysyms associated with each keycode.
+	pub keysyms_per_keycode: u8,
+	/// The keysyms of each keycode, `keysyms_per_keycode` at a time.
+	pub keysyms: Vec<Keysym>,
+}
+
+impl Keysyms {
+	/// Returns the [`KeyboardMapping`] of these keysyms, for a request which
+	/// started at `first_keycode`.
+	pub fn into_mapping(self, first_keycode: Keycode) -> KeyboardMapping {
+		KeyboardMapping {
+			first_keycode,
+			keysyms_per_keycode: self.keysyms_per_keycode,
+			keysyms: self.keysyms,
+		}
+	}
+}
+
+impl Reply for Keysyms {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+
+		let keysyms = chunk
+			.get(REPLY_PADDING..)?
+			.chunks_exact(4)
+			.map(|keysym| u32::from_be_bytes([keysym[0], keysym[1], keysym[2], keysym[3]]))
+			.collect();
+
+		Some(Self {
+			keysyms_per_keycode: *metabyte,
+			keysyms,
+		})
+	}
+}
+
+/// The modifier masks corresponding to commonly used modifier keys.
+///
+/// Each mask is one of `Mod1`–`Mod5`, or `0` if no modifier is mapped to that
+/// key. See [`ModifierMasks::resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ModifierMasks {
+	pub num_lock: u16,
+	pub scroll_lock: u16,
+
+	pub alt: u16,
+	pub meta: u16,
+	pub super_: u16,
+	pub hyper: u16,
+
+	pub mode_switch: u16,
+	pub level3_shift: u16,
+}
+
+impl ModifierMasks {
+	/// Determines which of `Mod1`–`Mod5` correspond to each modifier key by
+	/// looking up the keysyms of the keycodes mapped to those modifiers.
+	///
+	/// If a key is mapped to more than one modifier, the lowest one is used.
+	pub fn resolve(modifiers: &ModifierMapping, keyboard: &KeyboardMapping) -> Self {
+		let mut masks = Self::default();
+
+		for index in FIRST_MOD..MODIFIERS {
+			let mask = 1 << index;
+
+			for &keycode in modifiers.modifier(index) {
+				if keycode == 0 {
+					continue;
+				}
+
+				for &keysym in keyboard.keysyms(keycode) {
+					let field = match keysym {
+						keysyms::NUM_LOCK => &mut masks.num_lock,
+						keysyms::SCROLL_LOCK => &mut masks.scroll_lock,
+
+						keysyms::ALT_L | keysyms::ALT_R => &mut masks.alt,
+						keysyms::META_L | keysyms::META_R => &mut masks.meta,
+						keysyms::SUPER_L | keysyms::SUPER_R => &mut masks.super_,
+						keysyms::HYPER_L | keysyms::HYPER_R => &mut masks.hyper,
+
+						keysyms::MODE_SWITCH => &mut masks.mode_switch,
+						keysyms::ISO_LEVEL3_SHIFT => &mut masks.level3_shift,
+
+						_ => continue,
+					};
+
+					if *field == 0 {
+						*field = mask;
+					}
+				}
+			}
+		}
+
+		masks
+	}
+
+	/// Returns the masks of the lock modifiers which should usually be ignored
+	/// when matching key and button combinations: `Lock` (caps lock),
+	/// NumLock, and ScrollLock.
+	pub const fn lock_masks(&self) -> u16 {
+		/// The mask of the `Lock` modifier.
+		const LOCK: u16 = 1 << 1;
+
+		LOCK | self.num_lock | self.scroll_lock
+	}
+}
+
+/// Returns whether `frame` is a `MappingNotify` event for a change which can
+/// change the [`ModifierMasks`]: one to the modifier or keyboard mapping.
+pub(crate) fn changes_modifier_masks(frame: &X11Frame) -> bool {
+	// The chunk starts after the event code, so the `request` is at index 3.
+	matches!(
+		frame,
+		X11Frame::Event {
+			code: MAPPING_NOTIFY,
+			chunk,
+		} if matches!(chunk[3], MAPPING_MODIFIER | MAPPING_KEYBOARD)
+	)
+}
+
+/// Gets the [`ModifierMasks`] of the X server's current modifier and keyboard
+/// mappings.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::modifier_masks`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn modifier_masks(connection: &mut impl RoundTrip) -> Result<ModifierMasks, ReplyError> {
+	let writer = connection.writer();
+
+	// Cleared before the mappings are fetched, so that a change made while
+	// they are being fetched resolves the masks again next time.
+	if writer.modifiers_changed.swap(false, Ordering::AcqRel) {
+		writer.modifier_masks = None;
+	}
+	if let Some(masks) = writer.modifier_masks {
+		return Ok(masks);
+	}
+
+	let (min_keycode, max_keycode) = (writer.setup().min_keycode, writer.setup().max_keycode);
+	let count = max_keycode.saturating_sub(min_keycode).saturating_add(1);
+
+	let modifiers = request::reply_to(connection, &ModifierMapping::request()).await?;
+	let keyboard = request::reply_to(connection, &KeyboardMapping::request(min_keycode, count))
+		.await?
+		.into_mapping(min_keycode);
+
+	let masks = ModifierMasks::resolve(&modifiers, &keyboard);
+	connection.writer().modifier_masks = Some(masks);
+
+	Ok(masks)
+}
+
+impl Client {
+	/// Gets the [`ModifierMasks`] of the X server's current modifier and
+	/// keyboard mappings.
+	///
+	/// The masks are remembered, so only the first call makes round trips to
+	/// the X server, until a `MappingNotify` event for the modifier or
+	/// keyboard mapping is received. They are then resolved again by the next
+	/// call.
+	pub async fn modifier_masks(&mut self) -> Result<ModifierMasks, ReplyError> {
+		modifier_masks(self).await
+	}
+}
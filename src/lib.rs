@@ -2,9 +2,196 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-#![feature(let_chains)]
-
+pub mod atoms;
+#[cfg(feature = "click")]
+pub mod click;
 mod client;
+#[cfg(feature = "composite")]
+pub mod composite;
+pub mod coordinates;
+#[cfg(feature = "cursor")]
+pub mod cursor;
+#[cfg(feature = "damage")]
+pub mod damage;
+#[cfg(feature = "dnd")]
+pub mod dnd;
+pub mod draw;
+#[cfg(all(unix, feature = "dri3"))]
+pub mod dri3;
+pub mod event;
+pub mod event_mask;
+#[cfg(feature = "ewmh")]
+pub mod ewmh;
+pub mod extension;
+#[cfg(feature = "filter")]
+pub mod filter;
+#[cfg(feature = "focus")]
+pub mod focus;
+pub mod gc;
+pub mod geometry;
+pub mod grab;
+#[cfg(feature = "icccm")]
+pub mod icccm;
+#[cfg(feature = "image")]
+pub mod image;
+#[cfg(feature = "input")]
+pub mod input;
+pub mod keyboard;
+#[cfg(feature = "monitor")]
+pub mod monitor;
+#[cfg(feature = "pointer")]
+pub mod pointer;
+#[cfg(feature = "pool")]
+pub mod pool;
+#[cfg(feature = "profiler")]
+pub mod profiler;
+pub mod property;
+#[cfg(feature = "randr")]
+pub mod randr;
+pub mod raw;
+#[cfg(feature = "redraw")]
+pub mod redraw;
+#[cfg(feature = "region")]
+pub mod region;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod request;
+#[cfg(feature = "screensaver")]
+pub mod screensaver;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "selection")]
+pub mod selection;
+pub mod server;
+#[cfg(feature = "shape")]
+pub mod shape;
+#[cfg(all(unix, feature = "shm"))]
+pub mod shm;
 pub(crate) mod stream;
+#[cfg(feature = "sync")]
+pub mod sync;
+pub mod time;
+pub mod window;
+pub mod x11;
+pub mod xauth;
+#[cfg(feature = "xc_misc")]
+pub mod xc_misc;
+#[cfg(feature = "xfixes")]
+pub mod xfixes;
+pub mod xid;
+#[cfg(feature = "xkb")]
+pub mod xkb;
+#[cfg(feature = "xwayland")]
+pub mod xwayland;
 
 pub use client::*;
+
+// Compile-time guarantees that public types can be moved and shared between
+// threads, so that X.RS can be used from multi-threaded async runtimes.
+const _: () = {
+	const fn assert_send_sync<T: Send + Sync>() {}
+
+	assert_send_sync::<Client>();
+	assert_send_sync::<EventReader>();
+	assert_send_sync::<RequestWriter>();
+	assert_send_sync::<ConnectError>();
+	assert_send_sync::<Display>();
+	assert_send_sync::<EventStream>();
+	#[cfg(feature = "filter")]
+	assert_send_sync::<filter::MatchingEvents<filter::OnWindow>>();
+	assert_send_sync::<ErrorStream>();
+	assert_send_sync::<Setup>();
+
+	assert_send_sync::<QueueSnapshot>();
+	assert_send_sync::<ReplyCookie>();
+	assert_send_sync::<ReplyError>();
+	assert_send_sync::<raw::RawRequest>();
+	assert_send_sync::<raw::RawFrameHook>();
+	assert_send_sync::<raw::X11Frame>();
+
+	assert_send_sync::<atoms::AtomNames>();
+	#[cfg(feature = "click")]
+	assert_send_sync::<click::ClickCounter>();
+	#[cfg(feature = "composite")]
+	assert_send_sync::<composite::RedirectMode>();
+	assert_send_sync::<coordinates::CoordinateCache>();
+	#[cfg(feature = "cursor")]
+	assert_send_sync::<cursor::CursorTheme>();
+	#[cfg(feature = "damage")]
+	assert_send_sync::<damage::DamageNotifyEvent>();
+	#[cfg(feature = "dnd")]
+	assert_send_sync::<dnd::DragSource>();
+	#[cfg(feature = "dnd")]
+	assert_send_sync::<dnd::DropTarget>();
+	assert_send_sync::<draw::Drawable>();
+	#[cfg(all(unix, feature = "dri3"))]
+	assert_send_sync::<dri3::Dri3Buffer>();
+	assert_send_sync::<event::Event>();
+	assert_send_sync::<event::GenericEventDecoders<()>>();
+	#[cfg(feature = "ewmh")]
+	assert_send_sync::<ewmh::UserTime>();
+	#[cfg(feature = "ewmh")]
+	assert_send_sync::<ewmh::Icon>();
+	#[cfg(feature = "ewmh")]
+	assert_send_sync::<ewmh::QuirkRegistry>();
+	#[cfg(feature = "ewmh")]
+	assert_send_sync::<ewmh::TitleStream>();
+	#[cfg(feature = "ewmh")]
+	assert_send_sync::<ewmh::ClientListStream>();
+	assert_send_sync::<extension::Degradation>();
+	#[cfg(feature = "focus")]
+	assert_send_sync::<focus::FocusTracker>();
+	assert_send_sync::<gc::Gc>();
+	assert_send_sync::<grab::ServerGrabGuard>();
+	#[cfg(feature = "icccm")]
+	assert_send_sync::<icccm::ClassHint>();
+	#[cfg(feature = "input")]
+	assert_send_sync::<input::InputAdapter>();
+	#[cfg(feature = "input")]
+	assert_send_sync::<input::HierarchyChange>();
+	#[cfg(feature = "input")]
+	assert_send_sync::<input::DeviceGrab>();
+	assert_send_sync::<keyboard::ModifierMapping>();
+	assert_send_sync::<keyboard::KeyboardMapping>();
+	#[cfg(feature = "monitor")]
+	assert_send_sync::<monitor::LogicalMonitor>();
+	#[cfg(feature = "pointer")]
+	assert_send_sync::<pointer::PointerConfinement>();
+	#[cfg(feature = "pointer")]
+	assert_send_sync::<pointer::RelativePointer>();
+	#[cfg(feature = "pool")]
+	assert_send_sync::<pool::ClientPool>();
+	#[cfg(feature = "profiler")]
+	assert_send_sync::<profiler::Profiler>();
+	assert_send_sync::<property::Property>();
+	#[cfg(feature = "randr")]
+	assert_send_sync::<randr::ScreenResources>();
+	#[cfg(feature = "redraw")]
+	assert_send_sync::<redraw::RedrawCoordinator>();
+	#[cfg(feature = "region")]
+	assert_send_sync::<region::Region>();
+	assert_send_sync::<request::Cookie<window::Geometry>>();
+	#[cfg(feature = "render")]
+	assert_send_sync::<render::PictFormats>();
+	#[cfg(feature = "screensaver")]
+	assert_send_sync::<screensaver::ScreensaverInhibitor>();
+	#[cfg(feature = "selection")]
+	assert_send_sync::<selection::SelectionOwner>();
+	assert_send_sync::<server::ServerInfo>();
+	#[cfg(all(unix, feature = "shm"))]
+	assert_send_sync::<shm::ShmImage>();
+	#[cfg(feature = "sync")]
+	assert_send_sync::<sync::SyncRequestCounter>();
+	assert_send_sync::<time::LatencyEstimator>();
+	assert_send_sync::<window::Window>();
+	assert_send_sync::<window::WindowOptions>();
+	assert_send_sync::<x11::Error>();
+	assert_send_sync::<x11::ErrorNames>();
+	assert_send_sync::<xauth::Xauthority>();
+	#[cfg(feature = "xkb")]
+	assert_send_sync::<xkb::Xkb>();
+	#[cfg(feature = "xkb")]
+	assert_send_sync::<xkb::LayoutStream>();
+	#[cfg(feature = "xwayland")]
+	assert_send_sync::<xwayland::KeyboardCapture>();
+};
@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Listing the monitors windows are placed on, whichever extension the X
+//! server provides them with.
+//!
+//! [`Client::monitors`] gets the logical monitors of the default screen from
+//! the first of these that the X server supports:
+//!
+//! - RandR 1.5's monitors, which have names and a primary monitor.
+//! - Xinerama's screens, which are unnamed. The first is treated as the primary
+//!   monitor, as it is by most window managers.
+//! - The whole screen, as a single monitor.
+//!
+//! Falling back because RandR 1.5, or both extensions, aren't supported is
+//! recorded as a [`Degradation`], reported by [`Client::degradations`].
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> Result<(), xrs::ReplyError> {
+//! for monitor in client.monitors().await? {
+//!     let name = monitor.name.as_deref().unwrap_or("unnamed");
+//!     let primary = if monitor.primary { " (primary)" } else { "" };
+//!
+//!     println!("{name}{primary}: {:?}", monitor.area);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+	atoms,
+	client::RoundTrip,
+	extension::{self, Degradation},
+	geometry::Rectangle,
+	randr,
+	raw::{assert_wire_size, X11Frame},
+	request::{self, Reply, Request},
+	window::Window,
+	Client,
+	ReplyError,
+};
+use bytes::BytesMut;
+
+/// The name of the Xinerama extension.
+const XINERAMA: &str = "XINERAMA";
+
+/// The minor opcode of the `XineramaQueryScreens` request.
+const XINERAMA_QUERY_SCREENS: u8 = 5;
+
+/// The size of a Xinerama `ScreenInfo` in bytes.
+const SCREEN_INFO: usize = 8;
+
+/// The version of RandR which added monitors.
+const RANDR_MONITORS_VERSION: (u32, u32) = (1, 5);
+
+/// Monitors being listed from Xinerama, as RandR 1.5 isn't supported.
+const FROM_XINERAMA: Degradation = Degradation {
+	feature: "monitors",
+	preferred: "RandR 1.5",
+	fallback: "Xinerama",
+};
+
+/// The whole screen being used as the only monitor, as neither RandR 1.5
+/// nor Xinerama is supported.
+const FROM_SCREEN: Degradation = Degradation {
+	feature: "monitors",
+	preferred: "RandR 1.5",
+	fallback: "the whole screen",
+};
+
+/// Where a [`LogicalMonitor`] was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MonitorSource {
+	/// A RandR 1.5 monitor.
+	Randr,
+	/// A Xinerama screen.
+	Xinerama,
+	/// The whole screen, used when neither RandR 1.5 nor Xinerama is
+	/// available.
+	Screen,
+}
+
+/// A logical monitor: an area of the screen which windows are placed on.
+///
+/// See [`Client::monitors`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LogicalMonitor {
+	/// The monitor's name, such as `HDMI-1`, if it has one.
+	///
+	/// Only RandR monitors are named.
+	pub name: Option<String>,
+	/// Whether this is the primary monitor.
+	pub primary: bool,
+
+	pub area: Rectangle,
+	/// The physical width of the monitor, in millimetres, or 0 if it is
+	/// unknown.
+	pub mm_width: u32,
+	/// The physical height of the monitor, in millimetres, or 0 if it is
+	/// unknown.
+	pub mm_height: u32,
+
+	pub source: MonitorSource,
+}
+
+/// Returns whether the X server supports RandR 1.5's monitors.
+async fn has_randr_monitors(connection: &mut impl RoundTrip) -> Result<bool, ReplyError> {
+	if extension::query_extension(connection, randr::RANDR)
+		.await?
+		.is_none()
+	{
+		return Ok(false);
+	}
+
+	Ok(randr::randr_version(connection).await? >= RANDR_MONITORS_VERSION)
+}
+
+/// Gets the active RandR monitors of `root`'s screen.
+async fn randr_monitors(
+	connection: &mut impl RoundTrip, root: Window,
+) -> Result<Vec<LogicalMonitor>, ReplyError> {
+	let mut monitors = Vec::new();
+
+	for monitor in randr::get_monitors(connection, root, true).await? {
+		monitors.push(LogicalMonitor {
+			name: Some(atoms::get_atom_name(connection, monitor.name).await?),
+			primary: monitor.primary,
+
+			area: monitor.area,
+			mm_width: monitor.mm_width,
+			mm_height: monitor.mm_height,
+
+			source: MonitorSource::Randr,
+		});
+	}
+
+	Ok(monitors)
+}
+
+/// The Xinerama extension's `XineramaQueryScreens` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XineramaQueryScreens;
+
+impl Request for XineramaQueryScreens {
+	type Reply = XineramaScreens;
+
+	const NAME: &'static str = "XineramaQueryScreens";
+	const EXTENSION: Option<&'static str> = Some(XINERAMA);
+	const OPCODE: u8 = XINERAMA_QUERY_SCREENS;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(XineramaQueryScreens: [u8, u8, u16]);
+
+/// The areas of the Xinerama screens, which are empty if Xinerama isn't
+/// active.
+///
+/// This is the reply to an `XineramaQueryScreens` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XineramaScreens(pub Vec<Rectangle>);
+
+assert_wire_size!("XineramaQueryScreens reply": [u8, u8, u16, u32, u32, [u8; 20]] == 32);
+
+impl Reply for XineramaScreens {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[n0, n1, n2, n3] = chunk.get(..4)? else {
+			return None;
+		};
+		let count = u32::from_be_bytes([n0, n1, n2, n3]) as usize;
+
+		let screens = chunk.get(24..)?.get(..count.checked_mul(SCREEN_INFO)?)?;
+
+		Some(Self(
+			screens
+				.chunks_exact(SCREEN_INFO)
+				.map(|screen| Rectangle {
+					x: i16::from_be_bytes([screen[0], screen[1]]),
+					y: i16::from_be_bytes([screen[2], screen[3]]),
+					width: u16::from_be_bytes([screen[4], screen[5]]),
+					height: u16::from_be_bytes([screen[6], screen[7]]),
+				})
+				.collect(),
+		))
+	}
+}
+
+/// Gets the Xinerama screens, which are empty if Xinerama isn't active.
+async fn xinerama_monitors(
+	connection: &mut impl RoundTrip,
+) -> Result<Vec<LogicalMonitor>, ReplyError> {
+	let XineramaScreens(screens) = request::reply_to(connection, &XineramaQueryScreens).await?;
+
+	Ok(screens
+		.into_iter()
+		.enumerate()
+		.map(|(i, area)| LogicalMonitor {
+			name: None,
+			primary: i == 0,
+
+			area,
+			mm_width: 0,
+			mm_height: 0,
+
+			source: MonitorSource::Xinerama,
+		})
+		.collect())
+}
+
+/// Gets the logical monitors of the [default screen].
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::monitors`].
+///
+/// [default screen]: crate::RequestWriter::default_screen
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn monitors(connection: &mut impl RoundTrip) -> Result<Vec<LogicalMonitor>, ReplyError> {
+	let root = Window(connection.writer().default_screen().root);
+
+	let has_randr = has_randr_monitors(connection).await?;
+
+	if has_randr {
+		let monitors = randr_monitors(connection, root).await?;
+
+		if !monitors.is_empty() {
+			return Ok(monitors);
+		}
+	}
+
+	let has_xinerama = extension::query_extension(connection, XINERAMA)
+		.await?
+		.is_some();
+
+	if has_xinerama {
+		let monitors = xinerama_monitors(connection).await?;
+
+		if !monitors.is_empty() {
+			if !has_randr {
+				connection.writer().degrade(FROM_XINERAMA);
+			}
+
+			return Ok(monitors);
+		}
+	}
+
+	if !has_randr && !has_xinerama {
+		connection.writer().degrade(FROM_SCREEN);
+	}
+
+	let screen = connection.writer().default_screen();
+
+	Ok(vec![LogicalMonitor {
+		name: None,
+		primary: true,
+
+		area: Rectangle {
+			x: 0,
+			y: 0,
+			width: screen.width,
+			height: screen.height,
+		},
+		mm_width: u32::from(screen.width_mm),
+		mm_height: u32::from(screen.height_mm),
+
+		source: MonitorSource::Screen,
+	}])
+}
+
+impl Client {
+	/// Gets the logical monitors of the [default screen], from RandR 1.5 if
+	/// the X server supports it, or else from Xinerama.
+	///
+	/// If neither is available, or neither reports any monitors, the whole
+	/// screen is returned as the only monitor. See the [module docs] for
+	/// details.
+	///
+	/// [default screen]: Client::default_screen
+	/// [module docs]: crate::monitor
+	pub async fn monitors(&mut self) -> Result<Vec<LogicalMonitor>, ReplyError> {
+		monitors(self).await
+	}
+}
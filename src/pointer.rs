@@ -0,0 +1,859 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Confining the pointer to a window or an area of the screen, and reading
+//! its relative motion.
+//!
+//! Games and remote desktop viewers often need to keep the pointer within
+//! their window. [`Client::confine_pointer_to`] does this with a pointer grab
+//! whose `confine_to` window is the target window, which is the core
+//! protocol's way of confining the pointer. If the grab fails, such as because
+//! another client has grabbed the pointer, or if the target is an area
+//! rather than a window, the XFIXES extension's pointer barriers are placed
+//! around the area instead.
+//!
+//! The pointer stays confined until the returned [`PointerConfinement`] is
+//! [released]. The X server also releases it if the client disconnects, and
+//! releases a grab if the window becomes unviewable.
+//!
+//! Games and emulators which use the pointer for mouse look instead want
+//! how far it moved, without it ever stopping at the edge of the screen.
+//! [`Client::relative_pointer`] hides the cursor and reports the pointer's
+//! [`RelativeMotion`], using the XInput 2 extension's raw motion events if
+//! they are supported, or by warping the pointer back to the center of the
+//! window after each motion event otherwise.
+//!
+//! ```no_run
+//! # use xrs::{window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut pointer = client.relative_pointer(window).await?;
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     let writer = client.split_mut().1;
+//!
+//!     if let Some(motion) = pointer.observe(writer, &frame).await? {
+//!         println!("moved by {}, {}", motion.dx, motion.dy);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [released]: PointerConfinement::release
+
+use crate::{
+	client::RoundTrip,
+	coordinates,
+	event::Event,
+	extension,
+	geometry::{Point, Rectangle, Size},
+	grab::{GrabPointer, GrabStatus, PointerGrab},
+	raw::{assert_wire_size, RawRequest, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	server,
+	time::Timestamp,
+	window::{GetGeometry, Window},
+	xfixes::{require_xfixes, XFIXES},
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::io;
+
+/// The major opcode of the `WarpPointer` request.
+const WARP_POINTER: u8 = 41;
+
+/// The minor opcode of the `XFixesHideCursor` request.
+const HIDE_CURSOR: u8 = 29;
+/// The minor opcode of the `XFixesShowCursor` request.
+const SHOW_CURSOR: u8 = 30;
+/// The minor opcode of the `XFixesCreatePointerBarrier` request.
+const CREATE_POINTER_BARRIER: u8 = 31;
+/// The minor opcode of the `XFixesDestroyPointerBarrier` request.
+const DESTROY_POINTER_BARRIER: u8 = 32;
+
+/// The version of XFIXES which added hiding the cursor.
+const HIDE_CURSOR_VERSION: (u32, u32) = (4, 0);
+/// The version of XFIXES which added pointer barriers.
+const BARRIERS_VERSION: (u32, u32) = (5, 0);
+
+/// The name of the XInput extension.
+pub(crate) const XINPUT: &str = "XInputExtension";
+
+/// The minor opcode of the `XISelectEvents` request.
+pub(crate) const XI_SELECT_EVENTS: u8 = 46;
+/// The minor opcode of the `XIQueryVersion` request.
+const XI_QUERY_VERSION: u8 = 47;
+
+/// The version of XInput requested by this client: the highest used by this
+/// crate.
+const XI_VERSION: (u16, u16) = (2, 2);
+/// The version of XInput which added raw events.
+const XI_RAW_EVENTS_VERSION: (u32, u32) = (2, 0);
+/// The event type of XInput `RawMotion` events.
+const XI_RAW_MOTION: u16 = 17;
+/// The `XIAllMasterDevices` device ID.
+pub(crate) const XI_ALL_MASTER_DEVICES: u16 = 1;
+
+/// The directions in which a pointer barrier lets the pointer through.
+mod barrier_directions {
+	pub const POSITIVE_X: u32 = 1 << 0;
+	pub const POSITIVE_Y: u32 = 1 << 1;
+	pub const NEGATIVE_X: u32 = 1 << 2;
+	pub const NEGATIVE_Y: u32 = 1 << 3;
+}
+
+/// What to confine the pointer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfineTarget {
+	/// A window, which must be viewable.
+	Window(Window),
+	/// An area of the default screen, relative to its root window.
+	Area(Rectangle),
+}
+
+impl From<Window> for ConfineTarget {
+	fn from(window: Window) -> Self {
+		Self::Window(window)
+	}
+}
+
+impl From<Rectangle> for ConfineTarget {
+	fn from(area: Rectangle) -> Self {
+		Self::Area(area)
+	}
+}
+
+/// How the pointer is confined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Confinement {
+	/// By a pointer grab.
+	Grab,
+	/// By the given pointer barriers.
+	Barriers(Vec<u32>),
+}
+
+/// Keeps the pointer confined until it is [released].
+///
+/// See [`Client::confine_pointer_to`].
+///
+/// [released]: PointerConfinement::release
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the pointer stays confined until the confinement is released"]
+pub struct PointerConfinement {
+	confinement: Confinement,
+}
+
+impl PointerConfinement {
+	/// Returns whether the pointer is confined by a pointer grab, rather than
+	/// by pointer barriers.
+	pub const fn is_grab(&self) -> bool {
+		matches!(self.confinement, Confinement::Grab)
+	}
+
+	/// Releases the pointer.
+	///
+	/// Releasing a grab which the X server has already released, such as
+	/// because the window was unmapped, does nothing.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		match self.confinement {
+			Confinement::Grab => {
+				writer.ungrab_pointer(Timestamp::CURRENT_TIME).await?;
+			},
+
+			Confinement::Barriers(barriers) => {
+				// XFIXES was queried when the barriers were created, so its
+				// information is cached.
+				for barrier in barriers {
+					request::queue(writer, &DestroyPointerBarrier(barrier))
+						.await
+						.map_err(into_io_error)?;
+				}
+
+				writer.flush().await?;
+			},
+		}
+
+		Ok(())
+	}
+}
+
+/// How far the pointer moved, as reported by a [`RelativePointer`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RelativeMotion {
+	pub dx: f64,
+	pub dy: f64,
+}
+
+/// How a [`RelativePointer`] reads the pointer's motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MotionSource {
+	/// From XInput 2 `RawMotion` events, with the given XInput major opcode.
+	Raw { xinput: u8 },
+	/// From `MotionNotify` events, warping the pointer back to `center` of
+	/// the window after each one.
+	Warp { center: Point },
+}
+
+/// Hides the cursor and reports the pointer's relative motion until it is
+/// [released].
+///
+/// See [`Client::relative_pointer`].
+///
+/// [released]: RelativePointer::release
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the cursor stays hidden until the relative pointer is released"]
+pub struct RelativePointer {
+	window: Window,
+	root: Window,
+
+	/// The major opcode of XFIXES, which hid the cursor.
+	xfixes: u8,
+	source: MotionSource,
+}
+
+impl RelativePointer {
+	/// Returns whether motion is read from XInput 2 raw motion events, rather
+	/// than by warping the pointer.
+	///
+	/// Raw motion is unaccelerated and isn't stopped by the edges of the
+	/// screen.
+	pub const fn is_raw(&self) -> bool {
+		matches!(self.source, MotionSource::Raw { .. })
+	}
+
+	/// Returns the window the relative pointer was started for.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// Reads the pointer's relative motion from `frame`, if it is a motion
+	/// event.
+	///
+	/// Without XInput 2, `frame` must be a `MotionNotify` event on the window,
+	/// which must select [`POINTER_MOTION`]. The pointer is then warped back
+	/// to the center of the window, and the motion event caused by the warp is
+	/// ignored.
+	///
+	/// [`POINTER_MOTION`]: crate::event_mask::EventMask::POINTER_MOTION
+	pub async fn observe(
+		&mut self, writer: &mut RequestWriter, frame: &X11Frame,
+	) -> io::Result<Option<RelativeMotion>> {
+		match self.source {
+			MotionSource::Raw { xinput } => Ok(raw_motion(xinput, frame)),
+
+			MotionSource::Warp { center } => {
+				let Some(Event::MotionNotify(motion)) = Event::from_frame(frame) else {
+					return Ok(None);
+				};
+
+				if motion.event != self.window
+					|| !motion.same_screen
+					|| motion.event_position == center
+				{
+					return Ok(None);
+				}
+
+				warp_pointer(writer, self.window, center).await?;
+
+				Ok(Some(RelativeMotion {
+					dx: f64::from(motion.event_position.x) - f64::from(center.x),
+					dy: f64::from(motion.event_position.y) - f64::from(center.y),
+				}))
+			},
+		}
+	}
+
+	/// Shows the cursor again and stops selecting raw motion events.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		if let MotionSource::Raw { xinput } = self.source {
+			writer
+				.send_queued(select_raw_motion(xinput, self.root, false))
+				.await?;
+		}
+
+		writer
+			.send_raw(ShowCursor(self.window).to_raw(self.xfixes))
+			.await
+			.map(drop)
+	}
+}
+
+/// Reads the relative motion of the first two axes from `frame`, if it is an
+/// XInput 2 `RawMotion` event.
+fn raw_motion(xinput: u8, frame: &X11Frame) -> Option<RelativeMotion> {
+	/// The offset of the valuator mask within the event's chunk, which starts
+	/// at byte 10 of the event.
+	const MASK: usize = 22;
+	/// The size of an `FP3232` value.
+	const FP3232: usize = 8;
+
+	let X11Frame::GenericEvent {
+		extension,
+		event_type,
+		chunk,
+		..
+	} = frame
+	else {
+		return None;
+	};
+
+	if *extension != xinput || *event_type != XI_RAW_MOTION {
+		return None;
+	}
+
+	let mask_len = usize::from(u16::from_be_bytes(chunk.get(12..14)?.try_into().ok()?));
+	let mask = chunk.get(MASK..MASK + (mask_len * 4))?;
+
+	let is_set = |axis: usize| {
+		mask.get(axis / 32 * 4..axis / 32 * 4 + 4)
+			.is_some_and(|word| {
+				u32::from_be_bytes([word[0], word[1], word[2], word[3]]) & (1 << (axis % 32)) != 0
+			})
+	};
+	let count = mask
+		.iter()
+		.map(|byte| byte.count_ones() as usize)
+		.sum::<usize>();
+
+	// Each set axis has an accelerated value followed, after all of those,
+	// by its raw value.
+	let raw_values = MASK + mask.len() + (count * FP3232);
+	let raw_value = |index: usize| {
+		let start = raw_values + (index * FP3232);
+		let value = chunk.get(start..start + FP3232)?;
+
+		let integral = i32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+		let fraction = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+
+		Some(f64::from(integral) + (f64::from(fraction) / 4_294_967_296.0))
+	};
+
+	let mut motion = RelativeMotion::default();
+	let mut index = 0;
+
+	if is_set(0) {
+		motion.dx = raw_value(index)?;
+		index += 1;
+	}
+	if is_set(1) {
+		motion.dy = raw_value(index)?;
+	}
+
+	Some(motion)
+}
+
+/// Creates an `XISelectEvents` request which selects or deselects `RawMotion`
+/// events from every master device on `root`.
+fn select_raw_motion(xinput: u8, root: Window, select: bool) -> RawRequest {
+	let request = XISelectEvents {
+		window: root,
+		device: XI_ALL_MASTER_DEVICES,
+		mask: if select { 1 << XI_RAW_MOTION } else { 0 },
+	};
+
+	request.to_raw(xinput)
+}
+
+/// Warps the pointer to `position` relative to `window`.
+async fn warp_pointer(
+	writer: &mut RequestWriter, window: Window, position: Point,
+) -> io::Result<()> {
+	request::send_core(writer, &WarpPointer::to(window, position)).await
+}
+
+/// The `WarpPointer` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WarpPointer {
+	/// If given, the pointer is only moved if it is within `source_area` of
+	/// this window.
+	pub source: Option<Window>,
+	/// The window `position` is relative to, or `None` to move the pointer
+	/// by `position` instead.
+	pub destination: Option<Window>,
+	/// The area of `source` the pointer must be in, where a width or height
+	/// of `0` extends to the edge of the window.
+	pub source_area: Rectangle,
+	pub position: Point,
+}
+
+impl WarpPointer {
+	/// Returns a `WarpPointer` request which moves the pointer to `position`
+	/// relative to `window`, wherever it is.
+	pub const fn to(window: Window, position: Point) -> Self {
+		Self {
+			source: None,
+			destination: Some(window),
+			source_area: Rectangle::new(Point::ORIGIN, Size::new(0, 0)),
+			position,
+		}
+	}
+}
+
+impl Request for WarpPointer {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "WarpPointer";
+	const OPCODE: u8 = WARP_POINTER;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.source.map_or(0, Window::id));
+		body.put_u32(self.destination.map_or(0, Window::id));
+		self.source_area.write_to(body);
+		self.position.write_to(body);
+	}
+}
+
+assert_wire_size!(WarpPointer: [u8, [u8; 1], u16, Window, Window, Rectangle, Point]);
+
+/// The XInput extension's `XIQueryVersion` request, which tells the X server
+/// which version of XInput the client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIQueryVersion {
+	pub major_version: u16,
+	pub minor_version: u16,
+}
+
+impl Request for XIQueryVersion {
+	type Reply = XIVersion;
+
+	const NAME: &'static str = "XIQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.major_version);
+		body.put_u16(self.minor_version);
+	}
+}
+
+assert_wire_size!(XIQueryVersion: [u8, u8, u16, u16, u16]);
+
+/// The version of XInput supported by both the X server and the client.
+///
+/// This is the reply to an `XIQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XIVersion {
+	pub major_version: u16,
+	pub minor_version: u16,
+}
+
+assert_wire_size!("XIQueryVersion reply": [u8, u8, u16, u32, u16, u16, [u8; 20]] == 32);
+
+impl Reply for XIVersion {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[a0, a1, b0, b1] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u16::from_be_bytes([a0, a1]),
+			minor_version: u16::from_be_bytes([b0, b1]),
+		})
+	}
+}
+
+/// The XInput extension's `XISelectEvents` request, with one event mask for
+/// one device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XISelectEvents {
+	pub window: Window,
+	/// The device ID, or `XIAllDevices` (`0`) or `XIAllMasterDevices` (`1`).
+	pub device: u16,
+	/// The XInput 2 events to select, with bit `n` selecting event type `n`.
+	pub mask: u32,
+}
+
+impl Request for XISelectEvents {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XISelectEvents";
+	const EXTENSION: Option<&'static str> = Some(XINPUT);
+	const OPCODE: u8 = XI_SELECT_EVENTS;
+	const LENGTH: Option<u16> = Some(5);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		// num-masks
+		body.put_u16(1);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+
+		body.put_u16(self.device);
+		// mask-len
+		body.put_u16(1);
+		body.put_u32(self.mask);
+	}
+}
+
+assert_wire_size!(XISelectEvents: [u8, u8, u16, Window, u16, [u8; 2], u16, u16, u32]);
+
+/// Defines XFIXES requests whose body is just a window.
+macro_rules! xfixes_window_requests {
+	($($(#[$attr:meta])* $name:ident = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name(pub Window);
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = concat!("XFixes", stringify!($name));
+				const EXTENSION: Option<&'static str> = Some(XFIXES);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(2);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.0.id());
+				}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16, Window]);
+		)*
+	};
+}
+
+xfixes_window_requests! {
+	/// The XFIXES extension's `XFixesHideCursor` request, which hides the
+	/// cursor while it is over a window.
+	HideCursor = HIDE_CURSOR;
+	/// The XFIXES extension's `XFixesShowCursor` request, which undoes a
+	/// `HideCursor` request.
+	ShowCursor = SHOW_CURSOR;
+}
+
+/// The XFIXES extension's `XFixesDestroyPointerBarrier` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DestroyPointerBarrier(pub u32);
+
+impl Request for DestroyPointerBarrier {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XFixesDestroyPointerBarrier";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = DESTROY_POINTER_BARRIER;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(DestroyPointerBarrier: [u8, u8, u16, u32]);
+
+/// The XFIXES extension's `XFixesCreatePointerBarrier` request, for a
+/// barrier which applies to every device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreatePointerBarrier {
+	/// The ID of the new barrier.
+	pub barrier: u32,
+	/// A window on the screen the barrier is placed on.
+	pub window: Window,
+	/// The barrier's end points, as `[x1, y1, x2, y2]`; it must be
+	/// horizontal or vertical.
+	pub line: [i16; 4],
+	/// The directions in which the pointer may pass through the barrier.
+	pub directions: u32,
+}
+
+impl Request for CreatePointerBarrier {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XFixesCreatePointerBarrier";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = CREATE_POINTER_BARRIER;
+	const LENGTH: Option<u16> = Some(7);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.barrier);
+		body.put_u32(self.window.id());
+		for coordinate in self.line {
+			body.put_i16(coordinate);
+		}
+		body.put_u32(self.directions);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		// num-devices: barriers with no devices apply to every device.
+		body.put_u16(0);
+	}
+}
+
+assert_wire_size!(CreatePointerBarrier: [u8, u8, u16, u32, Window, [i16; 4], u32, [u8; 2], u16]);
+
+/// Tells the X server which version of XInput this client uses, if it hasn't
+/// been told yet, returning the version supported by both.
+async fn negotiate_xinput(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	let request = XIQueryVersion {
+		major_version: XI_VERSION.0,
+		minor_version: XI_VERSION.1,
+	};
+
+	extension::negotiate_version(connection, XINPUT, &request, |version| {
+		(version.major_version.into(), version.minor_version.into())
+	})
+	.await
+}
+
+/// Returns the XInput major opcode if the X server supports XInput 2, telling
+/// it that this client uses XInput 2.
+pub(crate) async fn query_xinput2(
+	connection: &mut impl RoundTrip,
+) -> Result<Option<u8>, ReplyError> {
+	let Some(xinput) = extension::query_extension(connection, XINPUT).await? else {
+		return Ok(None);
+	};
+	let version = negotiate_xinput(connection).await?;
+
+	Ok((version >= XI_RAW_EVENTS_VERSION).then_some(xinput.major_opcode))
+}
+
+/// Tells the X server that this client uses XInput 2, returning an error if
+/// it doesn't support XInput, or at least `version` of it.
+///
+/// XInput 2 requests other than `XIQueryVersion` are rejected until the
+/// X server has been told.
+#[cfg_attr(not(feature = "input"), allow(dead_code))]
+pub(crate) async fn require_xinput2(
+	connection: &mut impl RoundTrip, version: (u32, u32),
+) -> Result<(), ReplyError> {
+	extension::require_extension(connection, XINPUT).await?;
+
+	let supported = negotiate_xinput(connection).await?;
+	extension::require_version(XINPUT, supported, version)
+}
+
+/// Hides the cursor and starts reporting the pointer's relative motion for
+/// `window`, until the returned [`RelativePointer`] is released.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::relative_pointer`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn relative_pointer(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<RelativePointer, ReplyError> {
+	let xfixes = require_xfixes(connection, HIDE_CURSOR_VERSION).await?;
+	let (root, area) = root_area(connection, window).await?;
+
+	let source = match query_xinput2(connection).await? {
+		Some(xinput) => {
+			connection
+				.writer()
+				.send_queued(select_raw_motion(xinput, root, true))
+				.await
+				.map_err(ReplyError::Io)?;
+
+			MotionSource::Raw { xinput }
+		},
+
+		// Warping the pointer back would stop working as soon as it left the
+		// window.
+		None if server::quirks(connection)
+			.await?
+			.warps_only_over_own_windows =>
+		{
+			return Err(ReplyError::Io(io::Error::new(
+				io::ErrorKind::Unsupported,
+				"the X server supports neither XInput 2 nor warping the pointer outside the \
+				 client's windows",
+			)));
+		},
+
+		None => {
+			let center = Point::new(
+				(area.width / 2).try_into().unwrap_or(i16::MAX),
+				(area.height / 2).try_into().unwrap_or(i16::MAX),
+			);
+
+			warp_pointer(connection.writer(), window, center)
+				.await
+				.map_err(ReplyError::Io)?;
+
+			MotionSource::Warp { center }
+		},
+	};
+
+	request::send(connection, &HideCursor(window)).await?;
+
+	Ok(RelativePointer {
+		window,
+		root,
+
+		xfixes,
+		source,
+	})
+}
+
+/// Converts a `ReplyError` from a cached extension query into an I/O error.
+fn into_io_error(error: ReplyError) -> io::Error {
+	match error {
+		ReplyError::Io(error) => error,
+		error => io::Error::other(error),
+	}
+}
+
+/// Grabs the pointer, confining it to `window`.
+async fn grab_pointer(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<GrabStatus, ReplyError> {
+	let grab = PointerGrab {
+		// With owner-events, the client's windows still receive the events
+		// they selected.
+		owner_events: true,
+		confine_to: Some(window),
+		..PointerGrab::default()
+	};
+
+	request::reply_to(connection, &GrabPointer { window, grab }).await
+}
+
+/// Returns `window`'s root window and the area it covers, inside its border,
+/// relative to that root window.
+async fn root_area(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<(Window, Rectangle), ReplyError> {
+	let geometry = request::reply_to(
+		connection,
+		&GetGeometry {
+			drawable: window.id(),
+		},
+	)
+	.await?;
+	let root = geometry.root;
+
+	let position = coordinates::translate_coordinates(connection, window, root, Point::ORIGIN)
+		.await?
+		.ok_or_else(|| ReplyError::malformed("TranslateCoordinates"))?;
+
+	Ok((root, Rectangle::new(position, geometry.area.size())))
+}
+
+/// Places pointer barriers around `area` of `root`, returning their IDs.
+async fn create_barriers(
+	connection: &mut impl RoundTrip, root: Window, area: Rectangle,
+) -> Result<Vec<u32>, XidError> {
+	use barrier_directions::*;
+
+	let xfixes = require_xfixes(connection, BARRIERS_VERSION).await?;
+
+	let (left, top) = (area.x, area.y);
+	let right = area.x.saturating_add_unsigned(area.width);
+	let bottom = area.y.saturating_add_unsigned(area.height);
+
+	// Each barrier lets the pointer move into the area, but not out of it.
+	let edges = [
+		([left, top, left, bottom], POSITIVE_X),
+		([right, top, right, bottom], NEGATIVE_X),
+		([left, top, right, top], POSITIVE_Y),
+		([left, bottom, right, bottom], NEGATIVE_Y),
+	];
+
+	let mut barriers = Vec::with_capacity(edges.len());
+
+	for (line, directions) in edges {
+		let barrier = xid::generate_id(connection).await?;
+
+		let request = CreatePointerBarrier {
+			barrier,
+			window: root,
+			line,
+			directions,
+		};
+
+		connection
+			.writer()
+			.send_queued(request.to_raw(xfixes))
+			.await
+			.map_err(ReplyError::Io)?;
+
+		barriers.push(barrier);
+	}
+
+	connection.writer().flush().await.map_err(ReplyError::Io)?;
+
+	Ok(barriers)
+}
+
+/// Confines the pointer to `target`, a [`Window`] or a [`Rectangle`] of
+/// the screen, until the returned [`PointerConfinement`] is released.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::confine_pointer_to`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn confine_pointer_to(
+	connection: &mut impl RoundTrip, target: impl Into<ConfineTarget>,
+) -> Result<PointerConfinement, XidError> {
+	let (root, area) = match target.into() {
+		ConfineTarget::Window(window) => {
+			if grab_pointer(connection, window).await? == GrabStatus::Success {
+				return Ok(PointerConfinement {
+					confinement: Confinement::Grab,
+				});
+			}
+
+			root_area(connection, window).await?
+		},
+
+		ConfineTarget::Area(area) => (connection.writer().root_window(), area),
+	};
+
+	Ok(PointerConfinement {
+		confinement: Confinement::Barriers(create_barriers(connection, root, area).await?),
+	})
+}
+
+impl Client {
+	/// Confines the pointer to `target`, a [`Window`] or a [`Rectangle`] of
+	/// the screen, until the returned [`PointerConfinement`] is released.
+	///
+	/// Windows are confined to with a pointer grab. If the grab fails, or for
+	/// areas, XFIXES pointer barriers are placed around the area instead,
+	/// which only stop the pointer leaving once it is inside. If barriers are
+	/// needed, returns an [`Unsupported`] error if the X server doesn't
+	/// support XFIXES, or an [`UnsupportedVersion`] error if it doesn't
+	/// support XFIXES 5.0.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+	pub async fn confine_pointer_to(
+		&mut self, target: impl Into<ConfineTarget>,
+	) -> Result<PointerConfinement, XidError> {
+		confine_pointer_to(self, target).await
+	}
+
+	/// Hides the cursor and starts reporting the pointer's relative motion for
+	/// `window`, until the returned [`RelativePointer`] is released.
+	///
+	/// Motion is read from XInput 2 raw motion events if the X server
+	/// supports them. Otherwise, the pointer is warped to the center of
+	/// `window`, which must be viewable and select [`POINTER_MOTION`], and
+	/// back there after each motion. Returns an [`Unsupported`] error if the X
+	/// server doesn't support XFIXES, or if it needs to warp the pointer but
+	/// only does so over the client's own windows (see
+	/// [`ServerQuirks::warps_only_over_own_windows`]). Returns an
+	/// [`UnsupportedVersion`] error if it doesn't support XFIXES 4.0, which
+	/// hides the cursor.
+	///
+	/// Frames must be passed to [`RelativePointer::observe`] to read the
+	/// motion.
+	///
+	/// [`POINTER_MOTION`]: crate::event_mask::EventMask::POINTER_MOTION
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+	/// [`ServerQuirks::warps_only_over_own_windows`]: server::ServerQuirks::warps_only_over_own_windows
+	pub async fn relative_pointer(
+		&mut self, window: Window,
+	) -> Result<RelativePointer, ReplyError> {
+		relative_pointer(self, window).await
+	}
+}
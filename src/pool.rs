@@ -0,0 +1,196 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A pool of connections to many X servers.
+//!
+//! Tools which control many displays at once, such as multi-seat managers
+//! or test orchestrators driving dozens of Xephyr instances, can keep a
+//! [`ClientPool`] rather than tracking each [`Client`] themselves:
+//!
+//! ```no_run
+//! # use xrs::pool::ClientPool;
+//! # use std::time::Duration;
+//! # async fn example() -> Result<(), xrs::ConnectError> {
+//! let mut pool = ClientPool::new().with_idle_timeout(Duration::from_secs(60));
+//!
+//! for display in [":1", ":2", ":3"] {
+//!     let client = pool.get(display).await?;
+//!     let _root = client.root_window();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Connections are established the first time their display is requested,
+//! and closed once they have been unused for longer than the pool's idle
+//! timeout. [`ClientPool::check_health`] closes connections which no longer
+//! respond, so that the next request for their display reconnects.
+
+use crate::{Client, ConnectError, Display, DisplayName};
+use std::{
+	collections::HashMap,
+	time::{Duration, Instant},
+};
+
+/// The default time a connection can be unused before it is closed: 5
+/// minutes.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// A pool of [`Client`]s, keyed by display name, which are connected lazily.
+///
+/// See the [module documentation](self).
+pub struct ClientPool {
+	clients: HashMap<String, PooledClient>,
+
+	/// How long a connection can be unused before it is closed, if at all.
+	idle_timeout: Option<Duration>,
+}
+
+/// A [`Client`] in a [`ClientPool`].
+struct PooledClient {
+	client: Client,
+	/// When the client was last taken from the pool.
+	last_used: Instant,
+}
+
+impl Default for ClientPool {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl ClientPool {
+	/// Creates an empty pool which closes connections after 5 minutes of not
+	/// being used.
+	pub fn new() -> Self {
+		Self {
+			clients: HashMap::new(),
+			idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+		}
+	}
+
+	/// Sets how long connections can be unused before they are closed.
+	pub const fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+		self.idle_timeout = Some(idle_timeout);
+		self
+	}
+
+	/// Keeps connections open however long they are unused.
+	pub const fn without_idle_timeout(mut self) -> Self {
+		self.idle_timeout = None;
+		self
+	}
+
+	/// Returns how long connections can be unused before they are closed, or
+	/// `None` if they are never closed for being unused.
+	pub const fn idle_timeout(&self) -> Option<Duration> {
+		self.idle_timeout
+	}
+
+	/// Returns the number of open connections in the pool.
+	pub fn len(&self) -> usize {
+		self.clients.len()
+	}
+
+	/// Returns whether there are no open connections in the pool.
+	pub fn is_empty(&self) -> bool {
+		self.clients.is_empty()
+	}
+
+	/// Returns the display names of the open connections in the pool.
+	pub fn displays(&self) -> impl Iterator<Item = &str> {
+		self.clients.keys().map(String::as_str)
+	}
+
+	/// Returns whether there is an open connection to `display` in the pool.
+	pub fn contains(&self, display: &str) -> bool {
+		self.clients.contains_key(display)
+	}
+
+	/// Returns the connection to `display`, such as `:1` or `host:0.1`,
+	/// connecting to it if it isn't connected already.
+	///
+	/// Idle connections are closed first. New connections use authorization
+	/// from the user's Xauthority file, as with [`Client::connect`].
+	pub async fn get(&mut self, display: &str) -> Result<&mut Client, ConnectError> {
+		self.evict_idle();
+
+		if !self.clients.contains_key(display) {
+			let name = DisplayName::parse(display).map_err(ConnectError::Parse)?;
+			let client = Client::connect(Display::Specific(name), None).await?;
+
+			self.clients.insert(
+				display.to_owned(),
+				PooledClient {
+					client,
+					last_used: Instant::now(),
+				},
+			);
+		}
+
+		// The entry was either already present or has just been inserted.
+		let pooled = self
+			.clients
+			.get_mut(display)
+			.expect("expected pooled client");
+		pooled.last_used = Instant::now();
+
+		Ok(&mut pooled.client)
+	}
+
+	/// Inserts an already established connection to `display` into the pool,
+	/// returning the connection it replaces, if any.
+	pub fn insert(&mut self, display: impl Into<String>, client: Client) -> Option<Client> {
+		self.clients
+			.insert(
+				display.into(),
+				PooledClient {
+					client,
+					last_used: Instant::now(),
+				},
+			)
+			.map(|pooled| pooled.client)
+	}
+
+	/// Removes the connection to `display` from the pool and returns it.
+	pub fn remove(&mut self, display: &str) -> Option<Client> {
+		self.clients.remove(display).map(|pooled| pooled.client)
+	}
+
+	/// Closes connections which have been unused for longer than the idle
+	/// timeout, returning how many were closed.
+	pub fn evict_idle(&mut self) -> usize {
+		let Some(idle_timeout) = self.idle_timeout else {
+			return 0;
+		};
+
+		let before = self.clients.len();
+		self.clients
+			.retain(|_, pooled| pooled.last_used.elapsed() <= idle_timeout);
+
+		before - self.clients.len()
+	}
+
+	/// Checks that every connection in the pool still responds, closing any
+	/// that don't and returning their display names.
+	///
+	/// Checking a connection waits for a round trip to its X server. Events
+	/// received in the meantime are kept and returned by
+	/// [`Client::read_raw_frame`] as normal.
+	pub async fn check_health(&mut self) -> Vec<String> {
+		let mut unhealthy = Vec::new();
+
+		for (display, pooled) in &mut self.clients {
+			if pooled.client.sync().await.is_err() {
+				unhealthy.push(display.clone());
+			}
+		}
+
+		for display in &unhealthy {
+			self.clients.remove(display);
+		}
+
+		unhealthy
+	}
+}
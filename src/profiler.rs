@@ -0,0 +1,532 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Profiling how long the X server takes to respond to each kind of request.
+//!
+//! Every connection has a [`Profiler`], which records nothing until it is
+//! [started]. While it is running, the time from each request being written
+//! to its reply or error being received is recorded in a [`Histogram`] for
+//! that kind of request. Requests which don't generate a reply or error
+//! aren't recorded, since there is nothing to time them by.
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> std::io::Result<()> {
+//! let profiler = client.profiler();
+//! profiler.start();
+//!
+//! // Draw a few frames...
+//!
+//! profiler.stop();
+//!
+//! // Which requests took the most time altogether?
+//! for summary in profiler.summaries() {
+//!     println!(
+//!         "{}: {} requests, {:?} in total, p99 {:?}",
+//!         summary.kind, summary.count, summary.total, summary.p99,
+//!     );
+//! }
+//!
+//! // Or render the same breakdown with a flamegraph tool.
+//! let mut folded = Vec::new();
+//! profiler.write_folded(&mut folded)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [started]: Profiler::start
+
+use crate::{
+	client::in_flight::{self, InFlight},
+	raw::X11Frame,
+	Client,
+	EventReader,
+	RequestWriter,
+};
+use std::{
+	collections::BTreeMap,
+	fmt,
+	fmt::{Display, Formatter},
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+		Mutex,
+		MutexGuard,
+	},
+	time::{Duration, Instant},
+};
+
+/// The first major opcode which belongs to an extension, rather than the
+/// core protocol.
+const FIRST_EXTENSION_OPCODE: u8 = 128;
+
+/// The most samples kept for [`Profiler::samples`].
+const MAX_SAMPLES: usize = 65536;
+
+/// The number of bits of each recorded duration which [`Histogram`] buckets
+/// distinguish between, after its most significant bit.
+const SUB_BUCKET_BITS: u32 = 3;
+/// The number of buckets for each power of two.
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+/// The number of buckets needed to cover every `u64` number of nanoseconds.
+const BUCKETS: usize = (u64::BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS;
+
+/// A kind of request, identified by its opcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestKind {
+	/// The request's major opcode, which for extension requests identifies
+	/// the extension.
+	pub major_opcode: u8,
+	/// The request's minor opcode, if it is an extension request.
+	pub minor_opcode: Option<u8>,
+}
+
+impl RequestKind {
+	/// Returns the kind of a request frame, or `None` if `frame` isn't a
+	/// request.
+	pub fn of(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Request {
+			major_opcode,
+			metabyte,
+			..
+		} = frame
+		else {
+			return None;
+		};
+
+		Some(Self {
+			major_opcode: *major_opcode,
+			minor_opcode: (*major_opcode >= FIRST_EXTENSION_OPCODE).then_some(*metabyte),
+		})
+	}
+}
+
+impl Display for RequestKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.minor_opcode {
+			Some(minor_opcode) => write!(f, "{}.{minor_opcode}", self.major_opcode),
+			None => write!(f, "{}", self.major_opcode),
+		}
+	}
+}
+
+/// A histogram of the latencies of a kind of request.
+///
+/// Latencies are recorded with nanosecond resolution in buckets which are
+/// each within 12.5% of the latencies they hold, so percentiles are accurate
+/// to within 12.5% no matter how many latencies are recorded.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Histogram {
+	buckets: Box<[u64; BUCKETS]>,
+
+	count: u64,
+	/// The sum of every latency, in nanoseconds.
+	total: u128,
+
+	/// The shortest latency, in nanoseconds.
+	min: u64,
+	/// The longest latency, in nanoseconds.
+	max: u64,
+}
+
+impl Histogram {
+	/// Creates an empty histogram.
+	pub fn new() -> Self {
+		Self {
+			buckets: Box::new([0; BUCKETS]),
+
+			count: 0,
+			total: 0,
+
+			min: u64::MAX,
+			max: 0,
+		}
+	}
+
+	/// Returns the bucket which holds `nanos`.
+	const fn bucket(nanos: u64) -> usize {
+		if nanos < SUB_BUCKETS as u64 {
+			return nanos as usize;
+		}
+
+		// `nanos` is made of its most significant bit, followed by the
+		// `SUB_BUCKET_BITS` which pick the bucket for that power of two.
+		let exponent = u64::BITS - 1 - nanos.leading_zeros();
+		let shift = exponent - SUB_BUCKET_BITS;
+
+		((shift + 1) as usize) * SUB_BUCKETS + ((nanos >> shift) as usize - SUB_BUCKETS)
+	}
+
+	/// Returns the longest latency, in nanoseconds, held by `bucket`.
+	const fn bucket_max(bucket: usize) -> u64 {
+		if bucket < SUB_BUCKETS {
+			return bucket as u64;
+		}
+
+		let shift = (bucket / SUB_BUCKETS - 1) as u32;
+		let mantissa = (bucket % SUB_BUCKETS + SUB_BUCKETS) as u128;
+
+		let max = ((mantissa + 1) << shift) - 1;
+
+		if max > u64::MAX as u128 {
+			u64::MAX
+		} else {
+			max as u64
+		}
+	}
+
+	/// Records a `latency`.
+	pub fn record(&mut self, latency: Duration) {
+		let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+
+		self.buckets[Self::bucket(nanos)] += 1;
+
+		self.count += 1;
+		self.total += u128::from(nanos);
+
+		self.min = self.min.min(nanos);
+		self.max = self.max.max(nanos);
+	}
+
+	/// The number of latencies recorded.
+	pub const fn count(&self) -> u64 {
+		self.count
+	}
+
+	/// The sum of every latency recorded.
+	pub fn total(&self) -> Duration {
+		Duration::from_nanos(u64::try_from(self.total).unwrap_or(u64::MAX))
+	}
+
+	/// The mean latency, or [`Duration::ZERO`] if none have been recorded.
+	pub fn mean(&self) -> Duration {
+		match self.count {
+			0 => Duration::ZERO,
+			count => Duration::from_nanos((self.total / u128::from(count)) as u64),
+		}
+	}
+
+	/// The shortest latency, or [`Duration::ZERO`] if none have been
+	/// recorded.
+	pub const fn min(&self) -> Duration {
+		match self.count {
+			0 => Duration::ZERO,
+			_ => Duration::from_nanos(self.min),
+		}
+	}
+
+	/// The longest latency, or [`Duration::ZERO`] if none have been
+	/// recorded.
+	pub const fn max(&self) -> Duration {
+		Duration::from_nanos(self.max)
+	}
+
+	/// Returns the latency which `percentile` percent of latencies are no
+	/// longer than, or [`Duration::ZERO`] if none have been recorded.
+	///
+	/// `percentile` is clamped between 0 and 100.
+	pub fn percentile(&self, percentile: f64) -> Duration {
+		if self.count == 0 {
+			return Duration::ZERO;
+		}
+
+		let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil() as u64;
+		let rank = rank.max(1);
+
+		let mut seen = 0;
+
+		for (bucket, &count) in self.buckets.iter().enumerate() {
+			seen += count;
+
+			if seen >= rank {
+				let nanos = Self::bucket_max(bucket).clamp(self.min, self.max);
+
+				return Duration::from_nanos(nanos);
+			}
+		}
+
+		self.max()
+	}
+
+	/// Adds every latency recorded in `other`.
+	pub fn merge(&mut self, other: &Self) {
+		for (bucket, count) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+			*bucket += count;
+		}
+
+		self.count += other.count;
+		self.total += other.total;
+
+		self.min = self.min.min(other.min);
+		self.max = self.max.max(other.max);
+	}
+}
+
+impl Default for Histogram {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl fmt::Debug for Histogram {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Histogram")
+			.field("count", &self.count)
+			.field("min", &self.min())
+			.field("mean", &self.mean())
+			.field("max", &self.max())
+			.finish_non_exhaustive()
+	}
+}
+
+/// A summary of the latencies of a kind of request.
+///
+/// See [`Profiler::summaries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Summary {
+	pub kind: RequestKind,
+
+	/// The number of requests of this kind which were recorded.
+	pub count: u64,
+	/// The total time spent waiting for requests of this kind.
+	pub total: Duration,
+
+	pub mean: Duration,
+	/// The median latency.
+	pub p50: Duration,
+	pub p90: Duration,
+	pub p99: Duration,
+	pub max: Duration,
+}
+
+/// A single request's recorded latency.
+///
+/// See [`Profiler::samples`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sample {
+	pub kind: RequestKind,
+	pub sequence: u16,
+
+	/// When the request was written, relative to when the profiler was
+	/// started.
+	pub sent: Duration,
+	/// The time from the request being written to its reply or error being
+	/// received.
+	pub latency: Duration,
+}
+
+/// What a [`Profiler`] has recorded.
+#[derive(Default)]
+struct State {
+	/// When the profiler was started.
+	started: Option<Instant>,
+
+	/// The kind of each request which the X server has not yet been seen
+	/// to process.
+	in_flight: InFlight<RequestKind>,
+
+	histograms: BTreeMap<RequestKind, Histogram>,
+	samples: Vec<Sample>,
+}
+
+#[derive(Default)]
+struct Shared {
+	running: AtomicBool,
+	state: Mutex<State>,
+}
+
+/// Records the latency of requests, by kind, while it is running.
+///
+/// A `Profiler` is a handle shared by a [`Client`]'s [`EventReader`] and
+/// [`RequestWriter`], so it can be started, stopped and read from anywhere.
+///
+/// See [`Client::profiler`].
+#[derive(Clone, Default)]
+pub struct Profiler {
+	shared: Arc<Shared>,
+}
+
+impl Profiler {
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.shared
+			.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	/// Starts recording, keeping anything recorded before.
+	pub fn start(&self) {
+		let mut state = self.lock();
+
+		state.started.get_or_insert_with(Instant::now);
+		self.shared.running.store(true, Ordering::Relaxed);
+	}
+
+	/// Stops recording.
+	///
+	/// Requests which haven't been responded to yet aren't recorded.
+	pub fn stop(&self) {
+		let mut state = self.lock();
+
+		self.shared.running.store(false, Ordering::Relaxed);
+		state.in_flight.clear();
+	}
+
+	/// Returns whether the profiler is recording.
+	pub fn is_running(&self) -> bool {
+		self.shared.running.load(Ordering::Relaxed)
+	}
+
+	/// Forgets everything recorded so far.
+	pub fn reset(&self) {
+		let mut state = self.lock();
+		let running = self.is_running();
+
+		*state = State {
+			started: running.then(Instant::now),
+			..State::default()
+		};
+	}
+
+	/// Returns the histogram of each kind of request's latencies.
+	pub fn histograms(&self) -> BTreeMap<RequestKind, Histogram> {
+		self.lock().histograms.clone()
+	}
+
+	/// Summarizes each kind of request's latencies, starting with the kind
+	/// which took the most time altogether.
+	pub fn summaries(&self) -> Vec<Summary> {
+		let state = self.lock();
+
+		let mut summaries: Vec<_> = state
+			.histograms
+			.iter()
+			.map(|(&kind, histogram)| Summary {
+				kind,
+
+				count: histogram.count(),
+				total: histogram.total(),
+
+				mean: histogram.mean(),
+				p50: histogram.percentile(50.0),
+				p90: histogram.percentile(90.0),
+				p99: histogram.percentile(99.0),
+				max: histogram.max(),
+			})
+			.collect();
+
+		summaries.sort_by_key(|summary| std::cmp::Reverse(summary.total));
+
+		summaries
+	}
+
+	/// Returns the individual requests recorded, in the order they were
+	/// responded to.
+	///
+	/// Only the first 65536 requests are kept, so that a profiler left
+	/// running doesn't grow forever; the histograms include every request.
+	pub fn samples(&self) -> Vec<Sample> {
+		self.lock().samples.clone()
+	}
+
+	/// Writes the total time spent on each kind of request in the folded
+	/// stack format read by flamegraph tools such as `inferno` and
+	/// `flamegraph.pl`.
+	///
+	/// Each line is `xrs;<kind> <microseconds>`, where `<kind>` is the
+	/// request's major opcode, followed by `.<minor opcode>` for extension
+	/// requests.
+	pub fn write_folded(&self, out: &mut impl io::Write) -> io::Result<()> {
+		for (kind, histogram) in &self.lock().histograms {
+			writeln!(out, "xrs;{kind} {}", histogram.total().as_micros())?;
+		}
+
+		Ok(())
+	}
+
+	/// Records that the request `frame` was just written with the sequence
+	/// number `sequence`.
+	pub(crate) fn request_sent(&self, sequence: u16, frame: &X11Frame) {
+		if !self.is_running() {
+			return;
+		}
+
+		if let Some(kind) = RequestKind::of(frame) {
+			self.lock().in_flight.push(sequence, kind);
+		}
+	}
+
+	/// Records that `frame` was just received.
+	pub(crate) fn frame_received(&self, frame: &X11Frame) {
+		if !self.is_running() {
+			return;
+		}
+
+		if let Some((sequence, responded)) = in_flight::sequence_of(frame) {
+			self.complete(sequence, responded);
+		}
+	}
+
+	/// Removes requests up to `sequence` from the in-flight requests, since
+	/// the X server must have processed them.
+	///
+	/// If `responded` is true, the request with the sequence number
+	/// `sequence` has its latency recorded.
+	fn complete(&self, sequence: u16, responded: bool) {
+		let mut state = self.lock();
+		let started = *state.started.get_or_insert_with(Instant::now);
+
+		let Some((kind, sent)) = state.in_flight.complete(sequence, responded) else {
+			return;
+		};
+		let latency = sent.elapsed();
+
+		state.histograms.entry(kind).or_default().record(latency);
+
+		if state.samples.len() < MAX_SAMPLES {
+			state.samples.push(Sample {
+				kind,
+				sequence,
+
+				sent: sent.saturating_duration_since(started),
+				latency,
+			});
+		}
+	}
+}
+
+impl fmt::Debug for Profiler {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Profiler")
+			.field("running", &self.is_running())
+			.finish_non_exhaustive()
+	}
+}
+
+impl Client {
+	/// Returns the connection's [`Profiler`], which records how long the X
+	/// server takes to respond to each kind of request once it is started.
+	pub fn profiler(&self) -> Profiler {
+		self.writer.profiler()
+	}
+}
+
+impl RequestWriter {
+	/// Returns the connection's [`Profiler`].
+	///
+	/// See [`Client::profiler`].
+	pub fn profiler(&self) -> Profiler {
+		self.profiler.clone()
+	}
+}
+
+impl EventReader {
+	/// Returns the connection's [`Profiler`].
+	///
+	/// See [`Client::profiler`].
+	pub fn profiler(&self) -> Profiler {
+		self.profiler.clone()
+	}
+}
@@ -0,0 +1,563 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Reading and writing window properties.
+//!
+//! [`Client::get_property`] reads a property's whole value, however many
+//! `GetProperty` requests that takes, and [`Property`] decodes it into
+//! numbers, atoms, windows or text.
+
+use crate::{
+	atoms::Atom,
+	client::RoundTrip,
+	raw::{assert_wire_size, HexBytes, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{fmt, fmt::Formatter};
+use tokio::io;
+
+/// The major opcode of the `ChangeProperty` request.
+const CHANGE_PROPERTY: u8 = 18;
+/// The major opcode of the `DeleteProperty` request.
+const DELETE_PROPERTY: u8 = 19;
+/// The major opcode of the `GetProperty` request.
+const GET_PROPERTY: u8 = 20;
+
+/// The number of 4-byte units of a property's value requested by each
+/// `GetProperty` request: 64 KiB.
+pub(crate) const CHUNK_LENGTH: u32 = 16 * 1024;
+
+/// How [`Window::set_property`] combines the new value with the old one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PropertyMode {
+	/// The new value replaces the old one.
+	#[default]
+	Replace,
+	/// The new value is inserted before the old one.
+	Prepend,
+	/// The new value is added after the old one.
+	Append,
+}
+
+/// The value of a property.
+///
+/// A property's value is a list of 8, 16 or 32-bit values, as given by its
+/// `format`, along with an atom naming its type.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Property {
+	/// The type of the value.
+	pub r#type: Atom,
+	/// Whether the value is a list of 8, 16 or 32-bit values.
+	pub format: u8,
+	/// The value, with 16 and 32-bit values in big-endian byte order.
+	pub data: Vec<u8>,
+}
+
+impl Property {
+	/// Creates a property of 8-bit values.
+	pub fn u8s(r#type: Atom, values: &[u8]) -> Self {
+		Self {
+			r#type,
+			format: 8,
+			data: values.to_vec(),
+		}
+	}
+
+	/// Creates a property of 16-bit values.
+	pub fn u16s(r#type: Atom, values: &[u16]) -> Self {
+		Self {
+			r#type,
+			format: 16,
+			data: values
+				.iter()
+				.flat_map(|value| value.to_be_bytes())
+				.collect(),
+		}
+	}
+
+	/// Creates a property of 32-bit values.
+	pub fn u32s(r#type: Atom, values: &[u32]) -> Self {
+		Self {
+			r#type,
+			format: 32,
+			data: values
+				.iter()
+				.flat_map(|value| value.to_be_bytes())
+				.collect(),
+		}
+	}
+
+	/// Creates an `ATOM` property.
+	pub fn atoms(atoms: &[Atom]) -> Self {
+		Self::u32s(
+			Atom::ATOM,
+			&atoms.iter().map(|atom| atom.0).collect::<Vec<_>>(),
+		)
+	}
+
+	/// Creates a `WINDOW` property.
+	pub fn windows(windows: &[Window]) -> Self {
+		Self::u32s(
+			Atom::WINDOW,
+			&windows.iter().map(|window| window.0).collect::<Vec<_>>(),
+		)
+	}
+
+	/// Creates a text property of the given type, such as `UTF8_STRING`.
+	///
+	/// For `STRING` properties, which are Latin-1, see [`Property::latin1`].
+	pub fn text(r#type: Atom, text: &str) -> Self {
+		Self::u8s(r#type, text.as_bytes())
+	}
+
+	/// Creates a `STRING` property, encoding `text` as Latin-1.
+	///
+	/// Returns `None` if `text` contains characters which are not in
+	/// Latin-1.
+	pub fn latin1(text: &str) -> Option<Self> {
+		let data = text
+			.chars()
+			.map(|char| u8::try_from(char).ok())
+			.collect::<Option<Vec<_>>>()?;
+
+		Some(Self::u8s(Atom::STRING, &data))
+	}
+
+	/// The number of values in the property, in units of its format.
+	pub fn len(&self) -> usize {
+		match self.format {
+			8 | 16 | 32 => self.data.len() / (self.format as usize / 8),
+			_ => 0,
+		}
+	}
+
+	/// Whether the property has no values.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Reads the value as a list of 16-bit values.
+	///
+	/// Returns `None` if the value is not in the 16-bit format.
+	pub fn to_u16s(&self) -> Option<Vec<u16>> {
+		if self.format != 16 {
+			return None;
+		}
+
+		Some(
+			self.data
+				.chunks_exact(2)
+				.map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+				.collect(),
+		)
+	}
+
+	/// Reads the value as a list of 32-bit values.
+	///
+	/// Returns `None` if the value is not in the 32-bit format.
+	pub fn to_u32s(&self) -> Option<Vec<u32>> {
+		if self.format != 32 {
+			return None;
+		}
+
+		Some(
+			self.data
+				.chunks_exact(4)
+				.map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+				.collect(),
+		)
+	}
+
+	/// Reads the value as a list of atoms.
+	///
+	/// Returns `None` if the value is not of type `ATOM`.
+	pub fn to_atoms(&self) -> Option<Vec<Atom>> {
+		if self.r#type != Atom::ATOM {
+			return None;
+		}
+
+		Some(self.to_u32s()?.into_iter().map(Atom).collect())
+	}
+
+	/// Reads the value as a list of windows.
+	///
+	/// Returns `None` if the value is not of type `WINDOW`.
+	pub fn to_windows(&self) -> Option<Vec<Window>> {
+		if self.r#type != Atom::WINDOW {
+			return None;
+		}
+
+		Some(self.to_u32s()?.into_iter().map(Window).collect())
+	}
+
+	/// Reads the value as text.
+	///
+	/// `STRING` values are decoded as Latin-1, and values of any other type,
+	/// such as `UTF8_STRING`, as UTF-8, replacing invalid sequences. Returns
+	/// `None` if the value is not in the 8-bit format.
+	pub fn to_text(&self) -> Option<String> {
+		if self.format != 8 {
+			return None;
+		}
+
+		Some(decode_text(self.r#type, &self.data))
+	}
+
+	/// Reads the value as a list of null-terminated strings, as used by
+	/// properties such as `WM_CLASS` and `_NET_DESKTOP_NAMES`.
+	///
+	/// See [`to_text`](Property::to_text) for how the strings are decoded.
+	pub fn to_texts(&self) -> Option<Vec<String>> {
+		if self.format != 8 {
+			return None;
+		}
+
+		// The last string's terminator is optional.
+		let data = self.data.strip_suffix(&[0]).unwrap_or(&self.data);
+		if data.is_empty() {
+			return Some(Vec::new());
+		}
+
+		Some(
+			data.split(|byte| *byte == 0)
+				.map(|text| decode_text(self.r#type, text))
+				.collect(),
+		)
+	}
+
+	/// Reads the value as a `WM_CLASS` property: the instance name and class
+	/// name of the window.
+	///
+	/// Returns `None` if the value is not two strings.
+	pub fn to_wm_class(&self) -> Option<(String, String)> {
+		match <[String; 2]>::try_from(self.to_texts()?) {
+			Ok([instance, class]) => Some((instance, class)),
+			Err(_) => None,
+		}
+	}
+}
+
+/// Decodes text of the given type.
+fn decode_text(r#type: Atom, data: &[u8]) -> String {
+	match r#type {
+		Atom::STRING => data.iter().map(|byte| char::from(*byte)).collect(),
+		_ => String::from_utf8_lossy(data).into_owned(),
+	}
+}
+
+/// Formats the property with its value decoded: as text if it is 8-bit text,
+/// or as a list of its values otherwise. Long values are truncated.
+impl fmt::Debug for Property {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut debug = f.debug_struct("Property");
+		debug
+			.field("type", &self.r#type)
+			.field("format", &self.format)
+			.field("len", &self.len());
+
+		match self.format {
+			8 if is_text(self.r#type, &self.data) => {
+				let text = decode_text(self.r#type, &self.data);
+
+				match text.char_indices().nth(DEBUG_TEXT) {
+					Some((end, _)) => debug.field("text", &format_args!("{:?}…", &text[..end])),
+					None => debug.field("text", &text),
+				}
+			},
+
+			16 => debug.field("values", &Truncated(&self.to_u16s().unwrap_or_default())),
+
+			32 => match (self.to_atoms(), self.to_windows()) {
+				(Some(atoms), _) => debug.field("atoms", &Truncated(&atoms)),
+				(_, Some(windows)) => debug.field("windows", &Truncated(&windows)),
+				_ => debug.field("values", &Truncated(&self.to_u32s().unwrap_or_default())),
+			},
+
+			_ => debug.field("data", &HexBytes(&self.data)),
+		};
+
+		debug.finish()
+	}
+}
+
+/// The number of characters of text shown in a property's [`Debug`] output.
+///
+/// [`Debug`]: fmt::Debug
+const DEBUG_TEXT: usize = 64;
+/// The number of values shown in a property's [`Debug`] output.
+///
+/// [`Debug`]: fmt::Debug
+const DEBUG_VALUES: usize = 16;
+
+/// Formats a list of values, truncated to its first [`DEBUG_VALUES`].
+struct Truncated<'values, T>(&'values [T]);
+
+impl<T: fmt::Debug> fmt::Debug for Truncated<'_, T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut list = f.debug_list();
+		list.entries(self.0.iter().take(DEBUG_VALUES));
+
+		if self.0.len() > DEBUG_VALUES {
+			list.entry(&format_args!("…; {} values", self.0.len()));
+		}
+
+		list.finish()
+	}
+}
+
+/// Returns whether 8-bit `data` of `type` looks like text: it is `STRING` or
+/// valid UTF-8, without control characters other than whitespace and the
+/// null characters separating lists of strings.
+fn is_text(r#type: Atom, data: &[u8]) -> bool {
+	let printable = |byte: &u8| !byte.is_ascii_control() || matches!(byte, b'\0' | b'\t' | b'\n');
+
+	data.iter().all(printable) && (r#type == Atom::STRING || std::str::from_utf8(data).is_ok())
+}
+
+/// The `GetProperty` request, whose reply is a [`PropertyChunk`] of the
+/// property's value.
+///
+/// See [`Client::get_property`], which reads the whole value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetProperty {
+	/// Whether to delete the property once its whole value has been read.
+	pub delete: bool,
+	pub window: Window,
+	pub property: Atom,
+	/// The type of value wanted, or [`Atom::NONE`] for any type.
+	pub r#type: Atom,
+	/// How far into the value to start, in 4-byte units.
+	pub offset: u32,
+	/// How much of the value to read, in 4-byte units.
+	pub length: u32,
+}
+
+impl Request for GetProperty {
+	type Reply = PropertyChunk;
+
+	const NAME: &'static str = "GetProperty";
+	const OPCODE: u8 = GET_PROPERTY;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn metabyte(&self) -> u8 {
+		self.delete.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u32(self.property.0);
+		body.put_u32(self.r#type.0);
+		body.put_u32(self.offset);
+		body.put_u32(self.length);
+	}
+}
+
+assert_wire_size!(GetProperty: [u8, [u8; 1], u16, Window, Atom, Atom, u32, u32]);
+
+/// Part of the value of a property.
+///
+/// This is the reply to a `GetProperty` request. A property which doesn't
+/// exist has the type [`Atom::NONE`], and one of a different type to that
+/// requested has its type and format but no data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropertyChunk {
+	pub value: Property,
+	/// The number of bytes of the value after this chunk.
+	pub bytes_after: u32,
+}
+
+impl Reply for PropertyChunk {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte: format,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+		let &[t0, t1, t2, t3, a0, a1, a2, a3, l0, l1, l2, l3] = chunk.get(..12)? else {
+			return None;
+		};
+
+		// The length of the value in this reply, in units of its format.
+		let length = u32::from_be_bytes([l0, l1, l2, l3]) as usize;
+		let data = chunk.get(24..24 + length * (*format as usize / 8))?;
+
+		Some(Self {
+			value: Property {
+				r#type: Atom(u32::from_be_bytes([t0, t1, t2, t3])),
+				format: *format,
+				data: data.to_vec(),
+			},
+			bytes_after: u32::from_be_bytes([a0, a1, a2, a3]),
+		})
+	}
+}
+
+/// The `ChangeProperty` request.
+///
+/// See [`Window::set_property`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeProperty<'a> {
+	pub mode: PropertyMode,
+	pub window: Window,
+	pub property: Atom,
+	/// The new value, whose format must be 8, 16 or 32.
+	pub value: &'a Property,
+}
+
+impl Request for ChangeProperty<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ChangeProperty";
+	const OPCODE: u8 = CHANGE_PROPERTY;
+
+	fn metabyte(&self) -> u8 {
+		self.mode as u8
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u32(self.property.0);
+		body.put_u32(self.value.r#type.0);
+		body.put_u8(self.value.format);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+		body.put_u32(self.value.len() as u32);
+		body.put_slice(&self.value.data);
+	}
+}
+
+/// The `DeleteProperty` request.
+///
+/// See [`Window::delete_property`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeleteProperty {
+	pub window: Window,
+	pub property: Atom,
+}
+
+impl Request for DeleteProperty {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "DeleteProperty";
+	const OPCODE: u8 = DELETE_PROPERTY;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u32(self.property.0);
+	}
+}
+
+assert_wire_size!(DeleteProperty: [u8, [u8; 1], u16, Window, Atom]);
+
+/// Reads the whole value of `property` on `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_property`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_property(
+	connection: &mut impl RoundTrip, window: Window, property: Atom, r#type: Atom,
+) -> Result<Option<Property>, ReplyError> {
+	let mut value = Property {
+		r#type: Atom::NONE,
+		format: 0,
+		data: Vec::new(),
+	};
+
+	loop {
+		let offset = (value.data.len() / 4) as u32;
+
+		let request = GetProperty {
+			delete: false,
+			window,
+			property,
+			r#type,
+			offset,
+			length: CHUNK_LENGTH,
+		};
+		let PropertyChunk {
+			value: chunk,
+			bytes_after,
+		} = request::reply_to(connection, &request).await?;
+
+		value.r#type = chunk.r#type;
+		value.format = chunk.format;
+		value.data.extend_from_slice(&chunk.data);
+
+		// A property which doesn't exist has the type `None`, while one of a
+		// different type to that requested has a format but no data.
+		if value.r#type == Atom::NONE {
+			return Ok(None);
+		}
+
+		if bytes_after == 0 || (r#type != Atom::NONE && value.r#type != r#type) {
+			break;
+		}
+	}
+
+	Ok(Some(value))
+}
+
+impl Client {
+	/// Reads the whole value of `property` on `window`.
+	///
+	/// `r#type` is the type of value wanted, or [`Atom::NONE`] for any type.
+	/// If the property has a different type, it is returned with its type and
+	/// format but no data.
+	///
+	/// Returns `None` if the property doesn't exist.
+	pub async fn get_property(
+		&mut self, window: Window, property: Atom, r#type: Atom,
+	) -> Result<Option<Property>, ReplyError> {
+		get_property(self, window, property, r#type).await
+	}
+}
+
+impl Window {
+	/// Sets `property` on the window to `value`, combined with its existing
+	/// value according to `mode`.
+	///
+	/// The `value`'s format must be 8, 16 or 32.
+	pub async fn set_property(
+		self, writer: &mut RequestWriter, property: Atom, value: &Property, mode: PropertyMode,
+	) -> io::Result<()> {
+		if !matches!(value.format, 8 | 16 | 32) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"property format must be 8, 16 or 32",
+			));
+		}
+
+		let request = ChangeProperty {
+			mode,
+			window: self,
+			property,
+			value,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Deletes `property` from the window.
+	pub async fn delete_property(
+		self, writer: &mut RequestWriter, property: Atom,
+	) -> io::Result<()> {
+		let request = DeleteProperty {
+			window: self,
+			property,
+		};
+
+		request::send_core(writer, &request).await
+	}
+}
@@ -0,0 +1,2873 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The RandR extension, which configures outputs, CRTCs and monitors.
+//!
+//! A screen's outputs are its connectors, such as `HDMI-1`. Each output which
+//! is lit is driven by a CRTC, which scans out an area of the screen in one
+//! of the output's modes (resolutions and refresh rates). Monitors, added in
+//! version 1.5, are the logical areas of the screen which windows are placed
+//! on, usually one for each CRTC.
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> Result<(), xrs::ReplyError> {
+//! let root = client.root_window();
+//! let resources = client.get_screen_resources(root).await?;
+//!
+//! for &output in &resources.outputs {
+//!     let info = client.get_output_info(output, resources.config_timestamp).await?;
+//!
+//!     if let Some(crtc) = info.crtc {
+//!         let crtc = client.get_crtc_info(crtc, resources.config_timestamp).await?;
+//!         println!("{} shows {:?}", info.name, crtc.area);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Beyond configuring CRTCs directly, outputs can be given a mode with
+//! [`Client::set_output_mode`], dimmed with [`Client::set_backlight`] and
+//! tinted with [`Client::set_crtc_gamma`]. Providers, added in version 1.4,
+//! are the GPUs which render the screen and drive its outputs.
+//!
+//! Configuration changes are reported as [`RandrEvent`]s once selected with
+//! [`Client::select_randr_events`].
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	extension,
+	geometry::{Point, Rectangle, Size},
+	property::{Property, PropertyChunk, PropertyMode, CHUNK_LENGTH},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	server,
+	time::Timestamp,
+	window::{GetGeometry, Window},
+	Client,
+	ReplyError,
+};
+#[cfg(all(unix, feature = "fds"))]
+use crate::{client::reply_with_fds, xid, xid::XidError};
+use bytes::{BufMut, BytesMut};
+use std::io;
+#[cfg(all(unix, feature = "fds"))]
+use std::os::fd::OwnedFd;
+
+/// The name of the RandR extension.
+pub(crate) const RANDR: &str = "RANDR";
+
+/// The minor opcode of the `RRQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `RRSelectInput` request.
+const SELECT_INPUT: u8 = 4;
+/// The minor opcode of the `RRGetScreenSizeRange` request.
+const GET_SCREEN_SIZE_RANGE: u8 = 6;
+/// The minor opcode of the `RRSetScreenSize` request.
+const SET_SCREEN_SIZE: u8 = 7;
+/// The minor opcode of the `RRGetScreenResources` request.
+const GET_SCREEN_RESOURCES: u8 = 8;
+/// The minor opcode of the `RRGetOutputInfo` request.
+const GET_OUTPUT_INFO: u8 = 9;
+/// The minor opcode of the `RRQueryOutputProperty` request.
+const QUERY_OUTPUT_PROPERTY: u8 = 11;
+/// The minor opcode of the `RRChangeOutputProperty` request.
+const CHANGE_OUTPUT_PROPERTY: u8 = 13;
+/// The minor opcode of the `RRGetOutputProperty` request.
+const GET_OUTPUT_PROPERTY: u8 = 15;
+/// The minor opcode of the `RRGetCrtcInfo` request.
+const GET_CRTC_INFO: u8 = 20;
+/// The minor opcode of the `RRSetCrtcConfig` request.
+const SET_CRTC_CONFIG: u8 = 21;
+/// The minor opcode of the `RRGetCrtcGammaSize` request.
+const GET_CRTC_GAMMA_SIZE: u8 = 22;
+/// The minor opcode of the `RRGetCrtcGamma` request.
+const GET_CRTC_GAMMA: u8 = 23;
+/// The minor opcode of the `RRSetCrtcGamma` request.
+const SET_CRTC_GAMMA: u8 = 24;
+/// The minor opcode of the `RRGetScreenResourcesCurrent` request.
+const GET_SCREEN_RESOURCES_CURRENT: u8 = 25;
+/// The minor opcode of the `RRGetProviders` request.
+const GET_PROVIDERS: u8 = 32;
+/// The minor opcode of the `RRGetProviderInfo` request.
+const GET_PROVIDER_INFO: u8 = 33;
+/// The minor opcode of the `RRSetProviderOffloadSink` request.
+const SET_PROVIDER_OFFLOAD_SINK: u8 = 34;
+/// The minor opcode of the `RRSetProviderOutputSource` request.
+const SET_PROVIDER_OUTPUT_SOURCE: u8 = 35;
+/// The minor opcode of the `RRGetMonitors` request.
+const GET_MONITORS: u8 = 42;
+/// The minor opcode of the `RRSetMonitor` request.
+const SET_MONITOR: u8 = 43;
+/// The minor opcode of the `RRDeleteMonitor` request.
+const DELETE_MONITOR: u8 = 44;
+/// The minor opcode of the `RRCreateLease` request.
+#[cfg(all(unix, feature = "fds"))]
+const CREATE_LEASE: u8 = 45;
+/// The minor opcode of the `RRFreeLease` request.
+const FREE_LEASE: u8 = 46;
+
+/// The version of RandR requested by this client.
+const VERSION: (u32, u32) = (1, 6);
+
+/// The names of the output property holding a backlight's brightness, the
+/// second being that used by older drivers.
+const BACKLIGHT: [&str; 2] = ["Backlight", "BACKLIGHT"];
+
+/// The offset of an `RRScreenChangeNotify` event's code from the extension's
+/// first event code.
+const SCREEN_CHANGE_NOTIFY: u8 = 0;
+/// The offset of an `RRNotify` event's code from the extension's first event
+/// code.
+const NOTIFY: u8 = 1;
+
+/// The `sub-code` of `RRNotify` events for CRTC changes.
+const CRTC_CHANGE: u8 = 0;
+/// The `sub-code` of `RRNotify` events for output changes.
+const OUTPUT_CHANGE: u8 = 1;
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The size of a `MODEINFO` in bytes.
+const MODE_INFO: usize = 32;
+
+/// The RandR events selected on a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RandrEventMask(pub u16);
+
+impl RandrEventMask {
+	/// No events.
+	pub const NONE: Self = Self(0);
+
+	pub const SCREEN_CHANGE: Self = Self(1 << 0);
+	pub const CRTC_CHANGE: Self = Self(1 << 1);
+	pub const OUTPUT_CHANGE: Self = Self(1 << 2);
+	pub const OUTPUT_PROPERTY: Self = Self(1 << 3);
+	pub const PROVIDER_CHANGE: Self = Self(1 << 4);
+	pub const PROVIDER_PROPERTY: Self = Self(1 << 5);
+	pub const RESOURCE_CHANGE: Self = Self(1 << 6);
+
+	/// Returns the union of this mask and `other`.
+	pub const fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+
+	/// Whether every event in `other` is also in this mask.
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+/// A set of rotations and reflections, or a single rotation combined with
+/// reflections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rotation(pub u16);
+
+impl Rotation {
+	pub const ROTATE_0: Self = Self(1 << 0);
+	pub const ROTATE_90: Self = Self(1 << 1);
+	pub const ROTATE_180: Self = Self(1 << 2);
+	pub const ROTATE_270: Self = Self(1 << 3);
+	pub const REFLECT_X: Self = Self(1 << 4);
+	pub const REFLECT_Y: Self = Self(1 << 5);
+
+	/// Returns the union of this set and `other`.
+	pub const fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+
+	/// Whether every rotation and reflection in `other` is also in this set.
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+/// The result of a request which changes the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigStatus {
+	Success,
+	/// The configuration timestamp given is out of date; the configuration
+	/// must be queried again.
+	InvalidConfigTime,
+	/// The timestamp given is earlier than the last configuration change.
+	InvalidTime,
+	Failed,
+}
+
+impl ConfigStatus {
+	/// Returns the `ConfigStatus` encoded as `value`.
+	const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Success,
+			1 => Self::InvalidConfigTime,
+			2 => Self::InvalidTime,
+			3 => Self::Failed,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Whether a monitor is connected to an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Connection {
+	Connected,
+	Disconnected,
+	Unknown,
+}
+
+impl Connection {
+	/// Returns the `Connection` encoded as `value`.
+	const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Connected,
+			1 => Self::Disconnected,
+			2 => Self::Unknown,
+
+			_ => return None,
+		})
+	}
+}
+
+/// The range of sizes a screen can be given, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenSizeRange {
+	pub min: Size,
+	pub max: Size,
+}
+
+/// A mode: a resolution and its timings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModeInfo {
+	pub id: u32,
+	pub name: String,
+
+	pub width: u16,
+	pub height: u16,
+	/// The pixel clock, in Hz.
+	pub dot_clock: u32,
+
+	pub hsync_start: u16,
+	pub hsync_end: u16,
+	pub htotal: u16,
+	pub hskew: u16,
+
+	pub vsync_start: u16,
+	pub vsync_end: u16,
+	pub vtotal: u16,
+
+	/// Flags such as whether the mode is interlaced or doublescan.
+	pub flags: u32,
+}
+
+impl ModeInfo {
+	/// The `Interlace` mode flag.
+	const INTERLACE: u32 = 1 << 4;
+	/// The `DoubleScan` mode flag.
+	const DOUBLE_SCAN: u32 = 1 << 5;
+
+	/// Returns the mode's refresh rate in Hz, or `None` if its timings are
+	/// zero.
+	pub fn refresh_rate(&self) -> Option<f64> {
+		let mut vtotal = f64::from(self.vtotal);
+
+		if self.flags & Self::DOUBLE_SCAN != 0 {
+			vtotal *= 2.0;
+		}
+		if self.flags & Self::INTERLACE != 0 {
+			vtotal /= 2.0;
+		}
+
+		let total = f64::from(self.htotal) * vtotal;
+
+		(total != 0.0).then(|| f64::from(self.dot_clock) / total)
+	}
+}
+
+/// The CRTCs, outputs and modes of a screen.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScreenResources {
+	/// When the configuration was last changed.
+	pub timestamp: Timestamp,
+	/// When the set of outputs or modes last changed, which requests
+	/// changing the configuration must be given.
+	pub config_timestamp: Timestamp,
+
+	pub crtcs: Vec<u32>,
+	pub outputs: Vec<u32>,
+	pub modes: Vec<ModeInfo>,
+}
+
+impl ScreenResources {
+	/// Returns the mode with the given `id`.
+	pub fn mode(&self, id: u32) -> Option<&ModeInfo> {
+		self.modes.iter().find(|mode| mode.id == id)
+	}
+}
+
+/// Information about an output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputInfo {
+	pub timestamp: Timestamp,
+	/// The CRTC driving the output, if it is lit.
+	pub crtc: Option<u32>,
+	pub name: String,
+
+	/// The physical width of the connected monitor, in millimetres.
+	pub mm_width: u32,
+	/// The physical height of the connected monitor, in millimetres.
+	pub mm_height: u32,
+	pub connection: Connection,
+	pub subpixel_order: u8,
+
+	/// The CRTCs which can drive the output.
+	pub crtcs: Vec<u32>,
+	/// The modes the output supports, with its preferred modes first.
+	pub modes: Vec<u32>,
+	/// The number of `modes` which are preferred.
+	pub preferred_count: usize,
+	/// The outputs which can be driven by the same CRTC as this one at the
+	/// same time.
+	pub clones: Vec<u32>,
+}
+
+impl OutputInfo {
+	/// Returns the output's preferred modes.
+	pub fn preferred_modes(&self) -> &[u32] {
+		&self.modes[..self.preferred_count.min(self.modes.len())]
+	}
+}
+
+/// Information about a CRTC.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrtcInfo {
+	pub timestamp: Timestamp,
+	/// The area of the screen the CRTC scans out.
+	pub area: Rectangle,
+	/// The CRTC's mode, or `None` if it is disabled.
+	pub mode: Option<u32>,
+
+	pub rotation: Rotation,
+	/// The rotations and reflections the CRTC supports.
+	pub rotations: Rotation,
+
+	/// The outputs the CRTC is driving.
+	pub outputs: Vec<u32>,
+	/// The outputs the CRTC could drive.
+	pub possible_outputs: Vec<u32>,
+}
+
+/// A CRTC's gamma ramps, which map each value of a color channel to the
+/// intensity shown on its outputs.
+///
+/// Each ramp has the same number of entries, given by
+/// [`Client::get_crtc_gamma_size`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Gamma {
+	pub red: Vec<u16>,
+	pub green: Vec<u16>,
+	pub blue: Vec<u16>,
+}
+
+/// Information about an output property.
+///
+/// This is the reply to an `RRQueryOutputProperty` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputPropertyInfo {
+	/// Whether changes to the property are pending until the next mode set.
+	pub pending: bool,
+	/// Whether `values` is an inclusive range, rather than a list of the
+	/// valid values.
+	pub range: bool,
+	/// Whether clients are prevented from changing the property.
+	pub immutable: bool,
+	pub values: Vec<i32>,
+}
+
+/// The brightness of an output's backlight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Backlight {
+	pub value: i32,
+	/// The lowest brightness the backlight can be set to.
+	pub min: i32,
+	/// The highest brightness the backlight can be set to.
+	pub max: i32,
+}
+
+/// The capabilities of a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ProviderCapabilities(pub u32);
+
+impl ProviderCapabilities {
+	/// The provider can show the screen on the outputs of another provider.
+	pub const SOURCE_OUTPUT: Self = Self(1 << 0);
+	/// The provider can show another provider's screen on its outputs.
+	pub const SINK_OUTPUT: Self = Self(1 << 1);
+	/// The provider can render for another provider.
+	pub const SOURCE_OFFLOAD: Self = Self(1 << 2);
+	/// The provider can have its rendering offloaded to another provider.
+	pub const SINK_OFFLOAD: Self = Self(1 << 3);
+
+	/// Returns the union of these capabilities and `other`.
+	pub const fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+
+	/// Whether every capability in `other` is also in these capabilities.
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+/// The providers of a screen: the GPUs which render it and drive its outputs.
+///
+/// This is the reply to an `RRGetProviders` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Providers {
+	/// When the configuration was last changed.
+	pub timestamp: Timestamp,
+	pub providers: Vec<u32>,
+}
+
+/// Information about a provider.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProviderInfo {
+	pub timestamp: Timestamp,
+	pub capabilities: ProviderCapabilities,
+	pub name: String,
+
+	/// The CRTCs the provider drives.
+	pub crtcs: Vec<u32>,
+	/// The outputs the provider drives.
+	pub outputs: Vec<u32>,
+	/// The providers this one is associated with, and the capabilities this
+	/// one uses each of them for.
+	pub associated_providers: Vec<(u32, ProviderCapabilities)>,
+}
+
+/// A lease of CRTCs and outputs, which a client drives directly through the
+/// DRM device of the lease's file descriptor, such as to show a VR headset.
+///
+/// The lease lasts until it is freed with [`Client::free_lease`] or the
+/// file descriptor is closed.
+#[cfg(all(unix, feature = "fds"))]
+#[derive(Debug)]
+pub struct Lease {
+	pub id: u32,
+	/// The file descriptor of the DRM device.
+	pub fd: OwnedFd,
+}
+
+/// A monitor: a logical area of the screen.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Monitor {
+	pub name: Atom,
+	pub primary: bool,
+	/// Whether the monitor was created automatically for a CRTC, rather than
+	/// by a client.
+	pub automatic: bool,
+
+	pub area: Rectangle,
+	/// The physical width of the monitor, in millimetres.
+	pub mm_width: u32,
+	/// The physical height of the monitor, in millimetres.
+	pub mm_height: u32,
+
+	/// The outputs the monitor is shown on.
+	pub outputs: Vec<u32>,
+}
+
+impl Monitor {
+	/// Reads a `Monitor` from the start of `data`.
+	fn read_from(data: &mut &[u8]) -> Option<Self> {
+		let &[n0, n1, n2, n3, primary, automatic, o0, o1, x0, x1, y0, y1, w0, w1, h0, h1, mw0, mw1, mw2, mw3, mh0, mh1, mh2, mh3] =
+			take(data, 24)?
+		else {
+			return None;
+		};
+
+		let outputs = u32s(take(data, 4 * usize::from(u16::from_be_bytes([o0, o1])))?);
+
+		Some(Self {
+			name: Atom(u32::from_be_bytes([n0, n1, n2, n3])),
+			primary: primary != 0,
+			automatic: automatic != 0,
+
+			area: Rectangle {
+				x: i16::from_be_bytes([x0, x1]),
+				y: i16::from_be_bytes([y0, y1]),
+				width: u16::from_be_bytes([w0, w1]),
+				height: u16::from_be_bytes([h0, h1]),
+			},
+			mm_width: u32::from_be_bytes([mw0, mw1, mw2, mw3]),
+			mm_height: u32::from_be_bytes([mh0, mh1, mh2, mh3]),
+
+			outputs,
+		})
+	}
+
+	/// Writes this monitor as a `MONITORINFO`.
+	fn write_to(&self, buf: &mut impl BufMut) {
+		buf.put_u32(self.name.0);
+		buf.put_u8(self.primary.into());
+		buf.put_u8(self.automatic.into());
+		buf.put_u16(self.outputs.len() as u16);
+
+		buf.put_i16(self.area.x);
+		buf.put_i16(self.area.y);
+		buf.put_u16(self.area.width);
+		buf.put_u16(self.area.height);
+
+		buf.put_u32(self.mm_width);
+		buf.put_u32(self.mm_height);
+
+		for &output in &self.outputs {
+			buf.put_u32(output);
+		}
+	}
+}
+
+/// An `RRScreenChangeNotify` event: the screen's size or rotation changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenChangeEvent {
+	pub rotation: Rotation,
+	pub timestamp: Timestamp,
+	pub config_timestamp: Timestamp,
+
+	pub root: Window,
+	/// The window the event was selected on.
+	pub window: Window,
+
+	pub width: u16,
+	pub height: u16,
+	pub mm_width: u16,
+	pub mm_height: u16,
+}
+
+/// An `RRNotify` event for a CRTC whose configuration changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CrtcChangeEvent {
+	pub timestamp: Timestamp,
+	pub window: Window,
+
+	pub crtc: u32,
+	pub mode: Option<u32>,
+	pub rotation: Rotation,
+	pub area: Rectangle,
+}
+
+/// An `RRNotify` event for an output whose configuration or connection
+/// changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputChangeEvent {
+	pub timestamp: Timestamp,
+	pub config_timestamp: Timestamp,
+	pub window: Window,
+
+	pub output: u32,
+	pub crtc: Option<u32>,
+	pub mode: Option<u32>,
+	pub rotation: Rotation,
+	pub connection: Connection,
+	pub subpixel_order: u8,
+}
+
+/// A decoded RandR event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RandrEvent {
+	ScreenChange(ScreenChangeEvent),
+	CrtcChange(CrtcChangeEvent),
+	OutputChange(OutputChangeEvent),
+}
+
+impl RandrEvent {
+	/// Decodes a `RandrEvent` from an event frame, given the RandR
+	/// extension's [first event code].
+	///
+	/// Returns `None` if `frame` is not a RandR screen, CRTC or output change
+	/// event.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	pub fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let u16_at = |offset: usize| u16::from_be_bytes([chunk[offset - 1], chunk[offset]]);
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				chunk[offset - 1],
+				chunk[offset],
+				chunk[offset + 1],
+				chunk[offset + 2],
+			])
+		};
+		let optional = |value: u32| (value != 0).then_some(value);
+
+		match (code & !SEND_EVENT_MASK).wrapping_sub(first_event) {
+			SCREEN_CHANGE_NOTIFY => Some(Self::ScreenChange(ScreenChangeEvent {
+				rotation: Rotation(chunk[0].into()),
+				timestamp: Timestamp(u32_at(4)),
+				config_timestamp: Timestamp(u32_at(8)),
+
+				root: Window(u32_at(12)),
+				window: Window(u32_at(16)),
+
+				width: u16_at(24),
+				height: u16_at(26),
+				mm_width: u16_at(28),
+				mm_height: u16_at(30),
+			})),
+
+			NOTIFY => match chunk[0] {
+				CRTC_CHANGE => Some(Self::CrtcChange(CrtcChangeEvent {
+					timestamp: Timestamp(u32_at(4)),
+					window: Window(u32_at(8)),
+
+					crtc: u32_at(12),
+					mode: optional(u32_at(16)),
+					rotation: Rotation(u16_at(20)),
+					area: Rectangle {
+						x: u16_at(24) as i16,
+						y: u16_at(26) as i16,
+						width: u16_at(28),
+						height: u16_at(30),
+					},
+				})),
+
+				OUTPUT_CHANGE => Some(Self::OutputChange(OutputChangeEvent {
+					timestamp: Timestamp(u32_at(4)),
+					config_timestamp: Timestamp(u32_at(8)),
+					window: Window(u32_at(12)),
+
+					output: u32_at(16),
+					crtc: optional(u32_at(20)),
+					mode: optional(u32_at(24)),
+					rotation: Rotation(u16_at(28)),
+					connection: Connection::from_value(chunk[29])?,
+					subpixel_order: chunk[30],
+				})),
+
+				_ => None,
+			},
+
+			_ => None,
+		}
+	}
+}
+
+/// Takes `count` bytes from the start of `data`.
+fn take<'data>(data: &mut &'data [u8], count: usize) -> Option<&'data [u8]> {
+	let taken = data.get(..count)?;
+	*data = &data[count..];
+
+	Some(taken)
+}
+
+/// Reads a list of `u16`s.
+fn u16s(data: &[u8]) -> Vec<u16> {
+	data.chunks_exact(2)
+		.map(|value| u16::from_be_bytes([value[0], value[1]]))
+		.collect()
+}
+
+/// Reads a list of `u32`s.
+fn u32s(data: &[u8]) -> Vec<u32> {
+	data.chunks_exact(4)
+		.map(|value| u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+		.collect()
+}
+
+/// The RandR extension's `RRQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "RRQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of RandR supported by both the X server and the client.
+///
+/// This is the reply to an `RRQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("RRQueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[ma0, ma1, ma2, ma3, mi0, mi1, mi2, mi3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([ma0, ma1, ma2, ma3]),
+			minor_version: u32::from_be_bytes([mi0, mi1, mi2, mi3]),
+		})
+	}
+}
+
+/// The RandR extension's `RRSelectInput` request.
+///
+/// See [`Client::select_randr_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectInput {
+	pub window: Window,
+	pub enable: RandrEventMask,
+}
+
+impl Request for SelectInput {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSelectInput";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SELECT_INPUT;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u16(self.enable.0);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(SelectInput: [u8, u8, u16, Window, u16, [u8; 2]]);
+
+/// The RandR extension's `RRGetScreenSizeRange` request.
+///
+/// See [`Client::get_screen_size_range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetScreenSizeRange(pub Window);
+
+impl Request for GetScreenSizeRange {
+	type Reply = ScreenSizeRange;
+
+	const NAME: &'static str = "RRGetScreenSizeRange";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_SCREEN_SIZE_RANGE;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(GetScreenSizeRange: [u8, u8, u16, Window]);
+
+assert_wire_size!("RRGetScreenSizeRange reply": [u8, u8, u16, u32, Size, Size, [u8; 16]] == 32);
+
+impl Reply for ScreenSizeRange {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[w0, w1, h0, h1, mw0, mw1, mh0, mh1] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			min: Size::new(u16::from_be_bytes([w0, w1]), u16::from_be_bytes([h0, h1])),
+			max: Size::new(
+				u16::from_be_bytes([mw0, mw1]),
+				u16::from_be_bytes([mh0, mh1]),
+			),
+		})
+	}
+}
+
+/// The RandR extension's `RRSetScreenSize` request.
+///
+/// See [`Client::set_screen_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetScreenSize {
+	pub window: Window,
+	pub size: Size,
+	/// The physical width of the screen, in millimetres.
+	pub mm_width: u32,
+	/// The physical height of the screen, in millimetres.
+	pub mm_height: u32,
+}
+
+impl Request for SetScreenSize {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSetScreenSize";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_SCREEN_SIZE;
+	const LENGTH: Option<u16> = Some(5);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u16(self.size.width);
+		body.put_u16(self.size.height);
+		body.put_u32(self.mm_width);
+		body.put_u32(self.mm_height);
+	}
+}
+
+assert_wire_size!(SetScreenSize: [u8, u8, u16, Window, Size, u32, u32]);
+
+/// The RandR extension's `RRGetScreenResources` request.
+///
+/// See [`Client::get_screen_resources`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetScreenResources(pub Window);
+
+impl Request for GetScreenResources {
+	type Reply = ScreenResources;
+
+	const NAME: &'static str = "RRGetScreenResources";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_SCREEN_RESOURCES;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(GetScreenResources: [u8, u8, u16, Window]);
+
+/// The RandR extension's `RRGetScreenResourcesCurrent` request.
+///
+/// See [`Client::get_screen_resources_current`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetScreenResourcesCurrent(pub Window);
+
+impl Request for GetScreenResourcesCurrent {
+	type Reply = ScreenResources;
+
+	const NAME: &'static str = "RRGetScreenResourcesCurrent";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_SCREEN_RESOURCES_CURRENT;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(GetScreenResourcesCurrent: [u8, u8, u16, Window]);
+
+assert_wire_size!(
+	"RRGetScreenResourcesCurrent reply": [
+		u8, u8, u16, u32, Timestamp, Timestamp, u16, u16, u16, u16, [u8; 8],
+	] == 32
+);
+
+assert_wire_size!(
+	"RRGetScreenResources reply": [u8, u8, u16, u32, Timestamp, Timestamp, u16, u16, u16, u16, [u8; 8]] == 32
+);
+
+impl Reply for ScreenResources {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let header = chunk.get(..24)?;
+		let u16_at =
+			|offset: usize| usize::from(u16::from_be_bytes([header[offset], header[offset + 1]]));
+
+		let mut data = &chunk[24..];
+
+		let crtcs = u32s(take(&mut data, 4 * u16_at(8))?);
+		let outputs = u32s(take(&mut data, 4 * u16_at(10))?);
+		let modes = take(&mut data, MODE_INFO * u16_at(12))?;
+		let mut names = take(&mut data, u16_at(14))?;
+
+		let modes = modes
+			.chunks_exact(MODE_INFO)
+			.map(|mode| {
+				let u16_at = |offset: usize| u16::from_be_bytes([mode[offset], mode[offset + 1]]);
+				let u32_at = |offset: usize| {
+					u32::from_be_bytes([
+						mode[offset],
+						mode[offset + 1],
+						mode[offset + 2],
+						mode[offset + 3],
+					])
+				};
+
+				let name = take(&mut names, usize::from(u16_at(26)))?;
+
+				Some(ModeInfo {
+					id: u32_at(0),
+					name: String::from_utf8_lossy(name).into_owned(),
+
+					width: u16_at(4),
+					height: u16_at(6),
+					dot_clock: u32_at(8),
+
+					hsync_start: u16_at(12),
+					hsync_end: u16_at(14),
+					htotal: u16_at(16),
+					hskew: u16_at(18),
+
+					vsync_start: u16_at(20),
+					vsync_end: u16_at(22),
+					vtotal: u16_at(24),
+
+					flags: u32_at(28),
+				})
+			})
+			.collect::<Option<_>>()?;
+
+		Some(Self {
+			timestamp: Timestamp(u32::from_be_bytes([
+				header[0], header[1], header[2], header[3],
+			])),
+			config_timestamp: Timestamp(u32::from_be_bytes([
+				header[4], header[5], header[6], header[7],
+			])),
+
+			crtcs,
+			outputs,
+			modes,
+		})
+	}
+}
+
+/// The RandR extension's `RRGetOutputInfo` request.
+///
+/// See [`Client::get_output_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetOutputInfo {
+	pub output: u32,
+	pub config_timestamp: Timestamp,
+}
+
+impl Request for GetOutputInfo {
+	type Reply = OutputInfo;
+
+	const NAME: &'static str = "RRGetOutputInfo";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_OUTPUT_INFO;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.output);
+		body.put_u32(self.config_timestamp.0);
+	}
+}
+
+assert_wire_size!(GetOutputInfo: [u8, u8, u16, u32, Timestamp]);
+
+assert_wire_size!(
+	"RRGetOutputInfo reply": [u8, u8, u16, u32, Timestamp, u32, u32, u32, u8, u8, u16, u16, u16, u16, u16] == 36
+);
+
+impl Reply for OutputInfo {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let header = chunk.get(..28)?;
+		let u16_at =
+			|offset: usize| usize::from(u16::from_be_bytes([header[offset], header[offset + 1]]));
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				header[offset],
+				header[offset + 1],
+				header[offset + 2],
+				header[offset + 3],
+			])
+		};
+
+		let mut data = &chunk[28..];
+
+		let crtcs = u32s(take(&mut data, 4 * u16_at(18))?);
+		let modes = u32s(take(&mut data, 4 * u16_at(20))?);
+		let clones = u32s(take(&mut data, 4 * u16_at(24))?);
+		let name = take(&mut data, u16_at(26))?;
+
+		Some(Self {
+			timestamp: Timestamp(u32_at(0)),
+			crtc: Some(u32_at(4)).filter(|&crtc| crtc != 0),
+			name: String::from_utf8_lossy(name).into_owned(),
+
+			mm_width: u32_at(8),
+			mm_height: u32_at(12),
+			connection: Connection::from_value(header[16])?,
+			subpixel_order: header[17],
+
+			crtcs,
+			modes,
+			preferred_count: u16_at(22),
+			clones,
+		})
+	}
+}
+
+/// The RandR extension's `RRQueryOutputProperty` request.
+///
+/// See [`Client::query_output_property`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryOutputProperty {
+	pub output: u32,
+	pub property: Atom,
+}
+
+impl Request for QueryOutputProperty {
+	type Reply = OutputPropertyInfo;
+
+	const NAME: &'static str = "RRQueryOutputProperty";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = QUERY_OUTPUT_PROPERTY;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.output);
+		body.put_u32(self.property.0);
+	}
+}
+
+assert_wire_size!(QueryOutputProperty: [u8, u8, u16, u32, Atom]);
+
+assert_wire_size!("RRQueryOutputProperty reply": [u8, u8, u16, u32, bool, bool, bool, [u8; 21]] == 32);
+
+impl Reply for OutputPropertyInfo {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[pending, range, immutable] = chunk.get(..3)? else {
+			return None;
+		};
+
+		Some(Self {
+			pending: pending != 0,
+			range: range != 0,
+			immutable: immutable != 0,
+			values: u32s(chunk.get(24..)?)
+				.into_iter()
+				.map(|value| value as i32)
+				.collect(),
+		})
+	}
+}
+
+/// The RandR extension's `RRChangeOutputProperty` request.
+///
+/// See [`Client::set_output_property`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeOutputProperty<'a> {
+	pub output: u32,
+	pub property: Atom,
+	pub mode: PropertyMode,
+	/// The new value, whose format must be 8, 16 or 32.
+	pub value: &'a Property,
+}
+
+impl Request for ChangeOutputProperty<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRChangeOutputProperty";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = CHANGE_OUTPUT_PROPERTY;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.output);
+		body.put_u32(self.property.0);
+		body.put_u32(self.value.r#type.0);
+		body.put_u8(self.value.format);
+		body.put_u8(self.mode as u8);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_u32(self.value.len() as u32);
+		body.put_slice(&self.value.data);
+	}
+}
+
+// Followed by the value.
+assert_wire_size!(
+	"RRChangeOutputProperty request": [u8, u8, u16, u32, Atom, Atom, u8, u8, [u8; 2], u32] == 24
+);
+
+/// The RandR extension's `RRGetOutputProperty` request, whose reply is a
+/// [`PropertyChunk`] of the property's value.
+///
+/// See [`Client::get_output_property`], which reads the whole value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetOutputProperty {
+	pub output: u32,
+	pub property: Atom,
+	/// The type of value wanted, or [`Atom::NONE`] for any type.
+	pub r#type: Atom,
+	/// How far into the value to start, in 4-byte units.
+	pub offset: u32,
+	/// How much of the value to read, in 4-byte units.
+	pub length: u32,
+	/// Whether to delete the property once its whole value has been read.
+	pub delete: bool,
+	/// Whether to read the value which is pending until the next mode set,
+	/// rather than the current value.
+	pub pending: bool,
+}
+
+impl Request for GetOutputProperty {
+	type Reply = PropertyChunk;
+
+	const NAME: &'static str = "RRGetOutputProperty";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_OUTPUT_PROPERTY;
+	const LENGTH: Option<u16> = Some(7);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.output);
+		body.put_u32(self.property.0);
+		body.put_u32(self.r#type.0);
+		body.put_u32(self.offset);
+		body.put_u32(self.length);
+		body.put_u8(self.delete.into());
+		body.put_u8(self.pending.into());
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(GetOutputProperty: [u8, u8, u16, u32, Atom, Atom, u32, u32, bool, bool, [u8; 2]]);
+
+/// The RandR extension's `RRGetCrtcInfo` request.
+///
+/// See [`Client::get_crtc_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetCrtcInfo {
+	pub crtc: u32,
+	pub config_timestamp: Timestamp,
+}
+
+impl Request for GetCrtcInfo {
+	type Reply = CrtcInfo;
+
+	const NAME: &'static str = "RRGetCrtcInfo";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_CRTC_INFO;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.crtc);
+		body.put_u32(self.config_timestamp.0);
+	}
+}
+
+assert_wire_size!(GetCrtcInfo: [u8, u8, u16, u32, Timestamp]);
+
+assert_wire_size!(
+	"RRGetCrtcInfo reply": [u8, u8, u16, u32, Timestamp, Rectangle, u32, u16, u16, u16, u16] == 32
+);
+
+impl Reply for CrtcInfo {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let header = chunk.get(..24)?;
+		let u16_at = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				header[offset],
+				header[offset + 1],
+				header[offset + 2],
+				header[offset + 3],
+			])
+		};
+
+		let mut data = &chunk[24..];
+
+		let outputs = u32s(take(&mut data, 4 * usize::from(u16_at(20)))?);
+		let possible_outputs = u32s(take(&mut data, 4 * usize::from(u16_at(22)))?);
+
+		Some(Self {
+			timestamp: Timestamp(u32_at(0)),
+			area: Rectangle {
+				x: u16_at(4) as i16,
+				y: u16_at(6) as i16,
+				width: u16_at(8),
+				height: u16_at(10),
+			},
+			mode: Some(u32_at(12)).filter(|&mode| mode != 0),
+
+			rotation: Rotation(u16_at(16)),
+			rotations: Rotation(u16_at(18)),
+
+			outputs,
+			possible_outputs,
+		})
+	}
+}
+
+/// The RandR extension's `RRSetCrtcConfig` request.
+///
+/// See [`Client::set_crtc_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetCrtcConfig<'a> {
+	pub crtc: u32,
+	pub timestamp: Timestamp,
+	pub config_timestamp: Timestamp,
+	pub position: Point,
+	/// The mode to show, or `None` to disable the CRTC.
+	pub mode: Option<u32>,
+	pub rotation: Rotation,
+	pub outputs: &'a [u32],
+}
+
+impl Request for SetCrtcConfig<'_> {
+	type Reply = SetCrtcConfigReply;
+
+	const NAME: &'static str = "RRSetCrtcConfig";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_CRTC_CONFIG;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.crtc);
+		body.put_u32(self.timestamp.0);
+		body.put_u32(self.config_timestamp.0);
+		self.position.write_to(body);
+		body.put_u32(self.mode.unwrap_or(0));
+		body.put_u16(self.rotation.0);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+
+		for &output in self.outputs {
+			body.put_u32(output);
+		}
+	}
+}
+
+// Followed by the outputs.
+assert_wire_size!(
+	"RRSetCrtcConfig request": [u8, u8, u16, u32, Timestamp, Timestamp, Point, u32, u16, [u8; 2]] == 28
+);
+
+/// The result of an `RRSetCrtcConfig` request.
+///
+/// This is the reply to an `RRSetCrtcConfig` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetCrtcConfigReply {
+	pub status: ConfigStatus,
+	/// When the configuration was last changed.
+	pub timestamp: Timestamp,
+}
+
+assert_wire_size!("RRSetCrtcConfig reply": [u8, u8, u16, u32, Timestamp, [u8; 20]] == 32);
+
+impl Reply for SetCrtcConfigReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+		let &[t0, t1, t2, t3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			status: ConfigStatus::from_value(*metabyte)?,
+			timestamp: Timestamp(u32::from_be_bytes([t0, t1, t2, t3])),
+		})
+	}
+}
+
+/// The RandR extension's `RRGetCrtcGammaSize` request.
+///
+/// See [`Client::get_crtc_gamma_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetCrtcGammaSize(pub u32);
+
+impl Request for GetCrtcGammaSize {
+	type Reply = GammaSize;
+
+	const NAME: &'static str = "RRGetCrtcGammaSize";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_CRTC_GAMMA_SIZE;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(GetCrtcGammaSize: [u8, u8, u16, u32]);
+
+/// The number of entries in each of a CRTC's gamma ramps.
+///
+/// This is the reply to an `RRGetCrtcGammaSize` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GammaSize(pub u16);
+
+assert_wire_size!("RRGetCrtcGammaSize reply": [u8, u8, u16, u32, u16, [u8; 22]] == 32);
+
+impl Reply for GammaSize {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[s0, s1] = chunk.get(..2)? else {
+			return None;
+		};
+
+		Some(Self(u16::from_be_bytes([s0, s1])))
+	}
+}
+
+/// The RandR extension's `RRGetCrtcGamma` request.
+///
+/// See [`Client::get_crtc_gamma`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetCrtcGamma(pub u32);
+
+impl Request for GetCrtcGamma {
+	type Reply = Gamma;
+
+	const NAME: &'static str = "RRGetCrtcGamma";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_CRTC_GAMMA;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(GetCrtcGamma: [u8, u8, u16, u32]);
+
+// Followed by the red, green and blue ramps.
+assert_wire_size!("RRGetCrtcGamma reply": [u8, u8, u16, u32, u16, [u8; 22]] == 32);
+
+impl Reply for Gamma {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[s0, s1] = chunk.get(..2)? else {
+			return None;
+		};
+		let size = 2 * usize::from(u16::from_be_bytes([s0, s1]));
+
+		let mut data = chunk.get(24..)?;
+
+		Some(Self {
+			red: u16s(take(&mut data, size)?),
+			green: u16s(take(&mut data, size)?),
+			blue: u16s(take(&mut data, size)?),
+		})
+	}
+}
+
+/// The RandR extension's `RRSetCrtcGamma` request.
+///
+/// See [`Client::set_crtc_gamma`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetCrtcGamma<'a> {
+	pub crtc: u32,
+	/// The new ramps, each of which must have as many entries as the CRTC's
+	/// [`GammaSize`].
+	pub gamma: &'a Gamma,
+}
+
+impl Request for SetCrtcGamma<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSetCrtcGamma";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_CRTC_GAMMA;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.crtc);
+		body.put_u16(self.gamma.red.len() as u16);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+
+		for ramp in [&self.gamma.red, &self.gamma.green, &self.gamma.blue] {
+			for &value in ramp {
+				body.put_u16(value);
+			}
+		}
+	}
+}
+
+// Followed by the red, green and blue ramps.
+assert_wire_size!("RRSetCrtcGamma request": [u8, u8, u16, u32, u16, [u8; 2]] == 12);
+
+/// The RandR extension's `RRGetProviders` request.
+///
+/// See [`Client::get_providers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetProviders(pub Window);
+
+impl Request for GetProviders {
+	type Reply = Providers;
+
+	const NAME: &'static str = "RRGetProviders";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_PROVIDERS;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(GetProviders: [u8, u8, u16, Window]);
+
+// Followed by the providers.
+assert_wire_size!("RRGetProviders reply": [u8, u8, u16, u32, Timestamp, u16, [u8; 18]] == 32);
+
+impl Reply for Providers {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[t0, t1, t2, t3, n0, n1] = chunk.get(..6)? else {
+			return None;
+		};
+
+		let mut data = chunk.get(24..)?;
+		let providers = u32s(take(
+			&mut data,
+			4 * usize::from(u16::from_be_bytes([n0, n1])),
+		)?);
+
+		Some(Self {
+			timestamp: Timestamp(u32::from_be_bytes([t0, t1, t2, t3])),
+			providers,
+		})
+	}
+}
+
+/// The RandR extension's `RRGetProviderInfo` request.
+///
+/// See [`Client::get_provider_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetProviderInfo {
+	pub provider: u32,
+	pub config_timestamp: Timestamp,
+}
+
+impl Request for GetProviderInfo {
+	type Reply = ProviderInfo;
+
+	const NAME: &'static str = "RRGetProviderInfo";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_PROVIDER_INFO;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.provider);
+		body.put_u32(self.config_timestamp.0);
+	}
+}
+
+assert_wire_size!(GetProviderInfo: [u8, u8, u16, u32, Timestamp]);
+
+// Followed by the CRTCs, outputs, associated providers, their capabilities
+// and the name.
+assert_wire_size!(
+	"RRGetProviderInfo reply": [u8, u8, u16, u32, Timestamp, u32, u16, u16, u16, u16, [u8; 8]] == 32
+);
+
+impl Reply for ProviderInfo {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let header = chunk.get(..16)?;
+		let u16_at =
+			|offset: usize| usize::from(u16::from_be_bytes([header[offset], header[offset + 1]]));
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				header[offset],
+				header[offset + 1],
+				header[offset + 2],
+				header[offset + 3],
+			])
+		};
+
+		let mut data = chunk.get(24..)?;
+
+		let crtcs = u32s(take(&mut data, 4 * u16_at(8))?);
+		let outputs = u32s(take(&mut data, 4 * u16_at(10))?);
+		let associated = u32s(take(&mut data, 4 * u16_at(12))?);
+		let capabilities = u32s(take(&mut data, 4 * u16_at(12))?);
+		let name = take(&mut data, u16_at(14))?;
+
+		Some(Self {
+			timestamp: Timestamp(u32_at(0)),
+			capabilities: ProviderCapabilities(u32_at(4)),
+			name: String::from_utf8_lossy(name).into_owned(),
+
+			crtcs,
+			outputs,
+			associated_providers: associated
+				.into_iter()
+				.zip(capabilities.into_iter().map(ProviderCapabilities))
+				.collect(),
+		})
+	}
+}
+
+/// The RandR extension's `RRSetProviderOffloadSink` request.
+///
+/// See [`Client::set_provider_offload_sink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetProviderOffloadSink {
+	pub provider: u32,
+	/// The provider to offload rendering to, or `None` to stop offloading.
+	pub sink_provider: Option<u32>,
+	pub config_timestamp: Timestamp,
+}
+
+impl Request for SetProviderOffloadSink {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSetProviderOffloadSink";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_PROVIDER_OFFLOAD_SINK;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.provider);
+		body.put_u32(self.sink_provider.unwrap_or(0));
+		body.put_u32(self.config_timestamp.0);
+	}
+}
+
+assert_wire_size!(SetProviderOffloadSink: [u8, u8, u16, u32, u32, Timestamp]);
+
+/// The RandR extension's `RRSetProviderOutputSource` request.
+///
+/// See [`Client::set_provider_output_source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetProviderOutputSource {
+	pub provider: u32,
+	/// The provider whose screen is shown on `provider`'s outputs, or `None`
+	/// to stop showing it.
+	pub source_provider: Option<u32>,
+	pub config_timestamp: Timestamp,
+}
+
+impl Request for SetProviderOutputSource {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSetProviderOutputSource";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_PROVIDER_OUTPUT_SOURCE;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.provider);
+		body.put_u32(self.source_provider.unwrap_or(0));
+		body.put_u32(self.config_timestamp.0);
+	}
+}
+
+assert_wire_size!(SetProviderOutputSource: [u8, u8, u16, u32, u32, Timestamp]);
+
+/// The RandR extension's `RRGetMonitors` request.
+///
+/// See [`Client::get_monitors`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetMonitors {
+	pub window: Window,
+	/// Whether to only include monitors shown on an enabled CRTC.
+	pub get_active: bool,
+}
+
+impl Request for GetMonitors {
+	type Reply = Monitors;
+
+	const NAME: &'static str = "RRGetMonitors";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = GET_MONITORS;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u8(self.get_active.into());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(GetMonitors: [u8, u8, u16, Window, u8, [u8; 3]]);
+
+/// The monitors of a screen.
+///
+/// This is the reply to an `RRGetMonitors` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Monitors(pub Vec<Monitor>);
+
+assert_wire_size!("RRGetMonitors reply": [u8, u8, u16, u32, Timestamp, u32, u32, [u8; 12]] == 32);
+
+impl Reply for Monitors {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[_, _, _, _, n0, n1, n2, n3] = chunk.get(..8)? else {
+			return None;
+		};
+		let mut data = chunk.get(24..)?;
+
+		(0..u32::from_be_bytes([n0, n1, n2, n3]))
+			.map(|_| Monitor::read_from(&mut data))
+			.collect::<Option<_>>()
+			.map(Self)
+	}
+}
+
+/// The RandR extension's `RRSetMonitor` request.
+///
+/// See [`Client::set_monitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetMonitor<'a> {
+	pub window: Window,
+	pub monitor: &'a Monitor,
+}
+
+impl Request for SetMonitor<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRSetMonitor";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = SET_MONITOR;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		self.monitor.write_to(body);
+	}
+}
+
+// Followed by the monitor's outputs.
+assert_wire_size!(
+	"RRSetMonitor request": [u8, u8, u16, Window, Atom, bool, bool, u16, Rectangle, u32, u32] == 32
+);
+
+/// The RandR extension's `RRDeleteMonitor` request.
+///
+/// See [`Client::delete_monitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeleteMonitor {
+	pub window: Window,
+	pub name: Atom,
+}
+
+impl Request for DeleteMonitor {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRDeleteMonitor";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = DELETE_MONITOR;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u32(self.name.0);
+	}
+}
+
+assert_wire_size!(DeleteMonitor: [u8, u8, u16, Window, Atom]);
+
+/// The RandR extension's `RRCreateLease` request, whose reply is received
+/// alongside the lease's file descriptor.
+///
+/// See [`Client::create_lease`].
+#[cfg(all(unix, feature = "fds"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateLease<'a> {
+	pub window: Window,
+	/// The ID of the new lease.
+	pub lease: u32,
+	pub crtcs: &'a [u32],
+	pub outputs: &'a [u32],
+}
+
+#[cfg(all(unix, feature = "fds"))]
+impl Request for CreateLease<'_> {
+	type Reply = CreateLeaseReply;
+
+	const NAME: &'static str = "RRCreateLease";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = CREATE_LEASE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.id());
+		body.put_u32(self.lease);
+		body.put_u16(self.crtcs.len() as u16);
+		body.put_u16(self.outputs.len() as u16);
+
+		for &id in self.crtcs.iter().chain(self.outputs) {
+			body.put_u32(id);
+		}
+	}
+}
+
+// Followed by the CRTCs and outputs.
+#[cfg(all(unix, feature = "fds"))]
+assert_wire_size!("RRCreateLease request": [u8, u8, u16, Window, u32, u16, u16] == 16);
+
+/// The reply to an `RRCreateLease` request, whose file descriptor is
+/// received alongside it.
+#[cfg(all(unix, feature = "fds"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateLeaseReply {
+	/// The number of file descriptors sent with the reply.
+	pub nfd: u8,
+}
+
+#[cfg(all(unix, feature = "fds"))]
+assert_wire_size!("RRCreateLease reply": [u8, u8, u16, u32, [u8; 24]] == 32);
+
+#[cfg(all(unix, feature = "fds"))]
+impl Reply for CreateLeaseReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { metabyte, .. } = frame else {
+			return None;
+		};
+
+		Some(Self { nfd: *metabyte })
+	}
+}
+
+/// The RandR extension's `RRFreeLease` request.
+///
+/// See [`Client::free_lease`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeLease {
+	pub lease: u32,
+	/// Whether to turn off the lease's CRTCs and outputs, rather than leave
+	/// them showing whatever the lessee last set.
+	pub terminate: bool,
+}
+
+impl Request for FreeLease {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RRFreeLease";
+	const EXTENSION: Option<&'static str> = Some(RANDR);
+	const OPCODE: u8 = FREE_LEASE;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.lease);
+		body.put_u8(self.terminate.into());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(FreeLease: [u8, u8, u16, u32, u8, [u8; 3]]);
+
+/// Queries the version of RandR supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::randr_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn randr_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	let request = QueryVersion {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+
+	extension::negotiate_version(connection, RANDR, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await
+}
+
+/// Gets the CRTCs, outputs and modes of `window`'s screen.
+pub(crate) async fn screen_resources(
+	connection: &mut impl RoundTrip, window: Window, current: bool,
+) -> Result<ScreenResources, ReplyError> {
+	match current {
+		true => request::reply_to(connection, &GetScreenResourcesCurrent(window)).await,
+		false => request::reply_to(connection, &GetScreenResources(window)).await,
+	}
+}
+
+/// Configures `crtc` to show `mode` at `position` on `outputs`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_crtc_config`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+#[allow(clippy::too_many_arguments)]
+pub async fn set_crtc_config(
+	connection: &mut impl RoundTrip, crtc: u32, timestamp: Timestamp, config_timestamp: Timestamp,
+	position: Point, mode: Option<u32>, rotation: Rotation, outputs: &[u32],
+) -> Result<ConfigStatus, ReplyError> {
+	if server::quirks(connection)
+		.await?
+		.emulates_randr_configuration
+	{
+		return Err(ReplyError::Io(io::Error::new(
+			io::ErrorKind::Unsupported,
+			"the X server does not apply RandR configuration changes to its outputs",
+		)));
+	}
+
+	let request = SetCrtcConfig {
+		crtc,
+		timestamp,
+		config_timestamp,
+		position,
+		mode,
+		rotation,
+		outputs,
+	};
+
+	Ok(request::reply_to(connection, &request).await?.status)
+}
+
+/// Gets information about `output`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_output_info`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_output_info(
+	connection: &mut impl RoundTrip, output: u32, config_timestamp: Timestamp,
+) -> Result<OutputInfo, ReplyError> {
+	request::reply_to(
+		connection,
+		&GetOutputInfo {
+			output,
+			config_timestamp,
+		},
+	)
+	.await
+}
+
+/// Gets the monitors of `window`'s screen.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_monitors`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_monitors(
+	connection: &mut impl RoundTrip, window: Window, active: bool,
+) -> Result<Vec<Monitor>, ReplyError> {
+	let request = GetMonitors {
+		window,
+		get_active: active,
+	};
+	let Monitors(monitors) = request::reply_to(connection, &request).await?;
+
+	Ok(monitors)
+}
+
+/// Gets information about `crtc`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_crtc_info`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_crtc_info(
+	connection: &mut impl RoundTrip, crtc: u32, config_timestamp: Timestamp,
+) -> Result<CrtcInfo, ReplyError> {
+	request::reply_to(
+		connection,
+		&GetCrtcInfo {
+			crtc,
+			config_timestamp,
+		},
+	)
+	.await
+}
+
+/// Resizes `window`'s screen to `size`.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_screen_size`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_screen_size(
+	connection: &mut impl RoundTrip, window: Window, size: Size,
+) -> Result<(), ReplyError> {
+	let screen = connection.writer().default_screen();
+
+	// Millimetres per pixel are kept as they were when connecting.
+	let mm = |pixels: u16, mm: u16, setup_pixels: u16| match setup_pixels {
+		0 => 0,
+		setup_pixels => u32::from(pixels) * u32::from(mm) / u32::from(setup_pixels),
+	};
+	let request = SetScreenSize {
+		window,
+		size,
+		mm_width: mm(size.width, screen.width_mm, screen.width),
+		mm_height: mm(size.height, screen.height_mm, screen.height),
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Shows `mode` on `output` at `position`, rotated by `rotation`, or
+/// turns it off if `mode` is `None`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_output_mode`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_output_mode(
+	connection: &mut impl RoundTrip, output: u32, position: Point, mode: Option<u32>,
+	rotation: Rotation,
+) -> Result<ConfigStatus, ReplyError> {
+	let grab = connection
+		.writer()
+		.grab_server()
+		.await
+		.map_err(ReplyError::Io)?;
+
+	let status = configure_output(connection, output, position, mode, rotation).await;
+	let released = grab.release(connection.writer()).await;
+
+	let status = status?;
+	released.map_err(ReplyError::Io)?;
+
+	Ok(status)
+}
+
+/// Configures `output` for [`set_output_mode`] while the server is grabbed.
+///
+/// An output which is lit keeps its CRTC, along with any outputs cloned on
+/// it; otherwise, it is given the first of its CRTCs which isn't driving
+/// other outputs. Returns [`ConfigStatus::Failed`] if there isn't one, if
+/// `mode` isn't one of the screen's modes, or if the screen can't be made
+/// large enough to show it.
+///
+/// The screen is resized to the area covered by the CRTCs: it is grown
+/// before the CRTC is configured and shrunk after, so that every CRTC fits
+/// the screen throughout.
+async fn configure_output(
+	connection: &mut impl RoundTrip, output: u32, position: Point, mode: Option<u32>,
+	rotation: Rotation,
+) -> Result<ConfigStatus, ReplyError> {
+	let root = Window(connection.writer().default_screen().root);
+	let resources = screen_resources(connection, root, true).await?;
+	let config_timestamp = resources.config_timestamp;
+	let info = get_output_info(connection, output, config_timestamp).await?;
+
+	let (crtc, crtc_info) = match info.crtc {
+		Some(crtc) => (
+			crtc,
+			get_crtc_info(connection, crtc, config_timestamp).await?,
+		),
+
+		// The output is already off.
+		None if mode.is_none() => return Ok(ConfigStatus::Success),
+
+		None => {
+			let mut free = None;
+
+			for &crtc in &info.crtcs {
+				let crtc_info = get_crtc_info(connection, crtc, config_timestamp).await?;
+
+				if crtc_info.outputs.is_empty() {
+					free = Some((crtc, crtc_info));
+					break;
+				}
+			}
+
+			let Some(free) = free else {
+				return Ok(ConfigStatus::Failed);
+			};
+
+			free
+		},
+	};
+
+	let mut outputs = crtc_info.outputs;
+	let (mut mode, mut position, mut rotation) = (mode, position, rotation);
+
+	match mode {
+		Some(_) if !outputs.contains(&output) => outputs.push(output),
+		Some(_) => {},
+
+		None => {
+			outputs.retain(|&other| other != output);
+
+			// Clones keep showing the CRTC's current configuration.
+			if !outputs.is_empty() {
+				mode = crtc_info.mode;
+				position = crtc_info.area.position();
+				rotation = crtc_info.rotation;
+			}
+		},
+	}
+
+	let rotation = match rotation {
+		Rotation(0) => Rotation::ROTATE_0,
+		rotation => rotation,
+	};
+
+	// The area the CRTC will scan out, if it is enabled.
+	let area = match mode {
+		Some(id) => {
+			let Some(mode) = resources.mode(id) else {
+				return Ok(ConfigStatus::Failed);
+			};
+			let size = match rotation.contains(Rotation::ROTATE_90)
+				|| rotation.contains(Rotation::ROTATE_270)
+			{
+				true => Size::new(mode.height, mode.width),
+				false => Size::new(mode.width, mode.height),
+			};
+
+			Some(Rectangle::new(position, size))
+		},
+
+		None => None,
+	};
+
+	// The screen must cover every enabled CRTC.
+	let mut bounds = area;
+
+	for &other in resources.crtcs.iter().filter(|&&other| other != crtc) {
+		let other = get_crtc_info(connection, other, config_timestamp).await?;
+
+		if other.mode.is_some() {
+			bounds = Some(match bounds {
+				Some(bounds) => bounds.bounding(&other.area),
+				None => other.area,
+			});
+		}
+	}
+
+	let current = request::reply_to(connection, &GetGeometry { drawable: root.0 })
+		.await?
+		.area
+		.size();
+	let size = match bounds {
+		Some(bounds) => {
+			let range = request::reply_to(connection, &GetScreenSizeRange(root)).await?;
+
+			let (Ok(width), Ok(height)) = (
+				u16::try_from(bounds.right().max(0)),
+				u16::try_from(bounds.bottom().max(0)),
+			) else {
+				return Ok(ConfigStatus::Failed);
+			};
+			if width > range.max.width || height > range.max.height {
+				return Ok(ConfigStatus::Failed);
+			}
+
+			Size::new(width.max(range.min.width), height.max(range.min.height))
+		},
+
+		// With every CRTC disabled, the screen keeps its size.
+		None => current,
+	};
+	let grown = Size::new(
+		current.width.max(size.width),
+		current.height.max(size.height),
+	);
+
+	if grown != current {
+		set_screen_size(connection, root, grown).await?;
+	}
+
+	let status = set_crtc_config(
+		connection,
+		crtc,
+		Timestamp::CURRENT_TIME,
+		config_timestamp,
+		position,
+		mode,
+		rotation,
+		&outputs,
+	)
+	.await?;
+
+	// If the CRTC wasn't configured, the screen is given its old size back.
+	let size = match status {
+		ConfigStatus::Success => size,
+		_ => current,
+	};
+
+	if size != grown {
+		set_screen_size(connection, root, size).await?;
+	}
+
+	Ok(status)
+}
+
+/// Gets the value of `output`'s `property`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_output_property`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_output_property(
+	connection: &mut impl RoundTrip, output: u32, property: Atom, r#type: Atom,
+) -> Result<Option<Property>, ReplyError> {
+	let mut value = Property {
+		r#type: Atom::NONE,
+		format: 0,
+		data: Vec::new(),
+	};
+
+	loop {
+		let request = GetOutputProperty {
+			output,
+			property,
+			r#type,
+			offset: (value.data.len() / 4) as u32,
+			length: CHUNK_LENGTH,
+			delete: false,
+			pending: false,
+		};
+		let PropertyChunk {
+			value: chunk,
+			bytes_after,
+		} = request::reply_to(connection, &request).await?;
+
+		value.r#type = chunk.r#type;
+		value.format = chunk.format;
+		value.data.extend_from_slice(&chunk.data);
+
+		if value.r#type == Atom::NONE {
+			return Ok(None);
+		}
+
+		if bytes_after == 0 || (r#type != Atom::NONE && value.r#type != r#type) {
+			break;
+		}
+	}
+
+	Ok(Some(value))
+}
+
+/// Finds the property holding the brightness of `output`'s backlight.
+async fn backlight(
+	connection: &mut impl RoundTrip, output: u32,
+) -> Result<Option<(Atom, Backlight)>, ReplyError> {
+	for name in BACKLIGHT {
+		let Some(property) = atoms::intern_atom(connection, name, true).await? else {
+			continue;
+		};
+		let Some(value) = get_output_property(connection, output, property, Atom::INTEGER).await?
+		else {
+			continue;
+		};
+		let Some(&[value]) = value.to_u32s().as_deref() else {
+			continue;
+		};
+
+		let info = request::reply_to(connection, &QueryOutputProperty { output, property }).await?;
+		let &[min, max] = &info.values[..] else {
+			continue;
+		};
+
+		if info.range {
+			let backlight = Backlight {
+				value: value as i32,
+				min,
+				max,
+			};
+
+			return Ok(Some((property, backlight)));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Sets the brightness of `output`'s backlight.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_backlight`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_backlight(
+	connection: &mut impl RoundTrip, output: u32, value: i32,
+) -> Result<bool, ReplyError> {
+	let Some((property, backlight)) = backlight(connection, output).await? else {
+		return Ok(false);
+	};
+
+	let value = value.clamp(backlight.min, backlight.max);
+	let request = ChangeOutputProperty {
+		output,
+		property,
+		mode: PropertyMode::Replace,
+		value: &Property::u32s(Atom::INTEGER, &[value as u32]),
+	};
+	request::send(connection, &request).await?;
+
+	Ok(true)
+}
+
+/// Leases `crtcs` and `outputs` of `window`'s screen.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_lease`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+#[cfg(all(unix, feature = "fds"))]
+pub async fn create_lease(
+	connection: &mut impl RoundTrip, window: Window, crtcs: &[u32], outputs: &[u32],
+) -> Result<Lease, XidError> {
+	if !connection.writer().can_pass_fds() {
+		return Err(ReplyError::Io(io::Error::new(
+			io::ErrorKind::Unsupported,
+			"RandR leases require file descriptors to be passed over a Unix domain socket",
+		))
+		.into());
+	}
+
+	let randr = extension::require_extension(connection, RANDR).await?;
+	let lease = xid::generate_id(connection).await?;
+
+	let request = CreateLease {
+		window,
+		lease,
+		crtcs,
+		outputs,
+	};
+	let (frame, fds) = reply_with_fds(connection, request.to_raw(randr.major_opcode)).await?;
+
+	let malformed = || ReplyError::malformed(CreateLease::NAME);
+	CreateLeaseReply::from_reply(&frame).ok_or_else(malformed)?;
+	let fd = fds.into_iter().next().ok_or_else(malformed)?;
+
+	Ok(Lease { id: lease, fd })
+}
+
+/// Selects the RandR events in `mask` on `window`.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::select_randr_events`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn select_randr_events(
+	connection: &mut impl RoundTrip, window: Window, mask: RandrEventMask,
+) -> Result<(), ReplyError> {
+	request::send(
+		connection,
+		&SelectInput {
+			window,
+			enable: mask,
+		},
+	)
+	.await
+}
+
+/// Gets the CRTCs, outputs and modes of `window`'s screen.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_screen_resources`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_screen_resources(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<ScreenResources, ReplyError> {
+	screen_resources(connection, window, false).await
+}
+
+/// Gets the CRTCs, outputs and modes of `window`'s screen without polling
+/// for changes.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_screen_resources_current`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_screen_resources_current(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<ScreenResources, ReplyError> {
+	screen_resources(connection, window, true).await
+}
+
+/// Gets the range of sizes `window`'s screen can be given.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_screen_size_range`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_screen_size_range(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<ScreenSizeRange, ReplyError> {
+	request::reply_to(connection, &GetScreenSizeRange(window)).await
+}
+
+/// Creates a monitor on `window`'s screen, replacing any monitor with the
+/// same name.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_monitor`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_monitor(
+	connection: &mut impl RoundTrip, window: Window, monitor: &Monitor,
+) -> Result<(), ReplyError> {
+	request::send(connection, &SetMonitor { window, monitor }).await
+}
+
+/// Deletes the monitor called `name` from `window`'s screen.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::delete_monitor`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn delete_monitor(
+	connection: &mut impl RoundTrip, window: Window, name: Atom,
+) -> Result<(), ReplyError> {
+	request::send(connection, &DeleteMonitor { window, name }).await
+}
+
+/// Gets the number of entries in each of `crtc`'s gamma ramps.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_crtc_gamma_size`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_crtc_gamma_size(
+	connection: &mut impl RoundTrip, crtc: u32,
+) -> Result<u16, ReplyError> {
+	Ok(request::reply_to(connection, &GetCrtcGammaSize(crtc))
+		.await?
+		.0)
+}
+
+/// Gets `crtc`'s gamma ramps.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_crtc_gamma`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_crtc_gamma(
+	connection: &mut impl RoundTrip, crtc: u32,
+) -> Result<Gamma, ReplyError> {
+	request::reply_to(connection, &GetCrtcGamma(crtc)).await
+}
+
+/// Sets `crtc`'s gamma ramps.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_crtc_gamma`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_crtc_gamma(
+	connection: &mut impl RoundTrip, crtc: u32, gamma: &Gamma,
+) -> Result<(), ReplyError> {
+	request::send(connection, &SetCrtcGamma { crtc, gamma }).await
+}
+
+/// Gets information about `output`'s `property`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::query_output_property`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn query_output_property(
+	connection: &mut impl RoundTrip, output: u32, property: Atom,
+) -> Result<OutputPropertyInfo, ReplyError> {
+	request::reply_to(connection, &QueryOutputProperty { output, property }).await
+}
+
+/// Changes the value of `output`'s `property`.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_output_property`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_output_property(
+	connection: &mut impl RoundTrip, output: u32, property: Atom, value: &Property,
+	mode: PropertyMode,
+) -> Result<(), ReplyError> {
+	let request = ChangeOutputProperty {
+		output,
+		property,
+		mode,
+		value,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Gets the brightness of `output`'s backlight.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_backlight`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_backlight(
+	connection: &mut impl RoundTrip, output: u32,
+) -> Result<Option<Backlight>, ReplyError> {
+	Ok(backlight(connection, output)
+		.await?
+		.map(|(_, backlight)| backlight))
+}
+
+/// Gets the providers of `window`'s screen.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_providers`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_providers(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<Providers, ReplyError> {
+	request::reply_to(connection, &GetProviders(window)).await
+}
+
+/// Gets information about `provider`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_provider_info`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_provider_info(
+	connection: &mut impl RoundTrip, provider: u32, config_timestamp: Timestamp,
+) -> Result<ProviderInfo, ReplyError> {
+	request::reply_to(
+		connection,
+		&GetProviderInfo {
+			provider,
+			config_timestamp,
+		},
+	)
+	.await
+}
+
+/// Offloads `provider`'s rendering to `sink_provider`.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_provider_offload_sink`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_provider_offload_sink(
+	connection: &mut impl RoundTrip, provider: u32, sink_provider: Option<u32>,
+	config_timestamp: Timestamp,
+) -> Result<(), ReplyError> {
+	let request = SetProviderOffloadSink {
+		provider,
+		sink_provider,
+		config_timestamp,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Shows the screen of `source_provider` on `provider`'s outputs.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_provider_output_source`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_provider_output_source(
+	connection: &mut impl RoundTrip, provider: u32, source_provider: Option<u32>,
+	config_timestamp: Timestamp,
+) -> Result<(), ReplyError> {
+	let request = SetProviderOutputSource {
+		provider,
+		source_provider,
+		config_timestamp,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Frees the lease `lease`.
+///
+/// With a [`RequestWriter`], if the RandR extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::free_lease`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn free_lease(
+	connection: &mut impl RoundTrip, lease: u32, terminate: bool,
+) -> Result<(), ReplyError> {
+	request::send(connection, &FreeLease { lease, terminate }).await
+}
+
+impl Client {
+	/// Queries the version of RandR supported by the X server, as its major
+	/// and minor version.
+	///
+	/// This also tells the X server which version this client supports, 1.6,
+	/// which must be done before using requests added after version 1.1. The
+	/// version is negotiated once, so only the first query makes a round trip
+	/// to the X server.
+	pub async fn randr_version(&mut self) -> Result<(u32, u32), ReplyError> {
+		randr_version(self).await
+	}
+
+	/// Selects the RandR events in `mask` on `window`, replacing those this
+	/// client previously selected on it.
+	///
+	/// See [`RandrEvent::from_frame`] for decoding them.
+	pub async fn select_randr_events(
+		&mut self, window: Window, mask: RandrEventMask,
+	) -> Result<(), ReplyError> {
+		select_randr_events(self, window, mask).await
+	}
+
+	/// Gets the CRTCs, outputs and modes of `window`'s screen.
+	///
+	/// The X server polls for changes to the hardware first, which can take
+	/// a while; see [`Client::get_screen_resources_current`].
+	pub async fn get_screen_resources(
+		&mut self, window: Window,
+	) -> Result<ScreenResources, ReplyError> {
+		get_screen_resources(self, window).await
+	}
+
+	/// Gets the CRTCs, outputs and modes of `window`'s screen, as the X
+	/// server currently knows them, without polling for changes.
+	///
+	/// This needs RandR 1.3.
+	pub async fn get_screen_resources_current(
+		&mut self, window: Window,
+	) -> Result<ScreenResources, ReplyError> {
+		get_screen_resources_current(self, window).await
+	}
+
+	/// Gets the range of sizes `window`'s screen can be given.
+	pub async fn get_screen_size_range(
+		&mut self, window: Window,
+	) -> Result<ScreenSizeRange, ReplyError> {
+		get_screen_size_range(self, window).await
+	}
+
+	/// Resizes `window`'s screen to `size`, keeping the physical size in
+	/// proportion to the size the screen had when connecting.
+	///
+	/// `size` must be within the screen's [size range], and every enabled
+	/// CRTC must fit within it. See [`Client::set_output_mode`], which
+	/// resizes the screen as needed.
+	///
+	/// [size range]: Client::get_screen_size_range
+	pub async fn set_screen_size(&mut self, window: Window, size: Size) -> Result<(), ReplyError> {
+		set_screen_size(self, window, size).await
+	}
+
+	/// Gets information about `output`.
+	///
+	/// `config_timestamp` is that of the [`ScreenResources`] `output` is from.
+	pub async fn get_output_info(
+		&mut self, output: u32, config_timestamp: Timestamp,
+	) -> Result<OutputInfo, ReplyError> {
+		get_output_info(self, output, config_timestamp).await
+	}
+
+	/// Gets information about `crtc`.
+	///
+	/// `config_timestamp` is that of the [`ScreenResources`] `crtc` is from.
+	pub async fn get_crtc_info(
+		&mut self, crtc: u32, config_timestamp: Timestamp,
+	) -> Result<CrtcInfo, ReplyError> {
+		get_crtc_info(self, crtc, config_timestamp).await
+	}
+
+	/// Configures `crtc` to show `mode` with its top-left corner at
+	/// `position` of the screen, rotated by `rotation`, on `outputs`.
+	///
+	/// A `mode` of `None` and no `outputs` disables the CRTC. The
+	/// configuration is only changed if `config_timestamp` is that of the
+	/// current [`ScreenResources`], and `timestamp` is no earlier than the
+	/// last change.
+	///
+	/// Returns an [`Unsupported`] error if the X server, such as Xwayland,
+	/// only emulates configuration changes without applying them to its
+	/// outputs. See [`ServerQuirks::emulates_randr_configuration`].
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	/// [`ServerQuirks::emulates_randr_configuration`]: server::ServerQuirks::emulates_randr_configuration
+	#[allow(clippy::too_many_arguments)]
+	pub async fn set_crtc_config(
+		&mut self, crtc: u32, timestamp: Timestamp, config_timestamp: Timestamp, position: Point,
+		mode: Option<u32>, rotation: Rotation, outputs: &[u32],
+	) -> Result<ConfigStatus, ReplyError> {
+		set_crtc_config(
+			self,
+			crtc,
+			timestamp,
+			config_timestamp,
+			position,
+			mode,
+			rotation,
+			outputs,
+		)
+		.await
+	}
+
+	/// Gets the monitors of `window`'s screen.
+	///
+	/// If `active` is true, only monitors shown on an enabled CRTC are
+	/// included. This needs RandR 1.5.
+	pub async fn get_monitors(
+		&mut self, window: Window, active: bool,
+	) -> Result<Vec<Monitor>, ReplyError> {
+		get_monitors(self, window, active).await
+	}
+
+	/// Creates a monitor on `window`'s screen, replacing any monitor with the
+	/// same name.
+	///
+	/// This needs RandR 1.5.
+	pub async fn set_monitor(
+		&mut self, window: Window, monitor: &Monitor,
+	) -> Result<(), ReplyError> {
+		set_monitor(self, window, monitor).await
+	}
+
+	/// Deletes the monitor called `name` from `window`'s screen.
+	///
+	/// This needs RandR 1.5.
+	pub async fn delete_monitor(&mut self, window: Window, name: Atom) -> Result<(), ReplyError> {
+		delete_monitor(self, window, name).await
+	}
+
+	/// Shows `mode` on `output` with its top-left corner at `position` of
+	/// the screen, rotated by `rotation`, or turns it off if `mode` is
+	/// `None`, as `xrandr --output` does.
+	///
+	/// An output which is lit keeps its CRTC, along with any outputs cloned
+	/// on it, which must support `mode` too and are moved with it. An output
+	/// which is off is given the first of its CRTCs which isn't driving other
+	/// outputs, or [`ConfigStatus::Failed`] is returned if there isn't one.
+	///
+	/// The screen is resized to cover the CRTCs which are enabled, within
+	/// its [size range]; [`ConfigStatus::Failed`] is returned if it can't be
+	/// made large enough. The server is grabbed throughout, so that other
+	/// clients don't see or change the configuration in between.
+	///
+	/// Returns an [`Unsupported`] error if the X server only emulates
+	/// configuration changes, as [`set_crtc_config`] does.
+	///
+	/// [size range]: Client::get_screen_size_range
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	/// [`set_crtc_config`]: Client::set_crtc_config
+	pub async fn set_output_mode(
+		&mut self, output: u32, position: Point, mode: Option<u32>, rotation: Rotation,
+	) -> Result<ConfigStatus, ReplyError> {
+		set_output_mode(self, output, position, mode, rotation).await
+	}
+
+	/// Gets the number of entries in each of `crtc`'s gamma ramps.
+	///
+	/// This needs RandR 1.2.
+	pub async fn get_crtc_gamma_size(&mut self, crtc: u32) -> Result<u16, ReplyError> {
+		get_crtc_gamma_size(self, crtc).await
+	}
+
+	/// Gets `crtc`'s gamma ramps.
+	///
+	/// This needs RandR 1.2.
+	pub async fn get_crtc_gamma(&mut self, crtc: u32) -> Result<Gamma, ReplyError> {
+		get_crtc_gamma(self, crtc).await
+	}
+
+	/// Sets `crtc`'s gamma ramps, such as to tint its outputs for night
+	/// viewing.
+	///
+	/// Each ramp must have as many entries as
+	/// [`Client::get_crtc_gamma_size`] returns. This needs RandR 1.2.
+	pub async fn set_crtc_gamma(&mut self, crtc: u32, gamma: &Gamma) -> Result<(), ReplyError> {
+		set_crtc_gamma(self, crtc, gamma).await
+	}
+
+	/// Gets information about `output`'s `property`, such as the range of
+	/// values it can be set to.
+	///
+	/// This needs RandR 1.2.
+	pub async fn query_output_property(
+		&mut self, output: u32, property: Atom,
+	) -> Result<OutputPropertyInfo, ReplyError> {
+		query_output_property(self, output, property).await
+	}
+
+	/// Gets the value of `output`'s `property`, such as its `EDID`.
+	///
+	/// `r#type` is the type of value wanted, or [`Atom::NONE`] for any type.
+	/// If the property has a different type, its type is returned without
+	/// any data. Returns `None` if the property doesn't exist. This needs
+	/// RandR 1.2.
+	pub async fn get_output_property(
+		&mut self, output: u32, property: Atom, r#type: Atom,
+	) -> Result<Option<Property>, ReplyError> {
+		get_output_property(self, output, property, r#type).await
+	}
+
+	/// Changes the value of `output`'s `property`.
+	///
+	/// This needs RandR 1.2.
+	pub async fn set_output_property(
+		&mut self, output: u32, property: Atom, value: &Property, mode: PropertyMode,
+	) -> Result<(), ReplyError> {
+		set_output_property(self, output, property, value, mode).await
+	}
+
+	/// Gets the brightness of `output`'s backlight, as given by its
+	/// `Backlight` property.
+	///
+	/// Returns `None` if `output` doesn't have a backlight which the X
+	/// server's driver can control.
+	pub async fn get_backlight(&mut self, output: u32) -> Result<Option<Backlight>, ReplyError> {
+		get_backlight(self, output).await
+	}
+
+	/// Sets the brightness of `output`'s backlight, clamped to the range
+	/// given by [`Client::get_backlight`].
+	///
+	/// Returns whether `output` has a backlight which the X server's driver
+	/// can control.
+	pub async fn set_backlight(&mut self, output: u32, value: i32) -> Result<bool, ReplyError> {
+		set_backlight(self, output, value).await
+	}
+
+	/// Gets the providers of `window`'s screen: the GPUs which render it and
+	/// drive its outputs.
+	///
+	/// This needs RandR 1.4.
+	pub async fn get_providers(&mut self, window: Window) -> Result<Providers, ReplyError> {
+		get_providers(self, window).await
+	}
+
+	/// Gets information about `provider`.
+	///
+	/// `config_timestamp` is that of the [`ScreenResources`] of the
+	/// provider's screen. This needs RandR 1.4.
+	pub async fn get_provider_info(
+		&mut self, provider: u32, config_timestamp: Timestamp,
+	) -> Result<ProviderInfo, ReplyError> {
+		get_provider_info(self, provider, config_timestamp).await
+	}
+
+	/// Offloads `provider`'s rendering to `sink_provider`, or stops
+	/// offloading it if `sink_provider` is `None`.
+	///
+	/// This needs RandR 1.4.
+	pub async fn set_provider_offload_sink(
+		&mut self, provider: u32, sink_provider: Option<u32>, config_timestamp: Timestamp,
+	) -> Result<(), ReplyError> {
+		set_provider_offload_sink(self, provider, sink_provider, config_timestamp).await
+	}
+
+	/// Shows the screen of `source_provider` on `provider`'s outputs, or
+	/// stops showing it if `source_provider` is `None`.
+	///
+	/// This needs RandR 1.4.
+	pub async fn set_provider_output_source(
+		&mut self, provider: u32, source_provider: Option<u32>, config_timestamp: Timestamp,
+	) -> Result<(), ReplyError> {
+		set_provider_output_source(self, provider, source_provider, config_timestamp).await
+	}
+
+	/// Leases `crtcs` and `outputs` of `window`'s screen, so that they can be
+	/// driven directly through the returned [`Lease`]'s DRM device.
+	///
+	/// The X server stops using them until the lease is freed. Returns an
+	/// [`Unsupported`] error if the connection isn't over a Unix domain
+	/// socket. This needs RandR 1.6.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn create_lease(
+		&mut self, window: Window, crtcs: &[u32], outputs: &[u32],
+	) -> Result<Lease, XidError> {
+		create_lease(self, window, crtcs, outputs).await
+	}
+
+	/// Frees the lease `lease`, returning its CRTCs and outputs to the X
+	/// server.
+	///
+	/// If `terminate` is true, they are turned off. This needs RandR 1.6.
+	pub async fn free_lease(&mut self, lease: u32, terminate: bool) -> Result<(), ReplyError> {
+		free_lease(self, lease, terminate).await
+	}
+}
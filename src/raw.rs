@@ -0,0 +1,215 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Low-level access to the frames sent between X.RS and the X server.
+//!
+//! This allows requests to be sent and frames to be received for parts of the
+//! protocol which X.RS does not yet support, such as extensions without typed
+//! wrappers. Requests sent with [`Client::send_raw`] are assigned sequence
+//! numbers like any other request.
+//!
+//! With the `fds` feature, file descriptors can be passed to the X server
+//! with `Client::send_raw_with_fds` when connected over a Unix domain socket.
+//! File descriptors sent by the X server are received along with the frames
+//! they belong to: those sent with a reply to `Client::send_raw_with_fd_reply`
+//! are returned with it by `Client::wait_for_reply_with_fds`, and the rest
+//! are queued until they are claimed with `Client::take_fds`.
+
+pub use crate::client::rw::X11Frame;
+use crate::{client::rw::Error, Client, EventReader, RequestWriter};
+#[cfg(all(unix, feature = "fds"))]
+use std::os::fd::{BorrowedFd, OwnedFd};
+use tokio::io;
+pub use xrs_proto::{assert_wire_size, HexBytes, RawRequest, RequestTooLong, WireSize};
+
+/// A hook called with every [`X11Frame`] received from the X server.
+///
+/// The hook must be [`Send`] and [`Sync`] so that the [`Client`] it is set on
+/// remains [`Send`] and [`Sync`].
+///
+/// See [`Client::set_raw_frame_hook`].
+pub type RawFrameHook = Box<dyn FnMut(&X11Frame) + Send + Sync>;
+
+impl Client {
+	/// Sends a [`RawRequest`] to the X server, returning the sequence number
+	/// assigned to it.
+	///
+	/// Any reply, error, or events generated by the request must be received
+	/// with [`read_raw_frame`].
+	///
+	/// [`read_raw_frame`]: Client::read_raw_frame
+	pub async fn send_raw(&mut self, request: RawRequest) -> io::Result<u16> {
+		self.writer.send_raw(request).await
+	}
+
+	/// Queues a [`RawRequest`] to be sent to the X server, returning the
+	/// sequence number assigned to it.
+	///
+	/// Unlike [`send_raw`], which sends each request immediately, queued
+	/// requests are only sent once they fill the [flush threshold], when
+	/// [`flush`] is called, when another request is sent with [`send_raw`],
+	/// or when waiting for a reply. Queueing bursts of small requests avoids a
+	/// write to the connection for each of them.
+	///
+	/// [`send_raw`]: Client::send_raw
+	/// [flush threshold]: Client::set_flush_threshold
+	/// [`flush`]: Client::flush
+	pub async fn send_queued(&mut self, request: RawRequest) -> io::Result<u16> {
+		self.writer.send_queued(request).await
+	}
+
+	/// Sends any requests queued with [`send_queued`] to the X server.
+	///
+	/// [`send_queued`]: Client::send_queued
+	pub async fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush().await
+	}
+
+	/// Sends a [`RawRequest`] with `fds` attached, returning the sequence
+	/// number assigned to it.
+	///
+	/// The X server receives the file descriptors along with the request, and
+	/// keeps its own copies of them, so they can be closed once this returns.
+	/// Returns an [`Unsupported`] error if the connection isn't over a Unix
+	/// domain socket.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn send_raw_with_fds(
+		&mut self, request: RawRequest, fds: &[BorrowedFd<'_>],
+	) -> io::Result<u16> {
+		self.writer.send_raw_with_fds(request, fds).await
+	}
+
+	/// Returns whether file descriptors can be passed to the X server, which
+	/// is only possible when connected over a Unix domain socket.
+	#[cfg(all(unix, feature = "fds"))]
+	pub fn can_pass_fds(&self) -> bool {
+		self.writer.can_pass_fds()
+	}
+
+	/// Receives the next [`X11Frame`] from the X server.
+	///
+	/// Replies and errors for requests sent with [`send_raw_with_reply`] are
+	/// delivered to their [`ReplyCookie`]s instead of being returned here.
+	///
+	/// Requests held by batching are sent first, as the frames may be
+	/// responses to them.
+	///
+	/// Returns `None` if the connection was closed by the X server.
+	///
+	/// [`send_raw_with_reply`]: Client::send_raw_with_reply
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub async fn read_raw_frame(&mut self) -> io::Result<Option<X11Frame>> {
+		self.flush_batch().await?;
+
+		self.reader.read_raw_frame().await
+	}
+
+	/// Sets a hook to be called with every [`X11Frame`] received from the X
+	/// server, replacing any existing hook.
+	///
+	/// The hook is called before the frame is delivered, so it sees frames
+	/// that X.RS handles itself as well as those it does not understand.
+	/// Passing `None` removes the hook.
+	pub fn set_raw_frame_hook(&mut self, hook: Option<RawFrameHook>) {
+		self.reader.set_raw_frame_hook(hook);
+	}
+
+	/// Takes up to `count` of the file descriptors received from the X server
+	/// which haven't been claimed yet, oldest first.
+	///
+	/// File descriptors are received along with the frames they belong to,
+	/// so those sent with a frame returned by [`read_raw_frame`] have been
+	/// received by the time it is returned. File descriptors sent with a
+	/// reply to [`send_raw_with_fd_reply`] are claimed by that reply instead.
+	///
+	/// [`read_raw_frame`]: Client::read_raw_frame
+	/// [`send_raw_with_fd_reply`]: Client::send_raw_with_fd_reply
+	#[cfg(all(unix, feature = "fds"))]
+	pub fn take_fds(&mut self, count: usize) -> Vec<OwnedFd> {
+		self.reader.take_fds(count)
+	}
+}
+
+impl RequestWriter {
+	/// Sends a [`RawRequest`] to the X server, returning the sequence number
+	/// assigned to it.
+	///
+	/// See [`Client::send_raw`].
+	pub async fn send_raw(&mut self, request: RawRequest) -> io::Result<u16> {
+		let frame = self.request_frame(request)?;
+
+		self.write_frame(&frame).await?;
+
+		Ok(self.sequence())
+	}
+
+	/// Queues a [`RawRequest`] to be sent to the X server, returning the
+	/// sequence number assigned to it.
+	///
+	/// See [`Client::send_queued`].
+	pub async fn send_queued(&mut self, request: RawRequest) -> io::Result<u16> {
+		let frame = self.request_frame(request)?;
+
+		self.queue_frame(&frame).await?;
+
+		Ok(self.sequence())
+	}
+
+	/// Sends a [`RawRequest`] with `fds` attached, returning the sequence
+	/// number assigned to it.
+	///
+	/// See [`Client::send_raw_with_fds`].
+	#[cfg(all(unix, feature = "fds"))]
+	pub async fn send_raw_with_fds(
+		&mut self, request: RawRequest, fds: &[BorrowedFd<'_>],
+	) -> io::Result<u16> {
+		let frame = self.request_frame(request)?;
+
+		self.write_frame_with_fds(&frame, fds).await?;
+
+		Ok(self.sequence())
+	}
+
+	/// Sends any queued requests to the X server.
+	///
+	/// See [`Client::flush`].
+	pub async fn flush(&mut self) -> io::Result<()> {
+		self.flush_frames().await
+	}
+}
+
+impl EventReader {
+	/// Receives the next [`X11Frame`] from the X server.
+	///
+	/// See [`Client::read_raw_frame`].
+	pub async fn read_raw_frame(&mut self) -> io::Result<Option<X11Frame>> {
+		match self.read_frame().await {
+			Ok(frame) => Ok(frame),
+
+			Err(Error::Io(error)) => Err(error),
+			Err(Error::Incomplete) => Err(io::ErrorKind::UnexpectedEof.into()),
+		}
+	}
+
+	/// Sets a hook to be called with every [`X11Frame`] received from the X
+	/// server, replacing any existing hook.
+	///
+	/// See [`Client::set_raw_frame_hook`].
+	pub fn set_raw_frame_hook(&mut self, hook: Option<RawFrameHook>) {
+		self.frame_hook = hook;
+	}
+
+	/// Takes up to `count` of the file descriptors received from the X server
+	/// which haven't been claimed yet, oldest first.
+	///
+	/// See [`Client::take_fds`].
+	#[cfg(all(unix, feature = "fds"))]
+	pub fn take_fds(&mut self, count: usize) -> Vec<OwnedFd> {
+		let count = count.min(self.fds.len());
+
+		self.fds.drain(..count).collect()
+	}
+}
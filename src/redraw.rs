@@ -0,0 +1,210 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Damage-limited redrawing driven by `Expose` events.
+//!
+//! The X server reports exposed areas of a window as a series of `Expose`
+//! events, one rectangle at a time. A [`RedrawCoordinator`] collects those
+//! rectangles until the series is complete, then, once per frame, sets the
+//! window's clip rectangles to the exposed area and calls the draw callback
+//! once with the merged region, rather than redrawing once per event.
+
+#[cfg(feature = "render")]
+use crate::render::{Picture, SetPictureClipRectangles};
+use crate::{
+	gc::{ClipOrdering, SetClipRectangles},
+	geometry::{Point, Rectangle, Size},
+	raw::{RawRequest, X11Frame},
+	region::Region,
+	request::Request,
+	RequestWriter,
+};
+use std::collections::HashMap;
+use tokio::io;
+
+/// The event code of `Expose` events.
+const EXPOSE: u8 = 12;
+/// The event code of `GraphicsExpose` events.
+const GRAPHICS_EXPOSE: u8 = 13;
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// What the clip rectangles of a window are set on before it is redrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClipTarget {
+	/// A graphics context, clipped with `SetClipRectangles`.
+	Gc(u32),
+
+	/// A RENDER picture, clipped with `SetPictureClipRectangles`.
+	#[cfg(feature = "render")]
+	Picture {
+		/// The major opcode of the RENDER extension.
+		render_opcode: u8,
+		/// The picture to clip.
+		picture: u32,
+	},
+}
+
+impl ClipTarget {
+	/// Returns the request which sets this target's clip rectangles to
+	/// `region`.
+	fn request(&self, region: &Region) -> RawRequest {
+		let rectangles: Vec<Rectangle> = region.rectangles().collect();
+
+		match *self {
+			// A region's rectangles are sorted and banded.
+			Self::Gc(gc) => SetClipRectangles {
+				ordering: ClipOrdering::YXBanded,
+				gc,
+				origin: Point::ORIGIN,
+				rectangles: &rectangles,
+			}
+			.to_raw(0),
+
+			#[cfg(feature = "render")]
+			Self::Picture {
+				render_opcode,
+				picture,
+			} => SetPictureClipRectangles {
+				picture: Picture(picture),
+				origin: Point::ORIGIN,
+				rectangles: &rectangles,
+			}
+			.to_raw(render_opcode),
+		}
+	}
+}
+
+/// An exposed area of a window which needs to be redrawn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Redraw {
+	/// The window (or other drawable, for `GraphicsExpose` events) to redraw.
+	pub window: u32,
+	/// The exposed area of the window which needs to be redrawn.
+	pub region: Region,
+}
+
+/// Collects `Expose` events and coordinates damage-limited redraws.
+///
+/// Feed received frames to [`observe`], then call [`redraw`] once per frame.
+///
+/// [`observe`]: RedrawCoordinator::observe
+/// [`redraw`]: RedrawCoordinator::redraw
+#[derive(Debug, Clone, Default)]
+pub struct RedrawCoordinator {
+	/// The exposed areas of windows whose series of `Expose` events is not
+	/// yet complete.
+	pending: HashMap<u32, Region>,
+	/// The exposed areas of windows which are ready to be redrawn.
+	ready: HashMap<u32, Region>,
+
+	/// What each window's clip rectangles are set on.
+	targets: HashMap<u32, ClipTarget>,
+}
+
+impl RedrawCoordinator {
+	/// Creates a new `RedrawCoordinator` with nothing to redraw.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets what `window`'s clip rectangles are set on before it is redrawn.
+	///
+	/// If a window has no clip target, its draw callback is still called with
+	/// the exposed region, but no clip rectangles are set.
+	pub fn set_clip_target(&mut self, window: u32, target: Option<ClipTarget>) {
+		match target {
+			Some(target) => self.targets.insert(window, target),
+			None => self.targets.remove(&window),
+		};
+	}
+
+	/// Forgets `window`, for example once it has been destroyed.
+	pub fn forget(&mut self, window: u32) {
+		self.pending.remove(&window);
+		self.ready.remove(&window);
+		self.targets.remove(&window);
+	}
+
+	/// Records the exposed area from `frame` if it is an `Expose` or
+	/// `GraphicsExpose` event.
+	///
+	/// Returns `true` if `frame` completed a series of exposures, meaning
+	/// there is something to [`redraw`].
+	///
+	/// [`redraw`]: RedrawCoordinator::redraw
+	pub fn observe(&mut self, frame: &X11Frame) -> bool {
+		let X11Frame::Event { code, chunk } = frame else {
+			return false;
+		};
+
+		let u16_at = |index: usize| u16::from_be_bytes([chunk[index], chunk[index + 1]]);
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let count = match code & !SEND_EVENT_MASK {
+			EXPOSE => u16_at(15),
+			GRAPHICS_EXPOSE => u16_at(17),
+
+			_ => return false,
+		};
+
+		let window = u32::from_be_bytes([chunk[3], chunk[4], chunk[5], chunk[6]]);
+		// The position of an exposed rectangle is unsigned, so it may be
+		// beyond the range of a coordinate.
+		let rectangle = Rectangle::new(
+			Point::clamped(u16_at(7).into(), u16_at(9).into()),
+			Size::new(u16_at(11), u16_at(13)),
+		);
+
+		self.pending.entry(window).or_default().add(rectangle);
+
+		// `count` is the number of `Expose` events which are still to follow
+		// for this window.
+		if count != 0 {
+			return false;
+		}
+
+		if let Some(region) = self.pending.remove(&window) {
+			let ready = self.ready.entry(window).or_default();
+			*ready = ready.union(&region);
+		}
+
+		true
+	}
+
+	/// Whether any windows are ready to be redrawn.
+	pub fn needs_redraw(&self) -> bool {
+		!self.ready.is_empty()
+	}
+
+	/// Takes the exposed regions which are ready to be redrawn.
+	pub fn take(&mut self) -> Vec<Redraw> {
+		self.ready
+			.drain()
+			.map(|(window, region)| Redraw { window, region })
+			.collect()
+	}
+
+	/// Redraws every window with a completed series of exposures.
+	///
+	/// For each window, its clip target (if any) is clipped to the exposed
+	/// region, then `draw` is called once with that region.
+	pub async fn redraw<F>(&mut self, writer: &mut RequestWriter, mut draw: F) -> io::Result<()>
+	where
+		F: AsyncFnMut(&mut RequestWriter, &Redraw) -> io::Result<()>,
+	{
+		for redraw in self.take() {
+			if let Some(target) = self.targets.get(&redraw.window) {
+				writer.send_raw(target.request(&redraw.region)).await?;
+			}
+
+			draw(writer, &redraw).await?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,586 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Regions made of rectangles.
+//!
+//! A [`Region`] is an arbitrary area described by rectangles, supporting
+//! [union], [intersection] and [subtraction]. Regions are stored as
+//! y-x banded rectangles, the same representation the X server uses: the
+//! region is split into horizontal bands, and each band into sorted,
+//! non-overlapping spans. Every area has exactly one such representation, so
+//! two regions are equal if and only if they cover the same area.
+//!
+//! Regions are also how areas are given to and read from the SHAPE and
+//! Damage extensions; the latter stores them on the X server as XFIXES
+//! regions, which are created and read back as needed.
+//!
+//! [union]: Region::union
+//! [intersection]: Region::intersect
+//! [subtraction]: Region::subtract
+
+use crate::{
+	client::RoundTrip,
+	geometry::{Point, Rectangle},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	xfixes::{self, XFIXES},
+	xid::{self, XidError},
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The minor opcode of the `XFixesCreateRegion` request.
+const CREATE_REGION: u8 = 5;
+/// The minor opcode of the `XFixesDestroyRegion` request.
+const DESTROY_REGION: u8 = 10;
+/// The minor opcode of the `XFixesFetchRegion` request.
+const FETCH_REGION: u8 = 19;
+
+/// The version of XFIXES which added regions.
+#[cfg_attr(not(feature = "damage"), allow(dead_code))]
+const REGIONS_VERSION: (u32, u32) = (2, 0);
+
+/// A half-open horizontal range of coordinates, `left..right`.
+type Span = (i32, i32);
+
+/// A horizontal band of a [`Region`], covering the rows `top..bottom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Band {
+	top: i32,
+	bottom: i32,
+
+	/// The spans covered within this band, sorted, non-empty, and neither
+	/// overlapping nor touching.
+	spans: Vec<Span>,
+}
+
+/// An area made of rectangles.
+///
+/// See the [module documentation](self) for how regions are represented.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Region {
+	/// The bands of this region, sorted from top to bottom.
+	///
+	/// Bands do not overlap, and two touching bands never have the same spans
+	/// (they would be a single band).
+	bands: Vec<Band>,
+}
+
+impl From<Rectangle> for Region {
+	fn from(rectangle: Rectangle) -> Self {
+		if rectangle.is_empty() {
+			return Self::new();
+		}
+
+		Self {
+			bands: vec![Band {
+				top: rectangle.top(),
+				bottom: rectangle.bottom(),
+
+				spans: vec![(rectangle.left(), rectangle.right())],
+			}],
+		}
+	}
+}
+
+/// Collects the union of every rectangle at once, rather than adding them
+/// one at a time.
+impl FromIterator<Rectangle> for Region {
+	fn from_iter<T: IntoIterator<Item = Rectangle>>(rectangles: T) -> Self {
+		// Each rectangle's top, bottom and span, sorted by their tops.
+		let mut rectangles: Vec<(i32, i32, Span)> = rectangles
+			.into_iter()
+			.filter(|rectangle| !rectangle.is_empty())
+			.map(|rectangle| {
+				let span = (rectangle.left(), rectangle.right());
+
+				(rectangle.top(), rectangle.bottom(), span)
+			})
+			.collect();
+		rectangles.sort_unstable_by_key(|&(top, ..)| top);
+
+		let mut edges: Vec<i32> = rectangles
+			.iter()
+			.flat_map(|&(top, bottom, _)| [top, bottom])
+			.collect();
+		edges.sort_unstable();
+		edges.dedup();
+
+		let mut bands: Vec<Band> = Vec::new();
+		let mut rectangles = rectangles.into_iter().peekable();
+		// The bottoms and spans of the rectangles covering the current rows.
+		let mut covering: Vec<(i32, Span)> = Vec::new();
+
+		// Every edge of every rectangle is in `edges`, so each rectangle covers
+		// either all or none of each of these rows.
+		for rows in edges.windows(2) {
+			let (top, bottom) = (rows[0], rows[1]);
+
+			covering.retain(|&(covered_bottom, _)| covered_bottom > top);
+			while let Some((_, covered_bottom, span)) =
+				rectangles.next_if(|&(covered_top, ..)| covered_top <= top)
+			{
+				covering.push((covered_bottom, span));
+			}
+
+			let mut covered: Vec<Span> = covering.iter().map(|&(_, span)| span).collect();
+			covered.sort_unstable();
+
+			let mut spans: Vec<Span> = Vec::new();
+
+			for (left, right) in covered {
+				match spans.last_mut() {
+					Some(last) if last.1 >= left => last.1 = last.1.max(right),
+					_ => spans.push((left, right)),
+				}
+			}
+
+			push_band(&mut bands, top, bottom, spans);
+		}
+
+		Self { bands }
+	}
+}
+
+impl Region {
+	/// Creates a new, empty `Region`.
+	pub const fn new() -> Self {
+		Self { bands: Vec::new() }
+	}
+
+	/// Whether this region covers no area.
+	pub fn is_empty(&self) -> bool {
+		self.bands.is_empty()
+	}
+
+	/// The smallest rectangle containing this whole region, or `None` if the
+	/// region is empty.
+	pub fn extents(&self) -> Option<Rectangle> {
+		let top = self.bands.first()?.top;
+		let bottom = self.bands.last()?.bottom;
+
+		let left = self.bands.iter().map(|band| band.spans[0].0).min()?;
+		let right = self
+			.bands
+			.iter()
+			.filter_map(|band| band.spans.last())
+			.map(|span| span.1)
+			.max()?;
+
+		Some(Rectangle::from_edges_clamped(left, top, right, bottom))
+	}
+
+	/// Whether `point` lies within this region.
+	pub fn contains(&self, point: Point) -> bool {
+		let (x, y) = (i32::from(point.x), i32::from(point.y));
+
+		self.bands
+			.iter()
+			.find(|band| band.top <= y && y < band.bottom)
+			.is_some_and(|band| {
+				band.spans
+					.iter()
+					.any(|&(left, right)| left <= x && x < right)
+			})
+	}
+
+	/// The rectangles making up this region.
+	///
+	/// The rectangles are y-x banded: sorted by their top edge then their
+	/// left edge, with rectangles in the same band sharing their top and
+	/// bottom edges. This is the `YXBanded` ordering of clip rectangles in the
+	/// protocol.
+	pub fn rectangles(&self) -> impl Iterator<Item = Rectangle> + '_ {
+		self.bands.iter().flat_map(|band| {
+			band.spans.iter().map(|&(left, right)| {
+				Rectangle::from_edges_clamped(left, band.top, right, band.bottom)
+			})
+		})
+	}
+
+	/// Adds `rectangle` to this region.
+	pub fn add(&mut self, rectangle: Rectangle) {
+		*self = self.union(&rectangle.into());
+	}
+
+	/// Returns the area covered by either this region or `other`.
+	pub fn union(&self, other: &Self) -> Self {
+		self.combine(other, |a, b| a || b)
+	}
+
+	/// Returns the area covered by both this region and `other`.
+	pub fn intersect(&self, other: &Self) -> Self {
+		self.combine(other, |a, b| a && b)
+	}
+
+	/// Returns the area covered by this region but not by `other`.
+	pub fn subtract(&self, other: &Self) -> Self {
+		self.combine(other, |a, b| a && !b)
+	}
+
+	/// Returns the area covered by the points for which `op` returns `true`,
+	/// given whether each point is in this region and whether it is in
+	/// `other`.
+	fn combine(&self, other: &Self, op: impl Fn(bool, bool) -> bool) -> Self {
+		let mut edges: Vec<i32> = self
+			.bands
+			.iter()
+			.chain(&other.bands)
+			.flat_map(|band| [band.top, band.bottom])
+			.collect();
+		edges.sort_unstable();
+		edges.dedup();
+
+		let mut bands: Vec<Band> = Vec::new();
+		let (mut a, mut b) = (self.bands.iter().peekable(), other.bands.iter().peekable());
+
+		// Every band edge of either region is in `edges`, so each of these
+		// rows lies entirely within at most one band of each region.
+		for rows in edges.windows(2) {
+			let (top, bottom) = (rows[0], rows[1]);
+
+			let a_spans = spans_at(&mut a, top);
+			let b_spans = spans_at(&mut b, top);
+
+			push_band(
+				&mut bands,
+				top,
+				bottom,
+				combine_spans(a_spans, b_spans, &op),
+			);
+		}
+
+		Self { bands }
+	}
+}
+
+/// Adds the band of `spans` covering the rows `top..bottom` below `bands`,
+/// joining it to the last band if that band touches it and has the same
+/// spans.
+///
+/// Nothing is added if `spans` is empty.
+fn push_band(bands: &mut Vec<Band>, top: i32, bottom: i32, spans: Vec<Span>) {
+	if spans.is_empty() {
+		return;
+	}
+
+	match bands.last_mut() {
+		Some(last) if last.bottom == top && last.spans == spans => last.bottom = bottom,
+		_ => bands.push(Band { top, bottom, spans }),
+	}
+}
+
+/// Advances `bands` past those above `top` and returns the spans of the band
+/// covering the row `top`, if any.
+fn spans_at<'a>(
+	bands: &mut std::iter::Peekable<std::slice::Iter<'a, Band>>, top: i32,
+) -> &'a [Span] {
+	while bands.next_if(|band| band.bottom <= top).is_some() {}
+
+	match bands.peek() {
+		Some(band) if band.top <= top => &band.spans,
+		_ => &[],
+	}
+}
+
+/// Returns the spans covered by the coordinates for which `op` returns
+/// `true`, given whether each coordinate is in `a` and whether it is in `b`.
+fn combine_spans(a: &[Span], b: &[Span], op: impl Fn(bool, bool) -> bool) -> Vec<Span> {
+	let mut edges: Vec<i32> = a
+		.iter()
+		.chain(b)
+		.flat_map(|&(left, right)| [left, right])
+		.collect();
+	edges.sort_unstable();
+	edges.dedup();
+
+	let mut spans: Vec<Span> = Vec::new();
+	let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+
+	for columns in edges.windows(2) {
+		let (left, right) = (columns[0], columns[1]);
+
+		while a.next_if(|span| span.1 <= left).is_some() {}
+		while b.next_if(|span| span.1 <= left).is_some() {}
+
+		let in_a = a.peek().is_some_and(|span| span.0 <= left);
+		let in_b = b.peek().is_some_and(|span| span.0 <= left);
+
+		if !op(in_a, in_b) {
+			continue;
+		}
+
+		match spans.last_mut() {
+			Some(last) if last.1 == left => last.1 = right,
+			_ => spans.push((left, right)),
+		}
+	}
+
+	spans
+}
+
+/// The XFIXES extension's `XFixesCreateRegion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateRegion<'a> {
+	/// The ID of the new XFIXES region.
+	pub id: u32,
+	pub region: &'a Region,
+}
+
+impl Request for CreateRegion<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XFixesCreateRegion";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = CREATE_REGION;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.id);
+
+		for rectangle in self.region.rectangles() {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+// Followed by the rectangles.
+assert_wire_size!("XFixesCreateRegion request": [u8, u8, u16, u32] == 8);
+
+/// The XFIXES extension's `XFixesDestroyRegion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DestroyRegion(pub u32);
+
+impl Request for DestroyRegion {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XFixesDestroyRegion";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = DESTROY_REGION;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(DestroyRegion: [u8, u8, u16, u32]);
+
+/// The XFIXES extension's `XFixesFetchRegion` request, whose reply is the
+/// area covered by an XFIXES region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FetchRegion(pub u32);
+
+impl Request for FetchRegion {
+	type Reply = Region;
+
+	const NAME: &'static str = "XFixesFetchRegion";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = FETCH_REGION;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(FetchRegion: [u8, u8, u16, u32]);
+
+// Followed by the rectangles.
+assert_wire_size!("XFixesFetchRegion reply": [u8, u8, u16, u32, Rectangle, [u8; 16]] == 32);
+
+/// Both the `XFixesFetchRegion` and `ShapeGetRectangles` replies are a
+/// 32-byte header followed by the region's rectangles.
+impl Reply for Region {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		Some(read_rectangles(chunk.get(24..)?))
+	}
+}
+
+/// Reads the region covered by a list of rectangles.
+fn read_rectangles(data: &[u8]) -> Region {
+	data.chunks_exact(8)
+		.map(|rectangle| {
+			let u16_at =
+				|offset: usize| u16::from_be_bytes([rectangle[offset], rectangle[offset + 1]]);
+
+			Rectangle {
+				x: u16_at(0) as i16,
+				y: u16_at(2) as i16,
+				width: u16_at(4),
+				height: u16_at(6),
+			}
+		})
+		.collect()
+}
+
+/// Creates an XFIXES region covering `region`, returning its ID.
+#[cfg_attr(not(feature = "damage"), allow(dead_code))]
+pub(crate) async fn create_xfixes_region(
+	connection: &mut impl RoundTrip, region: &Region,
+) -> Result<u32, XidError> {
+	xfixes::require_xfixes(connection, REGIONS_VERSION).await?;
+	let id = xid::generate_id(connection).await?;
+
+	request::send(connection, &CreateRegion { id, region }).await?;
+
+	Ok(id)
+}
+
+/// Reads the area covered by the XFIXES region `id`.
+#[cfg_attr(not(feature = "damage"), allow(dead_code))]
+pub(crate) async fn fetch_xfixes_region(
+	connection: &mut impl RoundTrip, id: u32,
+) -> Result<Region, ReplyError> {
+	request::reply_to(connection, &FetchRegion(id)).await
+}
+
+/// Destroys the XFIXES region `id`.
+#[cfg_attr(not(feature = "damage"), allow(dead_code))]
+pub(crate) async fn destroy_xfixes_region(
+	connection: &mut impl RoundTrip, id: u32,
+) -> Result<(), ReplyError> {
+	request::send(connection, &DestroyRegion(id)).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rectangle(x: i16, y: i16, width: u16, height: u16) -> Rectangle {
+		Rectangle {
+			x,
+			y,
+			width,
+			height,
+		}
+	}
+
+	fn rectangles(region: &Region) -> Vec<Rectangle> {
+		region.rectangles().collect()
+	}
+
+	/// Two 10x10 squares overlapping in a 5x5 square.
+	fn overlapping() -> (Region, Region) {
+		(
+			rectangle(0, 0, 10, 10).into(),
+			rectangle(5, 5, 10, 10).into(),
+		)
+	}
+
+	/// Two 10x5 rectangles, one directly above the other.
+	fn adjacent() -> (Region, Region) {
+		(rectangle(0, 0, 10, 5).into(), rectangle(0, 5, 10, 5).into())
+	}
+
+	#[test]
+	fn union_with_empty() {
+		let (a, _) = overlapping();
+
+		assert_eq!(a.union(&Region::new()), a);
+		assert_eq!(Region::new().union(&a), a);
+		assert!(Region::new().union(&Region::new()).is_empty());
+	}
+
+	#[test]
+	fn union_of_overlapping() {
+		let (a, b) = overlapping();
+
+		assert_eq!(
+			rectangles(&a.union(&b)),
+			[
+				rectangle(0, 0, 10, 5),
+				rectangle(0, 5, 15, 5),
+				rectangle(5, 10, 10, 5),
+			]
+		);
+	}
+
+	#[test]
+	fn union_of_adjacent_bands_is_joined() {
+		let (a, b) = adjacent();
+		assert_eq!(rectangles(&a.union(&b)), [rectangle(0, 0, 10, 10)]);
+
+		let left = Region::from(rectangle(0, 0, 5, 10));
+		let right = Region::from(rectangle(5, 0, 5, 10));
+		assert_eq!(rectangles(&left.union(&right)), [rectangle(0, 0, 10, 10)]);
+	}
+
+	#[test]
+	fn intersect_with_empty() {
+		let (a, _) = overlapping();
+
+		assert!(a.intersect(&Region::new()).is_empty());
+		assert!(Region::new().intersect(&a).is_empty());
+	}
+
+	#[test]
+	fn intersect_of_overlapping() {
+		let (a, b) = overlapping();
+
+		assert_eq!(rectangles(&a.intersect(&b)), [rectangle(5, 5, 5, 5)]);
+	}
+
+	#[test]
+	fn intersect_of_adjacent_is_empty() {
+		let (a, b) = adjacent();
+
+		assert!(a.intersect(&b).is_empty());
+	}
+
+	#[test]
+	fn subtract_empty() {
+		let (a, _) = overlapping();
+
+		assert_eq!(a.subtract(&Region::new()), a);
+		assert!(Region::new().subtract(&a).is_empty());
+		assert!(a.subtract(&a).is_empty());
+	}
+
+	#[test]
+	fn subtract_overlapping() {
+		let (a, b) = overlapping();
+
+		assert_eq!(
+			rectangles(&a.subtract(&b)),
+			[rectangle(0, 0, 10, 5), rectangle(0, 5, 5, 5)]
+		);
+	}
+
+	#[test]
+	fn subtract_adjacent() {
+		let (a, b) = adjacent();
+
+		assert_eq!(a.subtract(&b), a);
+		assert_eq!(
+			rectangles(&a.union(&b).subtract(&b)),
+			[rectangle(0, 0, 10, 5)]
+		);
+	}
+
+	#[test]
+	fn collecting_is_adding_each_rectangle() {
+		let all = [
+			rectangle(0, 0, 10, 10),
+			rectangle(5, 5, 10, 10),
+			rectangle(0, 10, 5, 5),
+			rectangle(20, 0, 0, 5),
+			rectangle(15, 0, 5, 20),
+			rectangle(-5, 2, 30, 1),
+		];
+
+		let mut added = Region::new();
+		for rectangle in all {
+			added.add(rectangle);
+		}
+
+		assert_eq!(all.into_iter().collect::<Region>(), added);
+		assert!(std::iter::empty::<Rectangle>()
+			.collect::<Region>()
+			.is_empty());
+	}
+}
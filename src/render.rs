@@ -0,0 +1,1914 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The RENDER extension, for antialiased and alpha-blended drawing.
+//!
+//! The core protocol draws with pixel values through raster operations, so
+//! it can't blend translucent colors or draw smooth edges. RENDER instead
+//! composites [`Picture`]s, which pair a drawable with a [`PictFormat`]
+//! describing how its pixels hold color and alpha, using Porter-Duff
+//! [`Operator`]s:
+//!
+//! ```no_run
+//! # use xrs::{draw::Drawable, geometry::{Point, Rectangle, Size}, render::*, Client};
+//! # async fn example(client: &mut Client, window: xrs::window::Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let formats = client.query_pict_formats().await?;
+//! let visual = client.setup().screens[0].root_visual;
+//! let format = formats.format_for_visual(visual).ok_or("no format for the root visual")?;
+//!
+//! let picture = client
+//!     .create_picture(Drawable::from(window), format.id, &PictureValues::new())
+//!     .await?;
+//!
+//! // A translucent red rectangle, blended with what is already drawn.
+//! let red = Color::new(0x8000, 0, 0, 0x8000);
+//! let area = Rectangle::new(Point::new(10, 10), Size::new(80, 40));
+//! client.fill_rectangles(Operator::Over, picture, red, &[area]).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Colors are premultiplied by their alpha, as above. Besides drawables,
+//! pictures can be [solid fills] and [linear] or [radial] gradients, which
+//! are used as the source of a [composite]. Text is drawn by uploading glyph
+//! images to a [`GlyphSet`] and compositing runs of them.
+//!
+//! [solid fills]: Client::create_solid_fill
+//! [linear]: Client::create_linear_gradient
+//! [radial]: Client::create_radial_gradient
+//! [composite]: Client::composite
+
+use crate::{
+	client::RoundTrip,
+	draw::Drawable,
+	extension,
+	gc::SubwindowMode,
+	geometry::{Point, Rectangle},
+	raw::{assert_wire_size, WireSize, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	window,
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the RENDER extension.
+pub(crate) const RENDER: &str = "RENDER";
+
+/// The minor opcode of the `RenderQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `RenderQueryPictFormats` request.
+const QUERY_PICT_FORMATS: u8 = 1;
+/// The minor opcode of the `RenderCreatePicture` request.
+const CREATE_PICTURE: u8 = 4;
+/// The minor opcode of the `RenderChangePicture` request.
+const CHANGE_PICTURE: u8 = 5;
+/// The minor opcode of the `RenderSetPictureClipRectangles` request.
+const SET_PICTURE_CLIP_RECTANGLES: u8 = 6;
+/// The minor opcode of the `RenderFreePicture` request.
+const FREE_PICTURE: u8 = 7;
+/// The minor opcode of the `RenderComposite` request.
+const COMPOSITE: u8 = 8;
+/// The minor opcode of the `RenderTrapezoids` request.
+const TRAPEZOIDS: u8 = 10;
+/// The minor opcode of the `RenderCreateGlyphSet` request.
+const CREATE_GLYPH_SET: u8 = 17;
+/// The minor opcode of the `RenderFreeGlyphSet` request.
+const FREE_GLYPH_SET: u8 = 19;
+/// The minor opcode of the `RenderAddGlyphs` request.
+const ADD_GLYPHS: u8 = 20;
+/// The minor opcode of the `RenderCompositeGlyphs32` request.
+const COMPOSITE_GLYPHS_32: u8 = 25;
+/// The minor opcode of the `RenderFillRectangles` request.
+const FILL_RECTANGLES: u8 = 26;
+/// The minor opcode of the `RenderCreateSolidFill` request.
+const CREATE_SOLID_FILL: u8 = 33;
+/// The minor opcode of the `RenderCreateCursor` request.
+const CREATE_CURSOR: u8 = 27;
+/// The minor opcode of the `RenderCreateAnimCursor` request.
+const CREATE_ANIM_CURSOR: u8 = 31;
+/// The minor opcode of the `RenderCreateLinearGradient` request.
+const CREATE_LINEAR_GRADIENT: u8 = 34;
+/// The minor opcode of the `RenderCreateRadialGradient` request.
+const CREATE_RADIAL_GRADIENT: u8 = 35;
+
+/// The version of RENDER requested by this client.
+const VERSION: (u32, u32) = (0, 11);
+
+/// The most glyphs in a single glyph item of a `RenderCompositeGlyphs32`
+/// request; a length of 255 means the item changes the glyph set instead.
+const MAX_GLYPHS_PER_ITEM: usize = 254;
+
+/// The number of values a picture has.
+const VALUE_COUNT: usize = 13;
+
+/// A handle to a picture: a drawable, solid fill or gradient which can be
+/// composited.
+///
+/// Like [`Drawable`], a `Picture` is only an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Picture(pub u32);
+
+impl Picture {
+	/// The picture's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+}
+
+impl WireSize for Picture {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+/// A handle to a set of glyph images, for drawing text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphSet(pub u32);
+
+impl GlyphSet {
+	/// The glyph set's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+}
+
+impl WireSize for GlyphSet {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+/// A fixed-point number with 16 integer bits and 16 fractional bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Fixed(pub i32);
+
+impl Fixed {
+	pub const ZERO: Self = Self(0);
+	pub const ONE: Self = Self(1 << 16);
+
+	/// Converts an integer to a `Fixed`.
+	pub const fn from_integer(value: i16) -> Self {
+		Self((value as i32) << 16)
+	}
+
+	/// Converts a floating-point number to the nearest `Fixed`, saturating
+	/// values out of range.
+	pub fn from_f64(value: f64) -> Self {
+		Self((value * 65536.0).round() as i32)
+	}
+
+	/// Converts the `Fixed` to a floating-point number.
+	pub fn to_f64(self) -> f64 {
+		f64::from(self.0) / 65536.0
+	}
+}
+
+impl WireSize for Fixed {
+	const WIRE_SIZE: usize = i32::WIRE_SIZE;
+}
+
+/// A point with [`Fixed`] coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FixedPoint {
+	pub x: Fixed,
+	pub y: Fixed,
+}
+
+impl FixedPoint {
+	pub const fn new(x: Fixed, y: Fixed) -> Self {
+		Self { x, y }
+	}
+
+	fn write_to(&self, buf: &mut impl BufMut) {
+		buf.put_i32(self.x.0);
+		buf.put_i32(self.y.0);
+	}
+}
+
+impl WireSize for FixedPoint {
+	const WIRE_SIZE: usize = 2 * Fixed::WIRE_SIZE;
+}
+
+impl From<Point> for FixedPoint {
+	fn from(point: Point) -> Self {
+		Self::new(Fixed::from_integer(point.x), Fixed::from_integer(point.y))
+	}
+}
+
+/// A line through two [`FixedPoint`]s, bounding a [`Trapezoid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FixedLine {
+	pub start: FixedPoint,
+	pub end: FixedPoint,
+}
+
+/// A trapezoid with horizontal top and bottom edges.
+///
+/// The left and right edges are the parts of `left` and `right` between
+/// `top` and `bottom`; the lines may extend beyond them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Trapezoid {
+	pub top: Fixed,
+	pub bottom: Fixed,
+
+	pub left: FixedLine,
+	pub right: FixedLine,
+}
+
+impl Trapezoid {
+	fn write_to(&self, buf: &mut impl BufMut) {
+		buf.put_i32(self.top.0);
+		buf.put_i32(self.bottom.0);
+
+		for line in [self.left, self.right] {
+			line.start.write_to(buf);
+			line.end.write_to(buf);
+		}
+	}
+}
+
+/// A color with 16-bit channels, premultiplied by its alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+	pub red: u16,
+	pub green: u16,
+	pub blue: u16,
+	pub alpha: u16,
+}
+
+impl Color {
+	pub const TRANSPARENT: Self = Self::new(0, 0, 0, 0);
+	pub const BLACK: Self = Self::new(0, 0, 0, u16::MAX);
+	pub const WHITE: Self = Self::new(u16::MAX, u16::MAX, u16::MAX, u16::MAX);
+
+	/// Creates a color from channels which are already premultiplied by
+	/// `alpha`.
+	pub const fn new(red: u16, green: u16, blue: u16, alpha: u16) -> Self {
+		Self {
+			red,
+			green,
+			blue,
+			alpha,
+		}
+	}
+
+	/// Creates a color from 8-bit channels which aren't premultiplied, such
+	/// as those of CSS colors.
+	pub const fn from_rgba8(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+		// Scales an 8-bit channel to 16 bits, premultiplied by `alpha`.
+		const fn scale(channel: u8, alpha: u8) -> u16 {
+			(channel as u32 * alpha as u32 * 0x101 / 0xff) as u16
+		}
+
+		Self::new(
+			scale(red, alpha),
+			scale(green, alpha),
+			scale(blue, alpha),
+			alpha as u16 * 0x101,
+		)
+	}
+
+	fn write_to(&self, buf: &mut impl BufMut) {
+		buf.put_u16(self.red);
+		buf.put_u16(self.green);
+		buf.put_u16(self.blue);
+		buf.put_u16(self.alpha);
+	}
+}
+
+impl WireSize for Color {
+	const WIRE_SIZE: usize = 4 * u16::WIRE_SIZE;
+}
+
+/// A color at a point along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GradientStop {
+	/// How far along the gradient the stop is, from 0 to 1.
+	pub offset: Fixed,
+	pub color: Color,
+}
+
+/// How source, mask and destination pixels are combined.
+///
+/// The Porter-Duff operators are described in terms of the source and
+/// destination shapes; the blend modes are those of PDF, and need RENDER
+/// 0.11.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+	Clear = 0,
+	Source = 1,
+	Destination = 2,
+	/// The source is drawn over the destination: the usual alpha blending.
+	Over = 3,
+	OverReverse = 4,
+	In = 5,
+	InReverse = 6,
+	Out = 7,
+	OutReverse = 8,
+	Atop = 9,
+	AtopReverse = 10,
+	Xor = 11,
+	Add = 12,
+	Saturate = 13,
+
+	Multiply = 0x30,
+	Screen = 0x31,
+	Overlay = 0x32,
+	Darken = 0x33,
+	Lighten = 0x34,
+	ColorDodge = 0x35,
+	ColorBurn = 0x36,
+	HardLight = 0x37,
+	SoftLight = 0x38,
+	Difference = 0x39,
+	Exclusion = 0x3a,
+	HslHue = 0x3b,
+	HslSaturation = 0x3c,
+	HslColor = 0x3d,
+	HslLuminosity = 0x3e,
+}
+
+/// How a picture is drawn outside of its drawable's bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Repeat {
+	/// The picture is transparent outside of its bounds.
+	#[default]
+	None,
+	/// The picture is tiled.
+	Normal,
+	/// The nearest edge pixel is repeated.
+	Pad,
+	/// The picture is tiled, reflected in alternate tiles.
+	Reflect,
+}
+
+/// How the edges of trapezoids and other shapes are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PolyEdge {
+	/// Edges are aliased.
+	Sharp,
+	/// Edges are antialiased.
+	#[default]
+	Smooth,
+}
+
+/// How precisely shapes are rasterized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PolyMode {
+	/// Shapes are rasterized exactly as the specification describes.
+	#[default]
+	Precise,
+	/// Shapes may be rasterized approximately, if it is faster.
+	Imprecise,
+}
+
+/// Values which may be given when creating or changing a [`Picture`].
+///
+/// Values which are `None` are left unchanged, or given their default values
+/// when creating a picture. Each value has a setter of the same name, so that
+/// they can be chained:
+///
+/// ```
+/// # use xrs::render::{PictureValues, Repeat};
+/// let values = PictureValues::new().repeat(Repeat::Normal).component_alpha(true);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PictureValues {
+	/// How the picture is drawn outside of its bounds.
+	pub repeat: Option<Repeat>,
+	/// A picture whose alpha channel is used in place of this picture's, or
+	/// `0` (`None`) for the picture's own.
+	pub alpha_map: Option<u32>,
+	/// The x coordinate of the origin of the alpha map.
+	pub alpha_x_origin: Option<i16>,
+	/// The y coordinate of the origin of the alpha map.
+	pub alpha_y_origin: Option<i16>,
+	/// The x coordinate of the origin of the clip mask.
+	pub clip_x_origin: Option<i16>,
+	/// The y coordinate of the origin of the clip mask.
+	pub clip_y_origin: Option<i16>,
+	/// The bitmap outside of whose set bits nothing is drawn, or `0` (`None`)
+	/// for no clipping.
+	pub clip_mask: Option<u32>,
+	/// Whether `GraphicsExposure` events are generated when compositing from
+	/// areas which are obscured.
+	pub graphics_exposures: Option<bool>,
+	/// Whether drawing on windows is clipped by their children.
+	pub subwindow_mode: Option<SubwindowMode>,
+	/// How the edges of shapes are drawn.
+	pub poly_edge: Option<PolyEdge>,
+	/// How precisely shapes are rasterized.
+	pub poly_mode: Option<PolyMode>,
+	/// The atom naming the dithering used, or `0` (`None`) for none.
+	pub dither: Option<u32>,
+	/// Whether each color channel of the picture is used as a separate alpha
+	/// value when it is a mask, for subpixel antialiasing.
+	pub component_alpha: Option<bool>,
+}
+
+/// Defines a chainable setter for each of the [`PictureValues`].
+macro_rules! setters {
+	($($name:ident: $type:ty),* $(,)?) => {
+		impl PictureValues {
+			$(
+				#[doc = concat!("Sets [`", stringify!($name), "`](PictureValues::", stringify!($name), ").")]
+				pub const fn $name(mut self, $name: $type) -> Self {
+					self.$name = Some($name);
+					self
+				}
+			)*
+		}
+	};
+}
+
+setters! {
+	repeat: Repeat,
+	alpha_map: u32,
+	alpha_x_origin: i16,
+	alpha_y_origin: i16,
+	clip_x_origin: i16,
+	clip_y_origin: i16,
+	clip_mask: u32,
+	graphics_exposures: bool,
+	subwindow_mode: SubwindowMode,
+	poly_edge: PolyEdge,
+	poly_mode: PolyMode,
+	dither: u32,
+	component_alpha: bool,
+}
+
+impl PictureValues {
+	/// Creates `PictureValues` with every value unset.
+	pub const fn new() -> Self {
+		Self {
+			repeat: None,
+			alpha_map: None,
+			alpha_x_origin: None,
+			alpha_y_origin: None,
+			clip_x_origin: None,
+			clip_y_origin: None,
+			clip_mask: None,
+			graphics_exposures: None,
+			subwindow_mode: None,
+			poly_edge: None,
+			poly_mode: None,
+			dither: None,
+			component_alpha: None,
+		}
+	}
+
+	/// Returns the values in the order of their bits in the value mask.
+	fn to_values(self) -> [Option<u32>; VALUE_COUNT] {
+		[
+			self.repeat.map(|repeat| repeat as u32),
+			self.alpha_map,
+			self.alpha_x_origin.map(|x| x as u32),
+			self.alpha_y_origin.map(|y| y as u32),
+			self.clip_x_origin.map(|x| x as u32),
+			self.clip_y_origin.map(|y| y as u32),
+			self.clip_mask,
+			self.graphics_exposures.map(u32::from),
+			self.subwindow_mode.map(|mode| mode as u32),
+			self.poly_edge.map(|edge| edge as u32),
+			self.poly_mode.map(|mode| mode as u32),
+			self.dither,
+			self.component_alpha.map(u32::from),
+		]
+	}
+
+	/// Writes the value mask and the values which are present to `body`.
+	fn write_to(self, body: &mut impl BufMut) {
+		let values = self.to_values();
+
+		body.put_u32(window::value_mask(&values));
+		window::write_values(body, &values);
+	}
+}
+
+/// Whether a [`PictFormat`]'s pixels are colormap indices or hold their
+/// channels directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PictType {
+	Indexed,
+	Direct,
+}
+
+impl PictType {
+	pub(crate) const fn from_value(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(Self::Indexed),
+			1 => Some(Self::Direct),
+
+			_ => None,
+		}
+	}
+}
+
+/// Where each channel is in the pixels of a [`PictFormat`]: the channel's
+/// value is `(pixel >> shift) & mask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DirectFormat {
+	pub red_shift: u16,
+	pub red_mask: u16,
+	pub green_shift: u16,
+	pub green_mask: u16,
+	pub blue_shift: u16,
+	pub blue_mask: u16,
+	pub alpha_shift: u16,
+	pub alpha_mask: u16,
+}
+
+/// A format of pictures supported by the X server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PictFormat {
+	pub id: u32,
+	pub kind: PictType,
+	pub depth: u8,
+	/// Where each channel is in pixels, for `Direct` formats.
+	pub direct: DirectFormat,
+	/// The colormap of `Indexed` formats.
+	pub colormap: Option<u32>,
+}
+
+/// The standard formats which every X server supporting RENDER has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardFormat {
+	/// 8 bits each of alpha, red, green and blue.
+	Argb32,
+	/// 8 bits each of red, green and blue, with 8 unused bits.
+	Rgb24,
+	/// 8 bits of alpha, the usual format of glyph masks.
+	A8,
+	/// 4 bits of alpha.
+	A4,
+	/// 1 bit of alpha.
+	A1,
+}
+
+impl StandardFormat {
+	/// The depth and channel layout of the format.
+	const fn layout(self) -> (u8, DirectFormat) {
+		const fn alpha(mask: u16) -> DirectFormat {
+			DirectFormat {
+				red_shift: 0,
+				red_mask: 0,
+				green_shift: 0,
+				green_mask: 0,
+				blue_shift: 0,
+				blue_mask: 0,
+				alpha_shift: 0,
+				alpha_mask: mask,
+			}
+		}
+
+		const RGB: DirectFormat = DirectFormat {
+			red_shift: 16,
+			red_mask: 0xff,
+			green_shift: 8,
+			green_mask: 0xff,
+			blue_shift: 0,
+			blue_mask: 0xff,
+			alpha_shift: 0,
+			alpha_mask: 0,
+		};
+
+		match self {
+			Self::Argb32 => (
+				32,
+				DirectFormat {
+					alpha_shift: 24,
+					alpha_mask: 0xff,
+					..RGB
+				},
+			),
+			Self::Rgb24 => (24, RGB),
+			Self::A8 => (8, alpha(0xff)),
+			Self::A4 => (4, alpha(0xf)),
+			Self::A1 => (1, alpha(0x1)),
+		}
+	}
+}
+
+/// A depth supported by a screen, and the picture formats of its visuals.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PictDepth {
+	pub depth: u8,
+	/// Each visual of this depth and the ID of its picture format.
+	pub visuals: Vec<(u32, u32)>,
+}
+
+/// The picture formats of a screen's visuals.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PictScreen {
+	pub depths: Vec<PictDepth>,
+	/// The format used for glyphs when no format is given.
+	pub fallback: u32,
+	/// The subpixel order of the screen, if the X server reported it.
+	pub subpixel_order: Option<u32>,
+}
+
+/// The picture formats supported by the X server.
+///
+/// See [`Client::query_pict_formats`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PictFormats {
+	pub formats: Vec<PictFormat>,
+	/// The formats of each screen's visuals, in the same order as the
+	/// [`Setup`]'s screens.
+	///
+	/// [`Setup`]: crate::Setup
+	pub screens: Vec<PictScreen>,
+}
+
+impl PictFormats {
+	/// Returns the format with the given `id`.
+	pub fn format(&self, id: u32) -> Option<&PictFormat> {
+		self.formats.iter().find(|format| format.id == id)
+	}
+
+	/// Returns the format of pictures of windows with `visual`.
+	pub fn format_for_visual(&self, visual: u32) -> Option<&PictFormat> {
+		let id = self
+			.screens
+			.iter()
+			.flat_map(|screen| &screen.depths)
+			.flat_map(|depth| &depth.visuals)
+			.find_map(|&(id, format)| (id == visual).then_some(format))?;
+
+		self.format(id)
+	}
+
+	/// Returns the direct format matching `standard`.
+	pub fn standard(&self, standard: StandardFormat) -> Option<&PictFormat> {
+		let (depth, direct) = standard.layout();
+
+		self.formats.iter().find(|format| {
+			format.kind == PictType::Direct && format.depth == depth && format.direct == direct
+		})
+	}
+}
+
+/// A glyph image to add to a [`GlyphSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Glyph<'data> {
+	/// The ID the glyph is referred to by when compositing glyphs.
+	pub id: u32,
+
+	pub width: u16,
+	pub height: u16,
+	/// The position of the glyph's origin within its image.
+	pub origin: Point,
+	/// How far the origin of the next glyph is from this glyph's origin.
+	pub advance: Point,
+
+	/// The glyph's image in the glyph set's format, with each scanline
+	/// padded to 32 bits.
+	pub data: &'data [u8],
+}
+
+/// A run of glyphs to composite, starting at an offset from where the last
+/// run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphRun<'glyphs> {
+	/// The offset of the first glyph's origin from the origin after the last
+	/// glyph of the previous run, or from the source position for the first
+	/// run.
+	pub offset: Point,
+	/// The IDs of the glyphs.
+	pub glyphs: &'glyphs [u32],
+}
+
+/// Takes `count` bytes from the start of `data`.
+fn take<'data>(data: &mut &'data [u8], count: usize) -> Option<&'data [u8]> {
+	let taken = data.get(..count)?;
+	*data = &data[count..];
+
+	Some(taken)
+}
+
+/// Takes a `u32` from the start of `data`.
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+	let &[b0, b1, b2, b3] = take(data, 4)? else {
+		return None;
+	};
+
+	Some(u32::from_be_bytes([b0, b1, b2, b3]))
+}
+
+impl PictFormat {
+	/// Reads a `PictFormat` from the start of `data`.
+	fn read_from(data: &mut &[u8]) -> Option<Self> {
+		let id = take_u32(data)?;
+		let &[kind, depth, _, _, ref direct @ .., c0, c1, c2, c3] = take(data, 24)? else {
+			return None;
+		};
+		let u16_at = |offset: usize| u16::from_be_bytes([direct[offset], direct[offset + 1]]);
+
+		Some(Self {
+			id,
+			kind: PictType::from_value(kind)?,
+			depth,
+			direct: DirectFormat {
+				red_shift: u16_at(0),
+				red_mask: u16_at(2),
+				green_shift: u16_at(4),
+				green_mask: u16_at(6),
+				blue_shift: u16_at(8),
+				blue_mask: u16_at(10),
+				alpha_shift: u16_at(12),
+				alpha_mask: u16_at(14),
+			},
+			colormap: Some(u32::from_be_bytes([c0, c1, c2, c3])).filter(|&colormap| colormap != 0),
+		})
+	}
+}
+
+impl PictScreen {
+	/// Reads a `PictScreen`, without its subpixel order, from the start of
+	/// `data`.
+	fn read_from(data: &mut &[u8]) -> Option<Self> {
+		let depth_count = take_u32(data)?;
+		let fallback = take_u32(data)?;
+
+		let depths = (0..depth_count)
+			.map(|_| {
+				let &[depth, _, n0, n1, _, _, _, _] = take(data, 8)? else {
+					return None;
+				};
+
+				let visuals = (0..u16::from_be_bytes([n0, n1]))
+					.map(|_| Some((take_u32(data)?, take_u32(data)?)))
+					.collect::<Option<_>>()?;
+
+				Some(PictDepth { depth, visuals })
+			})
+			.collect::<Option<_>>()?;
+
+		Some(Self {
+			depths,
+			fallback,
+			subpixel_order: None,
+		})
+	}
+}
+
+/// Reads [`PictFormats`] from a `RenderQueryPictFormats` reply's chunk.
+fn pict_formats_from_chunk(chunk: &[u8]) -> Option<PictFormats> {
+	let mut header = chunk.get(..24)?;
+	let format_count = take_u32(&mut header)?;
+	let screen_count = take_u32(&mut header)?;
+	// The total numbers of depths and visuals are given again per screen.
+	let _depth_count = take_u32(&mut header)?;
+	let _visual_count = take_u32(&mut header)?;
+	let subpixel_count = take_u32(&mut header)?;
+
+	let mut data = &chunk[24..];
+
+	let formats = (0..format_count)
+		.map(|_| PictFormat::read_from(&mut data))
+		.collect::<Option<_>>()?;
+	let mut screens: Vec<PictScreen> = (0..screen_count)
+		.map(|_| PictScreen::read_from(&mut data))
+		.collect::<Option<_>>()?;
+
+	for screen in screens.iter_mut().take(subpixel_count as usize) {
+		screen.subpixel_order = Some(take_u32(&mut data)?);
+	}
+
+	Some(PictFormats { formats, screens })
+}
+
+/// Defines RENDER requests whose body is just an ID.
+macro_rules! id_requests {
+	($($(#[$attr:meta])* $name:ident($id:ty) = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name(pub $id);
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = concat!("Render", stringify!($name));
+				const EXTENSION: Option<&'static str> = Some(RENDER);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(2);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.0.id());
+				}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16, $id]);
+		)*
+	};
+}
+
+/// The RENDER extension's `RenderQueryVersion` request, which tells the X
+/// server which version of RENDER the client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "RenderQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of RENDER supported by both the X server and the client.
+///
+/// This is the reply to a `RenderQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("RenderQueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[a0, a1, a2, a3, b0, b1, b2, b3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([a0, a1, a2, a3]),
+			minor_version: u32::from_be_bytes([b0, b1, b2, b3]),
+		})
+	}
+}
+
+/// The RENDER extension's `RenderQueryPictFormats` request, whose reply is
+/// the X server's [`PictFormats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryPictFormats;
+
+impl Request for QueryPictFormats {
+	type Reply = PictFormats;
+
+	const NAME: &'static str = "RenderQueryPictFormats";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = QUERY_PICT_FORMATS;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(QueryPictFormats: [u8, u8, u16]);
+
+assert_wire_size!(
+	"RenderQueryPictFormats reply": [u8, u8, u16, u32, u32, u32, u32, u32, u32, [u8; 4]] == 32
+);
+
+impl Reply for PictFormats {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		pict_formats_from_chunk(chunk)
+	}
+}
+
+/// The RENDER extension's `RenderCreatePicture` request.
+///
+/// See [`Client::create_picture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreatePicture {
+	/// The ID of the new picture.
+	pub picture: Picture,
+	pub drawable: Drawable,
+	/// The ID of the picture's [`PictFormat`].
+	pub format: u32,
+	pub values: PictureValues,
+}
+
+impl Request for CreatePicture {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreatePicture";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_PICTURE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.format);
+		self.values.write_to(body);
+	}
+}
+
+// Followed by the values.
+assert_wire_size!("RenderCreatePicture request": [u8, u8, u16, Picture, u32, u32, u32] == 20);
+
+/// The RENDER extension's `RenderChangePicture` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangePicture {
+	pub picture: Picture,
+	pub values: PictureValues,
+}
+
+impl Request for ChangePicture {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderChangePicture";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CHANGE_PICTURE;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		self.values.write_to(body);
+	}
+}
+
+// Followed by the values.
+assert_wire_size!("RenderChangePicture request": [u8, u8, u16, Picture, u32] == 12);
+
+/// The RENDER extension's `RenderSetPictureClipRectangles` request, which
+/// clips a picture to a list of rectangles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetPictureClipRectangles<'a> {
+	pub picture: Picture,
+	/// The origin the rectangles are relative to.
+	pub origin: Point,
+	pub rectangles: &'a [Rectangle],
+}
+
+impl Request for SetPictureClipRectangles<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderSetPictureClipRectangles";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = SET_PICTURE_CLIP_RECTANGLES;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		self.origin.write_to(body);
+
+		for rectangle in self.rectangles {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+// Followed by the rectangles.
+assert_wire_size!("RenderSetPictureClipRectangles request": [u8, u8, u16, Picture, Point] == 12);
+
+id_requests! {
+	/// The RENDER extension's `RenderFreePicture` request.
+	FreePicture(Picture) = FREE_PICTURE;
+	/// The RENDER extension's `RenderFreeGlyphSet` request.
+	FreeGlyphSet(GlyphSet) = FREE_GLYPH_SET;
+}
+
+/// The RENDER extension's `RenderComposite` request.
+///
+/// See [`Client::composite`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Composite {
+	pub operator: Operator,
+	pub source: Picture,
+	pub mask: Option<Picture>,
+	pub destination: Picture,
+	pub source_position: Point,
+	pub mask_position: Point,
+	/// The area of `destination` to composite onto.
+	pub area: Rectangle,
+}
+
+impl Request for Composite {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderComposite";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = COMPOSITE;
+	const LENGTH: Option<u16> = Some(9);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.operator as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+		body.put_u32(self.source.id());
+		body.put_u32(self.mask.map_or(0, Picture::id));
+		body.put_u32(self.destination.id());
+		self.source_position.write_to(body);
+		self.mask_position.write_to(body);
+		self.area.write_to(body);
+	}
+}
+
+assert_wire_size!(
+	Composite: [u8, u8, u16, u8, [u8; 3], Picture, Picture, Picture, Point, Point, Rectangle]
+);
+
+/// The RENDER extension's `RenderTrapezoids` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Trapezoids<'a> {
+	pub operator: Operator,
+	pub source: Picture,
+	pub destination: Picture,
+	/// The format of the mask the trapezoids are rasterized into, or `None`
+	/// to composite each trapezoid separately.
+	pub mask_format: Option<u32>,
+	pub source_position: Point,
+	pub trapezoids: &'a [Trapezoid],
+}
+
+impl Request for Trapezoids<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderTrapezoids";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = TRAPEZOIDS;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		write_composite_header(body, self.operator, self.source, self.destination);
+		body.put_u32(self.mask_format.unwrap_or(0));
+		self.source_position.write_to(body);
+
+		for trapezoid in self.trapezoids {
+			trapezoid.write_to(body);
+		}
+	}
+}
+
+// Followed by the trapezoids.
+assert_wire_size!(
+	"RenderTrapezoids request": [u8, u8, u16, u8, [u8; 3], Picture, Picture, u32, Point] == 24
+);
+
+/// The RENDER extension's `RenderFillRectangles` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FillRectangles<'a> {
+	pub operator: Operator,
+	pub destination: Picture,
+	pub color: Color,
+	pub rectangles: &'a [Rectangle],
+}
+
+impl Request for FillRectangles<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderFillRectangles";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = FILL_RECTANGLES;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.operator as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+		body.put_u32(self.destination.id());
+		self.color.write_to(body);
+
+		for rectangle in self.rectangles {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+// Followed by the rectangles.
+assert_wire_size!("RenderFillRectangles request": [u8, u8, u16, u8, [u8; 3], Picture, Color] == 20);
+
+/// The RENDER extension's `RenderCreateSolidFill` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateSolidFill {
+	/// The ID of the new picture.
+	pub picture: Picture,
+	pub color: Color,
+}
+
+impl Request for CreateSolidFill {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateSolidFill";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_SOLID_FILL;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		self.color.write_to(body);
+	}
+}
+
+assert_wire_size!(CreateSolidFill: [u8, u8, u16, Picture, Color]);
+
+/// The RENDER extension's `RenderCreateLinearGradient` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateLinearGradient<'a> {
+	/// The ID of the new picture.
+	pub picture: Picture,
+	pub start: FixedPoint,
+	pub end: FixedPoint,
+	pub stops: &'a [GradientStop],
+}
+
+impl Request for CreateLinearGradient<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateLinearGradient";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_LINEAR_GRADIENT;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		self.start.write_to(body);
+		self.end.write_to(body);
+		write_stops(body, self.stops);
+	}
+}
+
+// Followed by the stops.
+assert_wire_size!(
+	"RenderCreateLinearGradient request": [u8, u8, u16, Picture, FixedPoint, FixedPoint, u32] == 28
+);
+
+/// The RENDER extension's `RenderCreateRadialGradient` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateRadialGradient<'a> {
+	/// The ID of the new picture.
+	pub picture: Picture,
+	/// The center and radius of the inner circle.
+	pub inner: (FixedPoint, Fixed),
+	/// The center and radius of the outer circle.
+	pub outer: (FixedPoint, Fixed),
+	pub stops: &'a [GradientStop],
+}
+
+impl Request for CreateRadialGradient<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateRadialGradient";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_RADIAL_GRADIENT;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.picture.id());
+		self.inner.0.write_to(body);
+		self.outer.0.write_to(body);
+		body.put_i32(self.inner.1 .0);
+		body.put_i32(self.outer.1 .0);
+		write_stops(body, self.stops);
+	}
+}
+
+// Followed by the stops.
+assert_wire_size!(
+	"RenderCreateRadialGradient request": [
+		u8, u8, u16, Picture, FixedPoint, FixedPoint, Fixed, Fixed, u32,
+	] == 36
+);
+
+/// The RENDER extension's `RenderCreateGlyphSet` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateGlyphSet {
+	/// The ID of the new glyph set.
+	pub glyph_set: GlyphSet,
+	/// The ID of the [`PictFormat`] of the glyphs' images.
+	pub format: u32,
+}
+
+impl Request for CreateGlyphSet {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateGlyphSet";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_GLYPH_SET;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.glyph_set.id());
+		body.put_u32(self.format);
+	}
+}
+
+assert_wire_size!(CreateGlyphSet: [u8, u8, u16, GlyphSet, u32]);
+
+/// The RENDER extension's `RenderAddGlyphs` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddGlyphs<'a> {
+	pub glyph_set: GlyphSet,
+	pub glyphs: &'a [Glyph<'a>],
+}
+
+impl Request for AddGlyphs<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderAddGlyphs";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = ADD_GLYPHS;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.glyph_set.id());
+		body.put_u32(self.glyphs.len() as u32);
+
+		for glyph in self.glyphs {
+			body.put_u32(glyph.id);
+		}
+
+		for glyph in self.glyphs {
+			body.put_u16(glyph.width);
+			body.put_u16(glyph.height);
+			glyph.origin.write_to(body);
+			glyph.advance.write_to(body);
+		}
+
+		for glyph in self.glyphs {
+			body.put_slice(glyph.data);
+			body.put_bytes(0, glyph.data.len().next_multiple_of(4) - glyph.data.len());
+		}
+	}
+}
+
+// Followed by the glyphs.
+assert_wire_size!("RenderAddGlyphs request": [u8, u8, u16, GlyphSet, u32] == 12);
+
+/// The RENDER extension's `RenderCompositeGlyphs32` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompositeGlyphs32<'a> {
+	pub operator: Operator,
+	pub source: Picture,
+	pub destination: Picture,
+	/// The format of the mask the glyphs are rasterized into, or `None` to
+	/// composite each glyph separately.
+	pub mask_format: Option<u32>,
+	pub glyph_set: GlyphSet,
+	pub source_position: Point,
+	pub runs: &'a [GlyphRun<'a>],
+}
+
+impl Request for CompositeGlyphs32<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCompositeGlyphs32";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = COMPOSITE_GLYPHS_32;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		write_composite_header(body, self.operator, self.source, self.destination);
+		body.put_u32(self.mask_format.unwrap_or(0));
+		body.put_u32(self.glyph_set.id());
+		self.source_position.write_to(body);
+
+		for run in self.runs {
+			// Runs longer than a single item can hold are split into several
+			// items, the rest of which continue where the last left off.
+			let mut offset = run.offset;
+
+			for glyphs in run.glyphs.chunks(MAX_GLYPHS_PER_ITEM) {
+				body.put_u8(glyphs.len() as u8);
+				// 3 unused bytes.
+				body.put_bytes(0, 3);
+				offset.write_to(body);
+
+				for glyph in glyphs {
+					body.put_u32(*glyph);
+				}
+
+				offset = Point::new(0, 0);
+			}
+		}
+	}
+}
+
+// Followed by the glyph items.
+assert_wire_size!(
+	"RenderCompositeGlyphs32 request": [
+		u8, u8, u16, u8, [u8; 3], Picture, Picture, u32, GlyphSet, Point,
+	] == 28
+);
+
+/// The RENDER extension's `RenderCreateCursor` request, which creates a
+/// cursor from a picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateCursor {
+	/// The ID of the new cursor.
+	pub cursor: u32,
+	/// The cursor's image, which the cursor keeps a copy of.
+	pub source: Picture,
+	pub hotspot: Point,
+}
+
+impl Request for CreateCursor {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateCursor";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_CURSOR;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.cursor);
+		body.put_u32(self.source.id());
+		body.put_u16(self.hotspot.x as u16);
+		body.put_u16(self.hotspot.y as u16);
+	}
+}
+
+assert_wire_size!(CreateCursor: [u8, u8, u16, u32, Picture, u16, u16]);
+
+/// The RENDER extension's `RenderCreateAnimCursor` request, which creates an
+/// animated cursor from other cursors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateAnimCursor<'a> {
+	/// The ID of the new cursor.
+	pub cursor: u32,
+	/// Each frame's cursor, and how long it is shown for in milliseconds.
+	pub frames: &'a [(u32, u32)],
+}
+
+impl Request for CreateAnimCursor<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "RenderCreateAnimCursor";
+	const EXTENSION: Option<&'static str> = Some(RENDER);
+	const OPCODE: u8 = CREATE_ANIM_CURSOR;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.cursor);
+
+		for &(cursor, delay) in self.frames {
+			body.put_u32(cursor);
+			body.put_u32(delay);
+		}
+	}
+}
+
+// Followed by the frames.
+assert_wire_size!("RenderCreateAnimCursor request": [u8, u8, u16, u32] == 8);
+
+/// Writes the start of the body of a request which composites `source`
+/// onto `destination` with `operator`.
+fn write_composite_header(
+	body: &mut impl BufMut, operator: Operator, source: Picture, destination: Picture,
+) {
+	body.put_u8(operator as u8);
+	// 3 unused bytes.
+	body.put_bytes(0, 3);
+	body.put_u32(source.id());
+	body.put_u32(destination.id());
+}
+
+/// Queries the version of RENDER supported by the X server, as its major
+/// and minor version.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::render_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn render_version(connection: &mut impl RoundTrip) -> Result<(u32, u32), ReplyError> {
+	let request = QueryVersion {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+
+	extension::negotiate_version(connection, RENDER, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await
+}
+
+/// Queries the picture formats supported by the X server, and the formats
+/// of each screen's visuals.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::query_pict_formats`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn query_pict_formats(
+	connection: &mut impl RoundTrip,
+) -> Result<PictFormats, ReplyError> {
+	request::reply_to(connection, &QueryPictFormats).await
+}
+
+/// Creates a picture of `drawable`, whose pixels are in the picture
+/// format with the ID `format`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// or a new range of resource IDs has to be requested, its reply is [awaited
+/// directly].
+///
+/// See [`Client::create_picture`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_picture(
+	connection: &mut impl RoundTrip, drawable: Drawable, format: u32, values: &PictureValues,
+) -> Result<Picture, XidError> {
+	let picture = Picture(xid::generate_id(connection).await?);
+
+	let request = CreatePicture {
+		picture,
+		drawable,
+		format,
+		values: *values,
+	};
+	request::send(connection, &request).await?;
+
+	Ok(picture)
+}
+
+/// Changes the `values` of `picture` which are set.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::change_picture`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn change_picture(
+	connection: &mut impl RoundTrip, picture: Picture, values: &PictureValues,
+) -> Result<(), ReplyError> {
+	let request = ChangePicture {
+		picture,
+		values: *values,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Clips drawing on `picture` to `rectangles`, moved by `origin`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_picture_clip_rectangles`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_picture_clip_rectangles(
+	connection: &mut impl RoundTrip, picture: Picture, origin: Point, rectangles: &[Rectangle],
+) -> Result<(), ReplyError> {
+	let request = SetPictureClipRectangles {
+		picture,
+		origin,
+		rectangles,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Frees `picture`. Its drawable isn't affected.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::free_picture`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn free_picture(
+	connection: &mut impl RoundTrip, picture: Picture,
+) -> Result<(), ReplyError> {
+	request::send(connection, &FreePicture(picture)).await
+}
+
+/// Composites `area` of `destination` with the same-sized areas of
+/// `source` at `source_position` and, if there is one, `mask` at
+/// `mask_position`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::composite`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+#[allow(clippy::too_many_arguments)]
+pub async fn composite(
+	connection: &mut impl RoundTrip, operator: Operator, source: Picture, mask: Option<Picture>,
+	destination: Picture, source_position: Point, mask_position: Point, area: Rectangle,
+) -> Result<(), ReplyError> {
+	let request = Composite {
+		operator,
+		source,
+		mask,
+		destination,
+		source_position,
+		mask_position,
+		area,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Combines `rectangles` of `color` with `destination` by `operator`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::fill_rectangles`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn fill_rectangles(
+	connection: &mut impl RoundTrip, operator: Operator, destination: Picture, color: Color,
+	rectangles: &[Rectangle],
+) -> Result<(), ReplyError> {
+	let request = FillRectangles {
+		operator,
+		destination,
+		color,
+		rectangles,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Composites the areas of `source` covered by `trapezoids` onto
+/// `destination`, with antialiased edges unless the destination's
+/// [`poly_edge`] is `Sharp`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::composite_trapezoids`].
+///
+/// [`poly_edge`]: PictureValues::poly_edge
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn composite_trapezoids(
+	connection: &mut impl RoundTrip, operator: Operator, source: Picture, destination: Picture,
+	mask_format: Option<u32>, source_position: Point, trapezoids: &[Trapezoid],
+) -> Result<(), ReplyError> {
+	let request = Trapezoids {
+		operator,
+		source,
+		destination,
+		mask_format,
+		source_position,
+		trapezoids,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Creates a picture of infinite size filled with `color`, for use as
+/// the source of a composite.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// or a new range of resource IDs has to be requested, its reply is [awaited
+/// directly].
+///
+/// See [`Client::create_solid_fill`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_solid_fill(
+	connection: &mut impl RoundTrip, color: Color,
+) -> Result<Picture, XidError> {
+	let picture = Picture(xid::generate_id(connection).await?);
+
+	request::send(connection, &CreateSolidFill { picture, color }).await?;
+
+	Ok(picture)
+}
+
+/// Writes the number of `stops`, their offsets and then their colors.
+fn write_stops(body: &mut impl BufMut, stops: &[GradientStop]) {
+	body.put_u32(stops.len() as u32);
+
+	for stop in stops {
+		body.put_i32(stop.offset.0);
+	}
+
+	for stop in stops {
+		stop.color.write_to(body);
+	}
+}
+
+/// Creates a picture of infinite size with a gradient along the line from
+/// `start` to `end` through `stops`, for use as the source of a
+/// composite.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// or a new range of resource IDs has to be requested, its reply is [awaited
+/// directly].
+///
+/// See [`Client::create_linear_gradient`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_linear_gradient(
+	connection: &mut impl RoundTrip, start: FixedPoint, end: FixedPoint, stops: &[GradientStop],
+) -> Result<Picture, XidError> {
+	let picture = Picture(xid::generate_id(connection).await?);
+
+	let request = CreateLinearGradient {
+		picture,
+		start,
+		end,
+		stops,
+	};
+	request::send(connection, &request).await?;
+
+	Ok(picture)
+}
+
+/// Creates a picture of infinite size with a gradient between the circles
+/// `inner` and `outer`, each given by their center and radius, for use as
+/// the source of a composite.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// or a new range of resource IDs has to be requested, its reply is [awaited
+/// directly].
+///
+/// See [`Client::create_radial_gradient`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_radial_gradient(
+	connection: &mut impl RoundTrip, inner: (FixedPoint, Fixed), outer: (FixedPoint, Fixed),
+	stops: &[GradientStop],
+) -> Result<Picture, XidError> {
+	let picture = Picture(xid::generate_id(connection).await?);
+
+	let request = CreateRadialGradient {
+		picture,
+		inner,
+		outer,
+		stops,
+	};
+	request::send(connection, &request).await?;
+
+	Ok(picture)
+}
+
+/// Creates an empty glyph set for glyphs in the picture format with the
+/// ID `format`, usually [`StandardFormat::A8`].
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// or a new range of resource IDs has to be requested, its reply is [awaited
+/// directly].
+///
+/// See [`Client::create_glyph_set`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_glyph_set(
+	connection: &mut impl RoundTrip, format: u32,
+) -> Result<GlyphSet, XidError> {
+	let glyph_set = GlyphSet(xid::generate_id(connection).await?);
+
+	request::send(connection, &CreateGlyphSet { glyph_set, format }).await?;
+
+	Ok(glyph_set)
+}
+
+/// Adds `glyphs` to `glyph_set`, replacing any glyphs with the same IDs.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::add_glyphs`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn add_glyphs(
+	connection: &mut impl RoundTrip, glyph_set: GlyphSet, glyphs: &[Glyph<'_>],
+) -> Result<(), ReplyError> {
+	request::send(connection, &AddGlyphs { glyph_set, glyphs }).await
+}
+
+/// Frees `glyph_set` and its glyphs.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::free_glyph_set`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn free_glyph_set(
+	connection: &mut impl RoundTrip, glyph_set: GlyphSet,
+) -> Result<(), ReplyError> {
+	request::send(connection, &FreeGlyphSet(glyph_set)).await
+}
+
+/// Composites `runs` of glyphs from `glyph_set` onto `destination`,
+/// using each glyph's image as a mask for `source`.
+///
+/// With a [`RequestWriter`], if the RENDER extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::composite_glyphs`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+#[allow(clippy::too_many_arguments)]
+pub async fn composite_glyphs(
+	connection: &mut impl RoundTrip, operator: Operator, source: Picture, destination: Picture,
+	mask_format: Option<u32>, glyph_set: GlyphSet, source_position: Point, runs: &[GlyphRun<'_>],
+) -> Result<(), ReplyError> {
+	let request = CompositeGlyphs32 {
+		operator,
+		source,
+		destination,
+		mask_format,
+		glyph_set,
+		source_position,
+		runs,
+	};
+
+	request::send(connection, &request).await
+}
+
+impl Client {
+	/// Queries the version of RENDER supported by the X server, as its major
+	/// and minor version.
+	///
+	/// The version is negotiated once, so only the first query makes a round
+	/// trip to the X server.
+	pub async fn render_version(&mut self) -> Result<(u32, u32), ReplyError> {
+		render_version(self).await
+	}
+
+	/// Queries the picture formats supported by the X server, and the formats
+	/// of each screen's visuals.
+	pub async fn query_pict_formats(&mut self) -> Result<PictFormats, ReplyError> {
+		query_pict_formats(self).await
+	}
+
+	/// Creates a picture of `drawable`, whose pixels are in the picture
+	/// format with the ID `format`.
+	///
+	/// The format must have the same depth as `drawable`; for windows, it is
+	/// the [format of their visual].
+	///
+	/// [format of their visual]: PictFormats::format_for_visual
+	pub async fn create_picture(
+		&mut self, drawable: Drawable, format: u32, values: &PictureValues,
+	) -> Result<Picture, XidError> {
+		create_picture(self, drawable, format, values).await
+	}
+
+	/// Changes the `values` of `picture` which are set.
+	pub async fn change_picture(
+		&mut self, picture: Picture, values: &PictureValues,
+	) -> Result<(), ReplyError> {
+		change_picture(self, picture, values).await
+	}
+
+	/// Clips drawing on `picture` to `rectangles`, moved by `origin`.
+	pub async fn set_picture_clip_rectangles(
+		&mut self, picture: Picture, origin: Point, rectangles: &[Rectangle],
+	) -> Result<(), ReplyError> {
+		set_picture_clip_rectangles(self, picture, origin, rectangles).await
+	}
+
+	/// Frees `picture`. Its drawable isn't affected.
+	pub async fn free_picture(&mut self, picture: Picture) -> Result<(), ReplyError> {
+		free_picture(self, picture).await
+	}
+
+	/// Composites `area` of `destination` with the same-sized areas of
+	/// `source` at `source_position` and, if there is one, `mask` at
+	/// `mask_position`.
+	///
+	/// Where there is a mask, the source is multiplied by its alpha before
+	/// being combined with the destination by `operator`.
+	#[allow(clippy::too_many_arguments)]
+	pub async fn composite(
+		&mut self, operator: Operator, source: Picture, mask: Option<Picture>,
+		destination: Picture, source_position: Point, mask_position: Point, area: Rectangle,
+	) -> Result<(), ReplyError> {
+		composite(
+			self,
+			operator,
+			source,
+			mask,
+			destination,
+			source_position,
+			mask_position,
+			area,
+		)
+		.await
+	}
+
+	/// Combines `rectangles` of `color` with `destination` by `operator`.
+	pub async fn fill_rectangles(
+		&mut self, operator: Operator, destination: Picture, color: Color, rectangles: &[Rectangle],
+	) -> Result<(), ReplyError> {
+		fill_rectangles(self, operator, destination, color, rectangles).await
+	}
+
+	/// Composites the areas of `source` covered by `trapezoids` onto
+	/// `destination`, with antialiased edges unless the destination's
+	/// [`poly_edge`] is `Sharp`.
+	///
+	/// The trapezoids are rasterized into a mask in `mask_format`, such as
+	/// [`StandardFormat::A8`], or are composited one by one if it is `None`.
+	/// `source_position` is the point of `source` which lines up with the
+	/// first trapezoid's top-left corner.
+	///
+	/// [`poly_edge`]: PictureValues::poly_edge
+	pub async fn composite_trapezoids(
+		&mut self, operator: Operator, source: Picture, destination: Picture,
+		mask_format: Option<u32>, source_position: Point, trapezoids: &[Trapezoid],
+	) -> Result<(), ReplyError> {
+		composite_trapezoids(
+			self,
+			operator,
+			source,
+			destination,
+			mask_format,
+			source_position,
+			trapezoids,
+		)
+		.await
+	}
+
+	/// Creates a picture of infinite size filled with `color`, for use as
+	/// the source of a composite.
+	pub async fn create_solid_fill(&mut self, color: Color) -> Result<Picture, XidError> {
+		create_solid_fill(self, color).await
+	}
+
+	/// Creates a picture of infinite size with a gradient along the line from
+	/// `start` to `end` through `stops`, for use as the source of a
+	/// composite.
+	///
+	/// Beyond the line, the gradient is drawn as given by the picture's
+	/// [`repeat`].
+	///
+	/// [`repeat`]: PictureValues::repeat
+	pub async fn create_linear_gradient(
+		&mut self, start: FixedPoint, end: FixedPoint, stops: &[GradientStop],
+	) -> Result<Picture, XidError> {
+		create_linear_gradient(self, start, end, stops).await
+	}
+
+	/// Creates a picture of infinite size with a gradient between the circles
+	/// `inner` and `outer`, each given by their center and radius, for use as
+	/// the source of a composite.
+	pub async fn create_radial_gradient(
+		&mut self, inner: (FixedPoint, Fixed), outer: (FixedPoint, Fixed), stops: &[GradientStop],
+	) -> Result<Picture, XidError> {
+		create_radial_gradient(self, inner, outer, stops).await
+	}
+
+	/// Creates an empty glyph set for glyphs in the picture format with the
+	/// ID `format`, usually [`StandardFormat::A8`].
+	pub async fn create_glyph_set(&mut self, format: u32) -> Result<GlyphSet, XidError> {
+		create_glyph_set(self, format).await
+	}
+
+	/// Adds `glyphs` to `glyph_set`, replacing any glyphs with the same IDs.
+	pub async fn add_glyphs(
+		&mut self, glyph_set: GlyphSet, glyphs: &[Glyph<'_>],
+	) -> Result<(), ReplyError> {
+		add_glyphs(self, glyph_set, glyphs).await
+	}
+
+	/// Frees `glyph_set` and its glyphs.
+	pub async fn free_glyph_set(&mut self, glyph_set: GlyphSet) -> Result<(), ReplyError> {
+		free_glyph_set(self, glyph_set).await
+	}
+
+	/// Composites `runs` of glyphs from `glyph_set` onto `destination`,
+	/// using each glyph's image as a mask for `source`.
+	///
+	/// The first glyph's origin is at `source_position` moved by the first
+	/// run's offset, and each glyph after it is moved by the advance of the
+	/// glyph before. The glyphs are combined into a mask in `mask_format`
+	/// before compositing, or are composited one by one if it is `None`.
+	#[allow(clippy::too_many_arguments)]
+	pub async fn composite_glyphs(
+		&mut self, operator: Operator, source: Picture, destination: Picture,
+		mask_format: Option<u32>, glyph_set: GlyphSet, source_position: Point,
+		runs: &[GlyphRun<'_>],
+	) -> Result<(), ReplyError> {
+		composite_glyphs(
+			self,
+			operator,
+			source,
+			destination,
+			mask_format,
+			glyph_set,
+			source_position,
+			runs,
+		)
+		.await
+	}
+}
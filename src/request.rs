@@ -0,0 +1,370 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Sending typed requests and receiving their typed replies.
+//!
+//! Every [`Request`] declares the type of its reply, so
+//! [`Client::send_request`] returns a [`Cookie`] which resolves to that type
+//! instead of an [`X11Frame`] which has to be read by hand. Requests which
+//! don't generate a reply have [`NoReply`] as their reply type, and their
+//! cookies can't be waited on, so a reply can't be waited for by mistake.
+//!
+//! ```no_run
+//! # use xrs::{grab::GrabServer, window::{GetGeometry, Window}, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), xrs::ReplyError> {
+//! let cookie = client.send_request(&GetGeometry { drawable: window.0 }).await?;
+//! let geometry = client.wait_for(cookie).await?;
+//!
+//! // `GrabServer` has no reply, so its cookie can't be waited for.
+//! let _sequence = client.send_request(&GrabServer).await?.sequence();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Extension requests name their extension, whose major opcode is queried
+//! the first time one of its requests is sent.
+//!
+//! [`X11Frame`]: crate::raw::X11Frame
+
+use crate::{
+	client::RoundTrip,
+	extension,
+	raw::{RawRequest, X11Frame},
+	Client,
+	ReplyCookie,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::BytesMut;
+use std::{
+	fmt,
+	fmt::Formatter,
+	future::Future,
+	marker::PhantomData,
+	pin::Pin,
+	task::{Context, Poll},
+};
+use tokio::io;
+pub use xrs_proto::{NoReply, Reply, ReplyType, Request};
+
+/// The major opcode of the `GetInputFocus` request.
+const GET_INPUT_FOCUS: u8 = 43;
+
+/// Cookies are only created without a [`ReplyCookie`] for [`NoReply`]
+/// requests, which aren't a [`Reply`].
+const HAS_REPLY: &str = "cookies for requests which generate a reply have a reply cookie";
+
+/// A typed request's pending reply.
+///
+/// A `Cookie` for a request which generates a [`Reply`] is a [`Future`]
+/// which resolves once the reply is received, like a [`ReplyCookie`]. It can
+/// only be awaited directly if the [`EventReader`] is being read from
+/// elsewhere; otherwise, use [`Client::wait_for`].
+///
+/// A `Cookie<NoReply>` only records the request's sequence number, and
+/// can't be waited for:
+///
+/// ```compile_fail
+/// # use xrs::{grab::GrabServer, Client};
+/// # async fn example(client: &mut Client) -> Result<(), xrs::ReplyError> {
+/// let cookie = client.send_request(&GrabServer).await?;
+/// client.wait_for(cookie).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`EventReader`]: crate::EventReader
+#[must_use = "the reply is discarded if the cookie is dropped"]
+pub struct Cookie<T> {
+	sequence: u16,
+	/// The cookie for the raw reply, if the request generates one.
+	reply: Option<ReplyCookie>,
+
+	/// The name of the request, for errors reading its reply.
+	name: &'static str,
+	_reply: PhantomData<fn() -> T>,
+}
+
+impl<T> Cookie<T> {
+	/// The sequence number of the request.
+	pub const fn sequence(&self) -> u16 {
+		self.sequence
+	}
+}
+
+impl<T: Reply> Cookie<T> {
+	/// Returns the [`ReplyCookie`] for the request's reply, to receive it as
+	/// an [`X11Frame`] instead.
+	///
+	/// [`X11Frame`]: crate::raw::X11Frame
+	pub fn into_raw(self) -> ReplyCookie {
+		self.reply.expect(HAS_REPLY)
+	}
+
+	/// Reads the reply from the raw reply received for the cookie.
+	fn read(name: &'static str, result: Result<X11Frame, ReplyError>) -> Result<T, ReplyError> {
+		T::from_reply(&result?).ok_or_else(|| ReplyError::malformed(name))
+	}
+}
+
+impl<T: Reply> Future for Cookie<T> {
+	type Output = Result<T, ReplyError>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let name = self.name;
+		let reply = self.reply.as_mut().expect(HAS_REPLY);
+
+		Pin::new(reply)
+			.poll(cx)
+			.map(|result| Self::read(name, result))
+	}
+}
+
+impl<T> fmt::Debug for Cookie<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Cookie")
+			.field("request", &self.name)
+			.field("sequence", &self.sequence)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Returns `request` as a [`RawRequest`], looking up its extension's major
+/// opcode first if it is an extension request.
+pub(crate) async fn to_raw<R: Request>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<RawRequest, ReplyError> {
+	let major_opcode = match R::EXTENSION {
+		Some(name) => {
+			extension::require_extension(connection, name)
+				.await?
+				.major_opcode
+		},
+
+		None => R::OPCODE,
+	};
+
+	Ok(request.to_raw(major_opcode))
+}
+
+/// Sends `request`, looking up its extension's major opcode first if it is
+/// an extension request.
+pub(crate) async fn send_request<R: Request>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<Cookie<R::Reply>, ReplyError> {
+	let raw = to_raw(connection, request).await?;
+
+	let writer = connection.writer();
+	let (sequence, reply) = if R::Reply::GENERATES_REPLY {
+		let cookie = writer
+			.send_raw_with_reply(raw)
+			.await
+			.map_err(ReplyError::Io)?;
+
+		(cookie.sequence(), Some(cookie))
+	} else {
+		(writer.send_raw(raw).await.map_err(ReplyError::Io)?, None)
+	};
+
+	Ok(Cookie {
+		sequence,
+		reply,
+
+		name: R::NAME,
+		_reply: PhantomData,
+	})
+}
+
+/// Sends `request`, which doesn't generate a reply.
+#[cfg_attr(
+	not(any(
+		feature = "composite",
+		feature = "cursor",
+		feature = "damage",
+		feature = "pointer",
+		feature = "randr",
+		feature = "region",
+		feature = "render",
+		feature = "screensaver",
+		feature = "script",
+		feature = "shape",
+		feature = "shm",
+		feature = "sync",
+		feature = "xkb"
+	)),
+	allow(dead_code)
+)]
+pub(crate) async fn send<R: Request<Reply = NoReply>>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<(), ReplyError> {
+	send_request(connection, request).await.map(drop)
+}
+
+/// Queues `request`, which doesn't generate a reply, to be sent with the
+/// next flush.
+#[cfg_attr(not(feature = "pointer"), allow(dead_code))]
+pub(crate) async fn queue<R: Request<Reply = NoReply>>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<(), ReplyError> {
+	let raw = to_raw(connection, request).await?;
+
+	connection
+		.writer()
+		.send_queued(raw)
+		.await
+		.map(drop)
+		.map_err(ReplyError::Io)
+}
+
+/// Sends `request`, a core request which doesn't generate a reply.
+pub(crate) async fn send_core<R: Request<Reply = NoReply>>(
+	writer: &mut RequestWriter, request: &R,
+) -> io::Result<()> {
+	debug_assert!(
+		R::EXTENSION.is_none(),
+		"{} is an extension request",
+		R::NAME
+	);
+
+	writer.send_raw(request.to_raw(R::OPCODE)).await.map(drop)
+}
+
+/// Waits for the reply for `cookie`.
+pub(crate) async fn wait_for<T: Reply>(
+	connection: &mut impl RoundTrip, cookie: Cookie<T>,
+) -> Result<T, ReplyError> {
+	let name = cookie.name;
+	let result = connection.reply(cookie.into_raw()).await;
+
+	Cookie::read(name, result)
+}
+
+/// Sends `request` and waits for its reply.
+pub(crate) async fn reply_to<R: Request>(
+	connection: &mut impl RoundTrip, request: &R,
+) -> Result<R::Reply, ReplyError>
+where
+	R::Reply: Reply,
+{
+	let cookie = send_request(connection, request).await?;
+
+	wait_for(connection, cookie).await
+}
+
+/// A `GetInputFocus` request, sent by [`sync`] only to wait for its reply.
+///
+/// `GetInputFocus` is the cheapest request which generates a reply. This is
+/// separate from the `focus` module's, which may not be enabled.
+struct SyncRequest;
+
+impl Request for SyncRequest {
+	type Reply = SyncReply;
+
+	const NAME: &'static str = "GetInputFocus";
+	const OPCODE: u8 = GET_INPUT_FOCUS;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+/// The reply to a [`SyncRequest`], whose contents are ignored.
+struct SyncReply;
+
+impl Reply for SyncReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		matches!(frame, X11Frame::Reply { .. }).then_some(Self)
+	}
+}
+
+/// Waits for a round trip to the X server.
+pub(crate) async fn sync(connection: &mut impl RoundTrip) -> Result<(), ReplyError> {
+	reply_to(connection, &SyncRequest).await.map(drop)
+}
+
+impl Client {
+	/// Sends a typed [`Request`], returning a [`Cookie`] for its reply.
+	///
+	/// If `request` is an extension request, its extension is queried first,
+	/// and an [`Unsupported`] error is returned if the X server doesn't
+	/// support it.
+	///
+	/// [`Unsupported`]: std::io::ErrorKind::Unsupported
+	pub async fn send_request<R: Request>(
+		&mut self, request: &R,
+	) -> Result<Cookie<R::Reply>, ReplyError> {
+		send_request(self, request).await
+	}
+
+	/// Reads frames until the reply for `cookie` is received, and returns it.
+	///
+	/// See [`Client::wait_for_reply`].
+	pub async fn wait_for<T: Reply>(&mut self, cookie: Cookie<T>) -> Result<T, ReplyError> {
+		wait_for(self, cookie).await
+	}
+
+	/// Sends a typed [`Request`] and waits for its reply.
+	///
+	/// See [`Client::send_request`].
+	pub async fn request<R: Request>(&mut self, request: &R) -> Result<R::Reply, ReplyError>
+	where
+		R::Reply: Reply,
+	{
+		reply_to(self, request).await
+	}
+
+	/// Waits until the X server has processed every request sent so far.
+	///
+	/// This sends a `GetInputFocus` request and waits for its reply. The X
+	/// server processes requests in order, so by the time the reply arrives,
+	/// every earlier request has been processed and any errors they generated
+	/// have been received: errors claimed by a [`ReplyCookie`] are delivered
+	/// to it, and the rest are kept to be returned by [`next_event`].
+	///
+	/// Window managers sync at startup, for example, to find out whether
+	/// selecting `SubstructureRedirect` on the root window failed because
+	/// another window manager is running.
+	///
+	/// [`next_event`]: Client::next_event
+	pub async fn sync(&mut self) -> Result<(), ReplyError> {
+		sync(self).await
+	}
+}
+
+impl RequestWriter {
+	/// Sends a typed [`Request`], returning a [`Cookie`] for its reply.
+	///
+	/// If the extension of an extension request has to be queried, its reply is
+	/// [awaited directly]. See [`Client::send_request`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	pub async fn send_request<R: Request>(
+		&mut self, request: &R,
+	) -> Result<Cookie<R::Reply>, ReplyError> {
+		send_request(self, request).await
+	}
+
+	/// Sends a typed [`Request`] and waits for its reply.
+	///
+	/// Replies are [awaited directly]. See [`Client::request`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	pub async fn request<R: Request>(&mut self, request: &R) -> Result<R::Reply, ReplyError>
+	where
+		R::Reply: Reply,
+	{
+		reply_to(self, request).await
+	}
+
+	/// Waits until the X server has processed every request sent so far.
+	///
+	/// Replies are [awaited directly]. By the time this returns, the
+	/// [`EventReader`] has received any errors generated by earlier requests.
+	/// See [`Client::sync`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	/// [`EventReader`]: crate::EventReader
+	pub async fn sync(&mut self) -> Result<(), ReplyError> {
+		sync(self).await
+	}
+}
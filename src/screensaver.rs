@@ -0,0 +1,437 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Keeping the display awake.
+//!
+//! Media players need to stop the screen saver from activating and the
+//! display from sleeping while something is playing.
+//! [`Client::inhibit_screensaver`] does this with whatever the X server
+//! supports:
+//!
+//! - The MIT-SCREEN-SAVER extension's `Suspend` request, which stops both the
+//!   screen saver and DPMS timers.
+//! - Disabling DPMS with the DPMS extension, if it was enabled.
+//! - Otherwise, faking activity with the XTEST extension, which must be done
+//!   periodically with [`ScreensaverInhibitor::keep_alive`].
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let inhibitor = client.inhibit_screensaver().await?;
+//! let writer = client.split_mut().1;
+//!
+//! if let Some(interval) = inhibitor.keep_alive_interval() {
+//!     // Call `inhibitor.keep_alive(writer)` every `interval` while playing.
+//! #   let _ = interval;
+//! }
+//!
+//! // ...
+//!
+//! inhibitor.release(writer).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The X server undoes a suspension if the client disconnects, but DPMS
+//! stays disabled until the inhibitor is [released].
+//!
+//! [released]: ScreensaverInhibitor::release
+
+use crate::{
+	client::RoundTrip,
+	extension,
+	geometry::Point,
+	input::{FakeInput, XTEST},
+	raw::{assert_wire_size, RawRequest, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{io, time::Duration};
+
+/// The name of the MIT-SCREEN-SAVER extension.
+const SCREEN_SAVER: &str = "MIT-SCREEN-SAVER";
+
+/// The minor opcode of the `ScreenSaverQueryVersion` request.
+const SCREEN_SAVER_QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `ScreenSaverSuspend` request.
+const SCREEN_SAVER_SUSPEND: u8 = 5;
+
+/// The version of MIT-SCREEN-SAVER which added suspending the screen saver.
+const SUSPEND_VERSION: (u8, u8) = (1, 1);
+
+/// The name of the DPMS extension.
+const DPMS: &str = "DPMS";
+
+/// The minor opcode of the `DPMSCapable` request.
+const DPMS_CAPABLE: u8 = 1;
+/// The minor opcode of the `DPMSEnable` request.
+const DPMS_ENABLE: u8 = 4;
+/// The minor opcode of the `DPMSDisable` request.
+const DPMS_DISABLE: u8 = 5;
+/// The minor opcode of the `DPMSInfo` request.
+const DPMS_INFO: u8 = 7;
+
+/// The event code of `MotionNotify` events, faked by XTEST to reset the idle
+/// timer.
+const MOTION_NOTIFY: u8 = 6;
+
+/// How often activity is faked when the screen saver can't be suspended.
+///
+/// The shortest screen saver timeout most desktops allow is one minute.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps the screen saver from activating and the display from sleeping until
+/// it is [released].
+///
+/// See [`Client::inhibit_screensaver`].
+///
+/// [released]: ScreensaverInhibitor::release
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the screen saver stays inhibited until the inhibitor is released"]
+pub struct ScreensaverInhibitor {
+	root: Window,
+
+	/// The major opcode of MIT-SCREEN-SAVER, if the screen saver was
+	/// suspended.
+	screen_saver: Option<u8>,
+	/// The major opcode of DPMS, if DPMS was disabled.
+	dpms: Option<u8>,
+	/// The major opcode of XTEST, if activity needs to be faked.
+	xtest: Option<u8>,
+}
+
+impl ScreensaverInhibitor {
+	/// Returns whether the screen saver was suspended with MIT-SCREEN-SAVER.
+	pub const fn is_suspended(&self) -> bool {
+		self.screen_saver.is_some()
+	}
+
+	/// Returns whether DPMS was enabled and has been disabled.
+	pub const fn disabled_dpms(&self) -> bool {
+		self.dpms.is_some()
+	}
+
+	/// Returns how often [`keep_alive`] must be called, or `None` if the
+	/// screen saver was suspended and it doesn't need to be.
+	///
+	/// [`keep_alive`]: ScreensaverInhibitor::keep_alive
+	pub const fn keep_alive_interval(&self) -> Option<Duration> {
+		match self.xtest {
+			Some(_) => Some(KEEP_ALIVE_INTERVAL),
+			None => None,
+		}
+	}
+
+	/// Fakes pointer activity with XTEST to reset the screen saver's idle
+	/// timer, if the screen saver couldn't be suspended.
+	///
+	/// The pointer is moved by one pixel and back again. Does nothing if the
+	/// screen saver was suspended.
+	pub async fn keep_alive(&self, writer: &mut RequestWriter) -> io::Result<()> {
+		let Some(xtest) = self.xtest else {
+			return Ok(());
+		};
+
+		for offset in [1, -1] {
+			writer
+				.send_queued(fake_relative_motion(xtest, self.root, offset))
+				.await?;
+		}
+
+		writer.flush().await
+	}
+
+	/// Lets the screen saver activate and the display sleep again, resuming
+	/// the screen saver and re-enabling DPMS if it was disabled.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		if let Some(screen_saver) = self.screen_saver {
+			writer
+				.send_queued(ScreenSaverSuspend { suspend: false }.to_raw(screen_saver))
+				.await?;
+		}
+
+		if let Some(dpms) = self.dpms {
+			writer.send_queued(DPMSEnable.to_raw(dpms)).await?;
+		}
+
+		writer.flush().await
+	}
+}
+
+/// Creates an `XTestFakeInput` request which moves the pointer by `offset`
+/// pixels in both directions.
+fn fake_relative_motion(xtest: u8, root: Window, offset: i16) -> RawRequest {
+	let request = FakeInput {
+		event_type: MOTION_NOTIFY,
+		// The motion is relative.
+		detail: 1,
+		root: Some(root),
+		position: Point::new(offset, offset),
+		..FakeInput::default()
+	};
+
+	request.to_raw(xtest)
+}
+
+/// The MIT-SCREEN-SAVER extension's `ScreenSaverQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenSaverQueryVersion {
+	pub major_version: u8,
+	pub minor_version: u8,
+}
+
+impl Request for ScreenSaverQueryVersion {
+	type Reply = ScreenSaverVersion;
+
+	const NAME: &'static str = "ScreenSaverQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(SCREEN_SAVER);
+	const OPCODE: u8 = SCREEN_SAVER_QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.major_version);
+		body.put_u8(self.minor_version);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(ScreenSaverQueryVersion: [u8, u8, u16, u8, u8, [u8; 2]]);
+
+/// The version of MIT-SCREEN-SAVER supported by the X server.
+///
+/// This is the reply to a `ScreenSaverQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenSaverVersion {
+	pub major_version: u16,
+	pub minor_version: u16,
+}
+
+assert_wire_size!("ScreenSaverQueryVersion reply": [u8, u8, u16, u32, u16, u16, [u8; 20]] == 32);
+
+impl Reply for ScreenSaverVersion {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[a0, a1, b0, b1] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u16::from_be_bytes([a0, a1]),
+			minor_version: u16::from_be_bytes([b0, b1]),
+		})
+	}
+}
+
+/// The MIT-SCREEN-SAVER extension's `ScreenSaverSuspend` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenSaverSuspend {
+	/// Whether to suspend the screen saver, or to resume it.
+	pub suspend: bool,
+}
+
+impl Request for ScreenSaverSuspend {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ScreenSaverSuspend";
+	const EXTENSION: Option<&'static str> = Some(SCREEN_SAVER);
+	const OPCODE: u8 = SCREEN_SAVER_SUSPEND;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.suspend.into());
+	}
+}
+
+assert_wire_size!(ScreenSaverSuspend: [u8, u8, u16, u32]);
+
+/// Defines DPMS requests with no body.
+macro_rules! dpms_requests {
+	($($(#[$attr:meta])* $name:ident: $reply:ty = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name;
+
+			impl Request for $name {
+				type Reply = $reply;
+
+				const NAME: &'static str = stringify!($name);
+				const EXTENSION: Option<&'static str> = Some(DPMS);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(1);
+
+				fn write_body(&self, _body: &mut BytesMut) {}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16]);
+		)*
+	};
+}
+
+dpms_requests! {
+	/// The DPMS extension's `DPMSCapable` request, whose reply is whether
+	/// the display supports DPMS.
+	DPMSCapable: DpmsCapability = DPMS_CAPABLE;
+	/// The DPMS extension's `DPMSEnable` request.
+	DPMSEnable: NoReply = DPMS_ENABLE;
+	/// The DPMS extension's `DPMSDisable` request.
+	DPMSDisable: NoReply = DPMS_DISABLE;
+	/// The DPMS extension's `DPMSInfo` request, whose reply is the display's
+	/// [`DpmsState`].
+	DPMSInfo: DpmsState = DPMS_INFO;
+}
+
+/// Whether the display supports DPMS.
+///
+/// This is the reply to a `DPMSCapable` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DpmsCapability {
+	pub capable: bool,
+}
+
+assert_wire_size!("DPMSCapable reply": [u8, u8, u16, u32, u8, [u8; 23]] == 32);
+
+impl Reply for DpmsCapability {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		Some(Self {
+			capable: *chunk.first()? != 0,
+		})
+	}
+}
+
+/// The display's DPMS power level, and whether DPMS is enabled.
+///
+/// This is the reply to a `DPMSInfo` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DpmsState {
+	/// The power level: `0` for on, `1` for standby, `2` for suspend and
+	/// `3` for off.
+	pub power_level: u16,
+	pub enabled: bool,
+}
+
+assert_wire_size!("DPMSInfo reply": [u8, u8, u16, u32, u16, u8, [u8; 21]] == 32);
+
+impl Reply for DpmsState {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[p0, p1, enabled] = chunk.get(..3)? else {
+			return None;
+		};
+
+		Some(Self {
+			power_level: u16::from_be_bytes([p0, p1]),
+			enabled: enabled != 0,
+		})
+	}
+}
+
+/// Suspends the screen saver with MIT-SCREEN-SAVER, returning its major
+/// opcode, or `None` if the X server doesn't support suspending it.
+async fn suspend(connection: &mut impl RoundTrip) -> Result<Option<u8>, ReplyError> {
+	let Some(screen_saver) = extension::query_extension(connection, SCREEN_SAVER).await? else {
+		return Ok(None);
+	};
+	let screen_saver = screen_saver.major_opcode;
+
+	// Suspending is the newest part of MIT-SCREEN-SAVER this crate uses, so
+	// its version is the one negotiated.
+	let (major, minor) = SUSPEND_VERSION;
+	let request = ScreenSaverQueryVersion {
+		major_version: major,
+		minor_version: minor,
+	};
+	let version = extension::negotiate_version(connection, SCREEN_SAVER, &request, |version| {
+		(version.major_version.into(), version.minor_version.into())
+	})
+	.await?;
+
+	if version < (major.into(), minor.into()) {
+		return Ok(None);
+	}
+
+	request::send(connection, &ScreenSaverSuspend { suspend: true }).await?;
+
+	Ok(Some(screen_saver))
+}
+
+/// Disables DPMS if it is enabled, returning the DPMS major opcode if it was
+/// disabled.
+async fn disable_dpms(connection: &mut impl RoundTrip) -> Result<Option<u8>, ReplyError> {
+	let Some(dpms) = extension::query_extension(connection, DPMS).await? else {
+		return Ok(None);
+	};
+	let dpms = dpms.major_opcode;
+
+	if !request::reply_to(connection, &DPMSCapable).await?.capable {
+		return Ok(None);
+	}
+	if !request::reply_to(connection, &DPMSInfo).await?.enabled {
+		return Ok(None);
+	}
+
+	request::send(connection, &DPMSDisable).await?;
+
+	Ok(Some(dpms))
+}
+
+/// Keeps the screen saver from activating and the display from sleeping
+/// until the returned [`ScreensaverInhibitor`] is released.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::inhibit_screensaver`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn inhibit_screensaver(
+	connection: &mut impl RoundTrip,
+) -> Result<ScreensaverInhibitor, ReplyError> {
+	let screen_saver = suspend(connection).await?;
+	let dpms = disable_dpms(connection).await?;
+
+	let xtest = match screen_saver {
+		Some(_) => None,
+		None => Some(
+			extension::require_extension(connection, XTEST)
+				.await?
+				.major_opcode,
+		),
+	};
+
+	Ok(ScreensaverInhibitor {
+		root: connection.writer().root_window(),
+
+		screen_saver,
+		dpms,
+		xtest,
+	})
+}
+
+impl Client {
+	/// Keeps the screen saver from activating and the display from sleeping
+	/// until the returned [`ScreensaverInhibitor`] is released.
+	///
+	/// The screen saver is suspended with MIT-SCREEN-SAVER 1.1 if possible,
+	/// and DPMS is disabled if it is enabled. If the screen saver can't be
+	/// suspended, [`ScreensaverInhibitor::keep_alive`] must be called
+	/// periodically to fake activity with XTEST instead; returns an
+	/// [`Unsupported`] error if XTEST isn't supported either.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn inhibit_screensaver(&mut self) -> Result<ScreensaverInhibitor, ReplyError> {
+		inhibit_screensaver(self).await
+	}
+}
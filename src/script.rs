@@ -0,0 +1,456 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A scripting facade mirroring the commands of `xdotool`.
+//!
+//! [`Script`] wraps a [`Client`] with methods named after `xdotool`'s most
+//! used commands, so that automation scripts can be ported to X.RS with
+//! little change:
+//!
+//! | `xdotool`         | [`Script`]                   |
+//! |-------------------|------------------------------|
+//! | `search`          | [`Script::search`]           |
+//! | `windowactivate`  | [`Script::window_activate`]  |
+//! | `getactivewindow` | [`Script::active_window`]    |
+//! | `key`             | [`Script::key`]              |
+//! | `type`            | [`Script::type_text`]        |
+//! | `mousemove`       | [`Script::mouse_move`]       |
+//!
+//! Keys are pressed with the XTEST extension, which the X server must
+//! support.
+
+use crate::{
+	atoms::Atom,
+	client::RoundTrip,
+	ewmh::{self, ActivationSource},
+	geometry::Point,
+	input::FakeInput,
+	keyboard::{KeyboardMapping, Keycode, Keysym},
+	pointer::WarpPointer,
+	property,
+	request,
+	time::Timestamp,
+	window::{GetWindowAttributes, MapState, QueryTree, Window},
+	Client,
+	ReplyError,
+};
+use std::{collections::VecDeque, error::Error, fmt, fmt::Formatter};
+
+/// The event code of `KeyPress` events.
+const KEY_PRESS: u8 = 2;
+/// The event code of `KeyRelease` events.
+const KEY_RELEASE: u8 = 3;
+
+/// The keysym of the left Shift key.
+const SHIFT_L: Keysym = 0xffe1;
+
+/// Keysym names accepted by [`Script::key`], besides single characters.
+///
+/// Names are matched case-insensitively.
+const KEYSYM_NAMES: &[(&str, Keysym)] = &[
+	("BackSpace", 0xff08),
+	("Tab", 0xff09),
+	("Return", 0xff0d),
+	("Enter", 0xff0d),
+	("Pause", 0xff13),
+	("Escape", 0xff1b),
+	("Esc", 0xff1b),
+	("Delete", 0xffff),
+	("Home", 0xff50),
+	("Left", 0xff51),
+	("Up", 0xff52),
+	("Right", 0xff53),
+	("Down", 0xff54),
+	("Page_Up", 0xff55),
+	("Prior", 0xff55),
+	("Page_Down", 0xff56),
+	("Next", 0xff56),
+	("End", 0xff57),
+	("Print", 0xff61),
+	("Insert", 0xff63),
+	("Menu", 0xff67),
+	("space", 0x20),
+	("F1", 0xffbe),
+	("F2", 0xffbf),
+	("F3", 0xffc0),
+	("F4", 0xffc1),
+	("F5", 0xffc2),
+	("F6", 0xffc3),
+	("F7", 0xffc4),
+	("F8", 0xffc5),
+	("F9", 0xffc6),
+	("F10", 0xffc7),
+	("F11", 0xffc8),
+	("F12", 0xffc9),
+	("Shift_L", 0xffe1),
+	("Shift_R", 0xffe2),
+	("Control_L", 0xffe3),
+	("Control_R", 0xffe4),
+	("Caps_Lock", 0xffe5),
+	("Meta_L", 0xffe7),
+	("Meta_R", 0xffe8),
+	("Alt_L", 0xffe9),
+	("Alt_R", 0xffea),
+	("Super_L", 0xffeb),
+	("Super_R", 0xffec),
+	// The aliases `xdotool` accepts for modifiers.
+	("shift", 0xffe1),
+	("ctrl", 0xffe3),
+	("control", 0xffe3),
+	("alt", 0xffe9),
+	("meta", 0xffe7),
+	("super", 0xffeb),
+];
+
+/// An error carrying out a [`Script`] command.
+#[derive(Debug)]
+pub enum ScriptError {
+	/// An error occurred sending a request or receiving its reply.
+	Reply(ReplyError),
+	/// A key name given to [`Script::key`] is not recognised.
+	UnknownKey(String),
+	/// No keycode in the keyboard mapping produces the keysym.
+	Unmapped(Keysym),
+}
+
+impl From<ReplyError> for ScriptError {
+	fn from(error: ReplyError) -> Self {
+		Self::Reply(error)
+	}
+}
+
+impl fmt::Display for ScriptError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Reply(error) => write!(f, "{error}"),
+			Self::UnknownKey(name) => write!(f, "unknown key name {name:?}"),
+			Self::Unmapped(keysym) => write!(f, "no keycode is mapped to keysym {keysym:#x}"),
+		}
+	}
+}
+
+impl Error for ScriptError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Reply(error) => Some(error),
+
+			_ => None,
+		}
+	}
+}
+
+/// Which windows [`Script::search`] matches.
+///
+/// Patterns are matched case-insensitively against any part of the window's
+/// property, rather than as regular expressions as in `xdotool`. Unlike
+/// `xdotool`, a window must match every pattern given, as with its `--all`
+/// option.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SearchQuery {
+	/// A pattern matched against the window's title (`--name`).
+	pub name: Option<String>,
+	/// A pattern matched against the window's class name (`--class`).
+	pub class: Option<String>,
+	/// A pattern matched against the window's instance name
+	/// (`--classname`).
+	pub instance: Option<String>,
+
+	/// Whether to only match windows which are viewable (`--onlyvisible`).
+	pub only_visible: bool,
+	/// How far below the root window to search (`--maxdepth`), or `None`
+	/// for no limit.
+	pub max_depth: Option<usize>,
+}
+
+/// Wraps a [`Client`] with methods mirroring `xdotool`'s commands.
+///
+/// See the [module documentation](self).
+pub struct Script<'client> {
+	client: &'client mut Client,
+
+	/// The keyboard mapping, fetched the first time keys are pressed.
+	keyboard: Option<KeyboardMapping>,
+}
+
+impl<'client> Script<'client> {
+	/// Creates a new `Script` sending requests with `client`.
+	pub fn new(client: &'client mut Client) -> Self {
+		Self {
+			client,
+			keyboard: None,
+		}
+	}
+
+	/// Returns the windows matching `query`, in breadth-first order from the
+	/// root window (`xdotool search`).
+	pub async fn search(&mut self, query: &SearchQuery) -> Result<Vec<Window>, ScriptError> {
+		let root = self.client.root_window();
+
+		let mut matches = Vec::new();
+		let mut queue = VecDeque::from([(root, 0)]);
+
+		while let Some((window, depth)) = queue.pop_front() {
+			if window != root && self.matches(window, query).await? {
+				matches.push(window);
+			}
+
+			if query.max_depth.is_none_or(|max_depth| depth < max_depth) {
+				queue.extend(
+					self.children(window)
+						.await?
+						.into_iter()
+						.map(|child| (child, depth + 1)),
+				);
+			}
+		}
+
+		Ok(matches)
+	}
+
+	/// Asks the window manager to activate `window`
+	/// (`xdotool windowactivate`).
+	///
+	/// Like `xdotool`, this says the request comes from a pager, so that
+	/// window managers with focus stealing prevention honour it.
+	pub async fn window_activate(&mut self, window: Window) -> Result<(), ScriptError> {
+		self.client
+			.activate_window(
+				window,
+				ActivationSource::Pager,
+				Timestamp::CURRENT_TIME,
+				None,
+			)
+			.await?;
+
+		Ok(())
+	}
+
+	/// Returns the window the window manager considers active
+	/// (`xdotool getactivewindow`).
+	pub async fn active_window(&mut self) -> Result<Option<Window>, ScriptError> {
+		Ok(self.client.active_window().await?)
+	}
+
+	/// Presses and releases the given keys (`xdotool key`).
+	///
+	/// `keys` is a whitespace-separated list of key combinations, each of
+	/// which is a list of keys joined with `+`, such as `ctrl+shift+t
+	/// Return`. Each key is a keysym name such as `Return` or `F5`, a
+	/// modifier alias such as `ctrl` or `super`, or a single character.
+	pub async fn key(&mut self, keys: &str) -> Result<(), ScriptError> {
+		for combination in keys.split_whitespace() {
+			let keysyms = combination
+				.split('+')
+				.map(parse_keysym)
+				.collect::<Result<Vec<_>, _>>()?;
+
+			let mut keycodes = Vec::with_capacity(keysyms.len());
+
+			for keysym in keysyms {
+				let (keycode, _) = self.keycode(keysym).await?;
+				keycodes.push(keycode);
+			}
+
+			for &keycode in &keycodes {
+				self.fake_key(KEY_PRESS, keycode).await?;
+			}
+			for &keycode in keycodes.iter().rev() {
+				self.fake_key(KEY_RELEASE, keycode).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Types `text` by pressing and releasing the key for each character,
+	/// holding Shift where needed (`xdotool type`).
+	///
+	/// Characters which aren't produced by any key in the current keyboard
+	/// mapping are an error; unlike `xdotool`, the mapping is not changed to
+	/// add them.
+	pub async fn type_text(&mut self, text: &str) -> Result<(), ScriptError> {
+		let (shift, _) = self.keycode(SHIFT_L).await?;
+
+		for char in text.chars() {
+			let (keycode, shifted) = self.keycode(char_keysym(char)).await?;
+
+			if shifted {
+				self.fake_key(KEY_PRESS, shift).await?;
+			}
+
+			self.fake_key(KEY_PRESS, keycode).await?;
+			self.fake_key(KEY_RELEASE, keycode).await?;
+
+			if shifted {
+				self.fake_key(KEY_RELEASE, shift).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Moves the pointer to `position` on the root window
+	/// (`xdotool mousemove`).
+	pub async fn mouse_move(&mut self, position: Point) -> Result<(), ScriptError> {
+		let root = self.client.root_window();
+
+		request::send_core(self.client.writer(), &WarpPointer::to(root, position))
+			.await
+			.map_err(ReplyError::Io)?;
+
+		Ok(())
+	}
+
+	/// Returns whether `window` matches `query`.
+	async fn matches(&mut self, window: Window, query: &SearchQuery) -> Result<bool, ReplyError> {
+		if query.name.is_some() {
+			let name = ewmh::window_title(&mut *self.client, window).await?;
+
+			if !contains(name.as_deref(), query.name.as_deref()) {
+				return Ok(false);
+			}
+		}
+
+		if query.class.is_some() || query.instance.is_some() {
+			let class =
+				property::get_property(&mut *self.client, window, Atom::WM_CLASS, Atom::STRING)
+					.await?
+					.and_then(|value| value.to_wm_class());
+			let (instance, class) = class.unzip();
+
+			if !contains(class.as_deref(), query.class.as_deref())
+				|| !contains(instance.as_deref(), query.instance.as_deref())
+			{
+				return Ok(false);
+			}
+		}
+
+		if query.only_visible && !self.is_viewable(window).await? {
+			return Ok(false);
+		}
+
+		Ok(true)
+	}
+
+	/// Returns whether `window` is viewable.
+	async fn is_viewable(&mut self, window: Window) -> Result<bool, ReplyError> {
+		let state = request::reply_to(&mut *self.client, &GetWindowAttributes(window)).await?;
+
+		Ok(state.map_state == MapState::Viewable)
+	}
+
+	/// Returns `window`'s children, from bottom to top of the stacking order.
+	async fn children(&mut self, window: Window) -> Result<Vec<Window>, ReplyError> {
+		Ok(request::reply_to(&mut *self.client, &QueryTree(window))
+			.await?
+			.children)
+	}
+
+	/// Returns a keycode which produces `keysym`, and whether Shift must be
+	/// held for it to do so.
+	async fn keycode(&mut self, keysym: Keysym) -> Result<(Keycode, bool), ScriptError> {
+		let keyboard = match &self.keyboard {
+			Some(keyboard) => keyboard,
+
+			None => {
+				let setup = self.client.setup();
+				let first_keycode = setup.min_keycode;
+				let count = setup.max_keycode - setup.min_keycode + 1;
+
+				let request = KeyboardMapping::request(first_keycode, count);
+				let keysyms = request::reply_to(&mut *self.client, &request).await?;
+
+				self.keyboard.insert(keysyms.into_mapping(first_keycode))
+			},
+		};
+
+		find_keycode(keyboard, keysym).ok_or(ScriptError::Unmapped(keysym))
+	}
+
+	/// Fakes a key press or release of `keycode` with XTEST.
+	async fn fake_key(&mut self, code: u8, keycode: Keycode) -> Result<(), ReplyError> {
+		let request = FakeInput {
+			event_type: code,
+			detail: keycode,
+			..FakeInput::default()
+		};
+
+		request::send(&mut *self.client, &request).await
+	}
+}
+
+/// Returns whether `value` contains `pattern`, ignoring case, or `true` if
+/// there is no pattern.
+fn contains(value: Option<&str>, pattern: Option<&str>) -> bool {
+	match (value, pattern) {
+		(_, None) => true,
+		(None, Some(_)) => false,
+
+		(Some(value), Some(pattern)) => value.to_lowercase().contains(&pattern.to_lowercase()),
+	}
+}
+
+/// Returns the keysym for a key name given to [`Script::key`].
+fn parse_keysym(name: &str) -> Result<Keysym, ScriptError> {
+	let mut chars = name.chars();
+
+	if let (Some(char), None) = (chars.next(), chars.next()) {
+		return Ok(char_keysym(char));
+	}
+
+	KEYSYM_NAMES
+		.iter()
+		.find(|(keysym_name, _)| keysym_name.eq_ignore_ascii_case(name))
+		.map(|&(_, keysym)| keysym)
+		.ok_or_else(|| ScriptError::UnknownKey(name.to_owned()))
+}
+
+/// Returns the keysym which types `char`.
+fn char_keysym(char: char) -> Keysym {
+	match char {
+		'\n' => 0xff0d,
+		'\t' => 0xff09,
+
+		// Latin-1 characters have keysyms equal to their code points.
+		' '..='~' | '\u{a0}'..='\u{ff}' => char as Keysym,
+		// Other Unicode characters have keysyms offset by `0x01000000`.
+		_ => 0x0100_0000 + char as Keysym,
+	}
+}
+
+/// Returns a keycode in `keyboard` which produces `keysym`, and whether Shift
+/// must be held for it to do so.
+fn find_keycode(keyboard: &KeyboardMapping, keysym: Keysym) -> Option<(Keycode, bool)> {
+	let width = usize::from(keyboard.keysyms_per_keycode);
+
+	if width == 0 {
+		return None;
+	}
+
+	let keycode = |index: usize| keyboard.first_keycode.checked_add((index / width) as u8);
+
+	// The first two keysyms of each keycode are produced without and with
+	// Shift.
+	let position = keyboard
+		.keysyms
+		.iter()
+		.enumerate()
+		.position(|(index, &candidate)| index % width < 2 && candidate == keysym);
+
+	if let Some(index) = position {
+		return Some((keycode(index)?, index % width == 1));
+	}
+
+	// A letter whose keycode only lists its lowercase keysym produces the
+	// uppercase keysym with Shift.
+	let lowercase = char::from_u32(keysym)
+		.filter(char::is_ascii_uppercase)?
+		.to_ascii_lowercase() as Keysym;
+
+	let index = keyboard.keysyms.chunks(width).position(|keysyms| {
+		keysyms.first() == Some(&lowercase) && keysyms.get(1).is_none_or(|&shifted| shifted == 0)
+	})?;
+
+	Some((keycode(index * width)?, true))
+}
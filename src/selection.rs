@@ -0,0 +1,1459 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Selections, such as the clipboard, as described by the ICCCM.
+//!
+//! A selection is owned by one client at a time, which converts it to the
+//! type, or target, another client asks for. The clipboard is the `CLIPBOARD`
+//! selection, and the text last selected with the mouse is the `PRIMARY`
+//! selection.
+//!
+//! [`Client::get_clipboard_text`] asks the clipboard's owner for its text,
+//! waiting for the owner to store it in a property of a temporary window.
+//! Events received in the meantime are kept, and returned by
+//! [`next_event`] afterwards:
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> Result<(), xrs::selection::SelectionError> {
+//! if let Some(text) = client.get_clipboard_text().await? {
+//!     println!("the clipboard holds {text:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Large values are sent in chunks with the `INCR` protocol, which is
+//! handled transparently. [`TransferOptions`] set how long to wait for each
+//! chunk, and how large the chunks a [`SelectionOwner`] sends are. Getting a
+//! selection must be done within a tokio runtime with its time driver
+//! enabled, for these timeouts.
+//!
+//! [`Client::set_clipboard_text`] takes ownership of the clipboard, returning
+//! a [`SelectionOwner`] which answers other clients' requests for the text as
+//! it is given their [`SelectionRequest`] events:
+//!
+//! ```no_run
+//! # use xrs::{event::Event, time::Timestamp, Client};
+//! # async fn example(client: &mut Client, time: Timestamp) -> Result<(), xrs::selection::SelectionError> {
+//! let Some(mut owner) = client.set_clipboard_text("Hello, world!", time).await? else {
+//!     return Ok(());
+//! };
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     if let Some(event) = Event::from_frame(&frame) {
+//!         owner.handle(client, &event).await?;
+//!     }
+//!
+//!     if !owner.is_owner() && !owner.is_transferring() {
+//!         // Another client has taken the clipboard, and the last value has
+//!         // been sent.
+//!         break;
+//!     }
+//! }
+//!
+//! owner.release(client.as_mut()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! An owner can [mirror] its value to other selections, such as `PRIMARY`
+//! for the clipboard, so that pasting with the middle mouse button pastes it
+//! too.
+//!
+//! Values other than text are offered in MIME types, such as `text/html` or
+//! `image/png`, with [`Client::offer_selection`]. [`mime_target`] and
+//! [`target_mime`] translate between MIME types and the targets X clients
+//! name them with. Several targets can be asked for at once with
+//! [`Client::get_selections`], which uses the ICCCM's `MULTIPLE` target.
+//!
+//! [`next_event`]: Client::next_event
+//! [`SelectionRequest`]: Event::SelectionRequest
+//! [mirror]: Client::mirror_selection
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	event::{Event, PropertyState, SelectionNotifyEvent, SelectionRequestEvent, SendEvent},
+	event_mask::EventMask,
+	geometry::Rectangle,
+	property::{self, Property, PropertyMode},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	window::{self, Window, WindowAttributes, WindowClass, WindowOptions},
+	xid::XidError,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{
+	error::Error,
+	fmt,
+	fmt::Formatter,
+	time::{Duration, Instant},
+};
+use tokio::{io, time};
+
+/// The major opcode of the `SetSelectionOwner` request.
+const SET_SELECTION_OWNER: u8 = 22;
+/// The major opcode of the `GetSelectionOwner` request.
+const GET_SELECTION_OWNER: u8 = 23;
+/// The major opcode of the `ConvertSelection` request.
+const CONVERT_SELECTION: u8 = 24;
+
+/// The name of the clipboard selection.
+const CLIPBOARD: &str = "CLIPBOARD";
+/// The name of the target listing the targets a selection can be converted
+/// to.
+const TARGETS: &str = "TARGETS";
+/// The name of the target for the time a selection was taken.
+const TIMESTAMP: &str = "TIMESTAMP";
+/// The name of the target and type for UTF-8 text.
+const UTF8_STRING: &str = "UTF8_STRING";
+/// The name of the type of values sent with the `INCR` protocol.
+const INCR: &str = "INCR";
+/// The name of the target for converting a selection to several targets at
+/// once.
+const MULTIPLE: &str = "MULTIPLE";
+/// The name of the type of the list of target and property pairs given with
+/// the `MULTIPLE` target.
+const ATOM_PAIR: &str = "ATOM_PAIR";
+
+/// The targets X clients name some MIME types with, as `(MIME type, target)`
+/// pairs.
+///
+/// Other MIME types are used as targets by their own name.
+const MIME_TARGETS: &[(&str, &str)] = &[
+	("text/plain;charset=utf-8", UTF8_STRING),
+	("text/plain;charset=iso-8859-1", "STRING"),
+];
+
+/// The name of the property selections are converted into.
+const SELECTION_PROPERTY: &str = "XRS_SELECTION";
+
+/// The size of the largest `ChangeProperty` request header, with BIG-REQUESTS'
+/// extended length, in bytes.
+const CHANGE_PROPERTY_HEADER: usize = 28;
+
+/// How selection values are transferred with the `INCR` protocol.
+///
+/// See [`Client::get_selection_with`] and
+/// [`SelectionOwner::set_transfer_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransferOptions {
+	/// The largest value a [`SelectionOwner`] sends at once, in bytes; larger
+	/// values are sent in chunks of this size.
+	///
+	/// Chunks are also limited to what fits in the [maximum request length].
+	///
+	/// [maximum request length]: Client::maximum_request_length
+	pub chunk_size: usize,
+	/// The longest to wait for the other client at each step of a transfer.
+	///
+	/// When getting a selection, this is how long to wait for the owner to
+	/// convert it, and for each chunk. A [`SelectionOwner`] abandons
+	/// transfers whose requestor hasn't asked for the next chunk in this
+	/// time.
+	pub timeout: Duration,
+}
+
+impl Default for TransferOptions {
+	fn default() -> Self {
+		Self {
+			chunk_size: 64 * 1024,
+			timeout: Duration::from_secs(5),
+		}
+	}
+}
+
+/// An error getting or owning a selection.
+#[derive(Debug)]
+pub enum SelectionError {
+	/// A window to receive or own the selection couldn't be created.
+	Window(XidError),
+	/// An error occurred communicating with the X server.
+	Reply(ReplyError),
+	/// The other client didn't respond within the [timeout].
+	///
+	/// [timeout]: TransferOptions::timeout
+	Timeout,
+}
+
+impl fmt::Display for SelectionError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Window(error) => write!(f, "error creating a selection window: {error}"),
+			Self::Reply(error) => write!(f, "{error}"),
+			Self::Timeout => write!(f, "timed out waiting for the selection"),
+		}
+	}
+}
+
+impl Error for SelectionError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Window(error) => Some(error),
+			Self::Reply(error) => Some(error),
+			Self::Timeout => None,
+		}
+	}
+}
+
+impl From<XidError> for SelectionError {
+	fn from(error: XidError) -> Self {
+		Self::Window(error)
+	}
+}
+
+impl From<ReplyError> for SelectionError {
+	fn from(error: ReplyError) -> Self {
+		Self::Reply(error)
+	}
+}
+
+impl From<io::Error> for SelectionError {
+	fn from(error: io::Error) -> Self {
+		Self::Reply(ReplyError::Io(error))
+	}
+}
+
+/// A value being sent to a requestor in chunks with the `INCR` protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Transfer {
+	requestor: Window,
+	property: Atom,
+
+	value: Property,
+	/// How much of the value has been sent, in bytes.
+	sent: usize,
+
+	/// When the transfer is abandoned if the requestor hasn't asked for the
+	/// next chunk.
+	deadline: Instant,
+}
+
+/// Ownership of a selection, answering requests for it with text, or with
+/// values set for other targets.
+///
+/// Requests are only answered when their events are given to
+/// [`SelectionOwner::handle`]. See [`Client::own_selection`].
+///
+/// Text larger than the [chunk size] is sent with the `INCR` protocol, which
+/// needs the requestor's `PropertyNotify` events to be given to `handle` too.
+///
+/// [chunk size]: TransferOptions::chunk_size
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the selection stays owned by an unresponsive window until it is released"]
+pub struct SelectionOwner {
+	/// The window which owns the selection.
+	window: Window,
+	selection: Atom,
+	/// When the selection was taken.
+	time: Timestamp,
+	/// The selections still owned by `window`: `selection`, and those it is
+	/// mirrored to.
+	owned: Vec<Atom>,
+
+	/// The text converted to the text targets, if there is any.
+	text: Option<String>,
+	/// The values converted to other targets.
+	values: Vec<(Atom, Property)>,
+
+	options: TransferOptions,
+	transfers: Vec<Transfer>,
+
+	targets: Atom,
+	timestamp: Atom,
+	utf8_string: Atom,
+	incr: Atom,
+	multiple: Atom,
+	atom_pair: Atom,
+}
+
+impl SelectionOwner {
+	/// Returns the window which owns the selection.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// Returns the selection owned.
+	pub const fn selection(&self) -> Atom {
+		self.selection
+	}
+
+	/// Returns the selections still owned: the selection, and those it has
+	/// been [mirrored] to.
+	///
+	/// [mirrored]: Client::mirror_selection
+	pub fn selections(&self) -> &[Atom] {
+		&self.owned
+	}
+
+	/// Returns whether any of the selections is still owned, which they are
+	/// until other clients take them.
+	pub fn is_owner(&self) -> bool {
+		!self.owned.is_empty()
+	}
+
+	/// Returns the text the selection is converted to, if any.
+	pub fn text(&self) -> Option<&str> {
+		self.text.as_deref()
+	}
+
+	/// Sets the text the selection is converted to.
+	pub fn set_text(&mut self, text: impl Into<String>) {
+		self.text = Some(text.into());
+	}
+
+	/// Sets the `value` the selection is converted to for `target`, such as
+	/// a `text/uri-list`.
+	///
+	/// This replaces any value already set for `target`.
+	pub fn set_value(&mut self, target: Atom, value: Property) {
+		match self
+			.values
+			.iter_mut()
+			.find(|(existing, _)| *existing == target)
+		{
+			Some((_, existing)) => *existing = value,
+			None => self.values.push((target, value)),
+		}
+	}
+
+	/// Returns how values are sent with the `INCR` protocol.
+	pub const fn transfer_options(&self) -> TransferOptions {
+		self.options
+	}
+
+	/// Sets how values are sent with the `INCR` protocol.
+	///
+	/// Transfers already in progress keep their chunk size.
+	pub fn set_transfer_options(&mut self, options: TransferOptions) {
+		self.options = options;
+	}
+
+	/// Returns whether a value is still being sent to a requestor with the
+	/// `INCR` protocol.
+	///
+	/// Transfers continue after the selection is taken by another client, so
+	/// the owner should keep handling events until they finish.
+	pub fn is_transferring(&self) -> bool {
+		!self.transfers.is_empty()
+	}
+
+	/// Returns the targets the selection can be converted to.
+	fn targets(&self) -> Vec<Atom> {
+		let mut targets = vec![self.targets, self.timestamp, self.multiple];
+
+		if let Some(text) = &self.text {
+			targets.push(self.utf8_string);
+
+			if Property::latin1(text).is_some() {
+				targets.push(Atom::STRING);
+			}
+		}
+
+		targets.extend(self.values.iter().map(|(target, _)| *target));
+
+		targets
+	}
+
+	/// Converts the selection to `target`, or returns `None` if it can't be.
+	fn convert(&self, target: Atom) -> Option<Property> {
+		match target {
+			target if target == self.targets => Some(Property::atoms(&self.targets())),
+			target if target == self.timestamp => {
+				Some(Property::u32s(Atom::INTEGER, &[self.time.0]))
+			},
+			target if target == self.utf8_string && self.text.is_some() => {
+				Some(Property::text(self.utf8_string, self.text.as_deref()?))
+			},
+			Atom::STRING if self.text.is_some() => Property::latin1(self.text.as_deref()?),
+
+			target => self
+				.values
+				.iter()
+				.find(|(value_target, _)| *value_target == target)
+				.map(|(_, value)| value.clone()),
+		}
+	}
+
+	/// Answers `SelectionRequest` events for the selections, and notices
+	/// when a `SelectionClear` event says one has been taken by another
+	/// client.
+	///
+	/// The selection can be converted to `TARGETS`, `TIMESTAMP`, `MULTIPLE`,
+	/// the targets of its values, and if it has text, `UTF8_STRING`, and
+	/// `STRING` if the text can be encoded as Latin-1.
+	///
+	/// `MULTIPLE` requests list their targets in a property of the requestor,
+	/// which is read with a round trip.
+	///
+	/// `PropertyNotify` events for values being sent with the `INCR` protocol
+	/// send their next chunk. Transfers whose requestor hasn't asked for the
+	/// next chunk within the [timeout] are abandoned.
+	///
+	/// Returns whether the event was for this selection.
+	///
+	/// [timeout]: TransferOptions::timeout
+	pub async fn handle(&mut self, client: &mut Client, event: &Event) -> Result<bool, ReplyError> {
+		let handled = self.handle_event(client, event).await?;
+
+		let now = Instant::now();
+		self.transfers.retain(|transfer| transfer.deadline > now);
+
+		Ok(handled)
+	}
+
+	/// Handles `event`, if it is for this selection.
+	async fn handle_event(
+		&mut self, connection: &mut impl RoundTrip, event: &Event,
+	) -> Result<bool, ReplyError> {
+		match event {
+			Event::SelectionRequest(request) if request.owner == self.window => {
+				self.answer(connection, request).await?;
+
+				Ok(true)
+			},
+
+			Event::SelectionClear(clear) if clear.owner == self.window => {
+				self.owned.retain(|&selection| selection != clear.selection);
+
+				Ok(true)
+			},
+
+			// The requestor deleted the last chunk, asking for the next.
+			Event::PropertyNotify(changed) if changed.state == PropertyState::Deleted => {
+				let Some(index) = self.transfers.iter().position(|transfer| {
+					transfer.requestor == changed.window && transfer.property == changed.atom
+				}) else {
+					return Ok(false);
+				};
+
+				self.send_chunk(connection.writer(), index)
+					.await
+					.map_err(ReplyError::Io)?;
+
+				Ok(true)
+			},
+
+			_ => Ok(false),
+		}
+	}
+
+	/// Stores the selection converted to `request`'s target in its property,
+	/// then tells the requestor whether it was converted.
+	async fn answer(
+		&mut self, connection: &mut impl RoundTrip, request: &SelectionRequestEvent,
+	) -> Result<(), ReplyError> {
+		// Obsolete clients don't name a property, so the target is used.
+		let property = match request.property {
+			Atom::NONE => request.target,
+			property => property,
+		};
+
+		// Requests from before the selection was taken are refused.
+		let current = self.owned.contains(&request.selection)
+			&& (request.time == Timestamp::CURRENT_TIME
+				|| self.time == Timestamp::CURRENT_TIME
+				|| !self.time.is_after(request.time));
+
+		let converted = match current {
+			false => Atom::NONE,
+
+			// `MULTIPLE` needs a property listing the targets, so obsolete
+			// clients can't use it.
+			true if request.target == self.multiple => match request.property {
+				Atom::NONE => Atom::NONE,
+				property => {
+					self.answer_multiple(connection, request.requestor, property)
+						.await?
+				},
+			},
+
+			true => match self.convert(request.target) {
+				Some(value) => {
+					self.send_value(connection.writer(), request.requestor, property, value)
+						.await
+						.map_err(ReplyError::Io)?;
+
+					property
+				},
+
+				None => Atom::NONE,
+			},
+		};
+
+		send_selection_notify(connection.writer(), request, converted)
+			.await
+			.map_err(ReplyError::Io)
+	}
+
+	/// Converts the selection to each target listed in `property` on
+	/// `requestor`, storing each in the property it is paired with.
+	///
+	/// The property of each target which can't be converted is replaced with
+	/// [`Atom::NONE`] in the list, as the ICCCM requires. Returns the property
+	/// to notify the requestor with, which is [`Atom::NONE`] if the list
+	/// couldn't be read.
+	async fn answer_multiple(
+		&mut self, connection: &mut impl RoundTrip, requestor: Window, property: Atom,
+	) -> Result<Atom, ReplyError> {
+		let Some(mut pairs) =
+			property::get_property(connection, requestor, property, self.atom_pair)
+				.await?
+				.and_then(|value| value.to_u32s())
+		else {
+			return Ok(Atom::NONE);
+		};
+
+		for pair in pairs.chunks_exact_mut(2) {
+			let (target, pair_property) = (Atom(pair[0]), Atom(pair[1]));
+
+			// A `MULTIPLE` target within `MULTIPLE` isn't converted.
+			let value = match target {
+				target if target == self.multiple => None,
+				target => self.convert(target),
+			};
+
+			match value {
+				Some(value) if pair_property != Atom::NONE => {
+					self.send_value(connection.writer(), requestor, pair_property, value)
+						.await
+						.map_err(ReplyError::Io)?;
+				},
+
+				_ => pair[1] = Atom::NONE.0,
+			}
+		}
+
+		requestor
+			.set_property(
+				connection.writer(),
+				property,
+				&Property::u32s(self.atom_pair, &pairs),
+				PropertyMode::Replace,
+			)
+			.await
+			.map_err(ReplyError::Io)?;
+
+		Ok(property)
+	}
+
+	/// Stores `value` in `property` on `requestor`, starting an `INCR`
+	/// transfer if it is larger than the chunk size.
+	async fn send_value(
+		&mut self, writer: &mut RequestWriter, requestor: Window, property: Atom, value: Property,
+	) -> io::Result<()> {
+		match value.data.len() > self.chunk_size(writer) {
+			true => {
+				self.start_transfer(writer, requestor, property, value)
+					.await
+			},
+
+			false => {
+				requestor
+					.set_property(writer, property, &value, PropertyMode::Replace)
+					.await
+			},
+		}
+	}
+
+	/// Returns the largest chunk sent at once, in bytes.
+	///
+	/// This is a multiple of 4, so that chunks never split 16 or 32-bit
+	/// values.
+	fn chunk_size(&self, writer: &RequestWriter) -> usize {
+		let maximum =
+			(writer.maximum_request_length() as usize * 4).saturating_sub(CHANGE_PROPERTY_HEADER);
+
+		(self.options.chunk_size.min(maximum) & !3).max(4)
+	}
+
+	/// Starts sending `value` to `requestor`'s `property` in chunks, by
+	/// storing its size with the `INCR` type.
+	///
+	/// The first chunk is sent once the requestor deletes the property.
+	async fn start_transfer(
+		&mut self, writer: &mut RequestWriter, requestor: Window, property: Atom, value: Property,
+	) -> io::Result<()> {
+		// The requestor's `PropertyNotify` events say when to send each chunk.
+		let attributes = WindowAttributes {
+			event_mask: Some(EventMask::PROPERTY_CHANGE),
+			..WindowAttributes::default()
+		};
+		requestor.set_attributes(writer, &attributes).await?;
+
+		let size = u32::try_from(value.data.len()).unwrap_or(u32::MAX);
+		requestor
+			.set_property(
+				writer,
+				property,
+				&Property::u32s(self.incr, &[size]),
+				PropertyMode::Replace,
+			)
+			.await?;
+
+		// A new request for the same property replaces any transfer to it.
+		self.transfers
+			.retain(|transfer| transfer.requestor != requestor || transfer.property != property);
+		self.transfers.push(Transfer {
+			requestor,
+			property,
+
+			value,
+			sent: 0,
+
+			deadline: Instant::now() + self.options.timeout,
+		});
+
+		Ok(())
+	}
+
+	/// Sends the next chunk of the transfer at `index`, finishing it with an
+	/// empty chunk once the whole value has been sent.
+	async fn send_chunk(&mut self, writer: &mut RequestWriter, index: usize) -> io::Result<()> {
+		let chunk_size = self.chunk_size(writer);
+		let deadline = Instant::now() + self.options.timeout;
+
+		let transfer = &mut self.transfers[index];
+		let end = transfer.value.data.len().min(transfer.sent + chunk_size);
+
+		let chunk = Property {
+			r#type: transfer.value.r#type,
+			format: transfer.value.format,
+			data: transfer.value.data[transfer.sent..end].to_vec(),
+		};
+		transfer
+			.requestor
+			.set_property(writer, transfer.property, &chunk, PropertyMode::Replace)
+			.await?;
+
+		transfer.sent = end;
+		transfer.deadline = deadline;
+
+		if chunk.data.is_empty() {
+			let transfer = self.transfers.swap_remove(index);
+
+			// Stop selecting the requestor's events, now that the transfer
+			// has finished.
+			let attributes = WindowAttributes {
+				event_mask: Some(EventMask::NONE),
+				..WindowAttributes::default()
+			};
+			transfer
+				.requestor
+				.set_attributes(writer, &attributes)
+				.await?;
+		}
+
+		Ok(())
+	}
+
+	/// Gives up ownership of the selections still owned, and destroys the
+	/// window which owned them.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		for &selection in &self.owned {
+			set_selection_owner(writer, None, selection, self.time).await?;
+		}
+
+		self.window.destroy(writer).await
+	}
+}
+
+/// Tells `request`'s requestor that the selection was converted into
+/// `property`, or that it couldn't be if `property` is [`Atom::NONE`].
+async fn send_selection_notify(
+	writer: &mut RequestWriter, request: &SelectionRequestEvent, property: Atom,
+) -> io::Result<()> {
+	let event = SelectionNotifyEvent {
+		time: request.time,
+		requestor: request.requestor,
+		selection: request.selection,
+		target: request.target,
+		property,
+	};
+
+	request::send_core(
+		writer,
+		&SendEvent {
+			propagate: false,
+			destination: request.requestor,
+			// No event mask: the event is sent to the requestor's owner.
+			event_mask: EventMask::NONE,
+			event: event.to_bytes(),
+		},
+	)
+	.await
+}
+
+/// The `SetSelectionOwner` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetSelectionOwner {
+	/// The new owner, or `None` to leave the selection without an owner.
+	pub owner: Option<Window>,
+	pub selection: Atom,
+	pub time: Timestamp,
+}
+
+impl Request for SetSelectionOwner {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SetSelectionOwner";
+	const OPCODE: u8 = SET_SELECTION_OWNER;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.owner.map_or(0, |owner| owner.0));
+		body.put_u32(self.selection.0);
+		body.put_u32(self.time.0);
+	}
+}
+
+assert_wire_size!(SetSelectionOwner: [u8, [u8; 1], u16, Window, Atom, Timestamp]);
+
+/// The `GetSelectionOwner` request, whose reply is the selection's
+/// [`CurrentOwner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetSelectionOwner(pub Atom);
+
+impl Request for GetSelectionOwner {
+	type Reply = CurrentOwner;
+
+	const NAME: &'static str = "GetSelectionOwner";
+	const OPCODE: u8 = GET_SELECTION_OWNER;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0 .0);
+	}
+}
+
+assert_wire_size!(GetSelectionOwner: [u8, [u8; 1], u16, Atom]);
+
+/// The window which owns a selection, or `None` if it has no owner.
+///
+/// This is the reply to a `GetSelectionOwner` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CurrentOwner(pub Option<Window>);
+
+assert_wire_size!("GetSelectionOwner reply": [u8, u8, u16, u32, Window, [u8; 20]] == 32);
+
+impl Reply for CurrentOwner {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[o0, o1, o2, o3] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self(match u32::from_be_bytes([o0, o1, o2, o3]) {
+			0 => None,
+			owner => Some(Window(owner)),
+		}))
+	}
+}
+
+/// The `ConvertSelection` request, which asks the owner of a selection to
+/// convert it to `target`, storing the result in `property` on `requestor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConvertSelection {
+	pub requestor: Window,
+	pub selection: Atom,
+	pub target: Atom,
+	/// The property to store the conversion in, or [`Atom::NONE`] to leave
+	/// it to the owner.
+	pub property: Atom,
+	pub time: Timestamp,
+}
+
+impl Request for ConvertSelection {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ConvertSelection";
+	const OPCODE: u8 = CONVERT_SELECTION;
+	const LENGTH: Option<u16> = Some(6);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.requestor.0);
+		body.put_u32(self.selection.0);
+		body.put_u32(self.target.0);
+		body.put_u32(self.property.0);
+		body.put_u32(self.time.0);
+	}
+}
+
+assert_wire_size!(ConvertSelection: [u8, [u8; 1], u16, Window, Atom, Atom, Atom, Timestamp]);
+
+/// Sets the owner of `selection` to `owner`, or to no window.
+async fn set_selection_owner(
+	writer: &mut RequestWriter, owner: Option<Window>, selection: Atom, time: Timestamp,
+) -> io::Result<()> {
+	request::send_core(
+		writer,
+		&SetSelectionOwner {
+			owner,
+			selection,
+			time,
+		},
+	)
+	.await
+}
+
+/// Returns the window which owns `selection`, if any.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_selection_owner`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_selection_owner(
+	connection: &mut impl RoundTrip, selection: Atom,
+) -> Result<Option<Window>, ReplyError> {
+	Ok(request::reply_to(connection, &GetSelectionOwner(selection))
+		.await?
+		.0)
+}
+
+/// Creates an unmapped `InputOnly` window to own or receive a selection.
+async fn create_selection_window(connection: &mut impl RoundTrip) -> Result<Window, XidError> {
+	let root = connection.writer().root_window();
+	let options = WindowOptions {
+		class: WindowClass::InputOnly,
+		..WindowOptions::default()
+	};
+
+	let area = Rectangle {
+		x: -1,
+		y: -1,
+		width: 1,
+		height: 1,
+	};
+
+	window::create_window(connection, root, area, &options).await
+}
+
+/// Takes ownership of `selection`, converting it to `text` if there is any.
+///
+/// Returns `None` if the selection couldn't be taken because it was taken
+/// by another client after `time`.
+pub(crate) async fn take_selection(
+	connection: &mut impl RoundTrip, selection: Atom, text: Option<String>, time: Timestamp,
+) -> Result<Option<SelectionOwner>, SelectionError> {
+	let targets = atoms::atom(connection, TARGETS).await?;
+	let timestamp = atoms::atom(connection, TIMESTAMP).await?;
+	let utf8_string = atoms::atom(connection, UTF8_STRING).await?;
+	let incr = atoms::atom(connection, INCR).await?;
+	let multiple = atoms::atom(connection, MULTIPLE).await?;
+	let atom_pair = atoms::atom(connection, ATOM_PAIR).await?;
+
+	let window = create_selection_window(connection).await?;
+
+	set_selection_owner(connection.writer(), Some(window), selection, time).await?;
+
+	if get_selection_owner(connection, selection).await? != Some(window) {
+		window.destroy(connection.writer()).await?;
+
+		return Ok(None);
+	}
+
+	Ok(Some(SelectionOwner {
+		window,
+		selection,
+		time,
+		owned: vec![selection],
+
+		text,
+		values: Vec::new(),
+
+		options: TransferOptions::default(),
+		transfers: Vec::new(),
+
+		targets,
+		timestamp,
+		utf8_string,
+		incr,
+		multiple,
+		atom_pair,
+	}))
+}
+
+/// Takes ownership of `selection` for `owner` too.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::mirror_selection`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn mirror_selection(
+	connection: &mut impl RoundTrip, owner: &mut SelectionOwner, selection: Atom,
+) -> Result<bool, ReplyError> {
+	if owner.owned.contains(&selection) {
+		return Ok(true);
+	}
+
+	set_selection_owner(
+		connection.writer(),
+		Some(owner.window),
+		selection,
+		owner.time,
+	)
+	.await
+	.map_err(ReplyError::Io)?;
+
+	if get_selection_owner(connection, selection).await? != Some(owner.window) {
+		return Ok(false);
+	}
+
+	owner.owned.push(selection);
+
+	Ok(true)
+}
+
+/// Returns the name of the target for the MIME type `mime_type`.
+///
+/// UTF-8 and Latin-1 text are named `UTF8_STRING` and `STRING`, as the ICCCM
+/// names them; other MIME types, such as `text/uri-list`, are targets by
+/// their own name.
+///
+/// ```
+/// use xrs::selection::mime_target;
+///
+/// assert_eq!(mime_target("text/plain;charset=utf-8"), "UTF8_STRING");
+/// assert_eq!(mime_target("image/png"), "image/png");
+/// ```
+pub fn mime_target(mime_type: &str) -> &str {
+	MIME_TARGETS
+		.iter()
+		.find(|(mime, _)| mime.eq_ignore_ascii_case(mime_type))
+		.map_or(mime_type, |(_, target)| target)
+}
+
+/// Returns the MIME type of the target named `target`, or `None` if it isn't
+/// a MIME type, such as `TARGETS`.
+///
+/// This is the reverse of [`mime_target`].
+///
+/// ```
+/// use xrs::selection::target_mime;
+///
+/// assert_eq!(target_mime("UTF8_STRING"), Some("text/plain;charset=utf-8"));
+/// assert_eq!(target_mime("text/uri-list"), Some("text/uri-list"));
+/// assert_eq!(target_mime("TIMESTAMP"), None);
+/// ```
+pub fn target_mime(target: &str) -> Option<&str> {
+	match MIME_TARGETS.iter().find(|(_, name)| *name == target) {
+		Some((mime, _)) => Some(mime),
+		None => target.contains('/').then_some(target),
+	}
+}
+
+/// Takes ownership of `selection` with a new window, converting it to the
+/// data of each of `offers` for the target of its MIME type.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::offer_selection`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn offer_selection(
+	connection: &mut impl RoundTrip, selection: Atom, offers: &[(&str, &[u8])], time: Timestamp,
+) -> Result<Option<SelectionOwner>, SelectionError> {
+	let mut values = Vec::with_capacity(offers.len());
+
+	for &(mime_type, data) in offers {
+		let target = atoms::atom(connection, mime_target(mime_type)).await?;
+
+		values.push((target, Property::u8s(target, data)));
+	}
+
+	let Some(mut owner) = take_selection(connection, selection, None, time).await? else {
+		return Ok(None);
+	};
+
+	for (target, value) in values {
+		owner.set_value(target, value);
+	}
+
+	Ok(Some(owner))
+}
+
+/// Sends any held requests, then receives the next event which `select`
+/// returns a value for within `timeout`, keeping any others.
+async fn wait_for_event<T>(
+	client: &mut Client, timeout: Duration, mut select: impl FnMut(Event) -> Option<T>,
+) -> Result<T, SelectionError> {
+	let frame = time::timeout(
+		timeout,
+		client.wait_for_frame(|frame| Event::from_frame(frame).and_then(&mut select).is_some()),
+	)
+	.await
+	.map_err(|_| SelectionError::Timeout)??
+	.ok_or(ReplyError::Disconnected)?;
+
+	Ok(Event::from_frame(&frame)
+		.and_then(select)
+		.ok_or_else(|| ReplyError::malformed("event"))?)
+}
+
+/// Asks the owner of `selection` to convert it to `target` in `property` on
+/// `window`, returning whether it was converted.
+async fn convert_selection(
+	client: &mut Client, window: Window, selection: Atom, target: Atom, property: Atom,
+	time: Timestamp, timeout: Duration,
+) -> Result<bool, SelectionError> {
+	let request = ConvertSelection {
+		requestor: window,
+		selection,
+		target,
+		property,
+		time,
+	};
+	request::send_core(client.writer(), &request).await?;
+
+	let notify = wait_for_event(client, timeout, |event| match event {
+		Event::SelectionNotify(notify) if notify.requestor == window => Some(notify),
+		_ => None,
+	})
+	.await?;
+
+	// The selection couldn't be converted, or has no owner.
+	Ok(notify.property != Atom::NONE)
+}
+
+/// Reads the value a selection was converted into in `property` on
+/// `window`, deleting it, unless it is the size of a value being sent with
+/// the `INCR` protocol.
+///
+/// Returns `None` if the property doesn't exist.
+async fn take_value(
+	client: &mut Client, window: Window, property: Atom, incr: Atom,
+) -> Result<Option<Property>, SelectionError> {
+	let Some(value) = property::get_property(client, window, property, Atom::NONE).await? else {
+		return Ok(None);
+	};
+
+	if value.r#type != incr {
+		window.delete_property(client.as_mut(), property).await?;
+	}
+
+	Ok(Some(value))
+}
+
+/// Receives the value being sent in chunks with the `INCR` protocol to
+/// `property` on `window`.
+async fn receive_incr(
+	client: &mut Client, window: Window, property: Atom, timeout: Duration,
+) -> Result<Option<Property>, SelectionError> {
+	// The value is sent in chunks, each written to the property once the
+	// last has been deleted, finishing with an empty chunk.
+	let attributes = WindowAttributes {
+		event_mask: Some(EventMask::PROPERTY_CHANGE),
+		..WindowAttributes::default()
+	};
+	window.set_attributes(client.as_mut(), &attributes).await?;
+	window.delete_property(client.as_mut(), property).await?;
+
+	// Selects changes to the property in `PropertyNotify` events.
+	let property_changed = |state: PropertyState| {
+		move |event| match event {
+			Event::PropertyNotify(changed)
+				if changed.window == window
+					&& changed.atom == property
+					&& changed.state == state =>
+			{
+				Some(changed)
+			},
+			_ => None,
+		}
+	};
+
+	let mut value: Option<Property> = None;
+
+	loop {
+		wait_for_event(client, timeout, property_changed(PropertyState::NewValue)).await?;
+
+		let Some(chunk) = property::get_property(client, window, property, Atom::NONE).await?
+		else {
+			continue;
+		};
+
+		window.delete_property(client.as_mut(), property).await?;
+
+		if chunk.data.is_empty() {
+			break;
+		}
+
+		match &mut value {
+			Some(value) => value.data.extend_from_slice(&chunk.data),
+			None => value = Some(chunk),
+		}
+	}
+
+	// Wait for the last chunk's deletion, so that its event isn't left to be
+	// received after the window is gone.
+	wait_for_event(client, timeout, property_changed(PropertyState::Deleted)).await?;
+
+	Ok(value)
+}
+
+/// Asks the owner of `selection` to convert it to `target` in `property` on
+/// `window`, and reads the result.
+async fn receive_selection(
+	client: &mut Client, window: Window, selection: Atom, target: Atom, property: Atom,
+	time: Timestamp, timeout: Duration,
+) -> Result<Option<Property>, SelectionError> {
+	let incr = atoms::atom(client, INCR).await?;
+
+	if !convert_selection(client, window, selection, target, property, time, timeout).await? {
+		return Ok(None);
+	}
+
+	match take_value(client, window, property, incr).await? {
+		Some(value) if value.r#type == incr => {
+			receive_incr(client, window, property, timeout).await
+		},
+		value => Ok(value),
+	}
+}
+
+/// Asks the owner of `selection` to convert it to each of `targets` at once
+/// with the `MULTIPLE` target, listing them in `property` on `window`, and
+/// reads the results.
+async fn receive_selections(
+	client: &mut Client, window: Window, selection: Atom, targets: &[Atom], property: Atom,
+	time: Timestamp, timeout: Duration,
+) -> Result<Vec<Option<Property>>, SelectionError> {
+	let incr = atoms::atom(client, INCR).await?;
+	let multiple = atoms::atom(client, MULTIPLE).await?;
+	let atom_pair = atoms::atom(client, ATOM_PAIR).await?;
+
+	let mut pairs = Vec::with_capacity(2 * targets.len());
+
+	for (i, target) in targets.iter().enumerate() {
+		let target_property = atoms::atom(client, &format!("{SELECTION_PROPERTY}_{i}")).await?;
+
+		pairs.extend([target.0, target_property.0]);
+	}
+
+	window
+		.set_property(
+			client.as_mut(),
+			property,
+			&Property::u32s(atom_pair, &pairs),
+			PropertyMode::Replace,
+		)
+		.await?;
+
+	let mut values = vec![None; targets.len()];
+
+	if !convert_selection(client, window, selection, multiple, property, time, timeout).await? {
+		return Ok(values);
+	}
+
+	// The owner replaces the property of each target it couldn't convert
+	// with `None`.
+	let pairs = property::get_property(client, window, property, atom_pair)
+		.await?
+		.and_then(|value| value.to_u32s())
+		.unwrap_or_default();
+	window.delete_property(client.as_mut(), property).await?;
+
+	let mut transfers = Vec::new();
+
+	for (value, pair) in values.iter_mut().zip(pairs.chunks_exact(2)) {
+		let target_property = Atom(pair[1]);
+
+		if target_property == Atom::NONE {
+			continue;
+		}
+
+		match take_value(client, window, target_property, incr).await? {
+			Some(incr_value) if incr_value.r#type == incr => {
+				transfers.push((value, target_property))
+			},
+			taken => *value = taken,
+		}
+	}
+
+	// Values sent with the `INCR` protocol are received one at a time, each
+	// starting when its property is deleted.
+	for (value, target_property) in transfers {
+		*value = receive_incr(client, window, target_property, timeout).await?;
+	}
+
+	Ok(values)
+}
+
+/// Asks the owner of `selection` at `time` to convert it to `target`, and
+/// reads the result.
+pub(crate) async fn get_selection(
+	client: &mut Client, selection: Atom, target: Atom, time: Timestamp, options: TransferOptions,
+) -> Result<Option<Property>, SelectionError> {
+	let property = atoms::atom(client, SELECTION_PROPERTY).await?;
+	let window = create_selection_window(client).await?;
+
+	let value = receive_selection(
+		client,
+		window,
+		selection,
+		target,
+		property,
+		time,
+		options.timeout,
+	)
+	.await;
+	window.destroy(client.as_mut()).await?;
+
+	value
+}
+
+/// Asks the owner of `selection` at `time` to convert it to each of
+/// `targets`, and reads the results.
+pub(crate) async fn get_selections(
+	client: &mut Client, selection: Atom, targets: &[Atom], time: Timestamp,
+	options: TransferOptions,
+) -> Result<Vec<Option<Property>>, SelectionError> {
+	let property = atoms::atom(client, SELECTION_PROPERTY).await?;
+	let window = create_selection_window(client).await?;
+
+	let values = receive_selections(
+		client,
+		window,
+		selection,
+		targets,
+		property,
+		time,
+		options.timeout,
+	)
+	.await;
+	window.destroy(client.as_mut()).await?;
+
+	values
+}
+
+/// Gets the text of `selection`, as UTF-8 if its owner supports it and
+/// otherwise as Latin-1.
+async fn get_selection_text(
+	client: &mut Client, selection: Atom,
+) -> Result<Option<String>, SelectionError> {
+	let utf8_string = atoms::atom(client, UTF8_STRING).await?;
+
+	let options = TransferOptions::default();
+
+	let time = Timestamp::CURRENT_TIME;
+
+	let value = match get_selection(client, selection, utf8_string, time, options).await? {
+		Some(value) => Some(value),
+		None => get_selection(client, selection, Atom::STRING, time, options).await?,
+	};
+
+	Ok(value.and_then(|value| value.to_text()))
+}
+
+/// Takes ownership of `selection` with a new window, converting it to
+/// `text` for other clients.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::own_selection`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn own_selection(
+	connection: &mut impl RoundTrip, selection: Atom, text: impl Into<String>, time: Timestamp,
+) -> Result<Option<SelectionOwner>, SelectionError> {
+	take_selection(connection, selection, Some(text.into()), time).await
+}
+
+/// Takes ownership of the clipboard, converting it to `text` for other
+/// clients.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_clipboard_text`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_clipboard_text(
+	connection: &mut impl RoundTrip, text: impl Into<String>, time: Timestamp,
+) -> Result<Option<SelectionOwner>, SelectionError> {
+	let clipboard = atoms::atom(connection, CLIPBOARD).await?;
+
+	take_selection(connection, clipboard, Some(text.into()), time).await
+}
+
+/// Takes ownership of the `PRIMARY` selection, converting it to `text`
+/// for other clients.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::set_primary_text`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_primary_text(
+	connection: &mut impl RoundTrip, text: impl Into<String>, time: Timestamp,
+) -> Result<Option<SelectionOwner>, SelectionError> {
+	take_selection(connection, Atom::PRIMARY, Some(text.into()), time).await
+}
+
+impl Client {
+	/// Asks the owner of `selection` to convert it to `target`, and returns
+	/// the result.
+	///
+	/// Returns `None` if the selection has no owner or couldn't be converted
+	/// to `target`. Values sent in chunks with the `INCR` protocol are
+	/// returned whole.
+	///
+	/// Events received while waiting for the owner are kept, and returned by
+	/// [`next_event`] afterwards. The owner must be another client, or a
+	/// [`SelectionOwner`] answering requests from another task.
+	///
+	/// Gives up with [`SelectionError::Timeout`] if the owner takes longer
+	/// than the default [timeout] to respond.
+	///
+	/// [`next_event`]: Client::next_event
+	/// [timeout]: TransferOptions::timeout
+	pub async fn get_selection(
+		&mut self, selection: Atom, target: Atom,
+	) -> Result<Option<Property>, SelectionError> {
+		get_selection(
+			self,
+			selection,
+			target,
+			Timestamp::CURRENT_TIME,
+			TransferOptions::default(),
+		)
+		.await
+	}
+
+	/// Asks the owner of `selection` to convert it to `target`, like
+	/// [`get_selection`], waiting for it as long as `options`' [timeout].
+	///
+	/// The [chunk size] only applies to owning selections, so it is ignored.
+	///
+	/// [`get_selection`]: Client::get_selection
+	/// [timeout]: TransferOptions::timeout
+	/// [chunk size]: TransferOptions::chunk_size
+	pub async fn get_selection_with(
+		&mut self, selection: Atom, target: Atom, options: TransferOptions,
+	) -> Result<Option<Property>, SelectionError> {
+		get_selection(self, selection, target, Timestamp::CURRENT_TIME, options).await
+	}
+
+	/// Gets the text on the clipboard, or `None` if it holds no text.
+	///
+	/// See [`Client::get_selection`].
+	pub async fn get_clipboard_text(&mut self) -> Result<Option<String>, SelectionError> {
+		let clipboard = atoms::atom(self, CLIPBOARD).await?;
+
+		get_selection_text(self, clipboard).await
+	}
+
+	/// Gets the text in the `PRIMARY` selection, usually the text last
+	/// selected, or `None` if there is none.
+	///
+	/// See [`Client::get_selection`].
+	pub async fn get_primary_text(&mut self) -> Result<Option<String>, SelectionError> {
+		get_selection_text(self, Atom::PRIMARY).await
+	}
+
+	/// Asks the owner of `selection` to convert it to each of `targets` at
+	/// once, and returns the results in the same order.
+	///
+	/// Each result is `None` if the selection couldn't be converted to its
+	/// target; all are `None` if the selection has no owner, or its owner
+	/// doesn't support the `MULTIPLE` target. See [`Client::get_selection`].
+	pub async fn get_selections(
+		&mut self, selection: Atom, targets: &[Atom],
+	) -> Result<Vec<Option<Property>>, SelectionError> {
+		get_selections(
+			self,
+			selection,
+			targets,
+			Timestamp::CURRENT_TIME,
+			TransferOptions::default(),
+		)
+		.await
+	}
+
+	/// Returns the window which owns `selection`, if any.
+	pub async fn get_selection_owner(
+		&mut self, selection: Atom,
+	) -> Result<Option<Window>, ReplyError> {
+		get_selection_owner(self, selection).await
+	}
+
+	/// Takes ownership of `selection` with a new window, converting it to
+	/// `text` for other clients.
+	///
+	/// `time` should be the timestamp of the event which caused the
+	/// selection to be taken, such as a key press, as the ICCCM requires.
+	/// Returns `None` if another client took the selection after `time`.
+	pub async fn own_selection(
+		&mut self, selection: Atom, text: impl Into<String>, time: Timestamp,
+	) -> Result<Option<SelectionOwner>, SelectionError> {
+		own_selection(self, selection, text, time).await
+	}
+
+	/// Takes ownership of the clipboard, converting it to `text` for other
+	/// clients.
+	///
+	/// See [`Client::own_selection`].
+	pub async fn set_clipboard_text(
+		&mut self, text: impl Into<String>, time: Timestamp,
+	) -> Result<Option<SelectionOwner>, SelectionError> {
+		set_clipboard_text(self, text, time).await
+	}
+
+	/// Takes ownership of the `PRIMARY` selection, converting it to `text`
+	/// for other clients, as when text is selected.
+	///
+	/// See [`Client::own_selection`].
+	pub async fn set_primary_text(
+		&mut self, text: impl Into<String>, time: Timestamp,
+	) -> Result<Option<SelectionOwner>, SelectionError> {
+		set_primary_text(self, text, time).await
+	}
+
+	/// Takes ownership of `selection` for `owner` too, so that it answers
+	/// requests for `selection` with the same values.
+	///
+	/// Mirroring the clipboard to `PRIMARY` lets what was copied be pasted
+	/// with the middle mouse button too:
+	///
+	/// ```no_run
+	/// # use xrs::{atoms::Atom, time::Timestamp, Client};
+	/// # async fn example(client: &mut Client, time: Timestamp) -> Result<(), xrs::selection::SelectionError> {
+	/// if let Some(mut owner) = client.set_clipboard_text("Hello, world!", time).await? {
+	///     client.mirror_selection(&mut owner, Atom::PRIMARY).await?;
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// Returns whether the selection was taken: it isn't if another client
+	/// took it after `owner`'s selection was taken.
+	pub async fn mirror_selection(
+		&mut self, owner: &mut SelectionOwner, selection: Atom,
+	) -> Result<bool, ReplyError> {
+		mirror_selection(self, owner, selection).await
+	}
+
+	/// Takes ownership of `selection` with a new window, converting it to the
+	/// data of each of `offers` for the target of its MIME type.
+	///
+	/// Each offer is a MIME type and its data, such as
+	/// `("text/html", b"<b>bold</b>")`; see [`mime_target`] for the targets
+	/// they are offered as. Offering text as
+	/// `text/plain;charset=utf-8` offers it as `UTF8_STRING`.
+	///
+	/// See [`Client::own_selection`].
+	pub async fn offer_selection(
+		&mut self, selection: Atom, offers: &[(&str, &[u8])], time: Timestamp,
+	) -> Result<Option<SelectionOwner>, SelectionError> {
+		offer_selection(self, selection, offers, time).await
+	}
+}
@@ -0,0 +1,381 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Identifying the X server, and the quirks known for it.
+//!
+//! X servers differ in what they can do: Xwayland can't capture or send input
+//! to Wayland clients, and neither it nor XQuartz can reconfigure outputs with
+//! RandR. [`Client::detect_server`] identifies the kind of X server from its
+//! vendor string and the extensions it supports, and its [`ServerQuirks`]
+//! say which operations are known not to work as they would on Xorg.
+//! [`ServerQuirks::limitations`] lists them as [`Limitation`]s, with
+//! descriptions which can be shown to users.
+//!
+//! Applications which only need to know whether they are running under
+//! Xwayland can use [`Client::is_xwayland`].
+//!
+//! Some of xrs's own helpers consult the quirks too:
+//!
+//! - `Client::relative_pointer` returns an [`Unsupported`] error instead of
+//!   warping the pointer back after each motion if that would stop working once
+//!   the pointer leaves the window, if the `pointer` feature is enabled.
+//! - `Client::set_crtc_config`, and so `Client::set_output_mode`, returns an
+//!   [`Unsupported`] error instead of configuring outputs which won't change,
+//!   if the `randr` feature is enabled.
+//! - `KeyboardCapture::needs_focus` says whether the keyboard must be captured
+//!   again whenever the window is focused, if the `xwayland` feature is
+//!   enabled.
+//!
+//! ```no_run
+//! # use xrs::Client;
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let server = client.detect_server().await?;
+//!
+//! for limitation in server.quirks.limitations() {
+//!     eprintln!("warning: {limitation}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Unsupported`]: std::io::ErrorKind::Unsupported
+
+use crate::{client::RoundTrip, extension, Client, ReplyError, Setup};
+#[cfg(feature = "randr")]
+use crate::{randr, window::Window};
+use std::fmt;
+
+/// The vendor string of the X.Org Foundation's X server and those built from
+/// it.
+const XORG_VENDOR: &str = "The X.Org Foundation";
+
+/// The name of the extension only present on Xwayland.
+const XWAYLAND: &str = "XWAYLAND";
+/// The prefix of the names of Xwayland's RandR outputs.
+#[cfg(feature = "randr")]
+const XWAYLAND_OUTPUT_PREFIX: &str = "XWAYLAND";
+/// The name of the extension only present on XQuartz.
+const APPLE_WM: &str = "Apple-WM";
+/// The name of the extension present on TigerVNC and TightVNC's X servers.
+const VNC_EXTENSION: &str = "VNC-EXTENSION";
+
+/// The kind of X server a [`Client`] is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServerKind {
+	/// The X.Org Foundation's X server.
+	///
+	/// Other X servers built from it which don't identify themselves, such
+	/// as Xvfb and Xephyr, are also detected as `Xorg`.
+	Xorg,
+	/// Xwayland, which runs X clients under a Wayland compositor.
+	Xwayland,
+	/// XQuartz, which runs X clients on macOS.
+	XQuartz,
+	/// A VNC server's X server, such as Xvnc.
+	Vnc,
+	/// Any other X server.
+	Other,
+}
+
+/// The version of an X server built from the X.Org Foundation's, decoded from
+/// its release number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+	pub major: u32,
+	pub minor: u32,
+	pub patch: u32,
+	pub snapshot: u32,
+}
+
+impl ServerVersion {
+	/// Decodes the version encoded in an X.Org release number as
+	/// `major * 10_000_000 + minor * 100_000 + patch * 1_000 + snapshot`.
+	pub const fn from_release_number(release_number: u32) -> Self {
+		Self {
+			major: release_number / 10_000_000,
+			minor: release_number / 100_000 % 100,
+			patch: release_number / 1_000 % 100,
+			snapshot: release_number % 1_000,
+		}
+	}
+}
+
+impl fmt::Display for ServerVersion {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+		if self.snapshot != 0 {
+			write!(f, ".{}", self.snapshot)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Operations known not to work on an X server as they do on Xorg.
+///
+/// The [`Default`] assumes the X server has no quirks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ServerQuirks {
+	/// Whether RandR configuration requests, such as setting a CRTC's mode,
+	/// are accepted but don't change the real outputs.
+	pub emulates_randr_configuration: bool,
+	/// Whether keyboard and pointer grabs only take effect while one of the
+	/// X server's windows has the focus.
+	pub grabs_only_while_focused: bool,
+	/// Whether capturing the root window only shows X windows, and not those
+	/// of other clients of the display server.
+	pub captures_only_x_windows: bool,
+	/// Whether the pointer is only warped while it is over one of the
+	/// client's windows.
+	pub warps_only_over_own_windows: bool,
+	/// Whether input synthesized with XTEST only reaches X clients.
+	pub synthetic_input_only_reaches_x_clients: bool,
+}
+
+impl ServerQuirks {
+	/// Returns the quirks known for `kind` of X server.
+	pub const fn for_kind(kind: ServerKind) -> Self {
+		match kind {
+			ServerKind::Xwayland => Self {
+				emulates_randr_configuration: true,
+				grabs_only_while_focused: true,
+				captures_only_x_windows: true,
+				warps_only_over_own_windows: true,
+				synthetic_input_only_reaches_x_clients: true,
+			},
+
+			ServerKind::XQuartz => Self {
+				emulates_randr_configuration: true,
+				grabs_only_while_focused: true,
+				captures_only_x_windows: true,
+				warps_only_over_own_windows: false,
+				synthetic_input_only_reaches_x_clients: true,
+			},
+
+			ServerKind::Xorg | ServerKind::Vnc | ServerKind::Other => Self {
+				emulates_randr_configuration: false,
+				grabs_only_while_focused: false,
+				captures_only_x_windows: false,
+				warps_only_over_own_windows: false,
+				synthetic_input_only_reaches_x_clients: false,
+			},
+		}
+	}
+
+	/// Returns the operations which these quirks stop from working as they
+	/// would on Xorg.
+	pub fn limitations(&self) -> Vec<Limitation> {
+		[
+			(self.grabs_only_while_focused, Limitation::GlobalGrabs),
+			(self.captures_only_x_windows, Limitation::ScreenCapture),
+			(self.warps_only_over_own_windows, Limitation::PointerWarping),
+			(
+				self.synthetic_input_only_reaches_x_clients,
+				Limitation::SyntheticInput,
+			),
+			(
+				self.emulates_randr_configuration,
+				Limitation::OutputConfiguration,
+			),
+		]
+		.into_iter()
+		.filter_map(|(applies, limitation)| applies.then_some(limitation))
+		.collect()
+	}
+}
+
+/// An operation known not to work on an X server as it does on Xorg.
+///
+/// These are advisory: applications can use them to warn the user or fall
+/// back to another way of doing the operation, such as a desktop portal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limitation {
+	/// Global keyboard shortcuts and other grabs, which don't receive input
+	/// while the display server's own clients have the focus, nor while the
+	/// compositor's shortcuts are not inhibited.
+	GlobalGrabs,
+	/// Capturing the screen, which only shows X windows.
+	ScreenCapture,
+	/// Warping the pointer, which only works while it is over one of the
+	/// client's windows.
+	PointerWarping,
+	/// Synthesizing input with XTEST, which only reaches X clients.
+	SyntheticInput,
+	/// Reconfiguring outputs with RandR, which doesn't change the real
+	/// outputs.
+	OutputConfiguration,
+}
+
+impl Limitation {
+	/// Returns a description of the limitation which can be shown to users.
+	pub const fn description(self) -> &'static str {
+		match self {
+			Self::GlobalGrabs => "global shortcuts only work while an X window is focused",
+			Self::ScreenCapture => "screen captures only show X windows",
+			Self::PointerWarping => "the pointer can only be moved over this application's windows",
+			Self::SyntheticInput => "simulated input only reaches X applications",
+			Self::OutputConfiguration => "display configuration changes are not applied",
+		}
+	}
+}
+
+impl fmt::Display for Limitation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.description())
+	}
+}
+
+/// The X server a [`Client`] is connected to, as identified by
+/// [`Client::detect_server`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerInfo {
+	pub kind: ServerKind,
+	/// The vendor string from the connection setup.
+	pub vendor: String,
+	/// The vendor's release number from the connection setup.
+	pub release_number: u32,
+	/// The version decoded from `release_number`, if the X server was built
+	/// from the X.Org Foundation's.
+	pub version: Option<ServerVersion>,
+	pub quirks: ServerQuirks,
+}
+
+impl ServerInfo {
+	/// Identifies the X server from its vendor string alone.
+	///
+	/// Xwayland and XQuartz use the X.Org Foundation's vendor string, so
+	/// they are detected as [`ServerKind::Xorg`]; [`Client::detect_server`]
+	/// also checks for their extensions.
+	pub fn from_setup(setup: &Setup) -> Self {
+		let kind = if setup.vendor.contains("VNC") {
+			ServerKind::Vnc
+		} else if setup.vendor == XORG_VENDOR {
+			ServerKind::Xorg
+		} else {
+			ServerKind::Other
+		};
+
+		Self::new(kind, setup)
+	}
+
+	fn new(kind: ServerKind, setup: &Setup) -> Self {
+		Self {
+			kind,
+			vendor: setup.vendor.clone(),
+			release_number: setup.release_number,
+			version: (setup.vendor == XORG_VENDOR)
+				.then(|| ServerVersion::from_release_number(setup.release_number)),
+			quirks: ServerQuirks::for_kind(kind),
+		}
+	}
+}
+
+/// Returns whether the X server is Xwayland.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::is_xwayland`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn is_xwayland(connection: &mut impl RoundTrip) -> Result<bool, ReplyError> {
+	if extension::query_extension(connection, XWAYLAND)
+		.await?
+		.is_some()
+	{
+		return Ok(true);
+	}
+
+	#[cfg(feature = "randr")]
+	if extension::query_extension(connection, randr::RANDR)
+		.await?
+		.is_some()
+	{
+		let root = Window(connection.writer().default_screen().root);
+		let resources = randr::screen_resources(connection, root, true).await?;
+
+		if let Some(&output) = resources.outputs.first() {
+			let info =
+				randr::get_output_info(connection, output, resources.config_timestamp).await?;
+
+			return Ok(info.name.starts_with(XWAYLAND_OUTPUT_PREFIX));
+		}
+	}
+
+	Ok(false)
+}
+
+/// Identifies the X server from its vendor string and the extensions it
+/// supports.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::detect_server`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn detect_server(connection: &mut impl RoundTrip) -> Result<ServerInfo, ReplyError> {
+	if let Some(server) = &connection.writer().server {
+		return Ok(server.clone());
+	}
+
+	let kind = if is_xwayland(connection).await? {
+		ServerKind::Xwayland
+	} else if extension::query_extension(connection, APPLE_WM)
+		.await?
+		.is_some()
+	{
+		ServerKind::XQuartz
+	} else if extension::query_extension(connection, VNC_EXTENSION)
+		.await?
+		.is_some()
+	{
+		ServerKind::Vnc
+	} else {
+		ServerInfo::from_setup(connection.writer().setup()).kind
+	};
+
+	let server = ServerInfo::new(kind, connection.writer().setup());
+	connection.writer().server = Some(server.clone());
+
+	Ok(server)
+}
+
+/// Returns the quirks known for the X server, identifying it if it hasn't
+/// been already.
+#[cfg_attr(
+	not(any(
+		feature = "ewmh",
+		feature = "pointer",
+		feature = "randr",
+		feature = "xwayland"
+	)),
+	allow(dead_code)
+)]
+pub(crate) async fn quirks(connection: &mut impl RoundTrip) -> Result<ServerQuirks, ReplyError> {
+	Ok(detect_server(connection).await?.quirks)
+}
+
+impl Client {
+	/// Identifies the X server from its vendor string and the extensions it
+	/// supports, returning the quirks known for it.
+	///
+	/// The result is cached, so only the first call makes round trips to the
+	/// X server.
+	pub async fn detect_server(&mut self) -> Result<ServerInfo, ReplyError> {
+		detect_server(self).await
+	}
+
+	/// Returns whether the X server is Xwayland, running under a Wayland
+	/// compositor.
+	///
+	/// Xwayland is identified by its XWAYLAND extension, or, for versions of
+	/// Xwayland older than the extension, by the names of its RandR outputs
+	/// if the `randr` feature is enabled. See [`ServerQuirks::for_kind`] for
+	/// what works differently under Xwayland.
+	pub async fn is_xwayland(&mut self) -> Result<bool, ReplyError> {
+		is_xwayland(self).await
+	}
+}
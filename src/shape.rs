@@ -0,0 +1,541 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The SHAPE extension, which gives windows non-rectangular shapes.
+//!
+//! A window has three shapes: its bounding shape, outside of which it is not
+//! drawn, its clip shape, outside of which its contents are not drawn, and
+//! its input shape, outside of which it receives no pointer input. Shapes are
+//! [`Region`]s, changed with [`Client::set_shape`] and the related methods
+//! and read with [`Client::get_shape`].
+//!
+//! [`Client::overlay_window`] combines an empty input shape with a 32-bit
+//! visual to create a transparent window which clicks pass through, for
+//! screen annotation and HUD tools.
+
+use crate::{
+	client::RoundTrip,
+	gc::ClipOrdering,
+	geometry::{Point, Rectangle},
+	raw::{assert_wire_size, X11Frame},
+	region::Region,
+	request::{self, NoReply, Reply, Request},
+	window::{self, StackMode, Window, WindowAttributes, WindowChanges},
+	xid::XidError,
+	Client,
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the SHAPE extension.
+const SHAPE: &str = "SHAPE";
+
+/// The minor opcode of the `ShapeQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `ShapeRectangles` request.
+const RECTANGLES: u8 = 1;
+/// The minor opcode of the `ShapeMask` request.
+const MASK: u8 = 2;
+/// The minor opcode of the `ShapeCombine` request.
+const COMBINE: u8 = 3;
+/// The minor opcode of the `ShapeOffset` request.
+const OFFSET: u8 = 4;
+/// The minor opcode of the `ShapeGetRectangles` request.
+const GET_RECTANGLES: u8 = 8;
+
+/// Which of a window's shapes to change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeKind {
+	/// The area outside of which the window, including its border, is not
+	/// drawn.
+	Bounding,
+	/// The area outside of which the window's contents are not drawn.
+	Clip,
+	/// The area outside of which the window receives no pointer input.
+	///
+	/// Input shapes were added in version 1.1 of the extension.
+	Input,
+}
+
+/// How a new shape is combined with a window's existing shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeOperation {
+	/// The new shape replaces the existing shape.
+	Set,
+	/// The shape covers the areas of both.
+	Union,
+	/// The shape covers the area covered by both.
+	Intersect,
+	/// The new shape is cut out of the existing shape.
+	Subtract,
+	/// The existing shape is cut out of the new shape.
+	Invert,
+}
+
+/// The SHAPE extension's `ShapeQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion;
+
+impl Request for QueryVersion {
+	type Reply = QueryVersionReply;
+
+	const NAME: &'static str = "ShapeQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16]);
+
+/// The version of SHAPE supported by the X server.
+///
+/// This is the reply to a `ShapeQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersionReply {
+	pub major_version: u16,
+	pub minor_version: u16,
+}
+
+assert_wire_size!("ShapeQueryVersion reply": [u8, u8, u16, u32, u16, u16, [u8; 20]] == 32);
+
+impl Reply for QueryVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[major0, major1, minor0, minor1] = chunk.get(0..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u16::from_be_bytes([major0, major1]),
+			minor_version: u16::from_be_bytes([minor0, minor1]),
+		})
+	}
+}
+
+/// The SHAPE extension's `ShapeRectangles` request.
+///
+/// See [`Client::set_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rectangles<'a> {
+	pub operation: ShapeOperation,
+	pub kind: ShapeKind,
+	/// How `rectangles` are sorted.
+	pub ordering: ClipOrdering,
+	pub window: Window,
+	/// The offset of `rectangles` from the window's origin.
+	pub offset: Point,
+	pub rectangles: &'a [Rectangle],
+}
+
+impl Request for Rectangles<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShapeRectangles";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = RECTANGLES;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.operation as u8);
+		body.put_u8(self.kind as u8);
+		body.put_u8(self.ordering as u8);
+		// 1 unused byte.
+		body.put_u8(0);
+		body.put_u32(self.window.0);
+		self.offset.write_to(body);
+
+		for rectangle in self.rectangles {
+			rectangle.write_to(body);
+		}
+	}
+}
+
+// Followed by the rectangles.
+assert_wire_size!(
+	"ShapeRectangles request": [u8, u8, u16, u8, u8, u8, [u8; 1], Window, Point] == 16
+);
+
+/// The SHAPE extension's `ShapeMask` request.
+///
+/// See [`Client::set_shape_mask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mask {
+	pub operation: ShapeOperation,
+	pub kind: ShapeKind,
+	pub window: Window,
+	/// The offset of `mask` from the window's origin.
+	pub offset: Point,
+	/// The 1-bit pixmap to use as the shape, or `None` to remove the shape.
+	pub mask: Option<u32>,
+}
+
+impl Request for Mask {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShapeMask";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = MASK;
+	const LENGTH: Option<u16> = Some(5);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.operation as u8);
+		body.put_u8(self.kind as u8);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_u32(self.window.0);
+		self.offset.write_to(body);
+		body.put_u32(self.mask.unwrap_or(0));
+	}
+}
+
+assert_wire_size!(Mask: [u8, u8, u16, u8, u8, [u8; 2], Window, Point, u32]);
+
+/// The SHAPE extension's `ShapeCombine` request.
+///
+/// See [`Client::combine_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Combine {
+	pub operation: ShapeOperation,
+	pub kind: ShapeKind,
+	pub source_kind: ShapeKind,
+	pub window: Window,
+	/// The offset of `source`'s shape from the window's origin.
+	pub offset: Point,
+	pub source: Window,
+}
+
+impl Request for Combine {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShapeCombine";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = COMBINE;
+	const LENGTH: Option<u16> = Some(5);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.operation as u8);
+		body.put_u8(self.kind as u8);
+		body.put_u8(self.source_kind as u8);
+		// 1 unused byte.
+		body.put_u8(0);
+		body.put_u32(self.window.0);
+		self.offset.write_to(body);
+		body.put_u32(self.source.0);
+	}
+}
+
+assert_wire_size!(Combine: [u8, u8, u16, u8, u8, u8, u8, Window, Point, Window]);
+
+/// The SHAPE extension's `ShapeOffset` request.
+///
+/// See [`Client::offset_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Offset {
+	pub kind: ShapeKind,
+	pub window: Window,
+	pub offset: Point,
+}
+
+impl Request for Offset {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShapeOffset";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = OFFSET;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.kind as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+		body.put_u32(self.window.0);
+		self.offset.write_to(body);
+	}
+}
+
+assert_wire_size!(Offset: [u8, u8, u16, u8, [u8; 3], Window, Point]);
+
+/// The SHAPE extension's `ShapeGetRectangles` request.
+///
+/// See [`Client::get_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetRectangles {
+	pub window: Window,
+	pub kind: ShapeKind,
+}
+
+impl Request for GetRectangles {
+	type Reply = Region;
+
+	const NAME: &'static str = "ShapeGetRectangles";
+	const EXTENSION: Option<&'static str> = Some(SHAPE);
+	const OPCODE: u8 = GET_RECTANGLES;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u8(self.kind as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(GetRectangles: [u8, u8, u16, Window, u8, [u8; 3]]);
+
+// Followed by the rectangles, which are read as a `Region`.
+assert_wire_size!("ShapeGetRectangles reply": [u8, u8, u16, u32, u32, [u8; 20]] == 32);
+
+/// Queries the version of the SHAPE extension supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::shape_version`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn shape_version(connection: &mut impl RoundTrip) -> Result<(u16, u16), ReplyError> {
+	let version = request::reply_to(connection, &QueryVersion).await?;
+
+	Ok((version.major_version, version.minor_version))
+}
+
+/// Combines `region` with `window`'s shape.
+///
+/// With a [`RequestWriter`], if the SHAPE extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_shape`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_shape(
+	connection: &mut impl RoundTrip, window: Window, kind: ShapeKind, operation: ShapeOperation,
+	offset: Point, region: &Region,
+) -> Result<(), ReplyError> {
+	let rectangles: Vec<_> = region.rectangles().collect();
+
+	let request = Rectangles {
+		operation,
+		kind,
+		// A region's rectangles are already banded, so the X server needn't
+		// sort them.
+		ordering: ClipOrdering::YXBanded,
+		window,
+		offset,
+		rectangles: &rectangles,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Gets `window`'s shape of the given `kind`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::get_shape`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn get_shape(
+	connection: &mut impl RoundTrip, window: Window, kind: ShapeKind,
+) -> Result<Region, ReplyError> {
+	request::reply_to(connection, &GetRectangles { window, kind }).await
+}
+
+/// Combines the area covered by the set bits of `mask` with `window`'s
+/// shape.
+///
+/// With a [`RequestWriter`], if the SHAPE extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::set_shape_mask`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_shape_mask(
+	connection: &mut impl RoundTrip, window: Window, kind: ShapeKind, operation: ShapeOperation,
+	offset: Point, mask: Option<u32>,
+) -> Result<(), ReplyError> {
+	let request = Mask {
+		operation,
+		kind,
+		window,
+		offset,
+		mask,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Combines `source`'s shape with `window`'s shape.
+///
+/// With a [`RequestWriter`], if the SHAPE extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::combine_shape`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn combine_shape(
+	connection: &mut impl RoundTrip, window: Window, kind: ShapeKind, operation: ShapeOperation,
+	offset: Point, source: Window, source_kind: ShapeKind,
+) -> Result<(), ReplyError> {
+	let request = Combine {
+		operation,
+		kind,
+		source_kind,
+		window,
+		offset,
+		source,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Moves `window`'s shape of the given `kind` by `offset`.
+///
+/// With a [`RequestWriter`], if the SHAPE extension hasn't been queried yet,
+/// its reply is [awaited directly].
+///
+/// See [`Client::offset_shape`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn offset_shape(
+	connection: &mut impl RoundTrip, window: Window, kind: ShapeKind, offset: Point,
+) -> Result<(), ReplyError> {
+	request::send(
+		connection,
+		&Offset {
+			kind,
+			window,
+			offset,
+		},
+	)
+	.await
+}
+
+/// Creates and maps a transparent window covering `area` of the root
+/// window, which pointer input passes through.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::overlay_window`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn overlay_window(
+	connection: &mut impl RoundTrip, area: Rectangle,
+) -> Result<Window, XidError> {
+	let root = Window(connection.writer().default_screen().root);
+
+	let attributes = WindowAttributes {
+		override_redirect: Some(true),
+
+		..WindowAttributes::default()
+	};
+	let window = window::create_argb_window(connection, root, area, &attributes).await?;
+
+	set_shape(
+		connection,
+		window,
+		ShapeKind::Input,
+		ShapeOperation::Set,
+		Point::default(),
+		&Region::new(),
+	)
+	.await?;
+
+	let writer = connection.writer();
+
+	window
+		.configure(
+			writer,
+			&WindowChanges {
+				stack_mode: Some(StackMode::Above),
+
+				..WindowChanges::default()
+			},
+		)
+		.await
+		.map_err(ReplyError::Io)?;
+	window.map(writer).await.map_err(ReplyError::Io)?;
+
+	Ok(window)
+}
+
+impl Client {
+	/// Queries the version of the SHAPE extension supported by the X server,
+	/// as its major and minor version.
+	pub async fn shape_version(&mut self) -> Result<(u16, u16), ReplyError> {
+		shape_version(self).await
+	}
+
+	/// Combines `region`, moved by `offset`, with `window`'s shape of the
+	/// given `kind`.
+	///
+	/// Setting an empty region makes the shape empty.
+	pub async fn set_shape(
+		&mut self, window: Window, kind: ShapeKind, operation: ShapeOperation, offset: Point,
+		region: &Region,
+	) -> Result<(), ReplyError> {
+		set_shape(self, window, kind, operation, offset, region).await
+	}
+
+	/// Gets `window`'s shape of the given `kind`, relative to its origin.
+	///
+	/// A window without a shape of that kind has the shape of its whole
+	/// area: including its border for the bounding and input shapes, and
+	/// excluding it for the clip shape.
+	pub async fn get_shape(
+		&mut self, window: Window, kind: ShapeKind,
+	) -> Result<Region, ReplyError> {
+		get_shape(self, window, kind).await
+	}
+
+	/// Combines the area covered by the set bits of the bitmap `mask`, moved
+	/// by `offset`, with `window`'s shape of the given `kind`.
+	///
+	/// Setting a `mask` of `None` removes the shape, so that it covers the
+	/// whole window again.
+	pub async fn set_shape_mask(
+		&mut self, window: Window, kind: ShapeKind, operation: ShapeOperation, offset: Point,
+		mask: Option<u32>,
+	) -> Result<(), ReplyError> {
+		set_shape_mask(self, window, kind, operation, offset, mask).await
+	}
+
+	/// Combines `source`'s shape of kind `source_kind`, moved by `offset`,
+	/// with `window`'s shape of the given `kind`.
+	pub async fn combine_shape(
+		&mut self, window: Window, kind: ShapeKind, operation: ShapeOperation, offset: Point,
+		source: Window, source_kind: ShapeKind,
+	) -> Result<(), ReplyError> {
+		combine_shape(self, window, kind, operation, offset, source, source_kind).await
+	}
+
+	/// Moves `window`'s shape of the given `kind` by `offset`.
+	pub async fn offset_shape(
+		&mut self, window: Window, kind: ShapeKind, offset: Point,
+	) -> Result<(), ReplyError> {
+		offset_shape(self, window, kind, offset).await
+	}
+
+	/// Creates and maps a transparent window covering `area` of the root
+	/// window, which pointer input passes through.
+	///
+	/// The window has a 32-bit visual, so that what is drawn on it is blended
+	/// with the windows beneath it by a compositing manager, and an empty
+	/// input shape. It is override-redirect, so the window manager leaves it
+	/// alone, and is raised above its siblings.
+	///
+	/// This needs version 1.1 of the SHAPE extension for input shapes; see
+	/// [`Client::shape_version`].
+	pub async fn overlay_window(&mut self, area: Rectangle) -> Result<Window, XidError> {
+		overlay_window(self, area).await
+	}
+}
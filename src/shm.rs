@@ -0,0 +1,895 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The MIT-SHM extension, which transfers images through shared memory.
+//!
+//! `PutImage` and `GetImage` copy image data through the connection, which
+//! for large images like whole-screen captures is slow. With MIT-SHM, the
+//! image data lives in a segment of memory shared between the client and
+//! the X server, so only a small request is sent to draw or read it:
+//!
+//! ```no_run
+//! # use xrs::{draw::Drawable, geometry::Point, image::ImageFormat, Client};
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let root = client.root_window();
+//! let screen = &client.setup().screens[0];
+//! let (depth, width, height) = (screen.root_depth, screen.width, screen.height);
+//!
+//! let mut image = client
+//!     .create_shm_image(ImageFormat::ZPixmap, depth, width, height)
+//!     .await?;
+//!
+//! image.get(client, Drawable::from(root), Point::new(0, 0), !0).await?;
+//! let screenshot: &[u8] = image.data();
+//! # let _ = screenshot;
+//!
+//! image.destroy(client.split_mut().1).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The X server must be on the same machine as the client. When connected
+//! over a Unix domain socket to an X server supporting MIT-SHM 1.2, segments
+//! are passed to it as file descriptors with `ShmAttachFd`; otherwise, they
+//! are System V shared memory segments.
+
+use crate::{
+	client::RoundTrip,
+	draw::Drawable,
+	extension,
+	gc::Gc,
+	geometry::{Point, Rectangle, Size},
+	image::{Image, ImageFormat},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	xid,
+	xid::XidError,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{io, ptr, ptr::NonNull, slice};
+
+/// The name of the MIT-SHM extension.
+const MIT_SHM: &str = "MIT-SHM";
+
+/// The minor opcode of the `ShmQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+/// The minor opcode of the `ShmAttach` request.
+const ATTACH: u8 = 1;
+/// The minor opcode of the `ShmDetach` request.
+const DETACH: u8 = 2;
+/// The minor opcode of the `ShmPutImage` request.
+const PUT_IMAGE: u8 = 3;
+/// The minor opcode of the `ShmGetImage` request.
+const GET_IMAGE: u8 = 4;
+/// The minor opcode of the `ShmAttachFd` request.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ATTACH_FD: u8 = 6;
+
+/// The version of MIT-SHM which added `ShmAttachFd`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ATTACH_FD_VERSION: (u16, u16) = (1, 2);
+
+/// The offset of a `ShmCompletion` event's code from the extension's first
+/// event code.
+const COMPLETION: u8 = 0;
+
+/// The bit set in an event's code if it was sent with `SendEvent`.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The version of MIT-SHM supported by the X server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShmVersion {
+	pub major: u16,
+	pub minor: u16,
+
+	/// Whether pixmaps can be created in shared memory segments.
+	pub shared_pixmaps: bool,
+}
+
+/// A `ShmCompletion` event, generated when the X server has finished with a
+/// segment for a [`ShmImage::put`] which asked for one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShmCompletionEvent {
+	/// The drawable which was drawn on.
+	pub drawable: Drawable,
+	/// The minor opcode of the request which completed.
+	pub minor_event: u16,
+	/// The major opcode of the request which completed.
+	pub major_event: u8,
+
+	/// The ID of the segment.
+	pub segment: u32,
+	/// The offset of the image data within the segment.
+	pub offset: u32,
+}
+
+impl ShmCompletionEvent {
+	/// Decodes a `ShmCompletionEvent` from an event frame, given the MIT-SHM
+	/// extension's [first event code].
+	///
+	/// Returns `None` if `frame` is not a `ShmCompletion` event.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	pub fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		if (code & !SEND_EVENT_MASK).wrapping_sub(first_event) != COMPLETION {
+			return None;
+		}
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let u32_at = |offset: usize| {
+			u32::from_be_bytes([
+				chunk[offset - 1],
+				chunk[offset],
+				chunk[offset + 1],
+				chunk[offset + 2],
+			])
+		};
+
+		Some(Self {
+			drawable: Drawable(u32_at(4)),
+			minor_event: u16::from_be_bytes([chunk[7], chunk[8]]),
+			major_event: chunk[9],
+
+			segment: u32_at(12),
+			offset: u32_at(16),
+		})
+	}
+}
+
+/// How a [`Mapping`] was mapped into this process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MappingKind {
+	/// A System V shared memory segment attached with `shmat`.
+	SystemV,
+	/// A file mapped with `mmap`.
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	File,
+}
+
+/// Shared memory mapped into this process, which is unmapped when dropped.
+#[derive(Debug)]
+struct Mapping {
+	address: NonNull<u8>,
+	size: usize,
+	kind: MappingKind,
+}
+
+// SAFETY: the mapping is plain memory, only accessed through references to
+// the `Mapping`.
+unsafe impl Send for Mapping {}
+// SAFETY: as above.
+unsafe impl Sync for Mapping {}
+
+impl Drop for Mapping {
+	fn drop(&mut self) {
+		let address = self.address.as_ptr().cast();
+
+		// SAFETY: `address` was returned by `shmat` or `mmap` for `size`
+		// bytes, and no references to it outlive the `Mapping`.
+		unsafe {
+			match self.kind {
+				MappingKind::SystemV => libc::shmdt(address),
+				#[cfg(any(target_os = "linux", target_os = "android"))]
+				MappingKind::File => libc::munmap(address, self.size),
+			};
+		}
+	}
+}
+
+/// Creates a private System V shared memory segment of `size` bytes and
+/// attaches it, returning its ID and mapping.
+fn create_system_v(size: usize) -> io::Result<(libc::c_int, Mapping)> {
+	// SAFETY: creating a new private segment has no preconditions.
+	let id = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+	if id == -1 {
+		return Err(io::Error::last_os_error());
+	}
+
+	// SAFETY: `id` is a segment which was just created, and a null address
+	// lets the kernel choose where to attach it.
+	let address = unsafe { libc::shmat(id, ptr::null(), 0) };
+
+	match NonNull::new(address.cast::<u8>()) {
+		Some(address) if address.as_ptr() as isize != -1 => Ok((
+			id,
+			Mapping {
+				address,
+				size,
+				kind: MappingKind::SystemV,
+			},
+		)),
+
+		_ => {
+			let error = io::Error::last_os_error();
+			remove_system_v(id);
+
+			Err(error)
+		},
+	}
+}
+
+/// Marks the System V shared memory segment `id` to be destroyed once every
+/// process has detached from it.
+fn remove_system_v(id: libc::c_int) {
+	// SAFETY: `IPC_RMID` doesn't read the buffer, so it can be null.
+	unsafe {
+		libc::shmctl(id, libc::IPC_RMID, ptr::null_mut());
+	}
+}
+
+/// Creates an anonymous file of `size` bytes and maps it, returning its file
+/// descriptor and mapping.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn create_memfd(size: usize) -> io::Result<(std::os::fd::OwnedFd, Mapping)> {
+	use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+	// SAFETY: the name is a nul-terminated string.
+	let fd = unsafe { libc::memfd_create(c"xrs-shm".as_ptr(), libc::MFD_CLOEXEC) };
+	if fd == -1 {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: `fd` was just created, so nothing else owns it.
+	let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+	let length = libc::off_t::try_from(size)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "segment is too large"))?;
+	// SAFETY: `fd` is an open file.
+	if unsafe { libc::ftruncate(fd.as_raw_fd(), length) } == -1 {
+		return Err(io::Error::last_os_error());
+	}
+
+	// SAFETY: `fd` is an open file of `size` bytes, and a null address lets
+	// the kernel choose where to map it.
+	let address = unsafe {
+		libc::mmap(
+			ptr::null_mut(),
+			size,
+			libc::PROT_READ | libc::PROT_WRITE,
+			libc::MAP_SHARED,
+			fd.as_raw_fd(),
+			0,
+		)
+	};
+	if address == libc::MAP_FAILED {
+		return Err(io::Error::last_os_error());
+	}
+
+	let address = NonNull::new(address.cast()).ok_or_else(io::Error::last_os_error)?;
+
+	Ok((
+		fd,
+		Mapping {
+			address,
+			size,
+			kind: MappingKind::File,
+		},
+	))
+}
+
+/// A segment of memory shared with the X server.
+///
+/// The X server keeps the segment attached until it is [detached] or the
+/// client disconnects. The memory is unmapped from this process when the
+/// `ShmSegment` is dropped.
+///
+/// See [`Client::create_shm_segment`].
+///
+/// [detached]: ShmSegment::detach
+#[derive(Debug)]
+#[must_use = "the X server keeps the segment attached until it is detached"]
+pub struct ShmSegment {
+	id: u32,
+	/// The major opcode of MIT-SHM.
+	major_opcode: u8,
+	read_only: bool,
+
+	mapping: Mapping,
+}
+
+impl ShmSegment {
+	/// The segment's ID.
+	pub const fn id(&self) -> u32 {
+		self.id
+	}
+
+	/// The size of the segment in bytes.
+	pub const fn size(&self) -> usize {
+		self.mapping.size
+	}
+
+	/// Returns whether the X server attached the segment read-only, so it
+	/// can draw from the segment but not read images into it.
+	pub const fn is_read_only(&self) -> bool {
+		self.read_only
+	}
+
+	/// The contents of the segment.
+	///
+	/// The X server writes to the segment while processing [`ShmImage::get`]
+	/// requests, so its contents are only meaningful once their replies have
+	/// been received.
+	pub fn data(&self) -> &[u8] {
+		// SAFETY: the mapping is valid for `size` bytes for as long as `self`.
+		unsafe { slice::from_raw_parts(self.mapping.address.as_ptr(), self.mapping.size) }
+	}
+
+	/// The contents of the segment, for writing images to draw.
+	pub fn data_mut(&mut self) -> &mut [u8] {
+		// SAFETY: the mapping is valid for `size` bytes for as long as `self`,
+		// which is borrowed mutably.
+		unsafe { slice::from_raw_parts_mut(self.mapping.address.as_ptr(), self.mapping.size) }
+	}
+
+	/// Detaches the segment from the X server and unmaps it.
+	pub async fn detach(self, writer: &mut RequestWriter) -> io::Result<()> {
+		writer
+			.send_raw(Detach(self.id).to_raw(self.major_opcode))
+			.await?;
+
+		Ok(())
+	}
+}
+
+/// An image stored in a [`ShmSegment`].
+///
+/// See [`Client::create_shm_image`].
+#[derive(Debug)]
+#[must_use = "the X server keeps the image's segment attached until it is destroyed"]
+pub struct ShmImage {
+	segment: ShmSegment,
+
+	/// The layout of the image, without any data.
+	layout: Image,
+}
+
+impl ShmImage {
+	pub const fn format(&self) -> ImageFormat {
+		self.layout.format
+	}
+
+	pub const fn depth(&self) -> u8 {
+		self.layout.depth
+	}
+
+	/// The visual of the window the image was last read from, or `0` if it
+	/// hasn't been read from a window.
+	pub const fn visual(&self) -> u32 {
+		self.layout.visual
+	}
+
+	pub const fn width(&self) -> u16 {
+		self.layout.width
+	}
+
+	pub const fn height(&self) -> u16 {
+		self.layout.height
+	}
+
+	/// The number of bytes in each scanline of the image data, including
+	/// padding.
+	pub fn bytes_per_line(&self) -> usize {
+		self.layout.bytes_per_line()
+	}
+
+	/// The segment the image is stored in.
+	pub const fn segment(&self) -> &ShmSegment {
+		&self.segment
+	}
+
+	/// The image data, laid out like [`Image::data`].
+	pub fn data(&self) -> &[u8] {
+		self.segment.data()
+	}
+
+	/// The image data, for writing an image to draw.
+	pub fn data_mut(&mut self) -> &mut [u8] {
+		self.segment.data_mut()
+	}
+
+	/// Copies the image out of shared memory into an [`Image`].
+	pub fn to_image(&self) -> Image {
+		Image {
+			data: self.data().to_vec(),
+			..self.layout.clone()
+		}
+	}
+
+	/// Draws the `source` area of the image on `drawable`, with its top-left
+	/// corner at `position`.
+	///
+	/// The X server reads the image data while processing the request, so
+	/// it mustn't be changed until then. If `send_event` is true, a
+	/// [`ShmCompletionEvent`] is generated once the X server has finished
+	/// with it.
+	pub async fn put(
+		&self, writer: &mut RequestWriter, drawable: Drawable, gc: &Gc, source: Rectangle,
+		position: Point, send_event: bool,
+	) -> io::Result<()> {
+		let request = PutImage {
+			drawable,
+			gc: gc.id(),
+			total: Size::new(self.layout.width, self.layout.height),
+			source,
+			position,
+			depth: self.layout.depth,
+			format: self.layout.format,
+			send_event,
+			segment: self.segment.id,
+			offset: 0,
+		};
+
+		writer
+			.send_raw(request.to_raw(self.segment.major_opcode))
+			.await?;
+
+		Ok(())
+	}
+
+	/// Reads the area of `drawable` the size of the image, with its top-left
+	/// corner at `position`, into the image.
+	///
+	/// Only the planes set in `plane_mask` are read; use `!0` for all planes.
+	/// Returns an [`InvalidData`] error if `drawable`'s depth doesn't match
+	/// the image's.
+	///
+	/// [`InvalidData`]: io::ErrorKind::InvalidData
+	pub async fn get(
+		&mut self, client: &mut Client, drawable: Drawable, position: Point, plane_mask: u32,
+	) -> Result<(), ReplyError> {
+		let format = match self.layout.format {
+			// Bitmaps are read as single-plane `XyPixmap` images, which are
+			// laid out the same.
+			ImageFormat::Bitmap => ImageFormat::XyPixmap,
+			format => format,
+		};
+
+		let request = GetImage {
+			drawable,
+			area: Rectangle::new(position, Size::new(self.layout.width, self.layout.height)),
+			plane_mask,
+			format,
+			segment: self.segment.id,
+			offset: 0,
+		};
+		let reply = request::reply_to(client, &request).await?;
+
+		if reply.depth != self.layout.depth {
+			return Err(ReplyError::Io(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"the drawable's depth doesn't match the image's",
+			)));
+		}
+
+		self.layout.visual = reply.visual;
+
+		Ok(())
+	}
+
+	/// Detaches the image's segment from the X server and unmaps it.
+	pub async fn destroy(self, writer: &mut RequestWriter) -> io::Result<()> {
+		self.segment.detach(writer).await
+	}
+}
+
+/// The MIT-SHM extension's `ShmQueryVersion` request, whose reply is the
+/// X server's [`ShmVersion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryVersion;
+
+impl Request for QueryVersion {
+	type Reply = ShmVersion;
+
+	const NAME: &'static str = "ShmQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(QueryVersion: [u8, u8, u16]);
+assert_wire_size!(
+	"ShmQueryVersion reply": [u8, u8, u16, u32, u16, u16, u16, u16, u8, [u8; 15]] == 32
+);
+
+impl Reply for ShmVersion {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+		let &[major0, major1, minor0, minor1] = chunk.get(..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			major: u16::from_be_bytes([major0, major1]),
+			minor: u16::from_be_bytes([minor0, minor1]),
+
+			shared_pixmaps: *metabyte != 0,
+		})
+	}
+}
+
+/// The MIT-SHM extension's `ShmAttach` request, which attaches a System V
+/// shared memory segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Attach {
+	/// The ID of the new segment.
+	pub segment: u32,
+	/// The System V ID of the shared memory.
+	pub shmid: u32,
+	pub read_only: bool,
+}
+
+impl Request for Attach {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShmAttach";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = ATTACH;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.segment);
+		body.put_u32(self.shmid);
+		body.put_u8(self.read_only.into());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+assert_wire_size!(Attach: [u8, u8, u16, u32, u32, u8, [u8; 3]]);
+
+/// The MIT-SHM extension's `ShmAttachFd` request, which attaches shared
+/// memory passed as a file descriptor.
+///
+/// The file descriptor is sent alongside the request.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttachFd {
+	/// The ID of the new segment.
+	pub segment: u32,
+	pub read_only: bool,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Request for AttachFd {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShmAttachFd";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = ATTACH_FD;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.segment);
+		body.put_u8(self.read_only.into());
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+assert_wire_size!(AttachFd: [u8, u8, u16, u32, u8, [u8; 3]]);
+
+/// The MIT-SHM extension's `ShmDetach` request.
+///
+/// See [`ShmSegment::detach`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Detach(pub u32);
+
+impl Request for Detach {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShmDetach";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = DETACH;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0);
+	}
+}
+
+assert_wire_size!(Detach: [u8, u8, u16, u32]);
+
+/// The MIT-SHM extension's `ShmPutImage` request.
+///
+/// See [`ShmImage::put`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PutImage {
+	pub drawable: Drawable,
+	pub gc: u32,
+	/// The size of the whole image in the segment.
+	pub total: Size,
+	/// The area of the image to draw.
+	pub source: Rectangle,
+	/// Where the top-left corner of `source` is drawn.
+	pub position: Point,
+	pub depth: u8,
+	pub format: ImageFormat,
+	/// Whether a [`ShmCompletionEvent`] is generated once the X server has
+	/// finished with the image data.
+	pub send_event: bool,
+	pub segment: u32,
+	/// The offset of the image data in the segment.
+	pub offset: u32,
+}
+
+impl Request for PutImage {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ShmPutImage";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = PUT_IMAGE;
+	const LENGTH: Option<u16> = Some(10);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		body.put_u32(self.gc);
+		body.put_u16(self.total.width);
+		body.put_u16(self.total.height);
+		body.put_u16(self.source.x as u16);
+		body.put_u16(self.source.y as u16);
+		body.put_u16(self.source.width);
+		body.put_u16(self.source.height);
+		self.position.write_to(body);
+		body.put_u8(self.depth);
+		body.put_u8(self.format as u8);
+		body.put_u8(self.send_event.into());
+		// 1 unused byte.
+		body.put_u8(0);
+		body.put_u32(self.segment);
+		body.put_u32(self.offset);
+	}
+}
+
+assert_wire_size!(
+	PutImage: [u8, u8, u16, u32, u32, Size, Rectangle, Point, u8, u8, u8, u8, u32, u32]
+);
+
+/// The MIT-SHM extension's `ShmGetImage` request.
+///
+/// See [`ShmImage::get`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetImage {
+	pub drawable: Drawable,
+	pub area: Rectangle,
+	/// The planes to read; `!0` reads all of them.
+	pub plane_mask: u32,
+	/// Either [`ImageFormat::XyPixmap`] or [`ImageFormat::ZPixmap`].
+	pub format: ImageFormat,
+	pub segment: u32,
+	/// The offset in the segment to read the image data into.
+	pub offset: u32,
+}
+
+impl Request for GetImage {
+	type Reply = GetImageReply;
+
+	const NAME: &'static str = "ShmGetImage";
+	const EXTENSION: Option<&'static str> = Some(MIT_SHM);
+	const OPCODE: u8 = GET_IMAGE;
+	const LENGTH: Option<u16> = Some(8);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable.id());
+		self.area.write_to(body);
+		body.put_u32(self.plane_mask);
+		body.put_u8(self.format as u8);
+		// 3 unused bytes.
+		body.put_bytes(0, 3);
+		body.put_u32(self.segment);
+		body.put_u32(self.offset);
+	}
+}
+
+assert_wire_size!(GetImage: [u8, u8, u16, u32, Rectangle, u32, u8, [u8; 3], u32, u32]);
+
+/// The depth and visual of an image read into a segment.
+///
+/// This is the reply to a `ShmGetImage` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetImageReply {
+	pub depth: u8,
+	/// The visual of the window the image was read from, or `0` if it was
+	/// read from a pixmap.
+	pub visual: u32,
+	/// The number of bytes of image data read.
+	pub size: u32,
+}
+
+assert_wire_size!("ShmGetImage reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for GetImageReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+		let &[v0, v1, v2, v3, s0, s1, s2, s3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			depth: *metabyte,
+			visual: u32::from_be_bytes([v0, v1, v2, v3]),
+			size: u32::from_be_bytes([s0, s1, s2, s3]),
+		})
+	}
+}
+
+/// Returns the version of MIT-SHM supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::shm_version`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn shm_version(connection: &mut impl RoundTrip) -> Result<ShmVersion, ReplyError> {
+	request::reply_to(connection, &QueryVersion).await
+}
+
+/// Creates a shared memory segment of `size` bytes and attaches it to the
+/// X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::create_shm_segment`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_shm_segment(
+	connection: &mut impl RoundTrip, size: usize, read_only: bool,
+) -> Result<ShmSegment, XidError> {
+	if size == 0 {
+		return Err(ReplyError::Io(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"shared memory segments can't be empty",
+		))
+		.into());
+	}
+
+	let major_opcode = extension::require_extension(connection, MIT_SHM)
+		.await?
+		.major_opcode;
+	let id = xid::generate_id(connection).await?;
+
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	if connection.writer().can_pass_fds() {
+		let version = shm_version(connection).await?;
+
+		if (version.major, version.minor) >= ATTACH_FD_VERSION {
+			use std::os::fd::AsFd;
+
+			let (fd, mapping) = create_memfd(size).map_err(ReplyError::Io)?;
+
+			let request = AttachFd {
+				segment: id,
+				read_only,
+			};
+
+			connection
+				.writer()
+				.send_raw_with_fds(request.to_raw(major_opcode), &[fd.as_fd()])
+				.await
+				.map_err(ReplyError::Io)?;
+
+			return Ok(ShmSegment {
+				id,
+				major_opcode,
+				read_only,
+
+				mapping,
+			});
+		}
+	}
+
+	let (system_v_id, mapping) = create_system_v(size).map_err(ReplyError::Io)?;
+
+	let request = Attach {
+		segment: id,
+		shmid: system_v_id as u32,
+		read_only,
+	};
+	let attached = request::send(connection, &request).await;
+
+	// Once the X server has processed the `ShmAttach` request, the segment
+	// can be marked to be destroyed when both sides have detached, so that
+	// it isn't leaked if either crashes.
+	let attached = match attached {
+		Ok(()) => shm_version(connection).await.map(|_| ()),
+		Err(error) => Err(error),
+	};
+	remove_system_v(system_v_id);
+	attached?;
+
+	Ok(ShmSegment {
+		id,
+		major_opcode,
+		read_only,
+
+		mapping,
+	})
+}
+
+/// Creates a blank image in a shared memory segment.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::create_shm_image`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_shm_image(
+	connection: &mut impl RoundTrip, format: ImageFormat, depth: u8, width: u16, height: u16,
+) -> Result<ShmImage, XidError> {
+	let layout =
+		Image::new(connection.writer().setup(), format, depth, width, height).ok_or_else(|| {
+			ReplyError::Io(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"the X server doesn't support images of that format and depth",
+			))
+		})?;
+
+	let segment = create_shm_segment(connection, layout.data.len(), false).await?;
+
+	Ok(ShmImage {
+		segment,
+
+		layout: Image {
+			data: Vec::new(),
+			..layout
+		},
+	})
+}
+
+impl Client {
+	/// Returns the version of MIT-SHM supported by the X server.
+	///
+	/// Returns an [`Unsupported`] error if the X server doesn't support
+	/// MIT-SHM.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn shm_version(&mut self) -> Result<ShmVersion, ReplyError> {
+		shm_version(self).await
+	}
+
+	/// Creates a shared memory segment of `size` bytes and attaches it to the
+	/// X server, which can only read from it if `read_only` is true.
+	///
+	/// The segment is passed to the X server as a file descriptor if
+	/// possible, or is otherwise a System V shared memory segment. Returns
+	/// an [`InvalidInput`] error if `size` is 0, and an [`Unsupported`] error
+	/// if the X server doesn't support MIT-SHM.
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn create_shm_segment(
+		&mut self, size: usize, read_only: bool,
+	) -> Result<ShmSegment, XidError> {
+		create_shm_segment(self, size, read_only).await
+	}
+
+	/// Creates a blank image in a shared memory segment, laid out as the X
+	/// server lays out images of `depth` in `format`.
+	///
+	/// Returns an [`InvalidInput`] error in the same cases [`Image::new`]
+	/// returns `None`. See [`Client::create_shm_segment`].
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	pub async fn create_shm_image(
+		&mut self, format: ImageFormat, depth: u8, width: u16, height: u16,
+	) -> Result<ShmImage, XidError> {
+		create_shm_image(self, format, depth, width, height).await
+	}
+}
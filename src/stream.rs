@@ -4,15 +4,16 @@
 
 use std::{
 	io::IoSlice,
+	net::IpAddr,
 	pin::Pin,
 	task::{Context, Poll},
 };
 #[cfg(unix)]
-use tokio::net::UnixStream;
+use tokio::net::{unix, UnixStream};
 use tokio::{
 	io,
 	io::{AsyncRead, AsyncWrite, ReadBuf},
-	net::TcpStream,
+	net::{tcp, TcpStream},
 };
 
 pub enum Stream {
@@ -24,23 +25,11 @@ pub enum Stream {
 impl AsyncRead for Stream {
 	fn poll_read(
 		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf,
-	) -> Poll<io::Result<usize>> {
-		match self {
-			Self::TcpStream(stream) => stream.poll_read(cx, buf),
-			#[cfg(unix)]
-			Self::UnixStream(stream) => stream.poll_read(cx, buf),
-		}
-	}
-}
-
-impl AsyncRead for &Stream {
-	fn poll_read(
-		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8],
-	) -> Poll<io::Result<usize>> {
-		match self {
-			Stream::TcpStream(stream) => <&TcpStream>::poll_read(Pin::new(&mut &*stream), cx, buf),
+	) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_read(cx, buf),
 			#[cfg(unix)]
-			Stream::UnixStream(stream) => <&UnixStream>::poll_read(Pin::new(&mut &*stream), cx, buf),
+			Self::UnixStream(stream) => Pin::new(stream).poll_read(cx, buf),
 		}
 	}
 }
@@ -49,78 +38,366 @@ impl AsyncWrite for Stream {
 	fn poll_write(
 		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		match self {
-			Self::TcpStream(stream) => stream.poll_write(cx, buf),
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_write(cx, buf),
 			#[cfg(unix)]
-			Self::UnixStream(stream) => stream.poll_write(cx, buf),
+			Self::UnixStream(stream) => Pin::new(stream).poll_write(cx, buf),
 		}
 	}
 
 	fn poll_write_vectored(
 		self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[IoSlice<'_>],
 	) -> Poll<io::Result<usize>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+			#[cfg(unix)]
+			Self::UnixStream(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+		}
+	}
+
+	fn is_write_vectored(&self) -> bool {
 		match self {
-			Self::TcpStream(stream) => stream.poll_write_vectored(cx, bufs),
+			Self::TcpStream(stream) => stream.is_write_vectored(),
 			#[cfg(unix)]
-			Self::UnixStream(stream) => stream.poll_write_vectored(cx, bufs),
+			Self::UnixStream(stream) => stream.is_write_vectored(),
 		}
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		match self {
-			Self::TcpStream(stream) => stream.poll_flush(cx),
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_flush(cx),
 			#[cfg(unix)]
-			Self::UnixStream(stream) => stream.poll_flush(cx),
+			Self::UnixStream(stream) => Pin::new(stream).poll_flush(cx),
 		}
 	}
 
 	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		match self {
-			Self::TcpStream(stream) => stream.poll_close(cx),
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_shutdown(cx),
 			#[cfg(unix)]
-			Self::UnixStream(stream) => stream.poll_close(cx),
+			Self::UnixStream(stream) => Pin::new(stream).poll_shutdown(cx),
 		}
 	}
 }
 
-impl AsyncWrite for &Stream {
-	fn poll_write(
-		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8],
-	) -> Poll<io::Result<usize>> {
+impl Stream {
+	/// Returns the IP address of the X server, or `None` if the connection is
+	/// not over TCP.
+	pub fn peer_ip(&self) -> io::Result<Option<IpAddr>> {
 		match self {
-			Stream::TcpStream(stream) => <&TcpStream>::poll_write(Pin::new(&mut &*stream), cx, buf),
+			Self::TcpStream(stream) => Ok(Some(stream.peer_addr()?.ip())),
 			#[cfg(unix)]
-			Stream::UnixStream(stream) => <&UnixStream>::poll_write(Pin::new(&mut &*stream), cx, buf),
+			Self::UnixStream(_) => Ok(None),
 		}
 	}
 
-	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+	/// Splits the stream into a [`ReadStream`] and a [`WriteStream`] which can
+	/// be used independently.
+	pub fn into_split(self) -> (ReadStream, WriteStream) {
 		match self {
-			Stream::TcpStream(stream) => <&TcpStream>::poll_flush(Pin::new(&mut &*stream), cx),
+			Self::TcpStream(stream) => {
+				let (read, write) = stream.into_split();
+
+				(ReadStream::TcpStream(read), WriteStream::TcpStream(write))
+			},
+
 			#[cfg(unix)]
-			Stream::UnixStream(stream) => <&UnixStream>::poll_flush(Pin::new(&mut &*stream), cx),
+			Self::UnixStream(stream) => {
+				let (read, write) = stream.into_split();
+
+				(ReadStream::UnixStream(read), WriteStream::UnixStream(write))
+			},
 		}
 	}
+}
 
-	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		match self {
-			Stream::TcpStream(stream) => <&TcpStream>::poll_close(Pin::new(&mut &*stream), cx),
+/// The reading half of a [`Stream`].
+pub enum ReadStream {
+	TcpStream(tcp::OwnedReadHalf),
+	#[cfg(unix)]
+	UnixStream(unix::OwnedReadHalf),
+}
+
+/// The writing half of a [`Stream`].
+pub enum WriteStream {
+	TcpStream(tcp::OwnedWriteHalf),
+	#[cfg(unix)]
+	UnixStream(unix::OwnedWriteHalf),
+}
+
+impl AsyncRead for ReadStream {
+	fn poll_read(
+		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf,
+	) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_read(cx, buf),
 			#[cfg(unix)]
-			Stream::UnixStream(stream) => <&UnixStream>::poll_close(Pin::new(&mut &*stream), cx),
+			Self::UnixStream(stream) => Pin::new(stream).poll_read(cx, buf),
+		}
+	}
+}
+
+impl AsyncWrite for WriteStream {
+	fn poll_write(
+		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_write(cx, buf),
+			#[cfg(unix)]
+			Self::UnixStream(stream) => Pin::new(stream).poll_write(cx, buf),
 		}
 	}
 
 	fn poll_write_vectored(
 		self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[IoSlice<'_>],
 	) -> Poll<io::Result<usize>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+			#[cfg(unix)]
+			Self::UnixStream(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+		}
+	}
+
+	fn is_write_vectored(&self) -> bool {
 		match self {
-			Stream::TcpStream(stream) => {
-				<&TcpStream>::poll_write_vectored(Pin::new(&mut &*stream), cx, bufs)
-			},
+			Self::TcpStream(stream) => stream.is_write_vectored(),
 			#[cfg(unix)]
-			Stream::UnixStream(stream) => {
-				<&UnixStream>::poll_write_vectored(Pin::new(&mut &*stream), cx, bufs)
-			},
+			Self::UnixStream(stream) => stream.is_write_vectored(),
+		}
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_flush(cx),
+			#[cfg(unix)]
+			Self::UnixStream(stream) => Pin::new(stream).poll_flush(cx),
+		}
+	}
+
+	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Self::TcpStream(stream) => Pin::new(stream).poll_shutdown(cx),
+			#[cfg(unix)]
+			Self::UnixStream(stream) => Pin::new(stream).poll_shutdown(cx),
+		}
+	}
+}
+
+#[cfg(all(unix, feature = "fds"))]
+impl ReadStream {
+	/// Reads up to `limit` bytes from the stream into `buffer`'s spare
+	/// capacity, pushing any file descriptors received with them onto `fds`,
+	/// and returns the number of bytes read.
+	///
+	/// File descriptors can only be received over Unix domain sockets; other
+	/// streams are read as normal.
+	pub async fn read_with_fds(
+		&mut self, buffer: &mut bytes::BytesMut, limit: usize,
+		fds: &mut std::collections::VecDeque<std::os::fd::OwnedFd>,
+	) -> io::Result<usize> {
+		use bytes::BufMut;
+		use std::os::fd::AsRawFd;
+		use tokio::io::{AsyncReadExt, Interest};
+
+		let stream = match self {
+			Self::TcpStream(stream) => return stream.read_buf(&mut buffer.limit(limit)).await,
+			Self::UnixStream(stream) => stream,
+		};
+
+		let socket: &UnixStream = stream.as_ref();
+
+		loop {
+			socket.readable().await?;
+
+			match socket.try_io(Interest::READABLE, || {
+				let spare = buffer.spare_capacity_mut();
+				let limit = limit.min(spare.len());
+
+				recv_with_fds(socket.as_raw_fd(), &mut spare[..limit], fds)
+			}) {
+				Ok(read) => {
+					// SAFETY: `recv_with_fds` initialized the first `read` bytes
+					//         of the spare capacity.
+					unsafe { buffer.set_len(buffer.len() + read) };
+
+					return Ok(read);
+				},
+
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+				Err(error) => return Err(error),
+			}
 		}
 	}
 }
+
+/// Receives bytes from `socket` into `buffer`, pushing any file descriptors
+/// sent with them as `SCM_RIGHTS` ancillary data onto `fds`, and returns the
+/// number of bytes received.
+#[cfg(all(unix, feature = "fds"))]
+fn recv_with_fds(
+	socket: std::os::fd::RawFd, buffer: &mut [std::mem::MaybeUninit<u8>],
+	fds: &mut std::collections::VecDeque<std::os::fd::OwnedFd>,
+) -> io::Result<usize> {
+	use std::{
+		mem,
+		os::fd::{FromRawFd, OwnedFd, RawFd},
+		ptr,
+	};
+
+	/// The most file descriptors which can be received in one read.
+	const MAX_FDS: u32 = 16;
+
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	const FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+	#[cfg(not(any(target_os = "linux", target_os = "android")))]
+	const FLAGS: libc::c_int = 0;
+
+	// SAFETY: `CMSG_SPACE` only does arithmetic.
+	let control_size =
+		unsafe { libc::CMSG_SPACE(MAX_FDS * mem::size_of::<RawFd>() as u32) } as usize;
+	// The control buffer must be aligned for `cmsghdr`.
+	let mut control = vec![0u64; control_size.div_ceil(mem::size_of::<u64>())];
+
+	let mut iov = libc::iovec {
+		iov_base: buffer.as_mut_ptr().cast(),
+		iov_len: buffer.len(),
+	};
+
+	// SAFETY: `msghdr` is valid when zeroed.
+	let mut message: libc::msghdr = unsafe { mem::zeroed() };
+	message.msg_iov = &mut iov;
+	message.msg_iovlen = 1;
+	message.msg_control = control.as_mut_ptr().cast();
+	message.msg_controllen = control_size as _;
+
+	// SAFETY: `message` points to `buffer` and the control buffer, both of
+	//         which outlive the call.
+	let read = match unsafe { libc::recvmsg(socket, &mut message, FLAGS) } {
+		-1 => return Err(io::Error::last_os_error()),
+		read => read as usize,
+	};
+
+	// SAFETY: the kernel has filled in `message`'s control buffer, so every
+	//         header returned by `CMSG_FIRSTHDR` and `CMSG_NXTHDR` is within it,
+	//         and the file descriptors it contains are now owned by us.
+	unsafe {
+		let mut header = libc::CMSG_FIRSTHDR(&message);
+
+		while !header.is_null() {
+			if (*header).cmsg_level == libc::SOL_SOCKET && (*header).cmsg_type == libc::SCM_RIGHTS {
+				let data = libc::CMSG_DATA(header).cast::<RawFd>();
+				let length = (*header).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+
+				for i in 0..length / mem::size_of::<RawFd>() {
+					fds.push_back(OwnedFd::from_raw_fd(ptr::read_unaligned(data.add(i))));
+				}
+			}
+
+			header = libc::CMSG_NXTHDR(&message, header);
+		}
+	}
+
+	if message.msg_flags & libc::MSG_CTRUNC != 0 {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			"too many file descriptors were received at once",
+		));
+	}
+
+	Ok(read)
+}
+
+#[cfg(all(unix, feature = "fds"))]
+impl WriteStream {
+	/// Returns whether file descriptors can be passed over the stream, which
+	/// is only possible over Unix domain sockets.
+	pub fn can_pass_fds(&self) -> bool {
+		matches!(self, Self::UnixStream(_))
+	}
+
+	/// Writes `bytes` to the stream with `fds` attached, so that the X server
+	/// receives the file descriptors along with the request they belong to.
+	///
+	/// Returns an [`Unsupported`] error if the stream isn't a Unix domain
+	/// socket.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn write_with_fds(
+		&mut self, bytes: &[u8], fds: &[std::os::fd::BorrowedFd<'_>],
+	) -> io::Result<()> {
+		use std::os::fd::AsRawFd;
+		use tokio::io::{AsyncWriteExt, Interest};
+
+		let Self::UnixStream(stream) = self else {
+			return Err(io::Error::new(
+				io::ErrorKind::Unsupported,
+				"file descriptors can only be passed over Unix domain sockets",
+			));
+		};
+
+		let fds: Vec<_> = fds.iter().map(AsRawFd::as_raw_fd).collect();
+
+		let written = loop {
+			let socket: &UnixStream = stream.as_ref();
+			socket.writable().await?;
+
+			match socket.try_io(Interest::WRITABLE, || {
+				send_with_fds(socket.as_raw_fd(), bytes, &fds)
+			}) {
+				Ok(written) => break written,
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+				Err(error) => return Err(error),
+			}
+		};
+
+		// The file descriptors are sent with the first bytes written, so the
+		// rest can be written as normal.
+		stream.write_all(&bytes[written..]).await
+	}
+}
+
+/// Sends as much of `bytes` as possible over `socket` with `fds` attached as
+/// `SCM_RIGHTS` ancillary data, returning the number of bytes sent.
+#[cfg(all(unix, feature = "fds"))]
+fn send_with_fds(
+	socket: std::os::fd::RawFd, bytes: &[u8], fds: &[std::os::fd::RawFd],
+) -> io::Result<usize> {
+	use std::{mem, ptr};
+
+	let fds_size = mem::size_of_val(fds) as u32;
+	// SAFETY: `CMSG_SPACE` only does arithmetic.
+	let control_size = unsafe { libc::CMSG_SPACE(fds_size) } as usize;
+	// The control buffer must be aligned for `cmsghdr`.
+	let mut control = vec![0u64; control_size.div_ceil(mem::size_of::<u64>())];
+
+	let mut iov = libc::iovec {
+		iov_base: bytes.as_ptr().cast_mut().cast(),
+		iov_len: bytes.len(),
+	};
+
+	// SAFETY: `msghdr` is valid when zeroed.
+	let mut message: libc::msghdr = unsafe { mem::zeroed() };
+	message.msg_iov = &mut iov;
+	message.msg_iovlen = 1;
+	message.msg_control = control.as_mut_ptr().cast();
+	message.msg_controllen = control_size as _;
+
+	// SAFETY: `message`'s control buffer is large enough for one `cmsghdr`
+	// followed by `fds`, so the header and its data are within it.
+	unsafe {
+		let header = libc::CMSG_FIRSTHDR(&message);
+
+		(*header).cmsg_level = libc::SOL_SOCKET;
+		(*header).cmsg_type = libc::SCM_RIGHTS;
+		(*header).cmsg_len = libc::CMSG_LEN(fds_size) as _;
+
+		ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(header).cast(), fds.len());
+	}
+
+	// SAFETY: `message` points to `bytes` and the control buffer, both of
+	// which outlive the call.
+	match unsafe { libc::sendmsg(socket, &message, 0) } {
+		-1 => Err(io::Error::last_os_error()),
+		written => Ok(written as usize),
+	}
+}
@@ -0,0 +1,1198 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The X Synchronization extension (SYNC): counters and alarms.
+//!
+//! A [`Counter`] is a 64-bit [`SyncValue`] kept by the X server, which
+//! clients can set, change and wait for. The X server also keeps system
+//! counters, such as `SERVERTIME` and `IDLETIME`, listed by
+//! [`Client::system_counters`]. An [`Alarm`] sends an [`AlarmNotifyEvent`]
+//! when a counter reaches a value.
+//!
+//! Window managers use a counter to resize windows without tearing: the
+//! window manager sends a `_NET_WM_SYNC_REQUEST` client message with a value
+//! before each resize, and waits for the client to set its counter to that
+//! value once it has redrawn the window at its new size. A
+//! [`SyncRequestCounter`] takes care of the client's side of this:
+//!
+//! ```no_run
+//! # use xrs::{window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut sync_request = client.create_sync_request_counter(window).await?;
+//! // `_NET_WM_SYNC_REQUEST` must also be added to the window's
+//! // `WM_PROTOCOLS`.
+//!
+//! while let Some(frame) = client.next_event().await? {
+//!     if sync_request.observe(&frame) {
+//!         continue;
+//!     }
+//!
+//!     // Redraw the window after a `ConfigureNotify` event, then...
+//!     sync_request.update(client.split_mut().1).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	event::{ClientMessageData, Event},
+	extension,
+	property::{Property, PropertyMode},
+	raw::{assert_wire_size, WireSize, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	time::Timestamp,
+	window::Window,
+	xid::{self, XidError},
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the SYNC extension.
+const SYNC: &str = "SYNC";
+
+/// The minor opcode of the `SyncInitialize` request.
+const INITIALIZE: u8 = 0;
+/// The minor opcode of the `SyncListSystemCounters` request.
+const LIST_SYSTEM_COUNTERS: u8 = 1;
+/// The minor opcode of the `SyncCreateCounter` request.
+const CREATE_COUNTER: u8 = 2;
+/// The minor opcode of the `SyncSetCounter` request.
+const SET_COUNTER: u8 = 3;
+/// The minor opcode of the `SyncChangeCounter` request.
+const CHANGE_COUNTER: u8 = 4;
+/// The minor opcode of the `SyncQueryCounter` request.
+const QUERY_COUNTER: u8 = 5;
+/// The minor opcode of the `SyncDestroyCounter` request.
+const DESTROY_COUNTER: u8 = 6;
+/// The minor opcode of the `SyncAwait` request.
+const AWAIT: u8 = 7;
+/// The minor opcode of the `SyncCreateAlarm` request.
+const CREATE_ALARM: u8 = 8;
+/// The minor opcode of the `SyncChangeAlarm` request.
+const CHANGE_ALARM: u8 = 9;
+/// The minor opcode of the `SyncQueryAlarm` request.
+const QUERY_ALARM: u8 = 10;
+/// The minor opcode of the `SyncDestroyAlarm` request.
+const DESTROY_ALARM: u8 = 11;
+
+/// The version of SYNC requested by this client.
+///
+/// The X server expects clients to send their version before any other SYNC
+/// request.
+const VERSION: (u8, u8) = (3, 1);
+
+/// The offset of a `CounterNotify` event's code from the extension's first
+/// event code.
+const COUNTER_NOTIFY: u8 = 0;
+/// The offset of an `AlarmNotify` event's code from the extension's first
+/// event code.
+const ALARM_NOTIFY: u8 = 1;
+
+/// The bit set in an event's code if it was sent with `SendEvent`.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// A 64-bit counter value.
+///
+/// The protocol sends these as a signed high 32 bits followed by unsigned low
+/// 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SyncValue(pub i64);
+
+impl SyncValue {
+	pub const ZERO: Self = Self(0);
+
+	/// Creates a value from its high and low 32 bits.
+	pub const fn from_parts(high: i32, low: u32) -> Self {
+		Self(((high as i64) << 32) | (low as i64))
+	}
+
+	/// The value's high 32 bits.
+	pub const fn high(self) -> i32 {
+		(self.0 >> 32) as i32
+	}
+
+	/// The value's low 32 bits.
+	pub const fn low(self) -> u32 {
+		self.0 as u32
+	}
+
+	/// Writes the value to `body` as it is sent in the protocol.
+	fn write_to(self, body: &mut impl BufMut) {
+		body.put_i32(self.high());
+		body.put_u32(self.low());
+	}
+
+	/// Reads a value from the 8 bytes of `data` at `offset`.
+	fn read_at(data: &[u8], offset: usize) -> Option<Self> {
+		let &[h0, h1, h2, h3, l0, l1, l2, l3] = data.get(offset..offset + 8)? else {
+			return None;
+		};
+
+		Some(Self::from_parts(
+			i32::from_be_bytes([h0, h1, h2, h3]),
+			u32::from_be_bytes([l0, l1, l2, l3]),
+		))
+	}
+}
+
+impl WireSize for SyncValue {
+	const WIRE_SIZE: usize = i32::WIRE_SIZE + u32::WIRE_SIZE;
+}
+
+impl From<i64> for SyncValue {
+	fn from(value: i64) -> Self {
+		Self(value)
+	}
+}
+
+/// A handle to a counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Counter(pub u32);
+
+impl Counter {
+	/// The counter's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+}
+
+impl WireSize for Counter {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+/// A handle to an alarm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Alarm(pub u32);
+
+impl Alarm {
+	/// The alarm's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+}
+
+impl WireSize for Alarm {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+/// A counter kept by the X server itself.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SystemCounter {
+	pub counter: Counter,
+	/// The approximate amount the counter changes by at a time.
+	pub resolution: SyncValue,
+	/// The counter's name, such as `SERVERTIME` or `IDLETIME`.
+	pub name: String,
+}
+
+/// How a trigger's value is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+	/// The value is used as it is.
+	Absolute,
+	/// The value is added to the counter's value when the trigger is set.
+	Relative,
+}
+
+/// When a trigger fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TestType {
+	/// When the counter's value changes from below the trigger's value to at
+	/// or above it.
+	PositiveTransition,
+	/// When the counter's value changes from above the trigger's value to at
+	/// or below it.
+	NegativeTransition,
+	/// When the counter's value is at or above the trigger's value.
+	PositiveComparison,
+	/// When the counter's value is at or below the trigger's value.
+	NegativeComparison,
+}
+
+/// The state of an [`Alarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlarmState {
+	Active,
+	/// The alarm fired, and its delta is `0` or its test can't be met again.
+	Inactive,
+	Destroyed,
+}
+
+impl ValueType {
+	const fn from_value(value: u32) -> Option<Self> {
+		match value {
+			0 => Some(Self::Absolute),
+			1 => Some(Self::Relative),
+
+			_ => None,
+		}
+	}
+}
+
+impl TestType {
+	const fn from_value(value: u32) -> Option<Self> {
+		match value {
+			0 => Some(Self::PositiveTransition),
+			1 => Some(Self::NegativeTransition),
+			2 => Some(Self::PositiveComparison),
+			3 => Some(Self::NegativeComparison),
+
+			_ => None,
+		}
+	}
+}
+
+impl AlarmState {
+	const fn from_value(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(Self::Active),
+			1 => Some(Self::Inactive),
+			2 => Some(Self::Destroyed),
+
+			_ => None,
+		}
+	}
+}
+
+/// The attributes of an [`Alarm`], for [`Client::create_alarm`] and
+/// [`Client::change_alarm`].
+///
+/// Attributes which are `None` are left unchanged, or given their default
+/// values when creating an alarm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AlarmAttributes {
+	/// The counter the alarm watches, or `None` for an alarm which never
+	/// fires.
+	pub counter: Option<Counter>,
+	pub value_type: Option<ValueType>,
+	/// The value the alarm's trigger tests the counter against.
+	pub value: Option<SyncValue>,
+	pub test_type: Option<TestType>,
+	/// The amount added to `value` each time the alarm fires.
+	pub delta: Option<SyncValue>,
+	/// Whether this client is sent [`AlarmNotifyEvent`]s for the alarm.
+	pub events: Option<bool>,
+}
+
+impl AlarmAttributes {
+	/// Writes the attributes' value mask and list of values to `body`.
+	///
+	/// Unlike most value lists, `value` and `delta` take up 8 bytes each.
+	fn write_to(&self, body: &mut impl BufMut) {
+		let present = [
+			self.counter.is_some(),
+			self.value_type.is_some(),
+			self.value.is_some(),
+			self.test_type.is_some(),
+			self.delta.is_some(),
+			self.events.is_some(),
+		];
+		let mask = present
+			.iter()
+			.enumerate()
+			.filter(|(_, present)| **present)
+			.fold(0, |mask, (bit, _)| mask | (1 << bit));
+
+		body.put_u32(mask);
+
+		if let Some(counter) = self.counter {
+			body.put_u32(counter.id());
+		}
+		if let Some(value_type) = self.value_type {
+			body.put_u32(value_type as u32);
+		}
+		if let Some(value) = self.value {
+			value.write_to(body);
+		}
+		if let Some(test_type) = self.test_type {
+			body.put_u32(test_type as u32);
+		}
+		if let Some(delta) = self.delta {
+			delta.write_to(body);
+		}
+		if let Some(events) = self.events {
+			body.put_u32(u32::from(events));
+		}
+	}
+}
+
+/// The attributes and state of an [`Alarm`], from [`Client::query_alarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlarmInfo {
+	pub counter: Option<Counter>,
+	pub value_type: ValueType,
+	pub value: SyncValue,
+	pub test_type: TestType,
+	pub delta: SyncValue,
+	pub events: bool,
+	pub state: AlarmState,
+}
+
+/// A condition to wait for with [`Client::await_conditions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WaitCondition {
+	pub counter: Counter,
+	pub value_type: ValueType,
+	pub value: SyncValue,
+	pub test_type: TestType,
+	/// How far past `value` the counter may be when the condition is met
+	/// before a [`CounterNotifyEvent`] is sent.
+	pub event_threshold: SyncValue,
+}
+
+/// A `CounterNotify` event, sent when a [`WaitCondition`] is met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CounterNotifyEvent {
+	pub counter: Counter,
+	/// The value the condition waited for.
+	pub wait_value: SyncValue,
+	pub counter_value: SyncValue,
+	pub timestamp: Timestamp,
+	/// The number of `CounterNotify` events which follow this one for the same
+	/// `SyncAwait` request.
+	pub count: u16,
+	/// Whether the counter was destroyed.
+	pub destroyed: bool,
+}
+
+/// An `AlarmNotify` event, sent when an alarm fires or its state changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlarmNotifyEvent {
+	pub alarm: Alarm,
+	pub counter_value: SyncValue,
+	pub alarm_value: SyncValue,
+	pub timestamp: Timestamp,
+	pub state: AlarmState,
+}
+
+/// Returns the chunk of `frame` if it is the SYNC event at `offset` from the
+/// extension's first event code.
+fn sync_event(first_event: u8, offset: u8, frame: &X11Frame) -> Option<&[u8; 31]> {
+	let X11Frame::Event { code, chunk } = frame else {
+		return None;
+	};
+
+	((code & !SEND_EVENT_MASK).wrapping_sub(first_event) == offset).then_some(chunk)
+}
+
+impl CounterNotifyEvent {
+	/// Decodes a `CounterNotifyEvent` from an event frame, given the SYNC
+	/// extension's [first event code].
+	///
+	/// Returns `None` if `frame` is not a `CounterNotify` event.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	pub fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {
+		let chunk = sync_event(first_event, COUNTER_NOTIFY, frame)?;
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let u32_at =
+			|i: usize| u32::from_be_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+
+		Some(Self {
+			counter: Counter(u32_at(3)),
+			wait_value: SyncValue::read_at(chunk, 7)?,
+			counter_value: SyncValue::read_at(chunk, 15)?,
+			timestamp: Timestamp(u32_at(23)),
+			count: u16::from_be_bytes([chunk[27], chunk[28]]),
+			destroyed: chunk[29] != 0,
+		})
+	}
+}
+
+impl AlarmNotifyEvent {
+	/// Decodes an `AlarmNotifyEvent` from an event frame, given the SYNC
+	/// extension's [first event code].
+	///
+	/// Returns `None` if `frame` is not an `AlarmNotify` event.
+	///
+	/// [first event code]: crate::extension::ExtensionInfo::first_event
+	pub fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {
+		let chunk = sync_event(first_event, ALARM_NOTIFY, frame)?;
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let u32_at =
+			|i: usize| u32::from_be_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+
+		Some(Self {
+			alarm: Alarm(u32_at(3)),
+			counter_value: SyncValue::read_at(chunk, 7)?,
+			alarm_value: SyncValue::read_at(chunk, 15)?,
+			timestamp: Timestamp(u32_at(23)),
+			state: AlarmState::from_value(chunk[27])?,
+		})
+	}
+}
+
+/// The client's side of the `_NET_WM_SYNC_REQUEST` protocol for a window.
+///
+/// See [`Client::create_sync_request_counter`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyncRequestCounter {
+	window: Window,
+	counter: Counter,
+
+	/// The `WM_PROTOCOLS` atom.
+	wm_protocols: Atom,
+	/// The `_NET_WM_SYNC_REQUEST` atom.
+	sync_request: Atom,
+
+	/// The value sent in the last sync request, which the counter is set to
+	/// once the window has been redrawn.
+	pending: Option<SyncValue>,
+}
+
+impl SyncRequestCounter {
+	/// Returns the window the counter was created for.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// Returns the counter set in the window's `_NET_WM_SYNC_REQUEST_COUNTER`.
+	pub const fn counter(&self) -> Counter {
+		self.counter
+	}
+
+	/// Returns the value the counter will be set to by the next [`update`],
+	/// if the window manager has sent a sync request since the last one.
+	///
+	/// [`update`]: SyncRequestCounter::update
+	pub const fn pending(&self) -> Option<SyncValue> {
+		self.pending
+	}
+
+	/// Records the value of `frame` if it is a `_NET_WM_SYNC_REQUEST` client
+	/// message for the window, returning whether it was.
+	///
+	/// The window manager sends the message before the `ConfigureNotify`
+	/// event for the resize it is about.
+	pub fn observe(&mut self, frame: &X11Frame) -> bool {
+		let Some(Event::ClientMessage(message)) = Event::from_frame(frame) else {
+			return false;
+		};
+		let ClientMessageData::U32([protocol, _timestamp, low, high, _]) = message.data else {
+			return false;
+		};
+
+		if message.window != self.window
+			|| message.message_type != self.wm_protocols
+			|| Atom(protocol) != self.sync_request
+		{
+			return false;
+		}
+
+		self.pending = Some(SyncValue::from_parts(high as i32, low));
+
+		true
+	}
+
+	/// Sets the counter to the value of the last sync request, telling the
+	/// window manager that the window has been redrawn at its new size.
+	///
+	/// Does nothing if no sync request has been received since the last
+	/// update.
+	pub async fn update(&mut self, writer: &mut RequestWriter) -> Result<(), ReplyError> {
+		let Some(value) = self.pending.take() else {
+			return Ok(());
+		};
+
+		set_counter(writer, self.counter, value).await
+	}
+}
+
+/// Defines SYNC requests whose body is just an ID.
+macro_rules! id_requests {
+	($($(#[$attr:meta])* $name:ident($id:ty) = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name(pub $id);
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = concat!("Sync", stringify!($name));
+				const EXTENSION: Option<&'static str> = Some(SYNC);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(2);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.0.id());
+				}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16, $id]);
+		)*
+	};
+}
+
+/// Defines SYNC requests whose body is a counter and a value.
+macro_rules! counter_value_requests {
+	($($(#[$attr:meta])* $name:ident { $value:ident } = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name {
+				pub counter: Counter,
+				pub $value: SyncValue,
+			}
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = concat!("Sync", stringify!($name));
+				const EXTENSION: Option<&'static str> = Some(SYNC);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(4);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.counter.id());
+					self.$value.write_to(body);
+				}
+			}
+
+			assert_wire_size!($name: [u8, u8, u16, Counter, SyncValue]);
+		)*
+	};
+}
+
+/// The SYNC extension's `SyncInitialize` request, which tells the X server
+/// which version of SYNC the client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Initialize {
+	pub major_version: u8,
+	pub minor_version: u8,
+}
+
+impl Request for Initialize {
+	type Reply = InitializeReply;
+
+	const NAME: &'static str = "SyncInitialize";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = INITIALIZE;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u8(self.major_version);
+		body.put_u8(self.minor_version);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(Initialize: [u8, u8, u16, u8, u8, [u8; 2]]);
+
+/// The version of SYNC supported by the X server.
+///
+/// This is the reply to a `SyncInitialize` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InitializeReply {
+	pub major_version: u8,
+	pub minor_version: u8,
+}
+
+assert_wire_size!("SyncInitialize reply": [u8, u8, u16, u32, u8, u8, [u8; 22]] == 32);
+
+impl Reply for InitializeReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[major_version, minor_version] = chunk.get(..2)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version,
+			minor_version,
+		})
+	}
+}
+
+/// The SYNC extension's `SyncListSystemCounters` request.
+///
+/// See [`Client::system_counters`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListSystemCounters;
+
+impl Request for ListSystemCounters {
+	type Reply = SystemCounters;
+
+	const NAME: &'static str = "SyncListSystemCounters";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = LIST_SYSTEM_COUNTERS;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(ListSystemCounters: [u8, u8, u16]);
+
+/// The counters kept by the X server itself.
+///
+/// This is the reply to a `SyncListSystemCounters` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SystemCounters(pub Vec<SystemCounter>);
+
+assert_wire_size!("SyncListSystemCounters reply": [u8, u8, u16, u32, u32, [u8; 20]] == 32);
+
+impl Reply for SystemCounters {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[n0, n1, n2, n3] = chunk.get(..4)? else {
+			return None;
+		};
+		let count = u32::from_be_bytes([n0, n1, n2, n3]);
+		// The counters follow 20 unused bytes.
+		let mut data = chunk.get(24..)?;
+
+		(0..count)
+			.map(|_| {
+				let &[c0, c1, c2, c3, _, _, _, _, n0, n1, ..] = data else {
+					return None;
+				};
+				let resolution = SyncValue::read_at(data, 4)?;
+
+				let name_length = usize::from(u16::from_be_bytes([n0, n1]));
+				let name = data.get(10..10 + name_length)?;
+
+				// Each counter is padded to a multiple of 4 bytes.
+				let length = (10 + name_length).next_multiple_of(4);
+				data = data.get(length..).unwrap_or_default();
+
+				Some(SystemCounter {
+					counter: Counter(u32::from_be_bytes([c0, c1, c2, c3])),
+					resolution,
+					name: String::from_utf8_lossy(name).into_owned(),
+				})
+			})
+			.collect::<Option<_>>()
+			.map(Self)
+	}
+}
+
+counter_value_requests! {
+	/// The SYNC extension's `SyncCreateCounter` request.
+	///
+	/// See [`Client::create_counter`].
+	CreateCounter { initial } = CREATE_COUNTER;
+	/// The SYNC extension's `SyncSetCounter` request.
+	///
+	/// See [`Client::set_counter`].
+	SetCounter { value } = SET_COUNTER;
+	/// The SYNC extension's `SyncChangeCounter` request.
+	///
+	/// See [`Client::change_counter`].
+	ChangeCounter { amount } = CHANGE_COUNTER;
+}
+
+/// The SYNC extension's `SyncQueryCounter` request.
+///
+/// See [`Client::query_counter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryCounter(pub Counter);
+
+impl Request for QueryCounter {
+	type Reply = SyncValue;
+
+	const NAME: &'static str = "SyncQueryCounter";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = QUERY_COUNTER;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(QueryCounter: [u8, u8, u16, Counter]);
+assert_wire_size!("SyncQueryCounter reply": [u8, u8, u16, u32, SyncValue, [u8; 16]] == 32);
+
+impl Reply for SyncValue {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		Self::read_at(chunk, 0)
+	}
+}
+
+id_requests! {
+	/// The SYNC extension's `SyncDestroyCounter` request.
+	///
+	/// See [`Client::destroy_counter`].
+	DestroyCounter(Counter) = DESTROY_COUNTER;
+	/// The SYNC extension's `SyncDestroyAlarm` request.
+	///
+	/// See [`Client::destroy_alarm`].
+	DestroyAlarm(Alarm) = DESTROY_ALARM;
+}
+
+/// The SYNC extension's `SyncAwait` request.
+///
+/// See [`Client::await_conditions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Await<'a>(pub &'a [WaitCondition]);
+
+impl Request for Await<'_> {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SyncAwait";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = AWAIT;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		for condition in self.0 {
+			body.put_u32(condition.counter.id());
+			body.put_u32(condition.value_type as u32);
+			condition.value.write_to(body);
+			body.put_u32(condition.test_type as u32);
+			condition.event_threshold.write_to(body);
+		}
+	}
+}
+
+// Followed by the wait conditions.
+assert_wire_size!("SyncAwait request": [u8, u8, u16] == 4);
+assert_wire_size!("WAITCONDITION": [Counter, u32, SyncValue, u32, SyncValue] == 28);
+
+/// The SYNC extension's `SyncCreateAlarm` request.
+///
+/// See [`Client::create_alarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateAlarm {
+	/// The ID of the new alarm.
+	pub alarm: Alarm,
+	pub attributes: AlarmAttributes,
+}
+
+impl Request for CreateAlarm {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SyncCreateAlarm";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = CREATE_ALARM;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.alarm.id());
+		self.attributes.write_to(body);
+	}
+}
+
+// Followed by the attributes.
+assert_wire_size!("SyncCreateAlarm request": [u8, u8, u16, Alarm, u32] == 12);
+
+/// The SYNC extension's `SyncChangeAlarm` request.
+///
+/// See [`Client::change_alarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeAlarm {
+	pub alarm: Alarm,
+	pub attributes: AlarmAttributes,
+}
+
+impl Request for ChangeAlarm {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "SyncChangeAlarm";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = CHANGE_ALARM;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.alarm.id());
+		self.attributes.write_to(body);
+	}
+}
+
+// Followed by the attributes.
+assert_wire_size!("SyncChangeAlarm request": [u8, u8, u16, Alarm, u32] == 12);
+
+/// The SYNC extension's `SyncQueryAlarm` request.
+///
+/// See [`Client::query_alarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryAlarm(pub Alarm);
+
+impl Request for QueryAlarm {
+	type Reply = AlarmInfo;
+
+	const NAME: &'static str = "SyncQueryAlarm";
+	const EXTENSION: Option<&'static str> = Some(SYNC);
+	const OPCODE: u8 = QUERY_ALARM;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0.id());
+	}
+}
+
+assert_wire_size!(QueryAlarm: [u8, u8, u16, Alarm]);
+assert_wire_size!(
+	"SyncQueryAlarm reply": [
+		u8, u8, u16, u32,
+		u32, u32, SyncValue, u32, SyncValue, u8, u8, [u8; 2],
+	] == 40
+);
+
+impl Reply for AlarmInfo {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let u32_at = |i: usize| {
+			chunk
+				.get(i..i + 4)
+				.map(|value| u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+		};
+
+		Some(Self {
+			counter: match u32_at(0)? {
+				0 => None,
+				counter => Some(Counter(counter)),
+			},
+			value_type: ValueType::from_value(u32_at(4)?)?,
+			value: SyncValue::read_at(chunk, 8)?,
+			test_type: TestType::from_value(u32_at(16)?)?,
+			delta: SyncValue::read_at(chunk, 20)?,
+			events: *chunk.get(28)? != 0,
+			state: AlarmState::from_value(*chunk.get(29)?)?,
+		})
+	}
+}
+
+/// Queries the version of SYNC supported by the X server.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::sync_version`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn sync_version(connection: &mut impl RoundTrip) -> Result<(u8, u8), ReplyError> {
+	let request = Initialize {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+	let (major, minor) = extension::negotiate_version(connection, SYNC, &request, |version| {
+		(version.major_version.into(), version.minor_version.into())
+	})
+	.await?;
+
+	// The version was negotiated from `u8`s.
+	Ok((major as u8, minor as u8))
+}
+
+/// Lists the counters kept by the X server itself.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::system_counters`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn system_counters(
+	connection: &mut impl RoundTrip,
+) -> Result<Vec<SystemCounter>, ReplyError> {
+	let SystemCounters(counters) = request::reply_to(connection, &ListSystemCounters).await?;
+
+	Ok(counters)
+}
+
+/// Creates a counter with the value `initial`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_counter(
+	connection: &mut impl RoundTrip, initial: SyncValue,
+) -> Result<Counter, XidError> {
+	// The version is sent every time, as the X server expects it before any
+	// other SYNC request.
+	sync_version(connection).await?;
+	let counter = Counter(xid::generate_id(connection).await?);
+
+	request::send(connection, &CreateCounter { counter, initial }).await?;
+
+	Ok(counter)
+}
+
+/// Sets `counter` to `value`.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::set_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_counter(
+	connection: &mut impl RoundTrip, counter: Counter, value: SyncValue,
+) -> Result<(), ReplyError> {
+	request::send(connection, &SetCounter { counter, value }).await
+}
+
+/// Queries the value of `counter`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::query_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn query_counter(
+	connection: &mut impl RoundTrip, counter: Counter,
+) -> Result<SyncValue, ReplyError> {
+	request::reply_to(connection, &QueryCounter(counter)).await
+}
+
+/// Stops the X server from processing this client's requests until one of
+/// `conditions` is met.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::await_conditions`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn await_conditions(
+	connection: &mut impl RoundTrip, conditions: &[WaitCondition],
+) -> Result<(), ReplyError> {
+	request::send(connection, &Await(conditions)).await
+}
+
+/// Creates an alarm with the given `attributes`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_alarm`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_alarm(
+	connection: &mut impl RoundTrip, attributes: &AlarmAttributes,
+) -> Result<Alarm, XidError> {
+	sync_version(connection).await?;
+	let alarm = Alarm(xid::generate_id(connection).await?);
+
+	let request = CreateAlarm {
+		alarm,
+		attributes: *attributes,
+	};
+	request::send(connection, &request).await?;
+
+	Ok(alarm)
+}
+
+/// Changes the attributes of `alarm` which are given in `attributes`.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::change_alarm`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn change_alarm(
+	connection: &mut impl RoundTrip, alarm: Alarm, attributes: &AlarmAttributes,
+) -> Result<(), ReplyError> {
+	let request = ChangeAlarm {
+		alarm,
+		attributes: *attributes,
+	};
+
+	request::send(connection, &request).await
+}
+
+/// Queries the attributes and state of `alarm`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::query_alarm`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn query_alarm(
+	connection: &mut impl RoundTrip, alarm: Alarm,
+) -> Result<AlarmInfo, ReplyError> {
+	request::reply_to(connection, &QueryAlarm(alarm)).await
+}
+
+/// Creates a counter for the `_NET_WM_SYNC_REQUEST` protocol and sets it in
+/// `window`'s `_NET_WM_SYNC_REQUEST_COUNTER`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly].
+///
+/// See [`Client::create_sync_request_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_sync_request_counter(
+	connection: &mut impl RoundTrip, window: Window,
+) -> Result<SyncRequestCounter, XidError> {
+	let counter = create_counter(connection, SyncValue::ZERO).await?;
+
+	let wm_protocols = atoms::atom(connection, "WM_PROTOCOLS").await?;
+	let sync_request = atoms::atom(connection, "_NET_WM_SYNC_REQUEST").await?;
+	let sync_request_counter = atoms::atom(connection, "_NET_WM_SYNC_REQUEST_COUNTER").await?;
+
+	window
+		.set_property(
+			connection.writer(),
+			sync_request_counter,
+			&Property::u32s(Atom::CARDINAL, &[counter.id()]),
+			PropertyMode::Replace,
+		)
+		.await
+		.map_err(ReplyError::Io)?;
+
+	Ok(SyncRequestCounter {
+		window,
+		counter,
+
+		wm_protocols,
+		sync_request,
+
+		pending: None,
+	})
+}
+
+/// Adds `amount` to the value of `counter`.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::change_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn change_counter(
+	connection: &mut impl RoundTrip, counter: Counter, amount: SyncValue,
+) -> Result<(), ReplyError> {
+	request::send(connection, &ChangeCounter { counter, amount }).await
+}
+
+/// Destroys `counter`.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::destroy_counter`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn destroy_counter(
+	connection: &mut impl RoundTrip, counter: Counter,
+) -> Result<(), ReplyError> {
+	request::send(connection, &DestroyCounter(counter)).await
+}
+
+/// Destroys `alarm`.
+///
+/// With a [`RequestWriter`], if the SYNC extension hasn't been queried yet, its
+/// reply is [awaited directly].
+///
+/// See [`Client::destroy_alarm`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn destroy_alarm(
+	connection: &mut impl RoundTrip, alarm: Alarm,
+) -> Result<(), ReplyError> {
+	request::send(connection, &DestroyAlarm(alarm)).await
+}
+
+impl Client {
+	/// Queries the version of SYNC supported by the X server, as its major and
+	/// minor version.
+	pub async fn sync_version(&mut self) -> Result<(u8, u8), ReplyError> {
+		sync_version(self).await
+	}
+
+	/// Lists the counters kept by the X server itself, such as `SERVERTIME`
+	/// and `IDLETIME`.
+	pub async fn system_counters(&mut self) -> Result<Vec<SystemCounter>, ReplyError> {
+		system_counters(self).await
+	}
+
+	/// Creates a counter with the value `initial`.
+	///
+	/// The client's SYNC version is sent first, as the X server expects.
+	pub async fn create_counter(&mut self, initial: SyncValue) -> Result<Counter, XidError> {
+		create_counter(self, initial).await
+	}
+
+	/// Sets `counter` to `value`.
+	pub async fn set_counter(
+		&mut self, counter: Counter, value: SyncValue,
+	) -> Result<(), ReplyError> {
+		set_counter(self, counter, value).await
+	}
+
+	/// Adds `amount` to the value of `counter`.
+	pub async fn change_counter(
+		&mut self, counter: Counter, amount: SyncValue,
+	) -> Result<(), ReplyError> {
+		change_counter(self, counter, amount).await
+	}
+
+	/// Queries the value of `counter`.
+	pub async fn query_counter(&mut self, counter: Counter) -> Result<SyncValue, ReplyError> {
+		query_counter(self, counter).await
+	}
+
+	/// Destroys `counter`.
+	///
+	/// Clients waiting for the counter are sent a [`CounterNotifyEvent`] with
+	/// `destroyed` set.
+	pub async fn destroy_counter(&mut self, counter: Counter) -> Result<(), ReplyError> {
+		destroy_counter(self, counter).await
+	}
+
+	/// Stops the X server from processing this client's requests until one of
+	/// `conditions` is met.
+	///
+	/// A [`CounterNotifyEvent`] is sent for each condition whose counter has
+	/// gone past its value by at least its `event_threshold`.
+	pub async fn await_conditions(
+		&mut self, conditions: &[WaitCondition],
+	) -> Result<(), ReplyError> {
+		await_conditions(self, conditions).await
+	}
+
+	/// Creates an alarm with the given `attributes`.
+	///
+	/// The client's SYNC version is sent first, as the X server expects.
+	pub async fn create_alarm(&mut self, attributes: &AlarmAttributes) -> Result<Alarm, XidError> {
+		create_alarm(self, attributes).await
+	}
+
+	/// Changes the attributes of `alarm` which are given in `attributes`.
+	pub async fn change_alarm(
+		&mut self, alarm: Alarm, attributes: &AlarmAttributes,
+	) -> Result<(), ReplyError> {
+		change_alarm(self, alarm, attributes).await
+	}
+
+	/// Queries the attributes and state of `alarm`.
+	pub async fn query_alarm(&mut self, alarm: Alarm) -> Result<AlarmInfo, ReplyError> {
+		query_alarm(self, alarm).await
+	}
+
+	/// Destroys `alarm`.
+	pub async fn destroy_alarm(&mut self, alarm: Alarm) -> Result<(), ReplyError> {
+		destroy_alarm(self, alarm).await
+	}
+
+	/// Creates a counter for the `_NET_WM_SYNC_REQUEST` protocol and sets it in
+	/// `window`'s `_NET_WM_SYNC_REQUEST_COUNTER`, returning a
+	/// [`SyncRequestCounter`] which answers the window manager's sync requests.
+	///
+	/// The window must also list `_NET_WM_SYNC_REQUEST` in its `WM_PROTOCOLS`
+	/// for the window manager to send them.
+	pub async fn create_sync_request_counter(
+		&mut self, window: Window,
+	) -> Result<SyncRequestCounter, XidError> {
+		create_sync_request_counter(self, window).await
+	}
+}
@@ -0,0 +1,279 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Server timestamps, their ordering, and event latency measurement.
+//!
+//! The X server stamps input and selection events with a [`Timestamp`]: the
+//! server's time in milliseconds, which wraps around roughly every 49.7 days.
+//! Because of that wrapping, timestamps can only be ordered relative to each
+//! other when they are less than half that period apart, which
+//! [`Timestamp::wrapping_cmp`] does.
+//!
+//! The server's clock is unrelated to the client's, so the time between an
+//! event happening and it being received cannot be read off directly. A
+//! [`LatencyEstimator`] instead measures latency relative to the fastest
+//! delivery it has seen, while following the drift between the two clocks.
+
+use crate::raw::{WireSize, X11Frame};
+use std::{
+	cmp::Ordering,
+	fmt,
+	time::{Duration, Instant},
+};
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The default length of the windows over which a [`LatencyEstimator`] takes
+/// the fastest delivery.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+
+/// A time on the X server's clock, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timestamp(pub u32);
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if *self == Self::CURRENT_TIME {
+			write!(f, "CurrentTime")
+		} else {
+			write!(f, "{}ms", self.0)
+		}
+	}
+}
+
+impl WireSize for Timestamp {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+impl Timestamp {
+	/// The special `CurrentTime` timestamp, which the server replaces with its
+	/// current time.
+	pub const CURRENT_TIME: Self = Self(0);
+
+	/// Reads the timestamp of an event frame.
+	///
+	/// Returns `None` if `frame` is not an event which carries a timestamp:
+	/// key, button, motion, crossing, property and selection events.
+	pub fn from_event(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		let offset = match code & !SEND_EVENT_MASK {
+			// KeyPress, KeyRelease, ButtonPress, ButtonRelease, MotionNotify,
+			// EnterNotify and LeaveNotify.
+			2..=8 => 3,
+			// PropertyNotify.
+			28 => 7,
+			// SelectionClear, SelectionRequest and SelectionNotify.
+			29..=31 => 3,
+
+			_ => return None,
+		};
+
+		Some(Self(u32::from_be_bytes([
+			chunk[offset],
+			chunk[offset + 1],
+			chunk[offset + 2],
+			chunk[offset + 3],
+		])))
+	}
+
+	/// Compares this timestamp with `other`, taking into account that the
+	/// server's clock wraps around.
+	///
+	/// A timestamp is considered later than another if it is less than half
+	/// the clock's period (about 24.9 days) after it.
+	pub const fn wrapping_cmp(self, other: Self) -> Ordering {
+		match self.0.wrapping_sub(other.0) {
+			0 => Ordering::Equal,
+			difference if difference < (1 << 31) => Ordering::Greater,
+			_ => Ordering::Less,
+		}
+	}
+
+	/// Whether this timestamp is later than `other`.
+	///
+	/// See [`wrapping_cmp`](Timestamp::wrapping_cmp).
+	pub const fn is_after(self, other: Self) -> bool {
+		matches!(self.wrapping_cmp(other), Ordering::Greater)
+	}
+
+	/// The signed number of milliseconds from `earlier` to this timestamp,
+	/// taking into account that the server's clock wraps around.
+	pub const fn wrapping_since(self, earlier: Self) -> i32 {
+		self.0.wrapping_sub(earlier.0) as i32
+	}
+}
+
+/// The fastest delivery seen within a window of samples.
+#[derive(Debug, Clone, Copy)]
+struct Baseline {
+	/// The smallest difference between receipt time and server time, in
+	/// milliseconds.
+	offset: f64,
+	/// When that sample was received, in milliseconds since the estimator's
+	/// first sample.
+	at: f64,
+}
+
+/// Estimates the latency between events happening on the X server and being
+/// received by the client.
+///
+/// The server's clock and the client's clock have an unknown offset, so the
+/// estimator assumes that the fastest delivery it sees has no latency beyond
+/// the unavoidable minimum, and reports each event's latency relative to
+/// that. Any constant latency present in every delivery is therefore not
+/// measured; what is measured is the extra latency of each event, which is
+/// what varies with load.
+///
+/// The two clocks also drift apart slowly. The fastest delivery is taken
+/// within consecutive windows of time (30 seconds by default), and the change
+/// since the first window is used to correct for the drift.
+#[derive(Debug, Clone)]
+pub struct LatencyEstimator {
+	/// The length of each window.
+	window: Duration,
+
+	/// When the first sample was received.
+	epoch: Option<Instant>,
+	/// The server time of the last sample.
+	last_timestamp: Timestamp,
+	/// The unwrapped server time of the last sample, in milliseconds since
+	/// the first sample.
+	last_server_time: i64,
+
+	/// When the current window started, in milliseconds since the first
+	/// sample.
+	window_start: f64,
+	/// The fastest delivery within the current window.
+	current: Option<Baseline>,
+
+	/// The fastest delivery within the first complete window.
+	origin: Option<Baseline>,
+	/// The fastest delivery within the last complete window.
+	baseline: Option<Baseline>,
+	/// The estimated drift of the server's clock relative to the client's,
+	/// as the change in offset per millisecond.
+	drift: f64,
+}
+
+impl Default for LatencyEstimator {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LatencyEstimator {
+	/// Creates a new `LatencyEstimator` with no samples.
+	pub fn new() -> Self {
+		Self::with_window(DEFAULT_WINDOW)
+	}
+
+	/// Creates a new `LatencyEstimator` which takes the fastest delivery
+	/// within windows of the given length.
+	pub fn with_window(window: Duration) -> Self {
+		Self {
+			window,
+
+			epoch: None,
+			last_timestamp: Timestamp(0),
+			last_server_time: 0,
+
+			window_start: 0.0,
+			current: None,
+
+			origin: None,
+			baseline: None,
+			drift: 0.0,
+		}
+	}
+
+	/// Records an event with the given server `timestamp` which was
+	/// `received` at the given time, and returns its estimated latency.
+	pub fn observe(&mut self, timestamp: Timestamp, received: Instant) -> Duration {
+		let epoch = match self.epoch {
+			Some(epoch) => {
+				self.last_server_time += i64::from(timestamp.wrapping_since(self.last_timestamp));
+				epoch
+			},
+
+			None => *self.epoch.insert(received),
+		};
+		self.last_timestamp = timestamp;
+
+		let now = millis(received.saturating_duration_since(epoch));
+		let offset = now - (self.last_server_time as f64);
+
+		if now - self.window_start >= millis(self.window) {
+			if let Some(current) = self.current.take() {
+				// The drift is measured from the first window rather than the
+				// previous one: timestamps only have millisecond precision,
+				// so the drift is only visible over long periods.
+				let origin = *self.origin.get_or_insert(current);
+				if current.at > origin.at {
+					self.drift = (current.offset - origin.offset) / (current.at - origin.at);
+				}
+
+				self.baseline = Some(current);
+			}
+
+			self.window_start = now;
+		}
+
+		let sample = Baseline { offset, at: now };
+		match &mut self.current {
+			Some(current) if current.offset <= offset => {},
+			current => *current = Some(sample),
+		}
+
+		let fastest = self.predicted_offset(now).unwrap_or(offset);
+
+		Duration::from_secs_f64((offset - fastest).max(0.0) / 1000.0)
+	}
+
+	/// Records an event frame which was `received` at the given time, and
+	/// returns its estimated latency.
+	///
+	/// Returns `None` if `frame` does not carry a timestamp.
+	pub fn observe_event(&mut self, frame: &X11Frame, received: Instant) -> Option<Duration> {
+		Some(self.observe(Timestamp::from_event(frame)?, received))
+	}
+
+	/// The estimated drift of the server's clock relative to the client's, in
+	/// parts per million.
+	///
+	/// A positive drift means that the server's clock runs slower than the
+	/// client's.
+	pub fn drift_ppm(&self) -> f64 {
+		self.drift * 1_000_000.0
+	}
+
+	/// Forgets all samples, for example after the server's clock was changed.
+	pub fn reset(&mut self) {
+		*self = Self::with_window(self.window);
+	}
+
+	/// The offset of the fastest possible delivery at `now`, as far as is
+	/// known.
+	fn predicted_offset(&self, now: f64) -> Option<f64> {
+		let predicted = self
+			.baseline
+			.map(|baseline| baseline.offset + (self.drift * (now - baseline.at)));
+
+		match (predicted, self.current) {
+			(Some(predicted), Some(current)) => Some(predicted.min(current.offset)),
+			(predicted, current) => predicted.or(current.map(|current| current.offset)),
+		}
+	}
+}
+
+/// Returns `duration` in milliseconds.
+fn millis(duration: Duration) -> f64 {
+	duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,970 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Creating and managing windows.
+//!
+//! [`Client::create_window`] generates an ID for a new window and creates it,
+//! returning a [`Window`] handle whose methods send the core requests for
+//! mapping, configuring, reparenting and destroying it.
+//! [`Client::create_simple_window`] and [`Client::create_argb_window`] fill
+//! in the options for the usual cases: a plain window like its parent, and a
+//! window with an alpha channel for compositing managers to blend.
+//!
+//! The handle's methods take a [`RequestWriter`]; a [`Client`]'s can be
+//! borrowed with [`as_mut`].
+//!
+//! None of these requests have replies: errors they cause are received from
+//! [`next_event`] like any other error.
+//!
+//! [`next_event`]: crate::EventReader::next_event
+//! [`as_mut`]: AsMut::as_mut
+
+use crate::{
+	client::RoundTrip,
+	event_mask::EventMask,
+	geometry::{Point, Rectangle},
+	raw::{assert_wire_size, WireSize, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	xid::XidError,
+	BackingStores,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+use std::{fmt, fmt::Formatter};
+use tokio::io;
+
+/// The major opcode of the `CreateWindow` request.
+const CREATE_WINDOW: u8 = 1;
+/// The major opcode of the `ChangeWindowAttributes` request.
+const CHANGE_WINDOW_ATTRIBUTES: u8 = 2;
+/// The major opcode of the `GetWindowAttributes` request.
+const GET_WINDOW_ATTRIBUTES: u8 = 3;
+/// The major opcode of the `DestroyWindow` request.
+const DESTROY_WINDOW: u8 = 4;
+/// The major opcode of the `ReparentWindow` request.
+const REPARENT_WINDOW: u8 = 7;
+/// The major opcode of the `MapWindow` request.
+const MAP_WINDOW: u8 = 8;
+/// The major opcode of the `UnmapWindow` request.
+const UNMAP_WINDOW: u8 = 10;
+/// The major opcode of the `ConfigureWindow` request.
+const CONFIGURE_WINDOW: u8 = 12;
+/// The major opcode of the `GetGeometry` request.
+const GET_GEOMETRY: u8 = 14;
+/// The major opcode of the `QueryTree` request.
+const QUERY_TREE: u8 = 15;
+/// The major opcode of the `CreateColormap` request.
+const CREATE_COLORMAP: u8 = 78;
+
+/// A handle to a window.
+///
+/// A `Window` is only an ID: it does not own the window, and dropping it does
+/// not destroy the window. It is formatted in hexadecimal, as tools like
+/// `xwininfo` show window IDs.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Window(pub u32);
+
+impl fmt::Debug for Window {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "Window({:#x})", self.0)
+	}
+}
+
+impl fmt::Display for Window {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{:#x}", self.0)
+	}
+}
+
+impl WireSize for Window {
+	const WIRE_SIZE: usize = u32::WIRE_SIZE;
+}
+
+/// The class of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WindowClass {
+	/// The class of the parent window.
+	#[default]
+	CopyFromParent,
+	/// A window which can be drawn to and receives input.
+	InputOutput,
+	/// An invisible window which only receives input.
+	InputOnly,
+}
+
+/// Where a window's contents or position is moved to when its parent is
+/// resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gravity {
+	/// As a bit gravity, the window's contents are discarded. As a window
+	/// gravity, the window is unmapped (`Unmap`).
+	Forget,
+
+	NorthWest,
+	North,
+	NorthEast,
+	West,
+	Center,
+	East,
+	SouthWest,
+	South,
+	SouthEast,
+
+	/// The contents or position stay where they are relative to the root
+	/// window.
+	Static,
+}
+
+impl Gravity {
+	/// Returns the `Gravity` encoded as `value`.
+	#[cfg_attr(not(feature = "icccm"), allow(dead_code))]
+	pub(crate) const fn from_value(value: u32) -> Option<Self> {
+		Some(match value {
+			0 => Self::Forget,
+
+			1 => Self::NorthWest,
+			2 => Self::North,
+			3 => Self::NorthEast,
+			4 => Self::West,
+			5 => Self::Center,
+			6 => Self::East,
+			7 => Self::SouthWest,
+			8 => Self::South,
+			9 => Self::SouthEast,
+
+			10 => Self::Static,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Where a window is placed in the stacking order by [`Window::configure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StackMode {
+	Above,
+	Below,
+	TopIf,
+	BottomIf,
+	Opposite,
+}
+
+impl StackMode {
+	/// Returns the `StackMode` encoded as `value`.
+	pub(crate) const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Above,
+			1 => Self::Below,
+			2 => Self::TopIf,
+			3 => Self::BottomIf,
+			4 => Self::Opposite,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Attributes of a window, for [`Client::create_window`] and
+/// [`Window::set_attributes`].
+///
+/// Attributes which are `None` are left unchanged, or given their default
+/// values when creating a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WindowAttributes {
+	/// The pixmap used as the window's background, or `0` (`None`) or `1`
+	/// (`ParentRelative`).
+	pub background_pixmap: Option<u32>,
+	/// The pixel value of the window's background, overriding
+	/// `background_pixmap`.
+	pub background_pixel: Option<u32>,
+	/// The pixmap used as the window's border, or `0` (`CopyFromParent`).
+	pub border_pixmap: Option<u32>,
+	/// The pixel value of the window's border, overriding `border_pixmap`.
+	pub border_pixel: Option<u32>,
+
+	/// Where the window's contents are moved when it is resized.
+	pub bit_gravity: Option<Gravity>,
+	/// Where the window is moved when its parent is resized.
+	pub win_gravity: Option<Gravity>,
+
+	/// When the X server should maintain the window's contents while it is
+	/// obscured.
+	pub backing_store: Option<BackingStores>,
+	/// The planes of the window's contents which the backing store preserves.
+	pub backing_planes: Option<u32>,
+	/// The pixel value used in planes which are not preserved.
+	pub backing_pixel: Option<u32>,
+
+	/// Whether the window manager should leave the window alone.
+	pub override_redirect: Option<bool>,
+	/// Whether the contents of windows obscured by this window should be
+	/// saved.
+	pub save_under: Option<bool>,
+
+	/// The events this client selects on the window.
+	pub event_mask: Option<EventMask>,
+	/// The events which are not propagated to the window's ancestors.
+	pub do_not_propagate_mask: Option<EventMask>,
+
+	/// The window's colormap, or `0` (`CopyFromParent`).
+	pub colormap: Option<u32>,
+	/// The cursor shown over the window, or `0` (`None`) to use its
+	/// parent's.
+	pub cursor: Option<u32>,
+}
+
+impl WindowAttributes {
+	/// Writes the attributes' value mask and list of values to `body`.
+	fn write_to(&self, body: &mut impl BufMut) {
+		let values = [
+			self.background_pixmap,
+			self.background_pixel,
+			self.border_pixmap,
+			self.border_pixel,
+			self.bit_gravity.map(|gravity| gravity as u32),
+			self.win_gravity.map(|gravity| gravity as u32),
+			self.backing_store.map(|backing_store| backing_store as u32),
+			self.backing_planes,
+			self.backing_pixel,
+			self.override_redirect.map(u32::from),
+			self.save_under.map(u32::from),
+			self.event_mask.map(|mask| mask.0),
+			self.do_not_propagate_mask.map(|mask| mask.0),
+			self.colormap,
+			self.cursor,
+		];
+
+		body.put_u32(value_mask(&values));
+		write_values(body, &values);
+	}
+}
+
+/// Options for creating a window with [`Client::create_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WindowOptions {
+	/// The depth of the window, or `0` for its parent's depth.
+	pub depth: u8,
+	pub class: WindowClass,
+	/// The visual of the window, or `0` (`CopyFromParent`) for its parent's
+	/// visual.
+	pub visual: u32,
+
+	/// The width of the window's border, in pixels.
+	pub border_width: u16,
+
+	pub attributes: WindowAttributes,
+}
+
+/// Changes to a window's geometry and stacking order, for
+/// [`Window::configure`].
+///
+/// Values which are `None` are left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WindowChanges {
+	pub x: Option<i16>,
+	pub y: Option<i16>,
+	pub width: Option<u16>,
+	pub height: Option<u16>,
+	pub border_width: Option<u16>,
+
+	/// The sibling which `stack_mode` is relative to.
+	pub sibling: Option<u32>,
+	/// Where to place the window in the stacking order.
+	pub stack_mode: Option<StackMode>,
+}
+
+impl WindowChanges {
+	/// Returns changes which move and resize a window to `area`.
+	pub const fn area(area: Rectangle) -> Self {
+		Self {
+			x: Some(area.x),
+			y: Some(area.y),
+			width: Some(area.width),
+			height: Some(area.height),
+
+			border_width: None,
+			sibling: None,
+			stack_mode: None,
+		}
+	}
+}
+
+/// Returns the value mask for a list of optional values: bit `n` is set if
+/// the `n`th value is present.
+pub(crate) fn value_mask(values: &[Option<u32>]) -> u32 {
+	values
+		.iter()
+		.enumerate()
+		.filter(|(_, value)| value.is_some())
+		.fold(0, |mask, (bit, _)| mask | (1 << bit))
+}
+
+/// Writes the values which are present to `body`, each taking up 4 bytes.
+pub(crate) fn write_values(body: &mut impl BufMut, values: &[Option<u32>]) {
+	for value in values.iter().flatten() {
+		body.put_u32(*value);
+	}
+}
+
+/// The `CreateWindow` request.
+///
+/// See [`Client::create_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateWindow {
+	/// The ID of the new window.
+	pub window: Window,
+	pub parent: Window,
+	pub area: Rectangle,
+	pub options: WindowOptions,
+}
+
+impl Request for CreateWindow {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CreateWindow";
+	const OPCODE: u8 = CREATE_WINDOW;
+
+	fn metabyte(&self) -> u8 {
+		self.options.depth
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u32(self.parent.0);
+		self.area.write_to(body);
+		body.put_u16(self.options.border_width);
+		body.put_u16(self.options.class as u16);
+		body.put_u32(self.options.visual);
+		self.options.attributes.write_to(body);
+	}
+}
+
+/// The `ChangeWindowAttributes` request.
+///
+/// See [`Window::set_attributes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeWindowAttributes {
+	pub window: Window,
+	pub attributes: WindowAttributes,
+}
+
+impl Request for ChangeWindowAttributes {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ChangeWindowAttributes";
+	const OPCODE: u8 = CHANGE_WINDOW_ATTRIBUTES;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		self.attributes.write_to(body);
+	}
+}
+
+/// Defines requests whose body is just a window.
+macro_rules! window_requests {
+	($($(#[$attr:meta])* $name:ident = $opcode:expr;)*) => {
+		$(
+			$(#[$attr])*
+			#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+			pub struct $name(pub Window);
+
+			impl Request for $name {
+				type Reply = NoReply;
+
+				const NAME: &'static str = stringify!($name);
+				const OPCODE: u8 = $opcode;
+				const LENGTH: Option<u16> = Some(2);
+
+				fn write_body(&self, body: &mut BytesMut) {
+					body.put_u32(self.0 .0);
+				}
+			}
+
+			assert_wire_size!($name: [u8, [u8; 1], u16, Window]);
+		)*
+	};
+}
+
+window_requests! {
+	/// The `DestroyWindow` request.
+	///
+	/// See [`Window::destroy`].
+	DestroyWindow = DESTROY_WINDOW;
+	/// The `MapWindow` request.
+	///
+	/// See [`Window::map`].
+	MapWindow = MAP_WINDOW;
+	/// The `UnmapWindow` request.
+	///
+	/// See [`Window::unmap`].
+	UnmapWindow = UNMAP_WINDOW;
+}
+
+/// The `ReparentWindow` request.
+///
+/// See [`Window::reparent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReparentWindow {
+	pub window: Window,
+	pub parent: Window,
+	pub position: Point,
+}
+
+impl Request for ReparentWindow {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ReparentWindow";
+	const OPCODE: u8 = REPARENT_WINDOW;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.window.0);
+		body.put_u32(self.parent.0);
+		self.position.write_to(body);
+	}
+}
+
+assert_wire_size!(ReparentWindow: [u8, [u8; 1], u16, Window, Window, Point]);
+
+/// The `ConfigureWindow` request.
+///
+/// See [`Window::configure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigureWindow {
+	pub window: Window,
+	pub changes: WindowChanges,
+}
+
+impl Request for ConfigureWindow {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "ConfigureWindow";
+	const OPCODE: u8 = CONFIGURE_WINDOW;
+
+	fn write_body(&self, body: &mut BytesMut) {
+		let changes = &self.changes;
+		let values = [
+			changes.x.map(|x| x as u32),
+			changes.y.map(|y| y as u32),
+			changes.width.map(u32::from),
+			changes.height.map(u32::from),
+			changes.border_width.map(u32::from),
+			changes.sibling,
+			changes.stack_mode.map(|mode| mode as u32),
+		];
+
+		body.put_u32(self.window.0);
+		body.put_u16(value_mask(&values) as u16);
+		// 2 unused bytes.
+		body.put_u16(0);
+		write_values(body, &values);
+	}
+}
+
+/// The `GetGeometry` request, whose reply is a drawable's [`Geometry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetGeometry {
+	/// The window or pixmap.
+	pub drawable: u32,
+}
+
+impl Request for GetGeometry {
+	type Reply = Geometry;
+
+	const NAME: &'static str = "GetGeometry";
+	const OPCODE: u8 = GET_GEOMETRY;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.drawable);
+	}
+}
+
+assert_wire_size!(GetGeometry: [u8, [u8; 1], u16, u32]);
+
+/// A drawable's root window, depth, and area relative to its parent.
+///
+/// This is the reply to a `GetGeometry` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Geometry {
+	pub root: Window,
+	pub depth: u8,
+	/// The drawable's area, inside its border; its position is that of the
+	/// outside corner of its border, relative to its parent's origin.
+	pub area: Rectangle,
+	pub border_width: u16,
+}
+
+assert_wire_size!("GetGeometry reply": [u8, u8, u16, u32, Window, Rectangle, u16, [u8; 10]] == 32);
+
+impl Reply for Geometry {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte: depth,
+			chunk,
+			..
+		} = frame
+		else {
+			return None;
+		};
+		let &[r0, r1, r2, r3, x0, x1, y0, y1, w0, w1, h0, h1, b0, b1] = chunk.get(..14)? else {
+			return None;
+		};
+
+		Some(Self {
+			root: Window(u32::from_be_bytes([r0, r1, r2, r3])),
+			depth: *depth,
+			area: Rectangle {
+				x: i16::from_be_bytes([x0, x1]),
+				y: i16::from_be_bytes([y0, y1]),
+				width: u16::from_be_bytes([w0, w1]),
+				height: u16::from_be_bytes([h0, h1]),
+			},
+			border_width: u16::from_be_bytes([b0, b1]),
+		})
+	}
+}
+
+/// The `QueryTree` request, whose reply is a window's [`Tree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryTree(pub Window);
+
+impl Request for QueryTree {
+	type Reply = Tree;
+
+	const NAME: &'static str = "QueryTree";
+	const OPCODE: u8 = QUERY_TREE;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0 .0);
+	}
+}
+
+assert_wire_size!(QueryTree: [u8, [u8; 1], u16, Window]);
+
+/// A window's root window, parent and children.
+///
+/// This is the reply to a `QueryTree` request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tree {
+	pub root: Window,
+	/// The window's parent, or `None` for a root window.
+	pub parent: Option<Window>,
+	/// The window's children, in bottom-to-top stacking order.
+	pub children: Vec<Window>,
+}
+
+impl Reply for Tree {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[r0, r1, r2, r3, p0, p1, p2, p3, n0, n1] = chunk.get(..10)? else {
+			return None;
+		};
+
+		// The children follow 14 unused bytes.
+		let count = usize::from(u16::from_be_bytes([n0, n1]));
+		let children = chunk
+			.get(24..24 + 4 * count)?
+			.chunks_exact(4)
+			.map(|id| Window(u32::from_be_bytes([id[0], id[1], id[2], id[3]])))
+			.collect();
+
+		Some(Self {
+			root: Window(u32::from_be_bytes([r0, r1, r2, r3])),
+			parent: match u32::from_be_bytes([p0, p1, p2, p3]) {
+				0 => None,
+				parent => Some(Window(parent)),
+			},
+			children,
+		})
+	}
+}
+
+/// Whether a window is mapped, and if so, whether it can be seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapState {
+	Unmapped,
+	/// The window is mapped, but an ancestor is not.
+	Unviewable,
+	Viewable,
+}
+
+/// The `GetWindowAttributes` request, whose reply is a window's
+/// [`WindowState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetWindowAttributes(pub Window);
+
+impl Request for GetWindowAttributes {
+	type Reply = WindowState;
+
+	const NAME: &'static str = "GetWindowAttributes";
+	const OPCODE: u8 = GET_WINDOW_ATTRIBUTES;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.0 .0);
+	}
+}
+
+assert_wire_size!(GetWindowAttributes: [u8, [u8; 1], u16, Window]);
+
+/// A window's current attributes.
+///
+/// This is the reply to a `GetWindowAttributes` request. Attributes which
+/// are only of use to the X server, such as backing store hints, are left
+/// out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowState {
+	/// The ID of the window's visual.
+	pub visual: u32,
+	pub class: WindowClass,
+	pub map_state: MapState,
+	pub override_redirect: bool,
+	/// The window's colormap, or `0` (`None`).
+	pub colormap: u32,
+	/// The events selected on the window by every client.
+	pub all_event_masks: EventMask,
+	/// The events selected on the window by this client.
+	pub your_event_mask: EventMask,
+	/// The events which are not propagated to the window's ancestors.
+	pub do_not_propagate_mask: u16,
+}
+
+assert_wire_size!(
+	"GetWindowAttributes reply": [
+		u8, u8, u16, u32,
+		u32, u16, u8, u8, u32, u32,
+		u8, u8, u8, u8,
+		u32, u32, u32, u16, [u8; 2],
+	] == 44
+);
+
+impl Reply for WindowState {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let u32_at = |offset: usize| -> Option<u32> {
+			Some(u32::from_be_bytes(
+				chunk.get(offset..offset + 4)?.try_into().ok()?,
+			))
+		};
+		let &[c0, c1] = chunk.get(4..6)? else {
+			return None;
+		};
+		let &[map_state, override_redirect] = chunk.get(18..20)? else {
+			return None;
+		};
+		let &[d0, d1] = chunk.get(32..34)? else {
+			return None;
+		};
+
+		Some(Self {
+			visual: u32_at(0)?,
+			class: match u16::from_be_bytes([c0, c1]) {
+				1 => WindowClass::InputOutput,
+				2 => WindowClass::InputOnly,
+				_ => return None,
+			},
+			map_state: match map_state {
+				0 => MapState::Unmapped,
+				1 => MapState::Unviewable,
+				2 => MapState::Viewable,
+				_ => return None,
+			},
+			override_redirect: override_redirect != 0,
+			colormap: u32_at(20)?,
+			all_event_masks: EventMask(u32_at(24)?),
+			your_event_mask: EventMask(u32_at(28)?),
+			do_not_propagate_mask: u16::from_be_bytes([d0, d1]),
+		})
+	}
+}
+
+/// The `CreateColormap` request.
+///
+/// Windows whose visual differs from their parent's need a colormap of their
+/// visual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateColormap {
+	/// Whether every entry of the colormap is allocated for writing by this
+	/// client; `TrueColor` colormaps have no writable entries.
+	pub alloc_all: bool,
+	/// The ID of the new colormap.
+	pub colormap: u32,
+	/// A window on the screen the colormap is for.
+	pub window: Window,
+	/// The ID of the colormap's visual.
+	pub visual: u32,
+}
+
+impl Request for CreateColormap {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "CreateColormap";
+	const OPCODE: u8 = CREATE_COLORMAP;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn metabyte(&self) -> u8 {
+		self.alloc_all.into()
+	}
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.colormap);
+		body.put_u32(self.window.0);
+		body.put_u32(self.visual);
+	}
+}
+
+assert_wire_size!(CreateColormap: [u8, [u8; 1], u16, u32, Window, u32]);
+
+/// Creates a window as a child of `parent`, covering `area` relative to
+/// the parent's origin.
+///
+/// With a [`RequestWriter`], if a new range of resource IDs has to be
+/// requested, its reply is [awaited directly].
+///
+/// See [`Client::create_window`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_window(
+	connection: &mut impl RoundTrip, parent: Window, area: Rectangle, options: &WindowOptions,
+) -> Result<Window, XidError> {
+	let window = Window(crate::xid::generate_id(connection).await?);
+
+	request::send_core(
+		connection.writer(),
+		&CreateWindow {
+			window,
+			parent,
+			area,
+			options: *options,
+		},
+	)
+	.await
+	.map_err(ReplyError::Io)?;
+
+	Ok(window)
+}
+
+/// Creates a window as a child of `parent` with its parent's depth and
+/// visual.
+///
+/// With a [`RequestWriter`], if a new range of resource IDs has to be
+/// requested, its reply is [awaited directly].
+///
+/// See [`Client::create_simple_window`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_simple_window(
+	connection: &mut impl RoundTrip, parent: Window, area: Rectangle, border_width: u16,
+	border_pixel: u32, background_pixel: u32,
+) -> Result<Window, XidError> {
+	let options = WindowOptions {
+		class: WindowClass::InputOutput,
+		border_width,
+		attributes: WindowAttributes {
+			background_pixel: Some(background_pixel),
+			border_pixel: Some(border_pixel),
+
+			..WindowAttributes::default()
+		},
+
+		..WindowOptions::default()
+	};
+
+	create_window(connection, parent, area, &options).await
+}
+
+/// Creates a window as a child of `parent` with a 32-bit visual.
+///
+/// With a [`RequestWriter`], if a new range of resource IDs has to be
+/// requested, its reply is [awaited directly].
+///
+/// See [`Client::create_argb_window`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn create_argb_window(
+	connection: &mut impl RoundTrip, parent: Window, area: Rectangle, attributes: &WindowAttributes,
+) -> Result<Window, XidError> {
+	let screen = connection.writer().default_screen();
+	let root = Window(screen.root);
+
+	let visual = screen
+		.argb_visual()
+		.map(|visual| visual.id)
+		.ok_or_else(|| {
+			ReplyError::Io(io::Error::new(
+				io::ErrorKind::Unsupported,
+				"the default screen has no 32-bit TrueColor visual",
+			))
+		})?;
+
+	// Windows with a different visual than their parent need a colormap of
+	// that visual.
+	let colormap = match attributes.colormap {
+		Some(colormap) => colormap,
+
+		None => {
+			let colormap = crate::xid::generate_id(connection).await?;
+
+			let request = CreateColormap {
+				alloc_all: false,
+				colormap,
+				window: root,
+				visual,
+			};
+			request::send_core(connection.writer(), &request)
+				.await
+				.map_err(ReplyError::Io)?;
+
+			colormap
+		},
+	};
+
+	let options = WindowOptions {
+		depth: 32,
+		class: WindowClass::InputOutput,
+		visual,
+		border_width: 0,
+
+		// The border pixel must be given, as the window's depth differs from
+		// its parent's.
+		attributes: WindowAttributes {
+			background_pixel: attributes.background_pixel.or(Some(0)),
+			border_pixel: attributes.border_pixel.or(Some(0)),
+			colormap: Some(colormap),
+
+			..*attributes
+		},
+	};
+
+	create_window(connection, parent, area, &options).await
+}
+
+impl Client {
+	/// Creates a window as a child of `parent`, covering `area` relative to
+	/// the parent's origin.
+	///
+	/// The window is created unmapped; see [`Window::map`]. Windows are
+	/// usually created as children of the [root window].
+	///
+	/// [root window]: Client::root_window
+	pub async fn create_window(
+		&mut self, parent: Window, area: Rectangle, options: &WindowOptions,
+	) -> Result<Window, XidError> {
+		create_window(self, parent, area, options).await
+	}
+
+	/// Creates a window as a child of `parent` with its parent's depth and
+	/// visual, covering `area` relative to the parent's origin.
+	///
+	/// The window has a border `border_width` pixels wide of `border_pixel`
+	/// and a background of `background_pixel`, such as the [`Screen`]'s
+	/// `black_pixel` and `white_pixel`. It is created unmapped; see
+	/// [`Window::map`].
+	///
+	/// [`Screen`]: crate::Screen
+	pub async fn create_simple_window(
+		&mut self, parent: Window, area: Rectangle, border_width: u16, border_pixel: u32,
+		background_pixel: u32,
+	) -> Result<Window, XidError> {
+		create_simple_window(
+			self,
+			parent,
+			area,
+			border_width,
+			border_pixel,
+			background_pixel,
+		)
+		.await
+	}
+
+	/// Creates a window as a child of `parent` with a 32-bit visual, whose
+	/// spare 8 bits are used as an alpha channel by compositing managers.
+	///
+	/// A colormap for the visual is created unless `attributes` gives one,
+	/// and the border and background are transparent unless `attributes`
+	/// gives their pixels. The window has no border and is created unmapped;
+	/// see [`Window::map`].
+	///
+	/// Returns an [`Unsupported`] error if the default screen has no 32-bit
+	/// `TrueColor` visual.
+	///
+	/// [`Unsupported`]: io::ErrorKind::Unsupported
+	pub async fn create_argb_window(
+		&mut self, parent: Window, area: Rectangle, attributes: &WindowAttributes,
+	) -> Result<Window, XidError> {
+		create_argb_window(self, parent, area, attributes).await
+	}
+}
+
+impl Window {
+	/// The window's ID.
+	pub const fn id(self) -> u32 {
+		self.0
+	}
+
+	/// Maps the window, making it visible once all of its ancestors are
+	/// mapped.
+	///
+	/// If the window manager has selected `SubstructureRedirect` on the
+	/// parent, it is asked to map the window instead.
+	pub async fn map(self, writer: &mut RequestWriter) -> io::Result<()> {
+		request::send_core(writer, &MapWindow(self)).await
+	}
+
+	/// Unmaps the window, hiding it and its descendants.
+	pub async fn unmap(self, writer: &mut RequestWriter) -> io::Result<()> {
+		request::send_core(writer, &UnmapWindow(self)).await
+	}
+
+	/// Destroys the window and all of its descendants.
+	pub async fn destroy(self, writer: &mut RequestWriter) -> io::Result<()> {
+		request::send_core(writer, &DestroyWindow(self)).await
+	}
+
+	/// Changes the window's position, size, border width or stacking order.
+	pub async fn configure(
+		self, writer: &mut RequestWriter, changes: &WindowChanges,
+	) -> io::Result<()> {
+		let request = ConfigureWindow {
+			window: self,
+			changes: *changes,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Changes the window's attributes.
+	pub async fn set_attributes(
+		self, writer: &mut RequestWriter, attributes: &WindowAttributes,
+	) -> io::Result<()> {
+		let request = ChangeWindowAttributes {
+			window: self,
+			attributes: *attributes,
+		};
+
+		request::send_core(writer, &request).await
+	}
+
+	/// Moves the window to be a child of `parent`, at `position` relative to
+	/// the parent's origin.
+	pub async fn reparent(
+		self, writer: &mut RequestWriter, parent: Window, position: Point,
+	) -> io::Result<()> {
+		let request = ReparentWindow {
+			window: self,
+			parent,
+			position,
+		};
+
+		request::send_core(writer, &request).await
+	}
+}
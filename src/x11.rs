@@ -0,0 +1,460 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Errors sent by the X server.
+//!
+//! The X server reports a request it could not carry out with an error
+//! frame, identifying the request by its sequence number and opcodes. Errors
+//! for requests with [`ReplyCookie`]s are returned as [`ReplyError::X11`];
+//! other errors are received as [`X11Frame::Error`]s alongside events, which
+//! [`Error::from_frame`] decodes, or can be received separately with
+//! [`EventReader::spawn_with_errors`].
+//!
+//! Errors are displayed with their Xlib names, such as
+//! `BadWindow (0x2a00003)`. Extension errors are only numbered by the X
+//! server, so their names are looked up in the [`ErrorNames`] of the
+//! extensions queried on the connection:
+//!
+//! ```no_run
+//! # use xrs::{x11, Client};
+//! # fn example(client: &Client, error: &x11::Error) {
+//! eprintln!("{}", client.error_names().display(error));
+//! # }
+//! ```
+//!
+//! [`ReplyCookie`]: crate::ReplyCookie
+//! [`ReplyError::X11`]: crate::ReplyError::X11
+//! [`EventReader::spawn_with_errors`]: crate::EventReader::spawn_with_errors
+
+use crate::{raw::X11Frame, Client, RequestWriter};
+use std::{fmt, fmt::Formatter};
+
+/// The names of the errors defined by extensions, by extension name, in
+/// order of their error codes.
+const EXTENSION_ERRORS: &[(&str, &[&str])] = &[
+	("DAMAGE", &["BadDamage"]),
+	("MIT-SHM", &["BadShmSeg"]),
+	(
+		"RANDR",
+		&[
+			"BadRROutput",
+			"BadRRCrtc",
+			"BadRRMode",
+			"BadRRProvider",
+			"BadRRLease",
+		],
+	),
+	(
+		"RENDER",
+		&[
+			"BadPictFormat",
+			"BadPicture",
+			"BadPictOp",
+			"BadGlyphSet",
+			"BadGlyph",
+		],
+	),
+	("SYNC", &["BadCounter", "BadAlarm", "BadFence"]),
+	("XFIXES", &["BadRegion", "BadBarrier"]),
+	(
+		"XInputExtension",
+		&["BadDevice", "BadEvent", "BadMode", "DeviceBusy", "BadClass"],
+	),
+	("XKEYBOARD", &["BadKeyboard"]),
+];
+
+/// The information included in every [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorDetails {
+	/// The sequence number of the request which caused the error.
+	pub sequence: u16,
+
+	/// The major opcode of the request which caused the error.
+	pub major_opcode: u8,
+	/// The minor opcode of the request which caused the error, for extension
+	/// requests.
+	pub minor_opcode: u16,
+
+	/// The value which caused the error, for errors which have one: the
+	/// invalid value of a `Value` error, or the invalid ID of a resource
+	/// error.
+	///
+	/// For other errors, this is unused and its meaning unspecified.
+	pub bad_value: u32,
+}
+
+/// An error sent by the X server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error {
+	/// The major or minor opcode of the request does not exist.
+	Request(ErrorDetails),
+	/// A numeric value in the request is out of range.
+	Value(ErrorDetails),
+	/// A window ID in the request does not name a window.
+	Window(ErrorDetails),
+	/// A pixmap ID in the request does not name a pixmap.
+	Pixmap(ErrorDetails),
+	/// An atom in the request does not exist.
+	Atom(ErrorDetails),
+	/// A cursor ID in the request does not name a cursor.
+	Cursor(ErrorDetails),
+	/// A font ID in the request does not name a font.
+	Font(ErrorDetails),
+	/// Arguments or attributes in the request do not match each other.
+	Match(ErrorDetails),
+	/// A drawable ID in the request does not name a window or pixmap.
+	Drawable(ErrorDetails),
+	/// The client is not allowed to carry out the request.
+	Access(ErrorDetails),
+	/// The X server could not allocate the requested resource.
+	Alloc(ErrorDetails),
+	/// A colormap ID in the request does not name a colormap.
+	Colormap(ErrorDetails),
+	/// A graphics context ID in the request does not name a graphics context.
+	GContext(ErrorDetails),
+	/// A resource ID chosen by the client is outside its range or already in
+	/// use.
+	IdChoice(ErrorDetails),
+	/// A named font or color does not exist.
+	Name(ErrorDetails),
+	/// The request's length is wrong for its contents, or longer than the
+	/// maximum request length.
+	Length(ErrorDetails),
+	/// The X server does not implement some part of the request.
+	Implementation(ErrorDetails),
+
+	/// An error defined by an extension, identified by its `code`.
+	///
+	/// The extension's [first error code] is needed to tell which of its
+	/// errors this is.
+	///
+	/// [first error code]: crate::extension::ExtensionInfo::first_error
+	Extension { code: u8, details: ErrorDetails },
+}
+
+impl Error {
+	/// The code of [`Error::Request`].
+	pub const BAD_REQUEST: u8 = 1;
+	/// The code of [`Error::Value`].
+	pub const BAD_VALUE: u8 = 2;
+	/// The code of [`Error::Window`].
+	pub const BAD_WINDOW: u8 = 3;
+	/// The code of [`Error::Pixmap`].
+	pub const BAD_PIXMAP: u8 = 4;
+	/// The code of [`Error::Atom`].
+	pub const BAD_ATOM: u8 = 5;
+	/// The code of [`Error::Cursor`].
+	pub const BAD_CURSOR: u8 = 6;
+	/// The code of [`Error::Font`].
+	pub const BAD_FONT: u8 = 7;
+	/// The code of [`Error::Match`].
+	pub const BAD_MATCH: u8 = 8;
+	/// The code of [`Error::Drawable`].
+	pub const BAD_DRAWABLE: u8 = 9;
+	/// The code of [`Error::Access`].
+	pub const BAD_ACCESS: u8 = 10;
+	/// The code of [`Error::Alloc`].
+	pub const BAD_ALLOC: u8 = 11;
+	/// The code of [`Error::Colormap`].
+	pub const BAD_COLORMAP: u8 = 12;
+	/// The code of [`Error::GContext`].
+	pub const BAD_GCONTEXT: u8 = 13;
+	/// The code of [`Error::IdChoice`].
+	pub const BAD_ID_CHOICE: u8 = 14;
+	/// The code of [`Error::Name`].
+	pub const BAD_NAME: u8 = 15;
+	/// The code of [`Error::Length`].
+	pub const BAD_LENGTH: u8 = 16;
+	/// The code of [`Error::Implementation`].
+	pub const BAD_IMPLEMENTATION: u8 = 17;
+
+	/// Decodes an `Error` from an error frame.
+	///
+	/// Returns `None` if `frame` is not an error.
+	pub fn from_frame(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Error {
+			code,
+			sequence,
+			metablock,
+			minor_opcode,
+			major_opcode,
+			..
+		} = *frame
+		else {
+			return None;
+		};
+
+		let details = ErrorDetails {
+			sequence,
+
+			major_opcode,
+			minor_opcode,
+
+			bad_value: u32::from_be_bytes(metablock),
+		};
+
+		Some(match code {
+			Self::BAD_REQUEST => Self::Request(details),
+			Self::BAD_VALUE => Self::Value(details),
+			Self::BAD_WINDOW => Self::Window(details),
+			Self::BAD_PIXMAP => Self::Pixmap(details),
+			Self::BAD_ATOM => Self::Atom(details),
+			Self::BAD_CURSOR => Self::Cursor(details),
+			Self::BAD_FONT => Self::Font(details),
+			Self::BAD_MATCH => Self::Match(details),
+			Self::BAD_DRAWABLE => Self::Drawable(details),
+			Self::BAD_ACCESS => Self::Access(details),
+			Self::BAD_ALLOC => Self::Alloc(details),
+			Self::BAD_COLORMAP => Self::Colormap(details),
+			Self::BAD_GCONTEXT => Self::GContext(details),
+			Self::BAD_ID_CHOICE => Self::IdChoice(details),
+			Self::BAD_NAME => Self::Name(details),
+			Self::BAD_LENGTH => Self::Length(details),
+			Self::BAD_IMPLEMENTATION => Self::Implementation(details),
+
+			code => Self::Extension { code, details },
+		})
+	}
+
+	/// The error's code.
+	pub const fn code(&self) -> u8 {
+		match self {
+			Self::Request(_) => Self::BAD_REQUEST,
+			Self::Value(_) => Self::BAD_VALUE,
+			Self::Window(_) => Self::BAD_WINDOW,
+			Self::Pixmap(_) => Self::BAD_PIXMAP,
+			Self::Atom(_) => Self::BAD_ATOM,
+			Self::Cursor(_) => Self::BAD_CURSOR,
+			Self::Font(_) => Self::BAD_FONT,
+			Self::Match(_) => Self::BAD_MATCH,
+			Self::Drawable(_) => Self::BAD_DRAWABLE,
+			Self::Access(_) => Self::BAD_ACCESS,
+			Self::Alloc(_) => Self::BAD_ALLOC,
+			Self::Colormap(_) => Self::BAD_COLORMAP,
+			Self::GContext(_) => Self::BAD_GCONTEXT,
+			Self::IdChoice(_) => Self::BAD_ID_CHOICE,
+			Self::Name(_) => Self::BAD_NAME,
+			Self::Length(_) => Self::BAD_LENGTH,
+			Self::Implementation(_) => Self::BAD_IMPLEMENTATION,
+
+			Self::Extension { code, .. } => *code,
+		}
+	}
+
+	/// The information included in the error.
+	pub const fn details(&self) -> &ErrorDetails {
+		match self {
+			Self::Request(details)
+			| Self::Value(details)
+			| Self::Window(details)
+			| Self::Pixmap(details)
+			| Self::Atom(details)
+			| Self::Cursor(details)
+			| Self::Font(details)
+			| Self::Match(details)
+			| Self::Drawable(details)
+			| Self::Access(details)
+			| Self::Alloc(details)
+			| Self::Colormap(details)
+			| Self::GContext(details)
+			| Self::IdChoice(details)
+			| Self::Name(details)
+			| Self::Length(details)
+			| Self::Implementation(details)
+			| Self::Extension { details, .. } => details,
+		}
+	}
+
+	/// The sequence number of the request which caused the error.
+	pub const fn sequence(&self) -> u16 {
+		self.details().sequence
+	}
+
+	/// Whether this error is caused by a resource ID which doesn't name a
+	/// resource of the right type, or which can't be chosen for a new one.
+	pub const fn is_resource_error(&self) -> bool {
+		self.offending_xid().is_some()
+	}
+
+	/// The invalid resource ID which caused the error, if it is a
+	/// [resource error].
+	///
+	/// [resource error]: Error::is_resource_error
+	pub const fn offending_xid(&self) -> Option<u32> {
+		match self {
+			Self::Window(details)
+			| Self::Pixmap(details)
+			| Self::Cursor(details)
+			| Self::Font(details)
+			| Self::Drawable(details)
+			| Self::Colormap(details)
+			| Self::GContext(details)
+			| Self::IdChoice(details) => Some(details.bad_value),
+
+			_ => None,
+		}
+	}
+
+	/// The error's name, as used by Xlib, such as `BadWindow`.
+	///
+	/// Returns `None` for extension errors, whose names depend on the
+	/// extension; see [`ErrorNames`].
+	pub const fn name(&self) -> Option<&'static str> {
+		Some(match self {
+			Self::Request(_) => "BadRequest",
+			Self::Value(_) => "BadValue",
+			Self::Window(_) => "BadWindow",
+			Self::Pixmap(_) => "BadPixmap",
+			Self::Atom(_) => "BadAtom",
+			Self::Cursor(_) => "BadCursor",
+			Self::Font(_) => "BadFont",
+			Self::Match(_) => "BadMatch",
+			Self::Drawable(_) => "BadDrawable",
+			Self::Access(_) => "BadAccess",
+			Self::Alloc(_) => "BadAlloc",
+			Self::Colormap(_) => "BadColormap",
+			Self::GContext(_) => "BadGC",
+			Self::IdChoice(_) => "BadIDChoice",
+			Self::Name(_) => "BadName",
+			Self::Length(_) => "BadLength",
+			Self::Implementation(_) => "BadImplementation",
+
+			Self::Extension { .. } => return None,
+		})
+	}
+
+	/// Writes the error to `f`, calling it `name` if it has one.
+	fn write(&self, f: &mut Formatter<'_>, name: Option<&str>) -> fmt::Result {
+		let details = self.details();
+
+		match name {
+			Some(name) => write!(f, "{name}")?,
+			None => write!(f, "extension error {}", self.code())?,
+		}
+
+		match self {
+			Self::Value(details) | Self::Atom(details) => write!(f, " ({})", details.bad_value)?,
+
+			_ => {
+				if let Some(xid) = self.offending_xid() {
+					write!(f, " ({xid:#x})")?;
+				}
+			},
+		}
+
+		write!(
+			f,
+			" in request {}.{} (sequence {})",
+			details.major_opcode, details.minor_opcode, details.sequence,
+		)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.write(f, self.name())
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The names of errors, including those of registered extensions.
+///
+/// Extension errors are numbered from the extension's [first error code],
+/// which differs between X servers, so their names are only known once the
+/// extension has been queried. [`Client::error_names`] registers the
+/// extensions X.RS knows the errors of which have been queried; others can
+/// be added with [`register`].
+///
+/// [first error code]: crate::extension::ExtensionInfo::first_error
+/// [`register`]: ErrorNames::register
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ErrorNames {
+	/// The first error code of each registered extension and the names of
+	/// its errors.
+	extensions: Vec<(u8, &'static [&'static str])>,
+}
+
+impl ErrorNames {
+	/// Creates `ErrorNames` with no extensions registered.
+	pub const fn new() -> Self {
+		Self {
+			extensions: Vec::new(),
+		}
+	}
+
+	/// Registers the `names` of an extension's errors, in order of their
+	/// codes from `first_error`.
+	pub fn register(&mut self, first_error: u8, names: &'static [&'static str]) {
+		self.extensions.push((first_error, names));
+	}
+
+	/// Returns the name of `error`, or `None` if it is an error of an
+	/// extension which isn't registered.
+	pub fn name(&self, error: &Error) -> Option<&'static str> {
+		if let Some(name) = error.name() {
+			return Some(name);
+		}
+
+		let code = error.code();
+
+		// An extension may implement fewer errors than are named, so the error
+		// belongs to the extension whose errors start closest before it.
+		let (first_error, names) = self
+			.extensions
+			.iter()
+			.filter(|(first_error, _)| *first_error <= code)
+			.max_by_key(|(first_error, _)| *first_error)?;
+
+		names.get(usize::from(code - first_error)).copied()
+	}
+
+	/// Returns a value which displays `error` with its name, if it has one.
+	pub fn display<'a>(&'a self, error: &'a Error) -> impl fmt::Display + 'a {
+		NamedError {
+			error,
+			name: self.name(error),
+		}
+	}
+}
+
+/// An error displayed with its name from [`ErrorNames`].
+struct NamedError<'a> {
+	error: &'a Error,
+	name: Option<&'static str>,
+}
+
+impl fmt::Display for NamedError<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		self.error.write(f, self.name)
+	}
+}
+
+impl Client {
+	/// Returns the names of errors, including those of the extensions which
+	/// have been queried.
+	///
+	/// See [`ErrorNames`].
+	pub fn error_names(&self) -> ErrorNames {
+		self.writer.error_names()
+	}
+}
+
+impl RequestWriter {
+	/// Returns the names of errors, including those of the extensions which
+	/// have been queried.
+	///
+	/// See [`Client::error_names`].
+	pub fn error_names(&self) -> ErrorNames {
+		let mut error_names = ErrorNames::new();
+
+		for &(name, names) in EXTENSION_ERRORS {
+			if let Some(Some(info)) = self.extensions.get(name) {
+				error_names.register(info.first_error, names);
+			}
+		}
+
+		error_names
+	}
+}
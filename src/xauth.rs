@@ -0,0 +1,555 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Reading and writing authorization cookies in Xauthority files.
+//!
+//! An Xauthority file (`$XAUTHORITY`, or `~/.Xauthority` by default) is a
+//! sequence of entries, each of which associates authorization data with a
+//! display on a particular host. Every entry is made of a big-endian `u16`
+//! address family followed by four fields, each of which is a big-endian
+//! `u16` length followed by that many bytes: the address, the display
+//! number, the authorization protocol name, and the authorization data.
+//!
+//! [`Client::connect`] looks up cookies itself, so this module is for tools
+//! like `xauth` which manage the file, and for minting cookies to hand out:
+//!
+//! ```no_run
+//! # use xrs::xauth::{self, Entry, Xauthority};
+//! # async fn example() -> std::io::Result<()> {
+//! let path = xauth::path().expect("no Xauthority file");
+//!
+//! let cookie = xauth::generate_cookie()?;
+//! let entry = Entry::mit_magic_cookie(Entry::FAMILY_LOCAL, b"myhost", b"1", &cookie);
+//!
+//! Xauthority::update(&path, |xauthority| xauthority.add(entry)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Xauthority files are shared between processes, so they are [locked] the
+//! same way `xauth` and libXau lock them while being changed.
+//!
+//! [`Client::connect`]: crate::Client::connect
+//! [locked]: XauthorityLock
+
+use crate::AuthInfo;
+use std::{
+	env,
+	ffi::OsString,
+	io::Read,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use tokio::{fs, io};
+
+/// The only authorization protocol that is looked up automatically.
+const MIT_MAGIC_COOKIE: &[u8] = b"MIT-MAGIC-COOKIE-1";
+
+/// The length of a `MIT-MAGIC-COOKIE-1` cookie, in bytes.
+const COOKIE_LENGTH: usize = 16;
+
+/// How old a lock file must be before it is assumed to have been left
+/// behind by a process which crashed.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(600);
+
+/// A single entry in an Xauthority file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Entry {
+	/// The address family of `address`, such as [`Entry::FAMILY_LOCAL`].
+	pub family: u16,
+	pub address: Vec<u8>,
+	/// The display number, written in decimal.
+	///
+	/// An empty display number matches any display.
+	pub number: Vec<u8>,
+
+	/// The name of the authorization protocol, such as `MIT-MAGIC-COOKIE-1`.
+	pub name: Vec<u8>,
+	/// The authorization data, such as a cookie.
+	pub data: Vec<u8>,
+}
+
+impl Entry {
+	/// The address family of an IPv4 address.
+	pub const FAMILY_INTERNET: u16 = 0;
+	/// The address family of an IPv6 address.
+	pub const FAMILY_INTERNET6: u16 = 6;
+	/// The address family of a local connection, with the machine's hostname
+	/// as the address.
+	pub const FAMILY_LOCAL: u16 = 256;
+	/// The address family which matches any address.
+	pub const FAMILY_WILD: u16 = 65535;
+
+	/// Creates a `MIT-MAGIC-COOKIE-1` entry authorizing connections to
+	/// display `number` at `address` with `cookie`.
+	pub fn mit_magic_cookie(family: u16, address: &[u8], number: &[u8], cookie: &[u8]) -> Self {
+		Self {
+			family,
+			address: address.to_vec(),
+			number: number.to_vec(),
+
+			name: MIT_MAGIC_COOKIE.to_vec(),
+			data: cookie.to_vec(),
+		}
+	}
+
+	/// Returns whether `self` and `other` are for the same display, address
+	/// and authorization protocol, so that one replaces the other.
+	pub fn same_key(&self, other: &Self) -> bool {
+		self.family == other.family
+			&& self.address == other.address
+			&& self.number == other.number
+			&& self.name == other.name
+	}
+
+	/// Appends the entry to `buf` as it is written in an Xauthority file.
+	///
+	/// Returns an [`InvalidInput`] error if any field is longer than
+	/// [`u16::MAX`] bytes.
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	pub fn write_to(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+		buf.extend_from_slice(&self.family.to_be_bytes());
+
+		for field in [&self.address, &self.number, &self.name, &self.data] {
+			let length = u16::try_from(field.len()).map_err(|_| {
+				io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"Xauthority entry fields can't be longer than 65535 bytes",
+				)
+			})?;
+
+			buf.extend_from_slice(&length.to_be_bytes());
+			buf.extend_from_slice(field);
+		}
+
+		Ok(())
+	}
+
+	/// Whether the entry authorizes connections to `display` at the given
+	/// `peer` address.
+	///
+	/// `peer` is `None` for local connections, such as those over unix domain
+	/// sockets.
+	fn matches(&self, peer: Option<IpAddr>, display: &[u8], hostname: Option<&[u8]>) -> bool {
+		if !self.number.is_empty() && self.number != display {
+			return false;
+		}
+
+		match (self.family, peer) {
+			(Self::FAMILY_WILD, _) => true,
+
+			// If the local hostname is unknown, no local entry can be told to be
+			// for this machine.
+			(Self::FAMILY_LOCAL, None) => hostname.is_some_and(|hostname| self.address == hostname),
+
+			(Self::FAMILY_INTERNET, Some(IpAddr::V4(address))) => self.address == address.octets(),
+			(Self::FAMILY_INTERNET6, Some(IpAddr::V6(address))) => self.address == address.octets(),
+
+			_ => false,
+		}
+	}
+}
+
+impl From<Entry> for AuthInfo {
+	fn from(entry: Entry) -> Self {
+		Self {
+			protocol_name: String::from_utf8_lossy(&entry.name).into_owned(),
+			protocol_data: entry.data,
+		}
+	}
+}
+
+/// Parses the entries of an Xauthority file.
+///
+/// Returns an error if the file ends partway through an entry.
+fn parse(mut buf: &[u8]) -> io::Result<Vec<Entry>> {
+	fn take_u16(buf: &mut &[u8]) -> io::Result<u16> {
+		let (value, rest) = buf.split_first_chunk::<2>().ok_or_else(truncated)?;
+		*buf = rest;
+
+		Ok(u16::from_be_bytes(*value))
+	}
+
+	fn take_field(buf: &mut &[u8]) -> io::Result<Vec<u8>> {
+		let length = take_u16(buf)? as usize;
+
+		if buf.len() < length {
+			return Err(truncated());
+		}
+		let (field, rest) = buf.split_at(length);
+		*buf = rest;
+
+		Ok(field.to_vec())
+	}
+
+	fn truncated() -> io::Error {
+		io::Error::new(io::ErrorKind::UnexpectedEof, "truncated Xauthority entry")
+	}
+
+	let mut entries = Vec::new();
+
+	while !buf.is_empty() {
+		entries.push(Entry {
+			family: take_u16(&mut buf)?,
+			address: take_field(&mut buf)?,
+			number: take_field(&mut buf)?,
+
+			name: take_field(&mut buf)?,
+			data: take_field(&mut buf)?,
+		});
+	}
+
+	Ok(entries)
+}
+
+/// The path of the user's Xauthority file: `$XAUTHORITY`, or `.Xauthority`
+/// in their home directory.
+pub fn path() -> Option<PathBuf> {
+	if let Some(path) = env::var_os("XAUTHORITY") {
+		return Some(path.into());
+	}
+
+	env::var_os("HOME").map(|home| PathBuf::from(home).join(".Xauthority"))
+}
+
+/// Generates a random `MIT-MAGIC-COOKIE-1` cookie from the system's random
+/// number generator.
+pub fn generate_cookie() -> io::Result<[u8; COOKIE_LENGTH]> {
+	let mut cookie = [0; COOKIE_LENGTH];
+	std::fs::File::open("/dev/urandom")?.read_exact(&mut cookie)?;
+
+	Ok(cookie)
+}
+
+/// The hostname of this machine, which is the address of local entries, as
+/// given by `gethostname(2)`.
+#[cfg(unix)]
+fn local_hostname() -> Option<Vec<u8>> {
+	// Hostnames are at most 255 bytes long, plus the terminating nul byte.
+	let mut buffer = [0u8; 256];
+
+	// SAFETY: `buffer` is valid for writes of `buffer.len()` bytes.
+	if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+		return None;
+	}
+
+	// A truncated hostname may not be nul-terminated, in which case it can't
+	// match an entry anyway.
+	let length = buffer.iter().position(|&byte| byte == 0)?;
+
+	(length > 0).then(|| buffer[..length].to_vec())
+}
+
+/// The hostname of this machine, which isn't known on this platform.
+#[cfg(not(unix))]
+fn local_hostname() -> Option<Vec<u8>> {
+	None
+}
+
+/// Returns `path` with `suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = OsString::from(path);
+	path.push(suffix);
+
+	path.into()
+}
+
+/// Returns the error for an Xauthority file which is locked by another
+/// process.
+fn locked() -> io::Error {
+	io::Error::new(
+		io::ErrorKind::WouldBlock,
+		"the Xauthority file is locked by another process",
+	)
+}
+
+/// The entries of an Xauthority file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Xauthority {
+	entries: Vec<Entry>,
+}
+
+impl Xauthority {
+	/// Creates an Xauthority file with no entries.
+	pub const fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+		}
+	}
+
+	/// Parses the entries of an Xauthority file from `buf`.
+	///
+	/// Returns an error if the file ends partway through an entry.
+	pub fn parse(buf: &[u8]) -> io::Result<Self> {
+		parse(buf).map(|entries| Self { entries })
+	}
+
+	/// Reads the Xauthority file at `path`.
+	///
+	/// A file which doesn't exist is read as having no entries.
+	pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		match fs::read(path).await {
+			Ok(buf) => Self::parse(&buf),
+
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+			Err(error) => Err(error),
+		}
+	}
+
+	/// Writes the entries to the Xauthority file at `path`, replacing it.
+	///
+	/// The entries are written to a temporary file next to `path` which then
+	/// replaces it, so that other processes never read a partly written
+	/// file. The file is only readable and writable by its owner.
+	pub async fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let path = path.as_ref();
+		let temporary = with_suffix(path, "-n");
+
+		let mut options = fs::OpenOptions::new();
+		options.write(true).create(true).truncate(true);
+		#[cfg(unix)]
+		options.mode(0o600);
+
+		let mut file = options.open(&temporary).await?;
+		io::AsyncWriteExt::write_all(&mut file, &self.to_bytes()?).await?;
+		file.sync_all().await?;
+
+		fs::rename(&temporary, path).await
+	}
+
+	/// Locks the Xauthority file at `path`, applies `change` to its entries,
+	/// and saves them.
+	///
+	/// Returns a [`WouldBlock`] error if another process has locked the
+	/// file; see [`XauthorityLock::acquire`].
+	///
+	/// [`WouldBlock`]: io::ErrorKind::WouldBlock
+	pub async fn update<T>(
+		path: impl AsRef<Path>, change: impl FnOnce(&mut Self) -> T,
+	) -> io::Result<T> {
+		let path = path.as_ref();
+		let lock = XauthorityLock::acquire(path).await?;
+
+		let mut xauthority = Self::load(path).await?;
+		let output = change(&mut xauthority);
+		xauthority.save(path).await?;
+
+		lock.release().await?;
+
+		Ok(output)
+	}
+
+	/// Returns the entries as they are written in an Xauthority file.
+	///
+	/// Returns an [`InvalidInput`] error if any field of an entry is longer
+	/// than [`u16::MAX`] bytes.
+	///
+	/// [`InvalidInput`]: io::ErrorKind::InvalidInput
+	pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+		let mut buf = Vec::new();
+
+		for entry in &self.entries {
+			entry.write_to(&mut buf)?;
+		}
+
+		Ok(buf)
+	}
+
+	/// The entries, in the order they are looked up.
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// Adds `entry`, replacing any entry with the [same key].
+	///
+	/// [same key]: Entry::same_key
+	pub fn add(&mut self, entry: Entry) {
+		match self
+			.entries
+			.iter_mut()
+			.find(|existing| existing.same_key(&entry))
+		{
+			Some(existing) => *existing = entry,
+			None => self.entries.push(entry),
+		}
+	}
+
+	/// Removes every entry for display `number` at `address`, returning the
+	/// entries which were removed.
+	pub fn remove(&mut self, family: u16, address: &[u8], number: &[u8]) -> Vec<Entry> {
+		let (removed, kept) = self.entries.drain(..).partition(|entry| {
+			entry.family == family && entry.address == address && entry.number == number
+		});
+		self.entries = kept;
+
+		removed
+	}
+
+	/// Adds every entry of `other`, replacing entries with the [same key].
+	///
+	/// [same key]: Entry::same_key
+	pub fn merge(&mut self, other: Self) {
+		for entry in other.entries {
+			self.add(entry);
+		}
+	}
+
+	/// Finds the `MIT-MAGIC-COOKIE-1` entry which authorizes connections to
+	/// `display` at the given `peer` address.
+	///
+	/// `peer` is `None` for local connections. Connections to loopback
+	/// addresses are treated as local too, as that is how Xauthority entries
+	/// are written for them.
+	pub fn find(&self, peer: Option<IpAddr>, display: u16) -> Option<&Entry> {
+		let peer = peer
+			.map(|peer| match peer {
+				IpAddr::V6(address) => address
+					.to_ipv4_mapped()
+					.map_or(IpAddr::V6(address), IpAddr::V4),
+				IpAddr::V4(_) => peer,
+			})
+			.filter(|peer| !peer.is_loopback());
+
+		let display = display.to_string().into_bytes();
+		let hostname = local_hostname();
+
+		self.entries.iter().find(|entry| {
+			entry.name == MIT_MAGIC_COOKIE && entry.matches(peer, &display, hostname.as_deref())
+		})
+	}
+}
+
+impl FromIterator<Entry> for Xauthority {
+	fn from_iter<I: IntoIterator<Item = Entry>>(entries: I) -> Self {
+		Self {
+			entries: entries.into_iter().collect(),
+		}
+	}
+}
+
+impl IntoIterator for Xauthority {
+	type Item = Entry;
+	type IntoIter = std::vec::IntoIter<Entry>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.entries.into_iter()
+	}
+}
+
+/// A lock on an Xauthority file, held until it is [released] or dropped.
+///
+/// Xauthority files are locked with the same lock files as `xauth` and
+/// libXau use: `<file>-c` is created exclusively, then hard linked to
+/// `<file>-l`.
+///
+/// [released]: XauthorityLock::release
+#[derive(Debug)]
+#[must_use = "the Xauthority file is unlocked when the lock is dropped"]
+pub struct XauthorityLock {
+	create: PathBuf,
+	link: PathBuf,
+
+	released: bool,
+}
+
+impl XauthorityLock {
+	/// Locks the Xauthority file at `path`.
+	///
+	/// Lock files left behind by a process which crashed are removed once
+	/// they are ten minutes old. Returns a [`WouldBlock`] error if another
+	/// process holds the lock, in which case it can be tried again later.
+	///
+	/// [`WouldBlock`]: io::ErrorKind::WouldBlock
+	pub async fn acquire(path: impl AsRef<Path>) -> io::Result<Self> {
+		let path = path.as_ref();
+
+		let mut lock = Self {
+			create: with_suffix(path, "-c"),
+			link: with_suffix(path, "-l"),
+
+			released: false,
+		};
+
+		for file in [&lock.create, &lock.link] {
+			if let Ok(metadata) = fs::metadata(file).await {
+				let age = metadata
+					.modified()
+					.ok()
+					.and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+				if age.is_some_and(|age| age >= STALE_LOCK_AGE) {
+					let _ = fs::remove_file(file).await;
+				}
+			}
+		}
+
+		let created = fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&lock.create)
+			.await;
+
+		match created {
+			Ok(_) => {},
+
+			// The lock files aren't ours, so they mustn't be removed.
+			Err(error) => {
+				lock.released = true;
+
+				return Err(match error.kind() {
+					io::ErrorKind::AlreadyExists => locked(),
+					_ => error,
+				});
+			},
+		}
+
+		match fs::hard_link(&lock.create, &lock.link).await {
+			Ok(()) => Ok(lock),
+
+			// Only `<file>-c` is ours, so only it is removed.
+			Err(error) => {
+				lock.released = true;
+				let _ = fs::remove_file(&lock.create).await;
+
+				Err(match error.kind() {
+					io::ErrorKind::AlreadyExists => locked(),
+					_ => error,
+				})
+			},
+		}
+	}
+
+	/// Unlocks the Xauthority file.
+	pub async fn release(mut self) -> io::Result<()> {
+		self.released = true;
+
+		fs::remove_file(&self.link).await?;
+		fs::remove_file(&self.create).await
+	}
+}
+
+impl Drop for XauthorityLock {
+	fn drop(&mut self) {
+		if !self.released {
+			let _ = std::fs::remove_file(&self.link);
+			let _ = std::fs::remove_file(&self.create);
+		}
+	}
+}
+
+/// Finds the `MIT-MAGIC-COOKIE-1` entry in the user's Xauthority file which
+/// authorizes connections to `display` at the given `peer` address.
+///
+/// Returns `None` if there is no Xauthority file, it cannot be read, or it
+/// has no matching entry. See [`Xauthority::find`].
+pub(crate) async fn find(peer: Option<IpAddr>, display: i16) -> Option<AuthInfo> {
+	let xauthority = Xauthority::parse(&fs::read(path()?).await.ok()?).ok()?;
+
+	xauthority
+		.find(peer, u16::try_from(display).ok()?)
+		.cloned()
+		.map(AuthInfo::from)
+}
@@ -0,0 +1,156 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Generated by `xrs-codegen` from `xrs-codegen/xml/xc_misc.xml`. Don't edit
+// this file by hand: edit the description, and regenerate it with `cargo run -p
+// xrs-codegen`.
+
+//! The XC-MISC extension, version 1.1.
+//!
+//! This module is generated from the extension's xcbproto description, so it
+//! mirrors the protocol directly. Its requests are sent with
+//! [`Client::request`] or [`Client::send_request`].
+//!
+//! [`Client::request`]: crate::Client::request
+//! [`Client::send_request`]: crate::Client::send_request
+
+use crate::{
+	raw::{assert_wire_size, X11Frame},
+	request::{Reply, Request},
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the XC-MISC extension.
+pub const EXTENSION_NAME: &str = "XC-MISC";
+/// The version of XC-MISC described by this module.
+pub const VERSION: (u16, u16) = (1, 1);
+
+/// The `GetVersion` request, whose reply is a [`GetVersionReply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetVersion {
+	pub client_major_version: u16,
+	pub client_minor_version: u16,
+}
+
+impl Request for GetVersion {
+	type Reply = GetVersionReply;
+
+	const NAME: &'static str = "GetVersion";
+	const EXTENSION: Option<&'static str> = Some(EXTENSION_NAME);
+	const OPCODE: u8 = 0;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.client_major_version);
+		body.put_u16(self.client_minor_version);
+	}
+}
+
+assert_wire_size!(GetVersion: [u8, u8, u16, u16, u16]);
+
+/// The reply to a [`GetVersion`] request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetVersionReply {
+	pub server_major_version: u16,
+	pub server_minor_version: u16,
+}
+
+impl Reply for GetVersionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		Some(Self {
+			server_major_version: u16::from_be_bytes(chunk.get(0..2)?.try_into().ok()?),
+			server_minor_version: u16::from_be_bytes(chunk.get(2..4)?.try_into().ok()?),
+		})
+	}
+}
+
+assert_wire_size!("GetVersion reply": [u8, [u8; 1], u16, u32, u16, u16, [u8; 20]] == 32);
+
+/// The `GetXIDRange` request, whose reply is a [`GetXidRangeReply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetXidRange;
+
+impl Request for GetXidRange {
+	type Reply = GetXidRangeReply;
+
+	const NAME: &'static str = "GetXIDRange";
+	const EXTENSION: Option<&'static str> = Some(EXTENSION_NAME);
+	const OPCODE: u8 = 1;
+	const LENGTH: Option<u16> = Some(1);
+
+	fn write_body(&self, _body: &mut BytesMut) {}
+}
+
+assert_wire_size!(GetXidRange: [u8, u8, u16]);
+
+/// The reply to a [`GetXidRange`] request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetXidRangeReply {
+	pub start_id: u32,
+	pub count: u32,
+}
+
+impl Reply for GetXidRangeReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		Some(Self {
+			start_id: u32::from_be_bytes(chunk.get(0..4)?.try_into().ok()?),
+			count: u32::from_be_bytes(chunk.get(4..8)?.try_into().ok()?),
+		})
+	}
+}
+
+assert_wire_size!("GetXIDRange reply": [u8, [u8; 1], u16, u32, u32, u32, [u8; 16]] == 32);
+
+/// The `GetXIDList` request, whose reply is a [`GetXidListReply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetXidList {
+	pub count: u32,
+}
+
+impl Request for GetXidList {
+	type Reply = GetXidListReply;
+
+	const NAME: &'static str = "GetXIDList";
+	const EXTENSION: Option<&'static str> = Some(EXTENSION_NAME);
+	const OPCODE: u8 = 2;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.count);
+	}
+}
+
+assert_wire_size!(GetXidList: [u8, u8, u16, u32]);
+
+/// The reply to a [`GetXidList`] request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetXidListReply {
+	pub ids: Vec<u32>,
+}
+
+impl Reply for GetXidListReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		let ids_len = u32::from_be_bytes(chunk.get(0..4)?.try_into().ok()?);
+		let rest = chunk.get(24..)?;
+		let (ids, _) = rest.split_at_checked(4 * ids_len as usize)?;
+		let ids = ids
+			.chunks_exact(4)
+			.map(|value| u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+			.collect();
+
+		Some(Self { ids })
+	}
+}
@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The XFIXES extension's version negotiation.
+//!
+//! XFIXES is used by the `pointer` module for pointer barriers and hiding the
+//! cursor, and by the `region` module for regions stored on the X server. Its
+//! version is negotiated once, the first time either needs it, so that each
+//! can be enabled without the other.
+
+use crate::{
+	client::RoundTrip,
+	extension,
+	raw::{assert_wire_size, X11Frame},
+	request::{Reply, Request},
+	ReplyError,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the XFIXES extension.
+pub(crate) const XFIXES: &str = "XFIXES";
+
+/// The minor opcode of the `XFixesQueryVersion` request.
+const QUERY_VERSION: u8 = 0;
+
+/// The version of XFIXES requested by this client: the highest used by this
+/// crate.
+#[cfg_attr(not(any(feature = "pointer", feature = "region")), allow(dead_code))]
+const XFIXES_VERSION: (u32, u32) = (5, 0);
+
+/// The XFIXES extension's `XFixesQueryVersion` request, which tells the X
+/// server which version of XFIXES the client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XFixesQueryVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+impl Request for XFixesQueryVersion {
+	type Reply = XFixesVersion;
+
+	const NAME: &'static str = "XFixesQueryVersion";
+	const EXTENSION: Option<&'static str> = Some(XFIXES);
+	const OPCODE: u8 = QUERY_VERSION;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u32(self.major_version);
+		body.put_u32(self.minor_version);
+	}
+}
+
+assert_wire_size!(XFixesQueryVersion: [u8, u8, u16, u32, u32]);
+
+/// The version of XFIXES supported by both the X server and the client.
+///
+/// This is the reply to an `XFixesQueryVersion` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XFixesVersion {
+	pub major_version: u32,
+	pub minor_version: u32,
+}
+
+assert_wire_size!("XFixesQueryVersion reply": [u8, u8, u16, u32, u32, u32, [u8; 16]] == 32);
+
+impl Reply for XFixesVersion {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[a0, a1, a2, a3, b0, b1, b2, b3] = chunk.get(..8)? else {
+			return None;
+		};
+
+		Some(Self {
+			major_version: u32::from_be_bytes([a0, a1, a2, a3]),
+			minor_version: u32::from_be_bytes([b0, b1, b2, b3]),
+		})
+	}
+}
+
+/// Returns the major opcode of XFIXES, having negotiated its version.
+///
+/// Returns an [`Unsupported`] error if the X server doesn't support XFIXES,
+/// or an [`UnsupportedVersion`] error if it doesn't support `version` of it.
+///
+/// [`Unsupported`]: io::ErrorKind::Unsupported
+/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+#[cfg_attr(not(any(feature = "pointer", feature = "region")), allow(dead_code))]
+pub(crate) async fn require_xfixes(
+	connection: &mut impl RoundTrip, version: (u32, u32),
+) -> Result<u8, ReplyError> {
+	let xfixes = extension::require_extension(connection, XFIXES).await?;
+
+	let request = XFixesQueryVersion {
+		major_version: XFIXES_VERSION.0,
+		minor_version: XFIXES_VERSION.1,
+	};
+	let supported = extension::negotiate_version(connection, XFIXES, &request, |version| {
+		(version.major_version, version.minor_version)
+	})
+	.await?;
+	extension::require_version(XFIXES, supported, version)?;
+
+	Ok(xfixes.major_opcode)
+}
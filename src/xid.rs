@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Allocation of resource IDs (XIDs).
+//!
+//! Clients choose the IDs of the windows, pixmaps, graphics contexts, fonts,
+//! cursors and colormaps they create themselves, from a range assigned to
+//! them by the X server when they connect: the IDs which have only the bits
+//! of the `resource_id_mask` set, combined with the `resource_id_base`.
+//!
+//! Once that range has been used up, the XC-MISC extension's `GetXIDRange`
+//! request can find IDs which have since been freed, if the `xc_misc` feature
+//! is enabled.
+
+use crate::{client::RoundTrip, Client, ReplyError, RequestWriter};
+#[cfg(feature = "xc_misc")]
+use crate::{
+	request,
+	xc_misc::{self, GetXidRange},
+};
+use std::{error::Error, fmt, fmt::Formatter};
+
+/// Hands out unused resource IDs.
+///
+/// See [`Client::generate_id`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XidAllocator {
+	/// The bits set in every ID.
+	base: u32,
+	/// The difference between consecutive IDs: the lowest bit of the
+	/// `resource_id_mask`.
+	increment: u32,
+
+	/// The next ID to hand out, without `base`.
+	next: u32,
+	/// The number of IDs left to hand out, starting from `next`.
+	remaining: u32,
+}
+
+impl XidAllocator {
+	/// Creates a new `XidAllocator` for the IDs in the range given by the X
+	/// server when connecting.
+	pub const fn new(resource_id_base: u32, resource_id_mask: u32) -> Self {
+		let increment = resource_id_mask & resource_id_mask.wrapping_neg();
+
+		Self {
+			base: resource_id_base,
+			increment,
+
+			// The ID with none of the mask bits set is not handed out, as it
+			// is the same as `base` alone.
+			next: increment,
+			remaining: match increment {
+				0 => 0,
+				increment => resource_id_mask / increment,
+			},
+		}
+	}
+
+	/// Returns a new ID, or `None` if the range has been used up.
+	pub fn allocate(&mut self) -> Option<u32> {
+		self.remaining = self.remaining.checked_sub(1)?;
+
+		let id = self.next | self.base;
+		self.next = self.next.wrapping_add(self.increment);
+
+		Some(id)
+	}
+
+	/// The number of IDs left in the current range.
+	pub const fn remaining(&self) -> u32 {
+		self.remaining
+	}
+
+	/// Continues handing out IDs from a range of `count` IDs starting at
+	/// `start`, as returned by a `GetXIDRange` request.
+	pub fn refill(&mut self, start: u32, count: u32) {
+		self.next = start;
+		self.remaining = count;
+	}
+}
+
+/// An error generating a resource ID.
+#[derive(Debug)]
+pub enum XidError {
+	/// Every ID available to the client is in use.
+	Exhausted,
+	/// An error occurred asking the X server for unused IDs.
+	Reply(ReplyError),
+}
+
+impl fmt::Display for XidError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Exhausted => write!(f, "every resource ID available to the client is in use"),
+			Self::Reply(error) => write!(f, "error asking for unused resource IDs: {error}"),
+		}
+	}
+}
+
+impl Error for XidError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Exhausted => None,
+			Self::Reply(error) => Some(error),
+		}
+	}
+}
+
+impl From<ReplyError> for XidError {
+	fn from(error: ReplyError) -> Self {
+		Self::Reply(error)
+	}
+}
+
+/// Generates a new resource ID, asking the X server for a new range with
+/// XC-MISC if the current one has been used up.
+pub(crate) async fn generate_id(connection: &mut impl RoundTrip) -> Result<u32, XidError> {
+	if let Some(id) = connection.writer().xids.allocate() {
+		return Ok(id);
+	}
+
+	#[cfg(feature = "xc_misc")]
+	refill_with_xc_misc(connection).await?;
+
+	connection
+		.writer()
+		.xids
+		.allocate()
+		.ok_or(XidError::Exhausted)
+}
+
+/// Refills the client's range of resource IDs with IDs which have been freed,
+/// found with XC-MISC.
+///
+/// Returns [`XidError::Exhausted`] if the X server doesn't support XC-MISC.
+#[cfg(feature = "xc_misc")]
+async fn refill_with_xc_misc(connection: &mut impl RoundTrip) -> Result<(), XidError> {
+	if crate::extension::query_extension(connection, xc_misc::EXTENSION_NAME)
+		.await?
+		.is_none()
+	{
+		return Err(XidError::Exhausted);
+	}
+
+	let range = request::reply_to(connection, &GetXidRange).await?;
+	connection.writer().xids.refill(range.start_id, range.count);
+
+	Ok(())
+}
+
+impl Client {
+	/// Generates a new resource ID, for a window, pixmap, graphics context,
+	/// font, cursor or colormap to be created with.
+	///
+	/// IDs come from the range assigned to the client when it connected. Once
+	/// that has been used up, the XC-MISC extension is used to find IDs which
+	/// have since been freed if the `xc_misc` feature is enabled;
+	/// [`XidError::Exhausted`] is returned if there are none, or XC-MISC is not
+	/// supported.
+	pub async fn generate_id(&mut self) -> Result<u32, XidError> {
+		generate_id(self).await
+	}
+}
+
+impl RequestWriter {
+	/// Generates a new resource ID.
+	///
+	/// If XC-MISC has to be used, its reply is [awaited directly].
+	///
+	/// See [`Client::generate_id`].
+	///
+	/// [awaited directly]: crate::RequestWriter#awaiting-replies
+	pub async fn generate_id(&mut self) -> Result<u32, XidError> {
+		generate_id(self).await
+	}
+}
@@ -0,0 +1,993 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The X Keyboard extension (XKB), for keymaps with multiple groups and
+//! levels.
+//!
+//! The core [`KeyboardMapping`] only describes up to two groups of two
+//! levels each, and which level a modifier selects is left to convention.
+//! XKB keymaps instead give each key a type, which says exactly which
+//! modifiers select which level, and up to four groups (layouts).
+//!
+//! [`Client::xkb`] negotiates the extension, selects its state and keymap
+//! events, and fetches the keymap and the current state. The returned [`Xkb`]
+//! tracks the keyboard state from the events it is given with
+//! [`Xkb::observe`], and resolves keycodes to keysyms:
+//!
+//! ```no_run
+//! # use xrs::{xkb::XkbEvent, Client};
+//! # async fn example(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut xkb = client.xkb().await?;
+//!
+//! while let Some(frame) = client.read_raw_frame().await? {
+//!     if let Some(XkbEvent::KeymapChanged) = xkb.observe(&frame) {
+//!         xkb.set_keymap(client.xkb_keymap().await?);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Layout indicators can instead watch the active group, and its name, with
+//! a [`LayoutStream`].
+//!
+//! [`KeyboardMapping`]: crate::keyboard::KeyboardMapping
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	extension::{self, ExtensionInfo, UnsupportedVersion},
+	keyboard::{Keycode, Keysym},
+	raw::{assert_wire_size, X11Frame},
+	request::{self, NoReply, Reply, Request},
+	Client,
+	EventStream,
+	ReplyError,
+	RequestWriter,
+};
+use bytes::{BufMut, BytesMut};
+
+/// The name of the XKB extension.
+const XKEYBOARD: &str = "XKEYBOARD";
+
+/// The minor opcode of the `XkbUseExtension` request.
+const USE_EXTENSION: u8 = 0;
+/// The minor opcode of the `XkbSelectEvents` request.
+const SELECT_EVENTS: u8 = 1;
+/// The minor opcode of the `XkbGetState` request.
+const GET_STATE: u8 = 4;
+/// The minor opcode of the `XkbGetMap` request.
+const GET_MAP: u8 = 8;
+/// The minor opcode of the `XkbGetNames` request.
+const GET_NAMES: u8 = 17;
+
+/// The version of XKB this module is written against.
+const VERSION: (u16, u16) = (1, 0);
+
+/// The device specifier for the core keyboard.
+const USE_CORE_KEYBOARD: u16 = 0x0100;
+
+/// The `xkbType` of `XkbNewKeyboardNotify` events.
+const NEW_KEYBOARD_NOTIFY: u8 = 0;
+/// The `xkbType` of `XkbMapNotify` events.
+const MAP_NOTIFY: u8 = 1;
+/// The `xkbType` of `XkbStateNotify` events.
+const STATE_NOTIFY: u8 = 2;
+
+/// The event mask bits of `XkbMapNotify` and `XkbStateNotify` events.
+const SELECTED_EVENTS: u16 = (1 << MAP_NOTIFY) | (1 << STATE_NOTIFY);
+
+/// The keymap components bit for key types.
+const KEY_TYPES: u16 = 1 << 0;
+/// The keymap components bit for keysyms.
+const KEY_SYMS: u16 = 1 << 1;
+
+/// The number of bytes of an `XkbGetMap` reply's chunk before its lists.
+const MAP_HEADER: usize = 32;
+
+/// The names component bit for group names.
+const GROUP_NAMES: u32 = 1 << 12;
+/// The number of bytes of an `XkbGetNames` reply's chunk before its lists.
+const NAMES_HEADER: usize = 24;
+
+/// The maximum number of groups a keymap can have.
+const MAX_GROUPS: usize = 4;
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The state of the keyboard: its modifiers and group.
+///
+/// The effective modifiers and group are those which select keysyms; they
+/// combine the base, latched and locked components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct XkbState {
+	/// The effective modifiers.
+	pub mods: u8,
+	/// The modifiers of the keys which are held down.
+	pub base_mods: u8,
+	/// The modifiers which are latched until the next key press.
+	pub latched_mods: u8,
+	/// The modifiers which are locked, such as by caps lock.
+	pub locked_mods: u8,
+
+	/// The effective group.
+	pub group: u8,
+	/// The group of the keys which are held down.
+	pub base_group: i16,
+	/// The group which is latched until the next key press.
+	pub latched_group: i16,
+	/// The group which is locked.
+	pub locked_group: u8,
+}
+
+/// A change reported by an XKB event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XkbEvent {
+	/// The keyboard state changed; this is the new state.
+	StateChanged(XkbState),
+	/// The keymap changed, so it should be fetched again with
+	/// [`Client::xkb_keymap`].
+	KeymapChanged,
+}
+
+/// Which level of a key each combination of modifiers selects.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyType {
+	/// The modifiers which are relevant to this type.
+	pub mods_mask: u8,
+	/// The number of levels of keys of this type.
+	pub levels: u8,
+
+	/// The level selected by each combination of the relevant modifiers.
+	///
+	/// Combinations which aren't listed select level 0.
+	pub entries: Vec<KeyTypeEntry>,
+}
+
+/// A combination of modifiers and the level it selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyTypeEntry {
+	/// Whether the entry is used, which it isn't if it depends on a virtual
+	/// modifier which isn't bound to any real modifiers.
+	pub active: bool,
+	/// The modifiers which select `level`.
+	pub mods_mask: u8,
+	pub level: u8,
+}
+
+impl KeyType {
+	/// Returns the level which `mods` select.
+	pub fn level(&self, mods: u8) -> u8 {
+		let mods = mods & self.mods_mask;
+
+		self.entries
+			.iter()
+			.find(|entry| entry.active && entry.mods_mask == mods)
+			.map_or(0, |entry| entry.level)
+	}
+}
+
+/// What happens when a key is pressed with a group greater than any of its
+/// own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupOverflow {
+	/// The group wraps around to the key's first group.
+	Wrap,
+	/// The key's last group is used.
+	Clamp,
+	/// The given group is used, or the first group if the key doesn't have
+	/// it.
+	Redirect(u8),
+}
+
+/// The keysyms of a single key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySymMap {
+	/// The index of the [`KeyType`] of each of the key's groups.
+	pub types: [u8; 4],
+	/// The number of groups the key has.
+	pub groups: u8,
+	pub overflow: GroupOverflow,
+
+	/// The number of keysyms in each group.
+	pub width: u8,
+	/// The keysyms of each group in turn, `width` per group.
+	pub keysyms: Vec<Keysym>,
+}
+
+impl KeySymMap {
+	/// Returns the group of this key which `group` selects.
+	///
+	/// Returns `None` if the key has no groups.
+	pub fn effective_group(&self, group: u8) -> Option<u8> {
+		match self.groups {
+			0 => None,
+			groups if group < groups => Some(group),
+
+			groups => Some(match self.overflow {
+				GroupOverflow::Wrap => group % groups,
+				GroupOverflow::Clamp => groups - 1,
+				GroupOverflow::Redirect(target) if target < groups => target,
+				GroupOverflow::Redirect(_) => 0,
+			}),
+		}
+	}
+}
+
+/// An XKB keymap: the key types and keysyms of every key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XkbKeymap {
+	/// The smallest keycode of the keyboard.
+	pub min_keycode: Keycode,
+	/// The largest keycode of the keyboard.
+	pub max_keycode: Keycode,
+
+	pub types: Vec<KeyType>,
+
+	/// The keycode of the first of `keys`.
+	pub first_keycode: Keycode,
+	/// The keysyms of each key from `first_keycode` onwards.
+	pub keys: Vec<KeySymMap>,
+}
+
+impl XkbKeymap {
+	/// Returns the keysyms of `keycode`.
+	pub fn key(&self, keycode: Keycode) -> Option<&KeySymMap> {
+		let offset = keycode.checked_sub(self.first_keycode)?;
+
+		self.keys.get(usize::from(offset))
+	}
+
+	/// Returns the keysym of `keycode` with the effective modifiers `mods` and
+	/// group `group`.
+	///
+	/// Returns `None` if `keycode` has no keysym at that group and level.
+	pub fn keysym(&self, keycode: Keycode, mods: u8, group: u8) -> Option<Keysym> {
+		let key = self.key(keycode)?;
+		let group = key.effective_group(group)?;
+
+		let key_type = self.types.get(usize::from(key.types[usize::from(group)]))?;
+		let level = key_type.level(mods);
+
+		let index = usize::from(group) * usize::from(key.width) + usize::from(level);
+
+		key.keysyms
+			.get(index)
+			.copied()
+			.filter(|&keysym| keysym != 0)
+	}
+
+	/// Returns the keysym of `keycode` for the `state` of a core key event.
+	///
+	/// The state of core events holds the effective modifiers in its low
+	/// byte and the effective group in bits 13 and 14.
+	pub fn translate(&self, keycode: Keycode, state: u16) -> Option<Keysym> {
+		self.keysym(keycode, state as u8, ((state >> 13) & 0b11) as u8)
+	}
+}
+
+/// Tracks the keyboard's XKB keymap and state.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Xkb {
+	extension: ExtensionInfo,
+	version: (u16, u16),
+
+	keymap: XkbKeymap,
+	state: XkbState,
+}
+
+impl Xkb {
+	/// Returns the version of XKB supported by the X server.
+	pub const fn version(&self) -> (u16, u16) {
+		self.version
+	}
+
+	/// Returns the current keymap.
+	pub const fn keymap(&self) -> &XkbKeymap {
+		&self.keymap
+	}
+
+	/// Replaces the keymap, such as after an [`XkbEvent::KeymapChanged`].
+	pub fn set_keymap(&mut self, keymap: XkbKeymap) {
+		self.keymap = keymap;
+	}
+
+	/// Returns the keyboard state as of the last event observed.
+	pub const fn state(&self) -> &XkbState {
+		&self.state
+	}
+
+	/// Returns the keysym of `keycode` in the current state.
+	pub fn keysym(&self, keycode: Keycode) -> Option<Keysym> {
+		self.keymap
+			.keysym(keycode, self.state.mods, self.state.group)
+	}
+
+	/// Updates the state from `frame` if it is an XKB event.
+	///
+	/// Returns the change the event reported, or `None` if `frame` was not an
+	/// XKB state or keymap event.
+	pub fn observe(&mut self, frame: &X11Frame) -> Option<XkbEvent> {
+		let X11Frame::Event { code, chunk } = frame else {
+			return None;
+		};
+
+		if code & !SEND_EVENT_MASK != self.extension.first_event {
+			return None;
+		}
+
+		// The event's chunk starts after its code, so offsets are one less
+		// than those in the protocol.
+		match chunk[0] {
+			NEW_KEYBOARD_NOTIFY | MAP_NOTIFY => Some(XkbEvent::KeymapChanged),
+
+			STATE_NOTIFY => {
+				self.state = XkbState {
+					mods: chunk[8],
+					base_mods: chunk[9],
+					latched_mods: chunk[10],
+					locked_mods: chunk[11],
+
+					group: chunk[12],
+					base_group: i16::from_be_bytes([chunk[13], chunk[14]]),
+					latched_group: i16::from_be_bytes([chunk[15], chunk[16]]),
+					locked_group: chunk[17],
+				};
+
+				Some(XkbEvent::StateChanged(self.state))
+			},
+
+			_ => None,
+		}
+	}
+}
+
+/// Takes the next `count` bytes from `data`.
+fn take<'data>(data: &mut &'data [u8], count: usize) -> Option<&'data [u8]> {
+	let taken = data.get(..count)?;
+	*data = &data[count..];
+
+	Some(taken)
+}
+
+/// Reads the key types from the start of `data`.
+fn read_types(data: &mut &[u8], count: u8) -> Option<Vec<KeyType>> {
+	(0..count)
+		.map(|_| {
+			let &[mods_mask, _, _, _, levels, entry_count, has_preserve, _] = take(data, 8)? else {
+				return None;
+			};
+
+			let entries = take(data, 8 * usize::from(entry_count))?
+				.chunks_exact(8)
+				.map(|entry| KeyTypeEntry {
+					active: entry[0] != 0,
+					mods_mask: entry[1],
+					level: entry[2],
+				})
+				.collect();
+
+			if has_preserve != 0 {
+				take(data, 4 * usize::from(entry_count))?;
+			}
+
+			Some(KeyType {
+				mods_mask,
+				levels,
+				entries,
+			})
+		})
+		.collect()
+}
+
+/// Reads the keysyms of each key from the start of `data`.
+fn read_keys(data: &mut &[u8], count: u8) -> Option<Vec<KeySymMap>> {
+	(0..count)
+		.map(|_| {
+			let &[t0, t1, t2, t3, group_info, width, n0, n1] = take(data, 8)? else {
+				return None;
+			};
+
+			let keysyms = take(data, 4 * usize::from(u16::from_be_bytes([n0, n1])))?
+				.chunks_exact(4)
+				.map(|keysym| u32::from_be_bytes([keysym[0], keysym[1], keysym[2], keysym[3]]))
+				.collect();
+
+			let overflow = match group_info & 0xc0 {
+				0x40 => GroupOverflow::Clamp,
+				0x80 => GroupOverflow::Redirect((group_info >> 4) & 0b11),
+				_ => GroupOverflow::Wrap,
+			};
+
+			Some(KeySymMap {
+				types: [t0, t1, t2, t3],
+				groups: group_info & 0x0f,
+				overflow,
+
+				width,
+				keysyms,
+			})
+		})
+		.collect()
+}
+
+/// Reads an [`XkbKeymap`] from an `XkbGetMap` reply with key types and
+/// keysyms.
+impl Reply for XkbKeymap {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+
+		let header = chunk.get(..MAP_HEADER)?;
+		let present = u16::from_be_bytes([header[4], header[5]]);
+
+		let mut data = &chunk[MAP_HEADER..];
+
+		let types = match present & KEY_TYPES {
+			0 => Vec::new(),
+			_ => read_types(&mut data, header[7])?,
+		};
+		let keys = match present & KEY_SYMS {
+			0 => Vec::new(),
+			_ => read_keys(&mut data, header[12])?,
+		};
+
+		Some(XkbKeymap {
+			min_keycode: header[2],
+			max_keycode: header[3],
+
+			types,
+
+			first_keycode: header[9],
+			keys,
+		})
+	}
+}
+
+/// The XKB extension's `XkbUseExtension` request, which tells the X server
+/// which version of XKB the client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UseExtension {
+	pub major_version: u16,
+	pub minor_version: u16,
+}
+
+impl Request for UseExtension {
+	type Reply = UseExtensionReply;
+
+	const NAME: &'static str = "XkbUseExtension";
+	const EXTENSION: Option<&'static str> = Some(XKEYBOARD);
+	const OPCODE: u8 = USE_EXTENSION;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.major_version);
+		body.put_u16(self.minor_version);
+	}
+}
+
+assert_wire_size!(UseExtension: [u8, u8, u16, u16, u16]);
+
+/// Whether the X server supports the client's version of XKB, and the
+/// version it supports.
+///
+/// This is the reply to an `XkbUseExtension` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UseExtensionReply {
+	pub supported: bool,
+	pub server_major: u16,
+	pub server_minor: u16,
+}
+
+assert_wire_size!("XkbUseExtension reply": [u8, u8, u16, u32, u16, u16, [u8; 20]] == 32);
+
+impl Reply for UseExtensionReply {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply {
+			metabyte, chunk, ..
+		} = frame
+		else {
+			return None;
+		};
+		let &[major0, major1, minor0, minor1] = chunk.get(0..4)? else {
+			return None;
+		};
+
+		Some(Self {
+			supported: *metabyte != 0,
+			server_major: u16::from_be_bytes([major0, major1]),
+			server_minor: u16::from_be_bytes([minor0, minor1]),
+		})
+	}
+}
+
+/// The XKB extension's `XkbSelectEvents` request, for the events without
+/// details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelectEvents {
+	pub device_spec: u16,
+	/// The event types this request affects.
+	pub affect_which: u16,
+	/// The event types to deselect.
+	pub clear: u16,
+	/// The event types to select without filtering their details.
+	pub select_all: u16,
+	/// The keymap components whose `XkbMapNotify` events this request
+	/// affects.
+	pub affect_map: u16,
+	/// The keymap components to select `XkbMapNotify` events for.
+	pub map: u16,
+}
+
+impl Request for SelectEvents {
+	type Reply = NoReply;
+
+	const NAME: &'static str = "XkbSelectEvents";
+	const EXTENSION: Option<&'static str> = Some(XKEYBOARD);
+	const OPCODE: u8 = SELECT_EVENTS;
+	const LENGTH: Option<u16> = Some(4);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.device_spec);
+		body.put_u16(self.affect_which);
+		body.put_u16(self.clear);
+		body.put_u16(self.select_all);
+		body.put_u16(self.affect_map);
+		body.put_u16(self.map);
+	}
+}
+
+assert_wire_size!(SelectEvents: [u8, u8, u16, u16, u16, u16, u16, u16, u16]);
+
+/// The XKB extension's `XkbGetState` request, whose reply is the keyboard's
+/// [`XkbState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetState {
+	pub device_spec: u16,
+}
+
+impl Request for GetState {
+	type Reply = XkbState;
+
+	const NAME: &'static str = "XkbGetState";
+	const EXTENSION: Option<&'static str> = Some(XKEYBOARD);
+	const OPCODE: u8 = GET_STATE;
+	const LENGTH: Option<u16> = Some(2);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.device_spec);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+	}
+}
+
+assert_wire_size!(GetState: [u8, u8, u16, u16, [u8; 2]]);
+assert_wire_size!(
+	"XkbGetState reply": [
+		u8, u8, u16, u32,
+		u8, u8, u8, u8, u8, u8, i16, i16,
+		u8, u8, u8, u8, u8, [u8; 1], u16, [u8; 6],
+	] == 32
+);
+
+impl Reply for XkbState {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let state = chunk.get(..10)?;
+
+		Some(Self {
+			mods: state[0],
+			base_mods: state[1],
+			latched_mods: state[2],
+			locked_mods: state[3],
+
+			group: state[4],
+			locked_group: state[5],
+			base_group: i16::from_be_bytes([state[6], state[7]]),
+			latched_group: i16::from_be_bytes([state[8], state[9]]),
+		})
+	}
+}
+
+/// The XKB extension's `XkbGetMap` request, for whole keymap components,
+/// whose reply is an [`XkbKeymap`].
+///
+/// Only key types and keysyms are read from the reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetMap {
+	pub device_spec: u16,
+	/// The keymap components to get in full.
+	pub full: u16,
+}
+
+impl Request for GetMap {
+	type Reply = XkbKeymap;
+
+	const NAME: &'static str = "XkbGetMap";
+	const EXTENSION: Option<&'static str> = Some(XKEYBOARD);
+	const OPCODE: u8 = GET_MAP;
+	const LENGTH: Option<u16> = Some(7);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.device_spec);
+		body.put_u16(self.full);
+		// partial, and the ranges of partial components, which are unused.
+		body.put_bytes(0, 20);
+	}
+}
+
+assert_wire_size!(GetMap: [u8, u8, u16, u16, u16, u16, [u8; 8], u16, [u8; 6], [u8; 2]]);
+
+assert_wire_size!(
+	"XkbGetMap reply": [
+		u8, u8, u16, u32, [u8; 2], u8, u8, u16, u8, u8, u8, u8, u16, u8, u8, u16, u8, u8, u8, u8,
+		u8, u8, u8, u8, u8, u8, u8, u8, u8, [u8; 1], u16,
+	] == 40
+);
+
+/// The XKB extension's `XkbGetNames` request, for the names of keymap
+/// components, whose reply is the keyboard's [`GroupNames`].
+///
+/// Only group names are read from the reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetNames {
+	pub device_spec: u16,
+	/// The names to get.
+	pub which: u32,
+}
+
+impl Request for GetNames {
+	type Reply = GroupNames;
+
+	const NAME: &'static str = "XkbGetNames";
+	const EXTENSION: Option<&'static str> = Some(XKEYBOARD);
+	const OPCODE: u8 = GET_NAMES;
+	const LENGTH: Option<u16> = Some(3);
+
+	fn write_body(&self, body: &mut BytesMut) {
+		body.put_u16(self.device_spec);
+		// 2 unused bytes.
+		body.put_bytes(0, 2);
+		body.put_u32(self.which);
+	}
+}
+
+assert_wire_size!(GetNames: [u8, u8, u16, u16, [u8; 2], u32]);
+
+/// The names of the keyboard's groups (layouts), such as `English (US)`.
+///
+/// This is the reply to an `XkbGetNames` request for group names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupNames {
+	/// The atom naming each group, or [`Atom::NONE`] if it has no name.
+	pub names: [Atom; MAX_GROUPS],
+}
+
+assert_wire_size!(
+	"XkbGetNames reply": [
+		u8, u8, u16, u32, u32, u8, u8, u8, u8, u16, u8, u8, u32, u8, u8, u16, [u8; 4],
+	] == 32
+);
+
+impl Reply for GroupNames {
+	fn from_reply(frame: &X11Frame) -> Option<Self> {
+		let X11Frame::Reply { chunk, .. } = frame else {
+			return None;
+		};
+		let &[w0, w1, w2, w3] = chunk.get(..4)? else {
+			return None;
+		};
+		let which = u32::from_be_bytes([w0, w1, w2, w3]);
+		// Which groups have names: the list only holds those.
+		let named = *chunk.get(7)?;
+
+		// Only group names were asked for, so they are the only list.
+		if which & !GROUP_NAMES != 0 {
+			return None;
+		}
+
+		let mut data = chunk.get(NAMES_HEADER..)?;
+		let mut names = [Atom::NONE; MAX_GROUPS];
+
+		for (group, name) in names.iter_mut().enumerate() {
+			if named & (1 << group) != 0 {
+				let &[a0, a1, a2, a3] = take(&mut data, 4)? else {
+					return None;
+				};
+				*name = Atom(u32::from_be_bytes([a0, a1, a2, a3]));
+			}
+		}
+
+		Some(Self { names })
+	}
+}
+
+/// Returns the XKB extension's information, having checked that the X
+/// server supports the version of XKB this module is written against.
+async fn use_extension(
+	connection: &mut impl RoundTrip,
+) -> Result<(ExtensionInfo, (u16, u16)), ReplyError> {
+	let xkb = extension::require_extension(connection, XKEYBOARD).await?;
+
+	if let Some(&version) = connection.writer().versions.get(XKEYBOARD) {
+		return Ok((xkb, (version.0 as u16, version.1 as u16)));
+	}
+
+	let request = UseExtension {
+		major_version: VERSION.0,
+		minor_version: VERSION.1,
+	};
+	let reply = request::reply_to(connection, &request).await?;
+	let version = (reply.server_major, reply.server_minor);
+
+	// The X server only enables XKB for the client if it accepts the version
+	// asked for, so a refusal is not cached: the extension is left unused.
+	if !reply.supported {
+		return Err(ReplyError::UnsupportedVersion(UnsupportedVersion {
+			extension: XKEYBOARD,
+			required: (VERSION.0.into(), VERSION.1.into()),
+			supported: (version.0.into(), version.1.into()),
+		}));
+	}
+
+	let negotiated = (version.0.into(), version.1.into());
+	connection.writer().versions.insert(XKEYBOARD, negotiated);
+
+	Ok((xkb, version))
+}
+
+/// Fetches the core keyboard's XKB keymap.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::xkb_keymap`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn xkb_keymap(connection: &mut impl RoundTrip) -> Result<XkbKeymap, ReplyError> {
+	use_extension(connection).await?;
+
+	let request = GetMap {
+		device_spec: USE_CORE_KEYBOARD,
+		full: KEY_TYPES | KEY_SYMS,
+	};
+
+	request::reply_to(connection, &request).await
+}
+
+/// Fetches the core keyboard's XKB state.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::xkb_state`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn xkb_state(connection: &mut impl RoundTrip) -> Result<XkbState, ReplyError> {
+	use_extension(connection).await?;
+
+	let request = GetState {
+		device_spec: USE_CORE_KEYBOARD,
+	};
+
+	request::reply_to(connection, &request).await
+}
+
+/// Fetches the names of the core keyboard's groups (layouts), indexed by
+/// group.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::xkb_group_names`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn xkb_group_names(
+	connection: &mut impl RoundTrip,
+) -> Result<[Option<String>; MAX_GROUPS], ReplyError> {
+	use_extension(connection).await?;
+
+	let request = GetNames {
+		device_spec: USE_CORE_KEYBOARD,
+		which: GROUP_NAMES,
+	};
+	let GroupNames { names: atoms } = request::reply_to(connection, &request).await?;
+
+	let mut names = [None, None, None, None];
+	for (name, atom) in names.iter_mut().zip(atoms) {
+		if atom != Atom::NONE {
+			*name = Some(atoms::get_atom_name(connection, atom).await?);
+		}
+	}
+
+	Ok(names)
+}
+
+/// Negotiates XKB and returns an [`Xkb`] tracking the core keyboard's
+/// keymap and state.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::xkb`].
+///
+/// [`RequestWriter`]: crate::RequestWriter
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn xkb(connection: &mut impl RoundTrip) -> Result<Xkb, ReplyError> {
+	let (extension, version) = use_extension(connection).await?;
+
+	let request = SelectEvents {
+		device_spec: USE_CORE_KEYBOARD,
+		affect_which: SELECTED_EVENTS,
+		clear: 0,
+		select_all: SELECTED_EVENTS,
+		affect_map: KEY_TYPES | KEY_SYMS,
+		map: KEY_TYPES | KEY_SYMS,
+	};
+	request::send(connection, &request).await?;
+
+	let keymap = xkb_keymap(connection).await?;
+	let state = xkb_state(connection).await?;
+
+	Ok(Xkb {
+		extension,
+		version,
+
+		keymap,
+		state,
+	})
+}
+
+/// The active keyboard layout: an XKB group, and its name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Layout {
+	/// The effective group.
+	pub group: u8,
+	/// The group's name, such as `English (US)`, if it has one.
+	pub name: Option<String>,
+}
+
+/// Watches the active keyboard layout, returning a [`LayoutStream`] which
+/// reports each change of XKB group or of the groups' names.
+///
+/// XKB is negotiated and its state and keymap events are selected, as with
+/// [`Client::xkb`], and the group names are fetched. Replies are [awaited
+/// directly], so the XKB events are received from `events`, and its
+/// [`EventReader`] must have been [spawned].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+/// [`EventReader`]: crate::EventReader
+/// [spawned]: crate::EventReader::spawn
+pub async fn layout_stream(
+	events: EventStream, mut writer: RequestWriter,
+) -> Result<LayoutStream, ReplyError> {
+	let xkb = xkb(&mut writer).await?;
+	let names = xkb_group_names(&mut writer).await?;
+
+	Ok(LayoutStream {
+		events,
+		writer,
+		xkb,
+		names,
+	})
+}
+
+/// The changes to the active keyboard layout, for layout indicators.
+///
+/// A change is reported when the effective XKB group changes, such as when
+/// the user switches layouts, and when the keymap changes the name of the
+/// active group, such as after `setxkbmap`.
+///
+/// See [`layout_stream`].
+pub struct LayoutStream {
+	events: EventStream,
+	writer: RequestWriter,
+	xkb: Xkb,
+
+	/// The name of each group.
+	names: [Option<String>; MAX_GROUPS],
+}
+
+impl LayoutStream {
+	/// Returns the active layout.
+	pub fn layout(&self) -> Layout {
+		let group = self.xkb.state().group;
+
+		Layout {
+			group,
+			name: self.names.get(usize::from(group)).cloned().flatten(),
+		}
+	}
+
+	/// Returns the [`Xkb`] tracking the keyboard, to resolve keycodes with.
+	///
+	/// Its keymap is not fetched again when it changes.
+	pub const fn xkb(&self) -> &Xkb {
+		&self.xkb
+	}
+
+	/// Returns the [`RequestWriter`] the group names are fetched with, to
+	/// send other requests.
+	pub fn writer(&mut self) -> &mut RequestWriter {
+		&mut self.writer
+	}
+
+	/// Stops watching the layout, returning the [`EventStream`] and
+	/// [`RequestWriter`].
+	pub fn into_parts(self) -> (EventStream, RequestWriter) {
+		(self.events, self.writer)
+	}
+
+	/// Receives the active layout once it has changed.
+	///
+	/// Other events are discarded, as are errors not claimed by a
+	/// [`ReplyCookie`].
+	///
+	/// Returns `None` once the connection has been closed.
+	///
+	/// [`ReplyCookie`]: crate::ReplyCookie
+	pub async fn recv(&mut self) -> Option<Result<Layout, ReplyError>> {
+		loop {
+			let frame = match self.events.recv().await? {
+				Ok(frame) => frame,
+				Err(error) => return Some(Err(ReplyError::Io(error))),
+			};
+
+			let old = self.layout();
+
+			match self.xkb.observe(&frame) {
+				Some(XkbEvent::StateChanged(_)) => {},
+
+				Some(XkbEvent::KeymapChanged) => match xkb_group_names(&mut self.writer).await {
+					Ok(names) => self.names = names,
+					Err(error) => return Some(Err(error)),
+				},
+
+				None => continue,
+			}
+
+			let layout = self.layout();
+			if layout != old {
+				return Some(Ok(layout));
+			}
+		}
+	}
+}
+
+impl Client {
+	/// Negotiates XKB and returns an [`Xkb`] tracking the core keyboard's
+	/// keymap and state.
+	///
+	/// XKB's state and keymap events are selected, so that they can be given
+	/// to [`Xkb::observe`]. Returns an [`Unsupported`] error if the X server
+	/// doesn't support XKB, or an [`UnsupportedVersion`] error if it doesn't
+	/// accept XKB 1.0.
+	///
+	/// [`Unsupported`]: std::io::ErrorKind::Unsupported
+	/// [`UnsupportedVersion`]: ReplyError::UnsupportedVersion
+	pub async fn xkb(&mut self) -> Result<Xkb, ReplyError> {
+		xkb(self).await
+	}
+
+	/// Fetches the core keyboard's XKB keymap.
+	pub async fn xkb_keymap(&mut self) -> Result<XkbKeymap, ReplyError> {
+		xkb_keymap(self).await
+	}
+
+	/// Fetches the core keyboard's XKB state.
+	pub async fn xkb_state(&mut self) -> Result<XkbState, ReplyError> {
+		xkb_state(self).await
+	}
+
+	/// Fetches the names of the core keyboard's groups (layouts), such as
+	/// `English (US)`, indexed by group.
+	///
+	/// Groups without a name are `None`. See [`layout_stream`] for watching
+	/// which group is active.
+	pub async fn xkb_group_names(&mut self) -> Result<[Option<String>; MAX_GROUPS], ReplyError> {
+		xkb_group_names(self).await
+	}
+}
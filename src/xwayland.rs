@@ -0,0 +1,292 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Helpers for Xwayland's conventions for keyboard grabs and commits.
+//!
+//! Under Xwayland, the Wayland compositor handles shortcuts like switching
+//! windows before any X client sees them, and an X client's keyboard grab
+//! only holds while one of its windows is focused. Terminal emulators,
+//! remote desktops and virtual machines which need every key press capture
+//! the keyboard with [`Client::capture_keyboard`], which follows the
+//! conventions that compositors recognize:
+//!
+//! 1. The window is marked with `_XWAYLAND_MAY_GRAB_KEYBOARD`, which asks the
+//!    compositor to let Xwayland grab the keyboard on its behalf.
+//! 2. If asked to, the window is made fullscreen through the EWMH, as
+//!    compositors are more willing to give up their shortcuts to fullscreen
+//!    windows.
+//! 3. The keyboard is grabbed.
+//!
+//! ```no_run
+//! # use xrs::{window::Window, Client};
+//! # async fn example(client: &mut Client, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+//! let capture = client.capture_keyboard(window, true).await?;
+//!
+//! if !capture.is_grabbed() && capture.needs_focus() {
+//!     // Capture the keyboard again once `window` receives a `FocusIn`
+//!     // event...
+//! }
+//!
+//! capture.release(client.split_mut().1).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Window managers running under Xwayland set `_XWAYLAND_ALLOW_COMMITS` on
+//! the windows they frame with [`Client::set_allow_commits`], so that
+//! Xwayland doesn't show a frame to the compositor until it has been fully
+//! drawn.
+
+use crate::{
+	atoms::{self, Atom},
+	client::RoundTrip,
+	ewmh,
+	grab::{GrabKeyboard, GrabStatus, KeyboardGrab},
+	property::{Property, PropertyMode},
+	request,
+	server,
+	time::Timestamp,
+	window::Window,
+	Client,
+	ReplyError,
+	RequestWriter,
+};
+use std::io;
+
+/// The `_NET_WM_STATE_REMOVE` action of `_NET_WM_STATE` messages.
+const STATE_REMOVE: u32 = 0;
+/// The `_NET_WM_STATE_ADD` action of `_NET_WM_STATE` messages.
+const STATE_ADD: u32 = 1;
+/// The source indication of messages sent by normal applications.
+const SOURCE_APPLICATION: u32 = 1;
+
+/// The result of a `GrabKeyboard` request.
+///
+/// Under Xwayland, [`GrabStatus::AlreadyGrabbed`] is also the result if none
+/// of the client's windows are focused.
+pub type KeyboardGrabStatus = GrabStatus;
+
+/// Keeps the keyboard captured until it is [released].
+///
+/// See [`Client::capture_keyboard`].
+///
+/// [released]: KeyboardCapture::release
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the keyboard stays captured until the capture is released"]
+pub struct KeyboardCapture {
+	window: Window,
+	status: KeyboardGrabStatus,
+
+	/// Whether the X server only lets the keyboard be grabbed while one of
+	/// its windows is focused.
+	needs_focus: bool,
+
+	/// The `_XWAYLAND_MAY_GRAB_KEYBOARD` atom, if it was set on the window.
+	may_grab: Option<Atom>,
+	/// The `_NET_WM_STATE` and `_NET_WM_STATE_FULLSCREEN` atoms, if the
+	/// window was made fullscreen.
+	fullscreen: Option<(Atom, Atom)>,
+}
+
+impl KeyboardCapture {
+	/// The window the keyboard is captured for.
+	pub const fn window(&self) -> Window {
+		self.window
+	}
+
+	/// The result of grabbing the keyboard.
+	pub const fn status(&self) -> KeyboardGrabStatus {
+		self.status
+	}
+
+	/// Returns whether the keyboard was grabbed.
+	pub fn is_grabbed(&self) -> bool {
+		self.status == KeyboardGrabStatus::Success
+	}
+
+	/// Returns whether the grab only holds while the window is focused,
+	/// which is the case under Xwayland and XQuartz.
+	///
+	/// If so, the keyboard should be captured again whenever the window
+	/// receives a `FocusIn` event, as the grab is released when the window
+	/// loses focus. See [`ServerQuirks::grabs_only_while_focused`].
+	///
+	/// [`ServerQuirks::grabs_only_while_focused`]: server::ServerQuirks::grabs_only_while_focused
+	pub const fn needs_focus(&self) -> bool {
+		self.needs_focus
+	}
+
+	/// Releases the keyboard, undoing everything done to capture it.
+	///
+	/// Releasing a grab which the X server has already released, such as
+	/// because the window was unmapped, does nothing.
+	pub async fn release(self, writer: &mut RequestWriter) -> io::Result<()> {
+		if self.is_grabbed() {
+			writer.ungrab_keyboard(Timestamp::CURRENT_TIME).await?;
+		}
+
+		if let Some((net_wm_state, fullscreen)) = self.fullscreen {
+			let data = [STATE_REMOVE, fullscreen.0, 0, SOURCE_APPLICATION, 0];
+
+			ewmh::send_root_message(writer, self.window, net_wm_state, data)
+				.await
+				.map_err(|error| match error {
+					ReplyError::Io(error) => error,
+					error => io::Error::other(error),
+				})?;
+		}
+
+		if let Some(may_grab) = self.may_grab {
+			self.window.delete_property(writer, may_grab).await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Sets `window`'s `_XWAYLAND_MAY_GRAB_KEYBOARD` property, returning the
+/// property's atom.
+async fn write_may_grab_keyboard(
+	connection: &mut impl RoundTrip, window: Window, allow: bool,
+) -> Result<Atom, ReplyError> {
+	let may_grab = atoms::atom(connection, "_XWAYLAND_MAY_GRAB_KEYBOARD").await?;
+
+	let writer = connection.writer();
+	let sent = if allow {
+		let value = Property::u32s(Atom::CARDINAL, &[1]);
+
+		window
+			.set_property(writer, may_grab, &value, PropertyMode::Replace)
+			.await
+	} else {
+		window.delete_property(writer, may_grab).await
+	};
+	sent.map_err(ReplyError::Io)?;
+
+	Ok(may_grab)
+}
+
+/// Sets `window`'s `_XWAYLAND_ALLOW_COMMITS` property.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::set_allow_commits`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_allow_commits(
+	connection: &mut impl RoundTrip, window: Window, allow: bool,
+) -> Result<(), ReplyError> {
+	let allow_commits = atoms::atom(connection, "_XWAYLAND_ALLOW_COMMITS").await?;
+	let value = Property::u32s(Atom::CARDINAL, &[allow.into()]);
+
+	window
+		.set_property(
+			connection.writer(),
+			allow_commits,
+			&value,
+			PropertyMode::Replace,
+		)
+		.await
+		.map_err(ReplyError::Io)
+}
+
+/// Captures the keyboard for `window`.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::capture_keyboard`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn capture_keyboard(
+	connection: &mut impl RoundTrip, window: Window, fullscreen: bool,
+) -> Result<KeyboardCapture, ReplyError> {
+	let xwayland = server::is_xwayland(connection).await?;
+	let needs_focus = server::quirks(connection).await?.grabs_only_while_focused;
+
+	let may_grab = if xwayland {
+		Some(write_may_grab_keyboard(connection, window, true).await?)
+	} else {
+		None
+	};
+
+	let fullscreen = if fullscreen {
+		let net_wm_state = atoms::atom(connection, "_NET_WM_STATE").await?;
+		let fullscreen = atoms::atom(connection, "_NET_WM_STATE_FULLSCREEN").await?;
+
+		let data = [STATE_ADD, fullscreen.0, 0, SOURCE_APPLICATION, 0];
+		ewmh::send_root_message(connection, window, net_wm_state, data).await?;
+
+		Some((net_wm_state, fullscreen))
+	} else {
+		None
+	};
+
+	let grab = KeyboardGrab {
+		owner_events: true,
+		..KeyboardGrab::default()
+	};
+	let status = request::reply_to(connection, &GrabKeyboard { window, grab }).await?;
+
+	Ok(KeyboardCapture {
+		window,
+		status,
+
+		needs_focus,
+
+		may_grab,
+		fullscreen,
+	})
+}
+
+/// Sets or removes `window`'s `_XWAYLAND_MAY_GRAB_KEYBOARD` property.
+///
+/// With a [`RequestWriter`], replies are [awaited directly]. See
+/// [`Client::set_may_grab_keyboard`].
+///
+/// [awaited directly]: crate::RequestWriter#awaiting-replies
+pub async fn set_may_grab_keyboard(
+	connection: &mut impl RoundTrip, window: Window, allow: bool,
+) -> Result<(), ReplyError> {
+	write_may_grab_keyboard(connection, window, allow)
+		.await
+		.map(drop)
+}
+
+impl Client {
+	/// Captures the keyboard for `window`, so that it receives every key
+	/// press, including those the compositor or window manager would
+	/// otherwise handle as shortcuts.
+	///
+	/// Under Xwayland, `window` is marked with `_XWAYLAND_MAY_GRAB_KEYBOARD`
+	/// first. If `fullscreen` is true, the window manager is asked to make
+	/// `window` fullscreen before the keyboard is grabbed.
+	///
+	/// Check [`KeyboardCapture::is_grabbed`] to see whether the grab
+	/// succeeded: under Xwayland, it only succeeds while `window` is focused.
+	pub async fn capture_keyboard(
+		&mut self, window: Window, fullscreen: bool,
+	) -> Result<KeyboardCapture, ReplyError> {
+		capture_keyboard(self, window, fullscreen).await
+	}
+
+	/// Sets or removes `window`'s `_XWAYLAND_MAY_GRAB_KEYBOARD` property,
+	/// which asks the compositor to let Xwayland grab the keyboard for it.
+	///
+	/// Compositors which don't recognize the property ignore it, as does
+	/// every X server other than Xwayland.
+	pub async fn set_may_grab_keyboard(
+		&mut self, window: Window, allow: bool,
+	) -> Result<(), ReplyError> {
+		set_may_grab_keyboard(self, window, allow).await
+	}
+
+	/// Sets `window`'s `_XWAYLAND_ALLOW_COMMITS` property, which controls
+	/// whether Xwayland shows changes to the window to the compositor.
+	///
+	/// This is for window managers, which disallow commits to a frame while
+	/// drawing it and allow them once it is complete.
+	pub async fn set_allow_commits(
+		&mut self, window: Window, allow: bool,
+	) -> Result<(), ReplyError> {
+		set_allow_commits(self, window, allow).await
+	}
+}
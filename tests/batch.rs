@@ -0,0 +1,68 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "batch")]
+
+mod common;
+
+use common::{with_fake_server, Response};
+use std::{io, time::Duration};
+use xrs::{
+	raw::{RawRequest, X11Frame},
+	Batching,
+};
+
+/// The opcode of `NoOperation` requests.
+const NO_OPERATION: u8 = 127;
+/// The event code of `PropertyNotify` events.
+const PROPERTY_NOTIFY: u8 = 28;
+
+/// A held batch is written once its `max_delay` has passed, even if nothing
+/// else is sent and only the [`EventReader`] is being read from.
+///
+/// [`EventReader`]: xrs::EventReader
+#[test]
+fn held_batch_written_at_deadline() {
+	let (event, requests) = with_fake_server(
+		|request| match request.opcode {
+			NO_OPERATION => {
+				let mut event = vec![0; 32];
+				event[0] = PROPERTY_NOTIFY;
+				event[2..4].copy_from_slice(&request.sequence.to_be_bytes());
+
+				Response::SendAndClose(event)
+			},
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			client.set_batching(Some(Batching {
+				max_delay: Duration::from_millis(10),
+				max_bytes: 16 * 1024,
+			}));
+
+			let (mut reader, mut writer) = client.split();
+			writer
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await?;
+
+			// The request is only answered once the timer has written it.
+			let event = reader.next_event().await?;
+			drop(writer);
+
+			Ok::<_, io::Error>(event)
+		},
+	);
+
+	let event = event.expect("reading the event failed");
+	assert!(matches!(
+		event,
+		Some(X11Frame::Event {
+			code: PROPERTY_NOTIFY,
+			..
+		})
+	));
+
+	let opcodes: Vec<_> = requests.iter().map(|request| request.opcode).collect();
+	assert_eq!(opcodes, [NO_OPERATION]);
+}
@@ -0,0 +1,76 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "click")]
+
+mod common;
+
+use common::{with_fake_server, Response};
+use xrs::{click::ClickCounter, event::Event, raw::RawRequest, x11, ReplyError};
+
+/// The opcode of `NoOperation` requests.
+const NO_OPERATION: u8 = 127;
+/// The event code of `ButtonPress` events.
+const BUTTON_PRESS: u8 = 4;
+
+/// Errors not claimed by a reply cookie are returned by the click-counting
+/// stream, rather than being dropped because they aren't events.
+#[test]
+fn click_events_pass_errors_through() {
+	let (results, _) = with_fake_server(
+		|request| match request.opcode {
+			NO_OPERATION => {
+				let sequence = request.sequence.to_be_bytes();
+
+				// A `BadWindow` error for window 0x400001.
+				let mut error = vec![0; 32];
+				error[1] = x11::Error::BAD_WINDOW;
+				error[2..4].copy_from_slice(&sequence);
+				error[4..8].copy_from_slice(&0x0040_0001u32.to_be_bytes());
+				error[10] = NO_OPERATION;
+
+				// A press of button 1.
+				let mut event = vec![0; 32];
+				event[0] = BUTTON_PRESS;
+				event[1] = 1;
+				event[2..4].copy_from_slice(&sequence);
+
+				let mut bytes = error;
+				bytes.extend(event);
+
+				Response::SendAndClose(bytes)
+			},
+
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			client
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await
+				.map_err(ReplyError::Io)?;
+
+			let (reader, _writer) = client.split();
+			let mut events = reader.spawn().counting_clicks(ClickCounter::new());
+
+			let mut results = Vec::new();
+			while let Some(result) = events.recv().await {
+				results.push(result);
+			}
+
+			Ok::<_, ReplyError>(results)
+		},
+	);
+
+	let results = results.expect("sending the request failed");
+	assert_eq!(results.len(), 2);
+
+	assert!(matches!(
+		&results[0],
+		Err(ReplyError::X11(x11::Error::Window(details))) if details.bad_value == 0x0040_0001
+	));
+	assert!(matches!(
+		&results[1],
+		Ok((Event::ButtonPress(_), Some(click))) if click.count == 1
+	));
+}
@@ -0,0 +1,300 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A fake X server for testing a [`Client`] against.
+//!
+//! It accepts one connection on a TCP port from 6080 to 6089, sends a
+//! connection setup reply for a single screen, and answers the client's
+//! `QueryExtension` request for BIG-REQUESTS to say that it isn't supported.
+//! Every request after that is handed to the test's handler.
+//!
+//! Tests which create resources or grab devices can use [`with_namespace`]
+//! instead, which frees and releases them even if the test panics.
+//!
+//! [`with_namespace`]: namespace::with_namespace
+
+// Each test uses only some of the fake X server.
+#![allow(dead_code)]
+
+pub mod namespace;
+
+use bytes::{BufMut, BytesMut};
+use std::{
+	io::{self, Read, Write},
+	net::{TcpListener, TcpStream},
+	thread,
+	thread::JoinHandle,
+};
+use tokio::runtime::{self, Runtime};
+use xrs::{AuthInfo, Client, Display, DisplayName};
+
+/// The displays the fake X server tries to listen as.
+const DISPLAYS: std::ops::RangeInclusive<i16> = 80..=89;
+
+/// A request received by the fake X server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+	pub opcode: u8,
+	/// The request's second byte.
+	pub data: u8,
+	pub sequence: u16,
+	/// The request's bytes after its 4-byte header.
+	pub body: Vec<u8>,
+}
+
+/// What the fake X server does after receiving a request.
+pub enum Response {
+	/// Sends nothing.
+	Nothing,
+	/// Sends these bytes.
+	Send(Vec<u8>),
+	/// Sends these bytes, then closes the connection.
+	SendAndClose(Vec<u8>),
+}
+
+/// Runs `test` with a [`Client`] connected to a fake X server which responds
+/// to each request with `handler`.
+///
+/// Returns the requests the fake X server received.
+pub fn with_fake_server<T>(
+	handler: impl FnMut(&Request) -> Response + Send + 'static, test: impl AsyncFnOnce(Client) -> T,
+) -> (T, Vec<Request>) {
+	let (listener, display) = listen().expect("failed to listen as a fake X server");
+	let server = thread::spawn(move || serve(listener, handler));
+
+	let runtime = runtime();
+
+	let result = runtime.block_on(async {
+		let client = connect(display).await;
+
+		test(client).await
+	});
+
+	(result, join(server))
+}
+
+/// Builds the runtime tests are run on.
+fn runtime() -> Runtime {
+	runtime::Builder::new_current_thread()
+		.enable_all()
+		.build()
+		.expect("failed to build a runtime")
+}
+
+fn join(server: JoinHandle<io::Result<Vec<Request>>>) -> Vec<Request> {
+	server
+		.join()
+		.expect("the fake X server panicked")
+		.expect("the fake X server failed")
+}
+
+/// Returns a reply with the given metabyte, sequence number and body, padded
+/// to at least 32 bytes.
+pub fn reply(metabyte: u8, sequence: u16, body: &[u8]) -> Vec<u8> {
+	let padded = body.len().max(24).next_multiple_of(4);
+
+	let mut reply = BytesMut::with_capacity(8 + padded);
+	reply.put_u8(1);
+	reply.put_u8(metabyte);
+	reply.put_u16(sequence);
+	reply.put_u32(((padded - 24) / 4) as u32);
+	reply.put_slice(body);
+	reply.put_bytes(0, padded - body.len());
+
+	reply.to_vec()
+}
+
+/// Listens for a connection on the TCP port of the first free display in
+/// [`DISPLAYS`].
+fn listen() -> io::Result<(TcpListener, i16)> {
+	let mut last_error = None;
+
+	for display in DISPLAYS {
+		match TcpListener::bind(("127.0.0.1", 6000 + display as u16)) {
+			Ok(listener) => return Ok((listener, display)),
+			Err(error) => last_error = Some(error),
+		}
+	}
+
+	Err(last_error.expect("there is at least one display to try"))
+}
+
+/// Connects a [`Client`] to the fake X server listening as `display`.
+async fn connect(display: i16) -> Client {
+	let name = DisplayName::parse(&format!("127.0.0.1:{display}"))
+		.unwrap_or_else(|_| panic!("invalid display name"));
+	let auth = AuthInfo {
+		protocol_name: String::new(),
+		protocol_data: Vec::new(),
+	};
+
+	Client::connect(Display::Specific(name), Some(auth))
+		.await
+		.unwrap_or_else(|_| panic!("failed to connect to the fake X server"))
+}
+
+/// Accepts one connection and responds to its requests with `handler` until
+/// either closes the connection.
+fn serve(
+	listener: TcpListener, mut handler: impl FnMut(&Request) -> Response,
+) -> io::Result<Vec<Request>> {
+	let (mut stream, _) = listener.accept()?;
+	stream.set_nodelay(true)?;
+
+	// The connection setup request: its header, then the authorization
+	// protocol name and data.
+	let mut header = [0; 12];
+	stream.read_exact(&mut header)?;
+
+	let name_length = u16::from_be_bytes([header[6], header[7]]) as usize;
+	let data_length = u16::from_be_bytes([header[8], header[9]]) as usize;
+	let mut auth = vec![0; name_length.next_multiple_of(4) + data_length.next_multiple_of(4)];
+	stream.read_exact(&mut auth)?;
+
+	stream.write_all(&setup())?;
+
+	// Answer the `QueryExtension` request for BIG-REQUESTS: it isn't
+	// supported.
+	let Some(query) = read_request(&mut stream, 1)? else {
+		return Ok(Vec::new());
+	};
+	stream.write_all(&reply(0, query.sequence, &[]))?;
+
+	let mut requests = Vec::new();
+	let mut sequence = 2;
+
+	while let Some(request) = read_request(&mut stream, sequence)? {
+		let response = handler(&request);
+		requests.push(request);
+
+		match response {
+			Response::Nothing => {},
+			Response::Send(bytes) => stream.write_all(&bytes)?,
+
+			Response::SendAndClose(bytes) => {
+				stream.write_all(&bytes)?;
+				break;
+			},
+		}
+
+		sequence = sequence.wrapping_add(1);
+	}
+
+	Ok(requests)
+}
+
+/// Reads the next request, or returns `None` if the connection was closed.
+fn read_request(stream: &mut TcpStream, sequence: u16) -> io::Result<Option<Request>> {
+	let mut header = [0; 4];
+
+	match stream.read_exact(&mut header) {
+		Ok(()) => {},
+
+		Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(error) => return Err(error),
+	}
+
+	let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+	let mut body = vec![0; (length * 4).saturating_sub(4)];
+	stream.read_exact(&mut body)?;
+
+	Ok(Some(Request {
+		opcode: header[0],
+		data: header[1],
+		sequence,
+		body,
+	}))
+}
+
+/// Returns a successful connection setup reply for a single 1920x1080
+/// TrueColor screen.
+fn setup() -> BytesMut {
+	/// The vendor string, which is padded to 4 bytes.
+	const VENDOR: &[u8] = b"Fake";
+
+	let mut body = BytesMut::new();
+
+	// release-number, resource-id-base, resource-id-mask and
+	// motion-buffer-size.
+	body.put_u32(1);
+	body.put_u32(0x0040_0000);
+	body.put_u32(0x001f_ffff);
+	body.put_u32(256);
+	body.put_u16(VENDOR.len() as u16);
+	// maximum-request-length.
+	body.put_u16(u16::MAX);
+	// 1 screen, 1 pixmap format.
+	body.put_u8(1);
+	body.put_u8(1);
+	// image-byte-order, bitmap-format-bit-order, bitmap-format-scanline-unit
+	// and bitmap-format-scanline-pad.
+	body.put_u8(0);
+	body.put_u8(0);
+	body.put_u8(32);
+	body.put_u8(32);
+	// min-keycode and max-keycode, then 4 unused bytes.
+	body.put_u8(8);
+	body.put_u8(255);
+	body.put_bytes(0, 4);
+	body.put_slice(VENDOR);
+
+	// The pixmap format: depth, bits-per-pixel and scanline-pad, then 5 unused
+	// bytes.
+	body.put_u8(24);
+	body.put_u8(32);
+	body.put_u8(32);
+	body.put_bytes(0, 5);
+
+	// The screen: root, default-colormap, white-pixel, black-pixel and
+	// current-input-masks.
+	body.put_u32(0x3b2);
+	body.put_u32(0x20);
+	body.put_u32(0x00ff_ffff);
+	body.put_u32(0);
+	body.put_u32(0);
+	// Its size in pixels and millimetres.
+	body.put_u16(1920);
+	body.put_u16(1080);
+	body.put_u16(508);
+	body.put_u16(285);
+	// min-installed-maps, max-installed-maps, root-visual, backing-stores,
+	// save-unders, root-depth and 1 allowed depth.
+	body.put_u16(1);
+	body.put_u16(1);
+	body.put_u32(0x21);
+	body.put_u8(0);
+	body.put_u8(0);
+	body.put_u8(24);
+	body.put_u8(1);
+
+	// The allowed depth: depth, 1 unused byte, 1 visual and 4 unused bytes.
+	body.put_u8(24);
+	body.put_u8(0);
+	body.put_u16(1);
+	body.put_bytes(0, 4);
+
+	// The visual: visual-id, TrueColor, bits-per-rgb-value, colormap-entries,
+	// the red, green and blue masks, and 4 unused bytes.
+	body.put_u32(0x21);
+	body.put_u8(4);
+	body.put_u8(8);
+	body.put_u16(256);
+	body.put_u32(0x00ff_0000);
+	body.put_u32(0x0000_ff00);
+	body.put_u32(0x0000_00ff);
+	body.put_bytes(0, 4);
+
+	let mut setup = BytesMut::with_capacity(8 + body.len());
+
+	// Success, then 1 unused byte, the protocol version and the length of
+	// the body in 4-byte units.
+	setup.put_u8(1);
+	setup.put_u8(0);
+	setup.put_u16(11);
+	setup.put_u16(0);
+	setup.put_u16((body.len() / 4) as u16);
+	setup.put_slice(&body);
+
+	setup
+}
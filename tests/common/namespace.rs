@@ -0,0 +1,226 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A namespace for the resources and grabs of a test, which are freed and
+//! released once the test finishes, even if it panics.
+//!
+//! The X server only frees a client's resources and releases its grabs once
+//! its connection is closed, so a test which panics while its connection is
+//! still held elsewhere would leave a shared display grabbed or half
+//! configured. [`with_namespace`] frees everything created through the
+//! [`Namespace`] as soon as the test returns or panics.
+
+use super::{connect, join, listen, runtime, serve, Request, Response};
+use std::{
+	io,
+	panic::{self, AssertUnwindSafe},
+	thread,
+};
+use xrs::{
+	gc::{Gc, GcValues},
+	geometry::Rectangle,
+	grab::{GrabStatus, KeyboardGrab, PointerGrab, ServerGrabGuard},
+	raw::RawRequest,
+	window::Window,
+	xid::XidError,
+	Client,
+	ReplyError,
+};
+
+/// The opcode of `DestroyWindow` requests.
+const DESTROY_WINDOW: u8 = 4;
+/// The opcode of `UngrabPointer` requests.
+const UNGRAB_POINTER: u8 = 27;
+/// The opcode of `UngrabKeyboard` requests.
+const UNGRAB_KEYBOARD: u8 = 32;
+/// The opcode of `FreePixmap` requests.
+const FREE_PIXMAP: u8 = 54;
+/// The opcode of `FreeGC` requests.
+const FREE_GC: u8 = 60;
+
+/// The `CurrentTime` timestamp, which releases grabs however recently they
+/// were made.
+const CURRENT_TIME: [u8; 4] = [0; 4];
+
+/// A resource created by a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+	Window(Window),
+	Pixmap(u32),
+	Gc(u32),
+}
+
+impl Resource {
+	/// Returns the request which frees the resource.
+	fn free_request(self) -> RawRequest {
+		let (opcode, id) = match self {
+			Self::Window(window) => (DESTROY_WINDOW, window.id()),
+			Self::Pixmap(pixmap) => (FREE_PIXMAP, pixmap),
+			Self::Gc(gc) => (FREE_GC, gc),
+		};
+
+		RawRequest::new(opcode, 0, id.to_be_bytes().to_vec())
+	}
+}
+
+/// A device grabbed by a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grab {
+	Pointer,
+	Keyboard,
+}
+
+impl Grab {
+	/// Returns the request which releases the grab.
+	fn release_request(self) -> RawRequest {
+		let opcode = match self {
+			Self::Pointer => UNGRAB_POINTER,
+			Self::Keyboard => UNGRAB_KEYBOARD,
+		};
+
+		RawRequest::new(opcode, 0, CURRENT_TIME.to_vec())
+	}
+}
+
+/// A [`Client`] which remembers the resources and grabs made through it, so
+/// that they can be freed and released once the test finishes.
+///
+/// See [`with_namespace`].
+pub struct Namespace {
+	pub client: Client,
+
+	/// The resources to free, in the order they were created.
+	resources: Vec<Resource>,
+	/// The devices to ungrab.
+	grabs: Vec<Grab>,
+	/// The server grab to release, if the server has been grabbed.
+	server_grab: Option<ServerGrabGuard>,
+}
+
+impl Namespace {
+	fn new(client: Client) -> Self {
+		Self {
+			client,
+
+			resources: Vec::new(),
+			grabs: Vec::new(),
+			server_grab: None,
+		}
+	}
+
+	/// Returns the resources which will be freed, in the order they were
+	/// created.
+	pub fn resources(&self) -> &[Resource] {
+		&self.resources
+	}
+
+	/// Remembers `resource`, created without the namespace, to be freed.
+	pub fn track(&mut self, resource: Resource) {
+		self.resources.push(resource);
+	}
+
+	/// Creates a window as a child of `parent` with its parent's depth and
+	/// visual, to be destroyed once the test finishes.
+	pub async fn create_window(
+		&mut self, parent: Window, area: Rectangle,
+	) -> Result<Window, XidError> {
+		let window = self
+			.client
+			.create_simple_window(parent, area, 0, 0, 0)
+			.await?;
+		self.track(Resource::Window(window));
+
+		Ok(window)
+	}
+
+	/// Creates a graphics context, to be freed once the test finishes.
+	pub async fn create_gc(&mut self, drawable: u32, values: &GcValues) -> Result<Gc, XidError> {
+		let gc = self.client.create_gc(drawable, values).await?;
+		self.track(Resource::Gc(gc.id()));
+
+		Ok(gc)
+	}
+
+	/// Grabs the pointer, to be ungrabbed once the test finishes.
+	///
+	/// The grab is remembered before it is made, so that it is released
+	/// even if the test panics while waiting for the reply.
+	pub async fn grab_pointer(
+		&mut self, window: Window, grab: &PointerGrab,
+	) -> Result<GrabStatus, ReplyError> {
+		self.grabs.push(Grab::Pointer);
+
+		self.client.grab_pointer(window, grab).await
+	}
+
+	/// Grabs the keyboard, to be ungrabbed once the test finishes.
+	///
+	/// The grab is remembered before it is made, like in [`grab_pointer`].
+	///
+	/// [`grab_pointer`]: Namespace::grab_pointer
+	pub async fn grab_keyboard(
+		&mut self, window: Window, grab: &KeyboardGrab,
+	) -> Result<GrabStatus, ReplyError> {
+		self.grabs.push(Grab::Keyboard);
+
+		self.client.grab_keyboard(window, grab).await
+	}
+
+	/// Grabs the server, to be ungrabbed once the test finishes.
+	pub async fn grab_server(&mut self) -> io::Result<()> {
+		self.server_grab = Some(self.client.grab_server().await?);
+
+		Ok(())
+	}
+
+	/// Releases every grab, and then frees every resource, newest first.
+	///
+	/// Errors are ignored, as the connection may already have been closed by
+	/// the test.
+	async fn free_all(&mut self) {
+		if let Some(guard) = self.server_grab.take() {
+			let _ = guard.release(self.client.split_mut().1).await;
+		}
+
+		let grabs = self.grabs.drain(..).map(Grab::release_request);
+		let resources = self.resources.drain(..).rev().map(Resource::free_request);
+
+		for request in grabs.chain(resources).collect::<Vec<_>>() {
+			let _ = self.client.send_queued(request).await;
+		}
+
+		let _ = self.client.flush().await;
+	}
+}
+
+/// Runs `test` with a [`Namespace`] connected to a fake X server which
+/// responds to each request with `handler`, like [`with_fake_server`].
+///
+/// Everything created or grabbed through the namespace is freed or released
+/// once `test` finishes. If `test` panics, this happens before the panic
+/// continues.
+///
+/// [`with_fake_server`]: super::with_fake_server
+pub fn with_namespace<T>(
+	handler: impl FnMut(&Request) -> Response + Send + 'static,
+	test: impl AsyncFnOnce(&mut Namespace) -> T,
+) -> (T, Vec<Request>) {
+	let (listener, display) = listen().expect("failed to listen as a fake X server");
+	let server = thread::spawn(move || serve(listener, handler));
+
+	let runtime = runtime();
+	let mut namespace = Namespace::new(runtime.block_on(connect(display)));
+
+	let result = panic::catch_unwind(AssertUnwindSafe(|| runtime.block_on(test(&mut namespace))));
+
+	runtime.block_on(namespace.free_all());
+	// Closing the connection stops the fake X server.
+	drop(namespace);
+	let requests = join(server);
+
+	match result {
+		Ok(result) => (result, requests),
+		Err(panic) => panic::resume_unwind(panic),
+	}
+}
@@ -0,0 +1,219 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "ewmh")]
+
+mod common;
+
+use common::{reply, with_fake_server, Request, Response};
+use xrs::{
+	ewmh::{self, ClientListChange},
+	raw::RawRequest,
+	window::Window,
+	ReplyError,
+};
+
+/// The opcode of `GetWindowAttributes` requests.
+const GET_WINDOW_ATTRIBUTES: u8 = 3;
+/// The opcode of `InternAtom` requests.
+const INTERN_ATOM: u8 = 16;
+/// The opcode of `GetProperty` requests.
+const GET_PROPERTY: u8 = 20;
+/// The opcode of `NoOperation` requests.
+const NO_OPERATION: u8 = 127;
+/// The event code of `PropertyNotify` events.
+const PROPERTY_NOTIFY: u8 = 28;
+
+/// The `WINDOW` atom.
+const WINDOW_TYPE: u32 = 33;
+/// The `WM_NAME` atom.
+const WM_NAME: u32 = 39;
+/// The atom the fake X server interns `_NET_WM_NAME` as.
+const NET_WM_NAME: u32 = 300;
+/// The atom the fake X server interns `_NET_CLIENT_LIST` as.
+const NET_CLIENT_LIST: u32 = 302;
+/// The atom the fake X server interns `UTF8_STRING` as.
+const UTF8_STRING: u32 = 301;
+
+/// The window whose title is watched.
+const WINDOW: u32 = 0x0060_0001;
+/// The fake X server's root window.
+const ROOT: u32 = 0x3b2;
+
+/// Returns the reply to a `GetProperty` request for a `UTF8_STRING` value,
+/// or for a property which isn't set if `value` is `None`.
+fn property_reply(sequence: u16, value: Option<&str>) -> Vec<u8> {
+	let Some(value) = value else {
+		return reply(0, sequence, &[]);
+	};
+
+	let mut body = Vec::new();
+	body.extend(UTF8_STRING.to_be_bytes());
+	// bytes-after, then the length of the value.
+	body.extend(0u32.to_be_bytes());
+	body.extend((value.len() as u32).to_be_bytes());
+	body.extend([0; 12]);
+	body.extend(value.as_bytes());
+
+	reply(8, sequence, &body)
+}
+
+/// Returns the reply to a `GetProperty` request for a `WINDOW` value.
+fn windows_reply(sequence: u16, windows: &[u32]) -> Vec<u8> {
+	let mut body = Vec::new();
+	body.extend(WINDOW_TYPE.to_be_bytes());
+	// bytes-after, then the length of the value.
+	body.extend(0u32.to_be_bytes());
+	body.extend((windows.len() as u32).to_be_bytes());
+	body.extend([0; 12]);
+	body.extend(windows.iter().flat_map(|window| window.to_be_bytes()));
+
+	reply(32, sequence, &body)
+}
+
+/// Returns a `PropertyNotify` event for a new value of `atom` on `window`.
+fn property_notify(sequence: u16, window: u32, atom: u32) -> Vec<u8> {
+	let mut event = vec![0; 32];
+	event[0] = PROPERTY_NOTIFY;
+	event[2..4].copy_from_slice(&sequence.to_be_bytes());
+	event[4..8].copy_from_slice(&window.to_be_bytes());
+	event[8..12].copy_from_slice(&atom.to_be_bytes());
+
+	event
+}
+
+/// Returns the reply to a `GetWindowAttributes` request for an
+/// `InputOutput` window.
+fn window_attributes_reply(sequence: u16) -> Vec<u8> {
+	let mut body = [0; 36];
+	body[4..6].copy_from_slice(&1u16.to_be_bytes());
+
+	reply(0, sequence, &body)
+}
+
+/// Returns the atom `request`, a `GetProperty` request, asks for.
+fn requested_property(request: &Request) -> u32 {
+	u32::from_be_bytes(request.body[4..8].try_into().unwrap())
+}
+
+/// A [`TitleStream`] reads the title from `WM_NAME` until `_NET_WM_NAME` is
+/// set, and then from `_NET_WM_NAME`.
+///
+/// [`TitleStream`]: xrs::ewmh::TitleStream
+#[test]
+fn title_stream_prefers_net_wm_name() {
+	// `_NET_WM_NAME` is only set once the `NoOperation` request is received.
+	let mut net_wm_name = None;
+
+	let (titles, _) = with_fake_server(
+		move |request| match request.opcode {
+			INTERN_ATOM => Response::Send(reply(0, request.sequence, &NET_WM_NAME.to_be_bytes())),
+
+			GET_WINDOW_ATTRIBUTES => Response::Send(window_attributes_reply(request.sequence)),
+
+			GET_PROPERTY => Response::Send(match requested_property(request) {
+				NET_WM_NAME => property_reply(request.sequence, net_wm_name),
+				WM_NAME => property_reply(request.sequence, Some("xterm")),
+
+				_ => property_reply(request.sequence, None),
+			}),
+
+			NO_OPERATION => {
+				net_wm_name = Some("~/src/xrs — Terminal");
+
+				// Changing `WM_NAME` doesn't change the title once
+				// `_NET_WM_NAME` is set.
+				let mut events = property_notify(request.sequence, WINDOW, NET_WM_NAME);
+				events.extend(property_notify(request.sequence, WINDOW, WM_NAME));
+
+				Response::Send(events)
+			},
+
+			_ => Response::Nothing,
+		},
+		async |client| {
+			let (reader, writer) = client.split();
+			let events = reader.spawn();
+
+			let mut stream = Window(WINDOW).title_stream(events, writer).await?;
+			let initial = stream.title().into_owned();
+
+			stream
+				.writer()
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await
+				.map_err(ReplyError::Io)?;
+			stream.writer().flush().await.map_err(ReplyError::Io)?;
+
+			stream.set_max_chars(Some(8));
+			let changed = stream
+				.recv()
+				.await
+				.transpose()?
+				.map(|title| title.into_owned());
+
+			Ok::<_, ReplyError>((initial, changed))
+		},
+	);
+
+	let (initial, changed) = titles.expect("watching the title failed");
+	assert_eq!(initial, "xterm");
+	assert_eq!(changed.as_deref(), Some("~/src/x…"));
+}
+
+/// A [`ClientListStream`] reports the windows added to and removed from
+/// `_NET_CLIENT_LIST`, rather than the whole list.
+///
+/// [`ClientListStream`]: xrs::ewmh::ClientListStream
+#[test]
+fn client_list_stream_reports_changes() {
+	// The list changes once the `NoOperation` request is received.
+	let mut clients: &[u32] = &[0x0060_0001, 0x0080_0001];
+
+	let (changes, _) = with_fake_server(
+		move |request| match request.opcode {
+			INTERN_ATOM => {
+				Response::Send(reply(0, request.sequence, &NET_CLIENT_LIST.to_be_bytes()))
+			},
+			GET_WINDOW_ATTRIBUTES => Response::Send(window_attributes_reply(request.sequence)),
+			GET_PROPERTY => Response::Send(windows_reply(request.sequence, clients)),
+
+			NO_OPERATION => {
+				clients = &[0x0080_0001, 0x00a0_0001];
+
+				Response::Send(property_notify(request.sequence, ROOT, NET_CLIENT_LIST))
+			},
+
+			_ => Response::Nothing,
+		},
+		async |client| {
+			let (reader, writer) = client.split();
+			let events = reader.spawn();
+
+			let mut stream = ewmh::client_list_stream(events, writer).await?;
+			let initial = stream.clients().to_vec();
+
+			stream
+				.writer()
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await
+				.map_err(ReplyError::Io)?;
+			stream.writer().flush().await.map_err(ReplyError::Io)?;
+
+			let change = stream.recv().await.transpose()?;
+
+			Ok::<_, ReplyError>((initial, change))
+		},
+	);
+
+	let (initial, change) = changes.expect("watching the client list failed");
+	assert_eq!(initial, [Window(0x0060_0001), Window(0x0080_0001)]);
+	assert_eq!(
+		change,
+		Some(ClientListChange {
+			added: vec![Window(0x00a0_0001)],
+			removed: vec![Window(0x0060_0001)],
+		})
+	);
+}
@@ -0,0 +1,47 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "focus")]
+
+mod common;
+
+use common::{reply, with_fake_server, Response};
+use xrs::{focus::FocusTarget, ReplyError};
+
+/// The opcode of `GrabServer` requests.
+const GRAB_SERVER: u8 = 36;
+/// The opcode of `UngrabServer` requests.
+const UNGRAB_SERVER: u8 = 37;
+/// The opcode of `GetInputFocus` requests.
+const GET_INPUT_FOCUS: u8 = 43;
+
+/// The `UngrabServer` request written for a dropped [`ServerGrabGuard`]
+/// takes a sequence number, so the reply to the next request must be
+/// expected one sequence number later.
+///
+/// [`ServerGrabGuard`]: xrs::grab::ServerGrabGuard
+#[test]
+fn reply_after_dropped_server_grab() {
+	let (focus, requests) = with_fake_server(
+		|request| match request.opcode {
+			// PointerRoot, reverting to PointerRoot.
+			GET_INPUT_FOCUS => {
+				Response::SendAndClose(reply(1, request.sequence, &1u32.to_be_bytes()))
+			},
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			let grab = client.grab_server().await.map_err(ReplyError::Io)?;
+			drop(grab);
+
+			client.get_input_focus().await
+		},
+	);
+
+	let focus = focus.expect("the reply to GetInputFocus was not received");
+	assert_eq!(focus.focus, FocusTarget::PointerRoot);
+
+	let opcodes: Vec<_> = requests.iter().map(|request| request.opcode).collect();
+	assert_eq!(opcodes, [GRAB_SERVER, UNGRAB_SERVER, GET_INPUT_FOCUS]);
+}
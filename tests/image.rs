@@ -0,0 +1,90 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "image")]
+
+use xrs::{
+	image::{Image, ImageFormat},
+	ImageOrder,
+};
+
+/// Returns a blank 1-row bitmap, 32 pixels wide, with 32-bit scanline units.
+fn bitmap(byte_order: ImageOrder, bit_order: ImageOrder) -> Image {
+	Image {
+		format: ImageFormat::Bitmap,
+		depth: 1,
+		visual: 0,
+
+		width: 32,
+		height: 1,
+
+		bits_per_pixel: 1,
+		scanline_pad: 32,
+		scanline_unit: 32,
+		byte_order,
+		bit_order,
+
+		data: vec![0; 4],
+	}
+}
+
+/// With a most significant byte first byte order, a least significant bit
+/// first bitmap's first pixel is the lowest bit of the last byte of its
+/// scanline unit.
+#[test]
+fn scanline_units_are_swapped_for_msb_bytes_and_lsb_bits() {
+	let mut image = bitmap(
+		ImageOrder::MostSignificantFirst,
+		ImageOrder::LeastSignificantFirst,
+	);
+
+	assert!(image.set_pixel(0, 0, 1));
+	assert!(image.set_pixel(9, 0, 1));
+	assert!(image.set_pixel(31, 0, 1));
+
+	assert_eq!(image.data, [0x80, 0, 0b10, 1]);
+	assert_eq!(
+		(0..32)
+			.filter(|&x| image.pixel(x, 0) == Some(1))
+			.collect::<Vec<_>>(),
+		[0, 9, 31]
+	);
+}
+
+/// With a least significant byte first byte order, a most significant bit
+/// first bitmap's first pixel is the highest bit of the last byte of its
+/// scanline unit.
+#[test]
+fn scanline_units_are_swapped_for_lsb_bytes_and_msb_bits() {
+	let mut image = bitmap(
+		ImageOrder::LeastSignificantFirst,
+		ImageOrder::MostSignificantFirst,
+	);
+
+	assert!(image.set_pixel(0, 0, 1));
+	assert!(image.set_pixel(9, 0, 1));
+	assert!(image.set_pixel(31, 0, 1));
+
+	assert_eq!(image.data, [1, 0, 0b0100_0000, 0x80]);
+	assert_eq!(
+		(0..32)
+			.filter(|&x| image.pixel(x, 0) == Some(1))
+			.collect::<Vec<_>>(),
+		[0, 9, 31]
+	);
+}
+
+/// If the byte order is the bit order, scanline units are read byte by byte.
+#[test]
+fn scanline_units_are_not_swapped_for_matching_orders() {
+	let mut image = bitmap(
+		ImageOrder::MostSignificantFirst,
+		ImageOrder::MostSignificantFirst,
+	);
+
+	assert!(image.set_pixel(0, 0, 1));
+	assert!(image.set_pixel(9, 0, 1));
+
+	assert_eq!(image.data, [0x80, 0b0100_0000, 0, 0]);
+}
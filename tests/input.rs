@@ -0,0 +1,240 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "input")]
+
+mod common;
+
+use common::{reply, with_fake_server, Request, Response};
+use xrs::{
+	grab::GrabStatus,
+	input::{
+		ClientPointer,
+		DeviceGrab,
+		DeviceId,
+		EventMode,
+		FailedGrab,
+		HierarchyChange,
+		PassiveGrabKind,
+		XI_ANY_MODIFIER,
+	},
+	time::Timestamp,
+	window::Window,
+	ReplyError,
+};
+
+/// The opcode of `QueryExtension` requests.
+const QUERY_EXTENSION: u8 = 98;
+
+/// The major opcode the fake X server gives XInput.
+const XINPUT: u8 = 131;
+
+/// The minor opcode of `XIChangeHierarchy` requests.
+const XI_CHANGE_HIERARCHY: u8 = 43;
+/// The minor opcode of `XIGetClientPointer` requests.
+const XI_GET_CLIENT_POINTER: u8 = 45;
+/// The minor opcode of `XIQueryVersion` requests.
+const XI_QUERY_VERSION: u8 = 47;
+/// The minor opcode of `XIAllowEvents` requests.
+const XI_ALLOW_EVENTS: u8 = 53;
+/// The minor opcode of `XIPassiveGrabDevice` requests.
+const XI_PASSIVE_GRAB_DEVICE: u8 = 54;
+
+/// The window touches are grabbed on.
+const WINDOW: u32 = 0x0060_0001;
+/// The modifiers another client has already grabbed touches with.
+const GRABBED_MODIFIERS: u32 = 0x4;
+
+/// Responds to the requests a fake X server supporting XInput 2.2 receives
+/// before XInput 2 requests, and to `XIGetClientPointer`.
+fn xinput_server(request: &Request) -> Response {
+	match (request.opcode, request.data) {
+		(QUERY_EXTENSION, _) => Response::Send(reply(0, request.sequence, &[1, XINPUT, 0, 0])),
+		(XINPUT, XI_QUERY_VERSION) => Response::Send(reply(0, request.sequence, &[0, 2, 0, 2])),
+
+		// The client pointer is device 12, which was set explicitly.
+		(XINPUT, XI_GET_CLIENT_POINTER) => {
+			Response::Send(reply(0, request.sequence, &[1, 0, 0, 12]))
+		},
+
+		// One combination of modifiers is already grabbed by another client,
+		// which Xorg reports as `BadAccess`.
+		(XINPUT, XI_PASSIVE_GRAB_DEVICE) => {
+			let mut body = vec![0; 24];
+			body[0..2].copy_from_slice(&1u16.to_be_bytes());
+			body.extend(GRABBED_MODIFIERS.to_be_bytes());
+			body.extend([10, 0, 0, 0]);
+
+			Response::Send(reply(0, request.sequence, &body))
+		},
+
+		_ => Response::Nothing,
+	}
+}
+
+/// Hierarchy changes are sent in one `XIChangeHierarchy` request, after the
+/// X server has been told this client uses XInput 2.
+#[test]
+fn change_hierarchy_sends_every_change() {
+	let (pointer, requests) = with_fake_server(xinput_server, async |mut client| {
+		client
+			.change_hierarchy(&[
+				HierarchyChange::AddMaster {
+					name: "second".to_owned(),
+					send_core: true,
+					enable: true,
+				},
+				HierarchyChange::AttachSlave {
+					device: DeviceId(10),
+					master: DeviceId(11),
+				},
+			])
+			.await?;
+
+		client.client_pointer(None).await
+	});
+
+	assert_eq!(
+		pointer.expect("getting the client pointer failed"),
+		ClientPointer {
+			device: DeviceId(12),
+			explicit: true,
+		}
+	);
+
+	let minor_opcodes: Vec<_> = requests
+		.iter()
+		.filter(|request| request.opcode == XINPUT)
+		.map(|request| request.data)
+		.collect();
+	assert_eq!(
+		minor_opcodes,
+		[XI_QUERY_VERSION, XI_CHANGE_HIERARCHY, XI_GET_CLIENT_POINTER]
+	);
+
+	let change = requests
+		.iter()
+		.find(|request| (request.opcode, request.data) == (XINPUT, XI_CHANGE_HIERARCHY))
+		.unwrap();
+	#[rustfmt::skip]
+	assert_eq!(change.body, [
+		// Two changes.
+		2, 0, 0, 0,
+		// `AddMaster`, 4 blocks long, with a 6-byte name, sending core events
+		// and enabled.
+		0, 1, 0, 4, 0, 6, 1, 1,
+		b's', b'e', b'c', b'o', b'n', b'd', 0, 0,
+		// `AttachSlave` of device 10 to device 11.
+		0, 3, 0, 2, 0, 10, 0, 11,
+	]);
+}
+
+/// Too many hierarchy changes to be sent are rejected without sending them.
+#[test]
+fn change_hierarchy_rejects_too_many_changes() {
+	let changes = vec![
+		HierarchyChange::DetachSlave {
+			device: DeviceId(10)
+		};
+		256
+	];
+
+	let (result, requests) = with_fake_server(xinput_server, async |mut client| {
+		client.change_hierarchy(&changes).await
+	});
+
+	assert!(matches!(result, Err(ReplyError::Io(_))));
+	assert!(requests.iter().all(|request| request.opcode != XINPUT));
+}
+
+/// Passively grabbing touches reports the modifiers which couldn't be
+/// grabbed, and accepting a touch sequence sends the XInput 2.2 form of
+/// `XIAllowEvents`.
+#[test]
+fn touch_grabs_are_accepted() {
+	let (failures, requests) = with_fake_server(xinput_server, async |mut client| {
+		let failures = client
+			.passive_grab_device(
+				Window(WINDOW),
+				DeviceId::VIRTUAL_CORE_POINTER,
+				PassiveGrabKind::TouchBegin,
+				&[XI_ANY_MODIFIER, GRABBED_MODIFIERS],
+				&DeviceGrab::default(),
+			)
+			.await?;
+
+		client
+			.allow_device_events(
+				DeviceId::VIRTUAL_CORE_POINTER,
+				Timestamp::CURRENT_TIME,
+				EventMode::AcceptTouch {
+					touch: 7,
+					grab_window: Window(WINDOW),
+				},
+			)
+			.await?;
+		client.flush().await.map_err(ReplyError::Io)?;
+
+		Ok::<_, ReplyError>(failures)
+	});
+
+	assert_eq!(
+		failures.expect("grabbing touches failed"),
+		[FailedGrab {
+			modifiers: GRABBED_MODIFIERS,
+			status: GrabStatus::AlreadyGrabbed,
+		}]
+	);
+
+	let grab = requests
+		.iter()
+		.find(|request| (request.opcode, request.data) == (XINPUT, XI_PASSIVE_GRAB_DEVICE))
+		.unwrap();
+	// `XIGrabtypeTouchBegin`, with `XIGrabModeTouch`.
+	assert_eq!(grab.body[22..24], [4, 2]);
+
+	let allow = requests
+		.iter()
+		.find(|request| (request.opcode, request.data) == (XINPUT, XI_ALLOW_EVENTS))
+		.unwrap();
+	#[rustfmt::skip]
+	assert_eq!(allow.body, [
+		// `CurrentTime`.
+		0, 0, 0, 0,
+		// The virtual core pointer, and `XIAcceptTouch`.
+		0, 2, 6, 0,
+		// The touch ID, and the grab window.
+		0, 0, 0, 7,
+		0x00, 0x60, 0x00, 0x01,
+	]);
+}
+
+/// Grabbing touches needs XInput 2.2, so isn't sent to an X server which
+/// only supports XInput 2.0.
+#[test]
+fn touch_grabs_need_xinput_2_2() {
+	let (result, requests) = with_fake_server(
+		|request| match (request.opcode, request.data) {
+			(XINPUT, XI_QUERY_VERSION) => Response::Send(reply(0, request.sequence, &[0, 2, 0, 0])),
+
+			_ => xinput_server(request),
+		},
+		async |mut client| {
+			client
+				.passive_grab_device(
+					Window(WINDOW),
+					DeviceId::VIRTUAL_CORE_POINTER,
+					PassiveGrabKind::TouchBegin,
+					&[XI_ANY_MODIFIER],
+					&DeviceGrab::default(),
+				)
+				.await
+		},
+	);
+
+	assert!(matches!(result, Err(ReplyError::UnsupportedVersion(_))));
+	assert!(requests
+		.iter()
+		.all(|request| (request.opcode, request.data) != (XINPUT, XI_PASSIVE_GRAB_DEVICE)));
+}
@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::{reply, with_fake_server, Response};
+use xrs::{keyboard::keysyms, raw::RawRequest, ReplyError};
+
+/// The opcode of `GetKeyboardMapping` requests.
+const GET_KEYBOARD_MAPPING: u8 = 101;
+/// The opcode of `GetModifierMapping` requests.
+const GET_MODIFIER_MAPPING: u8 = 119;
+/// The opcode of `NoOperation` requests.
+const NO_OPERATION: u8 = 127;
+/// The event code of `MappingNotify` events.
+const MAPPING_NOTIFY: u8 = 34;
+
+/// The keycode NumLock is on.
+const NUM_LOCK: u8 = 77;
+/// The fake X server's smallest and largest keycodes.
+const KEYCODES: std::ops::RangeInclusive<u8> = 8..=255;
+
+/// Returns the reply to a `GetModifierMapping` request in which NumLock is
+/// the only key mapped to the modifier at `index`.
+fn modifier_mapping_reply(sequence: u16, index: usize) -> Vec<u8> {
+	let mut body = vec![0; 24 + 8];
+	body[24 + index] = NUM_LOCK;
+
+	// One keycode per modifier.
+	reply(1, sequence, &body)
+}
+
+/// Returns the reply to a `GetKeyboardMapping` request for every keycode, in
+/// which only [`NUM_LOCK`] has a keysym.
+fn keyboard_mapping_reply(sequence: u16) -> Vec<u8> {
+	let mut body = vec![0; 24];
+
+	for keycode in KEYCODES {
+		let keysym = if keycode == NUM_LOCK {
+			keysyms::NUM_LOCK
+		} else {
+			0
+		};
+		body.extend(keysym.to_be_bytes());
+	}
+
+	// One keysym per keycode.
+	reply(1, sequence, &body)
+}
+
+/// The modifier masks are only resolved again once a `MappingNotify` event
+/// for the modifier mapping is received.
+#[test]
+fn modifier_masks_are_cached_until_the_mapping_changes() {
+	// NumLock is moved from `Mod2` to `Mod3` once the `NoOperation` request is
+	// received.
+	let mut num_lock_index = 4;
+
+	let (masks, requests) = with_fake_server(
+		move |request| match request.opcode {
+			GET_MODIFIER_MAPPING => {
+				Response::Send(modifier_mapping_reply(request.sequence, num_lock_index))
+			},
+			GET_KEYBOARD_MAPPING => Response::Send(keyboard_mapping_reply(request.sequence)),
+
+			NO_OPERATION => {
+				num_lock_index = 5;
+
+				let mut event = vec![0; 32];
+				event[0] = MAPPING_NOTIFY;
+				event[2..4].copy_from_slice(&request.sequence.to_be_bytes());
+				// `MappingModifier`.
+				event[4] = 0;
+
+				Response::Send(event)
+			},
+
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			let first = client.modifier_masks().await?;
+			let cached = client.modifier_masks().await?;
+
+			client
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await
+				.map_err(ReplyError::Io)?;
+			client.next_event().await.map_err(ReplyError::Io)?;
+
+			let changed = client.modifier_masks().await?;
+
+			Ok::<_, ReplyError>([first, cached, changed].map(|masks| masks.num_lock))
+		},
+	);
+
+	assert_eq!(
+		masks.expect("getting the modifier masks failed"),
+		[1 << 4, 1 << 4, 1 << 5]
+	);
+
+	let mapping_requests = requests
+		.iter()
+		.filter(|request| request.opcode == GET_MODIFIER_MAPPING)
+		.count();
+	assert_eq!(mapping_requests, 2);
+}
@@ -0,0 +1,97 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "monitor")]
+
+mod common;
+
+use common::{reply, with_fake_server, Request, Response};
+use xrs::{extension::Degradation, geometry::Rectangle, monitor::MonitorSource, ReplyError};
+
+/// The opcode of `QueryExtension` requests.
+const QUERY_EXTENSION: u8 = 98;
+
+/// The major opcode the fake X server gives XINERAMA.
+const XINERAMA: u8 = 140;
+
+/// The Xinerama screens reported by the fake X server.
+const SCREENS: [Rectangle; 2] = [
+	Rectangle {
+		x: 0,
+		y: 0,
+		width: 1920,
+		height: 1080,
+	},
+	Rectangle {
+		x: 1920,
+		y: 0,
+		width: 1280,
+		height: 1024,
+	},
+];
+
+/// Returns the name of the extension `request`, a `QueryExtension` request,
+/// asks about.
+fn queried_extension(request: &Request) -> &str {
+	let len = u16::from_be_bytes([request.body[0], request.body[1]]) as usize;
+
+	std::str::from_utf8(&request.body[4..][..len]).unwrap()
+}
+
+/// Returns the reply to a `XineramaQueryScreens` request for [`SCREENS`].
+fn screens_reply(sequence: u16) -> Vec<u8> {
+	let mut body = vec![0; 24];
+	body[0..4].copy_from_slice(&(SCREENS.len() as u32).to_be_bytes());
+
+	for screen in SCREENS {
+		body.extend(screen.x.to_be_bytes());
+		body.extend(screen.y.to_be_bytes());
+		body.extend(screen.width.to_be_bytes());
+		body.extend(screen.height.to_be_bytes());
+	}
+
+	reply(0, sequence, &body)
+}
+
+/// Listing monitors without RandR falls back to Xinerama's screens, and
+/// records that it did so once, however many times they are listed.
+#[test]
+fn monitors_without_randr_are_degraded_to_xinerama() {
+	let (monitors, _) = with_fake_server(
+		|request| match request.opcode {
+			QUERY_EXTENSION => Response::Send(match queried_extension(request) {
+				"XINERAMA" => reply(0, request.sequence, &[1, XINERAMA, 0, 0]),
+
+				_ => reply(0, request.sequence, &[]),
+			}),
+
+			XINERAMA => Response::Send(screens_reply(request.sequence)),
+
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			let monitors = client.monitors().await?;
+			client.monitors().await?;
+
+			Ok::<_, ReplyError>((monitors, client.degradations().to_vec()))
+		},
+	);
+
+	let (monitors, degradations) = monitors.expect("listing monitors failed");
+
+	let areas: Vec<_> = monitors.iter().map(|monitor| monitor.area).collect();
+	assert_eq!(areas, SCREENS);
+	assert!(monitors
+		.iter()
+		.all(|monitor| monitor.source == MonitorSource::Xinerama));
+
+	assert_eq!(
+		degradations,
+		[Degradation {
+			feature: "monitors",
+			preferred: "RandR 1.5",
+			fallback: "Xinerama",
+		}]
+	);
+}
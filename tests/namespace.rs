@@ -0,0 +1,104 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::{
+	namespace::{with_namespace, Resource},
+	reply,
+	Response,
+};
+use std::{
+	panic,
+	sync::{Arc, Mutex},
+};
+use xrs::{gc::GcValues, geometry::Rectangle, grab::PointerGrab, window::Window};
+
+/// The opcode of `CreateWindow` requests.
+const CREATE_WINDOW: u8 = 1;
+/// The opcode of `DestroyWindow` requests.
+const DESTROY_WINDOW: u8 = 4;
+/// The opcode of `GrabPointer` requests.
+const GRAB_POINTER: u8 = 26;
+/// The opcode of `UngrabPointer` requests.
+const UNGRAB_POINTER: u8 = 27;
+/// The opcode of `GrabServer` requests.
+const GRAB_SERVER: u8 = 36;
+/// The opcode of `UngrabServer` requests.
+const UNGRAB_SERVER: u8 = 37;
+/// The opcode of `CreateGC` requests.
+const CREATE_GC: u8 = 55;
+/// The opcode of `FreeGC` requests.
+const FREE_GC: u8 = 60;
+
+/// The fake X server's root window.
+const ROOT: Window = Window(0x3b2);
+
+/// A test which panics after creating a window and a graphics context and
+/// grabbing the pointer and the server has its grabs released, and then its
+/// resources freed, newest first.
+#[test]
+fn panicking_tests_free_their_resources() {
+	// The requests are recorded by the handler, as the panic means they
+	// aren't returned.
+	let received = Arc::new(Mutex::new(Vec::new()));
+
+	let recorded = Arc::clone(&received);
+	let panicked = panic::catch_unwind(move || {
+		with_namespace(
+			move |request| {
+				recorded.lock().unwrap().push(request.clone());
+
+				match request.opcode {
+					// `Success`.
+					GRAB_POINTER => Response::Send(reply(0, request.sequence, &[])),
+					_ => Response::Nothing,
+				}
+			},
+			async |namespace| {
+				let area = Rectangle {
+					x: 0,
+					y: 0,
+					width: 100,
+					height: 100,
+				};
+				let window = namespace.create_window(ROOT, area).await.unwrap();
+				let gc = namespace
+					.create_gc(window.id(), &GcValues::default())
+					.await
+					.unwrap();
+
+				namespace
+					.grab_pointer(window, &PointerGrab::default())
+					.await
+					.unwrap();
+				namespace.grab_server().await.unwrap();
+
+				assert_eq!(
+					namespace.resources(),
+					[Resource::Window(window), Resource::Gc(gc.id())]
+				);
+
+				panic!("the test failed");
+			},
+		)
+	});
+	assert!(panicked.is_err());
+
+	let received = received.lock().unwrap();
+	let opcodes: Vec<_> = received.iter().map(|request| request.opcode).collect();
+	assert_eq!(
+		opcodes,
+		[
+			CREATE_WINDOW,
+			CREATE_GC,
+			GRAB_POINTER,
+			GRAB_SERVER,
+			UNGRAB_SERVER,
+			UNGRAB_POINTER,
+			FREE_GC,
+			DESTROY_WINDOW,
+		]
+	);
+}
@@ -0,0 +1,56 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::{reply, with_fake_server, Response};
+use xrs::{QueuedFrameKind, ReplyError};
+
+/// The opcode of `GetInputFocus` requests.
+const GET_INPUT_FOCUS: u8 = 43;
+/// The event code of `PropertyNotify` events.
+const PROPERTY_NOTIFY: u8 = 28;
+
+/// Events received while waiting for a reply are held until they are read,
+/// and are included in [`queue_snapshot`]s ahead of anything still buffered.
+///
+/// [`queue_snapshot`]: xrs::Client::queue_snapshot
+#[test]
+fn snapshot_includes_deferred_frames() {
+	let (snapshot, _) = with_fake_server(
+		|request| match request.opcode {
+			GET_INPUT_FOCUS => {
+				let mut event = vec![0; 32];
+				event[0] = PROPERTY_NOTIFY;
+				event[2..4].copy_from_slice(&request.sequence.to_be_bytes());
+
+				// The event arrives before the reply, so it is deferred while
+				// waiting for the reply.
+				let mut bytes = event;
+				bytes.extend(reply(1, request.sequence, &1u32.to_be_bytes()));
+
+				Response::SendAndClose(bytes)
+			},
+			_ => Response::Nothing,
+		},
+		async |mut client| {
+			client.sync().await?;
+
+			Ok::<_, ReplyError>(client.queue_snapshot())
+		},
+	);
+
+	let snapshot = snapshot.expect("the reply to GetInputFocus was not received");
+
+	assert_eq!(snapshot.frames.len(), 1);
+	assert_eq!(snapshot.frames[0].length, 32);
+	assert_eq!(
+		snapshot.frames[0].kind,
+		QueuedFrameKind::Event {
+			code: PROPERTY_NOTIFY,
+			send_event: false,
+		}
+	);
+	assert_eq!(snapshot.incomplete_bytes, 0);
+}
@@ -0,0 +1,57 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+mod common;
+
+use common::{reply, with_fake_server, Response};
+use xrs::{raw::RawRequest, ReplyError};
+
+/// The opcode of `GetInputFocus` requests.
+const GET_INPUT_FOCUS: u8 = 43;
+
+/// A [`ReplyCookie`] for a request sent after the [`EventReader`] has seen
+/// the connection close resolves to [`ReplyError::Disconnected`], rather than
+/// waiting forever for a reply which can't arrive.
+///
+/// [`ReplyCookie`]: xrs::ReplyCookie
+/// [`EventReader`]: xrs::EventReader
+#[test]
+fn cookie_registered_after_close() {
+	let (result, _) = with_fake_server(
+		|request| match request.opcode {
+			GET_INPUT_FOCUS => {
+				Response::SendAndClose(reply(1, request.sequence, &1u32.to_be_bytes()))
+			},
+			_ => Response::Nothing,
+		},
+		async |client| {
+			let (mut reader, mut writer) = client.split();
+
+			let request = RawRequest::new(GET_INPUT_FOCUS, 0, Vec::new());
+			let cookie = writer
+				.send_raw_with_reply(request.clone())
+				.await
+				.map_err(ReplyError::Io)?;
+			writer.flush().await.map_err(ReplyError::Io)?;
+			reader.wait_for_reply(cookie).await?;
+
+			// The X server closed the connection after replying.
+			let frame = reader.next_event().await.map_err(ReplyError::Io)?;
+			assert!(
+				frame.is_none(),
+				"the fake X server didn't close the connection"
+			);
+
+			let cookie = writer
+				.send_raw_with_reply(request)
+				.await
+				.map_err(ReplyError::Io)?;
+
+			Ok::<_, ReplyError>(cookie.await)
+		},
+	);
+
+	let result = result.expect("the first request failed");
+	assert!(matches!(result, Err(ReplyError::Disconnected)));
+}
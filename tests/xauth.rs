@@ -0,0 +1,23 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use xrs::xauth::{Entry, Xauthority};
+
+/// A hostname which can't be this machine's, as hostnames can't contain
+/// spaces.
+const OTHER_HOST: &[u8] = b"not this host";
+
+/// A cookie for local connections to another machine isn't used to connect
+/// to a local display, but a wildcard entry after it is.
+#[test]
+fn local_entries_for_other_hosts_are_ignored() {
+	let other_host = Entry::mit_magic_cookie(Entry::FAMILY_LOCAL, OTHER_HOST, b"0", &[1; 16]);
+	let wild = Entry::mit_magic_cookie(Entry::FAMILY_WILD, b"", b"0", &[2; 16]);
+
+	let xauthority: Xauthority = [other_host.clone()].into_iter().collect();
+	assert_eq!(xauthority.find(None, 0), None);
+
+	let xauthority: Xauthority = [other_host, wild.clone()].into_iter().collect();
+	assert_eq!(xauthority.find(None, 0), Some(&wild));
+}
@@ -0,0 +1,143 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg(feature = "xkb")]
+
+mod common;
+
+use common::{reply, with_fake_server, Response};
+use xrs::{
+	raw::RawRequest,
+	xkb::{self, Layout},
+	ReplyError,
+};
+
+/// The opcode of `GetAtomName` requests.
+const GET_ATOM_NAME: u8 = 17;
+/// The opcode of `QueryExtension` requests.
+const QUERY_EXTENSION: u8 = 98;
+/// The opcode of `NoOperation` requests.
+const NO_OPERATION: u8 = 127;
+
+/// The major opcode the fake X server gives XKB.
+const XKB: u8 = 135;
+/// The event code of XKB events.
+const XKB_EVENT: u8 = 85;
+
+/// The minor opcode of `XkbUseExtension` requests.
+const USE_EXTENSION: u8 = 0;
+/// The minor opcode of `XkbGetState` requests.
+const GET_STATE: u8 = 4;
+/// The minor opcode of `XkbGetMap` requests.
+const GET_MAP: u8 = 8;
+/// The minor opcode of `XkbGetNames` requests.
+const GET_NAMES: u8 = 17;
+
+/// The `xkbType` of `XkbStateNotify` events.
+const STATE_NOTIFY: u8 = 2;
+
+/// The names of the keyboard's two groups.
+const GROUPS: [(u32, &str); 2] = [(400, "English (US)"), (401, "German")];
+
+/// Returns the reply to the `XkbGetNames` request for group names.
+fn names_reply(sequence: u16) -> Vec<u8> {
+	let mut body = vec![0; 24];
+	// which: group names.
+	body[0..4].copy_from_slice(&(1u32 << 12).to_be_bytes());
+	// Both groups have names.
+	body[7] = 0b11;
+
+	for (atom, _) in GROUPS {
+		body.extend(atom.to_be_bytes());
+	}
+
+	reply(0, sequence, &body)
+}
+
+/// Returns the reply to a `GetAtomName` request for one of [`GROUPS`].
+fn atom_name_reply(sequence: u16, atom: u32) -> Vec<u8> {
+	let (_, name) = GROUPS
+		.into_iter()
+		.find(|(group, _)| *group == atom)
+		.expect("only group names are asked for");
+
+	let mut body = vec![0; 24];
+	body[0..2].copy_from_slice(&(name.len() as u16).to_be_bytes());
+	body.extend(name.as_bytes());
+
+	reply(0, sequence, &body)
+}
+
+/// A [`LayoutStream`] reports the new group, and its name, when an
+/// `XkbStateNotify` event changes the effective group.
+///
+/// [`LayoutStream`]: xrs::xkb::LayoutStream
+#[test]
+fn layout_stream_reports_group_changes() {
+	let (layouts, _) = with_fake_server(
+		|request| match (request.opcode, request.data) {
+			(QUERY_EXTENSION, _) => {
+				Response::Send(reply(0, request.sequence, &[1, XKB, XKB_EVENT, 0]))
+			},
+
+			(XKB, USE_EXTENSION) => Response::Send(reply(1, request.sequence, &[0, 1, 0, 0])),
+			(XKB, GET_MAP) => Response::Send(reply(0, request.sequence, &[0; 32])),
+			(XKB, GET_STATE) => Response::Send(reply(0, request.sequence, &[])),
+			(XKB, GET_NAMES) => Response::Send(names_reply(request.sequence)),
+
+			(GET_ATOM_NAME, _) => {
+				let atom = u32::from_be_bytes(request.body[0..4].try_into().unwrap());
+
+				Response::Send(atom_name_reply(request.sequence, atom))
+			},
+
+			(NO_OPERATION, _) => {
+				// The effective group changes to the second group.
+				let mut event = vec![0; 32];
+				event[0] = XKB_EVENT;
+				event[1] = STATE_NOTIFY;
+				event[2..4].copy_from_slice(&request.sequence.to_be_bytes());
+				event[13] = 1;
+
+				Response::Send(event)
+			},
+
+			_ => Response::Nothing,
+		},
+		async |client| {
+			let (reader, writer) = client.split();
+			let events = reader.spawn();
+
+			let mut stream = xkb::layout_stream(events, writer).await?;
+			let initial = stream.layout();
+
+			stream
+				.writer()
+				.send_raw(RawRequest::new(NO_OPERATION, 0, Vec::new()))
+				.await
+				.map_err(ReplyError::Io)?;
+			stream.writer().flush().await.map_err(ReplyError::Io)?;
+
+			let changed = stream.recv().await.transpose()?;
+
+			Ok::<_, ReplyError>((initial, changed))
+		},
+	);
+
+	let (initial, changed) = layouts.expect("watching the layout failed");
+	assert_eq!(
+		initial,
+		Layout {
+			group: 0,
+			name: Some("English (US)".to_owned()),
+		}
+	);
+	assert_eq!(
+		changed,
+		Some(Layout {
+			group: 1,
+			name: Some("German".to_owned()),
+		})
+	);
+}
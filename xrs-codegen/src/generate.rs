@@ -0,0 +1,1235 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generating a module from a [`Protocol`].
+//!
+//! Generated code is written already formatted with the workspace's
+//! `.rustfmt.toml`, so that running `cargo fmt` doesn't make the checked-in
+//! modules differ from what the generator writes.
+
+use crate::protocol::{Event, Expr, Field, Protocol, Request, Type};
+use std::collections::BTreeSet;
+
+/// The maximum width of a line of code.
+const MAX_WIDTH: usize = 100;
+/// The maximum width of a line of a comment.
+const COMMENT_WIDTH: usize = 80;
+/// The maximum width of a method chain kept on one line.
+const CHAIN_WIDTH: usize = 60;
+/// The width of a tab, when measuring lines.
+const TAB_WIDTH: usize = 4;
+
+/// The size of every event.
+const EVENT_SIZE: usize = 32;
+/// The minimum size of a reply.
+const REPLY_SIZE: usize = 32;
+
+/// Generates the module for `protocol`, described by the file `source`.
+///
+/// Returns the module, and every definition which wasn't generated with the
+/// reason why: those [skipped] when reading the description, and requests
+/// and events which can't be generated. They are also listed in the
+/// module's documentation.
+///
+/// [skipped]: Protocol::skipped
+pub fn generate(protocol: &Protocol, source: &str) -> (String, Vec<(String, String)>) {
+	let mut generator = Generator {
+		protocol,
+		imports: BTreeSet::new(),
+	};
+	let mut items = Vec::new();
+	let mut skipped = protocol.skipped.clone();
+
+	for xid in &protocol.xid_types {
+		items.push(generator.xid_type(xid));
+	}
+
+	for request in &protocol.requests {
+		match generator.item(|generator| generator.request(request)) {
+			Ok(item) => items.push(item),
+			Err(reason) => skipped.push((request.name.clone(), reason)),
+		}
+	}
+
+	if !protocol.events.is_empty() {
+		items.push(
+			"/// The bit set in an event's code if it was sent with `SendEvent`.\nconst \
+			 SEND_EVENT_MASK: u8 = 0x80;\n"
+				.to_owned(),
+		);
+	}
+
+	for event in &protocol.events {
+		match generator.item(|generator| generator.event(event)) {
+			Ok(item) => items.push(item),
+			Err(reason) => skipped.push((event.name.clone(), reason)),
+		}
+	}
+
+	let imports = generator.imports();
+
+	let mut module = String::new();
+
+	module.push_str(
+		"// This Source Code Form is subject to the terms of the Mozilla Public\n// License, v. \
+		 2.0. If a copy of the MPL was not distributed with this\n// file, You can obtain one at \
+		 https://mozilla.org/MPL/2.0/.\n\n",
+	);
+	comment(
+		&mut module,
+		0,
+		"//",
+		&format!(
+			"Generated by `xrs-codegen` from `{source}`. Don't edit this file by hand: edit the \
+			 description, and regenerate it with `cargo run -p xrs-codegen`."
+		),
+	);
+	module.push('\n');
+
+	module_doc(&mut module, protocol, &skipped);
+	module.push('\n');
+
+	module.push_str(&imports);
+	module.push('\n');
+
+	module.push_str(&format!(
+		"/// The name of the {xname} extension.\npub const EXTENSION_NAME: &str = \
+		 \"{xname}\";\n/// The version of {xname} described by this module.\npub const VERSION: \
+		 (u16, u16) = ({major}, {minor});\n",
+		xname = protocol.xname,
+		major = protocol.major_version,
+		minor = protocol.minor_version,
+	));
+
+	for item in items {
+		module.push('\n');
+		module.push_str(&item);
+	}
+
+	(module, skipped)
+}
+
+/// Something used by generated code which has to be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Import {
+	Atom,
+	AssertWireSize,
+	WireSize,
+	X11Frame,
+	NoReply,
+	Reply,
+	Request,
+	Timestamp,
+	Window,
+
+	BufMut,
+	BytesMut,
+}
+
+impl Import {
+	/// The module the import is in, and its name.
+	const fn path(self) -> (&'static str, &'static str) {
+		match self {
+			Self::Atom => ("atoms", "Atom"),
+			Self::AssertWireSize => ("raw", "assert_wire_size"),
+			Self::WireSize => ("raw", "WireSize"),
+			Self::X11Frame => ("raw", "X11Frame"),
+			Self::NoReply => ("request", "NoReply"),
+			Self::Reply => ("request", "Reply"),
+			Self::Request => ("request", "Request"),
+			Self::Timestamp => ("time", "Timestamp"),
+			Self::Window => ("window", "Window"),
+
+			Self::BufMut => ("bytes", "BufMut"),
+			Self::BytesMut => ("bytes", "BytesMut"),
+		}
+	}
+}
+
+struct Generator<'a> {
+	protocol: &'a Protocol,
+	imports: BTreeSet<Import>,
+}
+
+impl Generator<'_> {
+	/// Generates an item with `generate`, only keeping the imports it uses if
+	/// it succeeds.
+	fn item(
+		&mut self, generate: impl FnOnce(&mut Self) -> Result<String, String>,
+	) -> Result<String, String> {
+		let imports = self.imports.clone();
+		let result = generate(self);
+
+		if result.is_err() {
+			self.imports = imports;
+		}
+
+		result
+	}
+
+	fn imports(&self) -> String {
+		let mut crate_imports: Vec<(&str, Vec<&str>)> = Vec::new();
+		let mut bytes_imports = Vec::new();
+
+		for import in &self.imports {
+			match import.path() {
+				("bytes", name) => bytes_imports.push(name),
+
+				(module, name) => match crate_imports.last_mut() {
+					Some((last, names)) if *last == module => names.push(name),
+					_ => crate_imports.push((module, vec![name])),
+				},
+			}
+		}
+
+		let paths: Vec<String> = crate_imports
+			.into_iter()
+			.map(|(module, names)| format!("{module}::{}", braced(&names)))
+			.collect();
+
+		let mut imports = String::new();
+
+		match &paths[..] {
+			[] => {},
+			[path] => imports.push_str(&format!("use crate::{path};\n")),
+
+			paths => {
+				let line = format!("use crate::{{{}}};", paths.join(", "));
+
+				// Nested groups are always written one path per line.
+				if line.len() <= MAX_WIDTH && !paths.iter().any(|path| path.contains('{')) {
+					imports.push_str(&line);
+					imports.push('\n');
+				} else {
+					imports.push_str("use crate::{\n");
+
+					for path in paths {
+						imports.push_str(&format!("\t{path},\n"));
+					}
+
+					imports.push_str("};\n");
+				}
+			},
+		}
+
+		if !bytes_imports.is_empty() {
+			imports.push_str(&format!("use bytes::{};\n", braced(&bytes_imports)));
+		}
+
+		imports
+	}
+
+	/// The name of `ty` in generated code, importing it if needed.
+	fn ty(&mut self, ty: &Type) -> String {
+		match ty {
+			Type::Card8 => "u8".to_owned(),
+			Type::Card16 => "u16".to_owned(),
+			Type::Card32 => "u32".to_owned(),
+			Type::Int8 => "i8".to_owned(),
+			Type::Int16 => "i16".to_owned(),
+			Type::Int32 => "i32".to_owned(),
+			Type::Bool => "bool".to_owned(),
+
+			Type::Window => {
+				self.imports.insert(Import::Window);
+				"Window".to_owned()
+			},
+			Type::Atom => {
+				self.imports.insert(Import::Atom);
+				"Atom".to_owned()
+			},
+			Type::Timestamp => {
+				self.imports.insert(Import::Timestamp);
+				"Timestamp".to_owned()
+			},
+
+			Type::Xid(name) => type_name(name),
+		}
+	}
+
+	fn xid_type(&mut self, xid: &str) -> String {
+		self.imports.insert(Import::WireSize);
+
+		let name = type_name(xid);
+
+		format!(
+			"/// A `{xid}` ID.\n#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\npub struct \
+			 {name}(pub u32);\n\nimpl WireSize for {name} {{\n\tconst WIRE_SIZE: usize = 4;\n}}\n"
+		)
+	}
+
+	fn request(&mut self, request: &Request) -> Result<String, String> {
+		self.imports.insert(Import::Request);
+		self.imports.insert(Import::BytesMut);
+
+		let name = type_name(&request.name);
+		let reply_name = format!("{name}Reply");
+
+		// Fields which only give the length of a list are written from the
+		// list's length instead.
+		let length_fields = length_fields(&request.fields)?;
+
+		let mut item = String::new();
+
+		match &request.reply {
+			Some(_) => comment(
+				&mut item,
+				0,
+				"///",
+				&format!(
+					"The `{}` request, whose reply is {} [`{reply_name}`].",
+					request.name,
+					article(&reply_name)
+				),
+			),
+			None => comment(
+				&mut item,
+				0,
+				"///",
+				&format!("The `{}` request.", request.name),
+			),
+		}
+		if let Some(doc) = &request.doc {
+			item.push_str("///\n");
+			comment(&mut item, 0, "///", doc);
+		}
+
+		let mut members = Vec::new();
+		let mut body = String::new();
+		let mut assertion = vec!["u8".to_owned(), "u8".to_owned(), "u16".to_owned()];
+		let mut size = 0;
+		let mut has_list = false;
+
+		for field in &request.fields {
+			match field {
+				Field::Value { name, ty, doc } => {
+					let rust_type = self.ty(ty);
+					assertion.push(rust_type.clone());
+					size += ty.size();
+
+					let name = field_name(name);
+
+					let value = match length_fields.iter().find(|(field, _)| *field == name) {
+						Some((_, list)) => {
+							if !is_primitive(ty) || *ty == Type::Bool {
+								return Err(format!(
+									"the length of `{list}` has the type `{rust_type}`"
+								));
+							}
+
+							format!("self.{list}.len() as {rust_type}")
+						},
+
+						None => {
+							members.push((name.clone(), rust_type.clone(), doc.clone()));
+
+							format!("self.{name}")
+						},
+					};
+
+					self.imports.insert(Import::BufMut);
+					body.push_str(&format!("\t\t{}\n", put(ty, &value)));
+				},
+
+				Field::Pad(bytes) => {
+					assertion.push(format!("[u8; {bytes}]"));
+					size += bytes;
+
+					self.imports.insert(Import::BufMut);
+					body.push_str(&pad(*bytes));
+				},
+
+				Field::List { name, ty, doc, .. } => {
+					has_list = true;
+
+					let rust_type = self.ty(ty);
+					let name = field_name(name);
+					members.push((name.clone(), format!("Vec<{rust_type}>"), doc.clone()));
+
+					self.imports.insert(Import::BufMut);
+					body.push_str(&match ty {
+						Type::Card8 => format!("\t\tbody.put_slice(&self.{name});\n"),
+
+						ty => format!(
+							"\n\t\tfor value in &self.{name} {{\n\t\t\t{}\n\t\t}}\n",
+							put(ty, "*value")
+						),
+					});
+				},
+			}
+		}
+
+		// Fixed-length requests are padded explicitly, so that their length
+		// can be checked against their fields.
+		let length = if has_list {
+			"None".to_owned()
+		} else {
+			let padding = (4 - size % 4) % 4;
+
+			if padding != 0 {
+				assertion.push(format!("[u8; {padding}]"));
+
+				self.imports.insert(Import::BufMut);
+				body.push_str(&pad(padding));
+			}
+
+			format!("Some({})", (4 + size + padding) / 4)
+		};
+
+		item.push_str(&structure(&name, &members, has_list));
+		item.push('\n');
+
+		let reply_type = match request.reply {
+			Some(_) => reply_name.clone(),
+
+			None => {
+				self.imports.insert(Import::NoReply);
+				"NoReply".to_owned()
+			},
+		};
+
+		item.push_str(&format!(
+			"impl Request for {name} {{\n\ttype Reply = {reply_type};\n\n\tconst NAME: &'static \
+			 str = \"{}\";\n\tconst EXTENSION: Option<&'static str> = \
+			 Some(EXTENSION_NAME);\n\tconst OPCODE: u8 = {};\n\tconst LENGTH: Option<u16> = \
+			 {length};\n\n",
+			request.name, request.opcode,
+		));
+
+		let body = body.trim_start_matches('\n');
+		if body.is_empty() {
+			item.push_str("\tfn write_body(&self, _body: &mut BytesMut) {}\n");
+		} else {
+			item.push_str("\tfn write_body(&self, body: &mut BytesMut) {\n");
+			item.push_str(body);
+			item.push_str("\t}\n");
+		}
+		item.push_str("}\n");
+
+		if !has_list {
+			self.imports.insert(Import::AssertWireSize);
+
+			item.push('\n');
+			item.push_str(&assertion_for(&name, &assertion, None));
+		}
+
+		if let Some(fields) = &request.reply {
+			item.push('\n');
+			item.push_str(&self.reply(request, &reply_name, fields)?);
+		}
+
+		Ok(item)
+	}
+
+	fn reply(&mut self, request: &Request, name: &str, fields: &[Field]) -> Result<String, String> {
+		self.imports.insert(Import::Reply);
+		self.imports.insert(Import::X11Frame);
+
+		let request_name = type_name(&request.name);
+		let length_fields = length_fields(fields)?;
+
+		let mut members = Vec::new();
+		let mut assertion = vec!["u8".to_owned()];
+		// Reads of fixed fields, and then of lists.
+		let mut reads = Vec::new();
+		let mut list_reads = Vec::new();
+
+		// The offset of the next field in the reply, which starts after the
+		// reply's first byte.
+		let mut position = 1;
+		let mut uses_metabyte = false;
+		let mut uses_chunk = false;
+		let mut has_list = false;
+
+		for (index, field) in fields.iter().enumerate() {
+			let later_list = fields[index + 1..]
+				.iter()
+				.any(|field| matches!(field, Field::List { .. }));
+
+			match field {
+				Field::Value { name, ty, doc } => {
+					if has_list {
+						return Err(format!("`{name}` follows a list"));
+					}
+
+					let rust_type = self.ty(ty);
+					let name = field_name(name);
+
+					// A field which doesn't fit in the reply's second byte
+					// starts after the header, leaving that byte unused.
+					if position == 1 && ty.size() != 1 {
+						assertion.extend([
+							"[u8; 1]".to_owned(),
+							"u16".to_owned(),
+							"u32".to_owned(),
+						]);
+						position = 8;
+					}
+
+					let read = if position == 1 {
+						assertion.extend([rust_type.clone(), "u16".to_owned(), "u32".to_owned()]);
+						position = 8;
+						uses_metabyte = true;
+
+						match ty {
+							Type::Int8 => "*metabyte as i8".to_owned(),
+							Type::Bool => "*metabyte != 0".to_owned(),
+
+							_ => "*metabyte".to_owned(),
+						}
+					} else {
+						assertion.push(rust_type.clone());
+						uses_chunk = true;
+
+						let read = read("chunk", position - 8, ty, &rust_type);
+						position += ty.size();
+
+						read
+					};
+
+					if !length_fields.iter().any(|(field, _)| *field == name) {
+						members.push((name.clone(), rust_type, doc.clone()));
+					}
+
+					reads.push((name, read));
+				},
+
+				Field::Pad(bytes) if has_list => {
+					if later_list {
+						list_reads.push(format!("\t\tlet rest = rest.get({bytes}..)?;\n"));
+					}
+				},
+
+				Field::Pad(bytes) => {
+					let mut bytes = *bytes;
+
+					if position == 1 && bytes > 0 {
+						assertion.extend([
+							"[u8; 1]".to_owned(),
+							"u16".to_owned(),
+							"u32".to_owned(),
+						]);
+						position = 8;
+						bytes -= 1;
+					}
+
+					if bytes > 0 {
+						assertion.push(format!("[u8; {bytes}]"));
+						position += bytes;
+					}
+				},
+
+				Field::List {
+					name,
+					ty,
+					length,
+					doc,
+				} => {
+					let rust_type = self.ty(ty);
+					let name = field_name(name);
+
+					// `rest` is the name of the bytes after the lists read so far.
+					if name == "rest" {
+						return Err("a list called `rest` is not supported yet".to_owned());
+					}
+					members.push((name.clone(), format!("Vec<{rust_type}>"), doc.clone()));
+
+					if !has_list {
+						has_list = true;
+						uses_chunk = true;
+
+						let fixed = position.max(8) - 8;
+						list_reads.push(format!("\t\tlet rest = chunk.get({fixed}..)?;\n"));
+					}
+
+					let rest = if later_list { "rest" } else { "_" };
+
+					let source = match length {
+						Some(_) => name.as_str(),
+						None => "rest",
+					};
+
+					list_reads.push(match length {
+						Some(length) => {
+							let mut bytes = expr(length);
+							if ty.size() != 1 {
+								if matches!(length, Expr::Op(..)) {
+									bytes = format!("({bytes})");
+								}
+
+								bytes = format!("{} * {bytes}", ty.size());
+							}
+
+							format!("\t\tlet ({name}, {rest}) = rest.split_at_checked({bytes})?;\n")
+						},
+
+						None if later_list => {
+							return Err(format!("`{name}` has no length, but isn't last"));
+						},
+						None => String::new(),
+					});
+					list_reads.push(collect(&name, source, ty, &rust_type));
+				},
+			}
+		}
+
+		if position == 1 {
+			assertion.extend(["[u8; 1]".to_owned(), "u16".to_owned(), "u32".to_owned()]);
+			position = 8;
+		}
+
+		let mut item = String::new();
+		comment(
+			&mut item,
+			0,
+			"///",
+			&format!(
+				"The reply to {} [`{request_name}`] request.",
+				article(&request_name)
+			),
+		);
+		item.push_str(&structure(name, &members, has_list));
+		item.push('\n');
+
+		let pattern = match (uses_metabyte, uses_chunk) {
+			(true, true) => "X11Frame::Reply { metabyte, chunk, .. }",
+			(true, false) => "X11Frame::Reply { metabyte, .. }",
+			(false, true) => "X11Frame::Reply { chunk, .. }",
+			(false, false) => "X11Frame::Reply { .. }",
+		};
+
+		item.push_str(&format!(
+			"impl Reply for {name} {{\n\tfn from_reply(frame: &X11Frame) -> Option<Self> {{\n"
+		));
+
+		if members.is_empty() {
+			item.push_str(&format!("\t\tmatches!(frame, {pattern}).then_some(Self)\n"));
+		} else {
+			// Patterns with more than one field are split over lines.
+			let pattern = match pattern {
+				"X11Frame::Reply { metabyte, chunk, .. }" => {
+					"X11Frame::Reply {\n\t\t\tmetabyte, chunk, ..\n\t\t} = frame\n\t\telse"
+				},
+
+				pattern => &format!("{pattern} = frame else"),
+			};
+
+			item.push_str(&format!(
+				"\t\tlet {pattern} {{\n\t\t\treturn None;\n\t\t}};\n\n"
+			));
+
+			if has_list {
+				for (name, read) in &reads {
+					item.push_str(&assignment(2, name, read));
+				}
+				for read in list_reads {
+					item.push_str(&read);
+				}
+				item.push('\n');
+
+				let names: Vec<&str> = members.iter().map(|(name, ..)| name.as_str()).collect();
+				item.push_str(&construction(2, &names));
+			} else {
+				item.push_str("\t\tSome(Self {\n");
+				for (name, read) in &reads {
+					item.push_str(&member(3, name, read));
+				}
+				item.push_str("\t\t})\n");
+			}
+		}
+
+		item.push_str("\t}\n}\n");
+
+		if !has_list {
+			let size = if position <= REPLY_SIZE {
+				if position < REPLY_SIZE {
+					assertion.push(format!("[u8; {}]", REPLY_SIZE - position));
+				}
+
+				REPLY_SIZE
+			} else {
+				let padding = (4 - position % 4) % 4;
+				if padding != 0 {
+					assertion.push(format!("[u8; {padding}]"));
+				}
+
+				position + padding
+			};
+
+			self.imports.insert(Import::AssertWireSize);
+
+			item.push('\n');
+			item.push_str(&assertion_for(
+				&format!("\"{} reply\"", request.name),
+				&assertion,
+				Some(size),
+			));
+		}
+
+		Ok(item)
+	}
+
+	fn event(&mut self, event: &Event) -> Result<String, String> {
+		self.imports.insert(Import::X11Frame);
+
+		let name = format!("{}Event", type_name(&event.name));
+
+		let mut members = Vec::new();
+		let mut reads = Vec::new();
+		let mut assertion = vec!["u8".to_owned()];
+
+		// The offset of the next field in the event, which starts after the
+		// event's code.
+		let mut position = 1;
+
+		for field in &event.fields {
+			if position == 2 && !event.no_sequence_number {
+				assertion.push("u16".to_owned());
+				position = 4;
+			}
+
+			match field {
+				Field::Value { name, ty, doc } => {
+					// A field which doesn't fit in the event's second byte
+					// starts after the sequence number, leaving that byte
+					// unused.
+					if position == 1 && ty.size() != 1 && !event.no_sequence_number {
+						assertion.extend(["[u8; 1]".to_owned(), "u16".to_owned()]);
+						position = 4;
+					}
+
+					let rust_type = self.ty(ty);
+					let name = field_name(name);
+
+					assertion.push(rust_type.clone());
+					// The event's chunk starts after its code.
+					reads.push((name.clone(), read("chunk", position - 1, ty, &rust_type)));
+					members.push((name, rust_type, doc.clone()));
+
+					position += ty.size();
+				},
+
+				Field::Pad(bytes) => {
+					let mut bytes = *bytes;
+
+					if position == 1 && !event.no_sequence_number && bytes > 1 {
+						assertion.extend(["[u8; 1]".to_owned(), "u16".to_owned()]);
+						position = 4;
+						bytes -= 1;
+					}
+
+					assertion.push(format!("[u8; {bytes}]"));
+					position += bytes;
+				},
+
+				Field::List { name, .. } => {
+					return Err(format!("`{name}` is a list, which events can't have yet"));
+				},
+			}
+		}
+
+		if position < 2 && !event.no_sequence_number {
+			if position == 1 {
+				assertion.push("[u8; 1]".to_owned());
+			}
+
+			assertion.push("u16".to_owned());
+			position = 4;
+		}
+
+		if position > EVENT_SIZE {
+			return Err(format!(
+				"its fields take {position} bytes, not {EVENT_SIZE}"
+			));
+		}
+		if position < EVENT_SIZE {
+			assertion.push(format!("[u8; {}]", EVENT_SIZE - position));
+		}
+
+		let mut item = String::new();
+
+		match &event.doc {
+			Some(doc) => comment(&mut item, 0, "///", doc),
+			None => comment(&mut item, 0, "///", &format!("A `{}` event.", event.name)),
+		}
+		item.push_str(&structure(&name, &members, false));
+		item.push('\n');
+
+		item.push_str(&format!(
+			"impl {name} {{\n\t/// The event's code, relative to the extension's first event \
+			 code.\n\tpub const NUMBER: u8 = {};\n\n",
+			event.number,
+		));
+		comment(
+			&mut item,
+			1,
+			"///",
+			&format!(
+				"Decodes a `{name}` from an event frame, given the {} extension's [first event \
+				 code].",
+				self.protocol.xname
+			),
+		);
+		item.push_str("\t///\n");
+		comment(
+			&mut item,
+			1,
+			"///",
+			&format!("Returns `None` if `frame` is not a `{}` event.", event.name),
+		);
+		let pattern = if members.is_empty() {
+			"X11Frame::Event { code, .. }"
+		} else {
+			"X11Frame::Event { code, chunk }"
+		};
+
+		item.push_str(&format!(
+			"\t///\n\t/// [first event code]: crate::extension::ExtensionInfo::first_event\n\tpub \
+			 fn from_frame(first_event: u8, frame: &X11Frame) -> Option<Self> {{\n\t\tlet \
+			 {pattern} = frame else {{\n\t\t\treturn None;\n\t\t}};\n\n\t\tif (code & \
+			 !SEND_EVENT_MASK).wrapping_sub(first_event) != Self::NUMBER {{\n\t\t\treturn \
+			 None;\n\t\t}}\n\n",
+		));
+
+		if members.is_empty() {
+			item.push_str("\t\tSome(Self)\n");
+		} else {
+			item.push_str("\t\tSome(Self {\n");
+			for (name, read) in &reads {
+				item.push_str(&member(3, name, read));
+			}
+			item.push_str("\t\t})\n");
+		}
+
+		item.push_str("\t}\n}\n");
+
+		self.imports.insert(Import::AssertWireSize);
+
+		item.push('\n');
+		item.push_str(&assertion_for(
+			&format!("\"{}\"", event.name),
+			&assertion,
+			Some(EVENT_SIZE),
+		));
+
+		Ok(item)
+	}
+}
+
+/// The fields of a message which only give the length of a later list,
+/// with the name of that list.
+fn length_fields(fields: &[Field]) -> Result<Vec<(String, String)>, String> {
+	let mut length_fields = Vec::new();
+
+	for field in fields {
+		if let Field::List {
+			name,
+			length: Some(Expr::Field(length)),
+			..
+		} = field
+		{
+			let length = field_name(length);
+
+			if length_fields.iter().any(|(field, _)| *field == length) {
+				return Err(format!("`{length}` is the length of more than one list"));
+			}
+
+			length_fields.push((length, field_name(name)));
+		}
+	}
+
+	Ok(length_fields)
+}
+
+/// Whether `ty` is a Rust primitive type, rather than a newtype of a `u32`.
+const fn is_primitive(ty: &Type) -> bool {
+	!matches!(
+		ty,
+		Type::Window | Type::Atom | Type::Timestamp | Type::Xid(_)
+	)
+}
+
+/// Writes `value`, of the type `ty`, to `body`.
+fn put(ty: &Type, value: &str) -> String {
+	match ty {
+		Type::Card8 => format!("body.put_u8({value});"),
+		Type::Card16 => format!("body.put_u16({value});"),
+		Type::Card32 => format!("body.put_u32({value});"),
+		Type::Int8 => format!("body.put_i8({value});"),
+		Type::Int16 => format!("body.put_i16({value});"),
+		Type::Int32 => format!("body.put_i32({value});"),
+		Type::Bool => format!("body.put_u8(u8::from({value}));"),
+
+		Type::Window | Type::Atom | Type::Timestamp | Type::Xid(_) => {
+			format!("body.put_u32({}.0);", value.trim_start_matches('*'))
+		},
+	}
+}
+
+/// Writes `bytes` unused bytes to `body`.
+fn pad(bytes: usize) -> String {
+	match bytes {
+		0 => String::new(),
+		1 => "\t\t// 1 unused byte.\n\t\tbody.put_u8(0);\n".to_owned(),
+
+		bytes => format!("\t\t// {bytes} unused bytes.\n\t\tbody.put_bytes(0, {bytes});\n"),
+	}
+}
+
+/// Reads a value of the type `ty` at `offset` in the bytes `source`.
+fn read(source: &str, offset: usize, ty: &Type, rust_type: &str) -> String {
+	let byte = match offset {
+		0 => format!("*{source}.first()?"),
+		offset => format!("*{source}.get({offset})?"),
+	};
+	let bytes = |size: usize| {
+		format!(
+			"{source}.get({offset}..{})?.try_into().ok()?",
+			offset + size
+		)
+	};
+
+	match ty {
+		Type::Card8 => byte,
+		Type::Int8 => format!("{byte} as i8"),
+		Type::Bool => format!("{byte} != 0"),
+
+		Type::Card16 | Type::Int16 | Type::Card32 | Type::Int32 => {
+			format!("{rust_type}::from_be_bytes({})", bytes(ty.size()))
+		},
+
+		_ => format!("{rust_type}(u32::from_be_bytes({}))", bytes(4)),
+	}
+}
+
+/// Collects the list `name` from `source`, the bytes read for it.
+fn collect(name: &str, source: &str, ty: &Type, rust_type: &str) -> String {
+	let map = match ty {
+		Type::Card8 => return format!("\t\tlet {name} = {source}.to_vec();\n"),
+		Type::Int8 => "iter().map(|&value| value as i8)".to_owned(),
+		Type::Bool => "iter().map(|&value| value != 0)".to_owned(),
+
+		Type::Card16 | Type::Int16 => {
+			format!("chunks_exact(2).map(|value| {rust_type}::from_be_bytes([value[0], value[1]]))")
+		},
+		Type::Card32 | Type::Int32 => format!(
+			"chunks_exact(4).map(|value| {rust_type}::from_be_bytes([value[0], value[1], \
+			 value[2], value[3]]))"
+		),
+
+		_ => format!(
+			"chunks_exact(4).map(|value| {rust_type}(u32::from_be_bytes([value[0], value[1], \
+			 value[2], value[3]])))"
+		),
+	};
+
+	let chain = format!("{source}.{map}.collect()");
+
+	if chain.len() <= CHAIN_WIDTH {
+		format!("\t\tlet {name} = {chain};\n")
+	} else {
+		let (method, map) = map.split_once('.').expect("maps are two methods");
+
+		format!("\t\tlet {name} = {source}\n\t\t\t.{method}\n\t\t\t.{map}\n\t\t\t.collect();\n")
+	}
+}
+
+/// Writes `expr` as a `usize`.
+fn expr(expr: &Expr) -> String {
+	match expr {
+		Expr::Field(name) => format!("{} as usize", field_name(name)),
+		Expr::Value(value) => value.to_string(),
+
+		Expr::Op(op, left, right) => {
+			let operand = |operand: &Expr| match operand {
+				Expr::Op(..) => format!("({})", self::expr(operand)),
+				operand => self::expr(operand),
+			};
+
+			format!("{} {op} {}", operand(left), operand(right))
+		},
+	}
+}
+
+/// A struct with the given fields, each with its name, type and
+/// documentation.
+fn structure(name: &str, members: &[(String, String, Option<String>)], has_list: bool) -> String {
+	let derives = if has_list {
+		"Debug, Clone, PartialEq, Eq, Hash"
+	} else {
+		"Debug, Clone, Copy, PartialEq, Eq, Hash"
+	};
+
+	let mut structure = format!("#[derive({derives})]\n");
+
+	if members.is_empty() {
+		structure.push_str(&format!("pub struct {name};\n"));
+		return structure;
+	}
+
+	structure.push_str(&format!("pub struct {name} {{\n"));
+
+	for (name, ty, doc) in members {
+		if let Some(doc) = doc {
+			comment(&mut structure, 1, "///", doc);
+		}
+
+		structure.push_str(&format!("\tpub {name}: {ty},\n"));
+	}
+
+	structure.push_str("}\n");
+	structure
+}
+
+/// Constructs `Self` from locals with the same names as its fields.
+fn construction(indent: usize, names: &[&str]) -> String {
+	let tabs = "\t".repeat(indent);
+	let line = format!("{tabs}Some(Self {{ {} }})\n", names.join(", "));
+
+	// Struct literals are kept on one line if they are short enough.
+	if names.join(", ").len() + "Self {  }".len() <= 50 && width(&line) <= MAX_WIDTH {
+		return line;
+	}
+
+	let mut construction = format!("{tabs}Some(Self {{\n");
+	for name in names {
+		construction.push_str(&format!("{tabs}\t{name},\n"));
+	}
+	construction.push_str(&format!("{tabs}}})\n"));
+
+	construction
+}
+
+/// A `let` statement assigning `value` to `name`.
+fn assignment(indent: usize, name: &str, value: &str) -> String {
+	let tabs = "\t".repeat(indent);
+
+	let line = format!("{tabs}let {name} = {value};\n");
+
+	// Long assignments are split after the `=`.
+	if width(&line) <= MAX_WIDTH {
+		line
+	} else {
+		format!("{tabs}let {name} =\n{tabs}\t{value};\n")
+	}
+}
+
+/// A field of a struct literal.
+fn member(indent: usize, name: &str, value: &str) -> String {
+	let tabs = "\t".repeat(indent);
+
+	wrapped(format!("{tabs}{name}: {value},\n"), indent)
+}
+
+/// Moves the argument of the first call in `line` onto its own line if
+/// `line` is too long.
+fn wrapped(line: String, indent: usize) -> String {
+	if width(&line) <= MAX_WIDTH {
+		return line;
+	}
+
+	let tabs = "\t".repeat(indent);
+	let (Some(start), Some(end)) = (line.find('('), line.rfind(')')) else {
+		return line;
+	};
+
+	format!(
+		"{}(\n{tabs}\t{},\n{tabs}){}",
+		&line[..start],
+		&line[start + 1..end],
+		&line[end + 1..]
+	)
+}
+
+/// An `assert_wire_size!` invocation for `name` with the given fields,
+/// which are compared with `size` if it is not a [`Request`]'s.
+///
+/// [`Request`]: crate::protocol::Request
+fn assertion_for(name: &str, fields: &[String], size: Option<usize>) -> String {
+	let size = size.map(|size| format!(" == {size}")).unwrap_or_default();
+	let line = format!(
+		"assert_wire_size!({name}: [{}]{size});\n",
+		fields.join(", ")
+	);
+
+	if width(&line) <= MAX_WIDTH {
+		return line;
+	}
+
+	let mut assertion = format!("assert_wire_size!({name}: [\n");
+	let mut current = String::from("\t");
+
+	for field in fields {
+		if width(&current) + field.len() + 1 > MAX_WIDTH {
+			assertion.push_str(current.trim_end());
+			assertion.push('\n');
+			current = String::from("\t");
+		}
+
+		current.push_str(field);
+		current.push_str(", ");
+	}
+
+	assertion.push_str(current.trim_end());
+	assertion.push('\n');
+	assertion.push_str(&format!("]{size});\n"));
+
+	assertion
+}
+
+/// The module's documentation.
+fn module_doc(module: &mut String, protocol: &Protocol, skipped: &[(String, String)]) {
+	module.push_str(&format!(
+		"//! The {} extension, version {}.{}.\n//!\n",
+		protocol.xname, protocol.major_version, protocol.minor_version
+	));
+	let events = if protocol.events.is_empty() {
+		""
+	} else {
+		", and its events are decoded from frames with their `from_frame` functions"
+	};
+	comment(
+		module,
+		0,
+		"//!",
+		&format!(
+			"This module is generated from the extension's xcbproto description, so it mirrors \
+			 the protocol directly. Its requests are sent with [`Client::request`] or \
+			 [`Client::send_request`]{events}."
+		),
+	);
+
+	if !skipped.is_empty() {
+		module.push_str("//!\n//! These definitions aren't generated yet:\n");
+
+		for (name, reason) in skipped {
+			let reason = reason.strip_suffix('.').unwrap_or(reason);
+
+			comment(module, 0, "//!  ", &format!("- `{name}`: {reason}."));
+		}
+	}
+
+	module.push_str(
+		"//!\n//! [`Client::request`]: crate::Client::request\n//! [`Client::send_request`]: \
+		 crate::Client::send_request\n",
+	);
+}
+
+/// Writes `text` as a comment starting with `prefix`, wrapped to the
+/// comment width.
+fn comment(output: &mut String, indent: usize, prefix: &str, text: &str) {
+	let tabs = "\t".repeat(indent);
+	// List items continue on lines indented to the item's text.
+	let (first_prefix, prefix) = match prefix.strip_suffix("  ") {
+		Some(prefix) => (prefix.to_owned(), format!("{prefix}   ")),
+		None => (prefix.to_owned(), format!("{prefix} ")),
+	};
+
+	let mut line = format!("{tabs}{first_prefix}");
+
+	for (index, word) in text.split_whitespace().enumerate() {
+		if index != 0 && width(&line) + 1 + word.len() > COMMENT_WIDTH {
+			output.push_str(&line);
+			output.push('\n');
+			line = format!("{tabs}{}", prefix.trim_end());
+			line.push_str(&" ".repeat(prefix.len() - prefix.trim_end().len() - 1));
+		}
+
+		line.push(' ');
+		line.push_str(word);
+	}
+
+	output.push_str(&line);
+	output.push('\n');
+}
+
+/// The width of `line`, counting tabs as [`TAB_WIDTH`] columns.
+fn width(line: &str) -> usize {
+	let line = line.trim_end_matches('\n');
+
+	line.chars().count() + line.matches('\t').count() * (TAB_WIDTH - 1)
+}
+
+/// The indefinite article for `word`.
+fn article(word: &str) -> &'static str {
+	if word.starts_with(['A', 'E', 'I', 'O', 'U']) {
+		"an"
+	} else {
+		"a"
+	}
+}
+
+/// `names` in braces, unless there is only one name.
+fn braced(names: &[&str]) -> String {
+	match names {
+		[name] => (*name).to_owned(),
+		names => format!("{{{}}}", names.join(", ")),
+	}
+}
+
+/// The Rust name of a type called `name` in its description, in
+/// `UpperCamelCase` with acronyms capitalized like words, such as `GetXidRange`
+/// for `GetXIDRange`.
+fn type_name(name: &str) -> String {
+	let chars: Vec<char> = name.chars().collect();
+	let mut words: Vec<String> = Vec::new();
+
+	for (index, &char) in chars.iter().enumerate() {
+		let previous = index.checked_sub(1).map(|index| chars[index]);
+		let next = chars.get(index + 1);
+
+		let starts_word = match previous {
+			None => true,
+			Some('_') => true,
+
+			Some(previous) => {
+				char.is_uppercase()
+					&& (previous.is_lowercase()
+						|| previous.is_ascii_digit()
+						|| (previous.is_uppercase()
+							&& next.is_some_and(|next| next.is_lowercase())))
+			},
+		};
+
+		if char == '_' {
+			continue;
+		}
+
+		if starts_word || words.is_empty() {
+			words.push(String::new());
+		}
+
+		words.last_mut().expect("a word was started").push(char);
+	}
+
+	words
+		.iter()
+		.map(|word| {
+			let mut chars = word.chars();
+			let first = chars.next().expect("words aren't empty");
+
+			first
+				.to_uppercase()
+				.chain(chars.flat_map(char::to_lowercase))
+				.collect::<String>()
+		})
+		.collect()
+}
+
+/// The Rust name of a field called `name` in its description.
+fn field_name(name: &str) -> String {
+	const KEYWORDS: &[&str] = &[
+		"as", "async", "await", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+		"extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+		"match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+		"struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+		"where", "while", "yield",
+	];
+
+	if KEYWORDS.contains(&name) {
+		format!("r#{name}")
+	} else {
+		name.to_owned()
+	}
+}
@@ -0,0 +1,117 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generates X.RS modules from the [xcbproto] XML descriptions of X11
+//! extensions.
+//!
+//! Each description in `xrs-codegen/xml` is generated into a module of the
+//! same name in X.RS's `src` directory, with a [`Request`] for each request,
+//! a [`Reply`] for each reply, and a `from_frame` function for each event.
+//! The generated modules are checked in, so building X.RS doesn't need this
+//! crate:
+//!
+//! ```text
+//! cargo run -p xrs-codegen             # regenerate every module
+//! cargo run -p xrs-codegen -- --check  # fail if any module is out of date
+//! ```
+//!
+//! To generate a new extension, copy its description from xcbproto into
+//! `xrs-codegen/xml`, run the generator, and declare the new module in
+//! X.RS's `lib.rs`. Definitions which can't be generated yet are reported,
+//! and listed in the module's documentation; the rest of the extension is
+//! still generated.
+//!
+//! [xcbproto]: https://gitlab.freedesktop.org/xorg/proto/xcbproto
+//! [`Request`]: ../xrs/request/trait.Request.html
+//! [`Reply`]: ../xrs/request/trait.Reply.html
+
+mod generate;
+mod protocol;
+mod xml;
+
+use protocol::Protocol;
+use std::{
+	env,
+	fs,
+	io,
+	path::{Path, PathBuf},
+	process::ExitCode,
+};
+
+fn main() -> ExitCode {
+	let check = match env::args().nth(1).as_deref() {
+		None => false,
+		Some("--check") => true,
+
+		Some(_) => {
+			eprintln!("usage: xrs-codegen [--check]");
+			return ExitCode::FAILURE;
+		},
+	};
+
+	match run(check) {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+
+		Err(error) => {
+			eprintln!("error: {error}");
+			ExitCode::FAILURE
+		},
+	}
+}
+
+/// Generates every module, or checks them if `check` is true.
+///
+/// Returns whether every module was up to date, when checking.
+fn run(check: bool) -> Result<bool, String> {
+	let codegen = Path::new(env!("CARGO_MANIFEST_DIR"));
+	let src = codegen.join("../src");
+
+	let mut up_to_date = true;
+
+	for path in descriptions(&codegen.join("xml")).map_err(|error| error.to_string())? {
+		let name = path.file_name().unwrap_or_default().to_string_lossy();
+		let source = format!("xrs-codegen/xml/{name}");
+
+		let xml = fs::read_to_string(&path).map_err(|error| format!("{source}: {error}"))?;
+		let root = xml::parse(&xml).map_err(|error| format!("{source}: {error}"))?;
+		let protocol = Protocol::from_xml(&root).map_err(|error| format!("{source}: {error}"))?;
+
+		let (module, skipped) = generate::generate(&protocol, &source);
+		let output = src.join(format!("{}.rs", protocol.header));
+
+		for (definition, reason) in skipped {
+			eprintln!("warning: {source}: skipped `{definition}`: {reason}");
+		}
+
+		if check {
+			if fs::read_to_string(&output).ok().as_deref() != Some(module.as_str()) {
+				eprintln!("src/{}.rs is out of date", protocol.header);
+				up_to_date = false;
+			}
+		} else {
+			fs::write(&output, module)
+				.map_err(|error| format!("src/{}.rs: {error}", protocol.header))?;
+		}
+	}
+
+	Ok(up_to_date)
+}
+
+/// The paths of the XML descriptions in `directory`, in order.
+fn descriptions(directory: &Path) -> io::Result<Vec<PathBuf>> {
+	let mut paths = Vec::new();
+
+	for entry in fs::read_dir(directory)? {
+		let path = entry?.path();
+
+		if path.extension().is_some_and(|extension| extension == "xml") {
+			paths.push(path);
+		}
+	}
+
+	paths.sort();
+
+	Ok(paths)
+}
@@ -0,0 +1,384 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The parts of an xcbproto description which are generated.
+//!
+//! Each request, event and XID type is read on its own: if one uses
+//! something which isn't supported yet, such as a `<switch>` or a struct
+//! type, it is [skipped] with the reason, and the rest of the extension is
+//! still generated.
+//!
+//! [skipped]: Protocol::skipped
+
+use crate::xml::Element;
+
+/// An extension's description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Protocol {
+	/// The name of the description, such as `xc_misc`, used as the name of the
+	/// generated module.
+	pub header: String,
+	/// The name of the extension, as queried from the X server.
+	pub xname: String,
+	pub major_version: u16,
+	pub minor_version: u16,
+
+	pub xid_types: Vec<String>,
+	pub requests: Vec<Request>,
+	pub events: Vec<Event>,
+
+	/// The definitions which weren't generated, with the reason why.
+	pub skipped: Vec<(String, String)>,
+}
+
+/// A request, and its reply if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+	pub name: String,
+	/// The request's minor opcode.
+	pub opcode: u8,
+	pub doc: Option<String>,
+
+	pub fields: Vec<Field>,
+	/// The fields of the request's reply, starting with its second byte.
+	pub reply: Option<Vec<Field>>,
+}
+
+/// An event, which is always 32 bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+	pub name: String,
+	/// The event's code, relative to the extension's first event code.
+	pub number: u8,
+	pub doc: Option<String>,
+
+	/// Whether the event has no sequence number after its first field, which
+	/// is only the case for `KeymapNotify` in the core protocol.
+	pub no_sequence_number: bool,
+	/// The event's fields, starting with its second byte.
+	pub fields: Vec<Field>,
+}
+
+/// A field of a request, reply or event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+	Value {
+		name: String,
+		ty: Type,
+		doc: Option<String>,
+	},
+
+	/// Unused bytes.
+	Pad(usize),
+
+	/// A list of values, with a `length` which is either an expression
+	/// involving earlier fields or, if `None`, the rest of the message.
+	List {
+		name: String,
+		ty: Type,
+		length: Option<Expr>,
+		doc: Option<String>,
+	},
+}
+
+/// The type of a [`Field`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+	Card8,
+	Card16,
+	Card32,
+	Int8,
+	Int16,
+	Int32,
+	Bool,
+
+	Window,
+	Atom,
+	Timestamp,
+
+	/// An XID type defined by the extension.
+	Xid(String),
+}
+
+impl Type {
+	/// The number of bytes a value of the type takes on the wire.
+	pub const fn size(&self) -> usize {
+		match self {
+			Self::Card8 | Self::Int8 | Self::Bool => 1,
+			Self::Card16 | Self::Int16 => 2,
+
+			Self::Card32
+			| Self::Int32
+			| Self::Window
+			| Self::Atom
+			| Self::Timestamp
+			| Self::Xid(_) => 4,
+		}
+	}
+}
+
+/// The length of a [`Field::List`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+	/// The value of an earlier field.
+	Field(String),
+	Value(u32),
+	/// `+`, `-`, `*` or `/` applied to two expressions.
+	Op(char, Box<Expr>, Box<Expr>),
+}
+
+impl Protocol {
+	/// Reads an extension's description from the root `<xcb>` element of its
+	/// xcbproto XML.
+	pub fn from_xml(root: &Element) -> Result<Self, String> {
+		if root.name != "xcb" {
+			return Err(format!("expected `<xcb>`, found `<{}>`", root.name));
+		}
+
+		let header = required(root, "header")?.to_owned();
+		let Some(xname) = root.attribute("extension-xname") else {
+			return Err(format!(
+				"`{header}` is not an extension; only extensions can be generated"
+			));
+		};
+		let version = |name| -> Result<u16, String> {
+			required(root, name)?
+				.parse()
+				.map_err(|_| format!("`{name}` is not a version number"))
+		};
+
+		let mut protocol = Self {
+			xname: xname.to_owned(),
+			major_version: version("major-version")?,
+			minor_version: version("minor-version")?,
+			header,
+
+			xid_types: Vec::new(),
+			requests: Vec::new(),
+			events: Vec::new(),
+
+			skipped: Vec::new(),
+		};
+
+		// XID types are read first, as they can be used before they are
+		// defined.
+		for element in root.elements().filter(|element| element.name == "xidtype") {
+			protocol
+				.xid_types
+				.push(required(element, "name")?.to_owned());
+		}
+
+		for element in root.elements() {
+			let name = element
+				.attribute("name")
+				.unwrap_or(&element.name)
+				.to_owned();
+
+			let result = match element.name.as_str() {
+				"import" | "xidtype" => Ok(()),
+
+				"request" => protocol
+					.request(element)
+					.map(|request| protocol.requests.push(request)),
+				"event" => protocol
+					.event(element)
+					.map(|event| protocol.events.push(event)),
+
+				other => Err(format!("`<{other}>` is not supported yet")),
+			};
+
+			if let Err(reason) = result {
+				protocol.skipped.push((name, reason));
+			}
+		}
+
+		Ok(protocol)
+	}
+
+	fn request(&self, element: &Element) -> Result<Request, String> {
+		let mut fields = Vec::new();
+		let mut reply = None;
+
+		for child in element.elements() {
+			match child.name.as_str() {
+				"reply" => {
+					let mut reply_fields = self.fields(child.elements())?;
+					document_fields(child, &mut reply_fields);
+
+					reply = Some(reply_fields);
+				},
+				"doc" => {},
+
+				_ => fields.push(self.field(child)?),
+			}
+		}
+
+		document_fields(element, &mut fields);
+
+		Ok(Request {
+			name: required(element, "name")?.to_owned(),
+			opcode: number(element, "opcode")?,
+			doc: doc(element),
+
+			fields,
+			reply,
+		})
+	}
+
+	fn event(&self, element: &Element) -> Result<Event, String> {
+		if element.attribute("xge") == Some("true") {
+			return Err("generic events are not supported yet".to_owned());
+		}
+
+		let mut fields = self.fields(element.elements())?;
+		document_fields(element, &mut fields);
+
+		Ok(Event {
+			name: required(element, "name")?.to_owned(),
+			number: number(element, "number")?,
+			doc: doc(element),
+
+			no_sequence_number: element.attribute("no-sequence-number") == Some("true"),
+			fields,
+		})
+	}
+
+	fn fields<'a>(
+		&self, elements: impl Iterator<Item = &'a Element>,
+	) -> Result<Vec<Field>, String> {
+		elements
+			.filter(|element| element.name != "doc")
+			.map(|element| self.field(element))
+			.collect()
+	}
+
+	fn field(&self, element: &Element) -> Result<Field, String> {
+		match element.name.as_str() {
+			"field" => Ok(Field::Value {
+				name: required(element, "name")?.to_owned(),
+				ty: self.ty(required(element, "type")?)?,
+				doc: None,
+			}),
+
+			"pad" => match element.attribute("bytes") {
+				Some(bytes) => bytes
+					.parse()
+					.map(Field::Pad)
+					.map_err(|_| format!("`{bytes}` is not a number of bytes")),
+
+				None => Err("aligning `<pad>`s are not supported yet".to_owned()),
+			},
+
+			"list" => Ok(Field::List {
+				name: required(element, "name")?.to_owned(),
+				ty: self.ty(required(element, "type")?)?,
+				length: element.elements().next().map(expr).transpose()?,
+				doc: None,
+			}),
+
+			other => Err(format!("`<{other}>` fields are not supported yet")),
+		}
+	}
+
+	fn ty(&self, name: &str) -> Result<Type, String> {
+		// Types from other descriptions are named with their header as a
+		// prefix, such as `xproto:WINDOW`.
+		let name = name.strip_prefix("xproto:").unwrap_or(name);
+
+		Ok(match name {
+			"CARD8" | "BYTE" | "char" | "void" => Type::Card8,
+			"CARD16" => Type::Card16,
+			"CARD32" => Type::Card32,
+			"INT8" => Type::Int8,
+			"INT16" => Type::Int16,
+			"INT32" => Type::Int32,
+			"BOOL" => Type::Bool,
+
+			"WINDOW" => Type::Window,
+			"ATOM" => Type::Atom,
+			"TIMESTAMP" => Type::Timestamp,
+
+			name if self.xid_types.iter().any(|xid| xid == name) => Type::Xid(name.to_owned()),
+
+			name => return Err(format!("the type `{name}` is not supported yet")),
+		})
+	}
+}
+
+/// Adds the field descriptions of `<doc>` elements to the fields they
+/// describe.
+fn document_fields(element: &Element, fields: &mut [Field]) {
+	let Some(doc) = element.elements().find(|child| child.name == "doc") else {
+		return;
+	};
+
+	for field_doc in doc.elements().filter(|child| child.name == "field") {
+		let Some(documented) = field_doc.attribute("name") else {
+			continue;
+		};
+
+		for field in &mut *fields {
+			if let Field::Value { name, doc, .. } | Field::List { name, doc, .. } = field {
+				if name == documented {
+					*doc = Some(field_doc.text());
+				}
+			}
+		}
+	}
+}
+
+/// The `<brief>` description in an element's `<doc>`, if it has one.
+fn doc(element: &Element) -> Option<String> {
+	element
+		.elements()
+		.find(|child| child.name == "doc")?
+		.elements()
+		.find(|child| child.name == "brief")
+		.map(Element::text)
+}
+
+fn required<'a>(element: &'a Element, attribute: &str) -> Result<&'a str, String> {
+	element
+		.attribute(attribute)
+		.ok_or_else(|| format!("`<{}>` has no `{attribute}`", element.name))
+}
+
+fn number(element: &Element, attribute: &str) -> Result<u8, String> {
+	let value = required(element, attribute)?;
+
+	value
+		.parse()
+		.map_err(|_| format!("`{value}` is not a valid {attribute}"))
+}
+
+fn expr(element: &Element) -> Result<Expr, String> {
+	match element.name.as_str() {
+		"fieldref" => Ok(Expr::Field(element.text())),
+		"value" => element
+			.text()
+			.parse()
+			.map(Expr::Value)
+			.map_err(|_| format!("`{}` is not a value", element.text())),
+
+		"op" => {
+			let op = match required(element, "op")? {
+				"+" => '+',
+				"-" => '-',
+				"*" => '*',
+				"/" => '/',
+
+				other => return Err(format!("the `{other}` operator is not supported yet")),
+			};
+			let mut operands = element.elements().map(expr);
+
+			match (operands.next(), operands.next()) {
+				(Some(left), Some(right)) => Ok(Expr::Op(op, Box::new(left?), Box::new(right?))),
+
+				_ => Err("`<op>` needs two operands".to_owned()),
+			}
+		},
+
+		other => Err(format!("`<{other}>` expressions are not supported yet")),
+	}
+}
@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A minimal XML reader, covering what the xcbproto descriptions use.
+//!
+//! Elements, attributes, text and the five predefined entities are read;
+//! the XML declaration, comments and `<!DOCTYPE>`s are skipped. CDATA
+//! sections, processing instructions inside elements and namespaces are not
+//! supported.
+
+use std::{error::Error, fmt, fmt::Formatter};
+
+/// An XML element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+	pub name: String,
+	pub attributes: Vec<(String, String)>,
+	pub children: Vec<Node>,
+}
+
+/// A child of an [`Element`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+	Element(Element),
+	Text(String),
+}
+
+impl Element {
+	/// The value of the attribute called `name`, if the element has one.
+	pub fn attribute(&self, name: &str) -> Option<&str> {
+		self.attributes
+			.iter()
+			.find(|(attribute, _)| attribute == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// The element's child elements, skipping text.
+	pub fn elements(&self) -> impl Iterator<Item = &Element> {
+		self.children.iter().filter_map(|child| match child {
+			Node::Element(element) => Some(element),
+			Node::Text(_) => None,
+		})
+	}
+
+	/// The element's text, with surrounding whitespace removed.
+	pub fn text(&self) -> String {
+		let text: String = self
+			.children
+			.iter()
+			.filter_map(|child| match child {
+				Node::Text(text) => Some(text.as_str()),
+				Node::Element(_) => None,
+			})
+			.collect();
+
+		text.trim().to_owned()
+	}
+}
+
+/// An error reading an XML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlError {
+	/// The line on which the error was found, starting at 1.
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for XmlError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl Error for XmlError {}
+
+/// Reads the root element of the XML document `source`.
+pub fn parse(source: &str) -> Result<Element, XmlError> {
+	let mut reader = Reader {
+		source,
+		position: 0,
+	};
+
+	reader.skip_misc()?;
+	let root = reader.element()?;
+	reader.skip_misc()?;
+
+	if reader.position < source.len() {
+		return Err(reader.error("content after the root element"));
+	}
+
+	Ok(root)
+}
+
+struct Reader<'a> {
+	source: &'a str,
+	/// The byte offset of the next character to be read.
+	position: usize,
+}
+
+impl Reader<'_> {
+	fn rest(&self) -> &str {
+		&self.source[self.position..]
+	}
+
+	fn error(&self, message: impl Into<String>) -> XmlError {
+		XmlError {
+			line: self.source[..self.position].lines().count().max(1),
+			message: message.into(),
+		}
+	}
+
+	/// Consumes `prefix` if the rest of the source starts with it.
+	fn eat(&mut self, prefix: &str) -> bool {
+		let eaten = self.rest().starts_with(prefix);
+
+		if eaten {
+			self.position += prefix.len();
+		}
+
+		eaten
+	}
+
+	fn expect(&mut self, prefix: &str) -> Result<(), XmlError> {
+		if self.eat(prefix) {
+			Ok(())
+		} else {
+			Err(self.error(format!("expected `{prefix}`")))
+		}
+	}
+
+	/// Consumes everything up to and including `end`.
+	fn skip_past(&mut self, end: &str) -> Result<(), XmlError> {
+		match self.rest().find(end) {
+			Some(offset) => {
+				self.position += offset + end.len();
+
+				Ok(())
+			},
+
+			None => Err(self.error(format!("expected `{end}`"))),
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		let rest = self.rest();
+
+		self.position += rest.len() - rest.trim_start().len();
+	}
+
+	/// Skips whitespace, comments, the XML declaration and `<!DOCTYPE>`s.
+	fn skip_misc(&mut self) -> Result<(), XmlError> {
+		loop {
+			self.skip_whitespace();
+
+			if self.eat("<!--") {
+				self.skip_past("-->")?;
+			} else if self.eat("<?") {
+				self.skip_past("?>")?;
+			} else if self.eat("<!") {
+				self.skip_past(">")?;
+			} else {
+				return Ok(());
+			}
+		}
+	}
+
+	fn name(&mut self) -> Result<String, XmlError> {
+		let rest = self.rest();
+		let length = rest
+			.find(|char: char| !(char.is_alphanumeric() || matches!(char, '_' | '-' | '.' | ':')))
+			.unwrap_or(rest.len());
+
+		if length == 0 {
+			return Err(self.error("expected a name"));
+		}
+
+		let name = rest[..length].to_owned();
+		self.position += length;
+
+		Ok(name)
+	}
+
+	fn element(&mut self) -> Result<Element, XmlError> {
+		self.expect("<")?;
+		let name = self.name()?;
+
+		let mut attributes = Vec::new();
+
+		loop {
+			self.skip_whitespace();
+
+			if self.eat("/>") {
+				return Ok(Element {
+					name,
+					attributes,
+					children: Vec::new(),
+				});
+			}
+
+			if self.eat(">") {
+				break;
+			}
+
+			let attribute = self.name()?;
+			self.skip_whitespace();
+			self.expect("=")?;
+			self.skip_whitespace();
+
+			let quote = if self.eat("\"") {
+				"\""
+			} else {
+				self.expect("'")?;
+				"'"
+			};
+			let Some(length) = self.rest().find(quote) else {
+				return Err(self.error("unterminated attribute value"));
+			};
+			let value = self.unescape(&self.rest()[..length])?;
+			self.position += length + 1;
+
+			attributes.push((attribute, value));
+		}
+
+		let mut children = Vec::new();
+
+		loop {
+			if self.eat("</") {
+				let end = self.name()?;
+
+				if end != name {
+					return Err(self.error(format!("expected `</{name}>`, found `</{end}>`")));
+				}
+
+				self.skip_whitespace();
+				self.expect(">")?;
+
+				return Ok(Element {
+					name,
+					attributes,
+					children,
+				});
+			}
+
+			if self.eat("<!--") {
+				self.skip_past("-->")?;
+			} else if self.rest().starts_with('<') {
+				children.push(Node::Element(self.element()?));
+			} else if self.rest().is_empty() {
+				return Err(self.error(format!("unclosed `<{name}>`")));
+			} else {
+				let length = self.rest().find('<').unwrap_or(self.rest().len());
+				let text = self.unescape(&self.rest()[..length])?;
+				self.position += length;
+
+				children.push(Node::Text(text));
+			}
+		}
+	}
+
+	/// Replaces the predefined entities in `text`.
+	fn unescape(&self, text: &str) -> Result<String, XmlError> {
+		let mut unescaped = String::with_capacity(text.len());
+		let mut rest = text;
+
+		while let Some(start) = rest.find('&') {
+			unescaped.push_str(&rest[..start]);
+			rest = &rest[start..];
+
+			let Some(end) = rest.find(';') else {
+				return Err(self.error("unterminated entity"));
+			};
+
+			unescaped.push(match &rest[1..end] {
+				"lt" => '<',
+				"gt" => '>',
+				"amp" => '&',
+				"quot" => '"',
+				"apos" => '\'',
+
+				entity => return Err(self.error(format!("unknown entity `&{entity};`"))),
+			});
+			rest = &rest[end + 1..];
+		}
+
+		unescaped.push_str(rest);
+
+		Ok(unescaped)
+	}
+}
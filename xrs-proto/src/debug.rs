@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::X11Frame;
+use core::fmt::{self, Debug, Display, Formatter};
+
+/// Formats bytes as hexadecimal, truncated to their first
+/// [`HexBytes::LIMIT`] bytes so that large values don't flood debug output.
+///
+/// ```
+/// use xrs_proto::HexBytes;
+///
+/// assert_eq!(format!("{:?}", HexBytes(&[0xde, 0xad])), "[de ad]");
+/// assert_eq!(
+///     format!("{:?}", HexBytes(&[0; 40])),
+///     "[00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 …; 40 bytes]",
+/// );
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexBytes<'bytes>(pub &'bytes [u8]);
+
+impl HexBytes<'_> {
+	/// The number of bytes shown before the rest are left out.
+	pub const LIMIT: usize = 32;
+}
+
+impl Debug for HexBytes<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let shown = &self.0[..self.0.len().min(Self::LIMIT)];
+
+		f.write_str("[")?;
+
+		for (index, byte) in shown.iter().enumerate() {
+			if index > 0 {
+				f.write_str(" ")?;
+			}
+
+			write!(f, "{byte:02x}")?;
+		}
+
+		if self.0.len() > Self::LIMIT {
+			write!(f, " …; {} bytes", self.0.len())?;
+		}
+
+		f.write_str("]")
+	}
+}
+
+impl Debug for X11Frame {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Request {
+				major_opcode,
+				metabyte,
+				length,
+				chunk,
+			} => f
+				.debug_struct("Request")
+				.field("major_opcode", major_opcode)
+				.field("metabyte", metabyte)
+				.field("length", length)
+				.field("chunk", &HexBytes(chunk))
+				.finish(),
+
+			Self::Reply {
+				metabyte,
+				sequence,
+				length,
+				chunk,
+			} => f
+				.debug_struct("Reply")
+				.field("metabyte", metabyte)
+				.field("sequence", sequence)
+				.field("length", length)
+				.field("chunk", &HexBytes(chunk))
+				.finish(),
+
+			Self::Event { code, chunk } => f
+				.debug_struct("Event")
+				.field("code", code)
+				.field("chunk", &HexBytes(chunk))
+				.finish(),
+
+			Self::GenericEvent {
+				extension,
+				sequence,
+				length,
+				event_type,
+				chunk,
+			} => f
+				.debug_struct("GenericEvent")
+				.field("extension", extension)
+				.field("sequence", sequence)
+				.field("length", length)
+				.field("event_type", event_type)
+				.field("chunk", &HexBytes(chunk))
+				.finish(),
+
+			Self::Error {
+				code,
+				sequence,
+				metablock,
+				minor_opcode,
+				major_opcode,
+				chunk,
+			} => f
+				.debug_struct("Error")
+				.field("code", code)
+				.field("sequence", sequence)
+				.field("metablock", &HexBytes(metablock))
+				.field("minor_opcode", minor_opcode)
+				.field("major_opcode", major_opcode)
+				.field("chunk", &HexBytes(chunk))
+				.finish(),
+		}
+	}
+}
+
+/// Summarizes the frame on one line, such as `reply to sequence 7 (32
+/// bytes)`.
+impl Display for X11Frame {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let size = self.size();
+
+		match self {
+			Self::Request {
+				major_opcode,
+				metabyte,
+				..
+			} => write!(f, "request {major_opcode}.{metabyte} ({size} bytes)"),
+
+			Self::Reply { sequence, .. } => {
+				write!(f, "reply to sequence {sequence} ({size} bytes)")
+			},
+
+			Self::Event { code, .. } => {
+				// Events sent by other clients have the top bit of their code
+				// set.
+				write!(f, "event {}", code & 0x7f)?;
+
+				if code & 0x80 != 0 {
+					f.write_str(" (sent)")?;
+				}
+
+				Ok(())
+			},
+
+			Self::GenericEvent {
+				extension,
+				sequence,
+				event_type,
+				..
+			} => write!(
+				f,
+				"generic event {event_type} of extension {extension} at sequence {sequence} \
+				 ({size} bytes)"
+			),
+
+			Self::Error {
+				code,
+				sequence,
+				minor_opcode,
+				major_opcode,
+				..
+			} => write!(
+				f,
+				"error {code} in request {major_opcode}.{minor_opcode} (sequence {sequence})"
+			),
+		}
+	}
+}
@@ -0,0 +1,299 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The bit set in an event code if the event was sent with a `SendEvent`
+/// request.
+pub const SEND_EVENT_MASK: u8 = 0x80;
+
+/// The codes of core events, without the [`SEND_EVENT_MASK`] bit.
+pub mod event_codes {
+	pub const KEY_PRESS: u8 = 2;
+	pub const KEY_RELEASE: u8 = 3;
+	pub const BUTTON_PRESS: u8 = 4;
+	pub const BUTTON_RELEASE: u8 = 5;
+	pub const MOTION_NOTIFY: u8 = 6;
+
+	pub const ENTER_NOTIFY: u8 = 7;
+	pub const LEAVE_NOTIFY: u8 = 8;
+	pub const FOCUS_IN: u8 = 9;
+	pub const FOCUS_OUT: u8 = 10;
+	pub const KEYMAP_NOTIFY: u8 = 11;
+
+	pub const EXPOSE: u8 = 12;
+	pub const GRAPHICS_EXPOSURE: u8 = 13;
+	pub const NO_EXPOSURE: u8 = 14;
+	pub const VISIBILITY_NOTIFY: u8 = 15;
+
+	pub const CREATE_NOTIFY: u8 = 16;
+	pub const DESTROY_NOTIFY: u8 = 17;
+	pub const UNMAP_NOTIFY: u8 = 18;
+	pub const MAP_NOTIFY: u8 = 19;
+	pub const MAP_REQUEST: u8 = 20;
+	pub const REPARENT_NOTIFY: u8 = 21;
+	pub const CONFIGURE_NOTIFY: u8 = 22;
+	pub const CONFIGURE_REQUEST: u8 = 23;
+	pub const GRAVITY_NOTIFY: u8 = 24;
+	pub const RESIZE_REQUEST: u8 = 25;
+	pub const CIRCULATE_NOTIFY: u8 = 26;
+	pub const CIRCULATE_REQUEST: u8 = 27;
+
+	pub const PROPERTY_NOTIFY: u8 = 28;
+	pub const SELECTION_CLEAR: u8 = 29;
+	pub const SELECTION_REQUEST: u8 = 30;
+	pub const SELECTION_NOTIFY: u8 = 31;
+	pub const COLORMAP_NOTIFY: u8 = 32;
+	pub const CLIENT_MESSAGE: u8 = 33;
+	pub const MAPPING_NOTIFY: u8 = 34;
+}
+
+/// Reads fields from an event's data by their offsets in the protocol.
+///
+/// The data is the `chunk` of an [`X11Frame::Event`], which starts after the
+/// event's code, so offsets are one more than the index they are read from.
+///
+/// # Panics
+/// Reading a field which doesn't fit in the 32-byte event panics.
+///
+/// [`X11Frame::Event`]: crate::X11Frame::Event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventFields<'data>(pub &'data [u8; 31]);
+
+impl EventFields<'_> {
+	pub const fn u8(&self, offset: usize) -> u8 {
+		self.0[offset - 1]
+	}
+
+	pub const fn bool(&self, offset: usize) -> bool {
+		self.u8(offset) != 0
+	}
+
+	pub const fn u16(&self, offset: usize) -> u16 {
+		u16::from_be_bytes([self.u8(offset), self.u8(offset + 1)])
+	}
+
+	pub const fn i16(&self, offset: usize) -> i16 {
+		self.u16(offset) as i16
+	}
+
+	pub const fn u32(&self, offset: usize) -> u32 {
+		u32::from_be_bytes([
+			self.u8(offset),
+			self.u8(offset + 1),
+			self.u8(offset + 2),
+			self.u8(offset + 3),
+		])
+	}
+
+	/// Reads the data of a `ClientMessage` event in the format given by its
+	/// `format` field.
+	///
+	/// Returns `None` if the format is not 8, 16 or 32.
+	pub fn client_message_data(&self) -> Option<ClientMessageData> {
+		let data = &self.0[11..31];
+
+		Some(match self.u8(1) {
+			8 => ClientMessageData::U8(data.try_into().ok()?),
+			16 => ClientMessageData::U16(core::array::from_fn(|index| self.u16(12 + 2 * index))),
+			32 => ClientMessageData::U32(core::array::from_fn(|index| self.u32(12 + 4 * index))),
+
+			_ => return None,
+		})
+	}
+}
+
+/// The `detail` of a focus event, describing the window's relationship to
+/// the focus change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusDetail {
+	Ancestor,
+	Virtual,
+	Inferior,
+	Nonlinear,
+	NonlinearVirtual,
+	Pointer,
+	PointerRoot,
+	None,
+}
+
+/// The `mode` of a focus event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusMode {
+	/// The focus changed normally.
+	Normal,
+	/// The focus changed because the keyboard was grabbed.
+	Grab,
+	/// The focus changed because the keyboard was ungrabbed.
+	Ungrab,
+	/// The focus changed while the keyboard was grabbed.
+	WhileGrabbed,
+}
+
+impl FocusDetail {
+	/// Returns the `FocusDetail` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Ancestor,
+			1 => Self::Virtual,
+			2 => Self::Inferior,
+			3 => Self::Nonlinear,
+			4 => Self::NonlinearVirtual,
+			5 => Self::Pointer,
+			6 => Self::PointerRoot,
+			7 => Self::None,
+
+			_ => return None,
+		})
+	}
+}
+
+impl FocusMode {
+	/// Returns the `FocusMode` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Normal,
+			1 => Self::Grab,
+			2 => Self::Ungrab,
+			3 => Self::WhileGrabbed,
+
+			_ => return None,
+		})
+	}
+}
+
+/// How much of a window is visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+	Unobscured,
+	PartiallyObscured,
+	FullyObscured,
+}
+
+impl Visibility {
+	/// Returns the `Visibility` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Unobscured,
+			1 => Self::PartiallyObscured,
+			2 => Self::FullyObscured,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Where a window was or is to be moved in the stacking order by a
+/// `CirculateWindow` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Place {
+	OnTop,
+	OnBottom,
+}
+
+impl Place {
+	/// Returns the `Place` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::OnTop,
+			1 => Self::OnBottom,
+
+			_ => return None,
+		})
+	}
+}
+
+/// Whether a property was changed or deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropertyState {
+	NewValue,
+	Deleted,
+}
+
+impl PropertyState {
+	/// Returns the `PropertyState` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::NewValue,
+			1 => Self::Deleted,
+
+			_ => return None,
+		})
+	}
+}
+
+/// The data of a `ClientMessage` event, in the format it was sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientMessageData {
+	U8([u8; 20]),
+	U16([u16; 10]),
+	U32([u32; 5]),
+}
+
+impl ClientMessageData {
+	/// The format of the data: the size of each of its values in bits.
+	pub const fn format(&self) -> u8 {
+		match self {
+			Self::U8(_) => 8,
+			Self::U16(_) => 16,
+			Self::U32(_) => 32,
+		}
+	}
+}
+
+/// Which mapping a `MappingNotify` event reports a change to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappingRequest {
+	/// The modifier mapping.
+	Modifier,
+	/// The keyboard mapping of `count` keycodes from `first_keycode`.
+	Keyboard,
+	/// The pointer button mapping.
+	Pointer,
+}
+
+impl MappingRequest {
+	/// Returns the `MappingRequest` encoded as `value`.
+	pub const fn from_value(value: u8) -> Option<Self> {
+		Some(match value {
+			0 => Self::Modifier,
+			1 => Self::Keyboard,
+			2 => Self::Pointer,
+
+			_ => return None,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fields_are_read_at_protocol_offsets() {
+		let mut chunk = [0; 31];
+		// A `ClientMessage` event's format, window and first 16-bit values.
+		chunk[0] = 16;
+		chunk[3..7].copy_from_slice(&0x0060_0001u32.to_be_bytes());
+		chunk[11..13].copy_from_slice(&0xfffeu16.to_be_bytes());
+		chunk[13..15].copy_from_slice(&7u16.to_be_bytes());
+
+		let fields = EventFields(&chunk);
+
+		assert_eq!(fields.u8(1), 16);
+		assert_eq!(fields.u32(4), 0x0060_0001);
+		assert_eq!(fields.i16(12), -2);
+
+		let Some(ClientMessageData::U16(data)) = fields.client_message_data() else {
+			panic!("the data wasn't read as 16-bit values");
+		};
+		assert_eq!(data[..3], [0xfffe, 7, 0]);
+	}
+
+	#[test]
+	fn client_message_data_needs_a_valid_format() {
+		let mut chunk = [0; 31];
+		chunk[0] = 24;
+
+		assert_eq!(EventFields(&chunk).client_message_data(), None);
+	}
+}
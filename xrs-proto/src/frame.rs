@@ -0,0 +1,755 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use bytes::{Buf, BufMut, Bytes};
+
+/// A single message sent between an X client and an X server.
+///
+/// Its [`Debug`] output shows the frame's data as [`HexBytes`].
+///
+/// [`Debug`]: core::fmt::Debug
+/// [`HexBytes`]: crate::HexBytes
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum X11Frame {
+	/// <table>
+	///     <tbody>
+	///         <tr>
+	///             <td><b>Byte</b></td>
+	///             <td><b>Meaning</b></td>
+	///         </tr>
+	///         <tr>
+	///             <td>0</td>
+	///             <td><code>major_opcode</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>1</td>
+	///             <td><code>metabyte</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>2</td>
+	///             <td rowspan="2"><code>length</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>3</td>
+	///         </tr>
+	///         <tr>
+	///             <td>4..(4 + (length * 4))</td>
+	///             <td><code>chunk</code></td>
+	///         </tr>
+	///     </tbody>
+	/// </table>
+	///
+	/// Note: requests are never read by X.RS; requests are received by X
+	/// servers, while X.RS is a client library. The only way to tell apart
+	/// requests vs. replies & events is whether they are received by an X
+	/// client or whether they are received by the X server.
+	Request {
+		/// The major opcode identifying the type of request (for core requests)
+		/// or the extension that defines the request (for extension requests).
+		major_opcode: u8,
+		/// A single byte in the header which may be used for additional data.
+		metabyte: u8,
+		/// The length of the request in blocks (units of 4 bytes), as written
+		/// in its header. See [`X11Frame::size`] for its size in bytes.
+		///
+		/// If the big-requests extension is enabled and this is `0`, then the
+		/// next block is the length of the request instead, but as a `u32`
+		/// value.
+		length: u16,
+
+		/// Additional data contained in the request, if any.
+		chunk: Bytes,
+	},
+
+	/// <table>
+	///     <tbody>
+	///         <tr>
+	///             <td><b>Byte</b></td>
+	///             <td><b>Meaning</b></td>
+	///         </tr>
+	///         <tr>
+	///             <td>0</td>
+	///             <td><code>1</code> - reply indicator</td>
+	///         </tr>
+	///         <tr>
+	///             <td>1</td>
+	///             <td><code>metabyte</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>2</td>
+	///             <td rowspan="2"><code>sequence</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>3</td>
+	///         </tr>
+	///         <tr>
+	///             <td>4</td>
+	///             <td rowspan="4"><code>length</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>5</td>
+	///         </tr>
+	///         <tr>
+	///             <td>6</td>
+	///         </tr>
+	///         <tr>
+	///             <td>7</td>
+	///         </tr>
+	///         <tr>
+	///             <td>8..(32 + (length * 4))</td>
+	///             <td><code>chunk</code></td>
+	///         </tr>
+	///     </tbody>
+	/// </table>
+	Reply {
+		/// A single byte in the header which may be used for additional data.
+		metabyte: u8,
+		/// The sequence number which identifies when the request that generated
+		/// the reply was sent.
+		sequence: u16,
+		/// The length of any additional data after the first 8 blocks (32
+		/// bytes) in the reply in blocks (units of 4 bytes), as written in its
+		/// header. See [`X11Frame::size`] for its size in bytes.
+		length: u32,
+
+		/// Data contained in the reply.
+		chunk: Bytes,
+	},
+
+	/// <table>
+	///     <tbody>
+	///         <tr>
+	///             <td><b>Byte</b></td>
+	///             <td><b>Meaning</b></td>
+	///         </tr>
+	///         <tr>
+	///             <td>0</td>
+	///             <td><code>code</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>1..32</td>
+	///             <td><code>chunk</code></td>
+	///         </tr>
+	///     </tbody>
+	/// </table>
+	Event {
+		/// The code uniquely identifying the type of event.
+		code: u8,
+		/// Data contained in the event.
+		chunk: [u8; 31],
+	},
+
+	/// <table>
+	///     <tbody>
+	///         <tr>
+	///             <td><b>Byte</b></td>
+	///             <td><b>Meaning</b></td>
+	///         </tr>
+	///         <tr>
+	///             <td>0</td>
+	///             <td><code>35</code> - generic event indicator</td>
+	///         </tr>
+	///         <tr>
+	///             <td>1</td>
+	///             <td><code>extension</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>2</td>
+	///             <td rowspan="2"><code>sequence</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>3</td>
+	///         </tr>
+	///         <tr>
+	///             <td>4</td>
+	///             <td rowspan="4"><code>length</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>5</td>
+	///         </tr>
+	///         <tr>
+	///             <td>6</td>
+	///         </tr>
+	///         <tr>
+	///             <td>7</td>
+	///         </tr>
+	///         <tr>
+	///             <td>8</td>
+	///             <td rowspan="2"><code>event_type</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>9</td>
+	///         </tr>
+	///         <tr>
+	///             <td>10..(32 + (length * 4))</td>
+	///             <td><code>chunk</code></td>
+	///         </tr>
+	///     </tbody>
+	/// </table>
+	///
+	/// Generic events are defined by the Generic Event Extension for
+	/// extension events which don't fit in 32 bytes.
+	GenericEvent {
+		/// The major opcode of the extension which defines the event.
+		extension: u8,
+		/// The sequence number of the last request the X server processed
+		/// before generating the event.
+		sequence: u16,
+		/// The length of any additional data after the first 8 blocks (32
+		/// bytes) in the event in blocks (units of 4 bytes), as written in its
+		/// header. See [`X11Frame::size`] for its size in bytes.
+		length: u32,
+		/// The code identifying the type of event within its extension.
+		event_type: u16,
+
+		/// Data contained in the event.
+		chunk: Bytes,
+	},
+
+	/// <table>
+	///     <tbody>
+	///         <tr>
+	///             <td><b>Byte</b></td>
+	///             <td><b>Meaning</b></td>
+	///         </tr>
+	///         <tr>
+	///             <td>0</td>
+	///             <td><code>0</code> - error indicator</td>
+	///         </tr>
+	///         <tr>
+	///             <td>1</td>
+	///             <td><code>code</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>2</td>
+	///             <td rowspan="2"><code>sequence</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>3</td>
+	///         </tr>
+	///         <tr>
+	///             <td>4</td>
+	///             <td rowspan="4"><code>metablock</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>5</td>
+	///         </tr>
+	///         <tr>
+	///             <td>6</td>
+	///         </tr>
+	///         <tr>
+	///             <td>7</td>
+	///         </tr>
+	///         <tr>
+	///             <td>8</td>
+	///             <td rowspan="2"><code>minor_opcode</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>9</td>
+	///         </tr>
+	///         <tr>
+	///             <td>10</td>
+	///             <td><code>major_opcode</code></td>
+	///         </tr>
+	///         <tr>
+	///             <td>11..32</td>
+	///             <td><code>chunk</code></td>
+	///         </tr>
+	///     </tbody>
+	/// </table>
+	Error {
+		/// The code uniquely identifying the type of error.
+		code: u8,
+		/// The sequence number which identifies when the last request sent that
+		/// relates to the generation of this error was sent.
+		sequence: u16,
+		/// A single block (4 bytes) in the header which may be used for
+		/// additional data.
+		metablock: [u8; 4],
+
+		/// The minor opcode identifying the type of the last request sent that
+		/// relates to the generation of this error within its extension (`0`
+		/// for core requests).
+		minor_opcode: u16,
+		/// The major opcode identifying the type of (for core requests) or the
+		/// extension which defines (for extension requests) the last request
+		/// sent that relates to the generation of this error.
+		major_opcode: u8,
+
+		/// Additional data contained in the error.
+		chunk: [u8; 21],
+	},
+}
+
+/// The code of [`X11Frame::GenericEvent`]s.
+///
+/// Generic events can't be sent with a `SendEvent` request, so this code
+/// never has the send event bit set.
+const GENERIC_EVENT: u8 = 35;
+
+/// The size of a block (the unit of length in the X11 protocol) in bytes.
+const BLOCK: u64 = 4;
+
+/// The size of a frame's header, in bytes, for replies and generic events,
+/// whose headers give the length of the data after the first 32 bytes.
+const LONG_FRAME_HEADER: usize = 8;
+
+/// Returns the number of bytes in `blocks` blocks of data following `base`
+/// bytes.
+///
+/// The arithmetic is done in 64 bits, so it can't overflow for any length
+/// a header can give; lengths too large to address on this target can never
+/// be complete, so they are [`Incomplete`].
+fn byte_length(base: usize, blocks: u32) -> Result<usize, Incomplete> {
+	let bytes = base as u64 + u64::from(blocks) * BLOCK;
+
+	usize::try_from(bytes).map_err(|_| Incomplete)
+}
+
+impl X11Frame {
+	/// Checks whether a whole frame is present at the start of `buf`,
+	/// advancing `buf` past it if it is.
+	///
+	/// Lengths are never trusted: a header giving a length larger than the
+	/// bytes present, however large, is [`Incomplete`].
+	///
+	/// ```
+	/// use xrs_proto::{Incomplete, X11Frame};
+	///
+	/// // A reply header giving the largest possible length.
+	/// let mut buf: &[u8] = &[1, 0, 0, 1, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+	///
+	/// assert_eq!(X11Frame::check(&mut buf), Err(Incomplete));
+	/// ```
+	// https://tokio.rs/tokio/tutorial/framing
+	pub fn check(buf: &mut &[u8]) -> Result<(), Incomplete> {
+		const REPLY_BODY: usize = 32 - LONG_FRAME_HEADER; // 24
+
+		match get_u8(buf)? {
+			// Reply or generic event
+			// Both have a length in the second block which counts the blocks
+			// after the first 32 bytes.
+			1 | GENERIC_EVENT => {
+				// Skip the rest of the first block; it can't be invalid at this
+				// level of abstraction.
+				skip(buf, 3)?;
+				let length = get_u32(buf)?;
+
+				// Skip the reply's data, verifying that enough bytes are
+				// present in the process.
+				skip(buf, byte_length(REPLY_BODY, length)?)
+			},
+
+			// Error or event
+			// Errors and events are always 32 bytes; none can be invalid at
+			// this level of abstraction, so we skip all of them, verifying that
+			// there are enough in the process.
+			_ => skip(buf, 32 - 1),
+		}
+	}
+
+	/// Parses the frame at the start of `buf`, advancing `buf` past it.
+	///
+	/// If the whole frame isn't present, [`Incomplete`] is returned, so a
+	/// frame needn't be [checked] first.
+	///
+	/// ```
+	/// use xrs_proto::{Incomplete, X11Frame};
+	///
+	/// // A reply header giving 1 block of data after the first 32 bytes, of
+	/// // which only 8 are present.
+	/// let mut buf: &[u8] = &[1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+	///
+	/// assert_eq!(X11Frame::parse(&mut buf), Err(Incomplete));
+	/// ```
+	///
+	/// [checked]: X11Frame::check
+	// https://tokio.rs/tokio/tutorial/framing
+	pub fn parse(buf: &mut &[u8]) -> Result<Self, Incomplete> {
+		const MESSAGE_BASE: usize = 32;
+		const ERROR_BODY: usize = MESSAGE_BASE - 11;
+		const REPLY_BODY: usize = MESSAGE_BASE - LONG_FRAME_HEADER;
+		const GENERIC_EVENT_BODY: usize = REPLY_BODY - 2;
+
+		match get_u8(buf)? {
+			// Error
+			0 => {
+				let error_code = get_u8(buf)?;
+				let sequence = get_u16(buf)?;
+				let metablock = [get_u8(buf)?, get_u8(buf)?, get_u8(buf)?, get_u8(buf)?];
+
+				let minor_opcode = get_u16(buf)?;
+				let major_opcode = get_u8(buf)?;
+
+				let chunk = take_array::<ERROR_BODY>(buf)?;
+
+				Ok(Self::Error {
+					code: error_code,
+					sequence,
+					metablock,
+
+					minor_opcode,
+					major_opcode,
+
+					chunk,
+				})
+			},
+
+			// Reply
+			1 => {
+				let metabyte = get_u8(buf)?;
+				let sequence = get_u16(buf)?;
+				let length = get_u32(buf)?;
+
+				let chunk = Bytes::copy_from_slice(take(buf, byte_length(REPLY_BODY, length)?)?);
+
+				Ok(Self::Reply {
+					metabyte,
+					sequence,
+					length,
+
+					chunk,
+				})
+			},
+
+			// Generic event
+			GENERIC_EVENT => {
+				let extension = get_u8(buf)?;
+				let sequence = get_u16(buf)?;
+				let length = get_u32(buf)?;
+				let event_type = get_u16(buf)?;
+
+				let chunk =
+					Bytes::copy_from_slice(take(buf, byte_length(GENERIC_EVENT_BODY, length)?)?);
+
+				Ok(Self::GenericEvent {
+					extension,
+					sequence,
+					length,
+					event_type,
+
+					chunk,
+				})
+			},
+
+			// Event
+			event_code => {
+				let chunk = take_array::<{ MESSAGE_BASE - 1 }>(buf)?;
+
+				Ok(Self::Event {
+					code: event_code,
+					chunk,
+				})
+			},
+		}
+	}
+
+	/// Returns the size of this frame in bytes, as written by [`write_to`].
+	///
+	/// [`write_to`]: X11Frame::write_to
+	pub fn size(&self) -> usize {
+		match self {
+			Self::Request { chunk, .. } => 4 + chunk.len(),
+			Self::Reply { chunk, .. } => LONG_FRAME_HEADER + chunk.len(),
+			Self::GenericEvent { chunk, .. } => LONG_FRAME_HEADER + 2 + chunk.len(),
+
+			Self::Event { .. } | Self::Error { .. } => 32,
+		}
+	}
+
+	/// Serializes this frame into `buf`.
+	pub fn write_to(&self, buf: &mut impl BufMut) {
+		match self {
+			Self::Request {
+				major_opcode,
+				metabyte,
+				length,
+				chunk,
+			} => {
+				buf.put_u8(*major_opcode);
+				buf.put_u8(*metabyte);
+				buf.put_u16(*length);
+				buf.put_slice(chunk);
+			},
+
+			Self::Reply {
+				metabyte,
+				sequence,
+				length,
+				chunk,
+			} => {
+				buf.put_u8(1);
+				buf.put_u8(*metabyte);
+				buf.put_u16(*sequence);
+				buf.put_u32(*length);
+				buf.put_slice(chunk);
+			},
+
+			Self::Event { code, chunk } => {
+				buf.put_u8(*code);
+				buf.put_slice(chunk);
+			},
+
+			Self::GenericEvent {
+				extension,
+				sequence,
+				length,
+				event_type,
+				chunk,
+			} => {
+				buf.put_u8(GENERIC_EVENT);
+				buf.put_u8(*extension);
+				buf.put_u16(*sequence);
+				buf.put_u32(*length);
+				buf.put_u16(*event_type);
+				buf.put_slice(chunk);
+			},
+
+			Self::Error {
+				code,
+				sequence,
+				metablock,
+				minor_opcode,
+				major_opcode,
+				chunk,
+			} => {
+				buf.put_u8(0);
+				buf.put_u8(*code);
+				buf.put_u16(*sequence);
+				buf.put_slice(metablock);
+				buf.put_u16(*minor_opcode);
+				buf.put_u8(*major_opcode);
+				buf.put_slice(chunk);
+			},
+		}
+	}
+}
+
+/// Not enough bytes are present to read a whole frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Incomplete;
+
+#[allow(dead_code)]
+fn peek_u8(buf: &mut &[u8]) -> Result<u8, Incomplete> {
+	if !buf.has_remaining() {
+		return Err(Incomplete);
+	}
+
+	Ok(buf.chunk()[0])
+}
+
+fn get_u8(buf: &mut &[u8]) -> Result<u8, Incomplete> {
+	if !buf.has_remaining() {
+		return Err(Incomplete);
+	}
+
+	Ok(buf.get_u8())
+}
+
+#[allow(dead_code)]
+fn peek_u16(buf: &mut &[u8]) -> Result<u16, Incomplete> {
+	if buf.remaining() < 2 {
+		return Err(Incomplete);
+	}
+
+	let chunk = buf.chunk();
+	Ok(u16::from_be_bytes([chunk[0], chunk[1]]))
+}
+
+fn get_u16(buf: &mut &[u8]) -> Result<u16, Incomplete> {
+	if buf.remaining() < 2 {
+		return Err(Incomplete);
+	}
+
+	Ok(buf.get_u16())
+}
+
+#[allow(dead_code)]
+fn peek_u32(buf: &mut &[u8]) -> Result<u32, Incomplete> {
+	if buf.remaining() < 4 {
+		return Err(Incomplete);
+	}
+
+	let chunk = buf.chunk();
+	Ok(u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+fn get_u32(buf: &mut &[u8]) -> Result<u32, Incomplete> {
+	if buf.remaining() < 4 {
+		return Err(Incomplete);
+	}
+
+	Ok(buf.get_u32())
+}
+
+/// Takes the next `count` bytes of `buf`.
+fn take<'a>(buf: &mut &'a [u8], count: usize) -> Result<&'a [u8], Incomplete> {
+	if buf.len() < count {
+		return Err(Incomplete);
+	}
+
+	let (taken, rest) = buf.split_at(count);
+	*buf = rest;
+
+	Ok(taken)
+}
+
+/// Takes the next `N` bytes of `buf`.
+fn take_array<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], Incomplete> {
+	let mut array = [0; N];
+	array.copy_from_slice(take(buf, N)?);
+
+	Ok(array)
+}
+
+fn skip(buf: &mut &[u8], count: usize) -> Result<(), Incomplete> {
+	if buf.remaining() < count {
+		return Err(Incomplete);
+	}
+
+	buf.advance(count);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{RawRequest, RequestTooLong};
+	use alloc::{vec, vec::Vec};
+
+	/// Returns a reply with the given `length` in its header, followed by
+	/// that many blocks of data after the first 32 bytes.
+	fn reply(length: u32) -> Vec<u8> {
+		let mut buf = vec![1, 0, 0, 1];
+		buf.extend_from_slice(&length.to_be_bytes());
+		buf.resize(byte_length(32, length).unwrap(), 0xaa);
+
+		buf
+	}
+
+	#[test]
+	fn reply_of_length_0() {
+		let buf = reply(0);
+
+		assert_eq!(X11Frame::check(&mut &buf[..]), Ok(()));
+
+		let mut rest = &buf[..];
+		let frame = X11Frame::parse(&mut rest).unwrap();
+
+		assert!(rest.is_empty());
+		assert!(matches!(&frame, X11Frame::Reply { length: 0, chunk, .. } if chunk.len() == 24));
+		assert_eq!(frame.size(), 32);
+
+		let mut written = Vec::new();
+		frame.write_to(&mut written);
+		assert_eq!(written, buf);
+	}
+
+	#[test]
+	fn reply_of_length_u16_max() {
+		let length = u32::from(u16::MAX);
+		let buf = reply(length);
+
+		assert_eq!(buf.len(), 32 + (4 * 0xffff));
+		assert_eq!(X11Frame::check(&mut &buf[..]), Ok(()));
+		assert_eq!(X11Frame::check(&mut &buf[..buf.len() - 1]), Err(Incomplete));
+
+		let frame = X11Frame::parse(&mut &buf[..]).unwrap();
+
+		assert!(matches!(frame, X11Frame::Reply { length: 0xffff, .. }));
+		assert_eq!(frame.size(), buf.len());
+	}
+
+	#[test]
+	fn reply_of_length_u32_max() {
+		// Only the header is present; the data would be 16 GiB.
+		let buf = [1, 0, 0, 1, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+
+		assert_eq!(X11Frame::check(&mut &buf[..]), Err(Incomplete));
+		assert_eq!(X11Frame::parse(&mut &buf[..]), Err(Incomplete));
+
+		#[cfg(target_pointer_width = "64")]
+		assert_eq!(byte_length(24, u32::MAX), Ok(24 + (4 * 0xffff_ffff)));
+	}
+
+	#[test]
+	fn generic_event_of_length_0() {
+		let mut buf = vec![GENERIC_EVENT, 131, 0, 1, 0, 0, 0, 0, 0, 7];
+		buf.resize(32, 0);
+
+		let mut rest = &buf[..];
+		let frame = X11Frame::parse(&mut rest).unwrap();
+
+		assert!(rest.is_empty());
+		assert!(matches!(
+			&frame,
+			X11Frame::GenericEvent { length: 0, event_type: 7, chunk, .. } if chunk.len() == 22
+		));
+		assert_eq!(frame.size(), 32);
+	}
+
+	#[test]
+	fn request_of_length_1() {
+		let frame = RawRequest::new(43, 0, Vec::new()).into_frame().unwrap();
+
+		assert!(matches!(frame, X11Frame::Request { length: 1, .. }));
+		assert_eq!(frame.size(), 4);
+
+		let mut written = Vec::new();
+		frame.write_to(&mut written);
+		assert_eq!(written, [43, 0, 0, 1]);
+	}
+
+	#[test]
+	fn request_of_length_u16_max() {
+		// The header is the last of the 65535 blocks.
+		let body = vec![0; 4 * (usize::from(u16::MAX) - 1)];
+		let frame = RawRequest::new(72, 2, body).into_frame().unwrap();
+
+		assert!(matches!(frame, X11Frame::Request { length: 0xffff, .. }));
+		assert_eq!(frame.size(), 4 * 0xffff);
+
+		let mut written = Vec::new();
+		frame.write_to(&mut written);
+		assert_eq!(written[..4], [72, 2, 0xff, 0xff]);
+	}
+
+	#[test]
+	fn request_too_long_without_big_requests() {
+		let body = vec![0; 4 * usize::from(u16::MAX)];
+
+		assert_eq!(
+			RawRequest::new(72, 2, body).into_frame(),
+			Err(RequestTooLong)
+		);
+	}
+
+	#[test]
+	fn big_request() {
+		// One block too long for the usual encoding; the extended length
+		// counts the extra block it takes up too.
+		let body = vec![0xaa; 4 * usize::from(u16::MAX)];
+		let frame = RawRequest::new(72, 2, body).into_big_frame().unwrap();
+
+		let X11Frame::Request { length, chunk, .. } = &frame else {
+			panic!("expected a request, got {frame:?}");
+		};
+
+		assert_eq!(*length, 0);
+		assert_eq!(chunk[..4], 0x0001_0001_u32.to_be_bytes());
+		assert_eq!(frame.size(), 4 * 0x0001_0001);
+
+		let mut written = Vec::new();
+		frame.write_to(&mut written);
+		assert_eq!(written[..9], [72, 2, 0, 0, 0, 1, 0, 1, 0xaa]);
+	}
+
+	#[test]
+	fn big_request_fitting_usual_encoding() {
+		let frame = RawRequest::new(72, 2, vec![0; 8]).into_big_frame().unwrap();
+
+		assert!(matches!(frame, X11Frame::Request { length: 3, .. }));
+		assert_eq!(frame.size(), 12);
+	}
+}
@@ -0,0 +1,26 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The X11 wire format used by X.RS.
+//!
+//! This crate contains the pure serialization and parsing of the messages
+//! sent between X clients and X servers. It has no dependency on an async
+//! runtime or on `std` (only `alloc`), so it can be reused in environments
+//! where X.RS itself cannot run, such as firmware or WebAssembly tooling.
+
+#![no_std]
+
+extern crate alloc;
+
+mod debug;
+mod event;
+mod frame;
+mod request;
+mod wire;
+
+pub use debug::*;
+pub use event::*;
+pub use frame::*;
+pub use request::*;
+pub use wire::*;
@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::X11Frame;
+use bytes::{BufMut, Bytes, BytesMut};
+use core::{fmt, fmt::Formatter};
+
+/// The size of a block (the unit of length in the X11 protocol) in bytes.
+const BLOCK: usize = 4;
+
+/// A request which has not been wrapped in a typed representation.
+///
+/// The `body` is everything after the 4-byte request header. It is padded
+/// with zeroes to a multiple of 4 bytes when the request is converted into an
+/// [`X11Frame`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawRequest {
+	/// The major opcode identifying the type of request (for core requests)
+	/// or the extension that defines the request (for extension requests).
+	pub opcode: u8,
+	/// A single byte in the header which may be used for additional data.
+	///
+	/// For extension requests, this is usually the minor opcode.
+	pub metabyte: u8,
+
+	/// The data contained in the request after its header.
+	pub body: Bytes,
+}
+
+impl RawRequest {
+	/// Creates a new `RawRequest` with the given `opcode`, `metabyte`, and
+	/// `body`.
+	pub fn new(opcode: u8, metabyte: u8, body: impl Into<Bytes>) -> Self {
+		Self {
+			opcode,
+			metabyte,
+
+			body: body.into(),
+		}
+	}
+
+	/// Converts this request into an [`X11Frame::Request`], padding its body
+	/// to a multiple of 4 bytes.
+	///
+	/// # Errors
+	/// Returns [`RequestTooLong`] if the request is too long for its length
+	/// to be represented in the request header.
+	pub fn into_frame(self) -> Result<X11Frame, RequestTooLong> {
+		let blocks = self.blocks();
+		let length = u16::try_from(blocks).map_err(|_| RequestTooLong)?;
+
+		Ok(self.frame(length, None))
+	}
+
+	/// Converts this request into an [`X11Frame::Request`] like
+	/// [`into_frame`], using the extended length encoding of the BIG-REQUESTS
+	/// extension if the request is too long for the usual encoding.
+	///
+	/// With the extended encoding, the frame's `length` is `0`, and its chunk
+	/// starts with the request's length as a `u32`, which counts the extra
+	/// block.
+	///
+	/// Only use this once BIG-REQUESTS has been enabled on the connection.
+	///
+	/// # Errors
+	/// Returns [`RequestTooLong`] if the request is too long for even the
+	/// extended length encoding.
+	///
+	/// [`into_frame`]: RawRequest::into_frame
+	pub fn into_big_frame(self) -> Result<X11Frame, RequestTooLong> {
+		let blocks = self.blocks();
+
+		match u16::try_from(blocks) {
+			Ok(length) => Ok(self.frame(length, None)),
+
+			Err(_) => {
+				let length = u32::try_from(blocks + 1).map_err(|_| RequestTooLong)?;
+
+				Ok(self.frame(0, Some(length)))
+			},
+		}
+	}
+
+	/// The length of the request in blocks, including its header and padding.
+	fn blocks(&self) -> usize {
+		1 + self.body.len().div_ceil(BLOCK)
+	}
+
+	/// Returns the request as an [`X11Frame::Request`] with the given
+	/// `length`, prefixing its body with the `extended_length` if there is
+	/// one.
+	fn frame(self, length: u16, extended_length: Option<u32>) -> X11Frame {
+		let padding = (BLOCK - (self.body.len() % BLOCK)) % BLOCK;
+
+		let chunk = if padding == 0 && extended_length.is_none() {
+			self.body
+		} else {
+			let mut chunk = BytesMut::with_capacity(BLOCK + self.body.len() + padding);
+
+			if let Some(extended_length) = extended_length {
+				chunk.put_u32(extended_length);
+			}
+
+			chunk.put_slice(&self.body);
+			chunk.put_bytes(0, padding);
+
+			chunk.freeze()
+		};
+
+		X11Frame::Request {
+			major_opcode: self.opcode,
+			metabyte: self.metabyte,
+			length,
+
+			chunk,
+		}
+	}
+}
+
+/// A request with a typed representation.
+///
+/// Each request declares the type of its reply at the type level, so that
+/// sending it returns a cookie for that type of reply. Requests which don't
+/// generate a reply have [`NoReply`] as their reply type, so their cookies
+/// can't be waited on for a reply which will never come.
+///
+/// Extension requests name their extension, whose major opcode is looked up
+/// when they are sent, and use [`OPCODE`] as their minor opcode.
+///
+/// ```
+/// use bytes::{BufMut, BytesMut};
+/// use xrs_proto::{NoReply, Request};
+///
+/// /// The XFIXES extension's `HideCursor` request.
+/// struct HideCursor {
+///     window: u32,
+/// }
+///
+/// impl Request for HideCursor {
+///     type Reply = NoReply;
+///
+///     const NAME: &'static str = "XFixesHideCursor";
+///     const EXTENSION: Option<&'static str> = Some("XFIXES");
+///     const OPCODE: u8 = 29;
+///
+///     fn write_body(&self, body: &mut BytesMut) {
+///         body.put_u32(self.window);
+///     }
+/// }
+///
+/// let request = HideCursor { window: 0x400001 }.to_raw(138);
+///
+/// assert_eq!((request.opcode, request.metabyte), (138, 29));
+/// assert_eq!(&request.body[..], [0x00, 0x40, 0x00, 0x01]);
+/// ```
+///
+/// [`OPCODE`]: Request::OPCODE
+pub trait Request {
+	/// The type of the request's reply, or [`NoReply`] if it doesn't generate
+	/// one.
+	type Reply: ReplyType;
+
+	/// The name of the request in the protocol, such as `GetInputFocus`.
+	const NAME: &'static str;
+	/// The name of the extension which defines the request, or `None` for
+	/// core requests.
+	const EXTENSION: Option<&'static str> = None;
+	/// The request's major opcode, or its minor opcode if it is an extension
+	/// request.
+	const OPCODE: u8;
+	/// The length of the request in 4-byte blocks, including its header, if
+	/// the protocol gives it a fixed length.
+	///
+	/// The body written by [`write_body`] is checked against it in debug
+	/// builds, and the request's fields can be checked against it at compile
+	/// time with [`assert_wire_size!`].
+	///
+	/// [`write_body`]: Request::write_body
+	/// [`assert_wire_size!`]: crate::assert_wire_size
+	const LENGTH: Option<u16> = None;
+
+	/// The metabyte of the request's header, which some core requests use for
+	/// data.
+	///
+	/// This is ignored for extension requests, whose metabyte is their minor
+	/// opcode.
+	fn metabyte(&self) -> u8 {
+		0
+	}
+
+	/// Writes the data contained in the request after its header.
+	fn write_body(&self, body: &mut BytesMut);
+
+	/// Returns the request as a [`RawRequest`], given the major opcode of its
+	/// extension if it is an extension request.
+	///
+	/// `major_opcode` is ignored for core requests.
+	fn to_raw(&self, major_opcode: u8) -> RawRequest {
+		let mut body = BytesMut::new();
+		self.write_body(&mut body);
+
+		if let Some(length) = Self::LENGTH {
+			debug_assert_eq!(
+				BLOCK + body.len(),
+				BLOCK * usize::from(length),
+				"{} body doesn't match its length in the protocol",
+				Self::NAME,
+			);
+		}
+
+		match Self::EXTENSION {
+			Some(_) => RawRequest::new(major_opcode, Self::OPCODE, body),
+			None => RawRequest::new(Self::OPCODE, self.metabyte(), body),
+		}
+	}
+}
+
+/// A request's reply, which can be read from an [`X11Frame::Reply`].
+pub trait Reply: Sized {
+	/// Reads the reply from `frame`.
+	///
+	/// Returns `None` if `frame` isn't a reply, or is too short.
+	fn from_reply(frame: &X11Frame) -> Option<Self>;
+}
+
+/// The type of a [`Request`]'s reply: either a [`Reply`], or [`NoReply`].
+pub trait ReplyType {
+	/// Whether requests with this reply type generate a reply.
+	const GENERATES_REPLY: bool;
+}
+
+impl<T: Reply> ReplyType for T {
+	const GENERATES_REPLY: bool = true;
+}
+
+/// The reply type of requests which don't generate a reply.
+///
+/// `NoReply` has no values, as no reply is ever received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoReply {}
+
+impl ReplyType for NoReply {
+	const GENERATES_REPLY: bool = false;
+}
+
+/// A request is too long for its length to be represented in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestTooLong;
+
+impl fmt::Display for RequestTooLong {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "request is too long to be sent")
+	}
+}
+
+impl core::error::Error for RequestTooLong {}
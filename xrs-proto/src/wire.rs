@@ -0,0 +1,111 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// A value which always takes the same number of bytes on the wire.
+///
+/// Sizes are added up by [`assert_wire_size!`] to check a message's layout
+/// against the protocol at compile time.
+///
+/// [`assert_wire_size!`]: crate::assert_wire_size
+pub trait WireSize {
+	/// The number of bytes the value takes on the wire.
+	const WIRE_SIZE: usize;
+}
+
+macro_rules! wire_size {
+	($($type:ty => $size:expr),* $(,)?) => {
+		$(
+			impl WireSize for $type {
+				const WIRE_SIZE: usize = $size;
+			}
+		)*
+	};
+}
+
+wire_size! {
+	u8 => 1,
+	i8 => 1,
+	bool => 1,
+	u16 => 2,
+	i16 => 2,
+	u32 => 4,
+	i32 => 4,
+}
+
+/// Unused bytes are written as arrays, such as `[u8; 3]`.
+impl<T: WireSize, const N: usize> WireSize for [T; N] {
+	const WIRE_SIZE: usize = N * T::WIRE_SIZE;
+}
+
+/// Asserts at compile time that the fields of a message, listed in the order
+/// they are sent, add up to its size in the protocol.
+///
+/// Unused bytes are listed as arrays of `u8`. This catches missing padding
+/// and fields of the wrong width before they reach the X server, which would
+/// otherwise misread every later field or close the connection.
+///
+/// For a type implementing [`Request`], the size is checked against its
+/// [`LENGTH`] instead, which must be given:
+///
+/// ```
+/// use bytes::{BufMut, BytesMut};
+/// use xrs_proto::{assert_wire_size, NoReply, Request};
+///
+/// struct UngrabPointer {
+///     time: u32,
+/// }
+///
+/// impl Request for UngrabPointer {
+///     type Reply = NoReply;
+///
+///     const NAME: &'static str = "UngrabPointer";
+///     const OPCODE: u8 = 27;
+///     const LENGTH: Option<u16> = Some(2);
+///
+///     fn write_body(&self, body: &mut BytesMut) {
+///         body.put_u32(self.time);
+///     }
+/// }
+///
+/// // The opcode, an unused byte, the length, and the time.
+/// assert_wire_size!(UngrabPointer: [u8, [u8; 1], u16, u32]);
+///
+/// // Events are always 32 bytes.
+/// assert_wire_size!("FocusIn": [u8, u8, u16, u32, u8, [u8; 23]] == 32);
+/// ```
+///
+/// Forgetting the padding at the end of `FocusIn` fails to compile:
+///
+/// ```compile_fail
+/// # use xrs_proto::assert_wire_size;
+/// assert_wire_size!("FocusIn": [u8, u8, u16, u32, u8] == 32);
+/// ```
+///
+/// [`Request`]: crate::Request
+/// [`LENGTH`]: crate::Request::LENGTH
+#[macro_export]
+macro_rules! assert_wire_size {
+	($request:ty: [$($field:ty),* $(,)?]) => {
+		const _: () = ::core::assert!(
+			0 $(+ <$field as $crate::WireSize>::WIRE_SIZE)*
+				== 4 * <$request as $crate::Request>::LENGTH.unwrap() as usize,
+			::core::concat!(
+				"the fields of ",
+				::core::stringify!($request),
+				" don't add up to its length in the protocol",
+			),
+		);
+	};
+
+	($name:literal: [$($field:ty),* $(,)?] == $size:expr) => {
+		const _: () = ::core::assert!(
+			0 $(+ <$field as $crate::WireSize>::WIRE_SIZE)* == $size,
+			::core::concat!(
+				"the fields of ",
+				$name,
+				" don't add up to its size in the protocol",
+			),
+		);
+	};
+}